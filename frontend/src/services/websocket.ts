@@ -60,7 +60,11 @@ class WebSocketService {
     console.log("Attempting WebSocket connection to ws://localhost:8080/ws");
 
     try {
-      const ws = new WebSocket("ws://localhost:8080/ws");
+      // Browsers cannot set headers on a WebSocket, so the token goes in the query
+      const token = localStorage.getItem("authToken") ?? "";
+      const ws = new WebSocket(
+        `ws://localhost:8080/ws?token=${encodeURIComponent(token)}`,
+      );
 
       ws.onopen = () => {
         console.log("WebSocket connected");
@@ -0,0 +1,445 @@
+//! Generates the client from `openapi.json`: a Rust type per schema in
+//! `components/schemas`, written to `models.rs`, and a `Client` method per operation,
+//! with a parameter struct for operations that take query parameters, written to
+//! `operations.rs`.
+
+use serde_json::{Map, Value};
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+const SPEC: &str = "openapi.json";
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "static", "struct", "trait", "true", "type", "unsafe", "use",
+    "where", "while",
+];
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", SPEC);
+    let spec = fs::read_to_string(SPEC).expect("openapi.json is readable");
+    let spec: Value = serde_json::from_str(&spec).expect("openapi.json is valid JSON");
+
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+    fs::write(Path::new(&out_dir).join("models.rs"), models(&spec)).expect("models.rs is writable");
+    fs::write(Path::new(&out_dir).join("operations.rs"), operations(&spec))
+        .expect("operations.rs is writable");
+}
+
+fn models(spec: &Value) -> String {
+    let mut out = String::new();
+    let schemas = spec["components"]["schemas"]
+        .as_object()
+        .cloned()
+        .unwrap_or_default();
+    for (name, schema) in &schemas {
+        doc(&mut out, "", schema["description"].as_str());
+        if let Some(values) = schema["enum"].as_array() {
+            string_enum(&mut out, name, values);
+        } else if let Some(properties) = schema["properties"].as_object() {
+            model_struct(&mut out, name, schema, properties);
+        } else {
+            writeln!(out, "pub type {} = serde_json::Value;\n", name).unwrap();
+        }
+    }
+    out
+}
+
+fn model_struct(out: &mut String, name: &str, schema: &Value, properties: &Map<String, Value>) {
+    let required = required(schema);
+    writeln!(
+        out,
+        "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]"
+    )
+    .unwrap();
+    writeln!(out, "pub struct {} {{", name).unwrap();
+    for (property, schema) in properties {
+        doc(out, "    ", schema["description"].as_str());
+        let field = snake_case(property);
+        if field != *property {
+            writeln!(out, "    #[serde(rename = {:?})]", property).unwrap();
+        }
+        let ty = rust_type(schema, "");
+        let ty = if !nullable(schema)
+            && (required.contains(&property.as_str()) || has_empty_value(schema))
+        {
+            if !required.contains(&property.as_str()) {
+                writeln!(out, "    #[serde(default)]").unwrap();
+            }
+            ty
+        } else {
+            writeln!(
+                out,
+                "    #[serde(default, skip_serializing_if = \"Option::is_none\")]"
+            )
+            .unwrap();
+            format!("Option<{}>", ty)
+        };
+        writeln!(out, "    pub {}: {},", field_name(&field), ty).unwrap();
+    }
+    writeln!(out, "}}\n").unwrap();
+}
+
+fn string_enum(out: &mut String, name: &str, values: &[Value]) {
+    let values: Vec<&str> = values.iter().filter_map(Value::as_str).collect();
+    writeln!(
+        out,
+        "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]"
+    )
+    .unwrap();
+    writeln!(out, "pub enum {} {{", name).unwrap();
+    for value in &values {
+        let variant = pascal_case(value);
+        if variant != *value {
+            writeln!(out, "    #[serde(rename = {:?})]", value).unwrap();
+        }
+        writeln!(out, "    {},", variant).unwrap();
+    }
+    writeln!(out, "}}\n").unwrap();
+
+    writeln!(out, "impl {} {{", name).unwrap();
+    writeln!(out, "    /// Value in JSON and in query strings").unwrap();
+    writeln!(out, "    pub fn as_str(&self) -> &'static str {{").unwrap();
+    writeln!(out, "        match self {{").unwrap();
+    for value in &values {
+        writeln!(
+            out,
+            "            {}::{} => {:?},",
+            name,
+            pascal_case(value),
+            value
+        )
+        .unwrap();
+    }
+    writeln!(out, "        }}\n    }}\n}}\n").unwrap();
+
+    writeln!(out, "impl std::fmt::Display for {} {{", name).unwrap();
+    writeln!(
+        out,
+        "    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{"
+    )
+    .unwrap();
+    writeln!(out, "        f.write_str(self.as_str())\n    }}\n}}\n").unwrap();
+}
+
+fn operations(spec: &Value) -> String {
+    let mut params = String::new();
+    let mut methods = String::new();
+    let paths = spec["paths"].as_object().cloned().unwrap_or_default();
+    for (path, item) in &paths {
+        let Some(item) = item.as_object() else {
+            continue;
+        };
+        for (method, operation) in item {
+            operation_method(&mut methods, &mut params, path, method, operation);
+        }
+    }
+    format!("{}impl Client {{\n{}}}\n", params, methods)
+}
+
+fn operation_method(
+    out: &mut String,
+    params_out: &mut String,
+    path: &str,
+    method: &str,
+    operation: &Value,
+) {
+    let id = operation["operationId"]
+        .as_str()
+        .expect("operations have an id");
+    let parameters = operation["parameters"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let path_params: Vec<&Value> = parameters.iter().filter(|p| p["in"] == "path").collect();
+    let query_params: Vec<&Value> = parameters.iter().filter(|p| p["in"] == "query").collect();
+
+    let mut arguments = vec!["&self".to_string()];
+    for param in &path_params {
+        let name = field_name(&snake_case(param["name"].as_str().unwrap_or_default()));
+        let ty = match param["schema"]["type"].as_str() {
+            Some("integer") => rust_type(&param["schema"], "models::"),
+            _ => "&str".to_string(),
+        };
+        arguments.push(format!("{}: {}", name, ty));
+    }
+    let params_type = format!("{}Params", pascal_case(id));
+    if !query_params.is_empty() {
+        query_struct(params_out, &params_type, id, &query_params);
+        arguments.push(format!("params: &{}", params_type));
+    }
+
+    let request = content(&operation["requestBody"]);
+    match &request {
+        Some((media_type, _)) if media_type == "application/json" => {
+            let ty = rust_type(&request.as_ref().unwrap().1, "models::");
+            arguments.push(format!("body: &{}", ty));
+        }
+        Some(_) => arguments.push("body: &str".to_string()),
+        None => {}
+    }
+
+    let (status, success) = operation["responses"]
+        .as_object()
+        .and_then(|responses| responses.iter().find(|(status, _)| status.starts_with('2')))
+        .map(|(status, response)| (status.clone(), response.clone()))
+        .expect("operations have a success response");
+    let response = content(&success);
+    let (returns, send) = match &response {
+        Some((media_type, schema)) if media_type == "application/json" => {
+            (rust_type(schema, "models::"), "send_json")
+        }
+        Some(_) => ("String".to_string(), "send_text"),
+        None => ("()".to_string(), "send_empty"),
+    };
+
+    let summary = operation["summary"].as_str().unwrap_or(id);
+    doc(out, "    ", Some(summary));
+    let access = match operation["x-permission"].as_str() {
+        Some(permission) => format!("requires the `{}` permission", permission),
+        None => "public".to_string(),
+    };
+    writeln!(
+        out,
+        "    ///\n    /// `{} {}`, {}; answers {} on success.",
+        method.to_uppercase(),
+        path,
+        access,
+        status
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "    pub async fn {}({}) -> Result<{}, Error> {{",
+        id,
+        arguments.join(", "),
+        returns
+    )
+    .unwrap();
+
+    let mut format_args = Vec::new();
+    let template: Vec<String> = path
+        .split('/')
+        .map(
+            |segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                Some(name) => {
+                    let name = field_name(&snake_case(name));
+                    let param = path_params.iter().find(|param| {
+                        field_name(&snake_case(param["name"].as_str().unwrap())) == name
+                    });
+                    match param.map(|param| &param["schema"]["type"]) {
+                        Some(Value::String(ty)) if ty == "integer" => format_args.push(name),
+                        _ => format_args.push(format!("encode_segment({})", name)),
+                    }
+                    "{}".to_string()
+                }
+                None => segment.to_string(),
+            },
+        )
+        .collect();
+    let path = if format_args.is_empty() {
+        format!("{:?}", path)
+    } else {
+        format!(
+            "&format!({:?}, {})",
+            template.join("/"),
+            format_args.join(", ")
+        )
+    };
+
+    let method = match method {
+        "get" => "GET",
+        "post" => "POST",
+        "put" => "PUT",
+        "delete" => "DELETE",
+        "patch" => "PATCH",
+        other => panic!("unsupported method {}", other),
+    };
+    writeln!(
+        out,
+        "        let request = self.request(reqwest::Method::{}, {});",
+        method, path
+    )
+    .unwrap();
+    if !query_params.is_empty() {
+        writeln!(out, "        let request = request.query(&params.pairs());").unwrap();
+    }
+    match &request {
+        Some((media_type, _)) if media_type == "application/json" => {
+            writeln!(out, "        let request = request.json(body);").unwrap();
+        }
+        Some((media_type, _)) => {
+            writeln!(
+                out,
+                "        let request = request\n            .header(reqwest::header::CONTENT_TYPE, {:?})\n            .body(body.to_string());",
+                media_type
+            )
+            .unwrap();
+        }
+        None => {}
+    }
+    writeln!(out, "        self.{}(request).await\n    }}\n", send).unwrap();
+}
+
+/// Parameter struct of an operation with query parameters
+fn query_struct(out: &mut String, name: &str, id: &str, params: &[&Value]) {
+    writeln!(out, "/// Query parameters of [`Client::{}`]", id).unwrap();
+    writeln!(out, "#[derive(Debug, Clone, Default)]").unwrap();
+    writeln!(out, "pub struct {} {{", name).unwrap();
+    let mut pairs = String::new();
+    for param in params {
+        let query_name = param["name"].as_str().unwrap_or_default();
+        let field = field_name(&snake_case(query_name));
+        let schema = &param["schema"];
+        doc(out, "    ", param["description"].as_str());
+        let ty = rust_type(schema, "models::");
+        if schema["type"] == "array" {
+            writeln!(out, "    pub {}: {},", field, ty).unwrap();
+            writeln!(
+                pairs,
+                "        for value in &self.{} {{\n            pairs.push(({:?}, value.to_string()));\n        }}",
+                field, query_name
+            )
+            .unwrap();
+        } else if param["required"] == true && !nullable(schema) {
+            writeln!(out, "    pub {}: {},", field, ty).unwrap();
+            writeln!(
+                pairs,
+                "        pairs.push(({:?}, self.{}.to_string()));",
+                query_name, field
+            )
+            .unwrap();
+        } else {
+            writeln!(out, "    pub {}: Option<{}>,", field, ty).unwrap();
+            writeln!(
+                pairs,
+                "        if let Some(value) = &self.{} {{\n            pairs.push(({:?}, value.to_string()));\n        }}",
+                field, query_name
+            )
+            .unwrap();
+        }
+    }
+    writeln!(out, "}}\n").unwrap();
+    writeln!(out, "impl {} {{", name).unwrap();
+    writeln!(
+        out,
+        "    fn pairs(&self) -> Vec<(&'static str, String)> {{\n        let mut pairs = Vec::new();\n{}        pairs\n    }}\n}}\n",
+        pairs
+    )
+    .unwrap();
+}
+
+/// Media type and schema of a request body or response, if it has content
+fn content(object: &Value) -> Option<(String, Value)> {
+    let content = object["content"].as_object()?;
+    let (media_type, body) = content.iter().next()?;
+    Some((media_type.clone(), body["schema"].clone()))
+}
+
+/// Rust type of a schema; `models` prefixes references to named schemas
+fn rust_type(schema: &Value, models: &str) -> String {
+    if let Some(reference) = schema["$ref"].as_str() {
+        let name = reference.rsplit('/').next().unwrap_or(reference);
+        return format!("{}{}", models, name);
+    }
+    if let Some([inner]) = schema["allOf"].as_array().map(Vec::as_slice) {
+        return rust_type(inner, models);
+    }
+    match schema["type"].as_str() {
+        Some("string") if schema["format"] == "date-time" => {
+            "chrono::DateTime<chrono::Utc>".to_string()
+        }
+        Some("string") => "String".to_string(),
+        Some("boolean") => "bool".to_string(),
+        Some("integer") => match schema["format"].as_str() {
+            Some("uint8") => "u8",
+            Some("uint16") => "u16",
+            Some("uint32") => "u32",
+            Some("uint64") => "u64",
+            Some("int32") => "i32",
+            _ => "i64",
+        }
+        .to_string(),
+        Some("number") if schema["format"] == "float" => "f32".to_string(),
+        Some("number") => "f64".to_string(),
+        Some("array") => format!("Vec<{}>", rust_type(&schema["items"], models)),
+        Some("object") => match &schema["additionalProperties"] {
+            Value::Object(values) if !values.is_empty() => format!(
+                "std::collections::HashMap<String, {}>",
+                rust_type(&schema["additionalProperties"], models)
+            ),
+            _ => "serde_json::Value".to_string(),
+        },
+        _ => "serde_json::Value".to_string(),
+    }
+}
+
+fn required(schema: &Value) -> Vec<&str> {
+    schema["required"]
+        .as_array()
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default()
+}
+
+fn nullable(schema: &Value) -> bool {
+    schema["nullable"] == true
+}
+
+/// Arrays and maps left out of a body mean empty, so they need no `Option`
+fn has_empty_value(schema: &Value) -> bool {
+    schema["type"] == "array"
+        || (schema["type"] == "object" && schema["additionalProperties"].is_object())
+}
+
+fn doc(out: &mut String, indent: &str, text: Option<&str>) {
+    let Some(text) = text else {
+        return;
+    };
+    for line in text.lines() {
+        if line.is_empty() {
+            writeln!(out, "{}///", indent).unwrap();
+        } else {
+            writeln!(out, "{}/// {}", indent, line).unwrap();
+        }
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else if c == '-' {
+            out.push('_');
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn pascal_case(name: &str) -> String {
+    name.split(['_', '-'])
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+fn field_name(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        format!("r#{}", name)
+    } else {
+        name.to_string()
+    }
+}
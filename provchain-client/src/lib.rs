@@ -0,0 +1,197 @@
+//! Typed client for the ProvChain REST API
+//!
+//! Models and operations are generated at build time from `openapi.json`, the document
+//! the node serves at `/api/openapi.json`. Every operation is a method of [`Client`]
+//! named after its `operationId`, taking path parameters, a `*Params` struct for query
+//! parameters and the request body, in that order.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), provchain_client::Error> {
+//! use provchain_client::Client;
+//!
+//! let mut client = Client::new("http://localhost:8080");
+//! client.login("farmer1", "secret-password").await?;
+//! let block = client.get_block(1).await?;
+//! println!("block {} has hash {}", block.index, block.hash);
+//! # Ok(())
+//! # }
+//! ```
+
+use serde::de::DeserializeOwned;
+use std::fmt;
+
+/// Request and response models of the API
+pub mod models {
+    use serde::{Deserialize, Serialize};
+
+    include!(concat!(env!("OUT_DIR"), "/models.rs"));
+}
+
+include!(concat!(env!("OUT_DIR"), "/operations.rs"));
+
+/// Error of an API call
+#[derive(Debug)]
+pub enum Error {
+    /// The request could not be sent or its response not read
+    Request(reqwest::Error),
+    /// The server answered with an error status
+    Api {
+        status: u16,
+        /// Error body, when the server sent one in the usual shape
+        error: Option<models::ApiError>,
+        body: String,
+    },
+    /// The response body does not match the expected model
+    Decode(serde_json::Error),
+}
+
+impl Error {
+    /// HTTP status of an error response
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Error::Api { status, .. } => Some(*status),
+            Error::Request(e) => e.status().map(|status| status.as_u16()),
+            Error::Decode(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Request(e) => write!(f, "request failed: {}", e),
+            Error::Api {
+                status,
+                error: Some(error),
+                ..
+            } => write!(f, "{} ({}): {}", error.error, status, error.message),
+            Error::Api { status, body, .. } => write!(f, "HTTP {}: {}", status, body),
+            Error::Decode(e) => write!(f, "unexpected response body: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Request(e) => Some(e),
+            Error::Decode(e) => Some(e),
+            Error::Api { .. } => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Request(e)
+    }
+}
+
+/// Client of one ProvChain node
+#[derive(Debug, Clone)]
+pub struct Client {
+    base_url: String,
+    http: reqwest::Client,
+    token: Option<String>,
+}
+
+impl Client {
+    /// Client of the node at `base_url`, e.g. `http://localhost:8080`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_http_client(base_url, reqwest::Client::new())
+    }
+
+    /// Client sending its requests through `http`, e.g. one with timeouts or proxies set
+    pub fn with_http_client(base_url: impl Into<String>, http: reqwest::Client) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            http,
+            token: None,
+        }
+    }
+
+    /// Sends `token` as bearer token with every request
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    pub fn set_token(&mut self, token: Option<String>) {
+        self.token = token;
+    }
+
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// Logs in and uses the returned token for the following requests
+    pub async fn login(
+        &mut self,
+        username: &str,
+        password: &str,
+    ) -> Result<models::AuthResponse, Error> {
+        let response = self
+            .authenticate(&models::AuthRequest {
+                username: username.to_string(),
+                password: password.to_string(),
+            })
+            .await?;
+        self.token = Some(response.token.clone());
+        Ok(response)
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self
+            .http
+            .request(method, format!("{}{}", self.base_url, path));
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, Error> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await?;
+        Err(Error::Api {
+            status: status.as_u16(),
+            error: serde_json::from_str(&body).ok(),
+            body,
+        })
+    }
+
+    async fn send_json<T: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T, Error> {
+        let body = self.send(request).await?.bytes().await?;
+        serde_json::from_slice(&body).map_err(Error::Decode)
+    }
+
+    async fn send_text(&self, request: reqwest::RequestBuilder) -> Result<String, Error> {
+        Ok(self.send(request).await?.text().await?)
+    }
+
+    async fn send_empty(&self, request: reqwest::RequestBuilder) -> Result<(), Error> {
+        self.send(request).await?;
+        Ok(())
+    }
+}
+
+/// Percent-encodes a path parameter
+fn encode_segment(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
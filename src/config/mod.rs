@@ -55,6 +55,9 @@ pub struct WebConfig {
     pub port: u16,
     pub jwt_secret: String,
     pub cors: CorsConfig,
+    /// Hosts webhooks may deliver to on loopback or private addresses
+    #[serde(default)]
+    pub webhook_allowed_hosts: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -161,6 +164,7 @@ impl Default for Config {
                     allow_credentials: true,
                     max_age: Some(3600),
                 },
+                webhook_allowed_hosts: Vec::new(),
             },
             ontology_config: None,
         }
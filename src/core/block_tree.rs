@@ -0,0 +1,527 @@
+//! Block tree and fork choice for the PoA network
+//!
+//! `Blockchain.chain` holds the canonical chain only. Competing blocks received from
+//! peers are kept here, keyed by hash, until a branch built from them outweighs the
+//! canonical suffix it competes with.
+//!
+//! Fork choice is authority-weighted longest chain: a branch scores one point per block
+//! signed by a member of the validator set, ties go to the branch signed by more distinct
+//! authorities, and remaining ties to the lexicographically smallest tip hash, so every
+//! node picks the same branch from the same blocks.
+//!
+//! Blocks whose branch does not reach the canonical chain yet (orphans) are bounded per
+//! signing authority, and a full tree evicts orphans before the tips of connected
+//! branches, so blocks that cannot win fork choice never push out ones that can.
+
+use crate::core::blockchain::Block;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+/// Default number of side-branch blocks kept before branch tips are evicted
+pub const DEFAULT_MAX_TREE_BLOCKS: usize = 1024;
+
+/// Default number of orphan blocks kept per signing authority
+pub const DEFAULT_MAX_ORPHANS_PER_AUTHORITY: usize = 64;
+
+/// Weight of a run of blocks under the fork-choice rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainWeight {
+    /// Blocks signed by a member of the validator set
+    pub authority_blocks: u64,
+    /// Distinct authorities that signed blocks in the run
+    pub distinct_authorities: usize,
+    /// Hash of the last block in the run
+    pub tip_hash: String,
+}
+
+impl ChainWeight {
+    /// Weigh a run of blocks against the given validator set.
+    ///
+    /// With an empty validator set (development networks) every block counts.
+    pub fn of(blocks: &[Block], authorities: &HashSet<String>) -> Self {
+        let authorized =
+            |block: &&Block| authorities.is_empty() || authorities.contains(&block.validator);
+
+        Self {
+            authority_blocks: blocks.iter().filter(authorized).count() as u64,
+            distinct_authorities: blocks
+                .iter()
+                .filter(authorized)
+                .map(|block| block.validator.as_str())
+                .collect::<HashSet<_>>()
+                .len(),
+            tip_hash: blocks
+                .last()
+                .map(|block| block.hash.clone())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Ord for ChainWeight {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.authority_blocks
+            .cmp(&other.authority_blocks)
+            .then(self.distinct_authorities.cmp(&other.distinct_authorities))
+            // Smaller tip hash wins, so it compares as the greater weight
+            .then_with(|| other.tip_hash.cmp(&self.tip_hash))
+    }
+}
+
+impl PartialOrd for ChainWeight {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Competing branch that outweighs the canonical chain
+#[derive(Debug, Clone)]
+pub struct ForkChoice {
+    /// Index of the last block shared with the canonical chain
+    pub fork_index: u64,
+    /// Blocks of the winning branch, starting at `fork_index + 1`
+    pub branch: Vec<Block>,
+}
+
+/// Side branches received from peers, keyed by block hash
+#[derive(Debug, Clone)]
+pub struct BlockTree {
+    blocks: HashMap<String, Block>,
+    max_blocks: usize,
+    max_orphans_per_authority: usize,
+}
+
+impl Default for BlockTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BlockTree {
+    /// Create an empty block tree
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_MAX_TREE_BLOCKS)
+    }
+
+    /// Create an empty block tree holding at most `max_blocks` side-branch blocks
+    pub fn with_capacity(max_blocks: usize) -> Self {
+        Self {
+            blocks: HashMap::new(),
+            max_blocks,
+            max_orphans_per_authority: DEFAULT_MAX_ORPHANS_PER_AUTHORITY,
+        }
+    }
+
+    /// Keep at most `max_orphans` orphan blocks signed by any one authority
+    pub fn with_max_orphans_per_authority(mut self, max_orphans: usize) -> Self {
+        self.max_orphans_per_authority = max_orphans;
+        self
+    }
+
+    /// Keep a block received from a peer that is not on the canonical chain.
+    ///
+    /// The block must carry a valid validator signature over its recomputed hash and,
+    /// when the validator set is not empty, be signed by one of its members. Returns
+    /// false if the block is rejected, already known, or an orphan of an authority
+    /// holding its share of orphans already.
+    pub fn insert(
+        &mut self,
+        block: Block,
+        canonical: &[Block],
+        authorities: &HashSet<String>,
+    ) -> bool {
+        if !block.verify_data_hash()
+            || block.hash != block.calculate_hash()
+            || !matches!(block.header().verify_signature(), Ok(true))
+        {
+            return false;
+        }
+        if !authorities.is_empty() && !authorities.contains(&block.validator) {
+            return false;
+        }
+
+        self.keep(block, canonical)
+    }
+
+    /// Keep a block rolled back from the canonical chain by a reorganization. It passed
+    /// the signed-block checks when it was added, so it is not verified again.
+    pub fn insert_orphaned(&mut self, block: Block, canonical: &[Block]) -> bool {
+        self.keep(block, canonical)
+    }
+
+    fn keep(&mut self, block: Block, canonical: &[Block]) -> bool {
+        if self.blocks.contains_key(&block.hash) {
+            return false;
+        }
+
+        let connected = self.connected(canonical);
+        let is_orphan = !self.connects(&block, canonical, &connected);
+        if is_orphan {
+            let orphans = self
+                .blocks
+                .values()
+                .filter(|kept| {
+                    kept.validator == block.validator && !connected.contains(kept.hash.as_str())
+                })
+                .count();
+            if orphans >= self.max_orphans_per_authority {
+                return false;
+            }
+        }
+
+        if self.blocks.len() >= self.max_blocks {
+            // Drop the tip furthest ahead, orphans first: evicting a tip never
+            // disconnects the blocks below it, and the new block's parent must stay for
+            // it to connect. An orphan never evicts the tip of a connected branch.
+            let evicted = self
+                .tips()
+                .into_iter()
+                .filter(|tip| tip.hash != block.previous_hash)
+                .filter(|tip| !is_orphan || !connected.contains(tip.hash.as_str()))
+                .max_by(|a, b| {
+                    let a_connected = connected.contains(a.hash.as_str());
+                    let b_connected = connected.contains(b.hash.as_str());
+                    b_connected
+                        .cmp(&a_connected)
+                        .then(a.index.cmp(&b.index))
+                        .then_with(|| a.hash.cmp(&b.hash))
+                })
+                .map(|tip| tip.hash.clone());
+            let Some(evicted) = evicted else {
+                return false;
+            };
+            self.blocks.remove(&evicted);
+        }
+
+        self.blocks.insert(block.hash.clone(), block);
+        true
+    }
+
+    /// Hashes of the tree blocks whose branch reaches the canonical chain
+    fn connected(&self, canonical: &[Block]) -> HashSet<&str> {
+        // Parents come first in index order, so one pass settles every block
+        let mut blocks: Vec<&Block> = self.blocks.values().collect();
+        blocks.sort_by_key(|block| block.index);
+
+        let mut connected = HashSet::new();
+        for block in blocks {
+            if self.connects(block, canonical, &connected) {
+                connected.insert(block.hash.as_str());
+            }
+        }
+        connected
+    }
+
+    /// Whether `block` builds on a canonical block or on a connected tree block
+    fn connects(&self, block: &Block, canonical: &[Block], connected: &HashSet<&str>) -> bool {
+        let Some(parent_index) = block.index.checked_sub(1) else {
+            return false;
+        };
+        let on_canonical = canonical
+            .get(parent_index as usize)
+            .is_some_and(|parent| parent.hash == block.previous_hash);
+        on_canonical
+            || (connected.contains(block.previous_hash.as_str())
+                && self
+                    .blocks
+                    .get(&block.previous_hash)
+                    .is_some_and(|parent| parent.index == parent_index))
+    }
+
+    /// Check whether a block hash is held in the tree
+    pub fn contains(&self, hash: &str) -> bool {
+        self.blocks.contains_key(hash)
+    }
+
+    /// Remove a block from the tree, e.g. once it became canonical
+    pub fn remove(&mut self, hash: &str) -> Option<Block> {
+        self.blocks.remove(hash)
+    }
+
+    /// Number of side-branch blocks held
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Whether the tree holds no side-branch blocks
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Drop all blocks at or below `index`; they can no longer win a fork
+    pub fn prune_at_or_below(&mut self, index: u64) {
+        self.blocks.retain(|_, block| block.index > index);
+    }
+
+    /// Blocks in the tree that no other tree block builds on
+    pub fn tips(&self) -> Vec<&Block> {
+        let parents: HashSet<&str> = self
+            .blocks
+            .values()
+            .map(|block| block.previous_hash.as_str())
+            .collect();
+
+        self.blocks
+            .values()
+            .filter(|block| !parents.contains(block.hash.as_str()))
+            .collect()
+    }
+
+    /// Walk back from `tip_hash` through the tree until reaching a canonical block.
+    ///
+    /// Returns the fork point and the branch in ascending order, or `None` if the
+    /// branch does not connect to the canonical chain (yet).
+    pub fn branch_to(&self, tip_hash: &str, canonical: &[Block]) -> Option<ForkChoice> {
+        let mut branch = Vec::new();
+        let mut current = self.blocks.get(tip_hash)?;
+
+        loop {
+            branch.push(current.clone());
+            if current.index == 0 {
+                return None;
+            }
+
+            let parent_index = current.index - 1;
+            if let Some(parent) = canonical.get(parent_index as usize) {
+                if parent.hash == current.previous_hash {
+                    branch.reverse();
+                    return Some(ForkChoice {
+                        fork_index: parent_index,
+                        branch,
+                    });
+                }
+            }
+
+            current = self
+                .blocks
+                .get(&current.previous_hash)
+                .filter(|parent| parent.index == parent_index)?;
+        }
+    }
+
+    /// Pick the branch that wins fork choice against the canonical chain, if any.
+    ///
+    /// Each connected branch is compared with the canonical blocks after its fork point;
+    /// branches forking below `min_fork_index` are ignored.
+    pub fn best_branch(
+        &self,
+        canonical: &[Block],
+        authorities: &HashSet<String>,
+        min_fork_index: u64,
+    ) -> Option<ForkChoice> {
+        let mut best: Option<(ChainWeight, ForkChoice)> = None;
+
+        for tip in self.tips() {
+            let Some(choice) = self.branch_to(&tip.hash, canonical) else {
+                continue;
+            };
+            if choice.fork_index < min_fork_index {
+                continue;
+            }
+
+            let canonical_suffix = &canonical[(choice.fork_index + 1) as usize..];
+            let branch_weight = ChainWeight::of(&choice.branch, authorities);
+            if branch_weight <= ChainWeight::of(canonical_suffix, authorities) {
+                continue;
+            }
+
+            if best
+                .as_ref()
+                .map(|(weight, _)| branch_weight > *weight)
+                .unwrap_or(true)
+            {
+                best = Some((branch_weight, choice));
+            }
+        }
+
+        best.map(|(_, choice)| choice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn validator(key: &SigningKey) -> String {
+        hex::encode(key.verifying_key().to_bytes())
+    }
+
+    fn block(index: u64, previous_hash: &str, label: &str, key: &SigningKey) -> Block {
+        let mut block = Block::new(
+            index,
+            format!(
+                "<http://example.org/{}> <http://example.org/p> \"{}\" .",
+                label, index
+            ),
+            previous_hash.to_string(),
+            String::new(),
+            validator(key),
+        );
+        block.signature = hex::encode(key.sign(block.hash.as_bytes()).to_bytes());
+        block
+    }
+
+    fn authorities() -> HashSet<String> {
+        [validator(&key(1)), validator(&key(2))]
+            .into_iter()
+            .collect()
+    }
+
+    fn canonical() -> Vec<Block> {
+        let genesis = block(0, "", "genesis", &key(1));
+        let c1 = block(1, &genesis.hash, "c1", &key(1));
+        vec![genesis, c1]
+    }
+
+    #[test]
+    fn test_longer_authority_branch_wins() {
+        let canonical = canonical();
+        let f1 = block(1, &canonical[0].hash, "f1", &key(2));
+        let f2 = block(2, &f1.hash, "f2", &key(1));
+        let mut tree = BlockTree::new();
+        assert!(tree.insert(f1.clone(), &canonical, &authorities()));
+        assert!(tree.insert(f2.clone(), &canonical, &authorities()));
+
+        let choice = tree.best_branch(&canonical, &authorities(), 0).unwrap();
+        assert_eq!(choice.fork_index, 0);
+        assert_eq!(
+            choice
+                .branch
+                .iter()
+                .map(|b| b.hash.as_str())
+                .collect::<Vec<_>>(),
+            vec![f1.hash.as_str(), f2.hash.as_str()]
+        );
+
+        // A fork below the minimum fork index is never chosen
+        assert!(tree.best_branch(&canonical, &authorities(), 1).is_none());
+    }
+
+    #[test]
+    fn test_unsigned_and_unauthorized_blocks_are_rejected() {
+        let canonical = canonical();
+        let mut tree = BlockTree::new();
+
+        let mut unsigned = block(1, &canonical[0].hash, "unsigned", &key(1));
+        unsigned.signature = String::new();
+        assert!(!tree.insert(unsigned, &canonical, &authorities()));
+
+        let mut forged = block(1, &canonical[0].hash, "forged", &key(1));
+        forged.data = forged.data.replace("forged", "altered");
+        assert!(!tree.insert(forged, &canonical, &authorities()));
+
+        let mallory = block(1, &canonical[0].hash, "mallory", &key(9));
+        assert!(!tree.insert(mallory.clone(), &canonical, &authorities()));
+        // Any valid signature is accepted while the validator set is empty
+        assert!(tree.insert(mallory, &canonical, &HashSet::new()));
+    }
+
+    #[test]
+    fn test_unauthorized_blocks_carry_no_weight() {
+        let canonical = canonical();
+        let m1 = block(1, &canonical[0].hash, "m1", &key(9));
+        let m2 = block(2, &m1.hash, "m2", &key(9));
+        let mut tree = BlockTree::new();
+        tree.insert_orphaned(m1, &canonical);
+        tree.insert_orphaned(m2, &canonical);
+
+        assert!(tree.best_branch(&canonical, &authorities(), 0).is_none());
+    }
+
+    #[test]
+    fn test_equal_weight_ties_break_on_tip_hash() {
+        // Both branches outweigh the empty suffix after genesis and tie with each other
+        let canonical = canonical()[..1].to_vec();
+        let b1 = block(1, &canonical[0].hash, "b1", &key(1));
+        let d1 = block(1, &canonical[0].hash, "d1", &key(1));
+        let mut tree = BlockTree::new();
+        tree.insert(b1.clone(), &canonical, &authorities());
+        tree.insert(d1.clone(), &canonical, &authorities());
+
+        let expected = b1.hash.min(d1.hash);
+        let choice = tree.best_branch(&canonical, &authorities(), 0).unwrap();
+        assert_eq!(choice.branch[0].hash, expected);
+    }
+
+    #[test]
+    fn test_disconnected_branch_is_ignored() {
+        let canonical = canonical();
+        let x2 = block(2, "missing", "x2", &key(1));
+        let mut tree = BlockTree::new();
+        tree.insert(x2.clone(), &canonical, &authorities());
+
+        assert!(tree.branch_to(&x2.hash, &canonical).is_none());
+        assert!(tree.best_branch(&canonical, &authorities(), 0).is_none());
+    }
+
+    #[test]
+    fn test_full_tree_evicts_tips_not_branch_roots() {
+        let canonical = canonical();
+        let f1 = block(1, &canonical[0].hash, "f1", &key(1));
+        let f2 = block(2, &f1.hash, "f2", &key(1));
+        let x1 = block(1, &canonical[0].hash, "x1", &key(2));
+        let mut tree = BlockTree::with_capacity(2);
+        assert!(tree.insert(f1.clone(), &canonical, &authorities()));
+        assert!(tree.insert(x1.clone(), &canonical, &authorities()));
+
+        // The new block's parent stays, the other tip goes
+        assert!(tree.insert(f2.clone(), &canonical, &authorities()));
+        assert!(tree.contains(&f1.hash));
+        assert!(tree.contains(&f2.hash));
+        assert!(!tree.contains(&x1.hash));
+
+        // Another block evicts a tip, never the root of the branch
+        let g1 = block(1, &canonical[0].hash, "g1", &key(2));
+        assert!(tree.insert(g1.clone(), &canonical, &authorities()));
+        assert!(tree.contains(&f1.hash));
+        assert!(!tree.contains(&f2.hash));
+    }
+
+    #[test]
+    fn test_orphans_are_bounded_per_authority_and_evicted_first() {
+        let canonical = canonical();
+        let f1 = block(1, &canonical[0].hash, "f1", &key(1));
+        let f2 = block(2, &f1.hash, "f2", &key(1));
+        let far = |label: &str, key: &SigningKey| block(900, "unknown", label, key);
+        let mut tree = BlockTree::with_capacity(3).with_max_orphans_per_authority(1);
+        assert!(tree.insert(f1.clone(), &canonical, &authorities()));
+
+        // Each authority keeps its own share of orphans
+        let o1 = far("o1", &key(2));
+        assert!(tree.insert(o1.clone(), &canonical, &authorities()));
+        assert!(!tree.insert(far("o2", &key(2)), &canonical, &authorities()));
+        let p1 = far("p1", &key(1));
+        assert!(tree.insert(p1.clone(), &canonical, &authorities()));
+
+        // A connected block evicts orphans before the tips of connected branches,
+        // even when those are further ahead
+        assert!(tree.insert(f2.clone(), &canonical, &authorities()));
+        assert!(tree.contains(&f1.hash));
+        assert!(tree.contains(&f2.hash));
+        assert_eq!(
+            [&o1, &p1]
+                .iter()
+                .filter(|orphan| tree.contains(&orphan.hash))
+                .count(),
+            1
+        );
+
+        // An orphan never evicts the tip of a connected branch
+        let mut tree = BlockTree::with_capacity(2);
+        assert!(tree.insert(f1.clone(), &canonical, &authorities()));
+        assert!(tree.insert(f2.clone(), &canonical, &authorities()));
+        assert!(!tree.insert(o1.clone(), &canonical, &authorities()));
+        assert!(tree.contains(&f2.hash));
+
+        // Rolled-back blocks are connected and may evict orphans, too
+        let mut tree = BlockTree::with_capacity(1);
+        assert!(tree.insert(o1.clone(), &canonical, &authorities()));
+        assert!(tree.insert_orphaned(canonical[1].clone(), &canonical[..1]));
+        assert!(!tree.contains(&o1.hash));
+    }
+}
@@ -1,16 +1,23 @@
+use crate::core::finality::FinalityCertificate;
+use crate::core::merkle::{merkle_root, MerkleProof, MerkleTree};
+use crate::core::proof::{TripleInclusionProof, TriplePattern, PROOF_VERSION};
+use crate::core::snapshot::StateSnapshot;
 use crate::error::{BlockchainError, ProvChainError, Result};
-use crate::governance::Governance;
+use crate::governance::{Governance, GovernanceGenesis, Proposal, ProposalStatus, ProposalVote};
 use crate::ontology::{OntologyConfig, OntologyManager, ShaclValidator};
 use crate::storage::rdf_store::{RDFStore, StorageConfig};
 use crate::trace_optimization::{EnhancedTraceResult, EnhancedTraceabilitySystem};
-use chrono::Utc;
-use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use crate::transaction::ledger::LedgerState;
+use crate::transaction::transaction::Transaction;
+use chrono::{SecondsFormat, Utc};
+use ed25519_dalek::{Signature, SigningKey, Verifier, VerifyingKey};
 use hex;
 use oxigraph::model::NamedNode;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::path::Path;
-use tracing::{debug, info};
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Block {
@@ -20,8 +27,15 @@ pub struct Block {
     pub previous_hash: String,
     pub hash: String,
     pub state_root: String, // State root hash for atomic consistency
-    pub validator: String,  // Public key of the validator
-    pub signature: String,  // Signature of the block hash
+    /// Canonical RDF hash of the block's data graph, committed at proposal time.
+    /// The block hash is derived from this value and it is never recomputed afterwards.
+    #[serde(default)]
+    pub data_hash: String,
+    /// Merkle root over the RDFC-1.0 canonical N-Quads of the block's data graph
+    #[serde(default)]
+    pub merkle_root: String,
+    pub validator: String, // Public key of the validator
+    pub signature: String, // Signature of the block hash
 }
 
 impl Block {
@@ -32,7 +46,10 @@ impl Block {
         state_root: String,
         validator: String,
     ) -> Self {
-        let timestamp = Utc::now().to_rfc3339();
+        // Second precision in UTC ("Z") so the timestamp survives the xsd:dateTime
+        // round trip through the RDF store unchanged and the hash stays reproducible
+        let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        let (data_hash, merkle_root) = Self::canonical_commitments(index, &data);
         let mut block = Block {
             index,
             timestamp,
@@ -40,6 +57,8 @@ impl Block {
             previous_hash,
             hash: String::new(),
             state_root,
+            data_hash,
+            merkle_root,
             validator,
             signature: String::new(),
         };
@@ -47,38 +66,268 @@ impl Block {
         block
     }
 
+    /// Named graph IRI holding the RDF data of the block with the given index
+    pub fn graph_uri(index: u64) -> String {
+        format!("http://provchain.org/block/{}", index)
+    }
+
+    /// Canonical hash of Turtle data as it will appear in the block's named graph
+    pub fn canonical_data_hash(index: u64, data: &str) -> String {
+        Self::canonical_commitments(index, data).0
+    }
+
+    /// Canonical data hash and Merkle root of Turtle data as it will appear in the
+    /// block's named graph
+    pub fn canonical_commitments(index: u64, data: &str) -> (String, String) {
+        match NamedNode::new(Self::graph_uri(index)) {
+            Ok(graph_name) => {
+                let mut temp_store = RDFStore::new();
+                temp_store.add_rdf_to_graph(data, &graph_name);
+                let leaves = temp_store.canonical_nquads_rdfc10(&graph_name);
+                (
+                    temp_store.canonicalize_graph(&graph_name),
+                    merkle_root(&leaves),
+                )
+            }
+            Err(_) => {
+                // Fallback to simple hash if graph name creation fails
+                let mut hasher = Sha256::new();
+                hasher.update(data.as_bytes());
+                (format!("{:x}", hasher.finalize()), merkle_root(&[data]))
+            }
+        }
+    }
+
+    /// Calculate the block hash from the header fields, including the committed data hash
     pub fn calculate_hash(&self) -> String {
-        self.calculate_hash_with_store(None)
+        hash_header(
+            self.index,
+            &self.timestamp,
+            &self.data_hash,
+            &self.merkle_root,
+            &self.previous_hash,
+            &self.validator,
+        )
+    }
+
+    /// Header of this block, without the RDF body
+    pub fn header(&self) -> BlockHeader {
+        BlockHeader {
+            index: self.index,
+            timestamp: self.timestamp.clone(),
+            previous_hash: self.previous_hash.clone(),
+            hash: self.hash.clone(),
+            state_root: self.state_root.clone(),
+            data_hash: self.data_hash.clone(),
+            merkle_root: self.merkle_root.clone(),
+            validator: self.validator.clone(),
+            signature: self.signature.clone(),
+        }
     }
 
+    /// Recalculate the block hash using the store's current view of the block graph.
+    ///
+    /// This is a verification helper: a result that differs from `hash` means the
+    /// store no longer matches the committed data hash. It is never written back.
     pub fn calculate_hash_with_store(&self, rdf_store: Option<&RDFStore>) -> String {
-        let rdf_hash = if let Some(store) = rdf_store {
-            // Use RDF canonicalization for the data
-            match NamedNode::new(format!("http://provchain.org/block/{}", self.index)) {
+        let rdf_hash = match rdf_store {
+            Some(store) => match NamedNode::new(Self::graph_uri(self.index)) {
                 Ok(graph_name) => store.canonicalize_graph(&graph_name),
-                Err(_) => {
-                    // Fallback to simple hash if graph name creation fails
-                    let mut hasher = Sha256::new();
-                    hasher.update(self.data.as_bytes());
-                    format!("{:x}", hasher.finalize())
+                Err(_) => self.data_hash.clone(),
+            },
+            None => self.data_hash.clone(),
+        };
+
+        hash_header(
+            self.index,
+            &self.timestamp,
+            &rdf_hash,
+            &self.merkle_root,
+            &self.previous_hash,
+            &self.validator,
+        )
+    }
+
+    /// Check that the committed data hash and Merkle root match the block's Turtle data.
+    /// Blocks created before Merkle roots were introduced carry an empty root.
+    pub fn verify_data_hash(&self) -> bool {
+        let (data_hash, merkle_root) = Self::canonical_commitments(self.index, &self.data);
+        self.data_hash == data_hash
+            && (self.merkle_root.is_empty() || self.merkle_root == merkle_root)
+    }
+}
+
+/// Block header: everything a light client needs to check the block hash, the
+/// validator signature and per-quad inclusion proofs without the block graph
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BlockHeader {
+    pub index: u64,
+    pub timestamp: String,
+    pub previous_hash: String,
+    pub hash: String,
+    pub state_root: String,
+    pub data_hash: String,
+    pub merkle_root: String,
+    pub validator: String,
+    pub signature: String,
+}
+
+impl BlockHeader {
+    /// Recalculate the block hash from the header fields
+    pub fn calculate_hash(&self) -> String {
+        hash_header(
+            self.index,
+            &self.timestamp,
+            &self.data_hash,
+            &self.merkle_root,
+            &self.previous_hash,
+            &self.validator,
+        )
+    }
+
+    /// Verify the validator's Ed25519 signature over the block hash.
+    ///
+    /// Returns `Ok(false)` when the header carries no real validator key or signature.
+    pub fn verify_signature(&self) -> Result<bool> {
+        let (Ok(validator_bytes), Ok(signature_bytes)) =
+            (hex::decode(&self.validator), hex::decode(&self.signature))
+        else {
+            return Ok(false);
+        };
+        let (Ok(key_bytes), Ok(sig_bytes)) = (
+            <[u8; 32]>::try_from(validator_bytes.as_slice()),
+            <[u8; 64]>::try_from(signature_bytes.as_slice()),
+        ) else {
+            return Ok(false);
+        };
+
+        let public_key = VerifyingKey::from_bytes(&key_bytes).map_err(|e| {
+            ProvChainError::Blockchain(BlockchainError::InvalidBlock(format!(
+                "Invalid validator public key: {}",
+                e
+            )))
+        })?;
+        let signature = Signature::from_bytes(&sig_bytes);
+
+        public_key
+            .verify(self.hash.as_bytes(), &signature)
+            .map_err(|e| {
+                ProvChainError::Blockchain(BlockchainError::InvalidBlock(format!(
+                    "Signature verification failed: {}",
+                    e
+                )))
+            })?;
+
+        Ok(true)
+    }
+
+    /// Check that a run of headers is internally consistent: each hash recomputes
+    /// from its header fields and links to the previous header
+    pub fn verify_chain(headers: &[BlockHeader]) -> bool {
+        for (i, header) in headers.iter().enumerate() {
+            if header.hash != header.calculate_hash() {
+                warn!("Header {} has invalid hash", header.index);
+                return false;
+            }
+            if i > 0 {
+                let prev = &headers[i - 1];
+                if header.index != prev.index + 1 || header.previous_hash != prev.hash {
+                    warn!(
+                        "Header {} does not link to header {}",
+                        header.index, prev.index
+                    );
+                    return false;
                 }
             }
-        } else {
-            // Fallback to simple hash if no store provided (for genesis block)
-            let mut hasher = Sha256::new();
-            hasher.update(self.data.as_bytes());
-            format!("{:x}", hasher.finalize())
-        };
+        }
+        true
+    }
+}
 
-        // Combine block metadata with canonicalized RDF hash
-        // Note: Validator is part of the hash, but signature is NOT (signature signs the hash)
-        let record = format!(
-            "{0}{1}{2}{3}{4}",
-            self.index, self.timestamp, rdf_hash, self.previous_hash, self.validator
-        );
-        let mut hasher = Sha256::new();
-        hasher.update(record.as_bytes());
-        format!("{:x}", hasher.finalize())
+fn hash_header(
+    index: u64,
+    timestamp: &str,
+    data_hash: &str,
+    merkle_root: &str,
+    previous_hash: &str,
+    validator: &str,
+) -> String {
+    // Combine block metadata with canonicalized RDF hash
+    // Note: Validator is part of the hash, but signature is NOT (signature signs the hash)
+    // The Merkle root is appended only when present so pre-Merkle block hashes still verify
+    let record = format!(
+        "{0}{1}{2}{3}{4}{5}",
+        index, timestamp, data_hash, previous_hash, validator, merkle_root
+    );
+    let mut hasher = Sha256::new();
+    hasher.update(record.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Outcome of checking a single persisted block against its committed hash and signature
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BlockVerificationIssue {
+    /// The stored block hash does not match the hash recomputed from the header
+    HashMismatch { expected: String, actual: String },
+    /// The block graph in the RDF store no longer matches the committed data hash
+    DataHashMismatch { committed: String, store: String },
+    /// The canonical N-Quads in the RDF store no longer match the committed Merkle root
+    MerkleRootMismatch { committed: String, store: String },
+    /// The validator signature does not verify against the stored block hash
+    SignatureMismatch(String),
+}
+
+/// Report produced when re-verifying a persisted chain
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChainVerificationReport {
+    /// Number of blocks checked
+    pub blocks_checked: usize,
+    /// Blocks whose signature verified against the stored hash
+    pub signatures_verified: usize,
+    /// Blocks carrying placeholder validators or signatures (genesis, legacy)
+    pub unsigned_blocks: Vec<u64>,
+    /// Blocks that had no persisted data hash and were migrated
+    pub migrated_blocks: Vec<u64>,
+    /// Problems found, keyed by block index
+    pub issues: Vec<(u64, BlockVerificationIssue)>,
+}
+
+impl ChainVerificationReport {
+    /// Whether every block verified cleanly
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Indices of blocks whose signatures no longer match their stored hash
+    pub fn signature_failures(&self) -> Vec<u64> {
+        self.issues
+            .iter()
+            .filter(|(_, issue)| {
+                matches!(
+                    issue,
+                    BlockVerificationIssue::SignatureMismatch(_)
+                        | BlockVerificationIssue::HashMismatch { .. }
+                )
+            })
+            .map(|(index, _)| *index)
+            .collect()
+    }
+}
+
+/// Indices of blocks as they are appended to the chain. A cloned chain gets a channel of
+/// its own, so blocks appended to a copy are not announced.
+#[derive(Debug)]
+struct BlockEvents(broadcast::Sender<u64>);
+
+impl Default for BlockEvents {
+    fn default() -> Self {
+        Self(broadcast::channel(1024).0)
+    }
+}
+
+impl Clone for BlockEvents {
+    fn clone(&self) -> Self {
+        Self::default()
     }
 }
 
@@ -89,6 +338,7 @@ pub struct Blockchain {
     pub ontology_manager: Option<OntologyManager>,
     pub shacl_validator: Option<ShaclValidator>,
     pub governance: Governance,
+    block_events: BlockEvents,
 }
 
 impl Default for Blockchain {
@@ -106,12 +356,13 @@ impl Blockchain {
             ontology_manager: None,
             shacl_validator: None,
             governance: Governance::new(),
+            block_events: BlockEvents::default(),
         };
 
         // Load the traceability ontology
         bc.load_ontology();
 
-        let mut genesis_block = bc.create_genesis_block();
+        let genesis_block = bc.create_genesis_block();
 
         // Add genesis block data to RDF store BEFORE calculating final hash
         if let Ok(graph_name) = NamedNode::new("http://provchain.org/block/0") {
@@ -122,9 +373,6 @@ impl Blockchain {
             eprintln!("Warning: Could not create graph name for genesis block");
         }
 
-        // Recalculate hash after all data is in the store
-        genesis_block.hash = genesis_block.calculate_hash_with_store(Some(&bc.rdf_store));
-
         bc.chain.push(genesis_block);
         bc
     }
@@ -139,6 +387,7 @@ impl Blockchain {
             ontology_manager: None,
             shacl_validator: None,
             governance: Governance::new(),
+            block_events: BlockEvents::default(),
         };
 
         // Load the traceability ontology
@@ -148,7 +397,7 @@ impl Blockchain {
         let store_len = bc.rdf_store.store.len().unwrap_or(0);
         if store_len == 0 {
             // Create genesis block for new blockchain
-            let mut genesis_block = bc.create_genesis_block();
+            let genesis_block = bc.create_genesis_block();
 
             // Add genesis block data to RDF store
             if let Ok(graph_name) = NamedNode::new("http://provchain.org/block/0") {
@@ -160,9 +409,6 @@ impl Blockchain {
                     "Warning: Could not create graph name for genesis block in persistent store"
                 );
             }
-
-            // Recalculate hash after adding data to RDF store (consistent with new() method)
-            genesis_block.hash = genesis_block.calculate_hash_with_store(Some(&bc.rdf_store));
             bc.chain.push(genesis_block);
 
             // Save to disk
@@ -177,7 +423,7 @@ impl Blockchain {
             // Create genesis block as fallback
             if bc.chain.is_empty() {
                 eprintln!("Warning: Store has data but no blocks loaded, creating genesis block");
-                let mut genesis_block = bc.create_genesis_block();
+                let genesis_block = bc.create_genesis_block();
 
                 if let Ok(graph_name) = NamedNode::new("http://provchain.org/block/0") {
                     bc.rdf_store
@@ -186,9 +432,6 @@ impl Blockchain {
                 } else {
                     eprintln!("Warning: Could not create graph name for fallback genesis block");
                 }
-
-                // Recalculate hash after adding data to RDF store (consistent with new() method)
-                genesis_block.hash = genesis_block.calculate_hash_with_store(Some(&bc.rdf_store));
                 bc.chain.push(genesis_block);
             }
         }
@@ -206,6 +449,7 @@ impl Blockchain {
             ontology_manager: None,
             shacl_validator: None,
             governance: Governance::new(),
+            block_events: BlockEvents::default(),
         };
 
         // Load the traceability ontology
@@ -214,7 +458,7 @@ impl Blockchain {
         // Check if we need to create genesis block or load existing chain
         if bc.rdf_store.store.len().unwrap_or(0) == 0 {
             // Create genesis block for new blockchain
-            let mut genesis_block = bc.create_genesis_block();
+            let genesis_block = bc.create_genesis_block();
 
             // Add genesis block data to RDF store
             if let Ok(graph_name) = NamedNode::new("http://provchain.org/block/0") {
@@ -224,9 +468,6 @@ impl Blockchain {
             } else {
                 eprintln!("Warning: Could not create graph name for genesis block in config store");
             }
-
-            // Recalculate hash after adding data to RDF store (consistent with new() method)
-            genesis_block.hash = genesis_block.calculate_hash_with_store(Some(&bc.rdf_store));
             bc.chain.push(genesis_block);
         } else {
             // Load existing blockchain from persistent storage
@@ -243,9 +484,9 @@ impl Blockchain {
         // Query to get all blocks ordered by index
         let query = r#"
             PREFIX prov: <http://provchain.org/>
-            SELECT ?block ?index ?timestamp ?hash ?prevHash ?dataGraph ?validator ?signature WHERE {
+            SELECT ?block ?index ?timestamp ?hash ?prevHash ?dataGraph ?validator ?signature ?dataHash ?merkleRoot WHERE {
                 GRAPH <http://provchain.org/blockchain> {
-                    ?block a prov:Block ;
+                    ?block a ?blockType ;
                            prov:hasIndex ?index ;
                            prov:hasTimestamp ?timestamp ;
                            prov:hasHash ?hash ;
@@ -253,7 +494,10 @@ impl Blockchain {
                            prov:hasDataGraphIRI ?dataGraph ;
                            prov:hasValidator ?validator ;
                            prov:hasSignature ?signature .
+                    OPTIONAL { ?block prov:hasDataHash ?dataHash }
+                    OPTIONAL { ?block prov:hasMerkleRoot ?merkleRoot }
                 }
+                FILTER(?blockType IN (prov:Block, prov:GenesisBlock))
             }
             ORDER BY ?index
         "#;
@@ -277,13 +521,14 @@ impl Blockchain {
                     sol.get("validator"),
                     sol.get("signature"),
                 ) {
-                    // Parse block data
-                    let index: u64 = index_term.to_string().parse().unwrap_or(0);
-                    let timestamp = timestamp_term.to_string().trim_matches('"').to_string();
-                    let hash = hash_term.to_string().trim_matches('"').to_string();
-                    let previous_hash = prev_hash_term.to_string().trim_matches('"').to_string();
-                    let validator = validator_term.to_string().trim_matches('"').to_string();
-                    let signature = signature_term.to_string().trim_matches('"').to_string();
+                    // Parse block data from the literal values (typed literals keep their datatype
+                    // in the term's string form, which would corrupt the hashed header fields)
+                    let index: u64 = literal_value(index_term).parse().unwrap_or(0);
+                    let timestamp = literal_value(timestamp_term);
+                    let hash = literal_value(hash_term);
+                    let previous_hash = literal_value(prev_hash_term);
+                    let validator = literal_value(validator_term);
+                    let signature = literal_value(signature_term);
 
                     // Extract RDF data from the block's graph
                     let data_graph_string = data_graph_term.to_string();
@@ -304,6 +549,14 @@ impl Blockchain {
                     println!("Processed data graph URI: '{}'", data_graph_uri);
                     let data = self.extract_rdf_data_from_graph(data_graph_uri)?;
 
+                    // Chains persisted before data hashes were committed have no
+                    // hasDataHash triple; derive it from the stored graph instead.
+                    let data_hash = match sol.get("dataHash") {
+                        Some(term) => literal_value(term),
+                        None => self.store_data_hash(index),
+                    };
+                    let merkle_root = sol.get("merkleRoot").map(literal_value).unwrap_or_default();
+
                     // For existing blocks, we'll use a placeholder state_root
                     // In a real implementation, this would be loaded from the blockchain metadata
                     let state_root =
@@ -317,6 +570,8 @@ impl Blockchain {
                         previous_hash,
                         hash,
                         state_root,
+                        data_hash,
+                        merkle_root,
                         validator,
                         signature,
                     };
@@ -327,6 +582,7 @@ impl Blockchain {
         }
 
         println!("Loaded {} blocks from persistent storage", self.chain.len());
+        self.restore_governance_state();
         Ok(())
     }
 
@@ -426,6 +682,7 @@ impl Blockchain {
             ontology_manager: None,
             shacl_validator: None,
             governance: Governance::new(),
+            block_events: BlockEvents::default(),
         };
 
         // Initialize ontology manager and SHACL validator
@@ -459,6 +716,7 @@ impl Blockchain {
             ontology_manager: None,
             shacl_validator: None,
             governance: Governance::new(),
+            block_events: BlockEvents::default(),
         };
 
         // Initialize ontology manager and SHACL validator
@@ -468,7 +726,7 @@ impl Blockchain {
         let store_len = bc.rdf_store.store.len().unwrap_or(0);
         if store_len == 0 {
             // Create genesis block for new blockchain
-            let mut genesis_block = bc.create_genesis_block();
+            let genesis_block = bc.create_genesis_block();
 
             // Add genesis block data to RDF store
             if let Ok(graph_name) = NamedNode::new("http://provchain.org/block/0") {
@@ -480,9 +738,6 @@ impl Blockchain {
                     "Warning: Could not create graph name for genesis block in persistent store"
                 );
             }
-
-            // Recalculate hash after adding data to RDF store (consistent with new() method)
-            genesis_block.hash = genesis_block.calculate_hash_with_store(Some(&bc.rdf_store));
             bc.chain.push(genesis_block);
 
             // Save to disk
@@ -497,7 +752,7 @@ impl Blockchain {
             // Create genesis block as fallback
             if bc.chain.is_empty() {
                 eprintln!("Warning: Store has data but no blocks loaded, creating genesis block");
-                let mut genesis_block = bc.create_genesis_block();
+                let genesis_block = bc.create_genesis_block();
 
                 if let Ok(graph_name) = NamedNode::new("http://provchain.org/block/0") {
                     bc.rdf_store
@@ -506,9 +761,6 @@ impl Blockchain {
                 } else {
                     eprintln!("Warning: Could not create graph name for fallback genesis block");
                 }
-
-                // Recalculate hash after adding data to RDF store (consistent with new() method)
-                genesis_block.hash = genesis_block.calculate_hash_with_store(Some(&bc.rdf_store));
                 bc.chain.push(genesis_block);
             }
         }
@@ -565,6 +817,7 @@ impl Blockchain {
             ontology_manager: None,
             shacl_validator: None,
             governance: Governance::new(),
+            block_events: BlockEvents::default(),
         };
 
         // Load the chain from the restored store
@@ -606,7 +859,7 @@ impl Blockchain {
     pub fn create_block_proposal(&mut self, data: String, validator: String) -> Result<Block> {
         // Ensure we have at least a genesis block
         if self.chain.is_empty() {
-            let mut genesis_block = self.create_genesis_block();
+            let genesis_block = self.create_genesis_block();
             if let Ok(graph_name) = NamedNode::new("http://provchain.org/block/0") {
                 self.rdf_store
                     .add_rdf_to_graph(&genesis_block.data, &graph_name);
@@ -618,8 +871,6 @@ impl Blockchain {
                     ),
                 ));
             }
-            // Recalculate hash after adding data to RDF store
-            genesis_block.hash = genesis_block.calculate_hash_with_store(Some(&self.rdf_store));
             self.chain.push(genesis_block);
         }
 
@@ -667,6 +918,10 @@ impl Blockchain {
         // Calculate state root
         let state_root = self.rdf_store.calculate_state_root();
 
+        // Governance proposals and votes waiting for a block go into this one
+        let mut data = data;
+        data.push_str(&self.governance.pending_block_data());
+
         let block = Block::new(index, data, previous_hash, state_root, validator);
 
         Ok(block)
@@ -674,6 +929,42 @@ impl Blockchain {
 
     /// Submit a signed block to the blockchain
     pub fn submit_signed_block(&mut self, block: Block) -> Result<()> {
+        // The block must extend the current tip; competing blocks go through `reorganize`
+        self.verify_signed_block(&block, self.chain.last())?;
+        self.append_block(block)
+    }
+
+    /// Run the signed-block checks for `block` as the child of `parent`
+    fn verify_signed_block(&self, block: &Block, parent: Option<&Block>) -> Result<()> {
+        if let Some(parent) = parent {
+            if block.index != parent.index + 1 || block.previous_hash != parent.hash {
+                return Err(ProvChainError::Blockchain(BlockchainError::InvalidBlock(
+                    format!(
+                        "Block {} does not extend the chain tip at index {}",
+                        block.index, parent.index
+                    ),
+                )));
+            }
+        }
+
+        // The hash committed at proposal time must match the block data and header,
+        // since it is what the validator signed and it is never recomputed.
+        if !block.verify_data_hash() {
+            return Err(ProvChainError::Blockchain(BlockchainError::InvalidBlock(
+                format!(
+                    "Block {} data does not match its committed data hash",
+                    block.index
+                ),
+            )));
+        }
+        let expected_hash = block.calculate_hash();
+        if block.hash != expected_hash {
+            return Err(ProvChainError::Blockchain(BlockchainError::HashMismatch {
+                expected: expected_hash,
+                actual: block.hash.clone(),
+            }));
+        }
+
         // Verify signature
         if !self.governance.validator_set.is_empty() {
             if !self.governance.validator_set.contains(&block.validator) {
@@ -737,6 +1028,17 @@ impl Blockchain {
             );
         }
 
+        Ok(())
+    }
+
+    /// Indices of the blocks appended from now on, whether built locally, proposed by
+    /// consensus or received from peers
+    pub fn subscribe_blocks(&self) -> broadcast::Receiver<u64> {
+        self.block_events.0.subscribe()
+    }
+
+    /// Append a verified block to the chain and the RDF store
+    fn append_block(&mut self, block: Block) -> Result<()> {
         // Add block data to RDF store
         if let Ok(graph_name) =
             NamedNode::new(format!("http://provchain.org/block/{}", block.index))
@@ -751,13 +1053,21 @@ impl Blockchain {
             ));
         }
 
-        // Recalculate hash after adding data to RDF store to ensure consistency
-        // Note: We modify the block here, which invalidates the signature if the hash changes!
-        // Ideally, the hash should be stable.
-        let mut final_block = block;
-        final_block.hash = final_block.calculate_hash_with_store(Some(&self.rdf_store));
+        let index = block.index;
+        self.chain.push(block);
+
+        // Governance transactions in the block open proposals and count votes, and
+        // proposals whose voting window ended before it expire
+        let genesis_recorded = self.governance.genesis.is_some();
+        let changed = self
+            .governance
+            .apply_block(self.chain.last().expect("block was just appended"));
+        if let Err(e) = self.persist_governance(&changed, !genesis_recorded) {
+            warn!("Failed to record governance state: {}", e);
+        }
 
-        self.chain.push(final_block);
+        // Nobody listening is not an error
+        let _ = self.block_events.0.send(index);
 
         // Persist changes to disk if using persistent storage
         if let Err(e) = self.rdf_store.save_to_disk() {
@@ -767,6 +1077,330 @@ impl Blockchain {
         Ok(())
     }
 
+    /// Roll the chain back so that `index` is the last block, removing the rolled-back
+    /// blocks' named graphs and metadata from the RDF store. Returns the removed blocks
+    /// in ascending order.
+    pub fn rollback_to(&mut self, index: u64) -> Result<Vec<Block>> {
+        let finalized_index = self.finalized_index();
+        if index < finalized_index {
+            return Err(ProvChainError::Blockchain(
+                BlockchainError::InvalidChainState(format!(
+                    "Cannot roll back to block {} below finalized checkpoint {}",
+                    index, finalized_index
+                )),
+            ));
+        }
+
+        let mut removed = Vec::new();
+        while self.chain.len() as u64 > index + 1 {
+            let block = self.chain.pop().expect("chain is longer than index + 1");
+            self.rdf_store.remove_block(block.index)?;
+            removed.push(block);
+        }
+        removed.reverse();
+
+        if !removed.is_empty() {
+            // Governance follows the chain; what the removed blocks recorded waits for
+            // the next block again
+            self.governance.requeue(&removed);
+            self.rebuild_governance()?;
+            self.rdf_store.save_to_disk()?;
+            info!("Rolled back {} block(s) to index {}", removed.len(), index);
+        }
+
+        Ok(removed)
+    }
+
+    /// Replace the blocks after `fork_index` with `branch`, replaying each branch block
+    /// through the usual signed-block checks.
+    ///
+    /// Returns the rolled-back blocks so the caller can keep them as a side branch. The
+    /// whole branch is verified, signatures and ledger, before anything is rolled back,
+    /// so a rejected branch leaves the chain untouched.
+    pub fn reorganize(&mut self, fork_index: u64, branch: Vec<Block>) -> Result<Vec<Block>> {
+        let Some(fork_block) = self.chain.get(fork_index as usize) else {
+            return Err(ProvChainError::Blockchain(BlockchainError::BlockNotFound(
+                fork_index.to_string(),
+            )));
+        };
+
+        let mut parent = fork_block;
+        for block in &branch {
+            self.verify_signed_block(block, Some(parent))?;
+            parent = block;
+        }
+
+        // The branch replays the ledger from the fork point under the same rules as a
+        // block extending the tip: no nonce replays, double spends or foreign inputs
+        let mut ledger = LedgerState::from_chain(&self.chain[..=fork_index as usize])
+            .map_err(|e| BlockchainError::InvalidChainState(e.to_string()))?;
+        for block in &branch {
+            ledger
+                .validate_block_data(&block.data)
+                .and_then(|_| ledger.apply_block(block))
+                .map_err(|e| {
+                    BlockchainError::InvalidBlock(format!(
+                        "Block {} breaks the ledger: {}",
+                        block.index, e
+                    ))
+                })?;
+        }
+
+        let orphaned = self.rollback_to(fork_index)?;
+        for block in branch {
+            let block_index = block.index;
+            if let Err(e) = self.append_block(block) {
+                warn!(
+                    "Reorganization aborted at block {}: {}; restoring previous chain",
+                    block_index, e
+                );
+                // The original blocks passed verification when they were first added
+                self.rollback_to(fork_index)?;
+                for original in orphaned {
+                    self.append_block(original)?;
+                }
+                return Err(e);
+            }
+        }
+
+        info!(
+            "Reorganized chain at fork index {}: {} block(s) replaced, new height {}",
+            fork_index,
+            orphaned.len(),
+            self.chain.len()
+        );
+        Ok(orphaned)
+    }
+
+    /// Last finalized checkpoint, loaded from the persisted finality certificates
+    pub fn last_finalized_checkpoint(&self) -> Option<FinalityCertificate> {
+        self.rdf_store.latest_finality_certificate()
+    }
+
+    /// Index of the last finalized block. The genesis block is always final.
+    pub fn finalized_index(&self) -> u64 {
+        self.last_finalized_checkpoint()
+            .map(|certificate| certificate.block_index)
+            .unwrap_or(0)
+    }
+
+    /// Verify and persist a finality certificate for a block on the canonical chain.
+    ///
+    /// Certificates at or below the current finalized checkpoint are ignored.
+    pub fn record_finality_certificate(&mut self, certificate: &FinalityCertificate) -> Result<()> {
+        if self
+            .last_finalized_checkpoint()
+            .is_some_and(|last| certificate.block_index <= last.block_index)
+        {
+            debug!(
+                "Ignoring finality certificate for block {} at or below the finalized checkpoint",
+                certificate.block_index
+            );
+            return Ok(());
+        }
+
+        let block = self
+            .chain
+            .get(certificate.block_index as usize)
+            .ok_or_else(|| {
+                ProvChainError::Blockchain(BlockchainError::BlockNotFound(
+                    certificate.block_index.to_string(),
+                ))
+            })?;
+        if block.hash != certificate.block_hash {
+            return Err(ProvChainError::Blockchain(
+                BlockchainError::InvalidChainState(format!(
+                    "Finality certificate for block {} does not match the canonical chain",
+                    certificate.block_index
+                )),
+            ));
+        }
+
+        certificate.verify(&self.governance.validator_set)?;
+        self.rdf_store.add_finality_certificate(certificate)?;
+
+        if let Err(e) = self.rdf_store.save_to_disk() {
+            eprintln!("Warning: Failed to persist finality certificate: {}", e);
+        }
+
+        info!(
+            "Block {} finalized with {} authority signatures",
+            certificate.block_index,
+            certificate.votes.len()
+        );
+        Ok(())
+    }
+
+    /// Snapshot the chain state at the last finalized checkpoint for fast sync.
+    ///
+    /// Blocks above the checkpoint are left out, so the snapshot only contains state
+    /// that can no longer be reorganized away.
+    pub fn create_snapshot(&self, signing_key: &SigningKey) -> Result<StateSnapshot> {
+        let certificate = self.last_finalized_checkpoint().ok_or_else(|| {
+            ProvChainError::Blockchain(BlockchainError::InvalidChainState(
+                "No finalized checkpoint to snapshot".to_string(),
+            ))
+        })?;
+
+        let mut store = RDFStore::new();
+        store.load_data_from_string(&self.rdf_store.export_nquads()?)?;
+        for index in certificate.block_index + 1..self.chain.len() as u64 {
+            store.remove_block(index)?;
+        }
+
+        let headers = self.get_headers(0, certificate.block_index);
+        StateSnapshot::create(&store, headers, certificate, signing_key)
+    }
+
+    /// Replace the local chain with a verified snapshot. Blocks after the snapshot are
+    /// then synced as usual.
+    ///
+    /// Only a chain that is behind the snapshot is replaced. If the chain rebuilt from the
+    /// snapshot does not match its headers, the previous chain is restored.
+    pub fn install_snapshot(&mut self, snapshot: &StateSnapshot) -> Result<()> {
+        if self.chain.len() as u64 > snapshot.block_index {
+            return Err(ProvChainError::Blockchain(
+                BlockchainError::InvalidChainState(format!(
+                    "Local chain at height {} is not behind snapshot block {}",
+                    self.chain.len(),
+                    snapshot.block_index
+                )),
+            ));
+        }
+        snapshot.verify(&self.governance.validator_set)?;
+
+        let previous_state = self.rdf_store.export_nquads()?;
+        let previous_chain = std::mem::take(&mut self.chain);
+        let previous_governance = self.governance.clone();
+        self.rdf_store.replace_contents(&snapshot.nquads)?;
+        self.load_chain_from_store()?;
+
+        let matches_headers = self.chain.len() == snapshot.headers.len()
+            && self
+                .chain
+                .iter()
+                .zip(&snapshot.headers)
+                .all(|(block, header)| block.hash == header.hash);
+        if !matches_headers {
+            self.rdf_store.replace_contents(&previous_state)?;
+            self.chain = previous_chain;
+            self.governance = previous_governance;
+            return Err(ProvChainError::Blockchain(
+                BlockchainError::InvalidChainState(format!(
+                    "Chain rebuilt from snapshot block {} does not match its headers",
+                    snapshot.block_index
+                )),
+            ));
+        }
+
+        if let Err(e) = self.rdf_store.save_to_disk() {
+            eprintln!("Warning: Failed to persist installed snapshot: {}", e);
+        }
+
+        info!(
+            "Installed snapshot at block {} with state root {}",
+            snapshot.block_index, snapshot.state_root
+        );
+        Ok(())
+    }
+
+    /// Queue a governance proposal for the next block. Returns the proposal as it
+    /// opens once a block includes it.
+    pub fn submit_governance_proposal(&mut self, tx: &Transaction) -> Result<Proposal> {
+        let next_block = self.chain.last().map_or(0, |block| block.index + 1);
+        Ok(self.governance.submit_proposal(tx.clone(), next_block)?)
+    }
+
+    /// Queue a validator's vote on a proposal for the next block. The vote counts, and
+    /// may execute the proposal, once a block includes it.
+    pub fn vote_on_proposal(&mut self, vote: ProposalVote) -> Result<()> {
+        Ok(self.governance.submit_vote(vote)?)
+    }
+
+    /// Write the given proposals to the governance graph, along with the network
+    /// configuration and validator set if one of them was executed, and the genesis
+    /// governance if `record_genesis` is set
+    fn persist_governance(&mut self, proposal_ids: &[String], record_genesis: bool) -> Result<()> {
+        if record_genesis {
+            if let Some(genesis) = &self.governance.genesis {
+                self.rdf_store.record_governance_genesis(genesis)?;
+            }
+        }
+
+        let mut executed = false;
+        for proposal_id in proposal_ids {
+            let Some(proposal) = self.governance.proposals.get(proposal_id) else {
+                continue;
+            };
+            self.rdf_store.record_governance_proposal(proposal)?;
+            executed |= proposal.status == ProposalStatus::Executed;
+        }
+        if executed {
+            self.record_governed_state()?;
+        }
+        Ok(())
+    }
+
+    /// Record the network configuration and validator set governance left in effect
+    fn record_governed_state(&mut self) -> Result<()> {
+        self.rdf_store
+            .record_network_config(&self.governance.network_config)?;
+        self.rdf_store
+            .record_validator_set(&self.governance.validator_set)?;
+        Ok(())
+    }
+
+    /// Replay the governance transactions on the chain from the genesis governance and
+    /// rewrite the governance graph to match
+    fn rebuild_governance(&mut self) -> Result<()> {
+        let previous: Vec<String> = self.governance.proposals.keys().cloned().collect();
+        self.governance.replay(&self.chain);
+
+        for proposal_id in &previous {
+            if !self.governance.proposals.contains_key(proposal_id) {
+                self.rdf_store.remove_governance_proposal(proposal_id)?;
+            }
+        }
+        for proposal in self.governance.proposals.values() {
+            self.rdf_store.record_governance_proposal(proposal)?;
+        }
+        if self.governance.genesis.is_some() {
+            self.record_governed_state()?;
+        }
+        Ok(())
+    }
+
+    /// Restore governance by replaying the loaded chain from the genesis governance
+    /// recorded in the governance graph
+    fn restore_governance_state(&mut self) {
+        if let Some(validator_set) = self.rdf_store.validator_set() {
+            self.governance.validator_set = validator_set;
+        }
+        if let Some(network_config) = self.rdf_store.network_config() {
+            self.governance.min_validators = network_config.min_validators;
+            self.governance.max_validators = network_config.max_validators;
+            self.governance.network_config = network_config;
+        }
+
+        self.governance.genesis = self.rdf_store.governance_genesis();
+        if self.governance.genesis.is_none() && self.chain.len() > 1 {
+            // Chains recorded before governance moved into blocks start from the
+            // validator set and configuration the governance graph holds
+            let genesis = GovernanceGenesis {
+                validator_set: self.governance.validator_set.iter().cloned().collect(),
+                network_config: self.governance.network_config.clone(),
+            };
+            if let Err(e) = self.rdf_store.record_governance_genesis(&genesis) {
+                warn!("Failed to record genesis governance: {}", e);
+            }
+            self.governance.genesis = Some(genesis);
+        }
+
+        if let Err(e) = self.rebuild_governance() {
+            warn!("Failed to rebuild governance state: {}", e);
+        }
+    }
+
     /// Legacy add_block for backward compatibility (uses dummy validator)
     pub fn add_block(&mut self, data: String) -> Result<()> {
         let validator = "LEGACY_VALIDATOR".to_string();
@@ -823,6 +1457,168 @@ impl Blockchain {
         temp_canonical_hash == main_canonical_hash
     }
 
+    /// Canonical hash of a block's data graph as currently held in the RDF store
+    fn store_data_hash(&self, index: u64) -> String {
+        match NamedNode::new(Block::graph_uri(index)) {
+            Ok(graph_name) => self.rdf_store.canonicalize_graph(&graph_name),
+            Err(_) => String::new(),
+        }
+    }
+
+    /// Re-verify every block's stored hash and validator signature.
+    ///
+    /// Blocks with placeholder validators (genesis, legacy `add_block`) are listed as
+    /// unsigned rather than failing, since they never carried a real signature.
+    pub fn verify_chain_signatures(&self) -> ChainVerificationReport {
+        let mut report = ChainVerificationReport::default();
+
+        for block in &self.chain {
+            report.blocks_checked += 1;
+
+            let expected_hash = block.calculate_hash();
+            if block.hash != expected_hash {
+                report.issues.push((
+                    block.index,
+                    BlockVerificationIssue::HashMismatch {
+                        expected: expected_hash,
+                        actual: block.hash.clone(),
+                    },
+                ));
+            }
+
+            let store_hash = self.store_data_hash(block.index);
+            if block.data_hash != store_hash {
+                report.issues.push((
+                    block.index,
+                    BlockVerificationIssue::DataHashMismatch {
+                        committed: block.data_hash.clone(),
+                        store: store_hash,
+                    },
+                ));
+            }
+
+            if !block.merkle_root.is_empty() {
+                let store_root = merkle_root(&self.canonical_quads(block.index));
+                if block.merkle_root != store_root {
+                    report.issues.push((
+                        block.index,
+                        BlockVerificationIssue::MerkleRootMismatch {
+                            committed: block.merkle_root.clone(),
+                            store: store_root,
+                        },
+                    ));
+                }
+            }
+
+            match block.header().verify_signature() {
+                Ok(true) => report.signatures_verified += 1,
+                Ok(false) => report.unsigned_blocks.push(block.index),
+                Err(e) => report.issues.push((
+                    block.index,
+                    BlockVerificationIssue::SignatureMismatch(e.to_string()),
+                )),
+            }
+        }
+
+        report
+    }
+
+    /// Migrate a persisted chain to committed data hashes and report blocks that no
+    /// longer verify. Blocks without a persisted data hash get one recorded from the store.
+    pub fn migrate_block_hashes(&mut self) -> Result<ChainVerificationReport> {
+        use oxigraph::sparql::QueryResults;
+
+        let mut report = self.verify_chain_signatures();
+
+        for block in &self.chain {
+            let query = format!(
+                r#"ASK {{ GRAPH <http://provchain.org/blockchain> {{ <{}> <http://provchain.org/hasDataHash> ?h }} }}"#,
+                Block::graph_uri(block.index)
+            );
+            let has_data_hash = matches!(
+                self.rdf_store.store.query(query.as_str()),
+                Ok(QueryResults::Boolean(true))
+            );
+            if !has_data_hash {
+                self.rdf_store.add_block_commitments(block);
+                report.migrated_blocks.push(block.index);
+            }
+        }
+
+        if !report.migrated_blocks.is_empty() {
+            self.rdf_store.save_to_disk()?;
+        }
+
+        info!(
+            "Chain migration checked {} blocks, migrated {}, found {} issues",
+            report.blocks_checked,
+            report.migrated_blocks.len(),
+            report.issues.len()
+        );
+
+        Ok(report)
+    }
+
+    /// Headers of the blocks in `from..=to`, clamped to the current chain
+    pub fn get_headers(&self, from: u64, to: u64) -> Vec<BlockHeader> {
+        self.chain
+            .iter()
+            .filter(|block| block.index >= from && block.index <= to)
+            .map(Block::header)
+            .collect()
+    }
+
+    /// Sorted RDFC-1.0 canonical N-Quads of a block's data graph: the Merkle leaves
+    pub fn canonical_quads(&self, index: u64) -> Vec<String> {
+        match NamedNode::new(Block::graph_uri(index)) {
+            Ok(graph_name) => self.rdf_store.canonical_nquads_rdfc10(&graph_name),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Merkle inclusion proof for a canonical N-Quads line of a block's data graph
+    pub fn inclusion_proof(&self, index: u64, canonical_quad: &str) -> Option<MerkleProof> {
+        let leaves = self.canonical_quads(index);
+        let leaf_index = leaves.iter().position(|leaf| leaf == canonical_quad)?;
+        MerkleTree::new(&leaves).proof(leaf_index)
+    }
+
+    /// Build self-contained inclusion proofs for the triples of a block matching `pattern`
+    pub fn prove_triples(
+        &self,
+        index: u64,
+        pattern: &TriplePattern,
+    ) -> Result<Vec<TripleInclusionProof>> {
+        let block = self.chain.get(index as usize).ok_or_else(|| {
+            ProvChainError::Blockchain(BlockchainError::BlockNotFound(index.to_string()))
+        })?;
+        if block.merkle_root.is_empty() {
+            return Err(ProvChainError::Blockchain(BlockchainError::InvalidBlock(
+                format!("Block {} predates Merkle roots", index),
+            )));
+        }
+
+        let leaves = self.canonical_quads(index);
+        let tree = MerkleTree::new(&leaves);
+        let header = block.header();
+
+        Ok(leaves
+            .iter()
+            .enumerate()
+            .filter(|(_, leaf)| pattern.matches_canonical(leaf))
+            .filter_map(|(leaf_index, leaf)| {
+                tree.proof(leaf_index)
+                    .map(|merkle_proof| TripleInclusionProof {
+                        version: PROOF_VERSION,
+                        graph_uri: Block::graph_uri(index),
+                        canonical_quad: leaf.clone(),
+                        merkle_proof,
+                        header: header.clone(),
+                    })
+            })
+            .collect())
+    }
+
     fn load_ontology(&mut self) {
         // For now, use hardcoded ontology loading
         // TODO: Integrate with CLI-based ontology selection in Step 7
@@ -874,3 +1670,12 @@ impl Blockchain {
         5
     }
 }
+
+/// Lexical value of a SPARQL solution term without quotes or datatype annotation
+fn literal_value(term: &oxigraph::model::Term) -> String {
+    match term {
+        oxigraph::model::Term::Literal(literal) => literal.value().to_string(),
+        oxigraph::model::Term::NamedNode(node) => node.as_str().to_string(),
+        other => other.to_string().trim_matches('"').to_string(),
+    }
+}
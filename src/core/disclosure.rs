@@ -0,0 +1,342 @@
+//! Selective disclosure of confidential triples
+//!
+//! A confidential triple is anchored on-chain only as a salted commitment, one triple
+//! per commitment in the block graph:
+//!
+//! ```text
+//! <urn:provchain:commitment:HASH> rdf:type pc:ConfidentialTripleCommitment .
+//! ```
+//!
+//! where `HASH` is SHA-256 over a domain tag, a random salt and the triple in N-Triples
+//! form. The plaintext and salt stay with the data owner. Disclosing a triple hands the
+//! recipient the plaintext, the salt and an inclusion proof of the commitment line, so
+//! the recipient can check the triple against the block's Merkle root and signed header
+//! without learning anything else that was committed.
+
+use crate::core::blockchain::Blockchain;
+use crate::core::proof::{parse_canonical_quad, TripleInclusionProof, TriplePattern};
+use crate::error::{BlockchainError, CryptoError, ProvChainError, Result, ValidationError};
+use oxigraph::io::RdfFormat;
+use oxigraph::model::vocab::rdf;
+use oxigraph::model::{NamedNode, Subject, Term, Triple};
+use oxigraph::store::Store;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Class of the commitment nodes written to block graphs
+pub const COMMITMENT_CLASS: &str = "http://provchain.org/core#ConfidentialTripleCommitment";
+
+/// Prefix of commitment node IRIs, followed by the hex commitment hash
+pub const COMMITMENT_PREFIX: &str = "urn:provchain:commitment:";
+
+/// Disclosure format version, bumped whenever the commitment scheme changes
+pub const DISCLOSURE_VERSION: u32 = 1;
+
+/// A confidential triple sealed with its salt, ready to be committed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedTriple {
+    /// The triple in N-Triples form, without the trailing ` .`
+    pub triple: String,
+    /// Hex-encoded random salt
+    pub salt: String,
+    /// Hex SHA-256 commitment anchored on-chain
+    pub commitment: String,
+}
+
+impl SealedTriple {
+    /// Seal a triple with a fresh random salt
+    pub fn seal(triple: &Triple) -> Self {
+        let salt = hex::encode(rand::random::<[u8; 16]>());
+        Self {
+            triple: triple.to_string(),
+            commitment: commitment_hash(triple, &salt),
+            salt,
+        }
+    }
+
+    /// Parse every triple of a Turtle document and seal each one.
+    ///
+    /// Blank nodes are rejected: their labels are not stable, so a disclosed triple
+    /// could not be matched against its commitment.
+    pub fn seal_turtle(data: &str) -> Result<Vec<Self>> {
+        let store = Store::new().map_err(|e| invalid("data", e.to_string()))?;
+        store
+            .load_from_reader(RdfFormat::Turtle, data.as_bytes())
+            .map_err(|e| invalid("data", format!("confidential data does not parse: {}", e)))?;
+
+        let mut sealed = Vec::new();
+        for quad in store.iter() {
+            let quad = quad.map_err(|e| invalid("data", e.to_string()))?;
+            if matches!(quad.subject, Subject::BlankNode(_))
+                || matches!(quad.object, Term::BlankNode(_))
+            {
+                return Err(invalid(
+                    "data",
+                    "confidential triples cannot contain blank nodes".to_string(),
+                ));
+            }
+            sealed.push(Self::seal(&Triple::new(
+                quad.subject,
+                quad.predicate,
+                quad.object,
+            )));
+        }
+        if sealed.is_empty() {
+            return Err(invalid("data", "no triples to commit".to_string()));
+        }
+        Ok(sealed)
+    }
+
+    /// Commitment triple in N-Triples, as written to the block
+    pub fn commitment_ntriples(&self) -> String {
+        format!("{} .", commitment_triple(&self.commitment))
+    }
+}
+
+/// Salted commitment of a triple
+pub fn commitment_hash(triple: &Triple, salt: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(b"provchain-disclosure:");
+    hasher.update(salt.as_bytes());
+    hasher.update(b":");
+    hasher.update(triple.to_string().as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// The on-chain triple anchoring a commitment
+pub fn commitment_triple(commitment: &str) -> Triple {
+    Triple::new(
+        NamedNode::new_unchecked(format!("{}{}", COMMITMENT_PREFIX, commitment)),
+        rdf::TYPE,
+        NamedNode::new_unchecked(COMMITMENT_CLASS),
+    )
+}
+
+/// Everything a recipient needs to check a disclosed triple
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisclosureProof {
+    /// Disclosure format version
+    pub version: u32,
+    /// The disclosed triple in N-Triples form, without the trailing ` .`
+    pub triple: String,
+    pub salt: String,
+    /// Inclusion proof of the commitment triple in its block
+    pub commitment_proof: TripleInclusionProof,
+}
+
+/// Outcome of verifying a disclosure
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisclosureVerification {
+    pub commitment: String,
+    /// Block the commitment is anchored in
+    pub block_index: u64,
+    pub block_hash: String,
+    /// Hex-encoded public key of the validator that signed the block
+    pub validator: String,
+    /// Whether the header carried a real Ed25519 signature
+    pub signed: bool,
+}
+
+impl DisclosureProof {
+    /// Build a disclosure of a sealed triple committed in block `index`
+    pub fn build(blockchain: &Blockchain, index: u64, sealed: &SealedTriple) -> Result<Self> {
+        let pattern = TriplePattern {
+            subject: Some(format!("{}{}", COMMITMENT_PREFIX, sealed.commitment)),
+            ..Default::default()
+        };
+        let commitment_proof = blockchain
+            .prove_triples(index, &pattern)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                ProvChainError::Blockchain(BlockchainError::InvalidBlock(format!(
+                    "Block {} does not hold commitment {}",
+                    index, sealed.commitment
+                )))
+            })?;
+
+        Ok(Self {
+            version: DISCLOSURE_VERSION,
+            triple: sealed.triple.clone(),
+            salt: sealed.salt.clone(),
+            commitment_proof,
+        })
+    }
+
+    /// Check the disclosure offline: the salted triple must hash to the proven
+    /// commitment, and the commitment's inclusion proof must hold.
+    pub fn verify(&self) -> Result<DisclosureVerification> {
+        if self.version != DISCLOSURE_VERSION {
+            return Err(invalid(
+                "version",
+                format!("unsupported disclosure version {}", self.version),
+            ));
+        }
+
+        let triple = parse_canonical_quad(&format!("{} .", self.triple))
+            .ok_or_else(|| invalid("triple", "disclosed triple does not parse".to_string()))?;
+        let commitment = commitment_hash(&triple, &self.salt);
+        let proven = parse_canonical_quad(&self.commitment_proof.canonical_quad);
+        if proven.as_ref() != Some(&commitment_triple(&commitment)) {
+            return Err(ProvChainError::Crypto(CryptoError::HashCalculationFailed(
+                "Disclosed triple and salt do not match the proven commitment".to_string(),
+            )));
+        }
+
+        let verification = self.commitment_proof.verify()?;
+        Ok(DisclosureVerification {
+            commitment,
+            block_index: verification.block_index,
+            block_hash: self.commitment_proof.header.hash.clone(),
+            validator: verification.validator,
+            signed: verification.signed,
+        })
+    }
+
+    /// Check the disclosure against `blockchain`: besides what `verify` checks, the
+    /// block must be the one at its index on this chain and, when the chain has a
+    /// validator set, be signed by one of its validators. A proof whose header was
+    /// signed by any other key fails.
+    pub fn verify_on(&self, blockchain: &Blockchain) -> Result<DisclosureVerification> {
+        let verification = self.verify()?;
+        let on_chain = blockchain
+            .chain
+            .get(verification.block_index as usize)
+            .is_some_and(|block| block.hash == verification.block_hash);
+        if !on_chain {
+            return Err(ProvChainError::Blockchain(
+                BlockchainError::ValidationFailed(format!(
+                    "Block {} with hash {} is not on this chain",
+                    verification.block_index, verification.block_hash
+                )),
+            ));
+        }
+
+        let validator_set = &blockchain.governance.validator_set;
+        if !validator_set.is_empty() && !validator_set.contains(&verification.validator) {
+            return Err(ProvChainError::Blockchain(
+                BlockchainError::ValidationFailed(format!(
+                    "Block {} is signed by untrusted validator {}",
+                    verification.block_index, verification.validator
+                )),
+            ));
+        }
+        Ok(verification)
+    }
+}
+
+fn invalid(field: &str, reason: String) -> ProvChainError {
+    ProvChainError::Validation(ValidationError::InvalidInput {
+        field: field.to_string(),
+        reason,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIDENTIAL: &str = r#"
+        @prefix ex: <http://example.org/> .
+        ex:batch1 ex:unitPrice "4.20" ;
+                  ex:recipe "secret blend" .
+    "#;
+
+    /// Chain with the commitments of `CONFIDENTIAL` in block 1
+    fn committed() -> (Blockchain, Vec<SealedTriple>) {
+        let sealed = SealedTriple::seal_turtle(CONFIDENTIAL).unwrap();
+        let data = sealed
+            .iter()
+            .map(SealedTriple::commitment_ntriples)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut blockchain = Blockchain::new();
+        blockchain.add_block(data).unwrap();
+        (blockchain, sealed)
+    }
+
+    #[test]
+    fn test_commitments_hide_triples() {
+        let (blockchain, sealed) = committed();
+        assert_eq!(sealed.len(), 2);
+
+        let block = &blockchain.chain[1];
+        assert!(!block.data.contains("secret blend"));
+        assert!(!block.data.contains("4.20"));
+        for triple in &sealed {
+            assert!(block.data.contains(&triple.commitment));
+        }
+
+        // The same triple sealed twice gets unrelated commitments
+        let again = SealedTriple::seal_turtle(CONFIDENTIAL).unwrap();
+        assert!(again.iter().all(|triple| !sealed.contains(triple)));
+    }
+
+    #[test]
+    fn test_disclosure_verifies_against_block() {
+        let (blockchain, sealed) = committed();
+        let proof = DisclosureProof::build(&blockchain, 1, &sealed[0]).unwrap();
+
+        let verification = proof.verify().unwrap();
+        assert_eq!(verification.block_index, 1);
+        assert_eq!(verification.block_hash, blockchain.chain[1].hash);
+        assert_eq!(verification.commitment, sealed[0].commitment);
+
+        // Survives a JSON round trip, as sent to the recipient
+        let json = serde_json::to_string(&proof).unwrap();
+        let received: DisclosureProof = serde_json::from_str(&json).unwrap();
+        assert!(received.verify().is_ok());
+        assert_eq!(received.verify_on(&blockchain).unwrap(), verification);
+    }
+
+    #[test]
+    fn test_disclosure_must_be_on_the_chain_and_trusted() {
+        let (blockchain, sealed) = committed();
+        let proof = DisclosureProof::build(&blockchain, 1, &sealed[0]).unwrap();
+        let verification = proof.verify_on(&blockchain).unwrap();
+
+        // A proof of a block this node does not have only verifies offline
+        let without_block = Blockchain::new();
+        assert!(proof.verify().is_ok());
+        assert!(proof.verify_on(&without_block).is_err());
+
+        // With a validator set, the block's validator must belong to it
+        let mut governed = blockchain.clone();
+        governed.governance.validator_set.insert("00".repeat(32));
+        let error = proof.verify_on(&governed).unwrap_err();
+        assert!(error.to_string().contains("untrusted validator"));
+        governed
+            .governance
+            .validator_set
+            .insert(verification.validator.clone());
+        assert!(proof.verify_on(&governed).is_ok());
+    }
+
+    #[test]
+    fn test_tampered_disclosures_fail() {
+        let (blockchain, sealed) = committed();
+        let proof = DisclosureProof::build(&blockchain, 1, &sealed[0]).unwrap();
+
+        let mut wrong_triple = proof.clone();
+        wrong_triple.triple = sealed[1].triple.clone();
+        assert!(wrong_triple.verify().is_err());
+
+        let mut wrong_salt = proof.clone();
+        wrong_salt.salt = sealed[1].salt.clone();
+        assert!(wrong_salt.verify().is_err());
+
+        let mut wrong_header = proof;
+        wrong_header.commitment_proof.header.merkle_root = "00".repeat(32);
+        assert!(wrong_header.verify().is_err());
+
+        // Commitments are only found in the block that holds them
+        assert!(DisclosureProof::build(&blockchain, 0, &sealed[0]).is_err());
+    }
+
+    #[test]
+    fn test_blank_nodes_are_rejected() {
+        let data = "@prefix ex: <http://example.org/> . [] ex:unitPrice \"4.20\" .";
+        assert!(SealedTriple::seal_turtle(data).is_err());
+        assert!(SealedTriple::seal_turtle("").is_err());
+    }
+}
@@ -0,0 +1,317 @@
+//! Checkpoint finality for the PoA network
+//!
+//! Every `checkpoint_interval` blocks, authorities co-sign the checkpoint block hash.
+//! Once more than two thirds of `Governance::validator_set` have signed the same hash,
+//! the votes form a finality certificate. Certificates are persisted in the RDF store
+//! and neither sync nor chain reorganization may go below the last finalized checkpoint.
+
+use crate::error::{CryptoError, ProvChainError, Result, ValidationError};
+use chrono::Utc;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Named graph holding finality certificates
+pub const FINALITY_GRAPH: &str = "http://provchain.org/finality";
+
+/// Default number of blocks between checkpoints
+pub const DEFAULT_CHECKPOINT_INTERVAL: u64 = 10;
+
+/// Number of votes needed from a validator set of `validator_count` members (> 2/3)
+pub fn supermajority(validator_count: usize) -> usize {
+    validator_count * 2 / 3 + 1
+}
+
+/// An authority's signature on a checkpoint block hash
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FinalityVote {
+    pub block_index: u64,
+    pub block_hash: String,
+    /// Hex-encoded Ed25519 public key of the voting authority
+    pub validator: String,
+    /// Hex-encoded signature over `signing_payload(block_index, block_hash)`
+    pub signature: String,
+}
+
+impl FinalityVote {
+    /// Bytes signed by a finality vote; domain-separated from block signatures
+    pub fn signing_payload(block_index: u64, block_hash: &str) -> Vec<u8> {
+        format!("provchain-finality:{}:{}", block_index, block_hash).into_bytes()
+    }
+
+    /// Sign a checkpoint with an authority key
+    pub fn sign(block_index: u64, block_hash: &str, signing_key: &SigningKey) -> Self {
+        let signature = signing_key.sign(&Self::signing_payload(block_index, block_hash));
+        Self {
+            block_index,
+            block_hash: block_hash.to_string(),
+            validator: hex::encode(signing_key.verifying_key().to_bytes()),
+            signature: hex::encode(signature.to_bytes()),
+        }
+    }
+
+    /// Verify the vote signature against the validator key it names
+    pub fn verify(&self) -> Result<()> {
+        let key_bytes: [u8; 32] = hex::decode(&self.validator)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| {
+                CryptoError::InvalidKeyFormat(format!("validator key {}", self.validator))
+            })?;
+        let signature_bytes: [u8; 64] = hex::decode(&self.signature)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| {
+                CryptoError::SignatureVerificationFailed("malformed vote signature".to_string())
+            })?;
+
+        let public_key = VerifyingKey::from_bytes(&key_bytes)
+            .map_err(|e| CryptoError::InvalidKeyFormat(e.to_string()))?;
+        public_key
+            .verify(
+                &Self::signing_payload(self.block_index, &self.block_hash),
+                &Signature::from_bytes(&signature_bytes),
+            )
+            .map_err(|e| CryptoError::SignatureVerificationFailed(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+/// Proof that a supermajority of the validator set finalized a checkpoint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FinalityCertificate {
+    pub block_index: u64,
+    pub block_hash: String,
+    pub votes: Vec<FinalityVote>,
+    /// RFC 3339 time the certificate was assembled
+    pub finalized_at: String,
+}
+
+impl FinalityCertificate {
+    /// Check that the certificate carries valid votes for its checkpoint from a
+    /// supermajority of `validator_set`
+    pub fn verify(&self, validator_set: &HashSet<String>) -> Result<()> {
+        if validator_set.is_empty() {
+            return Err(ProvChainError::Validation(
+                ValidationError::ConstraintViolation(
+                    "finality requires a non-empty validator set".to_string(),
+                ),
+            ));
+        }
+
+        let mut signers = HashSet::new();
+        for vote in &self.votes {
+            if vote.block_index != self.block_index
+                || vote.block_hash != self.block_hash
+                || !validator_set.contains(&vote.validator)
+            {
+                continue;
+            }
+            if vote.verify().is_ok() {
+                signers.insert(vote.validator.as_str());
+            }
+        }
+
+        let required = supermajority(validator_set.len());
+        if signers.len() < required {
+            return Err(ProvChainError::Validation(
+                ValidationError::ConstraintViolation(format!(
+                    "checkpoint {} has {} valid authority signatures, {} required",
+                    self.block_index,
+                    signers.len(),
+                    required
+                )),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Collects finality votes until a checkpoint reaches a supermajority
+#[derive(Debug, Clone)]
+pub struct FinalityTracker {
+    /// Blocks between checkpoints
+    pub checkpoint_interval: u64,
+    /// Votes per (checkpoint index, block hash), keyed by validator
+    votes: HashMap<(u64, String), HashMap<String, FinalityVote>>,
+    /// Index of the last finalized checkpoint; older votes are ignored
+    finalized_index: Option<u64>,
+}
+
+impl FinalityTracker {
+    /// Create a tracker for checkpoints every `checkpoint_interval` blocks
+    pub fn new(checkpoint_interval: u64) -> Self {
+        Self {
+            checkpoint_interval: checkpoint_interval.max(1),
+            votes: HashMap::new(),
+            finalized_index: None,
+        }
+    }
+
+    /// Change the checkpoint interval, e.g. after a governance configuration update
+    pub fn set_checkpoint_interval(&mut self, checkpoint_interval: u64) {
+        self.checkpoint_interval = checkpoint_interval.max(1);
+    }
+
+    /// Whether a block index is a checkpoint
+    pub fn is_checkpoint(&self, block_index: u64) -> bool {
+        block_index > 0 && block_index.is_multiple_of(self.checkpoint_interval)
+    }
+
+    /// Tell the tracker about a checkpoint finalized elsewhere (e.g. loaded from disk)
+    pub fn set_finalized(&mut self, block_index: u64) {
+        if self.finalized_index.is_none_or(|index| block_index > index) {
+            self.finalized_index = Some(block_index);
+        }
+        self.votes.retain(|(index, _), _| *index > block_index);
+    }
+
+    /// Whether this validator already voted for the checkpoint
+    pub fn has_voted(&self, block_index: u64, block_hash: &str, validator: &str) -> bool {
+        self.votes
+            .get(&(block_index, block_hash.to_string()))
+            .is_some_and(|votes| votes.contains_key(validator))
+    }
+
+    /// Record a vote. Returns the certificate once the checkpoint reaches a supermajority.
+    ///
+    /// The checkpoint is only finalized by `set_finalized`, once the certificate has been
+    /// recorded; until then its votes are kept and every further vote returns the
+    /// certificate again. Votes from outside the validator set, for non-checkpoint blocks,
+    /// or with bad signatures are rejected; votes at or below the last finalized
+    /// checkpoint are ignored.
+    pub fn add_vote(
+        &mut self,
+        vote: FinalityVote,
+        validator_set: &HashSet<String>,
+    ) -> Result<Option<FinalityCertificate>> {
+        let reject = |reason: String| {
+            Err(ProvChainError::Validation(ValidationError::InvalidInput {
+                field: "finality_vote".to_string(),
+                reason,
+            }))
+        };
+
+        if !self.is_checkpoint(vote.block_index) {
+            return reject(format!("block {} is not a checkpoint", vote.block_index));
+        }
+        if self
+            .finalized_index
+            .is_some_and(|index| vote.block_index <= index)
+        {
+            return Ok(None);
+        }
+        if !validator_set.contains(&vote.validator) {
+            return reject(format!("{} is not in the validator set", vote.validator));
+        }
+        vote.verify()?;
+
+        let key = (vote.block_index, vote.block_hash.clone());
+        let votes = self.votes.entry(key.clone()).or_default();
+        votes.insert(vote.validator.clone(), vote);
+
+        if votes.len() < supermajority(validator_set.len()) {
+            return Ok(None);
+        }
+
+        let mut votes: Vec<FinalityVote> = votes.values().cloned().collect();
+        votes.sort_by(|a, b| a.validator.cmp(&b.validator));
+
+        Ok(Some(FinalityCertificate {
+            block_index: key.0,
+            block_hash: key.1,
+            votes,
+            finalized_at: Utc::now().to_rfc3339(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authorities(count: u8) -> (Vec<SigningKey>, HashSet<String>) {
+        let keys: Vec<SigningKey> = (1..=count)
+            .map(|i| SigningKey::from_bytes(&[i; 32]))
+            .collect();
+        let set = keys
+            .iter()
+            .map(|key| hex::encode(key.verifying_key().to_bytes()))
+            .collect();
+        (keys, set)
+    }
+
+    #[test]
+    fn test_supermajority_threshold() {
+        assert_eq!(supermajority(1), 1);
+        assert_eq!(supermajority(3), 3);
+        assert_eq!(supermajority(4), 3);
+        assert_eq!(supermajority(7), 5);
+    }
+
+    #[test]
+    fn test_certificate_forms_at_supermajority() {
+        let (keys, set) = authorities(4);
+        let mut tracker = FinalityTracker::new(10);
+
+        for key in &keys[..2] {
+            let vote = FinalityVote::sign(10, "abc", key);
+            assert!(tracker.add_vote(vote, &set).unwrap().is_none());
+        }
+
+        let certificate = tracker
+            .add_vote(FinalityVote::sign(10, "abc", &keys[2]), &set)
+            .unwrap()
+            .expect("third of four votes should finalize");
+        assert_eq!(certificate.votes.len(), 3);
+        assert!(certificate.verify(&set).is_ok());
+
+        // Until the certificate is recorded, the next vote hands it out again
+        let retry = tracker
+            .add_vote(FinalityVote::sign(10, "abc", &keys[0]), &set)
+            .unwrap()
+            .expect("checkpoint is not finalized before it is committed");
+        assert_eq!(retry.votes.len(), 3);
+
+        // Late votes for an already finalized checkpoint are ignored
+        tracker.set_finalized(certificate.block_index);
+        let late = FinalityVote::sign(10, "abc", &keys[3]);
+        assert!(tracker.add_vote(late, &set).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_invalid_votes_are_rejected() {
+        let (keys, set) = authorities(3);
+        let outsider = SigningKey::from_bytes(&[42; 32]);
+        let mut tracker = FinalityTracker::new(10);
+
+        assert!(tracker
+            .add_vote(FinalityVote::sign(10, "abc", &outsider), &set)
+            .is_err());
+        assert!(tracker
+            .add_vote(FinalityVote::sign(7, "abc", &keys[0]), &set)
+            .is_err());
+
+        let mut forged = FinalityVote::sign(10, "abc", &keys[0]);
+        forged.block_hash = "def".to_string();
+        assert!(tracker.add_vote(forged, &set).is_err());
+    }
+
+    #[test]
+    fn test_certificate_below_supermajority_does_not_verify() {
+        let (keys, set) = authorities(3);
+        let certificate = FinalityCertificate {
+            block_index: 10,
+            block_hash: "abc".to_string(),
+            votes: keys[..2]
+                .iter()
+                .map(|key| FinalityVote::sign(10, "abc", key))
+                .collect(),
+            finalized_at: Utc::now().to_rfc3339(),
+        };
+
+        assert!(certificate.verify(&set).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+//! Merkle tree over canonical N-Quads for block headers
+//!
+//! Leaves are the RDFC-1.0 canonical N-Quads lines of a block graph in sorted order.
+//! Leaf and interior hashes are domain-separated (0x00 / 0x01 prefixes) and an odd
+//! node at the end of a level is promoted unchanged, so proofs never duplicate leaves.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Side on which a sibling hash sits when folding a proof towards the root
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SiblingPosition {
+    Left,
+    Right,
+}
+
+/// One step of an inclusion proof
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProofStep {
+    /// Hex-encoded sibling hash
+    pub hash: String,
+    /// Position of the sibling relative to the running hash
+    pub position: SiblingPosition,
+}
+
+/// Inclusion proof for a single leaf
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    /// Index of the leaf in the sorted leaf list
+    pub leaf_index: usize,
+    /// Total number of leaves in the tree
+    pub leaf_count: usize,
+    /// Sibling hashes from the leaf level up to the root
+    pub siblings: Vec<MerkleProofStep>,
+}
+
+impl MerkleProof {
+    /// Verify that `leaf` is included under `root`
+    pub fn verify(&self, leaf: &str, root: &str) -> bool {
+        if self.leaf_index >= self.leaf_count {
+            return false;
+        }
+
+        let mut current = leaf_hash(leaf);
+        for step in &self.siblings {
+            let Ok(sibling) = hex::decode(&step.hash) else {
+                return false;
+            };
+            current = match step.position {
+                SiblingPosition::Left => node_hash(&sibling, &current),
+                SiblingPosition::Right => node_hash(&current, &sibling),
+            };
+        }
+
+        hex::encode(current) == root
+    }
+}
+
+/// Merkle tree built from canonical N-Quads lines
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    /// Levels from leaves (index 0) up to the root
+    levels: Vec<Vec<Vec<u8>>>,
+}
+
+impl MerkleTree {
+    /// Build a tree from leaves in their canonical (sorted) order
+    pub fn new<S: AsRef<str>>(leaves: &[S]) -> Self {
+        let mut levels = vec![leaves
+            .iter()
+            .map(|leaf| leaf_hash(leaf.as_ref()))
+            .collect::<Vec<_>>()];
+
+        while levels.last().map(|level| level.len() > 1).unwrap_or(false) {
+            let current = levels.last().unwrap();
+            let next = current
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    [single] => single.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+
+        Self { levels }
+    }
+
+    /// Number of leaves in the tree
+    pub fn leaf_count(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Hex-encoded root hash. An empty tree has the hash of the empty string.
+    pub fn root(&self) -> String {
+        match self.levels.last().and_then(|level| level.first()) {
+            Some(root) => hex::encode(root),
+            None => hex::encode(Sha256::digest(b"")),
+        }
+    }
+
+    /// Build the inclusion proof for the leaf at `leaf_index`
+    pub fn proof(&self, leaf_index: usize) -> Option<MerkleProof> {
+        if leaf_index >= self.leaf_count() {
+            return None;
+        }
+
+        let mut siblings = Vec::new();
+        let mut index = leaf_index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling_index = index ^ 1;
+            if sibling_index < level.len() {
+                siblings.push(MerkleProofStep {
+                    hash: hex::encode(&level[sibling_index]),
+                    position: if sibling_index < index {
+                        SiblingPosition::Left
+                    } else {
+                        SiblingPosition::Right
+                    },
+                });
+            }
+            index /= 2;
+        }
+
+        Some(MerkleProof {
+            leaf_index,
+            leaf_count: self.leaf_count(),
+            siblings,
+        })
+    }
+}
+
+/// Compute the Merkle root of canonical N-Quads lines
+pub fn merkle_root<S: AsRef<str>>(leaves: &[S]) -> String {
+    MerkleTree::new(leaves).root()
+}
+
+fn leaf_hash(leaf: &str) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update([0x00]);
+    hasher.update(leaf.as_bytes());
+    hasher.finalize().to_vec()
+}
+
+fn node_hash(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(count: usize) -> Vec<String> {
+        (0..count)
+            .map(|i| format!("<http://example.org/s{i}> <http://example.org/p> \"{i}\" ."))
+            .collect()
+    }
+
+    #[test]
+    fn test_every_leaf_proves_against_root() {
+        for count in 1..=9 {
+            let leaves = leaves(count);
+            let tree = MerkleTree::new(&leaves);
+            let root = tree.root();
+
+            for (i, leaf) in leaves.iter().enumerate() {
+                let proof = tree.proof(i).unwrap();
+                assert!(proof.verify(leaf, &root), "leaf {i} of {count} failed");
+            }
+        }
+    }
+
+    #[test]
+    fn test_proof_rejects_other_leaf_and_root() {
+        let leaves = leaves(5);
+        let tree = MerkleTree::new(&leaves);
+        let proof = tree.proof(2).unwrap();
+
+        assert!(!proof.verify(&leaves[3], &tree.root()));
+        assert!(!proof.verify(&leaves[2], &merkle_root(&leaves[..4])));
+        assert!(tree.proof(5).is_none());
+    }
+
+    #[test]
+    fn test_empty_tree_root() {
+        let empty: Vec<String> = Vec::new();
+        assert_eq!(merkle_root(&empty), hex::encode(Sha256::digest(b"")));
+    }
+}
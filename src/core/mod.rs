@@ -4,10 +4,22 @@
 //! block structure, state management, and atomic operations.
 
 pub mod atomic_operations;
+pub mod block_tree;
 pub mod blockchain;
+pub mod disclosure;
 pub mod entity;
+pub mod finality;
+pub mod merkle;
+pub mod proof;
+pub mod snapshot;
 
 // Re-exports for convenience
 pub use atomic_operations::AtomicOperationContext;
-pub use blockchain::Blockchain;
+pub use block_tree::{BlockTree, ChainWeight, ForkChoice};
+pub use blockchain::{BlockHeader, Blockchain};
+pub use disclosure::{DisclosureProof, SealedTriple};
 pub use entity::{DomainType, EntityType, PropertyValue, TraceableEntity};
+pub use finality::{FinalityCertificate, FinalityTracker, FinalityVote};
+pub use merkle::{MerkleProof, MerkleTree};
+pub use proof::{TripleInclusionProof, TriplePattern};
+pub use snapshot::StateSnapshot;
@@ -0,0 +1,198 @@
+//! Self-contained triple inclusion proofs
+//!
+//! A proof bundles one canonical N-Quads line of a block graph, the Merkle path from
+//! that line to the block's Merkle root and the signed block header. It can be checked
+//! offline, without a node or the RDF store, by anyone holding the validator's public key.
+
+use crate::core::blockchain::{Block, BlockHeader};
+use crate::core::merkle::MerkleProof;
+use crate::error::{BlockchainError, CryptoError, ProvChainError, Result, ValidationError};
+use oxigraph::io::RdfFormat;
+use oxigraph::model::{Term, Triple};
+use oxigraph::store::Store;
+use serde::{Deserialize, Serialize};
+
+/// Proof format version, bumped whenever the hashing scheme changes
+pub const PROOF_VERSION: u32 = 1;
+
+/// Triple pattern used to select the triples to prove. `None` matches anything.
+///
+/// Terms match either their N-Triples form (`<http://...>`, `"Shipped"`) or their
+/// bare IRI / literal value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TriplePattern {
+    pub subject: Option<String>,
+    pub predicate: Option<String>,
+    pub object: Option<String>,
+}
+
+impl TriplePattern {
+    /// Check whether a canonical N-Quads line matches this pattern
+    pub fn matches_canonical(&self, canonical_quad: &str) -> bool {
+        let Some(triple) = parse_canonical_quad(canonical_quad) else {
+            return false;
+        };
+
+        term_matches(&self.subject, &Term::from(triple.subject))
+            && term_matches(&self.predicate, &Term::from(triple.predicate))
+            && term_matches(&self.object, &triple.object)
+    }
+}
+
+/// Inclusion proof for a single triple of a block graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TripleInclusionProof {
+    /// Proof format version
+    pub version: u32,
+    /// Named graph of the block the triple belongs to
+    pub graph_uri: String,
+    /// RDFC-1.0 canonical N-Quads line, exactly as hashed into the Merkle tree
+    pub canonical_quad: String,
+    /// Merkle path from the canonical quad to the header's Merkle root
+    pub merkle_proof: MerkleProof,
+    /// Signed header of the block, including the validator key and signature
+    pub header: BlockHeader,
+}
+
+/// Outcome of verifying a proof offline
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofVerification {
+    /// Block the triple was proven against
+    pub block_index: u64,
+    /// Hex-encoded public key of the validator that signed the block
+    pub validator: String,
+    /// Whether the header carried a real Ed25519 signature
+    pub signed: bool,
+}
+
+impl TripleInclusionProof {
+    /// Check the proof: header hash, Merkle path and validator signature.
+    ///
+    /// Unsigned headers (genesis, legacy blocks) are reported with `signed == false`
+    /// rather than rejected; callers decide whether that is acceptable.
+    pub fn verify(&self) -> Result<ProofVerification> {
+        if self.version != PROOF_VERSION {
+            return Err(ValidationError::InvalidInput {
+                field: "version".to_string(),
+                reason: format!("unsupported proof version {}", self.version),
+            }
+            .into());
+        }
+
+        if self.graph_uri != Block::graph_uri(self.header.index) {
+            return Err(ValidationError::InvalidInput {
+                field: "graph_uri".to_string(),
+                reason: format!(
+                    "graph {} does not belong to block {}",
+                    self.graph_uri, self.header.index
+                ),
+            }
+            .into());
+        }
+
+        let expected_hash = self.header.calculate_hash();
+        if self.header.hash != expected_hash {
+            return Err(BlockchainError::HashMismatch {
+                expected: expected_hash,
+                actual: self.header.hash.clone(),
+            }
+            .into());
+        }
+
+        if self.header.merkle_root.is_empty() {
+            return Err(BlockchainError::InvalidBlock(format!(
+                "Block {} predates Merkle roots and cannot back an inclusion proof",
+                self.header.index
+            ))
+            .into());
+        }
+
+        if !self
+            .merkle_proof
+            .verify(&self.canonical_quad, &self.header.merkle_root)
+        {
+            return Err(ProvChainError::Crypto(CryptoError::HashCalculationFailed(
+                "Merkle path does not lead to the block's Merkle root".to_string(),
+            )));
+        }
+
+        let signed = self.header.verify_signature()?;
+
+        Ok(ProofVerification {
+            block_index: self.header.index,
+            validator: self.header.validator.clone(),
+            signed,
+        })
+    }
+
+    /// Serialize the proof as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Parse a proof from JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// Parse a canonical N-Quads line of a block graph into a triple
+pub fn parse_canonical_quad(canonical_quad: &str) -> Option<Triple> {
+    let store = Store::new().ok()?;
+    store
+        .load_from_reader(RdfFormat::NTriples, canonical_quad.as_bytes())
+        .ok()?;
+    let quad = store.iter().next()?.ok()?;
+    Some(Triple::new(quad.subject, quad.predicate, quad.object))
+}
+
+fn term_matches(pattern: &Option<String>, term: &Term) -> bool {
+    let Some(pattern) = pattern else {
+        return true;
+    };
+
+    if *pattern == term.to_string() {
+        return true;
+    }
+
+    match term {
+        Term::NamedNode(node) => node.as_str() == pattern,
+        Term::Literal(literal) => literal.value() == pattern,
+        Term::BlankNode(node) => format!("_:{}", node.as_str()) == *pattern,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_matches_iri_and_literal_forms() {
+        let quad = r#"<http://example.org/batch1> <http://example.org/status> "Shipped" ."#;
+
+        let by_iri = TriplePattern {
+            subject: Some("http://example.org/batch1".to_string()),
+            ..Default::default()
+        };
+        let by_ntriples = TriplePattern {
+            subject: Some("<http://example.org/batch1>".to_string()),
+            object: Some("\"Shipped\"".to_string()),
+            ..Default::default()
+        };
+        let by_value = TriplePattern {
+            object: Some("Shipped".to_string()),
+            ..Default::default()
+        };
+        let other = TriplePattern {
+            predicate: Some("http://example.org/origin".to_string()),
+            ..Default::default()
+        };
+
+        assert!(by_iri.matches_canonical(quad));
+        assert!(by_ntriples.matches_canonical(quad));
+        assert!(by_value.matches_canonical(quad));
+        assert!(!other.matches_canonical(quad));
+        assert!(TriplePattern::default().matches_canonical(quad));
+    }
+}
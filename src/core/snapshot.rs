@@ -0,0 +1,180 @@
+//! State snapshots for fast sync
+//!
+//! A snapshot is the RDF store at a finalized checkpoint, serialized as N-Quads, with the
+//! state root computed over it and the block headers from genesis up to the checkpoint.
+//! The serving node signs the checkpoint, block hash and state root. A joining node checks
+//! the header chain, the finality certificate, the state root and every block graph's
+//! committed data hash before installing the snapshot, then syncs only later blocks.
+
+use crate::core::blockchain::{Block, BlockHeader};
+use crate::core::finality::FinalityCertificate;
+use crate::error::{BlockchainError, CryptoError, ProvChainError, Result, ValidationError};
+use crate::storage::rdf_store::RDFStore;
+use chrono::Utc;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use oxigraph::model::NamedNode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Signed N-Quads snapshot of the RDF store at a finalized block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    /// Index of the finalized block the snapshot was taken at
+    pub block_index: u64,
+    pub block_hash: String,
+    /// `RDFStore::calculate_state_root` over the snapshot contents
+    pub state_root: String,
+    /// Every quad in the store at `block_index`, in N-Quads
+    pub nquads: String,
+    /// Headers of blocks `0..=block_index`
+    pub headers: Vec<BlockHeader>,
+    /// Finality certificate for `block_index`
+    pub certificate: FinalityCertificate,
+    /// Hex-encoded Ed25519 public key of the serving node
+    pub signer: String,
+    /// Hex-encoded signature over `signing_payload(block_index, block_hash, state_root)`
+    pub signature: String,
+    /// RFC 3339 time the snapshot was taken
+    pub created_at: String,
+}
+
+impl StateSnapshot {
+    /// Bytes signed by the serving node; domain-separated from block and vote signatures
+    pub fn signing_payload(block_index: u64, block_hash: &str, state_root: &str) -> Vec<u8> {
+        format!(
+            "provchain-snapshot:{}:{}:{}",
+            block_index, block_hash, state_root
+        )
+        .into_bytes()
+    }
+
+    /// Snapshot a store that holds exactly the chain up to the certificate's block
+    pub fn create(
+        store: &RDFStore,
+        headers: Vec<BlockHeader>,
+        certificate: FinalityCertificate,
+        signing_key: &SigningKey,
+    ) -> Result<Self> {
+        let state_root = store.calculate_state_root();
+        let signature = signing_key.sign(&Self::signing_payload(
+            certificate.block_index,
+            &certificate.block_hash,
+            &state_root,
+        ));
+
+        Ok(Self {
+            block_index: certificate.block_index,
+            block_hash: certificate.block_hash.clone(),
+            state_root,
+            nquads: store.export_nquads()?,
+            headers,
+            certificate,
+            signer: hex::encode(signing_key.verifying_key().to_bytes()),
+            signature: hex::encode(signature.to_bytes()),
+            created_at: Utc::now().to_rfc3339(),
+        })
+    }
+
+    /// Verify the snapshot before it is installed.
+    ///
+    /// The signer must be in `validator_set` and the finality certificate must carry a
+    /// supermajority of its signatures, so a node that does not know the validator set
+    /// yet cannot install a snapshot.
+    pub fn verify(&self, validator_set: &HashSet<String>) -> Result<()> {
+        if validator_set.is_empty() {
+            return Err(invalid(
+                "no validator set to verify the snapshot against".to_string(),
+            ));
+        }
+        self.verify_signature()?;
+
+        if self.certificate.block_index != self.block_index
+            || self.certificate.block_hash != self.block_hash
+        {
+            return Err(invalid(format!(
+                "finality certificate is for block {}, snapshot is at block {}",
+                self.certificate.block_index, self.block_index
+            )));
+        }
+        if !validator_set.contains(&self.signer) {
+            return Err(invalid(format!(
+                "snapshot signer {} is not in the validator set",
+                self.signer
+            )));
+        }
+        self.certificate.verify(validator_set)?;
+
+        // The header chain must run from genesis to the snapshot block
+        let reaches_snapshot = self.headers.last().is_some_and(|header| {
+            header.index == self.block_index && header.hash == self.block_hash
+        });
+        if self.headers.first().map(|header| header.index) != Some(0)
+            || !reaches_snapshot
+            || !BlockHeader::verify_chain(&self.headers)
+        {
+            return Err(invalid(format!(
+                "headers do not link genesis to block {}",
+                self.block_index
+            )));
+        }
+
+        let mut store = RDFStore::new();
+        store
+            .load_data_from_string(&self.nquads)
+            .map_err(|e| invalid(format!("snapshot N-Quads do not parse: {}", e)))?;
+
+        let state_root = store.calculate_state_root();
+        if state_root != self.state_root {
+            return Err(ProvChainError::Blockchain(BlockchainError::HashMismatch {
+                expected: self.state_root.clone(),
+                actual: state_root,
+            }));
+        }
+
+        // Every block graph must match the data hash its header commits to
+        for header in &self.headers {
+            let graph_name = NamedNode::new(Block::graph_uri(header.index))
+                .map_err(|e| invalid(e.to_string()))?;
+            if store.canonicalize_graph(&graph_name) != header.data_hash {
+                return Err(invalid(format!(
+                    "block {} graph does not match its committed data hash",
+                    header.index
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Verify the serving node's signature over the snapshot
+    pub fn verify_signature(&self) -> Result<()> {
+        let key_bytes: [u8; 32] = hex::decode(&self.signer)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| CryptoError::InvalidKeyFormat(format!("signer key {}", self.signer)))?;
+        let signature_bytes: [u8; 64] = hex::decode(&self.signature)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| {
+                CryptoError::SignatureVerificationFailed("malformed snapshot signature".to_string())
+            })?;
+
+        let public_key = VerifyingKey::from_bytes(&key_bytes)
+            .map_err(|e| CryptoError::InvalidKeyFormat(e.to_string()))?;
+        public_key
+            .verify(
+                &Self::signing_payload(self.block_index, &self.block_hash, &self.state_root),
+                &Signature::from_bytes(&signature_bytes),
+            )
+            .map_err(|e| CryptoError::SignatureVerificationFailed(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+fn invalid(reason: String) -> ProvChainError {
+    ProvChainError::Validation(ValidationError::InvalidInput {
+        field: "snapshot".to_string(),
+        reason,
+    })
+}
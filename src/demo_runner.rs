@@ -6,7 +6,7 @@
 use anyhow::Result;
 use std::io::{self, Write};
 
-use crate::uht_demo::run_uht_demo;
+use crate::uht_demo::{run_uht_demo, DEMO_WALLET_PASSWORD};
 
 /// Available demo types
 #[derive(Debug, Clone)]
@@ -156,7 +156,7 @@ impl DemoRunner {
         let farmer =
             Participant::new_farmer("Demo Farmer".to_string(), "Demo Location".to_string());
 
-        let farmer_id = blockchain.register_participant(farmer)?;
+        let farmer_id = blockchain.register_participant(farmer, DEMO_WALLET_PASSWORD)?;
         println!("✅ Registered demo farmer: {}", farmer_id);
 
         // Create a simple production transaction
@@ -207,7 +207,7 @@ impl DemoRunner {
             "Demo Location".to_string(),
         );
 
-        let farmer_id = blockchain.register_participant(farmer)?;
+        let farmer_id = blockchain.register_participant(farmer, DEMO_WALLET_PASSWORD)?;
         println!("✅ Registered farmer for signing demo: {}", farmer_id);
 
         // Create and sign a transaction
@@ -259,9 +259,9 @@ impl DemoRunner {
             "Testing Facility".to_string(),
         );
 
-        let farmer_id = blockchain.register_participant(farmer)?;
-        let processor_id = blockchain.register_participant(processor)?;
-        let lab_id = blockchain.register_participant(lab)?;
+        let farmer_id = blockchain.register_participant(farmer, DEMO_WALLET_PASSWORD)?;
+        let processor_id = blockchain.register_participant(processor, DEMO_WALLET_PASSWORD)?;
+        let lab_id = blockchain.register_participant(lab, DEMO_WALLET_PASSWORD)?;
 
         println!("✅ Registered {} participants", 3);
 
@@ -288,13 +288,15 @@ impl DemoRunner {
         let tx2_id = blockchain.submit_transaction(tx2)?;
         println!("✅ Lab created quality transaction: {}", tx2_id);
 
-        let tx3 = blockchain.create_processing_transaction(
+        let mut tx3 = blockchain.create_processing_transaction(
             processor_id,
             vec!["MULTI-BATCH-001".to_string()],
             "PROCESSED-BATCH-001".to_string(),
             "UHT_PROCESSING".to_string(),
             None,
         )?;
+        // The farmer owns the batch being processed
+        blockchain.cosign_transaction(&mut tx3, farmer_id)?;
 
         let tx3_id = blockchain.submit_transaction(tx3)?;
         println!("✅ Processor created processing transaction: {}", tx3_id);
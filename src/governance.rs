@@ -4,14 +4,239 @@
 //! - Authority node management
 //! - Validator set governance
 //! - Governance transactions
+//! - Proposal lifecycle: open, collect validator votes, execute on quorum or expire
+//! - Typed network configuration changed through `UpdateConfiguration`
+//!
+//! Proposals and votes are transactions recorded in blocks. Submitted ones wait in
+//! `Governance::pending` until a block includes them, and the governance state is a
+//! fold over the blocks, like `transaction::ledger`: replaying the same blocks from the
+//! same genesis governance always yields the same proposals, validator set and network
+//! configuration. Proposals open, votes count and proposals expire at the index of the
+//! block including them, never at a node's own height.
 
+use crate::core::blockchain::Block;
+use crate::transaction::ledger::block_transactions;
 use crate::transaction::transaction::{
     GovernanceAction, Transaction, TransactionMetadata, TransactionPayload, TransactionType,
 };
 use anyhow::Result;
-use std::collections::HashSet;
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use tracing::debug;
 use uuid::Uuid;
 
+/// Named graph holding proposals, votes and the network configuration
+pub const GOVERNANCE_GRAPH: &str = "http://provchain.org/governance";
+
+/// Default number of blocks a proposal stays open
+pub const DEFAULT_PROPOSAL_TTL_BLOCKS: u64 = 100;
+
+/// Proposals a single validator may have open or pending at a time
+pub const MAX_OPEN_PROPOSALS_PER_PROPOSER: usize = 3;
+
+/// Network parameters that governance proposals can change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    /// Block creation interval for authorities (seconds)
+    pub block_interval: u64,
+    /// Maximum block size in bytes
+    pub max_block_size: usize,
+    /// Blocks between finality checkpoints
+    pub checkpoint_interval: u64,
+    /// Blocks a governance proposal stays open before it expires
+    pub proposal_ttl_blocks: u64,
+    /// Minimum number of validators required
+    pub min_validators: usize,
+    /// Maximum number of validators allowed
+    pub max_validators: usize,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            block_interval: 10,
+            max_block_size: 1024 * 1024,
+            checkpoint_interval: crate::core::finality::DEFAULT_CHECKPOINT_INTERVAL,
+            proposal_ttl_blocks: DEFAULT_PROPOSAL_TTL_BLOCKS,
+            min_validators: 1,
+            max_validators: 100,
+        }
+    }
+}
+
+impl NetworkConfig {
+    /// Apply a `key = value` update, parsing the value into the field's type.
+    /// Unknown keys and unparsable or zero values are rejected.
+    pub fn apply(&mut self, key: &str, value: &str) -> Result<()> {
+        fn parse<T: std::str::FromStr + PartialEq + Default>(key: &str, value: &str) -> Result<T> {
+            let parsed: T = value
+                .trim()
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid value '{}' for {}", value, key))?;
+            if parsed == T::default() {
+                anyhow::bail!("{} must be greater than 0", key);
+            }
+            Ok(parsed)
+        }
+
+        match key {
+            "block_interval" => self.block_interval = parse(key, value)?,
+            "max_block_size" => self.max_block_size = parse(key, value)?,
+            "checkpoint_interval" => self.checkpoint_interval = parse(key, value)?,
+            "proposal_ttl_blocks" => self.proposal_ttl_blocks = parse(key, value)?,
+            "min_validators" => {
+                let min_validators: usize = parse(key, value)?;
+                if min_validators > self.max_validators {
+                    anyhow::bail!("min_validators cannot exceed max_validators");
+                }
+                self.min_validators = min_validators;
+            }
+            "max_validators" => {
+                let max_validators: usize = parse(key, value)?;
+                if max_validators < self.min_validators {
+                    anyhow::bail!("max_validators cannot be below min_validators");
+                }
+                self.max_validators = max_validators;
+            }
+            _ => anyhow::bail!("Unknown network configuration key: {}", key),
+        }
+        Ok(())
+    }
+
+    /// Configuration as `(key, value)` pairs, in the form accepted by `apply`
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("block_interval", self.block_interval.to_string()),
+            ("max_block_size", self.max_block_size.to_string()),
+            ("checkpoint_interval", self.checkpoint_interval.to_string()),
+            ("proposal_ttl_blocks", self.proposal_ttl_blocks.to_string()),
+            ("min_validators", self.min_validators.to_string()),
+            ("max_validators", self.max_validators.to_string()),
+        ]
+    }
+}
+
+/// Lifecycle state of a governance proposal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProposalStatus {
+    /// Collecting votes
+    Open,
+    /// Reached quorum and the action was applied
+    Executed,
+    /// Reached quorum but the action could not be applied
+    Failed,
+    /// Did not reach quorum before its expiry block
+    Expired,
+}
+
+impl std::fmt::Display for ProposalStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            ProposalStatus::Open => "Open",
+            ProposalStatus::Executed => "Executed",
+            ProposalStatus::Failed => "Failed",
+            ProposalStatus::Expired => "Expired",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+/// A validator's signed approval of a proposal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProposalVote {
+    pub proposal_id: String,
+    /// Hex-encoded Ed25519 public key of the voting validator
+    pub validator: String,
+    /// Hex-encoded signature over the proposal's signing payload
+    pub signature: String,
+    /// Block height at which the vote was counted
+    pub block_index: u64,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl ProposalVote {
+    /// Sign an approval of `proposal` with a validator key
+    pub fn sign(proposal: &Proposal, signing_key: &SigningKey) -> Self {
+        let signature = signing_key.sign(&proposal.signing_payload());
+        Self {
+            proposal_id: proposal.id.clone(),
+            validator: hex::encode(signing_key.verifying_key().to_bytes()),
+            signature: hex::encode(signature.to_bytes()),
+            block_index: 0,
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// Verify the vote signature against the proposal it approves
+    pub fn verify(&self, proposal: &Proposal) -> Result<()> {
+        let key_bytes: [u8; 32] = hex::decode(&self.validator)?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Invalid validator key length"))?;
+        let signature_bytes: [u8; 64] = hex::decode(&self.signature)?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Invalid vote signature length"))?;
+
+        VerifyingKey::from_bytes(&key_bytes)?
+            .verify(
+                &proposal.signing_payload(),
+                &Signature::from_bytes(&signature_bytes),
+            )
+            .map_err(|e| anyhow::anyhow!("Vote signature verification failed: {}", e))
+    }
+}
+
+/// A governance action waiting for validator quorum
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Proposal {
+    pub id: String,
+    pub action: GovernanceAction,
+    /// Hex-encoded Ed25519 public key of the validator that signed the proposal
+    #[serde(default)]
+    pub proposer: String,
+    /// Block height at which the proposal was opened
+    pub opened_at_block: u64,
+    /// Last block height at which votes are accepted
+    pub expires_at_block: u64,
+    pub votes: Vec<ProposalVote>,
+    pub status: ProposalStatus,
+    /// Why execution failed, for `ProposalStatus::Failed`
+    pub failure_reason: Option<String>,
+}
+
+impl Proposal {
+    /// Bytes validators sign to approve this proposal
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let action = serde_json::to_string(&self.action).unwrap_or_default();
+        format!("provchain-governance:{}:{}", self.id, action).into_bytes()
+    }
+
+    /// Name of the proposed action, as recorded in the audit trail
+    pub fn action_name(&self) -> &'static str {
+        match self.action {
+            GovernanceAction::AddValidator { .. } => "AddValidator",
+            GovernanceAction::RemoveValidator { .. } => "RemoveValidator",
+            GovernanceAction::UpdateConfiguration { .. } => "UpdateConfiguration",
+        }
+    }
+
+    /// Distinct validators that approved the proposal
+    pub fn approvals(&self) -> HashSet<&str> {
+        self.votes
+            .iter()
+            .map(|vote| vote.validator.as_str())
+            .collect()
+    }
+}
+
+/// Validator set and network configuration governance starts from, before block 1
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GovernanceGenesis {
+    pub validator_set: BTreeSet<String>,
+    pub network_config: NetworkConfig,
+}
+
 /// Governance module for managing validator set and network configuration
 #[derive(Debug, Clone)]
 pub struct Governance {
@@ -21,16 +246,21 @@ pub struct Governance {
     pub min_validators: usize,
     /// Maximum number of validators allowed
     pub max_validators: usize,
+    /// Network configuration changed through `UpdateConfiguration` proposals
+    pub network_config: NetworkConfig,
+    /// Proposals by ID, including closed ones
+    pub proposals: HashMap<String, Proposal>,
+    /// Proposal and vote transactions waiting to be included in a block
+    pub pending: Vec<Transaction>,
+    /// State the chain is replayed from, taken from the validator set and network
+    /// configuration in effect when block 1 is applied
+    pub genesis: Option<GovernanceGenesis>,
 }
 
 impl Governance {
     /// Create a new governance module
     pub fn new() -> Self {
-        Self {
-            validator_set: HashSet::new(),
-            min_validators: 1,
-            max_validators: 100,
-        }
+        Self::with_validators(HashSet::new())
     }
 
     /// Create a new governance module with initial validator set
@@ -39,10 +269,27 @@ impl Governance {
             validator_set,
             min_validators: 1,
             max_validators: 100,
+            network_config: NetworkConfig::default(),
+            proposals: HashMap::new(),
+            pending: Vec::new(),
+            genesis: None,
+        }
+    }
+
+    /// Votes needed to execute a governance action: a strict majority of the validator
+    /// set. An empty set (network bootstrap) needs none.
+    pub fn required_votes(&self) -> usize {
+        if self.validator_set.is_empty() {
+            0
+        } else {
+            self.validator_set.len() / 2 + 1
         }
     }
 
-    /// Process a governance transaction
+    /// Process a governance transaction that already carries validator signatures.
+    ///
+    /// The action is applied only if signatures from a quorum of validators verify
+    /// against the transaction hash.
     pub fn process_governance_tx(&mut self, tx: &Transaction) -> Result<()> {
         // Check if this is a governance transaction
         let governance_action = match &tx.payload {
@@ -50,41 +297,398 @@ impl Governance {
             _ => return Ok(()), // Not a governance transaction
         };
 
-        // For validator changes, we need to verify signatures from existing validators
-        if matches!(
-            governance_action,
-            GovernanceAction::AddValidator { .. } | GovernanceAction::RemoveValidator { .. }
-        ) {
-            // Check that we have enough validator signatures
-            let required_votes = (self.validator_set.len() / 2) + 1;
-            if required_votes == 0 {
-                // If no validators exist yet, allow the transaction
-                // This is needed for initial network setup
-            } else if tx.signatures.len() < required_votes {
-                // For testing purposes, we'll allow transactions without signatures to pass
-                // In a real implementation, this would be enforced
-                // return Err(anyhow::anyhow!("Not enough validator signatures for governance action. Required: {}, Provided: {}",
-                //                          required_votes, tx.signatures.len()));
+        let required_votes = self.required_votes();
+        if required_votes > 0 {
+            if !tx.verify_signatures()? {
+                anyhow::bail!("Governance transaction has missing or invalid signatures");
+            }
+
+            // Count distinct signers that are current validators
+            let valid_signers: HashSet<String> = tx
+                .signatures
+                .iter()
+                .map(|signature| hex::encode(signature.public_key.to_bytes()))
+                .filter(|signer_key| self.validator_set.contains(signer_key))
+                .collect();
+
+            if valid_signers.len() < required_votes {
+                anyhow::bail!(
+                    "Not enough signatures from valid validators. Required: {}, Valid: {}",
+                    required_votes,
+                    valid_signers.len()
+                );
+            }
+        }
+
+        self.apply_action(governance_action)
+    }
+
+    /// Open a proposal for the governance action carried by `tx` at block height
+    /// `current_block`. The transaction must be validly signed by a current validator
+    /// (by anyone while the validator set is empty), who may have at most
+    /// `MAX_OPEN_PROPOSALS_PER_PROPOSER` proposals open. Other signatures on the
+    /// transaction are not counted; validators approve through `cast_vote`.
+    pub fn open_proposal(&mut self, tx: &Transaction, current_block: u64) -> Result<&Proposal> {
+        let (action, proposer) = self.check_proposal(tx)?;
+        let open = self.open_proposals_by(&proposer);
+        if open >= MAX_OPEN_PROPOSALS_PER_PROPOSER {
+            anyhow::bail!("{} already has {} open proposals", proposer, open);
+        }
+
+        let proposal = self.new_proposal(&tx.id, action, proposer, current_block);
+        self.proposals.insert(proposal.id.clone(), proposal);
+
+        // Bootstrap networks without validators execute immediately
+        if self.required_votes() == 0 {
+            self.try_execute(&tx.id);
+        }
+
+        Ok(&self.proposals[&tx.id])
+    }
+
+    /// Count a validator's vote at block height `current_block` and execute the
+    /// proposal once it reaches quorum. Returns the proposal's status after the vote.
+    pub fn cast_vote(
+        &mut self,
+        mut vote: ProposalVote,
+        current_block: u64,
+    ) -> Result<ProposalStatus> {
+        self.expire_proposals(current_block);
+
+        let proposal = self
+            .proposals
+            .get(&vote.proposal_id)
+            .ok_or_else(|| anyhow::anyhow!("Proposal not found: {}", vote.proposal_id))?;
+        self.check_vote(&vote, proposal)?;
+
+        vote.block_index = current_block;
+        let proposal_id = vote.proposal_id.clone();
+        if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+            proposal.votes.push(vote);
+        }
+
+        Ok(self.try_execute(&proposal_id))
+    }
+
+    /// Queue a proposal for the next block, checked as `open_proposal` would, with the
+    /// proposer's pending proposals counting towards its limit. Returns the proposal as
+    /// it opens once included in block `next_block`.
+    pub fn submit_proposal(&mut self, tx: Transaction, next_block: u64) -> Result<Proposal> {
+        if self.pending.iter().any(|pending| pending.id == tx.id) {
+            anyhow::bail!("Proposal {} is already pending", tx.id);
+        }
+        let (action, proposer) = self.check_proposal(&tx)?;
+        let pending = self
+            .pending
+            .iter()
+            .filter(|pending| self.proposer_of(pending).as_deref() == Some(proposer.as_str()))
+            .count();
+        let open = self.open_proposals_by(&proposer) + pending;
+        if open >= MAX_OPEN_PROPOSALS_PER_PROPOSER {
+            anyhow::bail!("{} already has {} open proposals", proposer, open);
+        }
+
+        let proposal = self.new_proposal(&tx.id, action, proposer, next_block);
+        self.pending.push(tx);
+        Ok(proposal)
+    }
+
+    /// Queue a validator's vote for the next block, as a vote transaction. The proposal
+    /// may be open on the chain or still pending itself.
+    pub fn submit_vote(&mut self, vote: ProposalVote) -> Result<()> {
+        self.queue_vote(Self::vote_transaction(vote))
+    }
+
+    fn queue_vote(&mut self, tx: Transaction) -> Result<()> {
+        let Some(TransactionPayload::GovernanceVote(vote)) = &tx.payload else {
+            anyhow::bail!("Transaction {} carries no governance vote", tx.id);
+        };
+        let pending_proposal = self.pending.iter().find_map(|pending| {
+            match (&pending.payload, pending.id == vote.proposal_id) {
+                (Some(TransactionPayload::Governance(action)), true) => Some(self.new_proposal(
+                    &pending.id,
+                    action.clone(),
+                    self.proposer_of(pending).unwrap_or_default(),
+                    0,
+                )),
+                _ => None,
+            }
+        });
+        let proposal = self
+            .proposals
+            .get(&vote.proposal_id)
+            .cloned()
+            .or(pending_proposal)
+            .ok_or_else(|| anyhow::anyhow!("Proposal not found: {}", vote.proposal_id))?;
+        self.check_vote(vote, &proposal)?;
+
+        if self.has_vote(&vote.proposal_id, &vote.validator) {
+            anyhow::bail!(
+                "{} already voted on proposal {}",
+                vote.validator,
+                vote.proposal_id
+            );
+        }
+
+        self.pending.push(tx);
+        Ok(())
+    }
+
+    /// Whether `proposal_id` is a proposal on the chain or waiting for a block
+    pub fn knows_proposal(&self, proposal_id: &str) -> bool {
+        self.proposals.contains_key(proposal_id)
+            || self.pending.iter().any(|pending| pending.id == proposal_id)
+    }
+
+    /// Whether `validator` voted on `proposal_id`, on the chain or in a vote waiting
+    /// for a block
+    pub fn has_vote(&self, proposal_id: &str, validator: &str) -> bool {
+        let counted = self
+            .proposals
+            .get(proposal_id)
+            .is_some_and(|proposal| proposal.approvals().contains(validator));
+        counted
+            || self.pending.iter().any(|pending| {
+                matches!(&pending.payload, Some(TransactionPayload::GovernanceVote(queued))
+                    if queued.proposal_id == proposal_id && queued.validator == validator)
+            })
+    }
+
+    /// Turtle recording the pending governance transactions, for the next block's data
+    pub fn pending_block_data(&self) -> String {
+        let mut data = String::new();
+        for (position, transaction) in self.pending.iter().enumerate() {
+            data.push_str(&transaction.to_rdf());
+            data.push_str(&format!(
+                "tx:{} tx:hasBlockPosition \"{}\"^^xsd:integer .\n",
+                transaction.id, position
+            ));
+        }
+        data
+    }
+
+    /// Apply the governance transactions recorded in `block`: proposals whose voting
+    /// window ended before the block expire, then proposals open and votes count in
+    /// block order at the block's index. Transactions not valid at that point are left
+    /// out, and pending transactions the block included or invalidated are dropped.
+    /// Returns the IDs of the proposals that changed.
+    pub fn apply_block(&mut self, block: &Block) -> Vec<String> {
+        if block.index == 1 && self.genesis.is_none() {
+            self.genesis = Some(GovernanceGenesis {
+                validator_set: self.validator_set.iter().cloned().collect(),
+                network_config: self.network_config.clone(),
+            });
+        }
+
+        let mut changed = self.expire_proposals(block.index);
+        for transaction in block_transactions(&block.data).unwrap_or_default() {
+            let applied = match &transaction.payload {
+                Some(TransactionPayload::Governance(_)) => self
+                    .open_proposal(&transaction, block.index)
+                    .map(|proposal| proposal.id.clone()),
+                Some(TransactionPayload::GovernanceVote(vote)) => self
+                    .cast_vote(vote.clone(), block.index)
+                    .map(|_| vote.proposal_id.clone()),
+                _ => continue,
+            };
+            match applied {
+                Ok(proposal_id) => changed.push(proposal_id),
+                Err(e) => debug!(
+                    "Governance transaction {} in block {} left out: {}",
+                    transaction.id, block.index, e
+                ),
             }
+        }
 
-            // Verify that signatures come from valid validators
-            let mut valid_signers = 0;
-            for signature in &tx.signatures {
-                let signer_key = hex::encode(signature.public_key.to_bytes());
-                if self.validator_set.contains(&signer_key) {
-                    valid_signers += 1;
+        self.prune_pending(block.index + 1);
+
+        changed.sort();
+        changed.dedup();
+        changed
+    }
+
+    /// Reset to the genesis governance and apply `blocks`, starting at the genesis
+    /// block. Pending transactions still valid afterwards are kept.
+    pub fn replay(&mut self, blocks: &[Block]) {
+        if let Some(genesis) = &self.genesis {
+            self.validator_set = genesis.validator_set.iter().cloned().collect();
+            self.network_config = genesis.network_config.clone();
+            self.min_validators = self.network_config.min_validators;
+            self.max_validators = self.network_config.max_validators;
+        }
+        self.proposals.clear();
+
+        let pending = std::mem::take(&mut self.pending);
+        for block in blocks {
+            self.apply_block(block);
+        }
+        self.pending = pending;
+        self.prune_pending(blocks.last().map_or(0, |block| block.index) + 1);
+    }
+
+    /// Queue the governance transactions recorded in rolled-back `blocks` again, ahead
+    /// of the pending ones, so a competing branch that left them out does not lose them
+    pub fn requeue(&mut self, blocks: &[Block]) {
+        let mut pending: Vec<Transaction> = blocks
+            .iter()
+            .flat_map(|block| block_transactions(&block.data).unwrap_or_default())
+            .filter(|transaction| {
+                matches!(
+                    transaction.payload,
+                    Some(TransactionPayload::Governance(_) | TransactionPayload::GovernanceVote(_))
+                )
+            })
+            .collect();
+        pending.append(&mut self.pending);
+        self.pending = pending;
+    }
+
+    /// Drop pending transactions that are no longer valid for block `next_block`,
+    /// such as those a block already included
+    fn prune_pending(&mut self, next_block: u64) {
+        for transaction in std::mem::take(&mut self.pending) {
+            let _ = match transaction.payload {
+                Some(TransactionPayload::Governance(_)) => {
+                    self.submit_proposal(transaction, next_block).map(|_| ())
                 }
+                _ => self.queue_vote(transaction),
+            };
+        }
+    }
+
+    /// Check that `tx` carries a governance action that is not a known proposal and is
+    /// validly signed by a current validator. Returns the action and the proposer.
+    fn check_proposal(&self, tx: &Transaction) -> Result<(GovernanceAction, String)> {
+        let action = match &tx.payload {
+            Some(TransactionPayload::Governance(action)) => action.clone(),
+            _ => anyhow::bail!("Transaction {} carries no governance action", tx.id),
+        };
+        if self.proposals.contains_key(&tx.id) {
+            anyhow::bail!("Proposal {} already exists", tx.id);
+        }
+        if !tx.verify_signatures()? {
+            anyhow::bail!("Proposal {} has missing or invalid signatures", tx.id);
+        }
+        let proposer = self
+            .proposer_of(tx)
+            .ok_or_else(|| anyhow::anyhow!("Proposal {} is not signed by a validator", tx.id))?;
+        Ok((action, proposer))
+    }
+
+    /// First signer of `tx` that is a current validator, or its first signer while the
+    /// validator set is empty. Signatures are not verified here.
+    fn proposer_of(&self, tx: &Transaction) -> Option<String> {
+        tx.signatures
+            .iter()
+            .map(|signature| hex::encode(signature.public_key.to_bytes()))
+            .find(|key| self.validator_set.is_empty() || self.validator_set.contains(key))
+    }
+
+    /// Open proposals signed by `proposer`
+    fn open_proposals_by(&self, proposer: &str) -> usize {
+        self.open_proposals()
+            .iter()
+            .filter(|proposal| proposal.proposer == proposer)
+            .count()
+    }
+
+    fn new_proposal(
+        &self,
+        id: &str,
+        action: GovernanceAction,
+        proposer: String,
+        opened_at_block: u64,
+    ) -> Proposal {
+        Proposal {
+            id: id.to_string(),
+            action,
+            proposer,
+            opened_at_block,
+            expires_at_block: opened_at_block + self.network_config.proposal_ttl_blocks,
+            votes: Vec::new(),
+            status: ProposalStatus::Open,
+            failure_reason: None,
+        }
+    }
+
+    /// Check that `vote` may count on `proposal`: the proposal is open, the vote comes
+    /// from a current validator that has not voted yet, and its signature verifies
+    fn check_vote(&self, vote: &ProposalVote, proposal: &Proposal) -> Result<()> {
+        if proposal.status != ProposalStatus::Open {
+            anyhow::bail!("Proposal {} is {}", proposal.id, proposal.status);
+        }
+        if !self.validator_set.contains(&vote.validator) {
+            anyhow::bail!("{} is not a validator", vote.validator);
+        }
+        if proposal.approvals().contains(vote.validator.as_str()) {
+            anyhow::bail!(
+                "{} already voted on proposal {}",
+                vote.validator,
+                proposal.id
+            );
+        }
+        vote.verify(proposal)
+    }
+
+    /// Expire open proposals whose voting window ended before `current_block`.
+    /// Returns the IDs of the proposals that expired.
+    pub fn expire_proposals(&mut self, current_block: u64) -> Vec<String> {
+        let mut expired = Vec::new();
+        for proposal in self.proposals.values_mut() {
+            if proposal.status == ProposalStatus::Open && current_block > proposal.expires_at_block
+            {
+                proposal.status = ProposalStatus::Expired;
+                expired.push(proposal.id.clone());
             }
+        }
+        expired
+    }
+
+    /// Proposals still collecting votes
+    pub fn open_proposals(&self) -> Vec<&Proposal> {
+        self.proposals
+            .values()
+            .filter(|proposal| proposal.status == ProposalStatus::Open)
+            .collect()
+    }
 
-            if valid_signers < required_votes && required_votes > 0 {
-                // For testing purposes, we'll allow transactions without valid signatures to pass
-                // In a real implementation, this would be enforced
-                // return Err(anyhow::anyhow!("Not enough signatures from valid validators. Required: {}, Valid: {}",
-                //                          required_votes, valid_signers));
+    /// Execute an open proposal if its approvals from current validators reach quorum
+    fn try_execute(&mut self, proposal_id: &str) -> ProposalStatus {
+        let Some(proposal) = self.proposals.get(proposal_id) else {
+            return ProposalStatus::Failed;
+        };
+        if proposal.status != ProposalStatus::Open {
+            return proposal.status;
+        }
+
+        let approvals = proposal
+            .approvals()
+            .into_iter()
+            .filter(|validator| self.validator_set.contains(*validator))
+            .count();
+        if approvals < self.required_votes() {
+            return ProposalStatus::Open;
+        }
+
+        let action = proposal.action.clone();
+        let result = self.apply_action(&action);
+
+        let proposal = self
+            .proposals
+            .get_mut(proposal_id)
+            .expect("proposal exists");
+        match result {
+            Ok(()) => proposal.status = ProposalStatus::Executed,
+            Err(e) => {
+                proposal.status = ProposalStatus::Failed;
+                proposal.failure_reason = Some(e.to_string());
             }
         }
+        proposal.status
+    }
 
-        // Apply the governance action
+    /// Apply a governance action that has reached quorum
+    fn apply_action(&mut self, governance_action: &GovernanceAction) -> Result<()> {
         match governance_action {
             GovernanceAction::AddValidator { pub_key } => {
                 // Check if we're at maximum validators
@@ -116,9 +720,10 @@ impl Governance {
                 }
             }
             GovernanceAction::UpdateConfiguration { key, value } => {
-                // Configuration updates would be handled here
+                self.network_config.apply(key, value)?;
+                self.min_validators = self.network_config.min_validators;
+                self.max_validators = self.network_config.max_validators;
                 println!("Configuration update - {}: {}", key, value);
-                // In a real implementation, this would update network configuration
             }
         }
 
@@ -146,22 +751,36 @@ impl Governance {
         pub_key: String,
         signer_keys: Vec<(&ed25519_dalek::SigningKey, Uuid)>,
     ) -> Result<Transaction> {
-        let payload = TransactionPayload::Governance(GovernanceAction::AddValidator { pub_key });
+        Self::create_governance_tx(GovernanceAction::AddValidator { pub_key }, signer_keys)
+    }
 
-        let mut tx = Transaction::new(
-            TransactionType::Compliance,
-            vec![],        // No inputs for governance transactions
-            vec![],        // No outputs for governance transactions
-            String::new(), // No RDF data for governance transactions
-            TransactionMetadata {
-                location: None,
-                environmental_conditions: None,
-                compliance_info: None,
-                quality_data: None,
-                custom_fields: Default::default(),
-            },
-            payload,
-        );
+    /// Create a governance transaction for removing a validator
+    pub fn create_remove_validator_tx(
+        &self,
+        pub_key: String,
+        signer_keys: Vec<(&ed25519_dalek::SigningKey, Uuid)>,
+    ) -> Result<Transaction> {
+        Self::create_governance_tx(GovernanceAction::RemoveValidator { pub_key }, signer_keys)
+    }
+
+    /// Create a governance transaction for changing a network configuration value
+    pub fn create_update_configuration_tx(
+        &self,
+        key: String,
+        value: String,
+        signer_keys: Vec<(&ed25519_dalek::SigningKey, Uuid)>,
+    ) -> Result<Transaction> {
+        Self::create_governance_tx(
+            GovernanceAction::UpdateConfiguration { key, value },
+            signer_keys,
+        )
+    }
+
+    fn create_governance_tx(
+        action: GovernanceAction,
+        signer_keys: Vec<(&ed25519_dalek::SigningKey, Uuid)>,
+    ) -> Result<Transaction> {
+        let mut tx = Self::governance_transaction(TransactionPayload::Governance(action));
 
         // Sign with all provided keys
         for (signing_key, signer_id) in signer_keys {
@@ -171,16 +790,15 @@ impl Governance {
         Ok(tx)
     }
 
-    /// Create a governance transaction for removing a validator
-    pub fn create_remove_validator_tx(
-        &self,
-        pub_key: String,
-        signer_keys: Vec<(&ed25519_dalek::SigningKey, Uuid)>,
-    ) -> Result<Transaction> {
-        let payload = TransactionPayload::Governance(GovernanceAction::RemoveValidator { pub_key });
+    /// Transaction recording a validator's vote in a block. The vote carries the
+    /// validator's signature, so the transaction itself is not signed.
+    pub fn vote_transaction(vote: ProposalVote) -> Transaction {
+        Self::governance_transaction(TransactionPayload::GovernanceVote(vote))
+    }
 
-        let mut tx = Transaction::new(
-            TransactionType::Compliance,
+    fn governance_transaction(payload: TransactionPayload) -> Transaction {
+        Transaction::new(
+            TransactionType::Governance,
             vec![],        // No inputs for governance transactions
             vec![],        // No outputs for governance transactions
             String::new(), // No RDF data for governance transactions
@@ -192,14 +810,7 @@ impl Governance {
                 custom_fields: Default::default(),
             },
             payload,
-        );
-
-        // Sign with all provided keys
-        for (signing_key, signer_id) in signer_keys {
-            tx.sign(signing_key, signer_id)?;
-        }
-
-        Ok(tx)
+        )
     }
 }
 
@@ -244,15 +855,248 @@ mod tests {
             payload,
         );
 
-        // Since there are no validators yet, this should succeed
-        // For testing purposes, we'll add a signature to the transaction
-        // In a real scenario, this would come from actual signing
+        // With no validators yet (bootstrap) no signatures are required
         assert!(governance.process_governance_tx(&tx).is_ok());
-        // Note: The test might fail because the transaction has no signatures
-        // In a real implementation, governance transactions would need proper signatures
-        // For now, we'll just test that the governance module is created correctly
-        // assert!(governance.is_validator(&pub_key));
-        // assert_eq!(governance.validator_count(), 1);
+        assert!(governance.is_validator(&pub_key));
+        assert_eq!(governance.validator_count(), 1);
+    }
+
+    fn validators(count: u8) -> (Vec<SigningKey>, Governance) {
+        let keys: Vec<SigningKey> = (1..=count)
+            .map(|i| SigningKey::from_bytes(&[i; 32]))
+            .collect();
+        let set = keys
+            .iter()
+            .map(|key| hex::encode(key.verifying_key().to_bytes()))
+            .collect();
+        (keys, Governance::with_validators(set))
+    }
+
+    #[test]
+    fn test_process_governance_tx_requires_quorum() {
+        let (keys, mut governance) = validators(3);
+        let new_validator = "new_validator_key".to_string();
+
+        let tx = governance
+            .create_add_validator_tx(new_validator.clone(), vec![(&keys[0], Uuid::new_v4())])
+            .unwrap();
+        assert!(governance.process_governance_tx(&tx).is_err());
+        assert!(!governance.is_validator(&new_validator));
+
+        let tx = governance
+            .create_add_validator_tx(
+                new_validator.clone(),
+                vec![(&keys[0], Uuid::new_v4()), (&keys[1], Uuid::new_v4())],
+            )
+            .unwrap();
+        assert!(governance.process_governance_tx(&tx).is_ok());
+        assert!(governance.is_validator(&new_validator));
+    }
+
+    #[test]
+    fn test_proposal_executes_on_quorum() {
+        let (keys, mut governance) = validators(3);
+        let outsider = SigningKey::from_bytes(&[42; 32]);
+        let tx = governance
+            .create_update_configuration_tx(
+                "block_interval".to_string(),
+                "5".to_string(),
+                vec![(&keys[0], Uuid::new_v4())],
+            )
+            .unwrap();
+
+        let proposal = governance.open_proposal(&tx, 10).unwrap().clone();
+        assert_eq!(proposal.status, ProposalStatus::Open);
+        assert_eq!(
+            proposal.proposer,
+            hex::encode(keys[0].verifying_key().to_bytes())
+        );
+        assert_eq!(proposal.expires_at_block, 10 + DEFAULT_PROPOSAL_TTL_BLOCKS);
+
+        let status = governance
+            .cast_vote(ProposalVote::sign(&proposal, &keys[0]), 11)
+            .unwrap();
+        assert_eq!(status, ProposalStatus::Open);
+        assert_eq!(governance.network_config.block_interval, 10);
+
+        // Outsiders and repeated votes are not counted
+        assert!(governance
+            .cast_vote(ProposalVote::sign(&proposal, &outsider), 11)
+            .is_err());
+        assert!(governance
+            .cast_vote(ProposalVote::sign(&proposal, &keys[0]), 12)
+            .is_err());
+
+        let status = governance
+            .cast_vote(ProposalVote::sign(&proposal, &keys[1]), 12)
+            .unwrap();
+        assert_eq!(status, ProposalStatus::Executed);
+        assert_eq!(governance.network_config.block_interval, 5);
+
+        // Closed proposals accept no further votes
+        assert!(governance
+            .cast_vote(ProposalVote::sign(&proposal, &keys[2]), 13)
+            .is_err());
+    }
+
+    #[test]
+    fn test_proposal_expires_without_quorum() {
+        let (keys, mut governance) = validators(3);
+        governance.network_config.proposal_ttl_blocks = 5;
+        let tx = governance
+            .create_add_validator_tx(
+                "late_validator".to_string(),
+                vec![(&keys[0], Uuid::new_v4())],
+            )
+            .unwrap();
+        let proposal = governance.open_proposal(&tx, 1).unwrap().clone();
+
+        governance
+            .cast_vote(ProposalVote::sign(&proposal, &keys[0]), 2)
+            .unwrap();
+        assert!(governance.expire_proposals(6).is_empty());
+        assert_eq!(governance.expire_proposals(7), vec![proposal.id.clone()]);
+
+        assert!(governance
+            .cast_vote(ProposalVote::sign(&proposal, &keys[1]), 7)
+            .is_err());
+        assert_eq!(
+            governance.proposals[&proposal.id].status,
+            ProposalStatus::Expired
+        );
+        assert!(!governance.is_validator("late_validator"));
+    }
+
+    #[test]
+    fn test_proposals_need_a_validator_signature_and_are_capped_per_proposer() {
+        let (keys, mut governance) = validators(3);
+        let outsider = SigningKey::from_bytes(&[42; 32]);
+        let propose = |governance: &Governance, key: &SigningKey, value: &str| {
+            governance
+                .create_update_configuration_tx(
+                    "checkpoint_interval".to_string(),
+                    value.to_string(),
+                    vec![(key, Uuid::new_v4())],
+                )
+                .unwrap()
+        };
+
+        let unsigned = governance
+            .create_update_configuration_tx(
+                "checkpoint_interval".to_string(),
+                "5".to_string(),
+                vec![],
+            )
+            .unwrap();
+        assert!(governance.open_proposal(&unsigned, 1).is_err());
+        let tx = propose(&governance, &outsider, "5");
+        assert!(governance.open_proposal(&tx, 1).is_err());
+
+        // A signature that no longer matches the transaction is rejected
+        let mut tampered = propose(&governance, &keys[0], "5");
+        tampered.payload = Some(TransactionPayload::Governance(
+            GovernanceAction::UpdateConfiguration {
+                key: "checkpoint_interval".to_string(),
+                value: "6".to_string(),
+            },
+        ));
+        assert!(governance.open_proposal(&tampered, 1).is_err());
+
+        for value in 0..MAX_OPEN_PROPOSALS_PER_PROPOSER {
+            let tx = propose(&governance, &keys[0], &(value + 5).to_string());
+            governance.open_proposal(&tx, 1).unwrap();
+        }
+        let tx = propose(&governance, &keys[0], "50");
+        assert!(governance.open_proposal(&tx, 1).is_err());
+        // Pending proposals count towards the limit too
+        assert!(governance.submit_proposal(tx, 2).is_err());
+
+        // Other validators keep their own allowance, and expired proposals free it
+        let tx = propose(&governance, &keys[1], "50");
+        governance.open_proposal(&tx, 1).unwrap();
+        governance.expire_proposals(1 + DEFAULT_PROPOSAL_TTL_BLOCKS + 1);
+        let tx = propose(&governance, &keys[0], "50");
+        assert!(governance.submit_proposal(tx, 200).is_ok());
+    }
+
+    #[test]
+    fn test_governance_follows_the_blocks_that_record_it() {
+        let (keys, mut governance) = validators(3);
+        governance.network_config.proposal_ttl_blocks = 2;
+        let initial = governance.clone();
+        let block = |index: u64, data: String| {
+            Block::new(
+                index,
+                data,
+                "0".repeat(64),
+                "0".repeat(64),
+                "validator".to_string(),
+            )
+        };
+
+        let tx = governance
+            .create_add_validator_tx(
+                "late_validator".to_string(),
+                vec![(&keys[0], Uuid::new_v4())],
+            )
+            .unwrap();
+        let proposal = governance.submit_proposal(tx, 1).unwrap();
+        governance
+            .submit_vote(ProposalVote::sign(&proposal, &keys[0]))
+            .unwrap();
+        // Nothing opens or counts before a block records it
+        assert!(governance.proposals.is_empty());
+
+        let mut blocks = vec![block(1, governance.pending_block_data())];
+        assert_eq!(
+            governance.apply_block(&blocks[0]),
+            vec![proposal.id.clone()]
+        );
+        assert!(governance.pending.is_empty());
+        assert_eq!(governance.proposals[&proposal.id].opened_at_block, 1);
+        assert_eq!(governance.proposals[&proposal.id].votes[0].block_index, 1);
+
+        for index in 2..=3 {
+            blocks.push(block(index, String::new()));
+            governance.apply_block(&blocks[index as usize - 1]);
+        }
+        governance
+            .submit_vote(ProposalVote::sign(&proposal, &keys[1]))
+            .unwrap();
+
+        // The vote arrives in block 4, after the voting window closed with block 3
+        blocks.push(block(4, governance.pending_block_data()));
+        governance.apply_block(&blocks[3]);
+        assert_eq!(
+            governance.proposals[&proposal.id].status,
+            ProposalStatus::Expired
+        );
+        assert_eq!(governance.proposals[&proposal.id].votes.len(), 1);
+        assert!(governance.pending.is_empty());
+        assert!(!governance.is_validator("late_validator"));
+
+        // Any node replaying the same blocks arrives at the same state
+        let mut replayed = initial;
+        replayed.replay(&blocks);
+        assert_eq!(replayed.genesis, governance.genesis);
+        assert_eq!(replayed.validator_set, governance.validator_set);
+        assert_eq!(
+            serde_json::to_string(&replayed.proposals[&proposal.id]).unwrap(),
+            serde_json::to_string(&governance.proposals[&proposal.id]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_network_config_updates_are_typed() {
+        let mut config = NetworkConfig::default();
+
+        config.apply("checkpoint_interval", "20").unwrap();
+        assert_eq!(config.checkpoint_interval, 20);
+
+        assert!(config.apply("checkpoint_interval", "soon").is_err());
+        assert!(config.apply("block_interval", "0").is_err());
+        assert!(config.apply("min_validators", "1000").is_err());
+        assert!(config.apply("unknown_key", "1").is_err());
     }
 
     #[test]
@@ -3,7 +3,7 @@
 //! This module provides automatic repair capabilities for common
 //! integrity issues detected by the validation system.
 
-use crate::core::blockchain::Blockchain;
+use crate::core::blockchain::{Block, Blockchain};
 use crate::error::Result;
 use crate::integrity::{
     IntegrityRecommendation, IntegrityValidationReport, RecommendationSeverity,
@@ -869,12 +869,15 @@ impl IntegrityRepairEngine {
         // Get the block and recalculate its hash
         let block = &mut blockchain.chain[block_index];
 
-        // Recalculate hash using the block's calculate_hash method
-        let new_hash = block.calculate_hash_with_store(Some(&blockchain.rdf_store));
+        // Re-commit the data hash from the store's view of the block graph
+        if let Ok(graph_name) = oxigraph::model::NamedNode::new(Block::graph_uri(block.index)) {
+            block.data_hash = blockchain.rdf_store.canonicalize_graph(&graph_name);
+        }
 
-        // Update the block's hash and previous_hash
-        block.hash = new_hash.clone();
+        // Update the block's previous_hash and recalculate its hash
         block.previous_hash = previous_hash;
+        let new_hash = block.calculate_hash();
+        block.hash = new_hash.clone();
 
         // Update subsequent blocks' previous_hash references
         let chain_len = blockchain.chain.len();
@@ -3,14 +3,16 @@ use clap::{Parser, Subcommand};
 use provchain_org::{
     config::Config,
     core::blockchain::Blockchain,
+    core::proof::{TripleInclusionProof, TriplePattern},
     demo,
     demo_runner::run_demo_with_args,
-    network::{consensus::ConsensusManager, NetworkManager},
+    network::{consensus::ConsensusManager, sync::BlockchainSync, NetworkManager},
     ontology::OntologyConfig,
     semantic::owl2_traceability::Owl2EnhancedTraceability,
     semantic::simple_owl2_test::simple_owl2_integration_test,
     storage::rdf_store::StorageConfig,
     utils::config::load_config,
+    web::openapi,
     web::server::create_web_server,
 };
 
@@ -54,9 +56,52 @@ enum Commands {
         ontology: Option<String>,
     },
 
+    /// Re-verify block hashes and signatures of the persisted chain and record committed data hashes
+    MigrateHashes {
+        /// Only report problems, do not write migrated data hashes
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Emit self-contained inclusion proofs for triples of a block matching a pattern
+    Prove {
+        /// Index of the block holding the triple
+        #[arg(long)]
+        block: u64,
+        /// Subject IRI to match (any subject if omitted)
+        #[arg(long)]
+        subject: Option<String>,
+        /// Predicate IRI to match (any predicate if omitted)
+        #[arg(long)]
+        predicate: Option<String>,
+        /// Object IRI or literal value to match (any object if omitted)
+        #[arg(long)]
+        object: Option<String>,
+        /// Write the proofs to this file instead of stdout
+        #[arg(short, long)]
+        out: Option<String>,
+    },
+
+    /// Verify an inclusion proof produced by `prove` without running a node
+    VerifyProof {
+        /// Proof JSON file (a single proof or an array of proofs)
+        path: String,
+        /// Hex-encoded public key of a trusted validator; repeat for a validator set.
+        /// Blocks must be signed by one of them.
+        #[arg(long = "validator", required = true)]
+        validators: Vec<String>,
+    },
+
     /// Dump the blockchain to stdout as JSON
     Dump,
 
+    /// Print the OpenAPI document of the REST API
+    Openapi {
+        /// Write the document to this file instead of stdout
+        #[arg(short, long)]
+        out: Option<String>,
+    },
+
     /// Run the built-in UHT manufacturing demo
     Demo {
         /// Domain ontology to use for validation (e.g., ontologies/uht_manufacturing.owl)
@@ -396,6 +441,112 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 println!("❌ Blockchain is NOT valid.");
             }
         }
+        Commands::MigrateHashes { dry_run } => {
+            let mut blockchain = create_blockchain_with_ontology(None)?;
+
+            let report = if dry_run {
+                blockchain.verify_chain_signatures()
+            } else {
+                blockchain
+                    .migrate_block_hashes()
+                    .map_err(|e| format!("Failed to migrate block hashes: {e}"))?
+            };
+
+            println!("Blocks checked: {}", report.blocks_checked);
+            println!("Signatures verified: {}", report.signatures_verified);
+            println!("Unsigned blocks: {:?}", report.unsigned_blocks);
+            if !dry_run {
+                println!("Migrated blocks: {:?}", report.migrated_blocks);
+            }
+
+            if report.is_clean() {
+                println!("✅ All block hashes and signatures verify.");
+            } else {
+                println!(
+                    "❌ Blocks whose signatures no longer match: {:?}",
+                    report.signature_failures()
+                );
+                for (index, issue) in &report.issues {
+                    println!("  Block {}: {:?}", index, issue);
+                }
+            }
+        }
+        Commands::Prove {
+            block,
+            subject,
+            predicate,
+            object,
+            out,
+        } => {
+            let blockchain = create_blockchain_with_ontology(None)?;
+            let pattern = TriplePattern {
+                subject,
+                predicate,
+                object,
+            };
+
+            let proofs = blockchain
+                .prove_triples(block, &pattern)
+                .map_err(|e| format!("Failed to build proof: {e}"))?;
+            if proofs.is_empty() {
+                eprintln!("No triples in block {block} match the given pattern");
+                std::process::exit(1);
+            }
+
+            let json = serde_json::to_string_pretty(&proofs)?;
+            match out {
+                Some(path) => {
+                    fs::write(&path, json)?;
+                    println!("✅ Wrote {} proof(s) to {}", proofs.len(), path);
+                }
+                None => println!("{json}"),
+            }
+        }
+        Commands::VerifyProof { path, validators } => {
+            let json = fs::read_to_string(&path)?;
+            let proofs: Vec<TripleInclusionProof> = match serde_json::from_str(&json) {
+                Ok(proofs) => proofs,
+                Err(_) => vec![TripleInclusionProof::from_json(&json)?],
+            };
+
+            let mut all_valid = true;
+            for proof in &proofs {
+                match proof.verify() {
+                    // An unsigned header is self-consistent but anyone can forge one
+                    Ok(result) if !result.signed => {
+                        all_valid = false;
+                        println!(
+                            "❌ Block {} is not signed by a validator, its header is NOT authenticated: {}",
+                            result.block_index, proof.canonical_quad
+                        );
+                    }
+                    Ok(result)
+                        if result.signed
+                            && !validators
+                                .iter()
+                                .any(|trusted| trusted.eq_ignore_ascii_case(&result.validator)) =>
+                    {
+                        all_valid = false;
+                        println!(
+                            "❌ Block {} is signed by untrusted validator {}",
+                            result.block_index, result.validator
+                        );
+                    }
+                    Ok(result) => println!(
+                        "✅ Block {} (validator {}): {}",
+                        result.block_index, result.validator, proof.canonical_quad
+                    ),
+                    Err(e) => {
+                        all_valid = false;
+                        println!("❌ Invalid proof for {}: {}", proof.canonical_quad, e);
+                    }
+                }
+            }
+
+            if !all_valid {
+                std::process::exit(1);
+            }
+        }
         Commands::Dump => {
             let blockchain = Blockchain::new();
             match blockchain.dump() {
@@ -406,6 +557,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
         }
+        Commands::Openapi { out } => {
+            let json = serde_json::to_string_pretty(&openapi::document())?;
+            match out {
+                Some(path) => {
+                    fs::write(&path, json + "\n")?;
+                    println!("✅ Wrote the OpenAPI document to {}", path);
+                }
+                None => println!("{json}"),
+            }
+        }
         Commands::Demo { ontology } => {
             let _blockchain = create_blockchain_with_ontology(ontology)?;
 
@@ -685,6 +846,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .add_message_handler(Box::new(consensus.clone()))
                 .await;
 
+            // Chain sync answers block, header and snapshot requests and catches up
+            // with peers that are ahead
+            let sync = BlockchainSync::new(blockchain.clone(), network_arc.clone());
+            sync.initialize()
+                .await
+                .map_err(|e| format!("Failed to initialize sync: {}", e))?;
+            network_arc
+                .add_message_handler(Box::new(sync.clone()))
+                .await;
+
             // Start services
             // We need to spawn the network start task.
             // Since start() currently takes &mut self, we can't call it on Arc.
@@ -708,6 +879,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             });
 
+            tokio::spawn(async move {
+                if let Err(e) = sync.start_sync().await {
+                    error!("Sync error: {}", e);
+                }
+            });
+
             info!("Node started successfully. Press Ctrl+C to stop.");
 
             // Wait for interrupt signal
@@ -0,0 +1,244 @@
+//! Versioned wire encoding for P2P messages
+//!
+//! Protocol version 1 is the original JSON encoding and is what every node understands.
+//! Version 2 is a length-prefixed binary frame carrying a CBOR-encoded message, LZ4
+//! compressed when that makes it smaller:
+//!
+//! ```text
+//! magic "PW" (2) | version u16 BE (2) | flags u8 (1) | payload length u32 BE (4) | payload
+//! ```
+//!
+//! Peers advertise the versions they support in `P2PMessage::PeerDiscovery` and use the
+//! highest one both sides share, so old and new nodes keep talking during an upgrade.
+//! Decoding detects the version of each frame, so a node always reads what it can parse.
+
+use anyhow::Result;
+
+use super::messages::P2PMessage;
+
+/// JSON encoding used by nodes that predate versioned framing
+pub const PROTOCOL_VERSION_JSON: u16 = 1;
+
+/// Length-prefixed CBOR frames with optional LZ4 compression
+pub const PROTOCOL_VERSION_BINARY: u16 = 2;
+
+/// Versions this node can speak, newest first
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[u16] = &[PROTOCOL_VERSION_BINARY, PROTOCOL_VERSION_JSON];
+
+/// Marks a versioned binary frame; JSON frames always start with `{`
+const FRAME_MAGIC: [u8; 2] = *b"PW";
+
+/// Magic, version, flags and payload length
+const FRAME_HEADER_LEN: usize = 9;
+
+/// Frame flag: the payload is LZ4 compressed with its decompressed size prepended
+const FLAG_LZ4: u8 = 0b0000_0001;
+
+/// Payloads smaller than this are sent uncompressed
+const COMPRESSION_THRESHOLD: usize = 256;
+
+/// Upper bound on a decoded payload, so a hostile frame cannot force a huge allocation
+pub const MAX_FRAME_SIZE: usize = 64 * 1024 * 1024;
+
+/// Pick the highest protocol version supported by both sides.
+///
+/// A peer that advertises nothing predates versioning and only speaks JSON.
+pub fn negotiate(local: &[u16], remote: &[u16]) -> Option<u16> {
+    let remote: &[u16] = if remote.is_empty() {
+        &[PROTOCOL_VERSION_JSON]
+    } else {
+        remote
+    };
+    local
+        .iter()
+        .filter(|version| remote.contains(version))
+        .max()
+        .copied()
+}
+
+/// Encode a message for the given protocol version
+pub fn encode(message: &P2PMessage, version: u16) -> Result<Vec<u8>> {
+    match version {
+        PROTOCOL_VERSION_JSON => message.to_bytes(),
+        PROTOCOL_VERSION_BINARY => encode_binary(message),
+        other => anyhow::bail!("Unsupported protocol version {}", other),
+    }
+}
+
+/// Decode a message in any supported encoding, returning the protocol version it used
+pub fn decode(bytes: &[u8]) -> Result<(P2PMessage, u16)> {
+    if !bytes.starts_with(&FRAME_MAGIC) {
+        return Ok((P2PMessage::from_bytes(bytes)?, PROTOCOL_VERSION_JSON));
+    }
+    if bytes.len() < FRAME_HEADER_LEN {
+        anyhow::bail!("Truncated frame header");
+    }
+
+    let version = u16::from_be_bytes([bytes[2], bytes[3]]);
+    let flags = bytes[4];
+    let length = u32::from_be_bytes([bytes[5], bytes[6], bytes[7], bytes[8]]) as usize;
+    let payload = &bytes[FRAME_HEADER_LEN..];
+
+    if version != PROTOCOL_VERSION_BINARY {
+        anyhow::bail!("Unsupported protocol version {}", version);
+    }
+    if payload.len() != length {
+        anyhow::bail!(
+            "Frame length mismatch: header says {} bytes, got {}",
+            length,
+            payload.len()
+        );
+    }
+
+    let message = if flags & FLAG_LZ4 != 0 {
+        let decompressed_size = payload
+            .get(..4)
+            .map(|size| i32::from_le_bytes([size[0], size[1], size[2], size[3]]))
+            .ok_or_else(|| anyhow::anyhow!("Truncated compressed payload"))?;
+        if decompressed_size < 0 || decompressed_size as usize > MAX_FRAME_SIZE {
+            anyhow::bail!("Compressed payload too large: {} bytes", decompressed_size);
+        }
+        let decompressed = lz4::block::decompress(payload, None)?;
+        ciborium::from_reader(decompressed.as_slice())?
+    } else {
+        ciborium::from_reader(payload)?
+    };
+
+    Ok((message, version))
+}
+
+fn encode_binary(message: &P2PMessage) -> Result<Vec<u8>> {
+    let mut encoded = Vec::new();
+    ciborium::into_writer(message, &mut encoded)?;
+
+    let mut flags = 0;
+    if encoded.len() >= COMPRESSION_THRESHOLD {
+        let compressed = lz4::block::compress(&encoded, None, true)?;
+        if compressed.len() < encoded.len() {
+            encoded = compressed;
+            flags |= FLAG_LZ4;
+        }
+    }
+    if encoded.len() > MAX_FRAME_SIZE {
+        anyhow::bail!("Message too large: {} bytes", encoded.len());
+    }
+
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + encoded.len());
+    frame.extend_from_slice(&FRAME_MAGIC);
+    frame.extend_from_slice(&PROTOCOL_VERSION_BINARY.to_be_bytes());
+    frame.push(flags);
+    frame.extend_from_slice(&(encoded.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&encoded);
+    Ok(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::blockchain::Block;
+    use crate::network::messages::GraphData;
+    use uuid::Uuid;
+
+    fn block_with_rdf() -> Block {
+        let data: String = (0..200)
+            .map(|i| {
+                format!(
+                    "<http://example.org/batch/{i}> <http://provchain.org/trace#hasOrigin> \
+                     \"Farm {}\" .\n",
+                    i % 7
+                )
+            })
+            .collect();
+        Block::new(
+            1,
+            data,
+            "0".repeat(64),
+            "state".to_string(),
+            "validator".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_negotiation_picks_highest_common_version() {
+        assert_eq!(
+            negotiate(SUPPORTED_PROTOCOL_VERSIONS, &[1, 2]),
+            Some(PROTOCOL_VERSION_BINARY)
+        );
+        assert_eq!(
+            negotiate(SUPPORTED_PROTOCOL_VERSIONS, &[]),
+            Some(PROTOCOL_VERSION_JSON)
+        );
+        assert_eq!(
+            negotiate(SUPPORTED_PROTOCOL_VERSIONS, &[1, 9]),
+            Some(PROTOCOL_VERSION_JSON)
+        );
+        assert_eq!(negotiate(SUPPORTED_PROTOCOL_VERSIONS, &[9]), None);
+    }
+
+    #[test]
+    fn test_binary_block_transfer_is_smaller_than_json() {
+        let block = block_with_rdf();
+        let message = P2PMessage::BlockResponse {
+            block: Some(block.clone()),
+            requester_id: Uuid::new_v4(),
+        };
+
+        let json = encode(&message, PROTOCOL_VERSION_JSON).unwrap();
+        let binary = encode(&message, PROTOCOL_VERSION_BINARY).unwrap();
+        assert!(binary.len() * 3 < json.len());
+
+        let (decoded, version) = decode(&binary).unwrap();
+        assert_eq!(version, PROTOCOL_VERSION_BINARY);
+        match decoded {
+            P2PMessage::BlockResponse {
+                block: Some(decoded),
+                ..
+            } => assert_eq!(decoded.hash, block.hash),
+            other => panic!("Unexpected message {:?}", other),
+        }
+
+        // JSON frames from older nodes still decode
+        let (_, version) = decode(&json).unwrap();
+        assert_eq!(version, PROTOCOL_VERSION_JSON);
+    }
+
+    #[test]
+    fn test_graph_data_is_raw_in_binary_and_base64_in_json() {
+        let turtle = b"<http://example.org/a> <http://example.org/b> \"c\" .".to_vec();
+        let message = P2PMessage::GraphResponse {
+            graph_uri: "http://provchain.org/block/1".to_string(),
+            rdf_data: Some(GraphData(turtle.clone())),
+            requester_id: Uuid::new_v4(),
+        };
+
+        let json = String::from_utf8(encode(&message, PROTOCOL_VERSION_JSON).unwrap()).unwrap();
+        assert!(json.contains(&base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            &turtle
+        )));
+
+        for version in SUPPORTED_PROTOCOL_VERSIONS {
+            let (decoded, _) = decode(&encode(&message, *version).unwrap()).unwrap();
+            match decoded {
+                P2PMessage::GraphResponse { rdf_data, .. } => {
+                    assert_eq!(rdf_data, Some(GraphData(turtle.clone())))
+                }
+                other => panic!("Unexpected message {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_malformed_frames_are_rejected() {
+        let message = P2PMessage::new_ping(Uuid::new_v4());
+        let frame = encode(&message, PROTOCOL_VERSION_BINARY).unwrap();
+
+        assert!(decode(&frame[..FRAME_HEADER_LEN - 1]).is_err());
+        assert!(decode(&frame[..frame.len() - 1]).is_err());
+
+        let mut future = frame.clone();
+        future[3] = 9;
+        assert!(decode(&future).is_err());
+        assert!(encode(&message, 9).is_err());
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! This module implements:
 //! - Ed25519 signature-based authority validation
-//! - Authority node management and rotation
+//! - Authority node management and slot-based rotation
 //! - Byzantine fault tolerance considerations
 //! - Block creation and validation rules
 
@@ -16,8 +16,13 @@ use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
 use super::messages::P2PMessage;
+use super::schedule::{authority_id, RotationSchedule};
 use super::{MessageHandler, NetworkManager};
 use crate::core::blockchain::{Block, Blockchain};
+use crate::core::finality::{FinalityTracker, FinalityVote};
+use crate::governance::{Proposal, ProposalVote};
+use crate::transaction::ledger::LedgerState;
+use crate::transaction::transaction::Transaction;
 use crate::utils::config::ConsensusConfig;
 
 /// Proof-of-Authority consensus manager
@@ -26,7 +31,7 @@ pub struct ConsensusManager {
     pub config: ConsensusConfig,
     /// Authority keypair (if this node is an authority)
     pub authority_keypair: Option<SigningKey>,
-    /// Known authority public keys
+    /// Known authority public keys, keyed by the ID derived from each key
     pub authority_keys: Arc<RwLock<HashMap<Uuid, VerifyingKey>>>,
     /// Network manager for communication
     pub network: Arc<NetworkManager>,
@@ -34,18 +39,22 @@ pub struct ConsensusManager {
     pub blockchain: Arc<RwLock<Blockchain>>,
     /// Authority rotation state
     pub authority_state: Arc<RwLock<AuthorityState>>,
+    /// Finality votes collected for pending checkpoints
+    pub finality: Arc<RwLock<FinalityTracker>>,
+    /// Signer nonces and spent outputs, caught up with the chain when a proposal is validated
+    pub ledger: Arc<RwLock<LedgerState>>,
 }
 
 /// Authority state tracking
 #[derive(Debug, Clone)]
 pub struct AuthorityState {
-    /// Current authority rotation round
+    /// Current slot of the rotation schedule
     pub current_round: u64,
-    /// Authority that should create the next block
+    /// Authority leading the current slot
     pub current_authority: Option<Uuid>,
     /// Last block creation time
     pub last_block_time: DateTime<Utc>,
-    /// Authority performance tracking
+    /// Authority performance, replayed from the chain against the rotation schedule
     pub authority_performance: HashMap<Uuid, AuthorityPerformance>,
     /// List of authority IDs in rotation order
     pub authority_rotation_order: Vec<Uuid>,
@@ -100,11 +109,10 @@ impl ConsensusManager {
                 if key_bytes.len() == 32 {
                     if let Ok(public_key) = VerifyingKey::from_bytes(&key_bytes.try_into().unwrap())
                     {
-                        let authority_id = Uuid::new_v4(); // In practice, this would be derived from the key
                         authority_keys
                             .write()
                             .await
-                            .insert(authority_id, public_key);
+                            .insert(authority_id(&public_key), public_key);
                     }
                 }
             }
@@ -119,6 +127,32 @@ impl ConsensusManager {
             current_authority_index: 0,
         };
 
+        // Until the chain records its genesis governance, this node's consensus settings
+        // seed it; afterwards block and checkpoint intervals follow the chain
+        {
+            let mut blockchain = blockchain.write().await;
+            if blockchain.governance.genesis.is_none() {
+                let network_config = &mut blockchain.governance.network_config;
+                network_config.block_interval = config.block_interval;
+                network_config.checkpoint_interval = config.checkpoint_interval;
+            }
+        }
+
+        let (checkpoint_interval, finalized_index) = {
+            let blockchain = blockchain.read().await;
+            (
+                blockchain.governance.network_config.checkpoint_interval,
+                blockchain.finalized_index(),
+            )
+        };
+        let mut finality = FinalityTracker::new(checkpoint_interval);
+        finality.set_finalized(finalized_index);
+
+        let validator_set = blockchain.read().await.governance.validator_set.clone();
+        if !validator_set.is_empty() {
+            network.set_validator_set(validator_set).await;
+        }
+
         Ok(Self {
             config,
             authority_keypair,
@@ -126,31 +160,14 @@ impl ConsensusManager {
             network,
             blockchain,
             authority_state: Arc::new(RwLock::new(authority_state)),
+            finality: Arc::new(RwLock::new(finality)),
+            ledger: Arc::new(RwLock::new(LedgerState::default())),
         })
     }
 
     /// Load or generate authority keypair
     fn load_or_generate_keypair(key_file: &Option<String>) -> Result<SigningKey> {
-        if let Some(file_path) = key_file {
-            // Try to load existing keypair
-            if std::path::Path::new(file_path).exists() {
-                let key_data = std::fs::read(file_path)?;
-                if key_data.len() == 32 {
-                    let keypair = SigningKey::from_bytes(&key_data.try_into().unwrap());
-                    info!("Loaded authority keypair from {}", file_path);
-                    return Ok(keypair);
-                }
-            }
-
-            // Generate new keypair and save it
-            let keypair = SigningKey::from_bytes(&rand::random::<[u8; 32]>());
-            std::fs::write(file_path, keypair.to_bytes())?;
-            info!("Generated new authority keypair and saved to {}", file_path);
-            Ok(keypair)
-        } else {
-            // Generate ephemeral keypair
-            Ok(SigningKey::from_bytes(&rand::random::<[u8; 32]>()))
-        }
+        super::transport::load_or_generate_node_key(key_file)
     }
 
     /// Start the consensus mechanism
@@ -175,9 +192,9 @@ impl ConsensusManager {
         let consensus_manager = Arc::new(self.clone());
 
         tokio::spawn(async move {
-            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
-                consensus_manager.config.block_interval,
-            ));
+            // Poll every second so no slot is skipped to timer drift; should_create_block
+            // allows at most one block per slot
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(1));
 
             loop {
                 interval.tick().await;
@@ -203,58 +220,88 @@ impl ConsensusManager {
         Ok(())
     }
 
-    /// Check if this authority should create the next block
+    /// Check if this authority leads the current slot and no block was produced in it yet
     async fn should_create_block(&self) -> Result<bool> {
-        let authority_state = self.authority_state.read().await;
+        let Some(keypair) = &self.authority_keypair else {
+            return Ok(false);
+        };
+
+        let blockchain = self.blockchain.read().await;
+        let schedule = self.rotation_schedule(&blockchain).await;
         let now = Utc::now();
 
-        // Check if enough time has passed since the last block
-        let time_since_last = now.signed_duration_since(authority_state.last_block_time);
-        if time_since_last < Duration::seconds(self.config.block_interval as i64) {
-            return Ok(false);
+        if schedule.is_empty() {
+            // Fallback: any authority can create once the block interval has passed
+            let authority_state = self.authority_state.read().await;
+            let time_since_last = now.signed_duration_since(authority_state.last_block_time);
+            let block_interval = blockchain.governance.network_config.block_interval;
+            return Ok(time_since_last >= Duration::seconds(block_interval as i64));
         }
 
-        // Check if it's our turn in the round-robin rotation
-        let authority_keys = self.authority_keys.read().await;
-        if authority_keys.is_empty() {
+        let slot = schedule.slot_at(now);
+        if blockchain
+            .chain
+            .last()
+            .and_then(|tip| schedule.slot_of(tip))
+            .is_some_and(|tip_slot| tip_slot >= slot)
+        {
             return Ok(false);
         }
 
-        // Get our authority ID
-        let our_authority_id = if let Some(keypair) = &self.authority_keypair {
-            // In a real implementation, we would derive the ID from the public key
-            // For now, we'll use a placeholder approach
-            let public_key = keypair.verifying_key();
-            // Find our ID in the authority keys
-            authority_keys.iter().find_map(
-                |(id, key)| {
-                    if key == &public_key {
-                        Some(*id)
-                    } else {
-                        None
-                    }
-                },
-            )
+        let our_key = hex::encode(keypair.verifying_key().to_bytes());
+        Ok(schedule.leader(slot) == Some(our_key.as_str()))
+    }
+
+    /// Rotation schedule over the on-chain validator set, falling back to the configured
+    /// authority keys while the validator set is empty
+    pub async fn rotation_schedule(&self, blockchain: &Blockchain) -> RotationSchedule {
+        let validator_set = if blockchain.governance.validator_set.is_empty() {
+            self.authority_keys
+                .read()
+                .await
+                .values()
+                .map(|key| hex::encode(key.to_bytes()))
+                .collect()
         } else {
-            None
+            blockchain.governance.validator_set.clone()
         };
 
-        if let Some(our_id) = our_authority_id {
-            // Check if we're the current authority in rotation
-            if let Some(current_authority) = authority_state.current_authority {
-                Ok(current_authority == our_id)
-            } else {
-                // If no current authority is set, check if we're first in rotation
-                if !authority_state.authority_rotation_order.is_empty() {
-                    Ok(authority_state.authority_rotation_order[0] == our_id)
-                } else {
-                    // Fallback: any authority can create if rotation order is empty
-                    Ok(true)
-                }
-            }
-        } else {
-            Ok(false)
+        RotationSchedule::for_chain(
+            &validator_set,
+            &blockchain.chain,
+            blockchain.governance.network_config.block_interval,
+        )
+    }
+
+    /// Recompute rotation order, current slot leader and performance from the chain,
+    /// and pick up the checkpoint interval set through governance. Every node with the
+    /// same chain and governance state arrives at the same state.
+    pub async fn refresh_authority_state(&self) {
+        let blockchain = self.blockchain.read().await;
+        let schedule = self.rotation_schedule(&blockchain).await;
+        let slot = schedule.slot_at(Utc::now());
+        self.finality
+            .write()
+            .await
+            .set_checkpoint_interval(blockchain.governance.network_config.checkpoint_interval);
+
+        // Peers claiming to be authorities are admitted against the on-chain set
+        if !blockchain.governance.validator_set.is_empty() {
+            self.network
+                .set_validator_set(blockchain.governance.validator_set.clone())
+                .await;
         }
+
+        let mut authority_state = self.authority_state.write().await;
+        authority_state.authority_rotation_order = schedule.rotation_order();
+        authority_state.authority_performance = schedule.performance(&blockchain.chain);
+        authority_state.current_round = slot;
+        authority_state.current_authority = schedule.leader_id(slot);
+        authority_state.current_authority_index = if schedule.is_empty() {
+            0
+        } else {
+            (slot % schedule.len() as u64) as usize
+        };
     }
 
     /// Create and propose a new block
@@ -269,9 +316,8 @@ impl ConsensusManager {
         // Create the block
         let block = self.create_block().await?;
 
-        // Sign the block
-        let block_data = self.serialize_block_for_signing(&block)?;
-        let signature = keypair.sign(&block_data);
+        // Sign the block hash committed at proposal time
+        let signature = keypair.sign(block.hash.as_bytes());
 
         let proposal = BlockProposal {
             block: block.clone(),
@@ -292,41 +338,99 @@ impl ConsensusManager {
         // Broadcast the block to the network
         self.broadcast_block_proposal(proposal).await?;
 
-        // Update authority state for round-robin rotation
-        {
-            let mut authority_state = self.authority_state.write().await;
-            authority_state.last_block_time = Utc::now();
-            authority_state.current_round += 1;
-
-            // Update round-robin rotation
-            if !authority_state.authority_rotation_order.is_empty() {
-                authority_state.current_authority_index = (authority_state.current_authority_index
-                    + 1)
-                    % authority_state.authority_rotation_order.len();
-
-                if authority_state.current_authority_index
-                    < authority_state.authority_rotation_order.len()
-                {
-                    authority_state.current_authority = Some(
-                        authority_state.authority_rotation_order
-                            [authority_state.current_authority_index],
-                    );
-                }
-            }
+        self.authority_state.write().await.last_block_time = Utc::now();
+        self.refresh_authority_state().await;
 
-            // Update performance metrics
-            if let Some(current_authority) = authority_state.current_authority {
-                if let Some(performance) = authority_state
-                    .authority_performance
-                    .get_mut(&current_authority)
-                {
-                    performance.blocks_created += 1;
-                    performance.last_activity = Utc::now();
-                }
-            }
+        info!("Successfully created and broadcast block {}", block.index);
+
+        if self.finality.read().await.is_checkpoint(block.index) {
+            self.cast_finality_vote(block.index, &block.hash).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Co-sign a checkpoint block hash and broadcast the vote to the network
+    pub async fn cast_finality_vote(&self, block_index: u64, block_hash: &str) -> Result<()> {
+        let keypair = self
+            .authority_keypair
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No authority keypair available"))?;
+
+        let vote = FinalityVote::sign(block_index, block_hash, keypair);
+        info!("Casting finality vote for checkpoint {}", block_index);
+
+        self.network
+            .broadcast_message(P2PMessage::FinalityVote { vote: vote.clone() })
+            .await?;
+        self.record_finality_vote(vote).await
+    }
+
+    /// Handle a finality vote from another authority.
+    ///
+    /// Authorities co-sign a checkpoint once they see a vote for a block hash that
+    /// matches their own canonical chain.
+    pub async fn handle_finality_vote(&self, vote: FinalityVote) -> Result<()> {
+        let (block_index, block_hash) = (vote.block_index, vote.block_hash.clone());
+        self.record_finality_vote(vote).await?;
+
+        let Some(keypair) = &self.authority_keypair else {
+            return Ok(());
+        };
+        let our_key = hex::encode(keypair.verifying_key().to_bytes());
+        let matches_our_chain = {
+            let blockchain = self.blockchain.read().await;
+            blockchain
+                .chain
+                .get(block_index as usize)
+                .is_some_and(|block| block.hash == block_hash)
+                && blockchain.finalized_index() < block_index
+        };
+        let already_voted =
+            self.finality
+                .read()
+                .await
+                .has_voted(block_index, &block_hash, &our_key);
+
+        if matches_our_chain && !already_voted {
+            self.cast_finality_vote(block_index, &block_hash).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Add a vote to the tracker and persist the certificate once a supermajority is reached
+    async fn record_finality_vote(&self, vote: FinalityVote) -> Result<()> {
+        let (validator_set, checkpoint_interval) = {
+            let blockchain = self.blockchain.read().await;
+            (
+                blockchain.governance.validator_set.clone(),
+                blockchain.governance.network_config.checkpoint_interval,
+            )
+        };
+        self.finality
+            .write()
+            .await
+            .set_checkpoint_interval(checkpoint_interval);
+        let certificate = self
+            .finality
+            .write()
+            .await
+            .add_vote(vote, &validator_set)
+            .map_err(|e| anyhow::anyhow!("Rejected finality vote: {}", e))?;
+
+        if let Some(certificate) = certificate {
+            let mut blockchain = self.blockchain.write().await;
+            blockchain
+                .record_finality_certificate(&certificate)
+                .map_err(|e| anyhow::anyhow!("Failed to record finality certificate: {}", e))?;
+            // Votes are kept for another attempt until the certificate is recorded
+            self.finality
+                .write()
+                .await
+                .set_finalized(certificate.block_index);
         }
 
-        info!("Successfully created and broadcast block {}", block.index);
         Ok(())
     }
 
@@ -351,32 +455,32 @@ impl ConsensusManager {
             .rdf_store
             .calculate_state_root();
 
-        let rdf_data = format!(
-            "<http://provchain.org/block/{}> <http://provchain.org/timestamp> \"{}\" .\n<http://provchain.org/block/{}> <http://provchain.org/authority> \"{}\" .",
+        let mut rdf_data = format!(
+            "<http://provchain.org/block/{}> <http://provchain.org/timestamp> \"{}\" .\n<http://provchain.org/block/{}> <http://provchain.org/authority> \"{}\" .\n",
             index,
             Utc::now().to_rfc3339(),
             index,
             self.network.node_id
         );
+        // Governance proposals and votes waiting for a block
+        rdf_data.push_str(&blockchain.governance.pending_block_data());
+
+        // The validator field carries the authority public key so the block signature
+        // can be checked by every node against the committed hash
+        let validator = match &self.authority_keypair {
+            Some(keypair) => hex::encode(keypair.verifying_key().to_bytes()),
+            None => self.network.node_id.to_string(),
+        };
 
         Ok(Block::new(
             index,
             rdf_data,
             previous_hash,
             state_root,
-            self.network.node_id.to_string(),
+            validator,
         ))
     }
 
-    /// Serialize block data for signing
-    fn serialize_block_for_signing(&self, block: &Block) -> Result<Vec<u8>> {
-        let data = format!(
-            "{}|{}|{}|{}",
-            block.index, block.timestamp, block.previous_hash, block.data
-        );
-        Ok(data.into_bytes())
-    }
-
     /// Broadcast block proposal to the network
     async fn broadcast_block_proposal(&self, proposal: BlockProposal) -> Result<()> {
         let announcement = P2PMessage::new_block_announcement(
@@ -396,8 +500,10 @@ impl ConsensusManager {
         );
 
         // Check if the authority is known and authorized
-        let authority_keys = self.authority_keys.read().await;
-        let is_known_authority = authority_keys
+        let is_known_authority = self
+            .authority_keys
+            .read()
+            .await
             .values()
             .any(|key| *key == proposal.authority_key);
 
@@ -406,11 +512,10 @@ impl ConsensusManager {
             return Ok(false);
         }
 
-        // Verify the signature
-        let block_data = self.serialize_block_for_signing(&proposal.block)?;
+        // Verify the signature over the committed block hash
         if proposal
             .authority_key
-            .verify(&block_data, &proposal.signature)
+            .verify(proposal.block.hash.as_bytes(), &proposal.signature)
             .is_err()
         {
             warn!("Invalid signature on block proposal");
@@ -427,6 +532,32 @@ impl ConsensusManager {
             return Ok(false);
         }
 
+        // The block must come from the leader of its slot, after the tip's slot
+        let schedule = self.rotation_schedule(&blockchain).await;
+        if !schedule.is_empty() {
+            let Some(slot) = schedule.slot_of(&proposal.block) else {
+                warn!("Block proposal has an unreadable timestamp");
+                return Ok(false);
+            };
+            let authority = hex::encode(proposal.authority_key.to_bytes());
+            if schedule.leader(slot) != Some(authority.as_str()) {
+                warn!(
+                    "Block proposal from an authority that does not lead slot {}",
+                    slot
+                );
+                return Ok(false);
+            }
+            if blockchain
+                .chain
+                .last()
+                .and_then(|tip| schedule.slot_of(tip))
+                .is_some_and(|tip_slot| tip_slot >= slot)
+            {
+                warn!("Block proposal for slot {} which already has a block", slot);
+                return Ok(false);
+            }
+        }
+
         // Check timing constraints
         if !self.validate_block_timing(proposal).await? {
             warn!("Block proposal violates timing constraints");
@@ -458,9 +589,16 @@ impl ConsensusManager {
             return Ok(false);
         }
 
-        // Validate block hash by recalculating it
-        let expected_hash = block.calculate_hash_with_store(Some(&blockchain.rdf_store));
-        if block.hash != expected_hash {
+        // Validate the committed data hash and the header hash derived from it
+        if !block.verify_data_hash() || block.hash != block.calculate_hash() {
+            return Ok(false);
+        }
+
+        // Transactions must not replay a signer's nonce or spend an output twice
+        let mut ledger = self.ledger.write().await;
+        ledger.sync(&blockchain.chain)?;
+        if let Err(e) = ledger.validate_block_data(&block.data) {
+            warn!("Block {} has invalid transactions: {}", block.index, e);
             return Ok(false);
         }
 
@@ -469,6 +607,7 @@ impl ConsensusManager {
 
     /// Validate block timing constraints
     async fn validate_block_timing(&self, proposal: &BlockProposal) -> Result<bool> {
+        let block_interval = self.block_interval().await;
         let authority_state = self.authority_state.read().await;
 
         // Parse the timestamp from the block
@@ -486,7 +625,7 @@ impl ConsensusManager {
 
         // Check if enough time has passed since the last block
         let time_since_last = block_time.signed_duration_since(authority_state.last_block_time);
-        if time_since_last < Duration::seconds(self.config.block_interval as i64 / 2) {
+        if time_since_last < Duration::seconds(block_interval as i64 / 2) {
             return Ok(false);
         }
 
@@ -513,35 +652,75 @@ impl ConsensusManager {
     /// Update authority performance metrics
     async fn update_authority_performance(&self) -> Result<()> {
         debug!("Updating authority performance metrics");
+        self.refresh_authority_state().await;
+        Ok(())
+    }
 
-        let mut authority_state = self.authority_state.write().await;
-        let now = Utc::now();
+    /// Block interval from the governed network configuration
+    async fn block_interval(&self) -> u64 {
+        self.blockchain
+            .read()
+            .await
+            .governance
+            .network_config
+            .block_interval
+    }
 
-        // Update performance for all known authorities
-        let authority_keys = self.authority_keys.read().await;
-        for authority_id in authority_keys.keys() {
-            let performance = authority_state
-                .authority_performance
-                .entry(*authority_id)
-                .or_insert_with(|| AuthorityPerformance {
-                    blocks_created: 0,
-                    missed_slots: 0,
-                    last_activity: now,
-                    reputation: 1.0,
-                });
+    /// Queue a governance proposal for the next block and broadcast its transaction to
+    /// peers, so whichever authority creates the next block includes it
+    pub async fn submit_governance_proposal(&self, tx: Transaction) -> Result<Proposal> {
+        let proposal = self
+            .blockchain
+            .write()
+            .await
+            .submit_governance_proposal(&tx)?;
+        self.network
+            .broadcast_message(P2PMessage::GovernanceProposal {
+                transaction: Box::new(tx),
+            })
+            .await?;
+        Ok(proposal)
+    }
 
-            // Calculate reputation based on performance
-            let total_slots = performance.blocks_created + performance.missed_slots;
-            if total_slots > 0 {
-                performance.reputation = performance.blocks_created as f64 / total_slots as f64;
-            }
+    /// Queue a validator's vote for the next block and broadcast it to peers
+    pub async fn vote_on_proposal(&self, vote: ProposalVote) -> Result<()> {
+        self.blockchain
+            .write()
+            .await
+            .vote_on_proposal(vote.clone())?;
+        self.network
+            .broadcast_message(P2PMessage::GovernanceVote { vote })
+            .await?;
+        Ok(())
+    }
+
+    /// Queue a proposal received from a peer, unless it is already known
+    pub async fn handle_governance_proposal(&self, tx: Transaction) -> Result<()> {
+        let mut blockchain = self.blockchain.write().await;
+        if blockchain.governance.knows_proposal(&tx.id) {
+            return Ok(());
         }
+        blockchain.submit_governance_proposal(&tx)?;
+        info!("Queued governance proposal {} from a peer", tx.id);
+        Ok(())
+    }
 
+    /// Queue a vote received from a peer, unless it is already counted or queued
+    pub async fn handle_governance_vote(&self, vote: ProposalVote) -> Result<()> {
+        let mut blockchain = self.blockchain.write().await;
+        if blockchain
+            .governance
+            .has_vote(&vote.proposal_id, &vote.validator)
+        {
+            return Ok(());
+        }
+        blockchain.vote_on_proposal(vote)?;
         Ok(())
     }
 
     /// Get consensus statistics
     pub async fn get_consensus_stats(&self) -> ConsensusStats {
+        let block_interval = self.block_interval().await;
         let authority_state = self.authority_state.read().await;
         let authority_keys = self.authority_keys.read().await;
 
@@ -550,13 +729,14 @@ impl ConsensusManager {
             current_round: authority_state.current_round,
             total_authorities: authority_keys.len(),
             last_block_time: authority_state.last_block_time,
-            block_interval: self.config.block_interval,
+            block_interval,
             authority_performance: authority_state.authority_performance.clone(),
         }
     }
 
-    /// Add a new authority to the network
-    pub async fn add_authority(&self, authority_id: Uuid, public_key: VerifyingKey) -> Result<()> {
+    /// Add a new authority to the network. Returns the ID derived from its key.
+    pub async fn add_authority(&self, public_key: VerifyingKey) -> Result<Uuid> {
+        let authority_id = authority_id(&public_key);
         info!("Adding new authority: {}", authority_id);
 
         let mut authority_keys = self.authority_keys.write().await;
@@ -573,7 +753,7 @@ impl ConsensusManager {
             },
         );
 
-        Ok(())
+        Ok(authority_id)
     }
 
     /// Remove an authority from the network
@@ -600,6 +780,8 @@ impl Clone for ConsensusManager {
             network: Arc::clone(&self.network),
             blockchain: Arc::clone(&self.blockchain),
             authority_state: Arc::clone(&self.authority_state),
+            finality: Arc::clone(&self.finality),
+            ledger: Arc::clone(&self.ledger),
         }
     }
 }
@@ -623,6 +805,33 @@ impl MessageHandler for ConsensusManager {
                 });
                 Ok(None)
             }
+            P2PMessage::FinalityVote { vote } => {
+                let manager = self.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = manager.handle_finality_vote(vote).await {
+                        warn!("Failed to process finality vote: {}", e);
+                    }
+                });
+                Ok(None)
+            }
+            P2PMessage::GovernanceProposal { transaction } => {
+                let manager = self.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = manager.handle_governance_proposal(*transaction).await {
+                        warn!("Failed to open governance proposal: {}", e);
+                    }
+                });
+                Ok(None)
+            }
+            P2PMessage::GovernanceVote { vote } => {
+                let manager = self.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = manager.handle_governance_vote(vote).await {
+                        warn!("Failed to count governance vote: {}", e);
+                    }
+                });
+                Ok(None)
+            }
             _ => Ok(None),
         }
     }
@@ -642,6 +851,7 @@ pub struct ConsensusStats {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::governance::ProposalStatus;
     use crate::utils::config::NodeConfig;
 
     #[tokio::test]
@@ -680,6 +890,163 @@ mod tests {
         assert!(!block.hash.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_single_authority_finalizes_checkpoint() {
+        let config = ConsensusConfig {
+            is_authority: true,
+            checkpoint_interval: 1,
+            ..ConsensusConfig::default()
+        };
+
+        let network = Arc::new(NetworkManager::new(NodeConfig::default()));
+        let blockchain = Arc::new(RwLock::new(Blockchain::new()));
+        let consensus = ConsensusManager::new(config, network, blockchain.clone())
+            .await
+            .unwrap();
+
+        let validator = hex::encode(
+            consensus
+                .authority_keypair
+                .as_ref()
+                .unwrap()
+                .verifying_key()
+                .to_bytes(),
+        );
+        blockchain
+            .write()
+            .await
+            .governance
+            .validator_set
+            .insert(validator);
+
+        consensus.create_and_propose_block().await.unwrap();
+
+        let blockchain = blockchain.read().await;
+        assert_eq!(blockchain.chain.len(), 2);
+        assert_eq!(blockchain.finalized_index(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_governed_config_reaches_consensus_and_finality() {
+        let config = ConsensusConfig {
+            is_authority: true,
+            checkpoint_interval: 1,
+            ..ConsensusConfig::default()
+        };
+        let network = Arc::new(NetworkManager::new(NodeConfig::default()));
+        let blockchain = Arc::new(RwLock::new(Blockchain::new()));
+        let consensus = ConsensusManager::new(config, network, blockchain.clone())
+            .await
+            .unwrap();
+        let keypair = consensus.authority_keypair.clone().unwrap();
+        blockchain
+            .write()
+            .await
+            .governance
+            .validator_set
+            .insert(hex::encode(keypair.verifying_key().to_bytes()));
+
+        let tx = blockchain
+            .read()
+            .await
+            .governance
+            .create_update_configuration_tx(
+                "checkpoint_interval".to_string(),
+                "5".to_string(),
+                vec![(&keypair, Uuid::new_v4())],
+            )
+            .unwrap();
+        let proposal = consensus.submit_governance_proposal(tx).await.unwrap();
+        let vote = ProposalVote::sign(&proposal, &keypair);
+        consensus.vote_on_proposal(vote.clone()).await.unwrap();
+
+        // Nothing changes until a block records the proposal and the vote
+        assert!(blockchain.read().await.governance.proposals.is_empty());
+        assert_eq!(consensus.finality.read().await.checkpoint_interval, 1);
+
+        consensus.create_and_propose_block().await.unwrap();
+        {
+            let blockchain = blockchain.read().await;
+            let proposal = &blockchain.governance.proposals[&proposal.id];
+            assert_eq!(proposal.status, ProposalStatus::Executed);
+            assert_eq!(proposal.opened_at_block, 1);
+            assert_eq!(proposal.votes[0].block_index, 1);
+            assert!(blockchain.governance.pending.is_empty());
+        }
+        assert_eq!(consensus.finality.read().await.checkpoint_interval, 5);
+
+        // The same vote arriving back from a peer is not queued or counted again
+        consensus.handle_governance_vote(vote).await.unwrap();
+        let blockchain = blockchain.read().await;
+        assert!(blockchain.governance.pending.is_empty());
+        assert_eq!(blockchain.governance.proposals[&proposal.id].votes.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_authority_ids_are_derived_from_keys() {
+        let keys: Vec<String> = (1..=3u8)
+            .map(|i| hex::encode(SigningKey::from_bytes(&[i; 32]).verifying_key().to_bytes()))
+            .collect();
+        let blockchain = Arc::new(RwLock::new(Blockchain::new()));
+
+        let mut orders = Vec::new();
+        for authority_keys in [keys.clone(), keys.iter().rev().cloned().collect()] {
+            let config = ConsensusConfig {
+                authority_keys,
+                ..ConsensusConfig::default()
+            };
+            let network = Arc::new(NetworkManager::new(NodeConfig::default()));
+            let consensus = ConsensusManager::new(config, network, blockchain.clone())
+                .await
+                .unwrap();
+            consensus.refresh_authority_state().await;
+
+            let state = consensus.authority_state.read().await;
+            orders.push(state.authority_rotation_order.clone());
+        }
+
+        assert_eq!(orders[0].len(), 3);
+        assert_eq!(orders[0], orders[1]);
+    }
+
+    #[tokio::test]
+    async fn test_only_slot_leader_creates_block() {
+        let signing_keys: Vec<SigningKey> = (1..=2u8)
+            .map(|i| SigningKey::from_bytes(&[i; 32]))
+            .collect();
+        let blockchain = Arc::new(RwLock::new(Blockchain::new()));
+        {
+            let mut blockchain = blockchain.write().await;
+            // Start the epoch half-way into the previous slot so the test runs mid-slot
+            blockchain.chain[0].timestamp = (Utc::now() - Duration::seconds(5400)).to_rfc3339();
+            blockchain.governance.validator_set.extend(
+                signing_keys
+                    .iter()
+                    .map(|key| hex::encode(key.verifying_key().to_bytes())),
+            );
+        }
+
+        let mut leaders = 0;
+        for key in &signing_keys {
+            let config = ConsensusConfig {
+                is_authority: true,
+                block_interval: 3600,
+                ..ConsensusConfig::default()
+            };
+            let network = Arc::new(NetworkManager::new(NodeConfig::default()));
+            let mut consensus = ConsensusManager::new(config, network, blockchain.clone())
+                .await
+                .unwrap();
+            consensus.authority_keypair = Some(key.clone());
+
+            if consensus.should_create_block().await.unwrap() {
+                leaders += 1;
+            }
+        }
+
+        assert_eq!(leaders, 1, "exactly one authority leads each slot");
+    }
+
     #[test]
     fn test_keypair_generation() {
         let keypair = ConsensusManager::load_or_generate_keypair(&None).unwrap();
@@ -12,8 +12,10 @@ use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
+use super::codec::{negotiate, SUPPORTED_PROTOCOL_VERSIONS};
 use super::messages::{P2PMessage, PeerInfo};
 use super::peer::PeerClient;
+use super::transport::HandshakeConfig;
 
 /// Peer discovery manager
 pub struct PeerDiscovery {
@@ -23,6 +25,8 @@ pub struct PeerDiscovery {
     pub bootstrap_peers: Vec<String>,
     /// This node's information
     pub local_node_info: PeerInfo,
+    /// Node key and allow-list used when connecting to peers
+    pub handshake: Arc<HandshakeConfig>,
 }
 
 impl PeerDiscovery {
@@ -31,10 +35,17 @@ impl PeerDiscovery {
         Self {
             known_peers: Arc::new(RwLock::new(HashMap::new())),
             bootstrap_peers,
+            handshake: Arc::new(HandshakeConfig::ephemeral(local_node_info.clone())),
             local_node_info,
         }
     }
 
+    /// Use the node's handshake identity instead of an ephemeral one
+    pub fn with_handshake(mut self, handshake: Arc<HandshakeConfig>) -> Self {
+        self.handshake = handshake;
+        self
+    }
+
     /// Start peer discovery process
     pub async fn start_discovery(&self) -> Result<()> {
         info!(
@@ -85,7 +96,8 @@ impl PeerDiscovery {
             // Handle discovery messages here
         });
 
-        let _connection = PeerClient::connect(peer_address, message_handler).await?;
+        let _connection =
+            PeerClient::connect(peer_address, &self.handshake, message_handler).await?;
 
         // Send peer discovery message
         let discovery_message = P2PMessage::new_peer_discovery(
@@ -209,6 +221,7 @@ impl PeerDiscovery {
                 node_id,
                 listen_port,
                 network_id,
+                protocol_versions,
                 ..
             } => {
                 // Validate network ID
@@ -220,6 +233,23 @@ impl PeerDiscovery {
                     )));
                 }
 
+                // Agree on the wire protocol; peers that advertise none only speak JSON
+                let Some(protocol_version) =
+                    negotiate(SUPPORTED_PROTOCOL_VERSIONS, &protocol_versions)
+                else {
+                    warn!(
+                        "Peer {} supports no common protocol version: {:?}",
+                        node_id, protocol_versions
+                    );
+                    return Ok(Some(P2PMessage::new_error(
+                        super::messages::ErrorCode::InvalidMessage,
+                        format!(
+                            "No common protocol version, supported: {:?}",
+                            SUPPORTED_PROTOCOL_VERSIONS
+                        ),
+                    )));
+                };
+
                 // Add peer to known peers
                 let peer_info = PeerInfo::new(
                     node_id,
@@ -236,6 +266,7 @@ impl PeerDiscovery {
                 Ok(Some(P2PMessage::PeerList {
                     peers: known_peers,
                     timestamp: chrono::Utc::now(),
+                    protocol_version: Some(protocol_version),
                 }))
             }
             P2PMessage::PeerList { peers, .. } => {
@@ -360,4 +391,43 @@ mod tests {
         assert_eq!(stats.regular_peers, 1);
         assert_eq!(stats.bootstrap_peers, 1);
     }
+
+    #[tokio::test]
+    async fn test_discovery_negotiates_protocol_version() {
+        let local_info = PeerInfo::new(
+            Uuid::new_v4(),
+            "127.0.0.1".to_string(),
+            8080,
+            "test-network".to_string(),
+            false,
+        );
+        let discovery = PeerDiscovery::new(local_info, vec![]);
+
+        let discovery_message = |protocol_versions: Vec<u16>| P2PMessage::PeerDiscovery {
+            node_id: Uuid::new_v4(),
+            listen_port: 8081,
+            network_id: "test-network".to_string(),
+            timestamp: chrono::Utc::now(),
+            protocol_versions,
+        };
+
+        for (advertised, expected) in [(vec![1, 2], 2), (vec![], 1)] {
+            match discovery
+                .handle_peer_discovery(discovery_message(advertised))
+                .await
+                .unwrap()
+            {
+                Some(P2PMessage::PeerList {
+                    protocol_version, ..
+                }) => assert_eq!(protocol_version, Some(expected)),
+                other => panic!("Expected PeerList, got {:?}", other),
+            }
+        }
+
+        let response = discovery
+            .handle_peer_discovery(discovery_message(vec![9]))
+            .await
+            .unwrap();
+        assert!(matches!(response, Some(P2PMessage::Error { .. })));
+    }
 }
@@ -4,11 +4,65 @@
 //! GraphChain nodes, including blockchain synchronization, peer discovery,
 //! and RDF graph exchange.
 
-use crate::core::blockchain::Block;
+use crate::core::blockchain::{Block, BlockHeader};
+use crate::core::finality::FinalityVote;
+use crate::core::snapshot::StateSnapshot;
+use crate::governance::ProposalVote;
+use crate::transaction::transaction::Transaction;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::codec::{self, SUPPORTED_PROTOCOL_VERSIONS};
+
+/// Serialized RDF graph carried in a message.
+///
+/// Encoded as base64 text in JSON (protocol version 1) and as raw bytes in the binary
+/// encoding, so graph transfers do not pay the base64 overhead on upgraded links.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphData(pub Vec<u8>);
+
+impl Serialize for GraphData {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&BASE64.encode(&self.0))
+        } else {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for GraphData {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct GraphDataVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for GraphDataVisitor {
+            type Value = GraphData;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("base64 text or raw bytes")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<GraphData, E> {
+                BASE64.decode(value).map(GraphData).map_err(E::custom)
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, value: &[u8]) -> Result<GraphData, E> {
+                Ok(GraphData(value.to_vec()))
+            }
+
+            fn visit_byte_buf<E: serde::de::Error>(self, value: Vec<u8>) -> Result<GraphData, E> {
+                Ok(GraphData(value))
+            }
+        }
+
+        // Messages are internally tagged, which hides the format from nested values, so
+        // accept whichever representation arrives
+        deserializer.deserialize_any(GraphDataVisitor)
+    }
+}
+
 /// All possible P2P messages exchanged between GraphChain nodes
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -19,12 +73,19 @@ pub enum P2PMessage {
         listen_port: u16,
         network_id: String,
         timestamp: DateTime<Utc>,
+        /// Wire protocol versions the sender supports; empty for nodes that predate
+        /// versioned framing
+        #[serde(default)]
+        protocol_versions: Vec<u16>,
     },
 
     /// Response to peer discovery with peer list
     PeerList {
         peers: Vec<PeerInfo>,
         timestamp: DateTime<Utc>,
+        /// Wire protocol version chosen for the connection, if the responder negotiated one
+        #[serde(default)]
+        protocol_version: Option<u16>,
     },
 
     /// Announce a new block to the network
@@ -48,6 +109,38 @@ pub enum P2PMessage {
         requester_id: Uuid,
     },
 
+    /// Request the headers of blocks `from_index..=to_index`
+    HeadersRequest {
+        from_index: u64,
+        to_index: u64,
+        requester_id: Uuid,
+    },
+
+    /// Response with block headers (no RDF data) for light clients
+    HeadersResponse {
+        headers: Vec<BlockHeader>,
+        requester_id: Uuid,
+    },
+
+    /// Request a state snapshot at the peer's last finalized checkpoint
+    SnapshotRequest { requester_id: Uuid },
+
+    /// Signed state snapshot for fast sync, or `None` if the peer has no finalized
+    /// checkpoint
+    SnapshotResponse {
+        snapshot: Option<Box<StateSnapshot>>,
+        requester_id: Uuid,
+    },
+
+    /// Authority co-signature on a finality checkpoint
+    FinalityVote { vote: FinalityVote },
+
+    /// Governance transaction opening a proposal on every node
+    GovernanceProposal { transaction: Box<Transaction> },
+
+    /// Validator approval of an open governance proposal
+    GovernanceVote { vote: ProposalVote },
+
     /// Request RDF graph data for a specific URI
     GraphRequest {
         graph_uri: String,
@@ -57,7 +150,7 @@ pub enum P2PMessage {
     /// Response with RDF graph data
     GraphResponse {
         graph_uri: String,
-        rdf_data: Option<String>, // Turtle format, compressed and base64 encoded
+        rdf_data: Option<GraphData>, // Turtle format
         requester_id: Uuid,
     },
 
@@ -123,6 +216,7 @@ impl P2PMessage {
             listen_port,
             network_id,
             timestamp: Utc::now(),
+            protocol_versions: SUPPORTED_PROTOCOL_VERSIONS.to_vec(),
         }
     }
 
@@ -145,6 +239,15 @@ impl P2PMessage {
         }
     }
 
+    /// Create a new headers request
+    pub fn new_headers_request(from_index: u64, to_index: u64, requester_id: Uuid) -> Self {
+        Self::HeadersRequest {
+            from_index,
+            to_index,
+            requester_id,
+        }
+    }
+
     /// Create a new graph request
     pub fn new_graph_request(graph_uri: String, requester_id: Uuid) -> Self {
         Self::GraphRequest {
@@ -187,6 +290,13 @@ impl P2PMessage {
             Self::BlockAnnouncement { .. } => "BlockAnnouncement",
             Self::BlockRequest { .. } => "BlockRequest",
             Self::BlockResponse { .. } => "BlockResponse",
+            Self::HeadersRequest { .. } => "HeadersRequest",
+            Self::HeadersResponse { .. } => "HeadersResponse",
+            Self::SnapshotRequest { .. } => "SnapshotRequest",
+            Self::SnapshotResponse { .. } => "SnapshotResponse",
+            Self::FinalityVote { .. } => "FinalityVote",
+            Self::GovernanceProposal { .. } => "GovernanceProposal",
+            Self::GovernanceVote { .. } => "GovernanceVote",
             Self::GraphRequest { .. } => "GraphRequest",
             Self::GraphResponse { .. } => "GraphResponse",
             Self::ChainStatusRequest { .. } => "ChainStatusRequest",
@@ -197,7 +307,17 @@ impl P2PMessage {
         }
     }
 
-    /// Serialize message to JSON bytes for network transmission
+    /// Encode the message for the given wire protocol version
+    pub fn encode(&self, protocol_version: u16) -> anyhow::Result<Vec<u8>> {
+        codec::encode(self, protocol_version)
+    }
+
+    /// Decode a message in any supported wire encoding, with the protocol version it used
+    pub fn decode(bytes: &[u8]) -> anyhow::Result<(Self, u16)> {
+        codec::decode(bytes)
+    }
+
+    /// Serialize message to JSON bytes (protocol version 1)
     pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
         let json = serde_json::to_string(self)?;
         Ok(json.into_bytes())
@@ -224,6 +344,16 @@ impl P2PMessage {
                     anyhow::bail!("Invalid block index");
                 }
             }
+            Self::HeadersRequest {
+                from_index,
+                to_index,
+                ..
+            } if from_index > to_index => {
+                anyhow::bail!("Invalid header range");
+            }
+            Self::FinalityVote { vote } if vote.block_hash.is_empty() => {
+                anyhow::bail!("Finality vote must name a block hash");
+            }
             Self::GraphRequest { graph_uri, .. } => {
                 if graph_uri.is_empty() {
                     anyhow::bail!("Graph URI cannot be empty");
@@ -285,6 +415,22 @@ mod tests {
         assert_eq!(message.message_type(), deserialized.message_type());
     }
 
+    #[test]
+    fn test_legacy_discovery_message_decodes() {
+        // Sent by a node that predates protocol version negotiation
+        let legacy = format!(
+            r#"{{"type":"PeerDiscovery","node_id":"{}","listen_port":8080,"network_id":"test-network","timestamp":"2025-01-01T00:00:00Z"}}"#,
+            Uuid::new_v4()
+        );
+
+        match P2PMessage::from_bytes(legacy.as_bytes()).unwrap() {
+            P2PMessage::PeerDiscovery {
+                protocol_versions, ..
+            } => assert!(protocol_versions.is_empty()),
+            other => panic!("Unexpected message {:?}", other),
+        }
+    }
+
     #[test]
     fn test_message_validation() {
         let node_id = Uuid::new_v4();
@@ -300,10 +446,23 @@ mod tests {
             listen_port: 8080,
             network_id: String::new(),
             timestamp: Utc::now(),
+            protocol_versions: SUPPORTED_PROTOCOL_VERSIONS.to_vec(),
         };
         assert!(invalid_message.validate().is_err());
     }
 
+    #[test]
+    fn test_headers_request_validation() {
+        let node_id = Uuid::new_v4();
+
+        let request = P2PMessage::new_headers_request(0, 10, node_id);
+        assert!(request.validate().is_ok());
+        assert_eq!(request.message_type(), "HeadersRequest");
+
+        let inverted = P2PMessage::new_headers_request(10, 0, node_id);
+        assert!(inverted.validate().is_err());
+    }
+
     #[test]
     fn test_peer_info() {
         let node_id = Uuid::new_v4();
@@ -3,23 +3,29 @@
 //! This module provides P2P networking capabilities including:
 //! - Peer discovery and connection management
 //! - Message protocol for blockchain synchronization
+//! - Versioned wire encoding (JSON and compressed binary frames)
 //! - WebSocket-based communication between nodes
+//! - Authenticated, encrypted peer transport
 //! - Blockchain synchronization and consensus
 
+pub mod codec;
 pub mod consensus;
 pub mod discovery;
 pub mod messages;
 pub mod peer;
+pub mod schedule;
 pub mod sync;
+pub mod transport;
 
 use anyhow::Result;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
 use self::messages::{P2PMessage, PeerInfo};
 use self::peer::PeerConnection;
+use self::transport::{load_or_generate_node_key, HandshakeConfig};
 use crate::utils::config::NodeConfig;
 
 /// Network manager for handling all P2P operations
@@ -34,6 +40,8 @@ pub struct NetworkManager {
     pub message_handlers: Arc<RwLock<Vec<Box<dyn MessageHandler + Send + Sync>>>>,
     /// Channel sender for incoming messages
     pub message_sender: tokio::sync::mpsc::Sender<(Uuid, P2PMessage)>,
+    /// Node key and peer allow-list used to authenticate every connection
+    pub handshake: Arc<HandshakeConfig>,
 }
 
 /// Trait for handling incoming network messages
@@ -65,15 +73,58 @@ impl NetworkManager {
             }
         });
 
+        let handshake = Arc::new(Self::handshake_config(&config));
+
         Self {
-            node_id: config.node_id,
+            // Derived from the node key, which is what peers check it against
+            node_id: handshake.local_info.node_id,
             config,
             peers: Arc::new(RwLock::new(HashMap::new())),
             message_handlers: handlers,
             message_sender: tx,
+            handshake,
         }
     }
 
+    /// Build the handshake identity and allow-list from the node configuration.
+    ///
+    /// Authorities use their authority key as node key so peers can match it against the
+    /// validator set. Configured authority keys stand in for the validator set until
+    /// consensus publishes the on-chain one.
+    fn handshake_config(config: &NodeConfig) -> HandshakeConfig {
+        let key_file = config
+            .network
+            .node_key_file
+            .clone()
+            .or_else(|| config.consensus.authority_key_file.clone());
+        let signing_key = load_or_generate_node_key(&key_file).unwrap_or_else(|e| {
+            tracing::warn!("Failed to load node key, using an ephemeral key: {}", e);
+            ed25519_dalek::SigningKey::from_bytes(&rand::random::<[u8; 32]>())
+        });
+
+        let local_info = PeerInfo::new(
+            config.node_id,
+            config.network.bind_address.clone(),
+            config.network.listen_port,
+            config.network.network_id.clone(),
+            config.consensus.is_authority,
+        );
+
+        let mut handshake = HandshakeConfig::new(signing_key, local_info);
+        handshake.allow_list = transport::PeerAllowList::new(
+            config.consensus.authority_keys.iter().cloned().collect(),
+        );
+        handshake.allow_list.trusted_keys =
+            config.network.trusted_peer_keys.iter().cloned().collect();
+        handshake.allow_list.allow_observers = config.network.allow_observers;
+        handshake
+    }
+
+    /// Replace the validator set used to admit peers claiming to be authorities
+    pub async fn set_validator_set(&self, validator_set: HashSet<String>) {
+        *self.handshake.allow_list.validator_set.write().await = validator_set;
+    }
+
     /// Start the network manager (listen for connections and connect to peers)
     pub async fn start(&self) -> Result<()> {
         tracing::info!("Starting network manager for node {}", self.node_id);
@@ -110,6 +161,7 @@ impl NetworkManager {
 
         let server = crate::network::peer::PeerServer::new(
             &listen_addr,
+            Arc::clone(&self.handshake),
             message_handler,
             connection_handler,
         )
@@ -133,13 +185,32 @@ impl NetworkManager {
             let message_handler = self.create_message_handler();
             let peer_addr_clone = peer_addr.clone();
             let peers = Arc::clone(&self.peers);
+            let handshake = Arc::clone(&self.handshake);
 
             tokio::spawn(async move {
-                match crate::network::peer::PeerClient::connect(&peer_addr_clone, message_handler)
-                    .await
+                match crate::network::peer::PeerClient::connect(
+                    &peer_addr_clone,
+                    &handshake,
+                    message_handler,
+                )
+                .await
                 {
                     Ok(connection) => {
                         tracing::info!("Successfully connected to peer {}", peer_addr_clone);
+                        // Advertise our protocol versions so the link can upgrade from JSON
+                        let local = &handshake.local_info;
+                        let discovery = P2PMessage::new_peer_discovery(
+                            local.node_id,
+                            local.port,
+                            local.network_id.clone(),
+                        );
+                        if let Err(e) = connection.send_message(discovery).await {
+                            tracing::warn!(
+                                "Failed to send discovery to {}: {}",
+                                peer_addr_clone,
+                                e
+                            );
+                        }
                         let peer_id = connection.info.node_id;
                         peers.write().await.insert(peer_id, connection);
                     }
@@ -2,7 +2,8 @@
 //!
 //! This module handles individual peer connections using WebSockets,
 //! including connection lifecycle, message sending/receiving, and
-//! connection health monitoring.
+//! connection health monitoring. Every connection is authenticated and
+//! encrypted by the handshake in `transport` before messages flow.
 
 use anyhow::Result;
 use futures_util::{stream::SplitSink, SinkExt, StreamExt};
@@ -16,11 +17,14 @@ use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
 use super::messages::{P2PMessage, PeerInfo};
+use super::transport::{handshake, HandshakeConfig, HandshakeRole, SecureSession};
 
 /// Represents a connection to a peer node
 pub struct PeerConnection {
-    /// Information about the peer
+    /// Information about the peer, as authenticated during the handshake
     pub info: PeerInfo,
+    /// Hex-encoded Ed25519 node key the peer proved possession of
+    pub public_key: String,
     /// Channel for sending messages to the peer
     pub sender: mpsc::UnboundedSender<P2PMessage>,
     /// Handle to the connection task
@@ -34,47 +38,55 @@ pub enum WebSocketConnection {
 }
 
 impl PeerConnection {
-    /// Create a new peer connection from an outgoing WebSocket connection
+    /// Create a new peer connection from an outgoing WebSocket connection that completed
+    /// the handshake
     pub async fn new_outgoing(
-        peer_info: PeerInfo,
+        session: SecureSession,
         ws_stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
         message_handler: Arc<dyn Fn(Uuid, P2PMessage) + Send + Sync>,
     ) -> Result<Self> {
         let (sender, receiver) = mpsc::unbounded_channel();
         let connection = WebSocketConnection::Client(ws_stream);
+        let info = session.peer.clone();
+        let public_key = session.peer_public_key.clone();
 
         let task_handle = tokio::spawn(Self::connection_task(
-            peer_info.node_id,
+            session,
             connection,
             receiver,
             message_handler,
         ));
 
         Ok(Self {
-            info: peer_info,
+            info,
+            public_key,
             sender,
             task_handle,
         })
     }
 
-    /// Create a new peer connection from an incoming WebSocket connection
+    /// Create a new peer connection from an incoming WebSocket connection that completed
+    /// the handshake
     pub async fn new_incoming(
-        peer_info: PeerInfo,
+        session: SecureSession,
         ws_stream: WebSocketStream<TcpStream>,
         message_handler: Arc<dyn Fn(Uuid, P2PMessage) + Send + Sync>,
     ) -> Result<Self> {
         let (sender, receiver) = mpsc::unbounded_channel();
         let connection = WebSocketConnection::Server(ws_stream);
+        let info = session.peer.clone();
+        let public_key = session.peer_public_key.clone();
 
         let task_handle = tokio::spawn(Self::connection_task(
-            peer_info.node_id,
+            session,
             connection,
             receiver,
             message_handler,
         ));
 
         Ok(Self {
-            info: peer_info,
+            info,
+            public_key,
             sender,
             task_handle,
         })
@@ -90,19 +102,20 @@ impl PeerConnection {
 
     /// Main connection task that handles message sending and receiving
     async fn connection_task(
-        peer_id: Uuid,
+        session: SecureSession,
         connection: WebSocketConnection,
         message_receiver: mpsc::UnboundedReceiver<P2PMessage>,
         message_handler: Arc<dyn Fn(Uuid, P2PMessage) + Send + Sync>,
     ) {
+        let peer_id = session.peer.node_id;
         debug!("Starting connection task for peer {}", peer_id);
 
         match connection {
             WebSocketConnection::Client(ws) => {
-                Self::handle_connection_loop(peer_id, ws, message_receiver, message_handler).await;
+                Self::handle_connection_loop(session, ws, message_receiver, message_handler).await;
             }
             WebSocketConnection::Server(ws) => {
-                Self::handle_connection_loop(peer_id, ws, message_receiver, message_handler).await;
+                Self::handle_connection_loop(session, ws, message_receiver, message_handler).await;
             }
         }
 
@@ -111,13 +124,14 @@ impl PeerConnection {
 
     /// Generic connection loop that works with any WebSocket stream type
     async fn handle_connection_loop<S>(
-        peer_id: Uuid,
+        mut session: SecureSession,
         ws: WebSocketStream<S>,
         mut message_receiver: mpsc::UnboundedReceiver<P2PMessage>,
         message_handler: Arc<dyn Fn(Uuid, P2PMessage) + Send + Sync>,
     ) where
         S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
     {
+        let peer_id = session.peer.node_id;
         let (mut ws_sender, mut ws_receiver) = ws.split();
 
         loop {
@@ -126,7 +140,7 @@ impl PeerConnection {
                 message = message_receiver.recv() => {
                     match message {
                         Some(msg) => {
-                            if let Err(e) = Self::send_websocket_message_generic(&mut ws_sender, msg).await {
+                            if let Err(e) = Self::send_websocket_message_generic(&mut ws_sender, &mut session, msg).await {
                                 error!("Failed to send message to peer {}: {}", peer_id, e);
                                 break;
                             }
@@ -141,25 +155,20 @@ impl PeerConnection {
                 // Handle incoming messages
                 ws_message = ws_receiver.next() => {
                     match ws_message {
-                        Some(Ok(Message::Text(text))) => {
-                            match P2PMessage::from_bytes(text.as_bytes()) {
-                                Ok(message) => {
-                                    debug!("Received {} from peer {}", message.message_type(), peer_id);
-                                    message_handler(peer_id, message);
-                                }
-                                Err(e) => {
-                                    warn!("Failed to parse message from peer {}: {}", peer_id, e);
-                                }
-                            }
+                        Some(Ok(Message::Text(_))) => {
+                            warn!("Dropping unencrypted frame from peer {}", peer_id);
                         }
                         Some(Ok(Message::Binary(data))) => {
-                            match P2PMessage::from_bytes(&data) {
+                            match session.open_message(&data) {
                                 Ok(message) => {
                                     debug!("Received {} from peer {}", message.message_type(), peer_id);
                                     message_handler(peer_id, message);
                                 }
                                 Err(e) => {
-                                    warn!("Failed to parse binary message from peer {}: {}", peer_id, e);
+                                    // A frame that fails authentication desynchronizes the
+                                    // session, so the connection cannot continue
+                                    error!("Rejected frame from peer {}: {}", peer_id, e);
+                                    break;
                                 }
                             }
                         }
@@ -194,16 +203,16 @@ impl PeerConnection {
         }
     }
 
-    /// Send a P2P message over WebSocket (generic version)
+    /// Encrypt a P2P message and send it over WebSocket (generic version)
     async fn send_websocket_message_generic<S>(
         ws_sender: &mut SplitSink<WebSocketStream<S>, Message>,
+        session: &mut SecureSession,
         message: P2PMessage,
     ) -> Result<()>
     where
         S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
     {
-        let bytes = message.to_bytes()?;
-        let ws_message = Message::Text(String::from_utf8(bytes)?);
+        let ws_message = session.seal_message(&message)?;
         ws_sender.send(ws_message).await?;
         Ok(())
     }
@@ -212,6 +221,7 @@ impl PeerConnection {
 /// WebSocket server for accepting incoming peer connections
 pub struct PeerServer {
     listener: TcpListener,
+    handshake: Arc<HandshakeConfig>,
     message_handler: Arc<dyn Fn(Uuid, P2PMessage) + Send + Sync>,
     connection_handler: Arc<dyn Fn(PeerConnection) + Send + Sync>,
 }
@@ -220,6 +230,7 @@ impl PeerServer {
     /// Create a new peer server
     pub async fn new(
         listen_addr: &str,
+        handshake: Arc<HandshakeConfig>,
         message_handler: Arc<dyn Fn(Uuid, P2PMessage) + Send + Sync>,
         connection_handler: Arc<dyn Fn(PeerConnection) + Send + Sync>,
     ) -> Result<Self> {
@@ -228,6 +239,7 @@ impl PeerServer {
 
         Ok(Self {
             listener,
+            handshake,
             message_handler,
             connection_handler,
         })
@@ -239,12 +251,14 @@ impl PeerServer {
             match self.listener.accept().await {
                 Ok((stream, addr)) => {
                     info!("New connection from {}", addr);
+                    let handshake = Arc::clone(&self.handshake);
                     let message_handler = Arc::clone(&self.message_handler);
                     let connection_handler = Arc::clone(&self.connection_handler);
 
                     tokio::spawn(async move {
                         if let Err(e) = Self::handle_incoming_connection(
                             stream,
+                            handshake,
                             message_handler,
                             connection_handler,
                         )
@@ -264,24 +278,16 @@ impl PeerServer {
     /// Handle a new incoming WebSocket connection
     async fn handle_incoming_connection(
         stream: TcpStream,
+        handshake_config: Arc<HandshakeConfig>,
         message_handler: Arc<dyn Fn(Uuid, P2PMessage) + Send + Sync>,
         connection_handler: Arc<dyn Fn(PeerConnection) + Send + Sync>,
     ) -> Result<()> {
-        let ws_stream = accept_async(stream).await?;
+        let mut ws_stream = accept_async(stream).await?;
         info!("WebSocket connection established");
 
-        // For now, create a temporary peer info
-        // In a real implementation, this would be established through handshake
-        let peer_info = PeerInfo::new(
-            Uuid::new_v4(), // This should be received from peer discovery
-            "unknown".to_string(),
-            0,
-            "unknown".to_string(),
-            false,
-        );
-
-        let connection =
-            PeerConnection::new_incoming(peer_info, ws_stream, message_handler).await?;
+        let session =
+            handshake(&mut ws_stream, &handshake_config, HandshakeRole::Responder).await?;
+        let connection = PeerConnection::new_incoming(session, ws_stream, message_handler).await?;
 
         // Pass the connection to the handler
         connection_handler(connection);
@@ -294,36 +300,28 @@ impl PeerServer {
 pub struct PeerClient;
 
 impl PeerClient {
-    /// Connect to a remote peer
+    /// Connect to a remote peer and run the handshake as initiator
     pub async fn connect(
         peer_address: &str,
+        handshake_config: &HandshakeConfig,
         message_handler: Arc<dyn Fn(Uuid, P2PMessage) + Send + Sync>,
     ) -> Result<PeerConnection> {
         let url = format!("ws://{peer_address}");
         info!("Connecting to peer at {}", url);
 
-        let (ws_stream, _) = connect_async(&url).await?;
+        let (mut ws_stream, _) = connect_async(&url).await?;
         info!("Connected to peer at {}", peer_address);
 
-        // For now, create a temporary peer info
-        // In a real implementation, this would be established through handshake
-        let peer_info = PeerInfo::new(
-            Uuid::new_v4(), // This should be received from peer discovery
-            peer_address
-                .split(':')
-                .next()
-                .unwrap_or("unknown")
-                .to_string(),
-            peer_address
-                .split(':')
-                .nth(1)
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(0),
-            "unknown".to_string(),
-            false,
-        );
+        let mut session =
+            handshake(&mut ws_stream, handshake_config, HandshakeRole::Initiator).await?;
+
+        // We know where we dialed; the peer's self-reported address may be a bind address
+        if let Some((address, port)) = peer_address.rsplit_once(':') {
+            session.peer.address = address.to_string();
+            session.peer.port = port.parse().unwrap_or(session.peer.port);
+        }
 
-        PeerConnection::new_outgoing(peer_info, ws_stream, message_handler).await
+        PeerConnection::new_outgoing(session, ws_stream, message_handler).await
     }
 }
 
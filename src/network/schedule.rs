@@ -0,0 +1,290 @@
+//! Authority rotation schedule for Proof-of-Authority block production
+//!
+//! Time is divided into slots of `block_interval` seconds counted from the genesis
+//! block timestamp. Each slot has exactly one leader, taken round-robin from the
+//! validator set sorted by public key, so every node holding the same chain and
+//! validator set computes the same leaders. Slots between two consecutive blocks
+//! that produced no block are missed slots of their leaders.
+
+use chrono::{DateTime, Utc};
+use ed25519_dalek::VerifyingKey;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use uuid::{Builder, Uuid};
+
+use super::consensus::AuthorityPerformance;
+use crate::core::blockchain::Block;
+
+/// Derive the authority ID of an Ed25519 public key.
+///
+/// The ID is the first 16 bytes of SHA-256 over the key, formatted as a version 8 UUID,
+/// so it is the same on every node.
+pub fn authority_id(public_key: &VerifyingKey) -> Uuid {
+    let digest = Sha256::new()
+        .chain_update(b"provchain-authority:")
+        .chain_update(public_key.as_bytes())
+        .finalize();
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    Builder::from_custom_bytes(bytes).into_uuid()
+}
+
+/// Derive the authority ID of a hex-encoded Ed25519 public key
+pub fn authority_id_from_hex(public_key: &str) -> Option<Uuid> {
+    let key_bytes: [u8; 32] = hex::decode(public_key).ok()?.try_into().ok()?;
+    let public_key = VerifyingKey::from_bytes(&key_bytes).ok()?;
+    Some(authority_id(&public_key))
+}
+
+/// Deterministic slot leader schedule over a validator set
+#[derive(Debug, Clone)]
+pub struct RotationSchedule {
+    /// Hex-encoded authority keys in rotation order, with their derived IDs
+    authorities: Vec<(String, Uuid)>,
+    /// Start of slot 0
+    epoch: DateTime<Utc>,
+    /// Slot length in seconds
+    slot_duration: u64,
+}
+
+impl RotationSchedule {
+    /// Build the schedule for a validator set. Keys that are not valid Ed25519 public
+    /// keys are left out of the rotation.
+    pub fn new(validator_set: &HashSet<String>, epoch: DateTime<Utc>, slot_duration: u64) -> Self {
+        let mut authorities: Vec<(String, Uuid)> = validator_set
+            .iter()
+            .filter_map(|key| authority_id_from_hex(key).map(|id| (key.clone(), id)))
+            .collect();
+        authorities.sort();
+
+        Self {
+            authorities,
+            epoch,
+            slot_duration: slot_duration.max(1),
+        }
+    }
+
+    /// Build the schedule for a chain, using its genesis block timestamp as the epoch
+    pub fn for_chain(validator_set: &HashSet<String>, chain: &[Block], slot_duration: u64) -> Self {
+        let epoch = chain
+            .first()
+            .and_then(block_time)
+            .unwrap_or(DateTime::<Utc>::UNIX_EPOCH);
+        Self::new(validator_set, epoch, slot_duration)
+    }
+
+    /// Whether the schedule has no authorities
+    pub fn is_empty(&self) -> bool {
+        self.authorities.is_empty()
+    }
+
+    /// Number of authorities in the rotation
+    pub fn len(&self) -> usize {
+        self.authorities.len()
+    }
+
+    /// Authority IDs in rotation order
+    pub fn rotation_order(&self) -> Vec<Uuid> {
+        self.authorities.iter().map(|(_, id)| *id).collect()
+    }
+
+    /// Slot containing `time`; times before the epoch fall in slot 0
+    pub fn slot_at(&self, time: DateTime<Utc>) -> u64 {
+        let elapsed = time.signed_duration_since(self.epoch).num_seconds().max(0) as u64;
+        elapsed / self.slot_duration
+    }
+
+    /// Slot a block was produced in, from its timestamp
+    pub fn slot_of(&self, block: &Block) -> Option<u64> {
+        block_time(block).map(|time| self.slot_at(time))
+    }
+
+    /// Hex-encoded key of the authority leading `slot`
+    pub fn leader(&self, slot: u64) -> Option<&str> {
+        self.leader_entry(slot).map(|(key, _)| key.as_str())
+    }
+
+    /// ID of the authority leading `slot`
+    pub fn leader_id(&self, slot: u64) -> Option<Uuid> {
+        self.leader_entry(slot).map(|(_, id)| *id)
+    }
+
+    fn leader_entry(&self, slot: u64) -> Option<&(String, Uuid)> {
+        if self.authorities.is_empty() {
+            return None;
+        }
+        self.authorities
+            .get((slot % self.authorities.len() as u64) as usize)
+    }
+
+    /// Replay a chain against the schedule: blocks created by each authority and the
+    /// slots it led without a block appearing. Only chain data is used, so every node
+    /// with the same chain gets the same result.
+    pub fn performance(&self, chain: &[Block]) -> HashMap<Uuid, AuthorityPerformance> {
+        let mut performance: HashMap<Uuid, AuthorityPerformance> = self
+            .authorities
+            .iter()
+            .map(|(_, id)| {
+                (
+                    *id,
+                    AuthorityPerformance {
+                        blocks_created: 0,
+                        missed_slots: 0,
+                        last_activity: self.epoch,
+                        reputation: 1.0,
+                    },
+                )
+            })
+            .collect();
+        let ids: HashMap<&str, Uuid> = self
+            .authorities
+            .iter()
+            .map(|(key, id)| (key.as_str(), *id))
+            .collect();
+
+        let mut last_slot = chain.first().and_then(|genesis| self.slot_of(genesis));
+        for block in chain.iter().skip(1) {
+            let Some(time) = block_time(block) else {
+                continue;
+            };
+            let slot = self.slot_at(time);
+
+            if let Some(previous) = last_slot {
+                if slot > previous + 1 {
+                    self.record_missed(previous + 1, slot - previous - 1, &mut performance);
+                }
+            }
+            last_slot = Some(last_slot.map_or(slot, |previous| previous.max(slot)));
+
+            if let Some(entry) = ids
+                .get(block.validator.as_str())
+                .and_then(|id| performance.get_mut(id))
+            {
+                entry.blocks_created += 1;
+                entry.last_activity = time;
+            }
+        }
+
+        for entry in performance.values_mut() {
+            let total_slots = entry.blocks_created + entry.missed_slots;
+            if total_slots > 0 {
+                entry.reputation = entry.blocks_created as f64 / total_slots as f64;
+            }
+        }
+        performance
+    }
+
+    /// Charge `count` consecutive empty slots starting at `first_slot` to their leaders
+    fn record_missed(
+        &self,
+        first_slot: u64,
+        count: u64,
+        performance: &mut HashMap<Uuid, AuthorityPerformance>,
+    ) {
+        let authorities = self.authorities.len() as u64;
+        if authorities == 0 {
+            return;
+        }
+        let full_rounds = count / authorities;
+        let remainder = count % authorities;
+
+        for (position, (_, id)) in self.authorities.iter().enumerate() {
+            // Offset of this authority's first slot in the gap
+            let offset = (position as u64 + authorities - first_slot % authorities) % authorities;
+            let missed = full_rounds + u64::from(offset < remainder);
+            if let Some(entry) = performance.get_mut(id) {
+                entry.missed_slots += missed;
+            }
+        }
+    }
+}
+
+fn block_time(block: &Block) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(&block.timestamp)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use ed25519_dalek::SigningKey;
+
+    fn validator_set(count: u8) -> HashSet<String> {
+        (1..=count)
+            .map(|i| hex::encode(SigningKey::from_bytes(&[i; 32]).verifying_key().to_bytes()))
+            .collect()
+    }
+
+    fn block(index: u64, time: DateTime<Utc>, validator: &str) -> Block {
+        Block {
+            index,
+            timestamp: time.to_rfc3339(),
+            data: String::new(),
+            previous_hash: String::new(),
+            hash: String::new(),
+            state_root: String::new(),
+            data_hash: String::new(),
+            merkle_root: String::new(),
+            validator: validator.to_string(),
+            signature: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_authority_id_is_derived_from_key() {
+        let key = SigningKey::from_bytes(&[7; 32]).verifying_key();
+        let other = SigningKey::from_bytes(&[8; 32]).verifying_key();
+
+        assert_eq!(authority_id(&key), authority_id(&key));
+        assert_ne!(authority_id(&key), authority_id(&other));
+        assert_eq!(
+            authority_id_from_hex(&hex::encode(key.to_bytes())),
+            Some(authority_id(&key))
+        );
+        assert_eq!(authority_id_from_hex("not a key"), None);
+    }
+
+    #[test]
+    fn test_schedule_is_independent_of_set_order() {
+        let epoch = Utc::now();
+        let a = RotationSchedule::new(&validator_set(4), epoch, 10);
+        let mut keys: Vec<String> = validator_set(4).into_iter().collect();
+        keys.reverse();
+        let b = RotationSchedule::new(&keys.into_iter().collect(), epoch, 10);
+
+        assert_eq!(a.rotation_order(), b.rotation_order());
+        assert_eq!(a.slot_at(epoch + Duration::seconds(25)), 2);
+        assert_eq!(a.leader(2), b.leader(2));
+        assert_eq!(a.leader(1), a.leader(5));
+    }
+
+    #[test]
+    fn test_missed_slots_are_charged_to_their_leaders() {
+        let epoch = DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let schedule = RotationSchedule::new(&validator_set(3), epoch, 10);
+        let at = |slot: i64| epoch + Duration::seconds(slot * 10);
+        let leader = |slot: u64| schedule.leader(slot).unwrap().to_string();
+
+        // Slots 2, 3 and 5 produce no block
+        let chain = vec![
+            block(0, at(0), "GENESIS_VALIDATOR"),
+            block(1, at(1), &leader(1)),
+            block(2, at(4), &leader(4)),
+            block(3, at(6), &leader(6)),
+        ];
+        let performance = schedule.performance(&chain);
+
+        let missed = |slot: u64| performance[&schedule.leader_id(slot).unwrap()].missed_slots;
+        let created = |slot: u64| performance[&schedule.leader_id(slot).unwrap()].blocks_created;
+        assert_eq!(missed(2), 2); // slots 2 and 5
+        assert_eq!(missed(3), 1);
+        assert_eq!(missed(1), 0);
+        assert_eq!(created(1), 2); // slots 1 and 4
+        assert_eq!(created(6), 1);
+        assert_eq!(performance.values().map(|p| p.missed_slots).sum::<u64>(), 3);
+    }
+}
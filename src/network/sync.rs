@@ -5,6 +5,7 @@
 //! - Conflict resolution for concurrent blocks
 //! - Merkle tree verification for data integrity
 //! - Incremental synchronization for efficiency
+//! - Fast sync from signed state snapshots at finalized checkpoints
 
 use anyhow::Result;
 use chrono::{DateTime, Utc};
@@ -15,8 +16,11 @@ use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
 use super::messages::P2PMessage;
-use super::NetworkManager;
-use crate::core::blockchain::{Block, Blockchain};
+use super::{MessageHandler, NetworkManager};
+use crate::core::block_tree::BlockTree;
+use crate::core::blockchain::{Block, BlockHeader, Blockchain};
+use crate::core::snapshot::StateSnapshot;
+use crate::transaction::ledger::LedgerState;
 
 /// Blockchain synchronization manager
 pub struct BlockchainSync {
@@ -28,8 +32,17 @@ pub struct BlockchainSync {
     pub sync_state: Arc<RwLock<SyncState>>,
     /// Pending block requests
     pub pending_requests: Arc<RwLock<HashMap<u64, DateTime<Utc>>>>,
+    /// Competing and not-yet-connected blocks received from peers
+    pub block_tree: Arc<RwLock<BlockTree>>,
+    /// Signer nonces and spent outputs, caught up with the chain when a block extends it
+    pub ledger: Arc<RwLock<LedgerState>>,
+    /// Peer asked for a state snapshot whose response is still outstanding
+    pub pending_snapshot: Arc<RwLock<Option<Uuid>>>,
 }
 
+/// Seconds to wait for a snapshot before falling back to block-by-block sync
+const SNAPSHOT_TIMEOUT_SECS: u64 = 30;
+
 /// Synchronization state information
 #[derive(Debug, Clone)]
 pub struct SyncState {
@@ -73,6 +86,9 @@ impl BlockchainSync {
             network,
             sync_state: Arc::new(RwLock::new(sync_state)),
             pending_requests: Arc::new(RwLock::new(HashMap::new())),
+            block_tree: Arc::new(RwLock::new(BlockTree::new())),
+            ledger: Arc::new(RwLock::new(LedgerState::default())),
+            pending_snapshot: Arc::new(RwLock::new(None)),
         }
     }
 
@@ -162,8 +178,17 @@ impl BlockchainSync {
 
             if !sync_state.is_syncing {
                 sync_state.is_syncing = true;
+                let behind = latest_block_index.saturating_sub(sync_state.current_height);
                 drop(sync_state); // Release lock before async operation
-                self.sync_from_peer(peer_id, chain_length).await?;
+
+                // Far behind: start from the peer's finalized state instead of replaying
+                // every block
+                let threshold = self.network.config.network.snapshot_sync_threshold;
+                if threshold > 0 && behind >= threshold {
+                    self.request_snapshot_from_peer(peer_id).await?;
+                } else {
+                    self.sync_from_peer(peer_id, chain_length).await?;
+                }
             }
         }
 
@@ -202,6 +227,123 @@ impl BlockchainSync {
         Ok(())
     }
 
+    /// Ask a peer for a state snapshot at its last finalized checkpoint.
+    ///
+    /// If the request cannot be sent or no snapshot arrives within
+    /// `SNAPSHOT_TIMEOUT_SECS`, sync falls back to fetching every block from the peer.
+    async fn request_snapshot_from_peer(&self, peer_id: Uuid) -> Result<()> {
+        info!("Requesting state snapshot from peer {}", peer_id);
+        *self.pending_snapshot.write().await = Some(peer_id);
+
+        let request = P2PMessage::SnapshotRequest {
+            requester_id: self.network.node_id,
+        };
+        if let Err(e) = self.network.send_to_peer(peer_id, request).await {
+            warn!("Failed to request snapshot from peer {}: {}", peer_id, e);
+            *self.pending_snapshot.write().await = None;
+            return self.sync_blocks_from_peer(peer_id).await;
+        }
+
+        let sync = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(tokio::time::Duration::from_secs(SNAPSHOT_TIMEOUT_SECS)).await;
+            let timed_out = {
+                let mut pending = sync.pending_snapshot.write().await;
+                if *pending == Some(peer_id) {
+                    *pending = None;
+                    true
+                } else {
+                    false
+                }
+            };
+            if timed_out {
+                warn!(
+                    "No snapshot from peer {} after {}s, syncing block by block",
+                    peer_id, SNAPSHOT_TIMEOUT_SECS
+                );
+                if let Err(e) = sync.sync_blocks_from_peer(peer_id).await {
+                    warn!("Block sync from peer {} failed: {}", peer_id, e);
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Fetch every block up to the highest known block from a peer
+    async fn sync_blocks_from_peer(&self, peer_id: Uuid) -> Result<()> {
+        let target_height = self.sync_state.read().await.highest_known_block + 1;
+        self.sync_from_peer(peer_id, target_height).await
+    }
+
+    /// Answer a snapshot request with a snapshot signed by this node's key
+    pub async fn handle_snapshot_request(&self, requester_id: Uuid) -> P2PMessage {
+        let snapshot = {
+            let blockchain = self.blockchain.read().await;
+            blockchain.create_snapshot(&self.network.handshake.signing_key)
+        };
+
+        let snapshot = match snapshot {
+            Ok(snapshot) => Some(Box::new(snapshot)),
+            Err(e) => {
+                debug!("No snapshot for peer {}: {}", requester_id, e);
+                None
+            }
+        };
+
+        P2PMessage::SnapshotResponse {
+            snapshot,
+            requester_id,
+        }
+    }
+
+    /// Install a snapshot received from a peer, then sync the blocks after it.
+    ///
+    /// If the peer has no snapshot or it fails verification, sync falls back to
+    /// fetching every block.
+    pub async fn handle_snapshot_response(
+        &self,
+        snapshot: Option<Box<StateSnapshot>>,
+        requester_id: Uuid,
+        peer_id: Uuid,
+    ) -> Result<()> {
+        if requester_id != self.network.node_id {
+            // This response is not for us
+            return Ok(());
+        }
+        {
+            let mut pending = self.pending_snapshot.write().await;
+            if *pending != Some(peer_id) {
+                warn!("Ignoring unrequested snapshot from peer {}", peer_id);
+                return Ok(());
+            }
+            *pending = None;
+        }
+
+        if let Some(snapshot) = snapshot {
+            let installed = {
+                let mut blockchain = self.blockchain.write().await;
+                blockchain
+                    .install_snapshot(&snapshot)
+                    .map(|()| blockchain.chain.len() as u64)
+            };
+            match installed {
+                Ok(height) => {
+                    info!(
+                        "Installed snapshot from peer {} at block {}",
+                        peer_id, snapshot.block_index
+                    );
+                    self.sync_state.write().await.current_height = height;
+                }
+                Err(e) => warn!("Rejected snapshot from peer {}: {}", peer_id, e),
+            }
+        } else {
+            info!("Peer {} has no snapshot, syncing block by block", peer_id);
+        }
+
+        self.sync_blocks_from_peer(peer_id).await
+    }
+
     /// Request a specific block from a peer
     async fn request_block_from_peer(&self, peer_id: Uuid, block_index: u64) -> Result<()> {
         debug!("Requesting block {} from peer {}", block_index, peer_id);
@@ -253,36 +395,77 @@ impl BlockchainSync {
         let mut sync_state = self.sync_state.write().await;
 
         // Validate block integrity
-        if !self.validate_block(&block, &blockchain).await? {
+        if !self.validate_block(&block) {
             warn!("Received invalid block {}, rejecting", block.index);
             return Ok(());
         }
 
-        // Check if this block extends our chain
-        if block.index == blockchain.chain.len() as u64 {
-            // This block extends our chain directly
-            let _ = blockchain.add_block(block.data.clone());
-            sync_state.current_height = blockchain.chain.len() as u64;
+        let extends_tip = blockchain
+            .chain
+            .last()
+            .map(|tip| block.index == tip.index + 1 && block.previous_hash == tip.hash)
+            .unwrap_or(false);
+        let already_canonical = blockchain
+            .chain
+            .get(block.index as usize)
+            .map(|existing| existing.hash == block.hash)
+            .unwrap_or(false);
+
+        if extends_tip {
+            // This block extends our chain directly; keep the peer's signed block as-is
+            let block_index = block.index;
+            let mut ledger = self.ledger.write().await;
+            ledger.sync(&blockchain.chain)?;
+            if let Err(e) = ledger.validate_block_data(&block.data) {
+                warn!("Rejected block {} from peer: {}", block_index, e);
+                return Ok(());
+            }
+            if let Err(e) = blockchain.submit_signed_block(block) {
+                warn!("Rejected block {} from peer: {}", block_index, e);
+                return Ok(());
+            }
 
             info!(
                 "Added block {} to blockchain (height: {})",
-                block.index, sync_state.current_height
+                block_index,
+                blockchain.chain.len()
             );
-        } else if block.index < blockchain.chain.len() as u64 {
-            // This is an older block, check for conflicts
-            if let Some(existing_block) = blockchain.chain.get(block.index as usize) {
-                if existing_block.hash != block.hash {
-                    warn!("Block conflict detected at index {}", block.index);
-                    self.handle_block_conflict(block, existing_block.clone())
-                        .await?;
-                }
-            }
+        } else if already_canonical {
+            debug!("Block {} is already on our chain", block.index);
+        } else if block.index <= blockchain.finalized_index() {
+            warn!(
+                "Rejecting block {} conflicting with finalized checkpoint {}",
+                block.index,
+                blockchain.finalized_index()
+            );
+            return Ok(());
         } else {
-            // This block is from the future, store it for later
-            debug!("Received future block {}, storing for later", block.index);
-            // In a full implementation, we'd store this in a pending blocks cache
+            // Competing block or a block whose parent we have not seen yet
+            if block.index < blockchain.chain.len() as u64 {
+                warn!("Block conflict detected at index {}", block.index);
+            } else {
+                debug!(
+                    "Received future block {}, keeping it in the block tree",
+                    block.index
+                );
+            }
+            let block_index = block.index;
+            if !self.block_tree.write().await.insert(
+                block,
+                &blockchain.chain,
+                &blockchain.governance.validator_set,
+            ) {
+                debug!(
+                    "Block {} is unsigned, unauthorized or already known; not kept",
+                    block_index
+                );
+            }
         }
 
+        // Blocks kept earlier may now connect to the chain or outweigh it
+        self.handle_block_conflict(&mut blockchain).await?;
+        sync_state.current_height = blockchain.chain.len() as u64;
+
         // Check if sync is complete
         if sync_state.current_height >= sync_state.highest_known_block {
             sync_state.is_syncing = false;
@@ -296,65 +479,61 @@ impl BlockchainSync {
         Ok(())
     }
 
-    /// Validate a received block
-    async fn validate_block(&self, block: &Block, blockchain: &Blockchain) -> Result<bool> {
-        // Check block index
-        if block.index > blockchain.chain.len() as u64 {
-            // Future block - we'll validate it when we have the previous blocks
-            return Ok(true);
-        }
-
-        // Check previous hash
-        if block.index > 0 {
-            if let Some(prev_block) = blockchain.chain.get((block.index - 1) as usize) {
-                if block.previous_hash != prev_block.hash {
-                    warn!("Block {} has invalid previous hash", block.index);
-                    return Ok(false);
-                }
-            }
-        }
-
-        // Validate block hash by recalculating it
-        let expected_hash = block.calculate_hash_with_store(Some(&blockchain.rdf_store));
-        if block.hash != expected_hash {
+    /// Validate a received block's commitments independently of where it fits in the chain
+    fn validate_block(&self, block: &Block) -> bool {
+        // Validate the committed data hash and the header hash derived from it
+        if !block.verify_data_hash() || block.hash != block.calculate_hash() {
             warn!("Block {} has invalid hash", block.index);
-            return Ok(false);
+            return false;
         }
 
-        Ok(true)
+        true
     }
 
-    /// Handle block conflicts (simple resolution for now)
-    async fn handle_block_conflict(&self, new_block: Block, existing_block: Block) -> Result<()> {
-        warn!("Handling block conflict at index {}", new_block.index);
-
-        // Parse timestamps for comparison
-        let new_time = chrono::DateTime::parse_from_rfc3339(&new_block.timestamp)
-            .map_err(|_| anyhow::anyhow!("Invalid timestamp format in new block"))?;
-        let existing_time = chrono::DateTime::parse_from_rfc3339(&existing_block.timestamp)
-            .map_err(|_| anyhow::anyhow!("Invalid timestamp format in existing block"))?;
-
-        // Simple conflict resolution: prefer the block with the earlier timestamp
-        if new_time < existing_time {
-            let block_index = new_block.index;
-            info!(
-                "Replacing block {} with earlier timestamped version",
-                block_index
-            );
-
-            let mut blockchain = self.blockchain.write().await;
-            blockchain.chain[block_index as usize] = new_block;
+    /// Apply fork choice over the block tree and reorganize onto the winning branch.
+    ///
+    /// The rule is authority-weighted longest chain (see `core::block_tree`), limited to
+    /// branches forking at or above the last finalized checkpoint. Blocks rolled back by a
+    /// reorganization are kept in the tree so they can win back later.
+    async fn handle_block_conflict(&self, blockchain: &mut Blockchain) -> Result<()> {
+        let mut block_tree = self.block_tree.write().await;
+
+        // Never reorganize below the last finalized checkpoint
+        let finalized_index = blockchain.finalized_index();
+        block_tree.prune_at_or_below(finalized_index);
+
+        // A branch that fails to replay is dropped so it is not retried forever
+        while let Some(choice) = block_tree.best_branch(
+            &blockchain.chain,
+            &blockchain.governance.validator_set,
+            finalized_index,
+        ) {
+            for block in &choice.branch {
+                block_tree.remove(&block.hash);
+            }
 
-            // In a full implementation, we'd need to:
-            // 1. Remove the old RDF data from the store
-            // 2. Add the new RDF data
-            // 3. Recompute hashes for subsequent blocks
-            // 4. Notify other peers of the change
-        } else {
-            debug!(
-                "Keeping existing block {} (earlier timestamp)",
-                existing_block.index
-            );
+            let branch_len = choice.branch.len();
+            match blockchain.reorganize(choice.fork_index, choice.branch) {
+                Ok(orphaned) => {
+                    if !orphaned.is_empty() {
+                        warn!(
+                            "Chain reorganized at fork index {}: {} block(s) replaced by {}",
+                            choice.fork_index,
+                            orphaned.len(),
+                            branch_len
+                        );
+                    }
+                    for block in orphaned {
+                        block_tree.insert_orphaned(block, &blockchain.chain);
+                    }
+                }
+                Err(e) => {
+                    warn!(
+                        "Rejected competing branch at fork index {}: {}",
+                        choice.fork_index, e
+                    );
+                }
+            }
         }
 
         Ok(())
@@ -427,6 +606,68 @@ impl BlockchainSync {
         }
     }
 
+    /// Answer a block request from a syncing peer
+    pub async fn handle_block_request(&self, block_index: u64, requester_id: Uuid) -> P2PMessage {
+        let block = self
+            .blockchain
+            .read()
+            .await
+            .chain
+            .get(block_index as usize)
+            .cloned();
+        P2PMessage::BlockResponse {
+            block,
+            requester_id,
+        }
+    }
+
+    /// Answer a chain status request with this node's tip
+    pub async fn handle_chain_status_request(&self, requester_id: Uuid) -> P2PMessage {
+        let blockchain = self.blockchain.read().await;
+        let tip = blockchain.chain.last();
+        P2PMessage::ChainStatusResponse {
+            latest_block_index: tip.map(|block| block.index).unwrap_or(0),
+            latest_block_hash: tip.map(|block| block.hash.clone()).unwrap_or_default(),
+            chain_length: blockchain.chain.len() as u64,
+            requester_id,
+        }
+    }
+
+    /// Answer a headers request from a light client or syncing peer
+    pub async fn handle_headers_request(
+        &self,
+        from_index: u64,
+        to_index: u64,
+        requester_id: Uuid,
+    ) -> P2PMessage {
+        /// Upper bound on headers returned in a single response
+        const MAX_HEADERS: u64 = 500;
+
+        let to_index = to_index.min(from_index.saturating_add(MAX_HEADERS - 1));
+        let headers = {
+            let blockchain = self.blockchain.read().await;
+            blockchain.get_headers(from_index, to_index)
+        };
+
+        P2PMessage::HeadersResponse {
+            headers,
+            requester_id,
+        }
+    }
+
+    /// Check that a run of headers is internally consistent: each hash recomputes
+    /// from its header fields and links to the previous header
+    pub fn verify_header_chain(headers: &[BlockHeader]) -> bool {
+        BlockHeader::verify_chain(headers)
+    }
+
+    /// Send a response to a peer request handled off the message handler
+    async fn respond(&self, peer_id: Uuid, response: P2PMessage) {
+        if let Err(e) = self.network.send_to_peer(peer_id, response).await {
+            warn!("Failed to answer peer {}: {}", peer_id, e);
+        }
+    }
+
     /// Announce a new block to the network
     pub async fn announce_new_block(&self, block: &Block) -> Result<()> {
         info!("Announcing new block {} to network", block.index);
@@ -478,7 +719,118 @@ impl Clone for BlockchainSync {
             network: Arc::clone(&self.network),
             sync_state: Arc::clone(&self.sync_state),
             pending_requests: Arc::clone(&self.pending_requests),
+            block_tree: Arc::clone(&self.block_tree),
+            ledger: Arc::clone(&self.ledger),
+            pending_snapshot: Arc::clone(&self.pending_snapshot),
+        }
+    }
+}
+
+impl MessageHandler for BlockchainSync {
+    fn handle_message(&self, peer_id: Uuid, message: P2PMessage) -> Result<Option<P2PMessage>> {
+        let sync = self.clone();
+        match message {
+            P2PMessage::ChainStatusRequest { requester_id } => {
+                tokio::spawn(async move {
+                    let response = sync.handle_chain_status_request(requester_id).await;
+                    sync.respond(peer_id, response).await;
+                });
+            }
+            P2PMessage::ChainStatusResponse {
+                latest_block_index,
+                latest_block_hash,
+                chain_length,
+                requester_id,
+            } if requester_id == self.network.node_id => {
+                tokio::spawn(async move {
+                    if let Err(e) = sync
+                        .handle_chain_status_response(
+                            latest_block_index,
+                            latest_block_hash,
+                            chain_length,
+                            peer_id,
+                        )
+                        .await
+                    {
+                        warn!("Failed to sync from peer {}: {}", peer_id, e);
+                    }
+                });
+            }
+            P2PMessage::BlockAnnouncement {
+                block_index,
+                block_hash,
+                previous_hash,
+                graph_uri,
+                timestamp: _,
+            } => {
+                tokio::spawn(async move {
+                    if let Err(e) = sync
+                        .handle_block_announcement(
+                            block_index,
+                            block_hash,
+                            previous_hash,
+                            graph_uri,
+                            peer_id,
+                        )
+                        .await
+                    {
+                        warn!("Failed to process block announcement: {}", e);
+                    }
+                });
+            }
+            P2PMessage::BlockRequest {
+                block_index,
+                requester_id,
+            } => {
+                tokio::spawn(async move {
+                    let response = sync.handle_block_request(block_index, requester_id).await;
+                    sync.respond(peer_id, response).await;
+                });
+            }
+            P2PMessage::BlockResponse {
+                block,
+                requester_id,
+            } => {
+                tokio::spawn(async move {
+                    if let Err(e) = sync.handle_block_response(block, requester_id).await {
+                        warn!("Failed to process block from peer {}: {}", peer_id, e);
+                    }
+                });
+            }
+            P2PMessage::HeadersRequest {
+                from_index,
+                to_index,
+                requester_id,
+            } => {
+                tokio::spawn(async move {
+                    let response = sync
+                        .handle_headers_request(from_index, to_index, requester_id)
+                        .await;
+                    sync.respond(peer_id, response).await;
+                });
+            }
+            P2PMessage::SnapshotRequest { requester_id } => {
+                tokio::spawn(async move {
+                    let response = sync.handle_snapshot_request(requester_id).await;
+                    sync.respond(peer_id, response).await;
+                });
+            }
+            P2PMessage::SnapshotResponse {
+                snapshot,
+                requester_id,
+            } => {
+                tokio::spawn(async move {
+                    if let Err(e) = sync
+                        .handle_snapshot_response(snapshot, requester_id, peer_id)
+                        .await
+                    {
+                        warn!("Failed to process snapshot from peer {}: {}", peer_id, e);
+                    }
+                });
+            }
+            _ => {}
         }
+        Ok(None)
     }
 }
 
@@ -496,6 +848,10 @@ pub struct SyncStats {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::transaction::transaction::{
+        Transaction, TransactionInput, TransactionMetadata, TransactionOutput, TransactionPayload,
+        TransactionType,
+    };
     use crate::utils::config::NodeConfig;
 
     #[tokio::test]
@@ -518,6 +874,295 @@ mod tests {
         assert!(stats.current_height >= 1); // At least genesis block exists
         assert_eq!(stats.sync_peers_count, 0);
     }
-}
 
+    #[tokio::test]
+    async fn test_heavier_branch_triggers_reorganization() {
+        let mut bc = Blockchain::new();
+        bc.add_block("@prefix ex: <http://example.org/> . ex:ours ex:p \"1\" .".to_string())
+            .unwrap();
+        let genesis_hash = bc.chain[0].hash.clone();
+        let ours = bc.chain[1].clone();
+
+        // Peer blocks only enter the block tree with a valid signature
+        let key = ed25519_dalek::SigningKey::from_bytes(&[3; 32]);
+        let signed = |index: u64, data: &str, previous_hash: String| {
+            let mut block = Block::new(
+                index,
+                data.to_string(),
+                previous_hash,
+                String::new(),
+                hex::encode(key.verifying_key().to_bytes()),
+            );
+            block.signature =
+                hex::encode(ed25519_dalek::Signer::sign(&key, block.hash.as_bytes()).to_bytes());
+            block
+        };
+        let fork1 = signed(
+            1,
+            "@prefix ex: <http://example.org/> . ex:theirs ex:p \"1\" .",
+            genesis_hash,
+        );
+        let fork2 = signed(
+            2,
+            "@prefix ex: <http://example.org/> . ex:theirs ex:p \"2\" .",
+            fork1.hash.clone(),
+        );
+
+        let blockchain = Arc::new(RwLock::new(bc));
+        let network = Arc::new(NetworkManager::new(NodeConfig::default()));
+        let sync = BlockchainSync::new(Arc::clone(&blockchain), network);
+
+        // Out of order: the tip of the competing branch arrives first
+        sync.process_received_block(fork2.clone()).await.unwrap();
+        assert_eq!(blockchain.read().await.chain[1].hash, ours.hash);
+
+        sync.process_received_block(fork1.clone()).await.unwrap();
+        let bc = blockchain.read().await;
+        assert_eq!(bc.chain.len(), 3);
+        assert_eq!(bc.chain[1].hash, fork1.hash);
+        assert_eq!(bc.chain[2].hash, fork2.hash);
+        assert!(bc.is_valid());
+        assert_eq!(
+            bc.rdf_store
+                .canonicalize_graph(&oxigraph::model::NamedNode::new(Block::graph_uri(1)).unwrap()),
+            fork1.data_hash
+        );
+
+        // The rolled-back block is kept as a side branch
+        assert!(sync.block_tree.read().await.contains(&ours.hash));
+        assert_eq!(sync.get_sync_stats().await.current_height, 3);
+    }
+
+    /// Production transaction of `batch_id` signed by `signer` with `nonce`
+    fn production(
+        signer: &ed25519_dalek::SigningKey,
+        signer_id: Uuid,
+        nonce: u64,
+        batch_id: &str,
+    ) -> Transaction {
+        let output = TransactionOutput {
+            id: format!("{}:0", batch_id),
+            owner: signer_id,
+            asset_type: "raw_material_batch".to_string(),
+            value: 1000.0,
+            metadata: HashMap::new(),
+        };
+        let metadata = TransactionMetadata {
+            location: None,
+            environmental_conditions: None,
+            compliance_info: None,
+            quality_data: None,
+            custom_fields: HashMap::new(),
+        };
+        let mut transaction = Transaction::new(
+            TransactionType::Production,
+            vec![],
+            vec![output],
+            String::new(),
+            metadata,
+            TransactionPayload::RdfData(String::new()),
+        );
+        transaction.nonce = nonce;
+        transaction.sign(signer, signer_id).unwrap();
+        transaction
+    }
 
+    /// Transfer signed by `signer` with `nonce`, spending `spent` into `output_id`
+    fn transfer(
+        signer: &ed25519_dalek::SigningKey,
+        signer_id: Uuid,
+        nonce: u64,
+        spent: &str,
+        output_id: &str,
+    ) -> Transaction {
+        let mut transaction = production(signer, signer_id, nonce, output_id);
+        let (prev_tx_id, output_index) = spent.rsplit_once(':').unwrap();
+        transaction.tx_type = TransactionType::Transfer;
+        transaction.inputs = vec![TransactionInput {
+            prev_tx_id: prev_tx_id.to_string(),
+            output_index: output_index.parse().unwrap(),
+            signature: None,
+            public_key: None,
+        }];
+        transaction.signatures.clear();
+        transaction.sign(signer, signer_id).unwrap();
+        transaction
+    }
+
+    /// Block data recording `transactions` in order
+    fn transaction_data(transactions: &[Transaction]) -> String {
+        let mut data = "@prefix tx: <http://provchain.org/tx#> .\n@prefix xsd: <http://www.w3.org/2001/XMLSchema#> .\n".to_string();
+        for (position, transaction) in transactions.iter().enumerate() {
+            data.push_str(&transaction.to_rdf());
+            data.push_str(&format!(
+                "tx:{} tx:hasBlockPosition \"{}\"^^xsd:integer .\n",
+                transaction.id, position
+            ));
+        }
+        data
+    }
+
+    #[tokio::test]
+    async fn test_branch_replaying_a_nonce_is_not_reorganized_onto() {
+        let mut bc = Blockchain::new();
+        bc.add_block("@prefix ex: <http://example.org/> . ex:ours ex:p \"1\" .".to_string())
+            .unwrap();
+        let genesis_hash = bc.chain[0].hash.clone();
+        let ours = bc.chain[1].clone();
+
+        let key = ed25519_dalek::SigningKey::from_bytes(&[3; 32]);
+        let signed = |index: u64, data: String, previous_hash: String| {
+            let mut block = Block::new(
+                index,
+                data,
+                previous_hash,
+                String::new(),
+                hex::encode(key.verifying_key().to_bytes()),
+            );
+            block.signature =
+                hex::encode(ed25519_dalek::Signer::sign(&key, block.hash.as_bytes()).to_bytes());
+            block
+        };
+        // The second block of the heavier branch signs nonce 0 of the farmer again
+        let farmer = ed25519_dalek::SigningKey::from_bytes(&[5; 32]);
+        let farmer_id = Uuid::new_v4();
+        let fork1 = signed(
+            1,
+            transaction_data(&[production(&farmer, farmer_id, 0, "MILK-001")]),
+            genesis_hash,
+        );
+        let fork2 = signed(
+            2,
+            transaction_data(&[production(&farmer, farmer_id, 0, "MILK-002")]),
+            fork1.hash.clone(),
+        );
+
+        let blockchain = Arc::new(RwLock::new(bc));
+        let network = Arc::new(NetworkManager::new(NodeConfig::default()));
+        let sync = BlockchainSync::new(Arc::clone(&blockchain), network);
+
+        sync.process_received_block(fork2.clone()).await.unwrap();
+        sync.process_received_block(fork1.clone()).await.unwrap();
+        let bc = blockchain.read().await;
+        assert_eq!(bc.chain.len(), 2);
+        assert_eq!(bc.chain[1].hash, ours.hash);
+        assert!(bc.is_valid());
+        let block_tree = sync.block_tree.read().await;
+        assert!(!block_tree.contains(&fork1.hash));
+        assert!(!block_tree.contains(&fork2.hash));
+    }
+
+    #[tokio::test]
+    async fn test_branch_spending_an_output_twice_is_not_reorganized_onto() {
+        let mut bc = Blockchain::new();
+        bc.add_block("@prefix ex: <http://example.org/> . ex:ours ex:p \"1\" .".to_string())
+            .unwrap();
+        let genesis_hash = bc.chain[0].hash.clone();
+        let ours = bc.chain[1].clone();
+
+        let key = ed25519_dalek::SigningKey::from_bytes(&[3; 32]);
+        let signed = |index: u64, data: String, previous_hash: String| {
+            let mut block = Block::new(
+                index,
+                data,
+                previous_hash,
+                String::new(),
+                hex::encode(key.verifying_key().to_bytes()),
+            );
+            block.signature =
+                hex::encode(ed25519_dalek::Signer::sign(&key, block.hash.as_bytes()).to_bytes());
+            block
+        };
+        // Each nonce is used once, but the batch produced in the first block is
+        // transferred in both blocks of the heavier branch
+        let farmer = ed25519_dalek::SigningKey::from_bytes(&[5; 32]);
+        let farmer_id = Uuid::new_v4();
+        let fork1 = signed(
+            1,
+            transaction_data(&[
+                production(&farmer, farmer_id, 0, "MILK-001"),
+                transfer(&farmer, farmer_id, 1, "MILK-001:0", "MILK-001-A"),
+            ]),
+            genesis_hash,
+        );
+        let fork2 = signed(
+            2,
+            transaction_data(&[transfer(&farmer, farmer_id, 2, "MILK-001:0", "MILK-001-B")]),
+            fork1.hash.clone(),
+        );
+
+        let blockchain = Arc::new(RwLock::new(bc));
+        let network = Arc::new(NetworkManager::new(NodeConfig::default()));
+        let sync = BlockchainSync::new(Arc::clone(&blockchain), network);
+
+        sync.process_received_block(fork2.clone()).await.unwrap();
+        sync.process_received_block(fork1.clone()).await.unwrap();
+        let bc = blockchain.read().await;
+        assert_eq!(bc.chain.len(), 2);
+        assert_eq!(bc.chain[1].hash, ours.hash);
+        assert!(!sync.block_tree.read().await.contains(&fork1.hash));
+    }
+
+    #[tokio::test]
+    async fn test_headers_request_returns_verifiable_headers() {
+        let mut bc = Blockchain::new();
+        bc.add_block("@prefix ex: <http://example.org/> . ex:a ex:p \"1\" .".to_string())
+            .unwrap();
+        bc.add_block("@prefix ex: <http://example.org/> . ex:b ex:p \"2\" .".to_string())
+            .unwrap();
+        let blockchain = Arc::new(RwLock::new(bc));
+        let network = Arc::new(NetworkManager::new(NodeConfig::default()));
+        let sync = BlockchainSync::new(blockchain, network);
+
+        let requester = Uuid::new_v4();
+        let response = sync.handle_headers_request(0, 10, requester).await;
+        let P2PMessage::HeadersResponse {
+            mut headers,
+            requester_id,
+        } = response
+        else {
+            panic!("expected HeadersResponse");
+        };
+
+        assert_eq!(requester_id, requester);
+        assert_eq!(headers.len(), 3);
+        assert!(headers.iter().all(|h| !h.merkle_root.is_empty()));
+        assert!(BlockchainSync::verify_header_chain(&headers));
+
+        headers[2].merkle_root = "00".repeat(32);
+        assert!(!BlockchainSync::verify_header_chain(&headers));
+    }
+
+    #[tokio::test]
+    async fn test_unrequested_snapshot_response_is_ignored() {
+        let blockchain = Arc::new(RwLock::new(Blockchain::new()));
+        let network = Arc::new(NetworkManager::new(NodeConfig::default()));
+        let node_id = network.node_id;
+        let sync = BlockchainSync::new(blockchain, network);
+
+        // No snapshot was requested from this peer, so nothing is installed or fetched
+        sync.handle_snapshot_response(None, node_id, Uuid::new_v4())
+            .await
+            .unwrap();
+        assert!(sync.pending_requests.read().await.is_empty());
+        assert!(sync.pending_snapshot.read().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_request_needs_finalized_checkpoint() {
+        let blockchain = Arc::new(RwLock::new(Blockchain::new()));
+        let network = Arc::new(NetworkManager::new(NodeConfig::default()));
+        let sync = BlockchainSync::new(blockchain, network);
+
+        let requester = Uuid::new_v4();
+        let P2PMessage::SnapshotResponse {
+            snapshot,
+            requester_id,
+        } = sync.handle_snapshot_request(requester).await
+        else {
+            panic!("expected SnapshotResponse");
+        };
+        assert_eq!(requester_id, requester);
+        assert!(snapshot.is_none());
+    }
+}
@@ -0,0 +1,724 @@
+//! Authenticated and encrypted peer transport
+//!
+//! Before any `P2PMessage` is exchanged, both ends of a WebSocket run a handshake:
+//!
+//! 1. Each side sends a `HandshakeHello` with its `PeerInfo`, its Ed25519 node key and
+//!    a fresh X25519 ephemeral key.
+//! 2. Each side signs the hash of both hellos (the transcript) with its node key,
+//!    proving possession of the key it announced. The role is part of the signed
+//!    payload so a signature cannot be reflected back.
+//! 3. The peer's node ID must be the one derived from its node key, and the peer is
+//!    checked against the `PeerAllowList`.
+//! 4. Session keys for each direction are derived with HKDF-SHA256 from the X25519
+//!    shared secret, salted with the transcript hash.
+//!
+//! Afterwards every frame is a binary WebSocket message sealed with
+//! ChaCha20-Poly1305 under a per-direction counter nonce, so frames that are forged,
+//! replayed, reordered or dropped fail to open. A rejected peer receives an
+//! `ErrorCode::AuthenticationFailed` error before the connection is closed.
+
+use anyhow::Result;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use futures_util::{SinkExt, StreamExt};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305};
+use ring::agreement::{agree_ephemeral, EphemeralPrivateKey, UnparsedPublicKey, X25519};
+use ring::hkdf;
+use ring::rand::SystemRandom;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::RwLock;
+use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
+use tracing::{debug, info, warn};
+
+use super::codec::{negotiate, PROTOCOL_VERSION_JSON, SUPPORTED_PROTOCOL_VERSIONS};
+use super::messages::{ErrorCode, P2PMessage, PeerInfo};
+use super::schedule::{authority_id, authority_id_from_hex};
+
+/// Handshake protocol identifier, bumped whenever the handshake or framing changes
+pub const PROTOCOL_ID: &str = "provchain-secure/1";
+
+/// Maximum time allowed for the handshake to complete
+pub const HANDSHAKE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Which end of the connection we are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandshakeRole {
+    /// The side that dialed the connection
+    Initiator,
+    /// The side that accepted the connection
+    Responder,
+}
+
+impl HandshakeRole {
+    fn label(self) -> &'static [u8] {
+        match self {
+            HandshakeRole::Initiator => b"initiator",
+            HandshakeRole::Responder => b"responder",
+        }
+    }
+
+    fn peer(self) -> Self {
+        match self {
+            HandshakeRole::Initiator => HandshakeRole::Responder,
+            HandshakeRole::Responder => HandshakeRole::Initiator,
+        }
+    }
+}
+
+/// First handshake message, sent in the clear by both sides
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandshakeHello {
+    pub protocol: String,
+    /// The sender's self-description; `is_authority` is checked against the validator set
+    pub peer: PeerInfo,
+    /// Hex-encoded Ed25519 node key
+    pub public_key: String,
+    /// Hex-encoded X25519 ephemeral key for this connection
+    pub ephemeral_key: String,
+}
+
+/// Second handshake message: the sender's signature over the transcript
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandshakeAuth {
+    /// Hex-encoded Ed25519 signature
+    pub signature: String,
+}
+
+/// Which peers may connect, tied to the governance validator set
+#[derive(Debug, Clone)]
+pub struct PeerAllowList {
+    /// Node keys admitted even when they are not validators
+    pub trusted_keys: HashSet<String>,
+    /// Admit peers that are neither validators nor trusted, as long as they do not
+    /// claim to be an authority
+    pub allow_observers: bool,
+    /// Current validator set, kept up to date by consensus
+    pub validator_set: Arc<RwLock<HashSet<String>>>,
+}
+
+impl PeerAllowList {
+    /// Create an allow-list over a validator set
+    pub fn new(validator_set: HashSet<String>) -> Self {
+        Self {
+            trusted_keys: HashSet::new(),
+            allow_observers: false,
+            validator_set: Arc::new(RwLock::new(validator_set)),
+        }
+    }
+
+    /// Check an authenticated peer. Peers claiming `is_authority` must hold a key in
+    /// the validator set; other peers must be validators, trusted, or observers.
+    pub async fn check(&self, peer: &PeerInfo, public_key: &str) -> Result<()> {
+        let validator_set = self.validator_set.read().await;
+        let is_validator = validator_set.contains(public_key);
+
+        if peer.is_authority && !is_validator {
+            anyhow::bail!(
+                "peer {} claims to be an authority but its key is not in the validator set",
+                peer.node_id
+            );
+        }
+        if !(is_validator || self.allow_observers || self.trusted_keys.contains(public_key)) {
+            anyhow::bail!("peer {} is not on the allow-list", peer.node_id);
+        }
+        Ok(())
+    }
+}
+
+/// Local identity and admission policy used for every handshake
+#[derive(Debug, Clone)]
+pub struct HandshakeConfig {
+    /// Ed25519 node key the peer must prove possession of
+    pub signing_key: SigningKey,
+    /// Our own peer information, sent to the remote side
+    pub local_info: PeerInfo,
+    /// Admission policy for remote peers
+    pub allow_list: PeerAllowList,
+}
+
+impl HandshakeConfig {
+    /// Create a handshake configuration with an empty validator set. The node ID in
+    /// `local_info` is replaced by the one derived from `signing_key`, the only ID peers
+    /// accept for this node.
+    pub fn new(signing_key: SigningKey, mut local_info: PeerInfo) -> Self {
+        local_info.node_id = authority_id(&signing_key.verifying_key());
+        Self {
+            signing_key,
+            local_info,
+            allow_list: PeerAllowList::new(HashSet::new()),
+        }
+    }
+
+    /// Handshake configuration with a throwaway node key, for nodes without one
+    pub fn ephemeral(local_info: PeerInfo) -> Self {
+        Self::new(
+            SigningKey::from_bytes(&rand::random::<[u8; 32]>()),
+            local_info,
+        )
+    }
+
+    /// Hex-encoded public node key
+    pub fn public_key(&self) -> String {
+        hex::encode(self.signing_key.verifying_key().to_bytes())
+    }
+}
+
+/// Load an Ed25519 node key from `key_file`, generating and saving one if the file does
+/// not exist. Without a file an ephemeral key is returned.
+///
+/// A key file that is not a 32-byte key is an error rather than replaced, as a new key
+/// would change the node's identity.
+pub fn load_or_generate_node_key(key_file: &Option<String>) -> Result<SigningKey> {
+    if let Some(file_path) = key_file {
+        // Try to load existing keypair
+        if std::path::Path::new(file_path).exists() {
+            let key_data = std::fs::read(file_path)?;
+            let key_bytes = <[u8; 32]>::try_from(key_data.as_slice()).map_err(|_| {
+                anyhow::anyhow!(
+                    "Node key file {} holds {} bytes instead of a 32-byte Ed25519 key",
+                    file_path,
+                    key_data.len()
+                )
+            })?;
+            info!("Loaded node keypair from {}", file_path);
+            return Ok(SigningKey::from_bytes(&key_bytes));
+        }
+
+        // Generate new keypair and save it
+        let keypair = SigningKey::from_bytes(&rand::random::<[u8; 32]>());
+        std::fs::write(file_path, keypair.to_bytes())?;
+        info!("Generated new node keypair and saved to {}", file_path);
+        Ok(keypair)
+    } else {
+        // Generate ephemeral keypair
+        Ok(SigningKey::from_bytes(&rand::random::<[u8; 32]>()))
+    }
+}
+
+/// Encryption state of an authenticated connection
+pub struct SecureSession {
+    /// Authenticated information about the remote peer
+    pub peer: PeerInfo,
+    /// Hex-encoded Ed25519 node key the peer proved possession of
+    pub peer_public_key: String,
+    send_key: LessSafeKey,
+    recv_key: LessSafeKey,
+    send_counter: u64,
+    recv_counter: u64,
+    /// Wire protocol version used for outgoing messages
+    protocol_version: u16,
+}
+
+impl std::fmt::Debug for SecureSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecureSession")
+            .field("peer", &self.peer)
+            .field("peer_public_key", &self.peer_public_key)
+            .field("send_counter", &self.send_counter)
+            .field("recv_counter", &self.recv_counter)
+            .field("protocol_version", &self.protocol_version)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SecureSession {
+    /// Encrypt and authenticate an outgoing frame
+    pub fn seal(&mut self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let nonce = counter_nonce(&mut self.send_counter)?;
+        let mut frame = plaintext.to_vec();
+        self.send_key
+            .seal_in_place_append_tag(nonce, Aad::from(PROTOCOL_ID.as_bytes()), &mut frame)
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt frame"))?;
+        Ok(frame)
+    }
+
+    /// Decrypt an incoming frame, rejecting forged, replayed or out-of-order frames
+    pub fn open(&mut self, frame: &[u8]) -> Result<Vec<u8>> {
+        let nonce = counter_nonce(&mut self.recv_counter)?;
+        let mut buffer = frame.to_vec();
+        let plaintext = self
+            .recv_key
+            .open_in_place(nonce, Aad::from(PROTOCOL_ID.as_bytes()), &mut buffer)
+            .map_err(|_| anyhow::anyhow!("Frame failed authentication"))?;
+        Ok(plaintext.to_vec())
+    }
+
+    /// Wire protocol version used for outgoing messages
+    pub fn protocol_version(&self) -> u16 {
+        self.protocol_version
+    }
+
+    /// Encrypt a message into a binary WebSocket frame
+    pub fn seal_message(&mut self, message: &P2PMessage) -> Result<Message> {
+        let encoded = message.encode(self.protocol_version)?;
+        Ok(Message::Binary(self.seal(&encoded)?))
+    }
+
+    /// Decrypt a binary WebSocket frame into a message.
+    ///
+    /// Protocol versions advertised in `PeerDiscovery`, chosen in `PeerList`, or used by
+    /// the peer's own frames switch outgoing messages to the newest version both sides
+    /// support.
+    pub fn open_message(&mut self, frame: &[u8]) -> Result<P2PMessage> {
+        let (message, frame_version) = P2PMessage::decode(&self.open(frame)?)?;
+        self.observe_protocol(&message, frame_version);
+        Ok(message)
+    }
+
+    fn observe_protocol(&mut self, message: &P2PMessage, frame_version: u16) {
+        let negotiated = match message {
+            P2PMessage::PeerDiscovery {
+                protocol_versions, ..
+            } => negotiate(SUPPORTED_PROTOCOL_VERSIONS, protocol_versions),
+            P2PMessage::PeerList {
+                protocol_version: Some(version),
+                ..
+            } => negotiate(SUPPORTED_PROTOCOL_VERSIONS, &[*version]),
+            _ => Some(frame_version.max(self.protocol_version)),
+        };
+
+        if let Some(version) = negotiated {
+            if version != self.protocol_version {
+                debug!(
+                    "Using wire protocol version {} with peer {}",
+                    version, self.peer.node_id
+                );
+                self.protocol_version = version;
+            }
+        }
+    }
+}
+
+/// Run the handshake over a freshly opened WebSocket.
+///
+/// On success the returned session encrypts all further traffic. If the remote peer
+/// fails authentication or the allow-list, it is sent an
+/// `ErrorCode::AuthenticationFailed` error and the handshake fails.
+pub async fn handshake<S>(
+    ws: &mut WebSocketStream<S>,
+    config: &HandshakeConfig,
+    role: HandshakeRole,
+) -> Result<SecureSession>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    tokio::time::timeout(HANDSHAKE_TIMEOUT, run_handshake(ws, config, role))
+        .await
+        .map_err(|_| anyhow::anyhow!("Handshake timed out"))?
+}
+
+async fn run_handshake<S>(
+    ws: &mut WebSocketStream<S>,
+    config: &HandshakeConfig,
+    role: HandshakeRole,
+) -> Result<SecureSession>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let rng = SystemRandom::new();
+    let ephemeral = EphemeralPrivateKey::generate(&X25519, &rng)
+        .map_err(|_| anyhow::anyhow!("Failed to generate ephemeral key"))?;
+    let ephemeral_public = ephemeral
+        .compute_public_key()
+        .map_err(|_| anyhow::anyhow!("Failed to compute ephemeral key"))?;
+
+    let local_hello = serde_json::to_string(&HandshakeHello {
+        protocol: PROTOCOL_ID.to_string(),
+        peer: config.local_info.clone(),
+        public_key: config.public_key(),
+        ephemeral_key: hex::encode(ephemeral_public.as_ref()),
+    })?;
+
+    // The initiator speaks first in each round
+    let remote_hello_text = match role {
+        HandshakeRole::Initiator => {
+            ws.send(Message::Text(local_hello.clone())).await?;
+            receive_text(ws).await?
+        }
+        HandshakeRole::Responder => {
+            let remote = receive_text(ws).await?;
+            ws.send(Message::Text(local_hello.clone())).await?;
+            remote
+        }
+    };
+    let remote_hello: HandshakeHello = serde_json::from_str(&remote_hello_text)
+        .map_err(|e| anyhow::anyhow!("Malformed handshake hello: {}", e))?;
+
+    if remote_hello.protocol != PROTOCOL_ID {
+        return reject(
+            ws,
+            ErrorCode::InvalidMessage,
+            format!("unsupported protocol {}", remote_hello.protocol),
+        )
+        .await;
+    }
+    if remote_hello.peer.network_id != config.local_info.network_id {
+        return reject(
+            ws,
+            ErrorCode::NetworkMismatch,
+            format!("peer is on network {}", remote_hello.peer.network_id),
+        )
+        .await;
+    }
+
+    let (initiator_hello, responder_hello) = match role {
+        HandshakeRole::Initiator => (&local_hello, &remote_hello_text),
+        HandshakeRole::Responder => (&remote_hello_text, &local_hello),
+    };
+    let transcript = transcript_hash(initiator_hello, responder_hello);
+
+    let local_auth = serde_json::to_string(&HandshakeAuth {
+        signature: hex::encode(
+            config
+                .signing_key
+                .sign(&auth_payload(role, &transcript))
+                .to_bytes(),
+        ),
+    })?;
+    let remote_auth_text = match role {
+        HandshakeRole::Initiator => {
+            ws.send(Message::Text(local_auth.clone())).await?;
+            receive_text(ws).await?
+        }
+        // The responder only proves its key once the initiator is admitted
+        HandshakeRole::Responder => receive_text(ws).await?,
+    };
+
+    let authenticated = serde_json::from_str::<HandshakeAuth>(&remote_auth_text)
+        .map_err(|e| anyhow::anyhow!("malformed handshake auth: {}", e))
+        .and_then(|auth| {
+            verify_auth(
+                &remote_hello.public_key,
+                &auth.signature,
+                &auth_payload(role.peer(), &transcript),
+            )
+        });
+    if let Err(e) = authenticated {
+        return reject(ws, ErrorCode::AuthenticationFailed, e.to_string()).await;
+    }
+    // Peers are tracked by node ID, so it must be bound to the key just proven
+    if authority_id_from_hex(&remote_hello.public_key) != Some(remote_hello.peer.node_id) {
+        return reject(
+            ws,
+            ErrorCode::AuthenticationFailed,
+            format!(
+                "node ID {} does not belong to key {}",
+                remote_hello.peer.node_id, remote_hello.public_key
+            ),
+        )
+        .await;
+    }
+    if let Err(e) = config
+        .allow_list
+        .check(&remote_hello.peer, &remote_hello.public_key)
+        .await
+    {
+        return reject(ws, ErrorCode::AuthenticationFailed, e.to_string()).await;
+    }
+
+    if role == HandshakeRole::Responder {
+        ws.send(Message::Text(local_auth)).await?;
+    }
+
+    let remote_ephemeral = hex::decode(&remote_hello.ephemeral_key)?;
+    let (initiator_key, responder_key) = agree_ephemeral(
+        ephemeral,
+        &UnparsedPublicKey::new(&X25519, &remote_ephemeral),
+        |shared_secret| {
+            let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, &transcript).extract(shared_secret);
+            (
+                session_key(&prk, b"provchain-session initiator"),
+                session_key(&prk, b"provchain-session responder"),
+            )
+        },
+    )
+    .map_err(|_| anyhow::anyhow!("Key agreement failed"))?;
+
+    let (send_key, recv_key) = match role {
+        HandshakeRole::Initiator => (initiator_key?, responder_key?),
+        HandshakeRole::Responder => (responder_key?, initiator_key?),
+    };
+
+    info!(
+        "Authenticated peer {} ({})",
+        remote_hello.peer.node_id, remote_hello.public_key
+    );
+    let mut peer = remote_hello.peer;
+    peer.update_last_seen();
+
+    Ok(SecureSession {
+        peer,
+        peer_public_key: remote_hello.public_key,
+        send_key,
+        recv_key,
+        send_counter: 0,
+        recv_counter: 0,
+        // Every node speaks JSON; PeerDiscovery upgrades the session from there
+        protocol_version: PROTOCOL_VERSION_JSON,
+    })
+}
+
+/// Tell the peer why it was rejected and fail the handshake
+async fn reject<S>(
+    ws: &mut WebSocketStream<S>,
+    error_code: ErrorCode,
+    reason: String,
+) -> Result<SecureSession>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    warn!("Rejecting peer during handshake: {}", reason);
+    let error = P2PMessage::new_error(error_code.clone(), reason.clone());
+    if let Ok(bytes) = error.to_bytes() {
+        let _ = ws.send(Message::Text(String::from_utf8(bytes)?)).await;
+    }
+    let _ = ws.close(None).await;
+    anyhow::bail!("Handshake failed ({:?}): {}", error_code, reason)
+}
+
+/// Read the next text frame of the handshake, surfacing errors sent by the peer
+async fn receive_text<S>(ws: &mut WebSocketStream<S>) -> Result<String>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    loop {
+        match ws.next().await {
+            Some(Ok(Message::Text(text))) => {
+                if let Ok(P2PMessage::Error {
+                    error_code,
+                    message,
+                    ..
+                }) = P2PMessage::from_bytes(text.as_bytes())
+                {
+                    anyhow::bail!("Peer rejected handshake ({:?}): {}", error_code, message);
+                }
+                return Ok(text);
+            }
+            Some(Ok(Message::Ping(_))) | Some(Ok(Message::Pong(_))) => continue,
+            Some(Ok(Message::Close(_))) | None => {
+                anyhow::bail!("Connection closed during handshake")
+            }
+            Some(Ok(_)) => anyhow::bail!("Unexpected frame during handshake"),
+            Some(Err(e)) => return Err(e.into()),
+        }
+    }
+}
+
+fn transcript_hash(initiator_hello: &str, responder_hello: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(PROTOCOL_ID.as_bytes());
+    for hello in [initiator_hello, responder_hello] {
+        hasher.update((hello.len() as u64).to_be_bytes());
+        hasher.update(hello.as_bytes());
+    }
+    hasher.finalize().into()
+}
+
+fn auth_payload(role: HandshakeRole, transcript: &[u8; 32]) -> Vec<u8> {
+    let mut payload = b"provchain-handshake:".to_vec();
+    payload.extend_from_slice(role.label());
+    payload.push(b':');
+    payload.extend_from_slice(transcript);
+    payload
+}
+
+fn verify_auth(public_key: &str, signature: &str, payload: &[u8]) -> Result<()> {
+    let key_bytes: [u8; 32] = hex::decode(public_key)?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("invalid node key length"))?;
+    let signature_bytes: [u8; 64] = hex::decode(signature)?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("invalid signature length"))?;
+
+    VerifyingKey::from_bytes(&key_bytes)?
+        .verify(payload, &Signature::from_bytes(&signature_bytes))
+        .map_err(|_| anyhow::anyhow!("peer did not prove possession of its node key"))
+}
+
+fn session_key(prk: &hkdf::Prk, info: &[u8]) -> Result<LessSafeKey> {
+    let info = [info];
+    let okm = prk
+        .expand(&info, &CHACHA20_POLY1305)
+        .map_err(|_| anyhow::anyhow!("Session key derivation failed"))?;
+    Ok(LessSafeKey::new(UnboundKey::from(okm)))
+}
+
+fn counter_nonce(counter: &mut u64) -> Result<Nonce> {
+    let mut nonce = [0u8; 12];
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    *counter = counter
+        .checked_add(1)
+        .ok_or_else(|| anyhow::anyhow!("Session nonce space exhausted"))?;
+    Ok(Nonce::assume_unique_for_key(nonce))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::codec::PROTOCOL_VERSION_BINARY;
+    use tokio_tungstenite::tungstenite::protocol::Role;
+    use uuid::Uuid;
+
+    fn config(seed: u8, is_authority: bool) -> HandshakeConfig {
+        let info = PeerInfo::new(
+            Uuid::new_v4(),
+            "127.0.0.1".to_string(),
+            8080 + seed as u16,
+            "test-network".to_string(),
+            is_authority,
+        );
+        let mut config = HandshakeConfig::new(SigningKey::from_bytes(&[seed; 32]), info);
+        config.allow_list.allow_observers = true;
+        config
+    }
+
+    async fn connect(
+        initiator: &HandshakeConfig,
+        responder: &HandshakeConfig,
+    ) -> (Result<SecureSession>, Result<SecureSession>) {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let mut client = WebSocketStream::from_raw_socket(client, Role::Client, None).await;
+        let mut server = WebSocketStream::from_raw_socket(server, Role::Server, None).await;
+
+        tokio::join!(
+            handshake(&mut client, initiator, HandshakeRole::Initiator),
+            handshake(&mut server, responder, HandshakeRole::Responder),
+        )
+    }
+
+    #[test]
+    fn test_malformed_node_key_file_is_not_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("node.key");
+        let key_file = Some(path.to_string_lossy().to_string());
+
+        let generated = load_or_generate_node_key(&key_file).unwrap();
+        let loaded = load_or_generate_node_key(&key_file).unwrap();
+        assert_eq!(generated.to_bytes(), loaded.to_bytes());
+
+        std::fs::write(&path, b"truncated").unwrap();
+        let error = load_or_generate_node_key(&key_file).unwrap_err();
+        assert!(error.to_string().contains("32-byte"));
+        assert_eq!(std::fs::read(&path).unwrap(), b"truncated");
+    }
+
+    #[tokio::test]
+    async fn test_handshake_establishes_encrypted_session() {
+        let initiator = config(1, false);
+        let responder = config(2, false);
+
+        let (client, server) = connect(&initiator, &responder).await;
+        let (mut client, mut server) = (client.unwrap(), server.unwrap());
+        assert_eq!(server.peer_public_key, initiator.public_key());
+        assert_eq!(client.peer.node_id, responder.local_info.node_id);
+
+        let message = P2PMessage::new_error(ErrorCode::InternalError, "ping".to_string());
+        let frame = client.seal(&message.to_bytes().unwrap()).unwrap();
+        assert!(!frame.windows(4).any(|window| window == b"ping".as_slice()));
+        let received = P2PMessage::from_bytes(&server.open(&frame).unwrap()).unwrap();
+        assert_eq!(received.message_type(), message.message_type());
+
+        // A replayed or tampered frame does not open
+        assert!(server.open(&frame).is_err());
+        let mut tampered = client.seal(b"hello").unwrap();
+        tampered[0] ^= 1;
+        assert!(server.open(&tampered).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_node_id_is_bound_to_node_key() {
+        let initiator = config(1, false);
+        let responder = config(2, false);
+        assert_eq!(
+            initiator.local_info.node_id,
+            authority_id(&initiator.signing_key.verifying_key())
+        );
+
+        // A peer announcing another node's ID cannot take its place
+        let mut impostor = config(3, false);
+        impostor.local_info.node_id = initiator.local_info.node_id;
+        let (client, server) = connect(&impostor, &responder).await;
+        assert!(client.is_err());
+        assert!(server.unwrap_err().to_string().contains("does not belong"));
+    }
+
+    #[tokio::test]
+    async fn test_peer_discovery_upgrades_wire_protocol() {
+        let initiator = config(1, false);
+        let responder = config(2, false);
+
+        let (client, server) = connect(&initiator, &responder).await;
+        let (mut client, mut server) = (client.unwrap(), server.unwrap());
+        assert_eq!(client.protocol_version(), PROTOCOL_VERSION_JSON);
+
+        let discovery = P2PMessage::new_peer_discovery(
+            initiator.local_info.node_id,
+            8081,
+            "test-network".to_string(),
+        );
+        let Message::Binary(frame) = client.seal_message(&discovery).unwrap() else {
+            panic!("expected a binary frame");
+        };
+        server.open_message(&frame).unwrap();
+        assert_eq!(server.protocol_version(), PROTOCOL_VERSION_BINARY);
+
+        let peer_list = P2PMessage::PeerList {
+            peers: vec![],
+            timestamp: chrono::Utc::now(),
+            protocol_version: Some(server.protocol_version()),
+        };
+        let Message::Binary(frame) = server.seal_message(&peer_list).unwrap() else {
+            panic!("expected a binary frame");
+        };
+        client.open_message(&frame).unwrap();
+        assert_eq!(client.protocol_version(), PROTOCOL_VERSION_BINARY);
+    }
+
+    #[tokio::test]
+    async fn test_authority_claim_requires_validator_key() {
+        let initiator = config(1, true);
+        let responder = config(2, false);
+
+        let (client, server) = connect(&initiator, &responder).await;
+        assert!(server
+            .unwrap_err()
+            .to_string()
+            .contains("AuthenticationFailed"));
+        assert!(client
+            .unwrap_err()
+            .to_string()
+            .contains("AuthenticationFailed"));
+
+        responder
+            .allow_list
+            .validator_set
+            .write()
+            .await
+            .insert(initiator.public_key());
+        let (client, server) = connect(&initiator, &responder).await;
+        assert!(client.is_ok());
+        assert!(server.unwrap().peer.is_authority);
+    }
+
+    #[tokio::test]
+    async fn test_closed_allow_list_rejects_unknown_peers() {
+        let initiator = config(1, false);
+        let mut responder = config(2, false);
+        responder.allow_list.allow_observers = false;
+        assert!(!PeerAllowList::new(HashSet::new()).allow_observers);
+
+        let (client, server) = connect(&initiator, &responder).await;
+        assert!(client.is_err());
+        assert!(server.is_err());
+
+        responder
+            .allow_list
+            .trusted_keys
+            .insert(initiator.public_key());
+        let (client, server) = connect(&initiator, &responder).await;
+        assert!(client.is_ok() && server.is_ok());
+    }
+}
@@ -555,6 +555,11 @@ impl OntologyManager {
         &self.domain_config.supported_transaction_types
     }
 
+    /// Store holding the loaded core and domain ontologies
+    pub fn ontology_store(&self) -> &Store {
+        &self.ontology_store
+    }
+
     /// Query the ontology store
     pub fn query_ontology(&self, sparql_query: &str) -> Result<String, OntologyError> {
         use oxigraph::sparql::QueryResults;
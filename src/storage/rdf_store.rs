@@ -11,6 +11,8 @@ use std::time::{Instant, SystemTime};
 use tracing::{debug, error, info, warn};
 
 use crate::core::blockchain::Block;
+use crate::core::finality::{FinalityCertificate, FINALITY_GRAPH};
+use crate::governance::{GovernanceGenesis, NetworkConfig, Proposal, GOVERNANCE_GRAPH};
 // use crate::shacl_validator::{ShaclValidator, ShaclConfig, ShaclValidationResult};
 
 /// Graph complexity classification for adaptive canonicalization
@@ -382,6 +384,26 @@ impl RDFStore {
         Ok(())
     }
 
+    /// Serialize every quad in the store as N-Quads
+    pub fn export_nquads(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        self.store
+            .dump_to_writer(RdfFormat::NQuads, &mut buffer)
+            .with_context(|| "Failed to serialize store as N-Quads")?;
+        String::from_utf8(buffer).with_context(|| "Store N-Quads are not valid UTF-8")
+    }
+
+    /// Replace the whole store with the given N-Quads, e.g. an installed snapshot
+    pub fn replace_contents(&mut self, nquads: &str) -> Result<()> {
+        self.store
+            .clear()
+            .with_context(|| "Failed to clear store")?;
+        if let Some(cache) = self.memory_cache.as_mut() {
+            cache.clear();
+        }
+        self.load_data_from_string(nquads)
+    }
+
     /// Warm up the memory cache with frequently accessed data
     fn warm_cache(&mut self) -> Result<()> {
         // First, get all named graphs without holding a mutable reference to cache
@@ -1137,6 +1159,543 @@ impl RDFStore {
         for quad in &quads {
             self.store.insert(quad).unwrap();
         }
+
+        self.add_block_commitments(block);
+    }
+
+    /// Persist a finality certificate in the finality graph
+    pub fn add_finality_certificate(&mut self, certificate: &FinalityCertificate) -> Result<()> {
+        let graph_name = NamedNode::new(FINALITY_GRAPH)?;
+        let certificate_uri = NamedNode::new(format!(
+            "http://provchain.org/finality/{}",
+            certificate.block_index
+        ))?;
+        let block_uri = NamedNode::new(format!(
+            "http://provchain.org/block/{}",
+            certificate.block_index
+        ))?;
+
+        let quads = [
+            Quad::new(
+                certificate_uri.clone(),
+                NamedNode::new("http://www.w3.org/1999/02/22-rdf-syntax-ns#type")?,
+                NamedNode::new("http://provchain.org/FinalityCertificate")?,
+                graph_name.clone(),
+            ),
+            Quad::new(
+                certificate_uri.clone(),
+                NamedNode::new("http://provchain.org/finalizes")?,
+                block_uri,
+                graph_name.clone(),
+            ),
+            Quad::new(
+                certificate_uri.clone(),
+                NamedNode::new("http://provchain.org/hasIndex")?,
+                Literal::new_typed_literal(
+                    certificate.block_index.to_string(),
+                    NamedNode::new_unchecked("http://www.w3.org/2001/XMLSchema#integer"),
+                ),
+                graph_name.clone(),
+            ),
+            Quad::new(
+                certificate_uri.clone(),
+                NamedNode::new("http://provchain.org/hasHash")?,
+                Literal::new_simple_literal(certificate.block_hash.clone()),
+                graph_name.clone(),
+            ),
+            Quad::new(
+                certificate_uri,
+                NamedNode::new("http://provchain.org/hasCertificateData")?,
+                Literal::new_simple_literal(serde_json::to_string(certificate)?),
+                graph_name,
+            ),
+        ];
+        for quad in &quads {
+            self.store.insert(quad)?;
+        }
+
+        Ok(())
+    }
+
+    /// Load the finality certificate with the highest block index, if any
+    pub fn latest_finality_certificate(&self) -> Option<FinalityCertificate> {
+        let query = format!(
+            r#"
+            PREFIX prov: <http://provchain.org/>
+            SELECT ?data WHERE {{
+                GRAPH <{}> {{
+                    ?certificate a prov:FinalityCertificate ;
+                                 prov:hasIndex ?index ;
+                                 prov:hasCertificateData ?data .
+                }}
+            }}
+            ORDER BY DESC(?index)
+            LIMIT 1
+        "#,
+            FINALITY_GRAPH
+        );
+
+        if let Ok(QueryResults::Solutions(mut solutions)) = self.store.query(&query) {
+            if let Some(Ok(solution)) = solutions.next() {
+                if let Some(Term::Literal(data)) = solution.get("data") {
+                    return serde_json::from_str(data.value()).ok();
+                }
+            }
+        }
+        None
+    }
+
+    /// Record a governance proposal and its votes in the governance graph.
+    ///
+    /// The proposal's status and data are replaced on every call, so the graph always
+    /// reflects the latest state; votes are only ever added and form the audit trail.
+    pub fn record_governance_proposal(&mut self, proposal: &Proposal) -> Result<()> {
+        let graph_name = NamedNode::new(GOVERNANCE_GRAPH)?;
+        let proposal_uri = NamedNode::new(format!(
+            "http://provchain.org/governance/proposal/{}",
+            proposal.id
+        ))?;
+        let xsd_integer = NamedNode::new_unchecked("http://www.w3.org/2001/XMLSchema#integer");
+
+        let previous: Vec<Quad> = self
+            .store
+            .quads_for_pattern(
+                Some(proposal_uri.as_ref().into()),
+                None,
+                None,
+                Some(graph_name.as_ref().into()),
+            )
+            .collect::<std::result::Result<_, _>>()?;
+        for quad in &previous {
+            self.store.remove(quad)?;
+        }
+
+        let mut quads = vec![
+            Quad::new(
+                proposal_uri.clone(),
+                NamedNode::new("http://www.w3.org/1999/02/22-rdf-syntax-ns#type")?,
+                NamedNode::new("http://provchain.org/GovernanceProposal")?,
+                graph_name.clone(),
+            ),
+            Quad::new(
+                proposal_uri.clone(),
+                NamedNode::new("http://provchain.org/hasAction")?,
+                Literal::new_simple_literal(proposal.action_name()),
+                graph_name.clone(),
+            ),
+            Quad::new(
+                proposal_uri.clone(),
+                NamedNode::new("http://provchain.org/hasActionData")?,
+                Literal::new_simple_literal(serde_json::to_string(&proposal.action)?),
+                graph_name.clone(),
+            ),
+            Quad::new(
+                proposal_uri.clone(),
+                NamedNode::new("http://provchain.org/openedAtBlock")?,
+                Literal::new_typed_literal(
+                    proposal.opened_at_block.to_string(),
+                    xsd_integer.clone(),
+                ),
+                graph_name.clone(),
+            ),
+            Quad::new(
+                proposal_uri.clone(),
+                NamedNode::new("http://provchain.org/expiresAtBlock")?,
+                Literal::new_typed_literal(
+                    proposal.expires_at_block.to_string(),
+                    xsd_integer.clone(),
+                ),
+                graph_name.clone(),
+            ),
+            Quad::new(
+                proposal_uri.clone(),
+                NamedNode::new("http://provchain.org/hasStatus")?,
+                Literal::new_simple_literal(proposal.status.to_string()),
+                graph_name.clone(),
+            ),
+            Quad::new(
+                proposal_uri.clone(),
+                NamedNode::new("http://provchain.org/hasProposalData")?,
+                Literal::new_simple_literal(serde_json::to_string(proposal)?),
+                graph_name.clone(),
+            ),
+        ];
+
+        for vote in &proposal.votes {
+            let vote_uri = NamedNode::new(format!(
+                "http://provchain.org/governance/proposal/{}/vote/{}",
+                proposal.id, vote.validator
+            ))?;
+            quads.extend([
+                Quad::new(
+                    vote_uri.clone(),
+                    NamedNode::new("http://www.w3.org/1999/02/22-rdf-syntax-ns#type")?,
+                    NamedNode::new("http://provchain.org/GovernanceVote")?,
+                    graph_name.clone(),
+                ),
+                Quad::new(
+                    vote_uri.clone(),
+                    NamedNode::new("http://provchain.org/onProposal")?,
+                    proposal_uri.clone(),
+                    graph_name.clone(),
+                ),
+                Quad::new(
+                    vote_uri.clone(),
+                    NamedNode::new("http://provchain.org/castBy")?,
+                    Literal::new_simple_literal(vote.validator.clone()),
+                    graph_name.clone(),
+                ),
+                Quad::new(
+                    vote_uri.clone(),
+                    NamedNode::new("http://provchain.org/atBlock")?,
+                    Literal::new_typed_literal(vote.block_index.to_string(), xsd_integer.clone()),
+                    graph_name.clone(),
+                ),
+                Quad::new(
+                    vote_uri.clone(),
+                    NamedNode::new("http://provchain.org/hasSignature")?,
+                    Literal::new_simple_literal(vote.signature.clone()),
+                    graph_name.clone(),
+                ),
+                Quad::new(
+                    vote_uri,
+                    NamedNode::new("http://provchain.org/hasTimestamp")?,
+                    Literal::new_typed_literal(
+                        vote.timestamp
+                            .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                        NamedNode::new_unchecked("http://www.w3.org/2001/XMLSchema#dateTime"),
+                    ),
+                    graph_name.clone(),
+                ),
+            ]);
+        }
+
+        for quad in &quads {
+            self.store.insert(quad)?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove a governance proposal and its votes from the governance graph. Used when
+    /// a rollback removes the block that opened it.
+    pub fn remove_governance_proposal(&mut self, proposal_id: &str) -> Result<()> {
+        let graph_name = NamedNode::new(GOVERNANCE_GRAPH)?;
+        let proposal_uri = NamedNode::new(format!(
+            "http://provchain.org/governance/proposal/{}",
+            proposal_id
+        ))?;
+        let on_proposal = NamedNode::new("http://provchain.org/onProposal")?;
+
+        let votes: Vec<Subject> = self
+            .store
+            .quads_for_pattern(
+                None,
+                Some(on_proposal.as_ref()),
+                Some(proposal_uri.as_ref().into()),
+                Some(graph_name.as_ref().into()),
+            )
+            .map(|quad| quad.map(|quad| quad.subject))
+            .collect::<std::result::Result<_, _>>()?;
+        let mut previous = Vec::new();
+        for subject in votes
+            .iter()
+            .map(Subject::as_ref)
+            .chain([proposal_uri.as_ref().into()])
+        {
+            for quad in self.store.quads_for_pattern(
+                Some(subject),
+                None,
+                None,
+                Some(graph_name.as_ref().into()),
+            ) {
+                previous.push(quad?);
+            }
+        }
+        for quad in &previous {
+            self.store.remove(quad)?;
+        }
+
+        Ok(())
+    }
+
+    /// Load all governance proposals recorded in the governance graph
+    pub fn governance_proposals(&self) -> Vec<Proposal> {
+        let query = format!(
+            r#"
+            PREFIX prov: <http://provchain.org/>
+            SELECT ?data WHERE {{
+                GRAPH <{}> {{
+                    ?proposal a prov:GovernanceProposal ;
+                              prov:hasProposalData ?data .
+                }}
+            }}
+        "#,
+            GOVERNANCE_GRAPH
+        );
+
+        let mut proposals = Vec::new();
+        if let Ok(QueryResults::Solutions(solutions)) = self.store.query(&query) {
+            for solution in solutions.flatten() {
+                if let Some(Term::Literal(data)) = solution.get("data") {
+                    match serde_json::from_str(data.value()) {
+                        Ok(proposal) => proposals.push(proposal),
+                        Err(e) => warn!("Skipping unreadable governance proposal: {}", e),
+                    }
+                }
+            }
+        }
+        proposals
+    }
+
+    /// Record the network configuration in the governance graph, one triple per key
+    /// under `http://provchain.org/config/`, replacing the previous values
+    pub fn record_network_config(&mut self, config: &NetworkConfig) -> Result<()> {
+        let graph_name = NamedNode::new(GOVERNANCE_GRAPH)?;
+        let config_uri = NamedNode::new("http://provchain.org/governance/config")?;
+
+        let previous: Vec<Quad> = self
+            .store
+            .quads_for_pattern(
+                Some(config_uri.as_ref().into()),
+                None,
+                None,
+                Some(graph_name.as_ref().into()),
+            )
+            .collect::<std::result::Result<_, _>>()?;
+        for quad in &previous {
+            self.store.remove(quad)?;
+        }
+
+        self.store.insert(&Quad::new(
+            config_uri.clone(),
+            NamedNode::new("http://www.w3.org/1999/02/22-rdf-syntax-ns#type")?,
+            NamedNode::new("http://provchain.org/NetworkConfig")?,
+            graph_name.clone(),
+        ))?;
+        self.store.insert(&Quad::new(
+            config_uri.clone(),
+            NamedNode::new("http://provchain.org/hasConfigData")?,
+            Literal::new_simple_literal(serde_json::to_string(config)?),
+            graph_name.clone(),
+        ))?;
+        for (key, value) in config.entries() {
+            self.store.insert(&Quad::new(
+                config_uri.clone(),
+                NamedNode::new(format!("http://provchain.org/config/{}", key))?,
+                Literal::new_simple_literal(value),
+                graph_name.clone(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Load the network configuration recorded in the governance graph, if any
+    pub fn network_config(&self) -> Option<NetworkConfig> {
+        let query = format!(
+            r#"
+            PREFIX prov: <http://provchain.org/>
+            SELECT ?data WHERE {{
+                GRAPH <{}> {{
+                    <http://provchain.org/governance/config> prov:hasConfigData ?data .
+                }}
+            }}
+        "#,
+            GOVERNANCE_GRAPH
+        );
+
+        if let Ok(QueryResults::Solutions(mut solutions)) = self.store.query(&query) {
+            if let Some(Ok(solution)) = solutions.next() {
+                if let Some(Term::Literal(data)) = solution.get("data") {
+                    return serde_json::from_str(data.value()).ok();
+                }
+            }
+        }
+        None
+    }
+
+    /// Record the genesis governance the chain's governance transactions are replayed
+    /// from, replacing any previous record
+    pub fn record_governance_genesis(&mut self, genesis: &GovernanceGenesis) -> Result<()> {
+        let graph_name = NamedNode::new(GOVERNANCE_GRAPH)?;
+        let genesis_uri = NamedNode::new("http://provchain.org/governance/genesis")?;
+
+        let previous: Vec<Quad> = self
+            .store
+            .quads_for_pattern(
+                Some(genesis_uri.as_ref().into()),
+                None,
+                None,
+                Some(graph_name.as_ref().into()),
+            )
+            .collect::<std::result::Result<_, _>>()?;
+        for quad in &previous {
+            self.store.remove(quad)?;
+        }
+
+        self.store.insert(&Quad::new(
+            genesis_uri.clone(),
+            NamedNode::new("http://www.w3.org/1999/02/22-rdf-syntax-ns#type")?,
+            NamedNode::new("http://provchain.org/GovernanceGenesis")?,
+            graph_name.clone(),
+        ))?;
+        self.store.insert(&Quad::new(
+            genesis_uri,
+            NamedNode::new("http://provchain.org/hasGenesisData")?,
+            Literal::new_simple_literal(serde_json::to_string(genesis)?),
+            graph_name,
+        ))?;
+
+        Ok(())
+    }
+
+    /// Load the genesis governance recorded in the governance graph, if any
+    pub fn governance_genesis(&self) -> Option<GovernanceGenesis> {
+        let query = format!(
+            r#"
+            PREFIX prov: <http://provchain.org/>
+            SELECT ?data WHERE {{
+                GRAPH <{}> {{
+                    <http://provchain.org/governance/genesis> prov:hasGenesisData ?data .
+                }}
+            }}
+        "#,
+            GOVERNANCE_GRAPH
+        );
+
+        if let Ok(QueryResults::Solutions(mut solutions)) = self.store.query(&query) {
+            if let Some(Ok(solution)) = solutions.next() {
+                if let Some(Term::Literal(data)) = solution.get("data") {
+                    return serde_json::from_str(data.value()).ok();
+                }
+            }
+        }
+        None
+    }
+
+    /// Record the validator set in the governance graph, one `prov:hasValidator` triple
+    /// per validator key, replacing the previous set
+    pub fn record_validator_set(&mut self, validator_set: &HashSet<String>) -> Result<()> {
+        let graph_name = NamedNode::new(GOVERNANCE_GRAPH)?;
+        let set_uri = NamedNode::new("http://provchain.org/governance/validators")?;
+
+        let previous: Vec<Quad> = self
+            .store
+            .quads_for_pattern(
+                Some(set_uri.as_ref().into()),
+                None,
+                None,
+                Some(graph_name.as_ref().into()),
+            )
+            .collect::<std::result::Result<_, _>>()?;
+        for quad in &previous {
+            self.store.remove(quad)?;
+        }
+
+        self.store.insert(&Quad::new(
+            set_uri.clone(),
+            NamedNode::new("http://www.w3.org/1999/02/22-rdf-syntax-ns#type")?,
+            NamedNode::new("http://provchain.org/ValidatorSet")?,
+            graph_name.clone(),
+        ))?;
+        for validator in validator_set {
+            self.store.insert(&Quad::new(
+                set_uri.clone(),
+                NamedNode::new("http://provchain.org/hasValidator")?,
+                Literal::new_simple_literal(validator),
+                graph_name.clone(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Load the validator set recorded in the governance graph, if any
+    pub fn validator_set(&self) -> Option<HashSet<String>> {
+        let graph_name = NamedNode::new(GOVERNANCE_GRAPH).ok()?;
+        let set_uri = NamedNode::new("http://provchain.org/governance/validators").ok()?;
+        let rdf_type = NamedNode::new("http://www.w3.org/1999/02/22-rdf-syntax-ns#type").ok()?;
+        let has_validator = NamedNode::new("http://provchain.org/hasValidator").ok()?;
+
+        let recorded = self
+            .store
+            .quads_for_pattern(
+                Some(set_uri.as_ref().into()),
+                Some(rdf_type.as_ref()),
+                None,
+                Some(graph_name.as_ref().into()),
+            )
+            .next()
+            .is_some();
+        if !recorded {
+            return None;
+        }
+
+        Some(
+            self.store
+                .quads_for_pattern(
+                    Some(set_uri.as_ref().into()),
+                    Some(has_validator.as_ref()),
+                    None,
+                    Some(graph_name.as_ref().into()),
+                )
+                .filter_map(|quad| match quad.ok()?.object {
+                    Term::Literal(literal) => Some(literal.value().to_string()),
+                    _ => None,
+                })
+                .collect(),
+        )
+    }
+
+    /// Remove a block's data graph and its metadata from the blockchain graph.
+    /// Used when a chain reorganization rolls a block back.
+    pub fn remove_block(&mut self, index: u64) -> Result<()> {
+        let block_uri = NamedNode::new(format!("http://provchain.org/block/{}", index))?;
+        let blockchain_graph = NamedNode::new("http://provchain.org/blockchain")?;
+
+        self.store
+            .clear_graph(block_uri.as_ref())
+            .with_context(|| format!("Failed to clear graph of block {}", index))?;
+
+        let metadata: Vec<Quad> = self
+            .store
+            .quads_for_pattern(
+                Some(block_uri.as_ref().into()),
+                None,
+                None,
+                Some(blockchain_graph.as_ref().into()),
+            )
+            .collect::<std::result::Result<_, _>>()?;
+        for quad in &metadata {
+            self.store.remove(quad)?;
+        }
+
+        Ok(())
+    }
+
+    /// Record the committed canonical data hash and Merkle root of a block in the
+    /// blockchain metadata graph
+    pub fn add_block_commitments(&mut self, block: &Block) {
+        let block_uri =
+            NamedNode::new(format!("http://provchain.org/block/{}", block.index)).unwrap();
+        let blockchain_graph = NamedNode::new("http://provchain.org/blockchain").unwrap();
+
+        for (predicate, value) in [
+            ("http://provchain.org/hasDataHash", &block.data_hash),
+            ("http://provchain.org/hasMerkleRoot", &block.merkle_root),
+        ] {
+            if value.is_empty() {
+                continue;
+            }
+            let quad = Quad::new(
+                block_uri.clone(),
+                NamedNode::new(predicate).unwrap(),
+                Literal::new_simple_literal(value.clone()),
+                blockchain_graph.clone(),
+            );
+            self.store.insert(&quad).unwrap();
+        }
     }
 
     pub fn query(&self, sparql: &str) -> QueryResults {
@@ -1620,6 +2179,18 @@ impl RDFStore {
 
     /// W3C RDFC-1.0 (RDF Dataset Canonicalization) implementation
     pub fn canonicalize_graph_rdfc10(&self, graph_name: &NamedNode) -> String {
+        let canonical_quads = self.canonical_nquads_rdfc10(graph_name);
+
+        // Hash the canonical N-Quads representation
+        let canonical_nquads = canonical_quads.join("\n");
+        self.hash_string(&canonical_nquads)
+    }
+
+    /// Canonical N-Quads lines of a graph under RDFC-1.0, sorted lexicographically.
+    ///
+    /// These lines are the leaves of the block Merkle tree, so a single quad can be
+    /// proven against a block header without the rest of the graph.
+    pub fn canonical_nquads_rdfc10(&self, graph_name: &NamedNode) -> Vec<String> {
         // Collect all quads in the specified graph
         let mut quads = Vec::new();
         for quad in self
@@ -1631,7 +2202,7 @@ impl RDFStore {
         }
 
         if quads.is_empty() {
-            return self.hash_string("");
+            return Vec::new();
         }
 
         // Step 1: Create canonical state
@@ -1664,8 +2235,11 @@ impl RDFStore {
                 .push(blank_node.clone());
         }
 
-        // Step 3: Issue canonical identifiers for unique hashes
-        for blank_nodes in hash_to_blank_nodes.values() {
+        // Step 3: Issue canonical identifiers for unique hashes, in hash order so the
+        // labels do not depend on HashMap iteration order
+        let mut sorted_first_degree: Vec<_> = hash_to_blank_nodes.iter().collect();
+        sorted_first_degree.sort_by(|a, b| a.0.cmp(b.0));
+        for (_, blank_nodes) in &sorted_first_degree {
             if blank_nodes.len() == 1 {
                 canonical_issuer.issue(Some(&blank_nodes[0]));
             }
@@ -1673,9 +2247,9 @@ impl RDFStore {
 
         // Step 4: Process shared hashes using N-degree hashing
         let mut hash_path_list = Vec::new();
-        for blank_nodes in hash_to_blank_nodes.values() {
+        for (_, blank_nodes) in &sorted_first_degree {
             if blank_nodes.len() > 1 {
-                for blank_node in blank_nodes {
+                for blank_node in blank_nodes.iter() {
                     if !canonical_issuer.issued.contains_key(blank_node) {
                         let (hash_result, _) = self.hash_n_degree_quads(
                             blank_node,
@@ -1705,10 +2279,7 @@ impl RDFStore {
 
         // Sort canonical quads lexicographically
         canonical_quads.sort();
-
-        // Step 6: Hash the canonical N-Quads representation
-        let canonical_nquads = canonical_quads.join("\n");
-        self.hash_string(&canonical_nquads)
+        canonical_quads
     }
 
     /// Hash first-degree quads for RDFC-1.0 algorithm
@@ -6,6 +6,7 @@
 //! traversal for supply chain traceability queries.
 
 use crate::core::blockchain::Blockchain;
+use crate::storage::rdf_store::RDFStore;
 use std::collections::{HashMap, HashSet};
 use std::time::Instant;
 
@@ -63,13 +64,18 @@ impl TraceFrontier {
 
 /// Trace pivot selector for identifying key traceability entities
 pub struct TracePivotSelector<'a> {
-    pub blockchain: &'a Blockchain,
+    pub rdf_store: &'a RDFStore,
 }
 
 impl<'a> TracePivotSelector<'a> {
     /// Create a new pivot selector
     pub fn new(blockchain: &'a Blockchain) -> Self {
-        TracePivotSelector { blockchain }
+        Self::with_store(&blockchain.rdf_store)
+    }
+
+    /// Create a pivot selector over `rdf_store` instead of a chain's whole store
+    pub fn with_store(rdf_store: &'a RDFStore) -> Self {
+        TracePivotSelector { rdf_store }
     }
 
     /// Find pivot entities in the current frontier
@@ -128,9 +134,7 @@ impl<'a> TracePivotSelector<'a> {
             entity, entity
         );
 
-        if let oxigraph::sparql::QueryResults::Solutions(solutions) =
-            self.blockchain.rdf_store.query(&query)
-        {
+        if let oxigraph::sparql::QueryResults::Solutions(solutions) = self.rdf_store.query(&query) {
             let mut connected_count = 0;
             for sol in solutions.flatten() {
                 if let Some(connected_term) = sol.get("connected") {
@@ -180,13 +184,19 @@ pub struct TraceEvent {
 
 /// Enhanced traceability system applying SSSP concepts
 pub struct EnhancedTraceabilitySystem<'a> {
-    pub blockchain: &'a Blockchain,
+    pub rdf_store: &'a RDFStore,
 }
 
 impl<'a> EnhancedTraceabilitySystem<'a> {
     /// Create a new enhanced traceability system
     pub fn new(blockchain: &'a Blockchain) -> Self {
-        EnhancedTraceabilitySystem { blockchain }
+        Self::with_store(&blockchain.rdf_store)
+    }
+
+    /// Trace over `rdf_store` instead of a chain's whole store, e.g. only the graphs a
+    /// caller may read
+    pub fn with_store(rdf_store: &'a RDFStore) -> Self {
+        EnhancedTraceabilitySystem { rdf_store }
     }
 
     /// Enhanced trace function applying frontier reduction and pivot selection
@@ -200,7 +210,7 @@ impl<'a> EnhancedTraceabilitySystem<'a> {
         let mut visited = HashSet::new();
 
         // Create pivot selector
-        let pivot_selector = TracePivotSelector::new(self.blockchain);
+        let pivot_selector = TracePivotSelector::with_store(self.rdf_store);
 
         // Maximum trace depth to prevent infinite loops
         const MAX_TRACE_DEPTH: usize = 50;
@@ -328,7 +338,7 @@ impl<'a> EnhancedTraceabilitySystem<'a> {
             );
 
             if let oxigraph::sparql::QueryResults::Solutions(solutions) =
-                self.blockchain.rdf_store.query(&outgoing_query)
+                self.rdf_store.query(&outgoing_query)
             {
                 for sol in solutions.flatten() {
                     if let Some(target_term) = sol.get("target") {
@@ -411,9 +421,7 @@ impl<'a> EnhancedTraceabilitySystem<'a> {
             entity, entity
         );
 
-        if let oxigraph::sparql::QueryResults::Solutions(solutions) =
-            self.blockchain.rdf_store.query(&query)
-        {
+        if let oxigraph::sparql::QueryResults::Solutions(solutions) = self.rdf_store.query(&query) {
             for sol in solutions.flatten() {
                 if let Some(count_term) = sol.get("count") {
                     if let Ok(count_str) = count_term.to_string().parse::<f64>() {
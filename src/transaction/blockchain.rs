@@ -4,20 +4,30 @@
 //! - Transaction-based blockchain operations
 //! - Multi-participant wallet integration
 //! - Supply chain specific transaction processing
+//! - UTXO and batch ownership state derived from the chain (see `ledger`)
 //! - Persistent storage with disk persistence
 
 use anyhow::{anyhow, Result};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use hex;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
 use crate::core::blockchain::Blockchain;
+use crate::transaction::ledger::{BatchOwnership, LedgerState, LEDGER_FILE};
 use crate::transaction::transaction::{
-    EnvironmentalConditions, QualityData, Transaction, TransactionMetadata, TransactionOutput,
-    TransactionPayload, TransactionPool, TransactionType,
+    CredentialAnchor, EnvironmentalConditions, KeyDelegation, KeyRotation, QualityData,
+    Transaction, TransactionInput, TransactionMetadata, TransactionOutput, TransactionPayload,
+    TransactionPool, TransactionType,
 };
-use crate::wallet::{Participant, ParticipantType, Wallet, WalletManager};
+use crate::wallet::credentials::{
+    AnchoredCredential, CredentialResolver, VerifiableCredential, STATUS_LIST_LENGTH,
+};
+use crate::wallet::{
+    Certificate, CertificateStatus, Participant, ParticipantType, PendingKey, Wallet, WalletManager,
+};
+use ed25519_dalek::{SigningKey, VerifyingKey};
 
 /// Enhanced blockchain with transaction support
 pub struct TransactionBlockchain {
@@ -27,54 +37,87 @@ pub struct TransactionBlockchain {
     pub transaction_pool: TransactionPool,
     /// Wallet manager for participants
     pub wallet_manager: WalletManager,
-    /// Transaction index for fast lookups
-    pub transaction_index: HashMap<String, (u64, usize)>, // tx_id -> (block_index, tx_index)
-    /// UTXO set for tracking unspent outputs
-    pub utxo_set: HashMap<String, TransactionOutput>, // output_id -> output
+    /// UTXO set, ownership and transaction locations derived from the chain
+    pub ledger: LedgerState,
+    /// File the ledger is saved to after each block
+    ledger_path: PathBuf,
 }
 
 impl TransactionBlockchain {
     /// Create a new transaction blockchain
     pub fn new(data_dir: &str) -> Result<Self> {
         let blockchain = Blockchain::new_persistent(data_dir)?;
-        let transaction_pool = TransactionPool::new(1000); // Max 1000 pending transactions
         let wallet_manager = WalletManager::new(format!("{}/wallets", data_dir))?;
+        // Caught up with, or rebuilt from, the blocks loaded above
+        let ledger_path = Path::new(data_dir).join(LEDGER_FILE);
+        let ledger = LedgerState::open(&ledger_path, &blockchain.chain)?;
+        let mut transaction_pool = TransactionPool::new(1000); // Max 1000 pending transactions
+        transaction_pool.confirm_nonces(ledger.nonces());
 
         Ok(Self {
             blockchain,
             transaction_pool,
             wallet_manager,
-            transaction_index: HashMap::new(),
-            utxo_set: HashMap::new(),
+            ledger,
+            ledger_path,
         })
     }
 
+    /// Use `iterations` rounds of PBKDF2 for participant keystores written from now on
+    pub fn with_kdf_iterations(mut self, iterations: u32) -> Self {
+        self.wallet_manager = self.wallet_manager.with_kdf_iterations(iterations);
+        self
+    }
+
     /// Submit a transaction to the blockchain
     pub fn submit_transaction(&mut self, transaction: Transaction) -> Result<String> {
         // Validate transaction
         transaction.validate()?;
+        self.check_double_spend(&transaction)?;
+
+        // Key records need no permission, only the keys of the participant
+        match &transaction.payload {
+            Some(TransactionPayload::KeyRotation(rotation)) => {
+                self.check_key_rotation(&transaction, rotation)?;
+            }
+            Some(TransactionPayload::KeyDelegation(delegation)) => {
+                self.check_key_delegation(&transaction, delegation)?;
+            }
+            Some(TransactionPayload::Credential(anchor)) => {
+                self.check_credential_anchor(&transaction, anchor)?;
+            }
+            _ => {
+                if let Some(signer) = transaction.signatures.first() {
+                    // Check if submitter has permission
+                    if let Some(wallet) = self.wallet_manager.get_wallet(signer.signer_id) {
+                        // Derived keys act for the participant once delegated on-chain
+                        if !self.is_authorized_key(wallet, &signer.public_key) {
+                            return Err(anyhow!(
+                                "Signing key is not authorized for participant {}",
+                                signer.signer_id
+                            ));
+                        }
 
-        // Check if submitter has permission
-        if let Some(signer) = transaction.signatures.first() {
-            if let Some(wallet) = self.wallet_manager.get_wallet(signer.signer_id) {
-                let operation = match transaction.tx_type {
-                    TransactionType::Production => "produce",
-                    TransactionType::Processing => "process",
-                    TransactionType::Transport => "transport",
-                    TransactionType::Quality => "quality_test",
-                    TransactionType::Compliance => "audit",
-                    TransactionType::Transfer => "transfer",
-                    _ => "unknown",
-                };
-
-                if !wallet.has_permission(operation) {
-                    return Err(anyhow!(
-                        "Participant does not have permission for {} operation",
-                        operation
-                    ));
+                        let operation = transaction
+                            .tx_type
+                            .required_operation()
+                            .unwrap_or("unknown");
+
+                        if !wallet.has_permission(operation) {
+                            return Err(anyhow!(
+                                "Participant does not have permission for {} operation",
+                                operation
+                            ));
+                        }
+
+                        self.check_input_owners(&transaction)?;
+                        if transaction.tx_type == TransactionType::Transfer {
+                            self.check_transfer(&transaction)?;
+                        }
+                    } else {
+                        return Err(anyhow!("Unknown participant"));
+                    }
                 }
-            } else {
-                return Err(anyhow!("Unknown participant"));
             }
         }
 
@@ -85,6 +128,547 @@ impl TransactionBlockchain {
         Ok(tx_id)
     }
 
+    /// Reject a transaction spending an output twice, or one already spent on-chain or by
+    /// a pending transaction, or creating an output with the ID of an existing one
+    fn check_double_spend(&self, transaction: &Transaction) -> Result<()> {
+        let mut spent = HashSet::new();
+        for input in &transaction.inputs {
+            let output_id = input.output_id();
+            if !spent.insert(output_id.clone()) {
+                return Err(anyhow!("Transaction spends output {} twice", output_id));
+            }
+            if let Some(tx_id) = self.ledger.spent_by(&output_id) {
+                return Err(anyhow!(
+                    "Output {} was already spent by transaction {}",
+                    output_id,
+                    tx_id
+                ));
+            }
+            if let Some(pending) = self.transaction_pool.pending.values().find(|pending| {
+                pending
+                    .inputs
+                    .iter()
+                    .any(|input| input.output_id() == output_id)
+            }) {
+                return Err(anyhow!(
+                    "Output {} is already spent by pending transaction {}",
+                    output_id,
+                    pending.id
+                ));
+            }
+        }
+
+        for output in &transaction.outputs {
+            let pending = self
+                .transaction_pool
+                .pending
+                .values()
+                .flat_map(|pending| &pending.outputs)
+                .any(|pending| pending.id == output.id);
+            if pending || self.ledger.output(&output.id).is_some() {
+                return Err(anyhow!("Output {} already exists", output.id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that every output a transaction spends, on-chain or created by a pending
+    /// transaction, is owned by one of its signers, each signing with an authorized key
+    fn check_input_owners(&self, transaction: &Transaction) -> Result<()> {
+        for input in &transaction.inputs {
+            let output_id = input.output_id();
+            let owner = self
+                .ledger
+                .utxo(&output_id)
+                .or_else(|| {
+                    self.transaction_pool
+                        .pending
+                        .values()
+                        .flat_map(|pending| &pending.outputs)
+                        .find(|output| output.id == output_id)
+                })
+                .map(|output| output.owner)
+                .ok_or_else(|| anyhow!("Output {} is not an unspent output", output_id))?;
+            let signed = transaction.signatures.iter().any(|signature| {
+                signature.signer_id == owner
+                    && self
+                        .wallet_manager
+                        .get_wallet(owner)
+                        .is_some_and(|wallet| self.is_authorized_key(wallet, &signature.public_key))
+            });
+            if !signed {
+                return Err(anyhow!(
+                    "Output {} is owned by participant {}, who did not sign",
+                    output_id,
+                    owner
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that a transfer spends unspent on-chain outputs and passes on their whole
+    /// value
+    fn check_transfer(&self, transaction: &Transaction) -> Result<()> {
+        let mut input_value = 0.0;
+        for input in &transaction.inputs {
+            let output_id = input.output_id();
+            let output = self
+                .ledger
+                .utxo(&output_id)
+                .ok_or_else(|| anyhow!("Output {} is not an unspent output", output_id))?;
+            input_value += output.value;
+        }
+
+        let output_value: f64 = transaction.outputs.iter().map(|o| o.value).sum();
+        if (input_value - output_value).abs() > 0.001 {
+            return Err(anyhow!("Transfer transaction input/output mismatch"));
+        }
+        Ok(())
+    }
+
+    /// Check that a key rotation retires the participant's current key and is signed
+    /// by both the retired and the new key
+    fn check_key_rotation(&self, transaction: &Transaction, rotation: &KeyRotation) -> Result<()> {
+        let wallet = self
+            .wallet_manager
+            .get_wallet(rotation.participant_id)
+            .ok_or_else(|| anyhow!("Unknown participant"))?;
+        if hex::encode(wallet.public_key.as_bytes()) != rotation.previous_key {
+            return Err(anyhow!(
+                "Key rotation does not retire the current key of participant {}",
+                rotation.participant_id
+            ));
+        }
+
+        for key in [&rotation.previous_key, &rotation.new_key] {
+            let signed = transaction.signatures.iter().any(|signature| {
+                signature.signer_id == rotation.participant_id
+                    && hex::encode(signature.public_key.as_bytes()) == *key
+            });
+            if !signed {
+                return Err(anyhow!("Key rotation is not signed by key {}", key));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that a key delegation is signed by the participant's current key and, unless
+    /// it is a revocation, by the delegated key itself
+    fn check_key_delegation(
+        &self,
+        transaction: &Transaction,
+        delegation: &KeyDelegation,
+    ) -> Result<()> {
+        let wallet = self
+            .wallet_manager
+            .get_wallet(delegation.participant_id)
+            .ok_or_else(|| anyhow!("Unknown participant"))?;
+        let signed_by = |key: &str| {
+            transaction.signatures.iter().any(|signature| {
+                signature.signer_id == delegation.participant_id
+                    && hex::encode(signature.public_key.as_bytes()) == key
+            })
+        };
+
+        if !signed_by(&hex::encode(wallet.public_key.as_bytes())) {
+            return Err(anyhow!(
+                "Key delegation is not signed by participant {}",
+                delegation.participant_id
+            ));
+        }
+        if delegation.revoked {
+            if !self
+                .ledger
+                .is_delegated(delegation.participant_id, &delegation.public_key)
+            {
+                return Err(anyhow!("Key {} is not delegated", delegation.public_key));
+            }
+        } else if !signed_by(&delegation.public_key) {
+            return Err(anyhow!(
+                "Key delegation is not signed by delegated key {}",
+                delegation.public_key
+            ));
+        }
+        Ok(())
+    }
+
+    /// Delegate the key at `derivation_path` below a participant's wallet key to the
+    /// line or device `label`, returning the device's wallet.
+    ///
+    /// The delegation is submitted as a transaction signed by both keys; the device key
+    /// acts for the participant once that transaction is in a block.
+    pub fn delegate_device_key(
+        &mut self,
+        participant_id: Uuid,
+        derivation_path: &str,
+        label: String,
+    ) -> Result<Wallet> {
+        let wallet = self
+            .wallet_manager
+            .get_wallet(participant_id)
+            .ok_or_else(|| anyhow!("Participant wallet not found"))?;
+        let device_wallet = wallet.derive_device_wallet(derivation_path)?;
+
+        let delegation = KeyDelegation {
+            participant_id,
+            public_key: hex::encode(device_wallet.public_key.as_bytes()),
+            derivation_path: device_wallet.derivation_path.clone().unwrap_or_default(),
+            label,
+            revoked: false,
+        };
+        let mut transaction = self.key_delegation_transaction(delegation)?;
+        transaction.sign(device_wallet.unlocked_key()?, participant_id)?;
+
+        self.submit_transaction(transaction)?;
+        Ok(device_wallet)
+    }
+
+    /// Revoke a delegated key of a participant once the revocation is in a block,
+    /// returning the ID of the revocation transaction
+    pub fn revoke_device_key(&mut self, participant_id: Uuid, public_key: &str) -> Result<String> {
+        let delegated = self
+            .ledger
+            .delegated_keys(participant_id)
+            .iter()
+            .find(|key| key.public_key == public_key && key.revoked_at.is_none())
+            .ok_or_else(|| anyhow!("Key {} is not delegated", public_key))?;
+
+        let delegation = KeyDelegation {
+            participant_id,
+            public_key: delegated.public_key.clone(),
+            derivation_path: delegated.derivation_path.clone(),
+            label: delegated.label.clone(),
+            revoked: true,
+        };
+        let transaction = self.key_delegation_transaction(delegation)?;
+        self.submit_transaction(transaction)
+    }
+
+    /// Delegation transaction signed by the participant's wallet key
+    fn key_delegation_transaction(&self, delegation: KeyDelegation) -> Result<Transaction> {
+        let wallet = self
+            .wallet_manager
+            .get_wallet(delegation.participant_id)
+            .ok_or_else(|| anyhow!("Participant wallet not found"))?;
+
+        let now = Utc::now().to_rfc3339();
+        let rdf_data = if delegation.revoked {
+            format!(
+                r#"
+ex:device_key_{} trace:revokedAt "{}"^^xsd:dateTime .
+"#,
+                delegation.public_key, now
+            )
+        } else {
+            format!(
+                r#"
+ex:device_key_{} a trace:DelegatedKey ;
+    prov:actedOnBehalfOf ex:participant_{} ;
+    rdfs:label "{}" ;
+    trace:derivationPath "{}" ;
+    trace:publicKey "{}" ;
+    trace:recordedAt "{}"^^xsd:dateTime .
+"#,
+                delegation.public_key,
+                delegation.participant_id,
+                delegation.label,
+                delegation.derivation_path,
+                delegation.public_key,
+                now
+            )
+        };
+
+        let mut transaction = Transaction::new(
+            TransactionType::Governance,
+            vec![],
+            vec![],
+            rdf_data,
+            TransactionMetadata {
+                location: None,
+                environmental_conditions: None,
+                compliance_info: None,
+                quality_data: None,
+                custom_fields: HashMap::new(),
+            },
+            TransactionPayload::KeyDelegation(delegation.clone()),
+        );
+        transaction.nonce = self.next_nonce(delegation.participant_id);
+        transaction.sign(wallet.unlocked_key()?, delegation.participant_id)?;
+        Ok(transaction)
+    }
+
+    /// Whether signatures by `public_key` act for the wallet's participant: its current
+    /// key, or a key delegated on-chain and not revoked
+    fn is_authorized_key(&self, wallet: &Wallet, public_key: &VerifyingKey) -> bool {
+        public_key == &wallet.public_key
+            || self
+                .ledger
+                .is_delegated(wallet.participant_id(), &hex::encode(public_key.as_bytes()))
+    }
+
+    /// Check that a credential record is signed by its issuer, who may certify others
+    fn check_credential_anchor(
+        &self,
+        transaction: &Transaction,
+        anchor: &CredentialAnchor,
+    ) -> Result<()> {
+        let issuer = self
+            .wallet_manager
+            .get_wallet(anchor.issuer_id)
+            .ok_or_else(|| anyhow!("Unknown issuer"))?;
+        if !issuer.has_permission("audit") {
+            return Err(anyhow!(
+                "Participant {} may not issue certificates",
+                anchor.issuer_id
+            ));
+        }
+        let signed = transaction.signatures.iter().any(|signature| {
+            signature.signer_id == anchor.issuer_id
+                && self.is_authorized_key(issuer, &signature.public_key)
+        });
+        if !signed {
+            return Err(anyhow!("Credential record is not signed by its issuer"));
+        }
+
+        let issued = self
+            .ledger
+            .anchored_credential(anchor.issuer_id, &anchor.credential_id)
+            .is_some();
+        if anchor.revoked && !issued {
+            return Err(anyhow!(
+                "Credential {} was not issued",
+                anchor.credential_id
+            ));
+        }
+        if !anchor.revoked && issued {
+            return Err(anyhow!(
+                "Credential {} is already anchored",
+                anchor.credential_id
+            ));
+        }
+        Ok(())
+    }
+
+    /// Issue `certificate` to `subject_id` as a verifiable credential signed by the
+    /// issuer, and submit its anchor for the next block. The certificate, with its
+    /// credential, is added to the subject's wallet and is valid once anchored.
+    pub fn issue_certificate(
+        &mut self,
+        issuer_id: Uuid,
+        subject_id: Uuid,
+        mut certificate: Certificate,
+    ) -> Result<Certificate> {
+        let issuer = self
+            .wallet_manager
+            .get_wallet(issuer_id)
+            .ok_or_else(|| anyhow!("Issuer wallet not found"))?;
+        if self.wallet_manager.get_wallet(subject_id).is_none() {
+            return Err(anyhow!("Subject wallet not found"));
+        }
+        let signing_key = issuer.unlocked_key()?;
+
+        let index = self.free_status_list_index(issuer_id)?;
+        let credential =
+            VerifiableCredential::for_certificate(&certificate, issuer_id, subject_id, index)
+                .sign(signing_key)?;
+        let anchor = CredentialAnchor {
+            credential_id: credential.id.clone(),
+            issuer_id,
+            subject_id,
+            digest: credential.digest()?,
+            status_list_index: index,
+            revoked: false,
+        };
+        let rdf_data = format!(
+            r#"
+ex:credential_{} a trace:VerifiableCredential ;
+    prov:wasAttributedTo ex:participant_{} ;
+    trace:hasCertificateType "{}" ;
+    trace:credentialDigest "{}" ;
+    trace:statusListIndex "{}"^^xsd:integer ;
+    trace:recordedAt "{}"^^xsd:dateTime .
+
+ex:participant_{} trace:hasCertificate ex:credential_{} .
+"#,
+            credential_iri(&anchor.credential_id),
+            issuer_id,
+            certificate.cert_type,
+            anchor.digest,
+            index,
+            Utc::now().to_rfc3339(),
+            subject_id,
+            credential_iri(&anchor.credential_id)
+        );
+
+        let mut transaction = credential_transaction(rdf_data, anchor);
+        transaction.nonce = self.next_nonce(issuer_id);
+        transaction.sign(signing_key, issuer_id)?;
+        self.submit_transaction(transaction)?;
+
+        certificate.status = CertificateStatus::Active;
+        certificate.credential = Some(credential);
+        let subject = self
+            .wallet_manager
+            .get_wallet_mut(subject_id)
+            .ok_or_else(|| anyhow!("Subject wallet not found"))?;
+        subject.add_certificate(certificate.clone());
+        let subject = subject.clone();
+        self.wallet_manager.save_wallet(&subject)?;
+        Ok(certificate)
+    }
+
+    /// Submit the revocation of a credential issued by `issuer_id`, returning the ID
+    /// of the revocation transaction. The credential fails verification once the
+    /// revocation is in a block.
+    pub fn revoke_certificate(&mut self, issuer_id: Uuid, credential_id: &str) -> Result<String> {
+        let issuer = self
+            .wallet_manager
+            .get_wallet(issuer_id)
+            .ok_or_else(|| anyhow!("Issuer wallet not found"))?;
+        let anchored = self
+            .ledger
+            .anchored_credential(issuer_id, credential_id)
+            .ok_or_else(|| anyhow!("Credential {} was not issued", credential_id))?;
+
+        let anchor = CredentialAnchor {
+            credential_id: anchored.credential_id.clone(),
+            issuer_id,
+            subject_id: anchored.subject_id,
+            digest: anchored.digest.clone(),
+            status_list_index: anchored.status_list_index,
+            revoked: true,
+        };
+        let rdf_data = format!(
+            r#"
+ex:credential_{} trace:revokedAt "{}"^^xsd:dateTime .
+"#,
+            credential_iri(credential_id),
+            Utc::now().to_rfc3339()
+        );
+
+        let mut transaction = credential_transaction(rdf_data, anchor);
+        transaction.nonce = self.next_nonce(issuer_id);
+        transaction.sign(issuer.unlocked_key()?, issuer_id)?;
+        self.submit_transaction(transaction)
+    }
+
+    /// The issuer's revocation list, as recorded on-chain, as a signed
+    /// `BitstringStatusListCredential`
+    pub fn status_list_credential(&self, issuer_id: Uuid) -> Result<VerifiableCredential> {
+        let issuer = self
+            .wallet_manager
+            .get_wallet(issuer_id)
+            .ok_or_else(|| anyhow!("Issuer wallet not found"))?;
+        self.ledger
+            .revocation_list(issuer_id)
+            .to_credential(issuer_id)?
+            .sign(issuer.unlocked_key()?)
+    }
+
+    /// Random revocation list entry not taken by an anchored or pending credential of
+    /// the issuer, so list positions reveal nothing about issuance order
+    fn free_status_list_index(&self, issuer_id: Uuid) -> Result<u64> {
+        let pending = self
+            .transaction_pool
+            .pending
+            .values()
+            .filter_map(|tx| match &tx.payload {
+                Some(TransactionPayload::Credential(anchor)) if anchor.issuer_id == issuer_id => {
+                    Some(anchor.status_list_index)
+                }
+                _ => None,
+            });
+        let taken: std::collections::HashSet<u64> = self
+            .ledger
+            .anchored_credentials(issuer_id)
+            .iter()
+            .map(|anchored| anchored.status_list_index)
+            .chain(pending)
+            .collect();
+        if taken.len() >= STATUS_LIST_LENGTH {
+            return Err(anyhow!("Revocation list of issuer {} is full", issuer_id));
+        }
+
+        loop {
+            let index = rand::random::<u64>() % STATUS_LIST_LENGTH as u64;
+            if !taken.contains(&index) {
+                return Ok(index);
+            }
+        }
+    }
+
+    /// Start replacing the signing key of an unlocked participant wallet with a fresh
+    /// one.
+    ///
+    /// The new key is stored encrypted under `password`, which must open the current
+    /// keystore if there is one, and a transaction linking the old key to the new one,
+    /// signed by both, is submitted for the next block. The wallet keeps signing with
+    /// the old key until that block is created.
+    pub fn rotate_participant_key(
+        &mut self,
+        participant_id: Uuid,
+        password: &str,
+    ) -> Result<PendingKey> {
+        let wallet = self
+            .wallet_manager
+            .get_wallet(participant_id)
+            .ok_or_else(|| anyhow!("Participant wallet not found"))?;
+        let old_key = wallet.unlocked_key()?.clone();
+        let new_key = SigningKey::from_bytes(&rand::random::<[u8; 32]>());
+
+        let rotation = KeyRotation {
+            participant_id,
+            previous_key: hex::encode(old_key.verifying_key().as_bytes()),
+            new_key: hex::encode(new_key.verifying_key().as_bytes()),
+        };
+        let rdf_data = format!(
+            r#"
+ex:key_rotation_{} a trace:KeyRotation ;
+    prov:wasAssociatedWith ex:participant_{} ;
+    trace:recordedAt "{}"^^xsd:dateTime ;
+    trace:previousPublicKey "{}" ;
+    trace:newPublicKey "{}" .
+"#,
+            rotation.new_key,
+            participant_id,
+            Utc::now().to_rfc3339(),
+            rotation.previous_key,
+            rotation.new_key
+        );
+
+        let mut transaction = Transaction::new(
+            TransactionType::Governance,
+            vec![],
+            vec![],
+            rdf_data,
+            TransactionMetadata {
+                location: None,
+                environmental_conditions: None,
+                compliance_info: None,
+                quality_data: None,
+                custom_fields: HashMap::new(),
+            },
+            TransactionPayload::KeyRotation(rotation),
+        );
+        transaction.nonce = self.next_nonce(participant_id);
+        transaction.sign(&old_key, participant_id)?;
+        transaction.sign(&new_key, participant_id)?;
+
+        // The new key is on disk before anything refers to it
+        let pending = self.wallet_manager.stage_rotation(
+            participant_id,
+            new_key,
+            password,
+            transaction.id.clone(),
+        )?;
+        if let Err(e) = self.submit_transaction(transaction) {
+            self.wallet_manager.discard_rotation(participant_id)?;
+            return Err(e);
+        }
+        Ok(pending)
+    }
+
     /// Create a new block with pending transactions
     pub fn create_block(&mut self, max_transactions: usize, validator_id: Uuid) -> Result<()> {
         let transactions = self
@@ -103,6 +687,7 @@ impl TransactionBlockchain {
 
         // Create block data from transactions
         let block_data = self.create_block_rdf_data(&transactions)?;
+        self.ledger.validate_block_data(&block_data)?;
 
         // Create block proposal
         // Note: We use the hex string representation of the public key as the validator ID in the block
@@ -118,29 +703,41 @@ impl TransactionBlockchain {
         // Submit signed block
         self.blockchain.submit_signed_block(block)?;
 
-        // Update transaction index and UTXO set
-        let block_index = self.blockchain.chain.len() as u64 - 1;
-        for (tx_index, transaction) in transactions.iter().enumerate() {
-            // Update transaction index
-            self.transaction_index
-                .insert(transaction.id.clone(), (block_index, tx_index));
-
-            // Update UTXO set
-            for output in &transaction.outputs {
-                self.utxo_set.insert(output.id.clone(), output.clone());
-            }
-
-            // Remove spent outputs
-            for input in &transaction.inputs {
-                self.utxo_set
-                    .remove(&format!("{}:{}", input.prev_tx_id, input.output_index));
-            }
-
-            // Remove from transaction pool
+        // The block is committed: its transactions leave the pool whatever happens next
+        for transaction in &transactions {
             self.transaction_pool.remove_transaction(&transaction.id);
         }
 
-        Ok(())
+        // Update the ledger from the block just added
+        self.ledger.sync(&self.blockchain.chain)?;
+        self.transaction_pool.confirm_nonces(self.ledger.nonces());
+        self.ledger.save(&self.ledger_path)?;
+
+        // Local wallets follow the key records of the block, and holders see their
+        // revoked certificates. Every record
+        // is applied even if an earlier one fails, and the first failure is reported.
+        let mut applied = Ok(());
+        for transaction in &transactions {
+            let result = match &transaction.payload {
+                Some(TransactionPayload::KeyRotation(rotation)) => self
+                    .wallet_manager
+                    .complete_rotation(rotation.participant_id, &rotation.new_key, &transaction.id)
+                    .map(|_| ()),
+                Some(TransactionPayload::Credential(anchor)) if anchor.revoked => self
+                    .wallet_manager
+                    .mark_certificate_revoked(anchor.subject_id, &anchor.credential_id),
+                _ => Ok(()),
+            };
+            if let Err(e) = result {
+                if applied.is_ok() {
+                    applied = Err(e.context(format!(
+                        "Block added, but transaction {} could not be applied to local wallets",
+                        transaction.id
+                    )));
+                }
+            }
+        }
+        applied
     }
 
     /// Create RDF data for a block from transactions
@@ -161,9 +758,12 @@ impl TransactionBlockchain {
         );
 
         // Add transaction data
-        for transaction in transactions {
+        for (position, transaction) in transactions.iter().enumerate() {
             rdf_data.push_str(&transaction.to_rdf());
-            rdf_data.push('\n');
+            rdf_data.push_str(&format!(
+                "tx:{} tx:hasBlockPosition \"{}\"^^xsd:integer .\n",
+                transaction.id, position
+            ));
 
             // Add the original RDF data from the transaction
             rdf_data.push_str(&transaction.rdf_data);
@@ -173,6 +773,21 @@ impl TransactionBlockchain {
         Ok(rdf_data)
     }
 
+    /// Add the signature of `participant_id`, such as the owner of an output the
+    /// transaction spends, to a transaction signed by another participant
+    pub fn cosign_transaction(
+        &self,
+        transaction: &mut Transaction,
+        participant_id: Uuid,
+    ) -> Result<()> {
+        let wallet = self
+            .wallet_manager
+            .get_wallet(participant_id)
+            .ok_or_else(|| anyhow!("Participant wallet not found"))?;
+        transaction.sign(wallet.unlocked_key()?, participant_id)?;
+        Ok(())
+    }
+
     /// Get transaction by ID
     pub fn get_transaction(&self, tx_id: &str) -> Option<Transaction> {
         // First check transaction pool
@@ -181,7 +796,7 @@ impl TransactionBlockchain {
         }
 
         // Then check blockchain
-        if let Some((_block_index, _tx_index)) = self.transaction_index.get(tx_id) {
+        if let Some(_location) = self.ledger.transaction_location(tx_id) {
             // In a full implementation, we would parse the block data to extract the transaction
             // For now, we'll return None as this requires more complex RDF parsing
             None
@@ -209,9 +824,14 @@ impl TransactionBlockchain {
         tx_ids
     }
 
-    /// Register a new participant
-    pub fn register_participant(&mut self, participant: Participant) -> Result<Uuid> {
-        self.wallet_manager.create_wallet(participant)
+    /// Register a new participant, storing its signing key encrypted under `password`
+    pub fn register_participant(
+        &mut self,
+        participant: Participant,
+        password: &str,
+    ) -> Result<Uuid> {
+        self.wallet_manager
+            .create_wallet_with_password(participant, password)
     }
 
     /// Get participant wallet
@@ -292,12 +912,15 @@ ex:participant_{} a trace:Farmer ;
         );
 
         // Sign the transaction
-        transaction.sign(wallet.signing_key.as_ref().unwrap(), producer_id)?;
+        transaction.nonce = self.next_nonce(producer_id);
+        transaction.sign(wallet.unlocked_key()?, producer_id)?;
 
         Ok(transaction)
     }
 
-    /// Create a processing transaction (manufacturer processes raw materials)
+    /// Create a processing transaction (manufacturer processes raw materials). The
+    /// owners of the input batches co-sign it (see `cosign_transaction`) before it is
+    /// submitted.
     pub fn create_processing_transaction(
         &self,
         processor_id: Uuid,
@@ -315,17 +938,19 @@ ex:participant_{} a trace:Farmer ;
             return Err(anyhow!("Processor does not have processing permission"));
         }
 
-        // Create transaction inputs (simplified - in reality we'd look up the actual UTXOs)
+        // Spend the current output of each batch, or the produced one while its
+        // production is still pending
         let inputs = input_batch_ids
             .iter()
-            .map(
-                |batch_id| crate::transaction::transaction::TransactionInput {
+            .map(|batch_id| match self.ledger.batch_ownership(batch_id) {
+                Some(ownership) => spend(ownership.latest_output()),
+                None => TransactionInput {
                     prev_tx_id: batch_id.clone(),
                     output_index: 0,
                     signature: None,
                     public_key: None,
                 },
-            )
+            })
             .collect();
 
         // Create transaction output
@@ -390,7 +1015,8 @@ ex:participant_{} a trace:Manufacturer ;
         );
 
         // Sign the transaction
-        transaction.sign(wallet.signing_key.as_ref().unwrap(), processor_id)?;
+        transaction.nonce = self.next_nonce(processor_id);
+        transaction.sign(wallet.unlocked_key()?, processor_id)?;
 
         Ok(transaction)
     }
@@ -463,7 +1089,8 @@ ex:participant_{} a trace:QualityLab ;
         );
 
         // Sign the transaction
-        transaction.sign(wallet.signing_key.as_ref().unwrap(), lab_id)?;
+        transaction.nonce = self.next_nonce(lab_id);
+        transaction.sign(wallet.unlocked_key()?, lab_id)?;
 
         Ok(transaction)
     }
@@ -527,11 +1154,114 @@ ex:participant_{} a trace:LogisticsProvider ;
         );
 
         // Sign the transaction
-        transaction.sign(wallet.signing_key.as_ref().unwrap(), logistics_id)?;
+        transaction.nonce = self.next_nonce(logistics_id);
+        transaction.sign(wallet.unlocked_key()?, logistics_id)?;
 
         Ok(transaction)
     }
 
+    /// Create a transfer of a batch from its current owner to another participant
+    pub fn create_transfer_transaction(
+        &self,
+        sender_id: Uuid,
+        batch_id: String,
+        recipient_id: Uuid,
+    ) -> Result<Transaction> {
+        let wallet = self
+            .wallet_manager
+            .get_wallet(sender_id)
+            .ok_or_else(|| anyhow!("Sender wallet not found"))?;
+
+        if !wallet.has_permission("transfer") {
+            return Err(anyhow!("Sender does not have transfer permission"));
+        }
+
+        let recipient = self
+            .wallet_manager
+            .get_wallet(recipient_id)
+            .ok_or_else(|| anyhow!("Recipient wallet not found"))?;
+
+        let ownership = self
+            .ledger
+            .batch_ownership(&batch_id)
+            .ok_or_else(|| anyhow!("Batch {} is not on the chain", batch_id))?;
+        if ownership.current_owner != Some(sender_id) {
+            return Err(anyhow!(
+                "Batch {} is not owned by participant {}",
+                batch_id,
+                sender_id
+            ));
+        }
+        let current = ownership.latest_output();
+
+        // The batch's outputs are numbered by transfer, `{batch_id}:{n}`
+        let output = TransactionOutput {
+            id: format!("{}:{}", batch_id, ownership.history.len()),
+            owner: recipient_id,
+            asset_type: current.asset_type.clone(),
+            value: current.value,
+            metadata: current.metadata.clone(),
+        };
+
+        // Create RDF data
+        let rdf_data = format!(
+            r#"
+ex:transfer_{} a trace:OwnershipTransfer ;
+    prov:used ex:{} ;
+    prov:wasAssociatedWith ex:participant_{} ;
+    trace:recordedAt "{}"^^xsd:dateTime ;
+    trace:transferredTo ex:participant_{} .
+
+ex:{} prov:wasAttributedTo ex:participant_{} .
+
+ex:participant_{} rdfs:label "{}" .
+"#,
+            output.id.replace(':', "_"),
+            batch_id,
+            sender_id,
+            Utc::now().to_rfc3339(),
+            recipient_id,
+            batch_id,
+            recipient_id,
+            recipient_id,
+            recipient.participant.name
+        );
+
+        let metadata = TransactionMetadata {
+            location: wallet.participant.location.clone(),
+            environmental_conditions: None,
+            compliance_info: None,
+            quality_data: None,
+            custom_fields: HashMap::new(),
+        };
+
+        let mut transaction = Transaction::new(
+            TransactionType::Transfer,
+            vec![spend(current)],
+            vec![output],
+            rdf_data.clone(),
+            metadata,
+            TransactionPayload::RdfData(rdf_data.clone()),
+        );
+
+        // Sign the transaction
+        transaction.nonce = self.next_nonce(sender_id);
+        transaction.sign(wallet.unlocked_key()?, sender_id)?;
+
+        Ok(transaction)
+    }
+
+    /// Nonce the next transaction signed by a participant must carry, after its
+    /// transactions on the chain and in the pool
+    pub fn next_nonce(&self, participant_id: Uuid) -> u64 {
+        self.transaction_pool.next_nonce(participant_id)
+    }
+
+    /// Current owner of a batch and the chain of transfers leading to them
+    pub fn batch_ownership(&self, batch_id: &str) -> Option<BatchOwnership> {
+        self.ledger.batch_ownership(batch_id)
+    }
+
     /// Get blockchain statistics
     pub fn get_statistics(&self) -> TransactionBlockchainStats {
         let pool_stats = self.transaction_pool.get_stats();
@@ -541,7 +1271,7 @@ ex:participant_{} a trace:LogisticsProvider ;
             total_blocks: self.blockchain.chain.len(),
             pending_transactions: pool_stats.total_transactions,
             total_participants: wallet_stats.total_participants,
-            total_utxos: self.utxo_set.len(),
+            total_utxos: self.ledger.utxos().count(),
             participant_distribution: wallet_stats.type_distribution,
             transaction_distribution: pool_stats.type_distribution,
         }
@@ -558,6 +1288,80 @@ ex:participant_{} a trace:LogisticsProvider ;
     }
 }
 
+/// Credentials verify against the chain: anchors, revocations and key rotations come
+/// from the ledger. Participant registration is not recorded on-chain, so an issuer that
+/// never rotated its key is checked against its wallet's key.
+impl CredentialResolver for TransactionBlockchain {
+    /// The issuer's current key, and keys it retired after `at`
+    fn is_issuer_key(&self, issuer_id: Uuid, public_key: &VerifyingKey, at: DateTime<Utc>) -> bool {
+        let encoded = hex::encode(public_key.as_bytes());
+        let retired = self.ledger.retired_keys(issuer_id);
+        let current = match retired.last() {
+            Some(rotation) => rotation.replaced_by == encoded,
+            None => self
+                .wallet_manager
+                .get_wallet(issuer_id)
+                .is_some_and(|wallet| wallet.public_key == *public_key),
+        };
+        current
+            || retired
+                .iter()
+                .any(|key| key.public_key == encoded && key.retired_at > at)
+    }
+
+    fn anchored_credential(
+        &self,
+        issuer_id: Uuid,
+        credential_id: &str,
+    ) -> Option<&AnchoredCredential> {
+        self.ledger.anchored_credential(issuer_id, credential_id)
+    }
+
+    fn is_revoked(&self, issuer_id: Uuid, index: u64) -> bool {
+        self.ledger.is_revoked(issuer_id, index)
+    }
+}
+
+/// Input spending `output`, whose ID has the form `{prev_tx_id}:{output_index}`
+fn spend(output: &TransactionOutput) -> TransactionInput {
+    let (prev_tx_id, output_index) = output
+        .id
+        .rsplit_once(':')
+        .and_then(|(prev_tx_id, index)| Some((prev_tx_id, index.parse().ok()?)))
+        .unwrap_or((output.id.as_str(), 0));
+    TransactionInput {
+        prev_tx_id: prev_tx_id.to_string(),
+        output_index,
+        signature: None,
+        public_key: None,
+    }
+}
+
+/// Governance transaction carrying a credential record
+fn credential_transaction(rdf_data: String, anchor: CredentialAnchor) -> Transaction {
+    Transaction::new(
+        TransactionType::Governance,
+        vec![],
+        vec![],
+        rdf_data,
+        TransactionMetadata {
+            location: None,
+            environmental_conditions: None,
+            compliance_info: None,
+            quality_data: None,
+            custom_fields: HashMap::new(),
+        },
+        TransactionPayload::Credential(anchor),
+    )
+}
+
+/// Local name of a credential in RDF, from its `urn:uuid:` ID
+fn credential_iri(credential_id: &str) -> &str {
+    credential_id
+        .strip_prefix("urn:uuid:")
+        .unwrap_or(credential_id)
+}
+
 /// Statistics for the transaction blockchain
 #[derive(Debug, Clone)]
 pub struct TransactionBlockchainStats {
@@ -575,10 +1379,14 @@ mod tests {
     use crate::wallet::Participant;
     use tempfile::tempdir;
 
+    const PASSWORD: &str = "wallet-password";
+
     #[test]
     fn test_transaction_blockchain_creation() {
         let temp_dir = tempdir().unwrap();
-        let blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap()).unwrap();
+        let blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+            .unwrap()
+            .with_kdf_iterations(10);
 
         let stats = blockchain.get_statistics();
         assert_eq!(stats.total_blocks, 1); // Genesis block
@@ -589,12 +1397,14 @@ mod tests {
     #[test]
     fn test_participant_registration() {
         let temp_dir = tempdir().unwrap();
-        let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap()).unwrap();
+        let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+            .unwrap()
+            .with_kdf_iterations(10);
 
         let farmer =
             Participant::new_farmer("John's Dairy Farm".to_string(), "Vermont, USA".to_string());
 
-        let participant_id = blockchain.register_participant(farmer).unwrap();
+        let participant_id = blockchain.register_participant(farmer, PASSWORD).unwrap();
         assert!(blockchain.get_participant_wallet(participant_id).is_some());
 
         let stats = blockchain.get_statistics();
@@ -604,12 +1414,14 @@ mod tests {
     #[test]
     fn test_production_transaction() {
         let temp_dir = tempdir().unwrap();
-        let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap()).unwrap();
+        let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+            .unwrap()
+            .with_kdf_iterations(10);
 
         let farmer =
             Participant::new_farmer("John's Dairy Farm".to_string(), "Vermont, USA".to_string());
 
-        let farmer_id = blockchain.register_participant(farmer).unwrap();
+        let farmer_id = blockchain.register_participant(farmer, PASSWORD).unwrap();
 
         let tx = blockchain
             .create_production_transaction(
@@ -625,6 +1437,665 @@ mod tests {
         assert_eq!(tx.outputs.len(), 1);
         assert_eq!(tx.outputs[0].value, 1000.0);
     }
+
+    #[test]
+    fn test_key_rotation_is_recorded_on_chain() {
+        let temp_dir = tempdir().unwrap();
+        let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+            .unwrap()
+            .with_kdf_iterations(10);
+
+        let farmer =
+            Participant::new_farmer("John's Dairy Farm".to_string(), "Vermont, USA".to_string());
+        let farmer_id = blockchain
+            .register_participant(farmer, "farm-password")
+            .unwrap();
+        let current_key = |blockchain: &TransactionBlockchain| {
+            hex::encode(
+                blockchain
+                    .get_participant_wallet(farmer_id)
+                    .unwrap()
+                    .public_key
+                    .as_bytes(),
+            )
+        };
+        let old_key = current_key(&blockchain);
+
+        assert!(blockchain
+            .rotate_participant_key(farmer_id, "wrong-password")
+            .is_err());
+        assert_eq!(blockchain.transaction_pool.pending.len(), 0);
+
+        let pending = blockchain
+            .rotate_participant_key(farmer_id, "farm-password")
+            .unwrap();
+        let tx = blockchain.get_transaction(&pending.transaction_id).unwrap();
+        match &tx.payload {
+            Some(TransactionPayload::KeyRotation(rotation)) => {
+                assert_eq!(rotation.previous_key, old_key);
+                assert_eq!(rotation.new_key, pending.public_key);
+            }
+            other => panic!("expected a key rotation payload, got {:?}", other),
+        }
+
+        // Until the rotation is in a block, the wallet signs with the old key, and the
+        // new key is already stored
+        assert_eq!(current_key(&blockchain), old_key);
+        let mut restarted = WalletManager::new(temp_dir.path().join("wallets")).unwrap();
+        restarted.unlock_wallet(farmer_id, "farm-password").unwrap();
+        assert_eq!(
+            restarted.get_wallet(farmer_id).unwrap().pending_key,
+            Some(pending.clone())
+        );
+
+        blockchain.create_block(10, farmer_id).unwrap();
+        let block = blockchain.blockchain.chain.last().unwrap();
+        assert!(block.data.contains("trace:KeyRotation"));
+        assert!(block.data.contains(&old_key));
+        assert!(block.data.contains(&pending.public_key));
+
+        assert_eq!(current_key(&blockchain), pending.public_key);
+        let wallet = blockchain.get_participant_wallet(farmer_id).unwrap();
+        assert!(wallet.pending_key.is_none());
+        assert_eq!(
+            wallet.retired_keys.last().unwrap().transaction_id,
+            Some(pending.transaction_id.clone())
+        );
+        let rotation = &blockchain.ledger.retired_keys(farmer_id)[0];
+        assert_eq!(rotation.public_key, old_key);
+        assert_eq!(rotation.replaced_by, pending.public_key);
+        // Replaying the rotation fails: the old key is no longer current
+        assert!(blockchain.submit_transaction(tx).is_err());
+    }
+
+    #[test]
+    fn test_delegated_device_key_acts_for_participant() {
+        let temp_dir = tempdir().unwrap();
+        let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+            .unwrap()
+            .with_kdf_iterations(10);
+        let farmer =
+            Participant::new_farmer("John's Dairy Farm".to_string(), "Vermont, USA".to_string());
+        let farmer_id = blockchain.register_participant(farmer, PASSWORD).unwrap();
+
+        let production = |blockchain: &TransactionBlockchain, signing_key: &SigningKey| {
+            let mut tx = blockchain
+                .create_production_transaction(
+                    farmer_id,
+                    format!("MILK-{}", Uuid::new_v4()),
+                    100.0,
+                    "Vermont, USA".to_string(),
+                    None,
+                )
+                .unwrap();
+            tx.signatures.clear();
+            tx.sign(signing_key, farmer_id).unwrap();
+            tx
+        };
+
+        let device = blockchain
+            .delegate_device_key(farmer_id, "m/0'/1'", "Milking line 1".to_string())
+            .unwrap();
+        assert_eq!(device.participant_id(), farmer_id);
+        assert_eq!(device.derivation_path.as_deref(), Some("m/0'/1'"));
+        let device_key = device.unlocked_key().unwrap().clone();
+
+        // The device signs for the farm only once the delegation is in a block
+        let tx = production(&blockchain, &device_key);
+        assert!(blockchain.submit_transaction(tx).is_err());
+        blockchain.create_block(10, farmer_id).unwrap();
+        let block = blockchain.blockchain.chain.last().unwrap();
+        assert!(block.data.contains("trace:DelegatedKey"));
+        assert!(block.data.contains("prov:actedOnBehalfOf"));
+
+        let tx = production(&blockchain, &device_key);
+        blockchain.submit_transaction(tx).unwrap();
+
+        // The delegation is chain state: a ledger rebuilt from the blocks has it
+        let device_public_key = hex::encode(device.public_key.as_bytes());
+        let rebuilt = LedgerState::from_chain(&blockchain.blockchain.chain).unwrap();
+        assert!(rebuilt.is_delegated(farmer_id, &device_public_key));
+        assert_eq!(
+            rebuilt.delegated_keys(farmer_id)[0].label,
+            "Milking line 1".to_string()
+        );
+
+        let stranger = SigningKey::from_bytes(&rand::random::<[u8; 32]>());
+        let tx = production(&blockchain, &stranger);
+        assert!(blockchain.submit_transaction(tx).is_err());
+
+        blockchain
+            .revoke_device_key(farmer_id, &device_public_key)
+            .unwrap();
+        blockchain.create_block(10, farmer_id).unwrap();
+        let tx = production(&blockchain, &device_key);
+        assert!(blockchain.submit_transaction(tx).is_err());
+        assert!(!LedgerState::from_chain(&blockchain.blockchain.chain)
+            .unwrap()
+            .is_delegated(farmer_id, &device_public_key));
+    }
+
+    #[test]
+    fn test_committed_transactions_leave_the_pool() {
+        let temp_dir = tempdir().unwrap();
+        let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+            .unwrap()
+            .with_kdf_iterations(10);
+        let farmer =
+            Participant::new_farmer("John's Dairy Farm".to_string(), "Vermont, USA".to_string());
+        let farmer_id = blockchain.register_participant(farmer, PASSWORD).unwrap();
+        let validator =
+            Participant::new_uht_manufacturer("Alpine UHT".to_string(), "Wisconsin".to_string());
+        let validator_id = blockchain
+            .register_participant(validator, PASSWORD)
+            .unwrap();
+
+        blockchain
+            .rotate_participant_key(farmer_id, PASSWORD)
+            .unwrap();
+        // The rotation cannot be applied to a wallet that is gone, but the block is in
+        blockchain.wallet_manager.remove_wallet(farmer_id).unwrap();
+        let height = blockchain.blockchain.chain.len();
+        assert!(blockchain.create_block(10, validator_id).is_err());
+        assert_eq!(blockchain.blockchain.chain.len(), height + 1);
+        assert_eq!(blockchain.transaction_pool.pending.len(), 0);
+        assert_eq!(blockchain.ledger.next_nonce(farmer_id), 1);
+    }
+
+    #[test]
+    fn test_certificate_credentials_are_anchored_and_revocable() {
+        let temp_dir = tempdir().unwrap();
+        let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+            .unwrap()
+            .with_kdf_iterations(10);
+        let farmer =
+            Participant::new_farmer("John's Dairy Farm".to_string(), "Vermont, USA".to_string());
+        let farmer_id = blockchain.register_participant(farmer, PASSWORD).unwrap();
+        let mut auditor =
+            Participant::new_quality_lab("USDA Organic".to_string(), "Washington".to_string());
+        auditor.participant_type = ParticipantType::Auditor;
+        auditor.permissions =
+            crate::wallet::ParticipantPermissions::for_type(&ParticipantType::Auditor);
+        let auditor_id = blockchain.register_participant(auditor, PASSWORD).unwrap();
+
+        let certificate = Certificate {
+            id: "ORGANIC-001".to_string(),
+            cert_type: "ORGANIC".to_string(),
+            issuer: "USDA".to_string(),
+            issued_at: Utc::now() - chrono::Duration::minutes(1),
+            expires_at: Utc::now() + chrono::Duration::days(365),
+            status: CertificateStatus::Active,
+            metadata: HashMap::new(),
+            credential: None,
+        };
+        assert!(blockchain
+            .issue_certificate(farmer_id, farmer_id, certificate.clone())
+            .is_err());
+        let issued = blockchain
+            .issue_certificate(auditor_id, farmer_id, certificate)
+            .unwrap();
+        let credential_id = issued.credential.unwrap().id;
+
+        let holder = |blockchain: &TransactionBlockchain| {
+            blockchain
+                .get_participant_wallet(farmer_id)
+                .unwrap()
+                .clone()
+        };
+        assert!(!holder(&blockchain).has_valid_certificate("ORGANIC", &blockchain));
+
+        blockchain.create_block(10, auditor_id).unwrap();
+        let block = blockchain.blockchain.chain.last().unwrap();
+        assert!(block.data.contains("trace:VerifiableCredential"));
+        assert!(holder(&blockchain).has_valid_certificate("ORGANIC", &blockchain));
+        assert!(!holder(&blockchain).has_valid_certificate("FDA_APPROVED", &blockchain));
+
+        // Editing the local struct does not make the certificate valid
+        let mut forged = holder(&blockchain);
+        forged.participant.certificates[0].cert_type = "FDA_APPROVED".to_string();
+        assert!(!forged.has_valid_certificate("FDA_APPROVED", &blockchain));
+
+        blockchain
+            .revoke_certificate(auditor_id, &credential_id)
+            .unwrap();
+        blockchain.create_block(10, auditor_id).unwrap();
+        assert!(!holder(&blockchain).has_valid_certificate("ORGANIC", &blockchain));
+        assert_eq!(
+            holder(&blockchain).participant.certificates[0].status,
+            CertificateStatus::Revoked
+        );
+
+        let status_list = blockchain.status_list_credential(auditor_id).unwrap();
+        let index = blockchain.ledger.anchored_credentials(auditor_id)[0].status_list_index;
+        let encoded = status_list.credential_subject["encodedList"]
+            .as_str()
+            .unwrap();
+        assert!(crate::wallet::credentials::StatusList::decode(encoded)
+            .unwrap()
+            .is_set(index));
+
+        // Anchors and revocations come from the chain, not from local wallets
+        let rebuilt = LedgerState::from_chain(&blockchain.blockchain.chain).unwrap();
+        assert_eq!(
+            rebuilt.anchored_credentials(auditor_id),
+            blockchain.ledger.anchored_credentials(auditor_id)
+        );
+        assert!(rebuilt.is_revoked(auditor_id, index));
+    }
+
+    #[test]
+    fn test_batch_transfers_are_tracked_and_double_spends_rejected() {
+        let temp_dir = tempdir().unwrap();
+        let data_dir = temp_dir.path().to_str().unwrap();
+        let mut blockchain = TransactionBlockchain::new(data_dir)
+            .unwrap()
+            .with_kdf_iterations(10);
+        let farmer_id = blockchain
+            .register_participant(
+                Participant::new_farmer(
+                    "John's Dairy Farm".to_string(),
+                    "Vermont, USA".to_string(),
+                ),
+                PASSWORD,
+            )
+            .unwrap();
+        let processor_id = blockchain
+            .register_participant(
+                Participant::new_uht_manufacturer(
+                    "Valley Processing".to_string(),
+                    "Vermont, USA".to_string(),
+                ),
+                PASSWORD,
+            )
+            .unwrap();
+        let retailer_id = blockchain
+            .register_participant(
+                Participant::new_retailer("Corner Store".to_string(), "Boston, USA".to_string()),
+                PASSWORD,
+            )
+            .unwrap();
+
+        let production = blockchain
+            .create_production_transaction(
+                farmer_id,
+                "MILK-001".to_string(),
+                1000.0,
+                "Vermont, USA".to_string(),
+                None,
+            )
+            .unwrap();
+        let production_id = blockchain.submit_transaction(production).unwrap();
+        // Only batches on the chain can change hands
+        assert!(blockchain
+            .create_transfer_transaction(farmer_id, "MILK-001".to_string(), processor_id)
+            .is_err());
+        blockchain.create_block(10, farmer_id).unwrap();
+
+        let to_processor = blockchain
+            .create_transfer_transaction(farmer_id, "MILK-001".to_string(), processor_id)
+            .unwrap();
+        let to_retailer = blockchain
+            .create_transfer_transaction(farmer_id, "MILK-001".to_string(), retailer_id)
+            .unwrap();
+        let first_transfer = blockchain.submit_transaction(to_processor).unwrap();
+        let error = blockchain.submit_transaction(to_retailer).unwrap_err();
+        assert!(error.to_string().contains("already spent by pending"));
+        blockchain.create_block(10, farmer_id).unwrap();
+
+        // Spending the farmer's output again is rejected once it is on-chain, too
+        let mut replay = blockchain
+            .create_production_transaction(
+                farmer_id,
+                "MILK-002".to_string(),
+                1000.0,
+                "Vermont, USA".to_string(),
+                None,
+            )
+            .unwrap();
+        replay.tx_type = TransactionType::Transfer;
+        replay.inputs = vec![spend(
+            &blockchain.ledger.output("MILK-001:0").unwrap().output,
+        )];
+        replay.outputs[0].owner = retailer_id;
+        replay.outputs[0].metadata = blockchain
+            .ledger
+            .output("MILK-001:0")
+            .unwrap()
+            .output
+            .metadata
+            .clone();
+        replay.signatures.clear();
+        let farmer_wallet = blockchain.get_participant_wallet(farmer_id).unwrap();
+        replay
+            .sign(farmer_wallet.unlocked_key().unwrap(), farmer_id)
+            .unwrap();
+        let error = blockchain.submit_transaction(replay).unwrap_err();
+        assert!(error.to_string().contains("already spent by transaction"));
+        assert!(blockchain
+            .create_transfer_transaction(farmer_id, "MILK-001".to_string(), retailer_id)
+            .is_err());
+
+        let second_transfer = blockchain
+            .create_transfer_transaction(processor_id, "MILK-001".to_string(), retailer_id)
+            .map(|tx| blockchain.submit_transaction(tx))
+            .unwrap()
+            .unwrap();
+        blockchain.create_block(10, processor_id).unwrap();
+
+        let ownership = blockchain.batch_ownership("MILK-001").unwrap();
+        assert_eq!(ownership.current_owner, Some(retailer_id));
+        assert_eq!(ownership.consumed_by, None);
+        let owners: Vec<_> = ownership.history.iter().map(|r| r.output.owner).collect();
+        assert_eq!(owners, vec![farmer_id, processor_id, retailer_id]);
+        let transactions: Vec<_> = ownership
+            .history
+            .iter()
+            .map(|r| r.created_by.as_str())
+            .collect();
+        assert_eq!(
+            transactions,
+            vec![
+                production_id.as_str(),
+                first_transfer.as_str(),
+                second_transfer.as_str()
+            ]
+        );
+        assert_eq!(ownership.latest_output().value, 1000.0);
+        assert_eq!(blockchain.get_statistics().total_utxos, 1);
+
+        // A restart catches up from the saved ledger, or rebuilds it from the blocks
+        let ledger = serde_json::to_value(&blockchain.ledger).unwrap();
+        drop(blockchain);
+        let restarted = TransactionBlockchain::new(data_dir)
+            .unwrap()
+            .with_kdf_iterations(10);
+        assert_eq!(serde_json::to_value(&restarted.ledger).unwrap(), ledger);
+        drop(restarted);
+        std::fs::remove_file(temp_dir.path().join(LEDGER_FILE)).unwrap();
+        let rebuilt = TransactionBlockchain::new(data_dir)
+            .unwrap()
+            .with_kdf_iterations(10);
+        assert_eq!(serde_json::to_value(&rebuilt.ledger).unwrap(), ledger);
+        assert_eq!(
+            rebuilt.batch_ownership("MILK-001").unwrap().current_owner,
+            Some(retailer_id)
+        );
+    }
+
+    #[test]
+    fn test_blocks_only_carry_transactions_signed_by_input_owners() {
+        let temp_dir = tempdir().unwrap();
+        let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+            .unwrap()
+            .with_kdf_iterations(10);
+        let farmer_id = blockchain
+            .register_participant(
+                Participant::new_farmer(
+                    "John's Dairy Farm".to_string(),
+                    "Vermont, USA".to_string(),
+                ),
+                PASSWORD,
+            )
+            .unwrap();
+        let processor_id = blockchain
+            .register_participant(
+                Participant::new_uht_manufacturer(
+                    "Valley Processing".to_string(),
+                    "Vermont, USA".to_string(),
+                ),
+                PASSWORD,
+            )
+            .unwrap();
+        let production = blockchain
+            .create_production_transaction(
+                farmer_id,
+                "MILK-001".to_string(),
+                1000.0,
+                "Vermont, USA".to_string(),
+                None,
+            )
+            .unwrap();
+        blockchain.submit_transaction(production).unwrap();
+
+        // Processing the farmer's batch needs the farmer's signature
+        let mut processing = blockchain
+            .create_processing_transaction(
+                processor_id,
+                vec!["MILK-001".to_string()],
+                "UHT-001".to_string(),
+                "UHT".to_string(),
+                None,
+            )
+            .unwrap();
+        let error = blockchain
+            .submit_transaction(processing.clone())
+            .unwrap_err();
+        assert!(error.to_string().contains("who did not sign"));
+        blockchain
+            .cosign_transaction(&mut processing, farmer_id)
+            .unwrap();
+        blockchain.submit_transaction(processing).unwrap();
+        blockchain.create_block(10, farmer_id).unwrap();
+        let ownership = blockchain.batch_ownership("MILK-001").unwrap();
+        assert_eq!(ownership.current_owner, None);
+
+        // Blocks built elsewhere are held to the same rules
+        let production = blockchain
+            .create_production_transaction(
+                farmer_id,
+                "MILK-002".to_string(),
+                1000.0,
+                "Vermont, USA".to_string(),
+                None,
+            )
+            .unwrap();
+        blockchain.submit_transaction(production).unwrap();
+        blockchain.create_block(10, farmer_id).unwrap();
+        let transfer = blockchain
+            .create_transfer_transaction(farmer_id, "MILK-002".to_string(), processor_id)
+            .unwrap();
+        let validate = |blockchain: &TransactionBlockchain, transaction: &Transaction| {
+            blockchain.ledger.validate_block_data(
+                &blockchain
+                    .create_block_rdf_data(std::slice::from_ref(transaction))
+                    .unwrap(),
+            )
+        };
+        validate(&blockchain, &transfer).unwrap();
+
+        let mut tampered = transfer.clone();
+        tampered.outputs[0].owner = Uuid::new_v4();
+        let error = validate(&blockchain, &tampered).unwrap_err();
+        assert!(error.to_string().contains("is not validly signed"));
+
+        let processor_key = blockchain
+            .get_participant_wallet(processor_id)
+            .unwrap()
+            .unlocked_key()
+            .unwrap()
+            .clone();
+        let mut stolen = transfer.clone();
+        stolen.signatures.clear();
+        stolen.nonce = blockchain.ledger.next_nonce(processor_id);
+        stolen.sign(&processor_key, processor_id).unwrap();
+        let error = validate(&blockchain, &stolen).unwrap_err();
+        assert!(error.to_string().contains("without its signature"));
+
+        let mut impersonating = transfer;
+        impersonating.signatures.clear();
+        impersonating.sign(&processor_key, farmer_id).unwrap();
+        let error = validate(&blockchain, &impersonating).unwrap_err();
+        assert!(error.to_string().contains("does not sign for participant"));
+    }
+
+    #[test]
+    fn test_nonces_prevent_replay_across_blocks() {
+        let temp_dir = tempdir().unwrap();
+        let data_dir = temp_dir.path().to_str().unwrap();
+        let mut blockchain = TransactionBlockchain::new(data_dir)
+            .unwrap()
+            .with_kdf_iterations(10);
+        let farmer_id = blockchain
+            .register_participant(
+                Participant::new_farmer(
+                    "John's Dairy Farm".to_string(),
+                    "Vermont, USA".to_string(),
+                ),
+                PASSWORD,
+            )
+            .unwrap();
+        let produce = |blockchain: &TransactionBlockchain, batch_id: &str| {
+            blockchain
+                .create_production_transaction(
+                    farmer_id,
+                    batch_id.to_string(),
+                    1000.0,
+                    "Vermont, USA".to_string(),
+                    None,
+                )
+                .unwrap()
+        };
+
+        // Transactions signed before either is submitted share a nonce
+        let first = produce(&blockchain, "MILK-001");
+        let conflicting = produce(&blockchain, "MILK-002");
+        assert_eq!((first.nonce, conflicting.nonce), (0, 0));
+        blockchain.submit_transaction(first).unwrap();
+        let error = blockchain.submit_transaction(conflicting).unwrap_err();
+        assert!(error.to_string().contains("already used"));
+        let second = produce(&blockchain, "MILK-002");
+        assert_eq!(second.nonce, 1);
+        blockchain.submit_transaction(second).unwrap();
+        blockchain.create_block(10, farmer_id).unwrap();
+        assert_eq!(blockchain.ledger.next_nonce(farmer_id), 2);
+        assert_eq!(blockchain.next_nonce(farmer_id), 2);
+
+        // A nonce included in a block cannot be signed over again
+        let mut replay = produce(&blockchain, "MILK-003");
+        replay.nonce = 1;
+        replay.signatures.clear();
+        let farmer_wallet = blockchain.get_participant_wallet(farmer_id).unwrap();
+        replay
+            .sign(farmer_wallet.unlocked_key().unwrap(), farmer_id)
+            .unwrap();
+        let replayed_block = blockchain
+            .create_block_rdf_data(std::slice::from_ref(&replay))
+            .unwrap();
+        let error = blockchain.submit_transaction(replay).unwrap_err();
+        assert!(error.to_string().contains("already used"));
+
+        // Block validation applies the same rule to blocks built elsewhere
+        let error = blockchain
+            .ledger
+            .validate_block_data(&replayed_block)
+            .unwrap_err();
+        assert!(error.to_string().contains("next nonce of participant"));
+        let third = produce(&blockchain, "MILK-003");
+        let mut skipping = third.clone();
+        skipping.nonce = 3;
+        assert!(blockchain
+            .ledger
+            .validate_block_data(&blockchain.create_block_rdf_data(&[skipping]).unwrap())
+            .is_err());
+        assert!(blockchain
+            .ledger
+            .validate_block_data(&blockchain.create_block_rdf_data(&[third]).unwrap())
+            .is_ok());
+
+        // Nonces are part of the chain state and survive a restart
+        drop(blockchain);
+        std::fs::remove_file(temp_dir.path().join(LEDGER_FILE)).unwrap();
+        let restarted = TransactionBlockchain::new(data_dir)
+            .unwrap()
+            .with_kdf_iterations(10);
+        assert_eq!(restarted.next_nonce(farmer_id), 2);
+    }
+
+    #[test]
+    fn test_chain_blocks_leave_double_spends_out_of_the_ledger() {
+        let temp_dir = tempdir().unwrap();
+        let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+            .unwrap()
+            .with_kdf_iterations(10);
+        let farmer_id = blockchain
+            .register_participant(
+                Participant::new_farmer(
+                    "John's Dairy Farm".to_string(),
+                    "Vermont, USA".to_string(),
+                ),
+                PASSWORD,
+            )
+            .unwrap();
+        let processor_id = blockchain
+            .register_participant(
+                Participant::new_uht_manufacturer(
+                    "Valley Processing".to_string(),
+                    "Vermont, USA".to_string(),
+                ),
+                PASSWORD,
+            )
+            .unwrap();
+        let retailer_id = blockchain
+            .register_participant(
+                Participant::new_retailer("Corner Store".to_string(), "Boston, USA".to_string()),
+                PASSWORD,
+            )
+            .unwrap();
+        let production = blockchain
+            .create_production_transaction(
+                farmer_id,
+                "MILK-001".to_string(),
+                1000.0,
+                "Vermont, USA".to_string(),
+                None,
+            )
+            .unwrap();
+        blockchain.submit_transaction(production).unwrap();
+        blockchain.create_block(10, farmer_id).unwrap();
+
+        // Two transfers of the same output, each continuing the farmer's nonces
+        let to_processor = blockchain
+            .create_transfer_transaction(farmer_id, "MILK-001".to_string(), processor_id)
+            .unwrap();
+        let mut to_retailer = blockchain
+            .create_transfer_transaction(farmer_id, "MILK-001".to_string(), retailer_id)
+            .unwrap();
+        to_retailer.nonce = to_processor.nonce + 1;
+        to_retailer.signatures.clear();
+        let farmer_wallet = blockchain.get_participant_wallet(farmer_id).unwrap();
+        to_retailer
+            .sign(farmer_wallet.unlocked_key().unwrap(), farmer_id)
+            .unwrap();
+        let data = blockchain
+            .create_block_rdf_data(&[to_processor.clone(), to_retailer.clone()])
+            .unwrap();
+        assert!(blockchain.ledger.validate_block_data(&data).is_err());
+
+        // A block on the chain anyway applies the first spend and leaves the second out
+        let mut ledger = blockchain.ledger.clone();
+        let tip = blockchain.blockchain.chain.last().unwrap();
+        let block = crate::core::blockchain::Block::new(
+            ledger.height(),
+            data,
+            tip.hash.clone(),
+            tip.state_root.clone(),
+            tip.validator.clone(),
+        );
+        ledger.apply_block(&block).unwrap();
+        assert!(ledger.transaction_location(&to_processor.id).is_some());
+        assert!(ledger.transaction_location(&to_retailer.id).is_none());
+        let ownership = ledger.batch_ownership("MILK-001").unwrap();
+        assert_eq!(ownership.current_owner, Some(processor_id));
+        assert!(ownership
+            .history
+            .iter()
+            .all(|record| record.created_by != to_retailer.id));
+        assert_eq!(
+            ledger.spent_by("MILK-001:0"),
+            Some(to_processor.id.as_str())
+        );
+        assert_eq!(ledger.next_nonce(farmer_id), to_processor.nonce + 1);
+    }
 }
 
 #[cfg(test)]
@@ -635,6 +2106,8 @@ mod security_tests {
     use std::thread;
     use tempfile::tempdir;
 
+    const PASSWORD: &str = "wallet-password";
+
     /// Blockchain integration security tests
     mod blockchain_integration_security {
         use super::*;
@@ -642,13 +2115,14 @@ mod security_tests {
         #[test]
         fn test_block_transaction_validation() {
             let temp_dir = tempdir().unwrap();
-            let mut blockchain =
-                TransactionBlockchain::new(temp_dir.path().to_str().unwrap()).unwrap();
+            let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+                .unwrap()
+                .with_kdf_iterations(10);
 
             // Register a participant
             let farmer =
                 Participant::new_farmer("Test Farm".to_string(), "Test Location".to_string());
-            let farmer_id = blockchain.register_participant(farmer).unwrap();
+            let farmer_id = blockchain.register_participant(farmer, PASSWORD).unwrap();
 
             // Create valid transaction
             let valid_tx = blockchain
@@ -686,8 +2160,9 @@ mod security_tests {
         #[test]
         fn test_chain_integrity_verification() {
             let temp_dir = tempdir().unwrap();
-            let mut blockchain =
-                TransactionBlockchain::new(temp_dir.path().to_str().unwrap()).unwrap();
+            let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+                .unwrap()
+                .with_kdf_iterations(10);
 
             // Verify initial chain integrity
             assert!(blockchain.validate(), "Initial blockchain should be valid");
@@ -695,7 +2170,7 @@ mod security_tests {
             // Register participant and create transactions
             let farmer =
                 Participant::new_farmer("Test Farm".to_string(), "Test Location".to_string());
-            let farmer_id = blockchain.register_participant(farmer).unwrap();
+            let farmer_id = blockchain.register_participant(farmer, PASSWORD).unwrap();
 
             // Create and submit multiple transactions
             for i in 0..5 {
@@ -739,15 +2214,17 @@ mod security_tests {
         #[test]
         fn test_fork_resolution_security() {
             let temp_dir = tempdir().unwrap();
-            let mut blockchain =
-                TransactionBlockchain::new(temp_dir.path().to_str().unwrap()).unwrap();
+            let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+                .unwrap()
+                .with_kdf_iterations(10);
 
             // Create two competing blocks with same parent
             let farmer =
                 Participant::new_farmer("Test Farm".to_string(), "Test Location".to_string());
-            let farmer_id = blockchain.register_participant(farmer).unwrap();
+            let farmer_id = blockchain.register_participant(farmer, PASSWORD).unwrap();
 
-            // Create transactions
+            // Create and submit transactions; each is signed with the nonce after the
+            // previous one's
             let tx1 = blockchain
                 .create_production_transaction(
                     farmer_id,
@@ -757,6 +2234,9 @@ mod security_tests {
                     None,
                 )
                 .unwrap();
+            blockchain
+                .submit_transaction(tx1)
+                .expect("First transaction should submit");
 
             let tx2 = blockchain
                 .create_production_transaction(
@@ -767,11 +2247,6 @@ mod security_tests {
                     None,
                 )
                 .unwrap();
-
-            // Submit transactions
-            blockchain
-                .submit_transaction(tx1)
-                .expect("First transaction should submit");
             blockchain
                 .submit_transaction(tx2)
                 .expect("Second transaction should submit");
@@ -796,13 +2271,14 @@ mod security_tests {
         #[test]
         fn test_consensus_integration_security() {
             let temp_dir = tempdir().unwrap();
-            let mut blockchain =
-                TransactionBlockchain::new(temp_dir.path().to_str().unwrap()).unwrap();
+            let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+                .unwrap()
+                .with_kdf_iterations(10);
 
             // Test that only properly validated transactions are included in blocks
             let farmer =
                 Participant::new_farmer("Test Farm".to_string(), "Test Location".to_string());
-            let farmer_id = blockchain.register_participant(farmer).unwrap();
+            let farmer_id = blockchain.register_participant(farmer, PASSWORD).unwrap();
 
             // Create valid transaction
             let valid_tx = blockchain
@@ -834,8 +2310,9 @@ mod security_tests {
         #[test]
         fn test_permission_enforcement() {
             let temp_dir = tempdir().unwrap();
-            let mut blockchain =
-                TransactionBlockchain::new(temp_dir.path().to_str().unwrap()).unwrap();
+            let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+                .unwrap()
+                .with_kdf_iterations(10);
 
             // Register different types of participants
             let farmer =
@@ -845,8 +2322,10 @@ mod security_tests {
                 "Test Location".to_string(),
             );
 
-            let farmer_id = blockchain.register_participant(farmer).unwrap();
-            let processor_id = blockchain.register_participant(processor).unwrap();
+            let farmer_id = blockchain.register_participant(farmer, PASSWORD).unwrap();
+            let processor_id = blockchain
+                .register_participant(processor, PASSWORD)
+                .unwrap();
 
             // Test farmer permissions
             let farmer_production_tx = blockchain.create_production_transaction(
@@ -881,12 +2360,13 @@ mod security_tests {
         #[test]
         fn test_replay_attack_protection() {
             let temp_dir = tempdir().unwrap();
-            let mut blockchain =
-                TransactionBlockchain::new(temp_dir.path().to_str().unwrap()).unwrap();
+            let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+                .unwrap()
+                .with_kdf_iterations(10);
 
             let farmer =
                 Participant::new_farmer("Test Farm".to_string(), "Test Location".to_string());
-            let farmer_id = blockchain.register_participant(farmer).unwrap();
+            let farmer_id = blockchain.register_participant(farmer, PASSWORD).unwrap();
 
             // Create transaction
             let tx = blockchain
@@ -914,8 +2394,9 @@ mod security_tests {
         #[test]
         fn test_sybil_attack_resistance() {
             let temp_dir = tempdir().unwrap();
-            let mut blockchain =
-                TransactionBlockchain::new(temp_dir.path().to_str().unwrap()).unwrap();
+            let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+                .unwrap()
+                .with_kdf_iterations(10);
 
             // Attempt to register many participants (potential Sybil attack)
             let mut participant_ids = Vec::new();
@@ -923,7 +2404,7 @@ mod security_tests {
                 let participant =
                     Participant::new_farmer(format!("Farm {}", i), format!("Location {}", i));
 
-                let registration_result = blockchain.register_participant(participant);
+                let registration_result = blockchain.register_participant(participant, PASSWORD);
                 if registration_result.is_ok() {
                     participant_ids.push(registration_result.unwrap());
                 }
@@ -967,12 +2448,13 @@ mod security_tests {
         #[test]
         fn test_denial_of_service_resistance() {
             let temp_dir = tempdir().unwrap();
-            let mut blockchain =
-                TransactionBlockchain::new(temp_dir.path().to_str().unwrap()).unwrap();
+            let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+                .unwrap()
+                .with_kdf_iterations(10);
 
             let farmer =
                 Participant::new_farmer("Test Farm".to_string(), "Test Location".to_string());
-            let farmer_id = blockchain.register_participant(farmer).unwrap();
+            let farmer_id = blockchain.register_participant(farmer, PASSWORD).unwrap();
 
             // Test with large transaction data (potential DoS attack)
             let large_description = "A".repeat(100000); // Large description
@@ -1028,13 +2510,14 @@ mod security_tests {
         #[test]
         fn test_privacy_leakage_prevention() {
             let temp_dir = tempdir().unwrap();
-            let mut blockchain =
-                TransactionBlockchain::new(temp_dir.path().to_str().unwrap()).unwrap();
+            let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+                .unwrap()
+                .with_kdf_iterations(10);
 
             // Test with sensitive data
             let farmer =
                 Participant::new_farmer("Private Farm".to_string(), "Secret Location".to_string());
-            let farmer_id = blockchain.register_participant(farmer).unwrap();
+            let farmer_id = blockchain.register_participant(farmer, PASSWORD).unwrap();
 
             // Create transaction with potentially sensitive data
             let sensitive_tx = blockchain
@@ -1069,8 +2552,9 @@ mod security_tests {
         #[test]
         fn test_blockchain_state_consistency() {
             let temp_dir = tempdir().unwrap();
-            let mut blockchain =
-                TransactionBlockchain::new(temp_dir.path().to_str().unwrap()).unwrap();
+            let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+                .unwrap()
+                .with_kdf_iterations(10);
 
             // Track initial state
             let initial_stats = blockchain.get_statistics();
@@ -1079,7 +2563,7 @@ mod security_tests {
             // Perform series of operations
             let farmer =
                 Participant::new_farmer("Test Farm".to_string(), "Test Location".to_string());
-            let farmer_id = blockchain.register_participant(farmer).unwrap();
+            let farmer_id = blockchain.register_participant(farmer, PASSWORD).unwrap();
 
             // Create multiple transactions
             let transactions: Vec<_> = (0..5)
@@ -1130,7 +2614,9 @@ mod security_tests {
         fn test_concurrent_blockchain_operations() {
             let temp_dir = tempdir().unwrap();
             let blockchain = Arc::new(Mutex::new(
-                TransactionBlockchain::new(temp_dir.path().to_str().unwrap()).unwrap(),
+                TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+                    .unwrap()
+                    .with_kdf_iterations(10),
             ));
             let mut handles = vec![];
 
@@ -1144,7 +2630,7 @@ mod security_tests {
                     let participant =
                         Participant::new_farmer(format!("Farm {}", i), format!("Location {}", i));
 
-                    let participant_id = blockchain.register_participant(participant);
+                    let participant_id = blockchain.register_participant(participant, PASSWORD);
 
                     if participant_id.is_ok() {
                         let pid = participant_id.unwrap();
@@ -1193,12 +2679,13 @@ mod security_tests {
         #[test]
         fn test_transaction_pool_overflow_protection() {
             let temp_dir = tempdir().unwrap();
-            let mut blockchain =
-                TransactionBlockchain::new(temp_dir.path().to_str().unwrap()).unwrap();
+            let mut blockchain = TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+                .unwrap()
+                .with_kdf_iterations(10);
 
             let farmer =
                 Participant::new_farmer("Test Farm".to_string(), "Test Location".to_string());
-            let farmer_id = blockchain.register_participant(farmer).unwrap();
+            let farmer_id = blockchain.register_participant(farmer, PASSWORD).unwrap();
 
             // Fill transaction pool beyond capacity
             let mut successful_submissions = 0;
@@ -0,0 +1,851 @@
+//! UTXO and batch ownership state derived from the chain
+//!
+//! Every transaction written into a block carries a signed copy of itself
+//! (`tx:hasSignedTransaction`, see `Transaction::to_rdf`) next to its readable triples,
+//! and its position in the block. The ledger is a fold over the signed copies in block
+//! order, so replaying the same blocks always yields the same state: outputs and their
+//! spends, nonces, the keys participants signed with, delegated and retired, and the
+//! credentials issuers anchored and revoked. Governance proposals and votes are left
+//! to [`crate::governance::Governance`], which folds them the same way.
+//!
+//! A transaction only enters the ledger if its signatures verify, each signer signs
+//! with its key on the chain (the key of its first transaction, as rotated since, or a
+//! key it delegated), key and credential records are signed by the participant they
+//! are about, and every output it spends exists and is owned by one of its signers.
+//! Blocks breaking these rules, or reusing nonces or outputs, fail
+//! [`LedgerState::validate_block_data`]; where such a block is on the chain anyway,
+//! the offending transactions are left out.
+//!
+//! A copy is saved after each block, stamped with the height and hash of the last block
+//! it covers; on startup it is caught up with the chain, or rebuilt from the genesis
+//! block when it is missing or belongs to another chain.
+
+use crate::core::blockchain::Block;
+use crate::transaction::transaction::{
+    CredentialAnchor, KeyDelegation, KeyRotation, Transaction, TransactionOutput,
+    TransactionPayload, TransactionType,
+};
+use crate::wallet::credentials::{AnchoredCredential, StatusList, STATUS_LIST_LENGTH};
+use crate::wallet::RetiredKey;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use oxigraph::io::RdfFormat;
+use oxigraph::model::Term;
+use oxigraph::sparql::QueryResults;
+use oxigraph::store::Store;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use uuid::Uuid;
+
+/// File the ledger is saved to, in the blockchain's data directory
+pub const LEDGER_FILE: &str = "ledger.json";
+
+const TX_NAMESPACE: &str = "http://provchain.org/tx#";
+
+/// Where a transaction was included in the chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionLocation {
+    pub block_index: u64,
+    pub position: usize,
+}
+
+/// An output created on-chain and, once spent, the transaction spending it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputRecord {
+    pub output: TransactionOutput,
+    /// Transaction creating the output
+    pub created_by: String,
+    pub tx_type: TransactionType,
+    pub block_index: u64,
+    pub timestamp: DateTime<Utc>,
+    pub spent_by: Option<String>,
+}
+
+/// Current owner of a batch and the outputs it passed through to get there
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchOwnership {
+    pub batch_id: String,
+    /// Owner of the batch's unspent output; `None` once the batch was consumed
+    pub current_owner: Option<Uuid>,
+    /// Transaction that consumed the batch, e.g. processing it into another batch
+    pub consumed_by: Option<String>,
+    /// Outputs of the batch from the one producing it to the latest transfer
+    pub history: Vec<OutputRecord>,
+}
+
+impl BatchOwnership {
+    /// Latest output of the batch, unspent unless the batch was consumed
+    pub fn latest_output(&self) -> &TransactionOutput {
+        &self.history[self.history.len() - 1].output
+    }
+}
+
+/// A derived key that signs on behalf of the participant, as delegated on-chain
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DelegatedKey {
+    /// Hex-encoded public key of the derived key
+    pub public_key: String,
+    /// Path of the key below the participant's wallet key
+    pub derivation_path: String,
+    /// Line or device the key belongs to
+    pub label: String,
+    pub delegated_at: DateTime<Utc>,
+    /// Set once a revocation is recorded; the key no longer signs for the participant
+    pub revoked_at: Option<DateTime<Utc>>,
+    /// Transaction recording the delegation
+    pub transaction_id: String,
+}
+
+/// UTXO set, spent outputs, transaction locations, participant keys and credentials as
+/// of a block
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LedgerState {
+    /// Number of blocks applied
+    height: u64,
+    /// Hash of the last block applied
+    tip_hash: String,
+    transactions: BTreeMap<String, TransactionLocation>,
+    outputs: BTreeMap<String, OutputRecord>,
+    /// Next nonce of each signer
+    nonces: BTreeMap<Uuid, u64>,
+    /// Hex-encoded key each participant signs with
+    participant_keys: BTreeMap<Uuid, String>,
+    /// Keys each participant delegated, revoked ones included
+    delegations: BTreeMap<Uuid, Vec<DelegatedKey>>,
+    /// Keys each participant rotated out, oldest first
+    retired_keys: BTreeMap<Uuid, Vec<RetiredKey>>,
+    /// Credentials each issuer anchored
+    credentials: BTreeMap<Uuid, Vec<AnchoredCredential>>,
+    /// Revocation list of each issuer with revoked credentials
+    revocation_lists: BTreeMap<Uuid, StatusList>,
+}
+
+impl LedgerState {
+    /// Derive the ledger from `blocks`, starting at the genesis block
+    pub fn from_chain(blocks: &[Block]) -> Result<Self> {
+        let mut ledger = Self::default();
+        ledger.sync(blocks)?;
+        Ok(ledger)
+    }
+
+    /// Load the ledger saved at `path` and catch it up with `blocks`, rebuilding it when
+    /// there is no usable saved copy; the result is saved back if it changed
+    pub fn open<P: AsRef<Path>>(path: P, blocks: &[Block]) -> Result<Self> {
+        let path = path.as_ref();
+        let mut ledger = fs::read(path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<Self>(&bytes).ok())
+            .unwrap_or_default();
+        if ledger.sync(blocks)? {
+            ledger.save(path)?;
+        }
+        Ok(ledger)
+    }
+
+    /// Write the ledger to `path`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&temp, path)?;
+        Ok(())
+    }
+
+    /// Apply the blocks of `blocks` not applied yet, starting over when the ledger does
+    /// not end on a block of this chain. Returns whether anything changed.
+    pub fn sync(&mut self, blocks: &[Block]) -> Result<bool> {
+        let height = self.height as usize;
+        let on_chain = height == 0
+            || blocks
+                .get(height - 1)
+                .is_some_and(|block| block.hash == self.tip_hash);
+        let reset = !on_chain;
+        if reset {
+            *self = Self::default();
+        }
+
+        let applied = blocks.len() > self.height as usize;
+        for block in &blocks[self.height as usize..] {
+            self.apply_block(block)?;
+        }
+        Ok(reset || applied)
+    }
+
+    /// Apply the transactions recorded in `block`, which must be the next block
+    pub fn apply_block(&mut self, block: &Block) -> Result<()> {
+        if block.index != self.height {
+            return Err(anyhow!(
+                "Ledger is at height {} but block {} was applied",
+                self.height,
+                block.index
+            ));
+        }
+
+        let records = BlockRecords::parse(&block.data)?;
+        self.apply_records(&records, block.index, false)?;
+
+        self.height = block.index + 1;
+        self.tip_hash = block.hash.clone();
+        Ok(())
+    }
+
+    /// Apply the transactions of a block in block order. When `strict`, the first
+    /// transaction that may not enter the ledger fails the block; otherwise it is left
+    /// out.
+    fn apply_records(
+        &mut self,
+        records: &BlockRecords,
+        block_index: u64,
+        strict: bool,
+    ) -> Result<()> {
+        let block_outputs = records.outputs();
+        let mut spent = HashMap::new();
+        let mut applied = Vec::new();
+        for record in &records.transactions {
+            // Governance transactions are signed by validator keys and folded into
+            // `Governance` instead
+            let governance = record.transaction.as_ref().is_ok_and(|transaction| {
+                matches!(
+                    transaction.payload,
+                    Some(TransactionPayload::Governance(_) | TransactionPayload::GovernanceVote(_))
+                )
+            });
+            if governance {
+                continue;
+            }
+
+            let checked = record
+                .transaction
+                .as_ref()
+                .map_err(|e| anyhow!("Transaction {} cannot be read: {}", record.tx_id, e));
+            let checked = checked.and_then(|transaction| {
+                self.check_transaction(transaction, &block_outputs)?;
+                self.check_sequence(transaction, &spent)?;
+                Ok(transaction)
+            });
+            let transaction = match checked {
+                Ok(transaction) => transaction,
+                Err(e) if strict => return Err(e),
+                Err(_) => continue,
+            };
+
+            for input in &transaction.inputs {
+                spent
+                    .entry(input.output_id())
+                    .or_insert_with(|| transaction.id.clone());
+            }
+            self.apply_transaction(transaction, block_index, record.position)?;
+            applied.push(transaction);
+        }
+
+        // Spends are applied after all outputs of the block exist, in block order, and
+        // only the first spend of an output counts
+        for transaction in applied {
+            for input in &transaction.inputs {
+                if let Some(record) = self.outputs.get_mut(&input.output_id()) {
+                    record
+                        .spent_by
+                        .get_or_insert_with(|| transaction.id.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Record a checked transaction's location, nonce, signing keys, payload and outputs
+    fn apply_transaction(
+        &mut self,
+        transaction: &Transaction,
+        block_index: u64,
+        position: usize,
+    ) -> Result<()> {
+        let tx_id = &transaction.id;
+        self.transactions.insert(
+            tx_id.clone(),
+            TransactionLocation {
+                block_index,
+                position,
+            },
+        );
+        if let Some(signer_id) = transaction.signer_id() {
+            let next_nonce = self.nonces.entry(signer_id).or_default();
+            *next_nonce = (*next_nonce).max(transaction.nonce + 1);
+        }
+        // A participant is bound to the key of its first signature on the chain
+        for signature in &transaction.signatures {
+            self.participant_keys
+                .entry(signature.signer_id)
+                .or_insert_with(|| hex::encode(signature.public_key.as_bytes()));
+        }
+        match &transaction.payload {
+            Some(TransactionPayload::KeyDelegation(delegation)) => {
+                self.apply_delegation(delegation, tx_id, transaction.timestamp);
+            }
+            Some(TransactionPayload::KeyRotation(rotation)) => {
+                self.apply_rotation(rotation, tx_id, transaction.timestamp);
+            }
+            Some(TransactionPayload::Credential(anchor)) => {
+                self.apply_credential(anchor, tx_id, transaction.timestamp)?;
+            }
+            _ => {}
+        }
+        for output in &transaction.outputs {
+            // Output IDs are unique; a later output reusing one never enters the ledger
+            self.outputs
+                .entry(output.id.clone())
+                .or_insert_with(|| OutputRecord {
+                    output: output.clone(),
+                    created_by: tx_id.clone(),
+                    tx_type: transaction.tx_type.clone(),
+                    block_index,
+                    timestamp: transaction.timestamp,
+                    spent_by: None,
+                });
+        }
+        Ok(())
+    }
+
+    /// Check that a transaction may enter the ledger: its signatures verify and are made
+    /// with keys of their signers, key and credential records are signed by the
+    /// participant they are about, and every output it spends, on the chain or created
+    /// in the same block, belongs to one of its signers
+    fn check_transaction(
+        &self,
+        transaction: &Transaction,
+        block_outputs: &HashMap<&str, &TransactionOutput>,
+    ) -> Result<()> {
+        let tx_id = &transaction.id;
+        if !transaction.verify_signatures()? {
+            return Err(anyhow!("Transaction {} is not validly signed", tx_id));
+        }
+        let signed_with = |participant_id: Uuid, key: &str| {
+            transaction.signatures.iter().any(|signature| {
+                signature.signer_id == participant_id
+                    && hex::encode(signature.public_key.as_bytes()) == key
+            })
+        };
+
+        for signature in &transaction.signatures {
+            let key = hex::encode(signature.public_key.as_bytes());
+            // Key records are also signed by the key they introduce
+            let introduced = match &transaction.payload {
+                Some(TransactionPayload::KeyRotation(rotation)) => {
+                    rotation.participant_id == signature.signer_id && rotation.new_key == key
+                }
+                Some(TransactionPayload::KeyDelegation(delegation)) => {
+                    !delegation.revoked
+                        && delegation.participant_id == signature.signer_id
+                        && delegation.public_key == key
+                }
+                _ => false,
+            };
+            if !introduced
+                && !self.is_current_key(signature.signer_id, &key)
+                && !self.is_delegated(signature.signer_id, &key)
+            {
+                return Err(anyhow!(
+                    "Transaction {} is signed with key {}, which does not sign for participant {}",
+                    tx_id,
+                    key,
+                    signature.signer_id
+                ));
+            }
+        }
+
+        match &transaction.payload {
+            Some(TransactionPayload::KeyRotation(rotation)) => {
+                let participant_id = rotation.participant_id;
+                if !self.is_current_key(participant_id, &rotation.previous_key) {
+                    return Err(anyhow!(
+                        "Key rotation {} does not retire the current key of participant {}",
+                        tx_id,
+                        participant_id
+                    ));
+                }
+                for key in [&rotation.previous_key, &rotation.new_key] {
+                    if !signed_with(participant_id, key) {
+                        return Err(anyhow!(
+                            "Key rotation {} is not signed by key {}",
+                            tx_id,
+                            key
+                        ));
+                    }
+                }
+            }
+            Some(TransactionPayload::KeyDelegation(delegation)) => {
+                let participant_id = delegation.participant_id;
+                let by_participant = transaction.signatures.iter().any(|signature| {
+                    let key = hex::encode(signature.public_key.as_bytes());
+                    signature.signer_id == participant_id
+                        && key != delegation.public_key
+                        && self.is_current_key(participant_id, &key)
+                });
+                if !by_participant {
+                    return Err(anyhow!(
+                        "Key delegation {} is not signed by participant {}",
+                        tx_id,
+                        participant_id
+                    ));
+                }
+                if !delegation.revoked && !signed_with(participant_id, &delegation.public_key) {
+                    return Err(anyhow!(
+                        "Key delegation {} is not signed by delegated key {}",
+                        tx_id,
+                        delegation.public_key
+                    ));
+                }
+            }
+            Some(TransactionPayload::Credential(anchor)) => {
+                let by_issuer = transaction
+                    .signatures
+                    .iter()
+                    .any(|signature| signature.signer_id == anchor.issuer_id);
+                if !by_issuer {
+                    return Err(anyhow!(
+                        "Credential record {} is not signed by its issuer",
+                        tx_id
+                    ));
+                }
+                if anchor.status_list_index >= STATUS_LIST_LENGTH as u64 {
+                    return Err(anyhow!(
+                        "Credential record {} is outside the revocation list",
+                        tx_id
+                    ));
+                }
+            }
+            _ => {}
+        }
+
+        let signers: HashSet<Uuid> = transaction
+            .signatures
+            .iter()
+            .map(|signature| signature.signer_id)
+            .collect();
+        for input in &transaction.inputs {
+            let output_id = input.output_id();
+            let output = self
+                .outputs
+                .get(&output_id)
+                .map(|record| &record.output)
+                .or_else(|| block_outputs.get(output_id.as_str()).copied())
+                .ok_or_else(|| {
+                    anyhow!("Transaction {} spends unknown output {}", tx_id, output_id)
+                })?;
+            if !signers.contains(&output.owner) {
+                return Err(anyhow!(
+                    "Transaction {} spends output {} of participant {} without its signature",
+                    tx_id,
+                    output_id,
+                    output.owner
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that a transaction continues its signer's nonce sequence and spends no
+    /// output spent on the chain or by an earlier transaction of the block
+    fn check_sequence(
+        &self,
+        transaction: &Transaction,
+        spent: &HashMap<String, String>,
+    ) -> Result<()> {
+        if let Some(signer_id) = transaction.signer_id() {
+            let next_nonce = self.next_nonce(signer_id);
+            if transaction.nonce != next_nonce {
+                return Err(anyhow!(
+                    "Transaction {} has nonce {} but the next nonce of participant {} is {}",
+                    transaction.id,
+                    transaction.nonce,
+                    signer_id,
+                    next_nonce
+                ));
+            }
+        }
+
+        let mut inputs = HashSet::new();
+        for input in &transaction.inputs {
+            let output_id = input.output_id();
+            let spender = self
+                .spent_by(&output_id)
+                .or_else(|| spent.get(&output_id).map(String::as_str))
+                .or_else(|| (!inputs.insert(output_id.clone())).then_some(transaction.id.as_str()));
+            if let Some(spender) = spender {
+                return Err(anyhow!(
+                    "Transaction {} spends output {} already spent by transaction {}",
+                    transaction.id,
+                    output_id,
+                    spender
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Record a delegation, replacing an earlier one of the same key, or revoke a key
+    fn apply_delegation(
+        &mut self,
+        delegation: &KeyDelegation,
+        tx_id: &str,
+        timestamp: DateTime<Utc>,
+    ) {
+        let keys = self
+            .delegations
+            .entry(delegation.participant_id)
+            .or_default();
+        if delegation.revoked {
+            if let Some(key) = keys
+                .iter_mut()
+                .find(|key| key.public_key == delegation.public_key)
+            {
+                key.revoked_at.get_or_insert(timestamp);
+            }
+            return;
+        }
+        keys.retain(|key| key.public_key != delegation.public_key);
+        keys.push(DelegatedKey {
+            public_key: delegation.public_key.clone(),
+            derivation_path: delegation.derivation_path.clone(),
+            label: delegation.label.clone(),
+            delegated_at: timestamp,
+            revoked_at: None,
+            transaction_id: tx_id.to_string(),
+        });
+    }
+
+    /// Retire the participant's previous key in favour of the new one
+    fn apply_rotation(&mut self, rotation: &KeyRotation, tx_id: &str, timestamp: DateTime<Utc>) {
+        self.participant_keys
+            .insert(rotation.participant_id, rotation.new_key.clone());
+        self.retired_keys
+            .entry(rotation.participant_id)
+            .or_default()
+            .push(RetiredKey {
+                public_key: rotation.previous_key.clone(),
+                replaced_by: rotation.new_key.clone(),
+                retired_at: timestamp,
+                transaction_id: Some(tx_id.to_string()),
+            });
+    }
+
+    /// Anchor a credential, or set its entry of the issuer's revocation list
+    fn apply_credential(
+        &mut self,
+        anchor: &CredentialAnchor,
+        tx_id: &str,
+        timestamp: DateTime<Utc>,
+    ) -> Result<()> {
+        if anchor.revoked {
+            if let Some(anchored) =
+                self.anchored_credential(anchor.issuer_id, &anchor.credential_id)
+            {
+                let index = anchored.status_list_index;
+                self.revocation_lists
+                    .entry(anchor.issuer_id)
+                    .or_default()
+                    .set(index)?;
+            }
+            return Ok(());
+        }
+        let credentials = self.credentials.entry(anchor.issuer_id).or_default();
+        credentials.retain(|existing| existing.credential_id != anchor.credential_id);
+        credentials.push(AnchoredCredential {
+            credential_id: anchor.credential_id.clone(),
+            subject_id: anchor.subject_id,
+            digest: anchor.digest.clone(),
+            status_list_index: anchor.status_list_index,
+            anchored_at: timestamp,
+            transaction_id: tx_id.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Check the transactions recorded in block data before it is added: each must be
+    /// allowed into the ledger, continue its signer's nonce sequence and spend only
+    /// outputs not spent before
+    pub fn validate_block_data(&self, data: &str) -> Result<()> {
+        let records = BlockRecords::parse(data)?;
+        let mut ledger = self.clone();
+        ledger.apply_records(&records, self.height, true)
+    }
+
+    /// Nonce the next transaction of `signer_id` included in a block must carry
+    pub fn next_nonce(&self, signer_id: Uuid) -> u64 {
+        self.nonces.get(&signer_id).copied().unwrap_or(0)
+    }
+
+    /// Next nonce of every signer with transactions on the chain
+    pub fn nonces(&self) -> impl Iterator<Item = (Uuid, u64)> + '_ {
+        self.nonces
+            .iter()
+            .map(|(signer_id, nonce)| (*signer_id, *nonce))
+    }
+
+    /// Keys delegated by `participant_id`, revoked ones included
+    pub fn delegated_keys(&self, participant_id: Uuid) -> &[DelegatedKey] {
+        self.delegations
+            .get(&participant_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Hex-encoded key `participant_id` signs with, once it signed a transaction on the
+    /// chain
+    pub fn participant_key(&self, participant_id: Uuid) -> Option<&str> {
+        self.participant_keys
+            .get(&participant_id)
+            .map(String::as_str)
+    }
+
+    /// Whether the hex-encoded `public_key` is the key `participant_id` signs with, or
+    /// the participant has not signed on the chain yet
+    fn is_current_key(&self, participant_id: Uuid, public_key: &str) -> bool {
+        self.participant_key(participant_id)
+            .is_none_or(|key| key == public_key)
+    }
+
+    /// Whether the hex-encoded `public_key` is delegated by `participant_id` and not
+    /// revoked
+    pub fn is_delegated(&self, participant_id: Uuid, public_key: &str) -> bool {
+        self.delegated_keys(participant_id)
+            .iter()
+            .any(|key| key.public_key == public_key && key.revoked_at.is_none())
+    }
+
+    /// Keys `participant_id` rotated out, oldest first
+    pub fn retired_keys(&self, participant_id: Uuid) -> &[RetiredKey] {
+        self.retired_keys
+            .get(&participant_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Credentials anchored by `issuer_id`, revoked ones included
+    pub fn anchored_credentials(&self, issuer_id: Uuid) -> &[AnchoredCredential] {
+        self.credentials
+            .get(&issuer_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Issuance record of a credential, if it was anchored
+    pub fn anchored_credential(
+        &self,
+        issuer_id: Uuid,
+        credential_id: &str,
+    ) -> Option<&AnchoredCredential> {
+        self.anchored_credentials(issuer_id)
+            .iter()
+            .find(|anchored| anchored.credential_id == credential_id)
+    }
+
+    /// Revocation list of `issuer_id`
+    pub fn revocation_list(&self, issuer_id: Uuid) -> StatusList {
+        self.revocation_lists
+            .get(&issuer_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Whether entry `index` of the issuer's revocation list is set
+    pub fn is_revoked(&self, issuer_id: Uuid, index: u64) -> bool {
+        self.revocation_lists
+            .get(&issuer_id)
+            .is_some_and(|list| list.is_set(index))
+    }
+
+    /// Number of blocks the ledger covers
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    /// Block and position of an included transaction
+    pub fn transaction_location(&self, tx_id: &str) -> Option<TransactionLocation> {
+        self.transactions.get(tx_id).copied()
+    }
+
+    /// Any output created on-chain, spent or not
+    pub fn output(&self, output_id: &str) -> Option<&OutputRecord> {
+        self.outputs.get(output_id)
+    }
+
+    /// An output that exists and has not been spent
+    pub fn utxo(&self, output_id: &str) -> Option<&TransactionOutput> {
+        self.outputs
+            .get(output_id)
+            .filter(|record| record.spent_by.is_none())
+            .map(|record| &record.output)
+    }
+
+    /// Unspent outputs, by ID
+    pub fn utxos(&self) -> impl Iterator<Item = &TransactionOutput> {
+        self.outputs
+            .values()
+            .filter(|record| record.spent_by.is_none())
+            .map(|record| &record.output)
+    }
+
+    /// Transaction that spent an output, if it was spent
+    pub fn spent_by(&self, output_id: &str) -> Option<&str> {
+        self.outputs.get(output_id)?.spent_by.as_deref()
+    }
+
+    /// Who owns `batch_id` now, and through which transfers, following the batch's
+    /// outputs (those whose `batch_id` metadata names it) from the one that produced it
+    pub fn batch_ownership(&self, batch_id: &str) -> Option<BatchOwnership> {
+        let batch_outputs: Vec<&OutputRecord> = self
+            .outputs
+            .values()
+            .filter(|record| {
+                record.output.metadata.get("batch_id").map(String::as_str) == Some(batch_id)
+            })
+            .collect();
+        let mut record = *batch_outputs
+            .iter()
+            .filter(|record| record.tx_type != TransactionType::Transfer)
+            .min_by_key(|record| (record.block_index, &record.output.id))?;
+
+        let mut history = vec![record.clone()];
+        while let Some(spender) = &record.spent_by {
+            let Some(next) = batch_outputs.iter().find(|output| {
+                output.created_by == *spender && output.tx_type == TransactionType::Transfer
+            }) else {
+                break;
+            };
+            record = next;
+            history.push(record.clone());
+        }
+
+        Some(BatchOwnership {
+            batch_id: batch_id.to_string(),
+            current_owner: record.spent_by.is_none().then_some(record.output.owner),
+            consumed_by: record.spent_by.clone(),
+            history,
+        })
+    }
+}
+
+/// Readable transactions recorded in block data, in block order
+pub fn block_transactions(data: &str) -> Result<Vec<Transaction>> {
+    Ok(BlockRecords::parse(data)?
+        .transactions
+        .into_iter()
+        .filter_map(|record| record.transaction.ok())
+        .collect())
+}
+
+/// The signed copy of a transaction recorded in a block
+struct TransactionRecord {
+    tx_id: String,
+    position: usize,
+    /// The transaction, unless its copy cannot be read
+    transaction: Result<Transaction>,
+}
+
+/// Transaction records of one block, in block order
+#[derive(Default)]
+struct BlockRecords {
+    transactions: Vec<TransactionRecord>,
+}
+
+impl BlockRecords {
+    /// Read the records from a block's Turtle data; blocks whose data is not RDF, such
+    /// as the genesis block, carry no transactions
+    fn parse(data: &str) -> Result<Self> {
+        let store = Store::new()?;
+        if store
+            .load_from_reader(RdfFormat::Turtle, Cursor::new(data.as_bytes()))
+            .is_err()
+        {
+            return Ok(Self::default());
+        }
+
+        let mut records = Self::default();
+        for row in select(
+            &store,
+            "SELECT ?tx ?position ?signed WHERE {
+                ?tx tx:hasSignedTransaction ?signed .
+                OPTIONAL { ?tx tx:hasBlockPosition ?position }
+            }",
+        )? {
+            let tx_id = row.transaction()?;
+            let position = row
+                .value("position")
+                .ok()
+                .and_then(|p| p.parse().ok())
+                .unwrap_or(0);
+            let transaction = serde_json::from_str::<Transaction>(&row.value("signed")?)
+                .map_err(|e| anyhow!("Invalid signed transaction: {}", e))
+                .and_then(|transaction| {
+                    if transaction.id == tx_id {
+                        Ok(transaction)
+                    } else {
+                        Err(anyhow!("Signed transaction has ID {}", transaction.id))
+                    }
+                });
+            records.transactions.push(TransactionRecord {
+                tx_id,
+                position,
+                transaction,
+            });
+        }
+        records
+            .transactions
+            .sort_by(|a, b| (a.position, &a.tx_id).cmp(&(b.position, &b.tx_id)));
+        Ok(records)
+    }
+
+    /// Outputs created by the block's readable transactions, by ID
+    fn outputs(&self) -> HashMap<&str, &TransactionOutput> {
+        self.transactions
+            .iter()
+            .filter_map(|record| record.transaction.as_ref().ok())
+            .flat_map(|transaction| &transaction.outputs)
+            .map(|output| (output.id.as_str(), output))
+            .collect()
+    }
+}
+
+/// One solution of a ledger query
+struct Row(HashMap<String, Term>);
+
+impl Row {
+    /// Lexical value of a bound variable
+    fn value(&self, variable: &str) -> Result<String> {
+        match self.0.get(variable) {
+            Some(Term::Literal(literal)) => Ok(literal.value().to_string()),
+            Some(Term::NamedNode(node)) => Ok(node.as_str().to_string()),
+            _ => Err(anyhow!("Ledger record has no {}", variable)),
+        }
+    }
+
+    /// ID of the transaction in `?tx`
+    fn transaction(&self) -> Result<String> {
+        let iri = self.value("tx")?;
+        iri.strip_prefix(TX_NAMESPACE)
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("Not a transaction IRI: {}", iri))
+    }
+}
+
+fn select(store: &Store, query: &str) -> Result<Vec<Row>> {
+    let query = format!("PREFIX tx: <{}>\n{}", TX_NAMESPACE, query);
+    let QueryResults::Solutions(solutions) = store.query(query.as_str())? else {
+        return Err(anyhow!("Ledger query did not return solutions"));
+    };
+    solutions
+        .map(|solution| {
+            let solution = solution?;
+            Ok(Row(solution
+                .iter()
+                .map(|(variable, term)| (variable.as_str().to_string(), term.clone()))
+                .collect()))
+        })
+        .collect()
+}
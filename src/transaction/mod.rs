@@ -3,6 +3,7 @@
 //! This module contains transaction processing, validation, and blockchain integration.
 
 pub mod blockchain;
+pub mod ledger;
 pub mod transaction;
 
 // Re-exports for convenience
@@ -7,12 +7,13 @@
 //! - Multi-signature support
 
 use crate::error::TransactionError;
+use crate::governance::ProposalVote;
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
 use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
 /// Transaction types for supply chain operations
@@ -36,6 +37,22 @@ pub enum TransactionType {
     Governance,
 }
 
+impl TransactionType {
+    /// Participant operation (see `wallet::ParticipantPermissions::allows`) a signer needs
+    /// to submit this type of transaction; `None` when no participant permission covers it
+    pub fn required_operation(&self) -> Option<&'static str> {
+        match self {
+            TransactionType::Production => Some("produce"),
+            TransactionType::Processing => Some("process"),
+            TransactionType::Transport => Some("transport"),
+            TransactionType::Quality => Some("quality_test"),
+            TransactionType::Compliance => Some("audit"),
+            TransactionType::Transfer => Some("transfer"),
+            TransactionType::Environmental | TransactionType::Governance => None,
+        }
+    }
+}
+
 /// Transaction input referencing previous outputs
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionInput {
@@ -49,6 +66,13 @@ pub struct TransactionInput {
     pub public_key: Option<VerifyingKey>,
 }
 
+impl TransactionInput {
+    /// ID of the output this input spends, `{prev_tx_id}:{output_index}`
+    pub fn output_id(&self) -> String {
+        format!("{}:{}", self.prev_tx_id, self.output_index)
+    }
+}
+
 /// Transaction output creating new assets/states
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionOutput {
@@ -118,6 +142,43 @@ pub enum GovernanceAction {
     UpdateConfiguration { key: String, value: String },
 }
 
+/// Link from a participant's retired signing key to the key replacing it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KeyRotation {
+    pub participant_id: Uuid,
+    /// Hex-encoded public key being retired
+    pub previous_key: String,
+    /// Hex-encoded public key replacing it
+    pub new_key: String,
+}
+
+/// Delegation of a key derived from a participant's wallet key to sign for the
+/// participant, or the revocation of one
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KeyDelegation {
+    pub participant_id: Uuid,
+    /// Hex-encoded public key of the derived key
+    pub public_key: String,
+    /// Path of the key below the participant's wallet key
+    pub derivation_path: String,
+    /// Line or device the key belongs to
+    pub label: String,
+    pub revoked: bool,
+}
+
+/// Anchor of a verifiable credential issued to a participant, or its revocation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CredentialAnchor {
+    pub credential_id: String,
+    pub issuer_id: Uuid,
+    pub subject_id: Uuid,
+    /// Hex-encoded SHA-256 of the signed credential
+    pub digest: String,
+    /// Entry of the issuer's revocation status list
+    pub status_list_index: u64,
+    pub revoked: bool,
+}
+
 /// Transaction payload variants
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TransactionPayload {
@@ -125,6 +186,14 @@ pub enum TransactionPayload {
     RdfData(String),
     /// Governance action payload
     Governance(GovernanceAction),
+    /// Validator vote on a governance proposal, signed by the validator
+    GovernanceVote(ProposalVote),
+    /// Participant key rotation, signed by both the retired and the new key
+    KeyRotation(KeyRotation),
+    /// Delegation or revocation of a derived key, signed by the participant's key
+    KeyDelegation(KeyDelegation),
+    /// Issuance or revocation of a credential, signed by the issuer
+    Credential(CredentialAnchor),
 }
 
 impl Default for TransactionPayload {
@@ -154,9 +223,10 @@ pub struct Transaction {
     pub metadata: TransactionMetadata,
     /// Transaction fee (optional for private networks)
     pub fee: Option<f64>,
-    /// Nonce for replay protection
+    /// Position in the sequence of transactions of the first signer, starting at 0;
+    /// each nonce is accepted once and in order, so a signed transaction cannot be replayed
     pub nonce: u64,
-    /// Transaction payload (RDF data or governance actions)
+    /// Transaction payload (RDF data, governance actions or key records)
     pub payload: Option<TransactionPayload>,
 }
 
@@ -253,24 +323,12 @@ impl Transaction {
                 })?;
                 hasher.update(inputs_json.as_bytes());
 
-                let outputs_json = serde_json::to_string(&self.outputs).map_err(|e| {
-                    TransactionError::InvalidTransaction(format!(
-                        "Failed to serialize outputs: {}",
-                        e
-                    ))
-                })?;
-                hasher.update(outputs_json.as_bytes());
+                hasher.update(canonical_json(&self.outputs, "outputs")?.as_bytes());
 
                 hasher.update(canonical_hash.as_bytes()); // Use canonicalized RDF hash
                 hasher.update(self.timestamp.to_rfc3339().as_bytes());
 
-                let metadata_json = serde_json::to_string(&self.metadata).map_err(|e| {
-                    TransactionError::InvalidTransaction(format!(
-                        "Failed to serialize metadata: {}",
-                        e
-                    ))
-                })?;
-                hasher.update(metadata_json.as_bytes());
+                hasher.update(canonical_json(&self.metadata, "metadata")?.as_bytes());
 
                 hasher.update(self.nonce.to_le_bytes());
 
@@ -278,6 +336,8 @@ impl Transaction {
                     hasher.update(fee.to_le_bytes());
                 }
 
+                self.hash_structured_payload(&mut hasher)?;
+
                 return Ok(format!("{:x}", hasher.finalize()));
             }
         }
@@ -298,18 +358,12 @@ impl Transaction {
         })?;
         hasher.update(inputs_json.as_bytes());
 
-        let outputs_json = serde_json::to_string(&self.outputs).map_err(|e| {
-            TransactionError::InvalidTransaction(format!("Failed to serialize outputs: {}", e))
-        })?;
-        hasher.update(outputs_json.as_bytes());
+        hasher.update(canonical_json(&self.outputs, "outputs")?.as_bytes());
 
         hasher.update(self.rdf_data.as_bytes());
         hasher.update(self.timestamp.to_rfc3339().as_bytes());
 
-        let metadata_json = serde_json::to_string(&self.metadata).map_err(|e| {
-            TransactionError::InvalidTransaction(format!("Failed to serialize metadata: {}", e))
-        })?;
-        hasher.update(metadata_json.as_bytes());
+        hasher.update(canonical_json(&self.metadata, "metadata")?.as_bytes());
 
         hasher.update(self.nonce.to_le_bytes());
 
@@ -317,9 +371,36 @@ impl Transaction {
             hasher.update(fee.to_le_bytes());
         }
 
+        self.hash_structured_payload(&mut hasher)?;
+
         Ok(format!("{:x}", hasher.finalize()))
     }
 
+    /// Commit governance actions and key records to the signed hash so signatures
+    /// cover them. RDF payloads are already covered through `rdf_data`.
+    fn hash_structured_payload(&self, hasher: &mut Sha256) -> Result<(), TransactionError> {
+        let payload_json = match &self.payload {
+            Some(TransactionPayload::Governance(action)) => serde_json::to_string(action),
+            Some(TransactionPayload::GovernanceVote(vote)) => serde_json::to_string(vote),
+            Some(TransactionPayload::KeyRotation(rotation)) => serde_json::to_string(rotation),
+            Some(TransactionPayload::KeyDelegation(delegation)) => {
+                serde_json::to_string(delegation)
+            }
+            Some(TransactionPayload::Credential(anchor)) => serde_json::to_string(anchor),
+            _ => return Ok(()),
+        };
+        let payload_json = payload_json.map_err(|e| {
+            TransactionError::InvalidTransaction(format!("Failed to serialize payload: {}", e))
+        })?;
+        hasher.update(payload_json.as_bytes());
+        Ok(())
+    }
+
+    /// Participant whose nonce sequence the transaction belongs to, its first signer
+    pub fn signer_id(&self) -> Option<Uuid> {
+        self.signatures.first().map(|signature| signature.signer_id)
+    }
+
     /// Sign the transaction with a private key
     pub fn sign(
         &mut self,
@@ -446,12 +527,17 @@ impl Transaction {
                 }
             }
             TransactionType::Transfer => {
-                // Transfer transactions should have equal input and output values
-                let input_value: f64 = self.inputs.len() as f64; // Simplified
-                let output_value: f64 = self.outputs.iter().map(|o| o.value).sum();
-
-                if (input_value - output_value).abs() > 0.001 {
-                    return Err(anyhow!("Transfer transaction input/output mismatch"));
+                // Transfers move existing outputs; that their values add up is checked
+                // against the spent outputs when the transaction is submitted
+                if self.inputs.is_empty() || self.outputs.is_empty() {
+                    return Err(anyhow!("Transfer transaction must have inputs and outputs"));
+                }
+                if self
+                    .outputs
+                    .iter()
+                    .any(|output| !output.value.is_finite() || output.value < 0.0)
+                {
+                    return Err(anyhow!("Transfer transaction has an invalid output value"));
                 }
             }
             TransactionType::Quality => {
@@ -473,7 +559,49 @@ impl Transaction {
     }
 
     /// Convert transaction to RDF representation
+    ///
+    /// Besides the transaction's own RDF this records its signer and the outputs it
+    /// spends and creates, and a signed copy of the whole transaction as JSON, which is
+    /// what `transaction::ledger` verifies and derives its state from.
     pub fn to_rdf(&self) -> String {
+        let mut ledger_rdf = String::new();
+        if let Some(signer_id) = self.signer_id() {
+            ledger_rdf.push_str(&format!(
+                "tx:{} tx:hasSigner \"{}\" .\n",
+                self.id, signer_id
+            ));
+        }
+        ledger_rdf.push_str(&format!(
+            "tx:{} tx:hasSignedTransaction {} .\n",
+            self.id,
+            turtle_string(&serde_json::to_string(self).unwrap_or_default())
+        ));
+        for input in &self.inputs {
+            ledger_rdf.push_str(&format!(
+                "tx:{} tx:spends {} .\n",
+                self.id,
+                turtle_string(&input.output_id())
+            ));
+        }
+        for output in &self.outputs {
+            ledger_rdf.push_str(&format!(
+                r#"tx:{} tx:hasOutput [
+    tx:hasOutputId {} ;
+    tx:hasOwner "{}" ;
+    tx:hasAssetType {} ;
+    tx:hasValue "{}"^^xsd:double ;
+    tx:hasOutputMetadata {}
+] .
+"#,
+                self.id,
+                turtle_string(&output.id),
+                output.owner,
+                turtle_string(&output.asset_type),
+                output.value,
+                turtle_string(&serde_json::to_string(&output.metadata).unwrap_or_default())
+            ));
+        }
+
         format!(
             r#"
 @prefix tx: <http://provchain.org/tx#> .
@@ -486,6 +614,7 @@ tx:{} a tx:Transaction ;
     tx:hasNonce "{}"^^xsd:integer ;
     tx:hasSignatureCount "{}"^^xsd:integer .
 
+{}
 {}
 "#,
             self.id,
@@ -493,11 +622,39 @@ tx:{} a tx:Transaction ;
             self.timestamp.to_rfc3339(),
             self.nonce,
             self.signatures.len(),
+            ledger_rdf,
             self.rdf_data
         )
     }
 }
 
+/// JSON of `value` with object keys in sorted order, so hashes do not depend on the
+/// iteration order of maps
+fn canonical_json(value: &impl Serialize, what: &str) -> Result<String, TransactionError> {
+    serde_json::to_value(value)
+        .map(|value| value.to_string())
+        .map_err(|e| {
+            TransactionError::InvalidTransaction(format!("Failed to serialize {}: {}", what, e))
+        })
+}
+
+/// Quoted Turtle string literal of `value`
+fn turtle_string(value: &str) -> String {
+    let mut literal = String::with_capacity(value.len() + 2);
+    literal.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            _ => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
 /// Transaction pool for managing pending transactions
 #[derive(Debug)]
 pub struct TransactionPool {
@@ -507,6 +664,8 @@ pub struct TransactionPool {
     pub max_size: usize,
     /// Transaction priority queue
     pub priority_queue: Vec<String>,
+    /// Next nonce of each signer on the chain, before any pending transaction
+    pub confirmed_nonces: HashMap<Uuid, u64>,
 }
 
 impl TransactionPool {
@@ -516,17 +675,72 @@ impl TransactionPool {
             pending: HashMap::new(),
             max_size,
             priority_queue: Vec::new(),
+            confirmed_nonces: HashMap::new(),
+        }
+    }
+
+    /// Nonce the next transaction of `signer_id` must carry, following its pending ones
+    pub fn next_nonce(&self, signer_id: Uuid) -> u64 {
+        self.pending
+            .values()
+            .filter(|tx| tx.signer_id() == Some(signer_id))
+            .map(|tx| tx.nonce + 1)
+            .max()
+            .unwrap_or(0)
+            .max(self.confirmed_nonce(signer_id))
+    }
+
+    /// Take the signers' next nonces from the chain, dropping pending transactions whose
+    /// nonce was used by a transaction included meanwhile
+    pub fn confirm_nonces(&mut self, nonces: impl IntoIterator<Item = (Uuid, u64)>) {
+        self.confirmed_nonces.extend(nonces);
+        let stale: Vec<String> = self
+            .pending
+            .values()
+            .filter(|tx| {
+                tx.signer_id()
+                    .is_some_and(|signer_id| tx.nonce < self.confirmed_nonce(signer_id))
+            })
+            .map(|tx| tx.id.clone())
+            .collect();
+        for tx_id in stale {
+            self.remove_transaction(&tx_id);
         }
     }
 
+    fn confirmed_nonce(&self, signer_id: Uuid) -> u64 {
+        self.confirmed_nonces.get(&signer_id).copied().unwrap_or(0)
+    }
+
     /// Add a transaction to the pool
     pub fn add_transaction(&mut self, transaction: Transaction) -> Result<()> {
         // Validate transaction before adding
         transaction.validate()?;
 
+        // Each signer's nonces are used once and in order
+        if let Some(signer_id) = transaction.signer_id() {
+            let expected = self.next_nonce(signer_id);
+            if transaction.nonce < expected {
+                return Err(anyhow!(
+                    "Nonce {} of participant {} was already used; next nonce is {}",
+                    transaction.nonce,
+                    signer_id,
+                    expected
+                ));
+            }
+            if transaction.nonce > expected {
+                return Err(anyhow!(
+                    "Nonce {} of participant {} is out of order; next nonce is {}",
+                    transaction.nonce,
+                    signer_id,
+                    expected
+                ));
+            }
+        }
+
         // Check pool capacity
         if self.pending.len() >= self.max_size {
-            self.evict_lowest_priority()?;
+            self.evict_lowest_priority(&transaction)?;
         }
 
         let tx_id = transaction.id.clone();
@@ -549,21 +763,55 @@ impl TransactionPool {
         self.pending.remove(tx_id)
     }
 
-    /// Get transactions for block creation
+    /// Get transactions for block creation, by priority but keeping each signer's
+    /// transactions in nonce order
     pub fn get_transactions_for_block(&self, max_count: usize) -> Vec<Transaction> {
-        self.priority_queue
+        let mut queue: Vec<&Transaction> = self
+            .priority_queue
             .iter()
-            .take(max_count)
             .filter_map(|id| self.pending.get(id))
-            .cloned()
-            .collect()
+            .collect();
+        let mut next_nonces = self.confirmed_nonces.clone();
+        let mut transactions = Vec::new();
+
+        while transactions.len() < max_count {
+            let ready = queue.iter().position(|tx| match tx.signer_id() {
+                Some(signer_id) => tx.nonce == next_nonces.get(&signer_id).copied().unwrap_or(0),
+                None => true,
+            });
+            let Some(index) = ready else {
+                break;
+            };
+            let transaction = queue.remove(index);
+            if let Some(signer_id) = transaction.signer_id() {
+                next_nonces.insert(signer_id, transaction.nonce + 1);
+            }
+            transactions.push(transaction.clone());
+        }
+        transactions
     }
 
-    /// Evict lowest priority transaction
-    fn evict_lowest_priority(&mut self) -> Result<()> {
-        if let Some(tx_id) = self.priority_queue.last().cloned() {
-            self.remove_transaction(&tx_id);
-        }
+    /// Evict the lowest priority transaction that no pending transaction, nor `incoming`,
+    /// follows in nonce order
+    fn evict_lowest_priority(&mut self, incoming: &Transaction) -> Result<()> {
+        let followed: HashSet<(Uuid, u64)> = self
+            .pending
+            .values()
+            .chain([incoming])
+            .filter_map(|tx| Some((tx.signer_id()?, tx.nonce.checked_sub(1)?)))
+            .collect();
+        let evicted = self
+            .priority_queue
+            .iter()
+            .rev()
+            .find(|id| {
+                let tx = &self.pending[*id];
+                tx.signer_id()
+                    .is_none_or(|signer_id| !followed.contains(&(signer_id, tx.nonce)))
+            })
+            .cloned()
+            .ok_or_else(|| anyhow!("Transaction pool is full"))?;
+        self.remove_transaction(&evicted);
         Ok(())
     }
 
@@ -687,6 +935,66 @@ mod tests {
         assert!(pool.add_transaction(tx).is_ok());
         assert_eq!(pool.pending.len(), 1);
     }
+
+    #[test]
+    fn test_transaction_pool_enforces_nonce_order() {
+        let mut pool = TransactionPool::new(10);
+        let signing_key = SigningKey::from_bytes(&rand::random::<[u8; 32]>());
+        let signer_id = Uuid::new_v4();
+        let signed = |nonce: u64, timestamp: DateTime<Utc>| {
+            let mut tx = Transaction::new(
+                TransactionType::Production,
+                vec![],
+                vec![TransactionOutput {
+                    id: format!("output{}", nonce),
+                    owner: signer_id,
+                    asset_type: "milk_batch".to_string(),
+                    value: 100.0,
+                    metadata: HashMap::new(),
+                }],
+                "@prefix ex: <http://example.org/> . ex:test ex:value \"test\" .".to_string(),
+                TransactionMetadata {
+                    location: None,
+                    environmental_conditions: None,
+                    compliance_info: None,
+                    quality_data: None,
+                    custom_fields: HashMap::new(),
+                },
+                TransactionPayload::RdfData(String::new()),
+            );
+            tx.nonce = nonce;
+            tx.timestamp = timestamp;
+            tx.sign(&signing_key, signer_id).unwrap();
+            tx
+        };
+        let now = Utc::now();
+
+        assert_eq!(pool.next_nonce(signer_id), 0);
+        let error = pool.add_transaction(signed(1, now)).unwrap_err();
+        assert!(error.to_string().contains("out of order"));
+
+        pool.add_transaction(signed(0, now)).unwrap();
+        // Ahead of nonce 0 by priority, but still picked after it
+        pool.add_transaction(signed(1, now - chrono::Duration::seconds(60)))
+            .unwrap();
+        assert_eq!(pool.next_nonce(signer_id), 2);
+        let error = pool.add_transaction(signed(1, now)).unwrap_err();
+        assert!(error.to_string().contains("already used"));
+
+        let nonces: Vec<u64> = pool
+            .get_transactions_for_block(10)
+            .iter()
+            .map(|tx| tx.nonce)
+            .collect();
+        assert_eq!(nonces, vec![0, 1]);
+        assert_eq!(pool.get_transactions_for_block(1)[0].nonce, 0);
+
+        // Once nonce 0 is on-chain its pending copy is dropped and it cannot come back
+        pool.confirm_nonces([(signer_id, 1)]);
+        assert_eq!(pool.pending.len(), 1);
+        assert!(pool.add_transaction(signed(0, now)).is_err());
+        assert_eq!(pool.next_nonce(signer_id), 2);
+    }
 }
 
 #[cfg(test)]
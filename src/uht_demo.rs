@@ -12,6 +12,9 @@ use crate::transaction::blockchain::TransactionBlockchain;
 use crate::transaction::transaction::EnvironmentalConditions;
 use crate::wallet::{Certificate, CertificateStatus, Participant};
 
+/// Password the demos store participant signing keys under
+pub const DEMO_WALLET_PASSWORD: &str = "demo-wallet-password";
+
 /// UHT Manufacturing Demo
 pub struct UHTDemo {
     /// The transaction blockchain
@@ -64,9 +67,10 @@ impl UHTDemo {
             expires_at: Utc::now() + Duration::days(335),
             status: CertificateStatus::Active,
             metadata: HashMap::new(),
+            credential: None,
         });
 
-        let farmer_john_id = blockchain.register_participant(farmer_john)?;
+        let farmer_john_id = blockchain.register_participant(farmer_john, DEMO_WALLET_PASSWORD)?;
         println!(
             "✅ Registered Farmer John (Organic Dairy) - ID: {}",
             farmer_john_id
@@ -77,7 +81,7 @@ impl UHTDemo {
             "Mary's Premium Dairy".to_string(),
             "Wisconsin, USA".to_string(),
         );
-        let farmer_mary_id = blockchain.register_participant(farmer_mary)?;
+        let farmer_mary_id = blockchain.register_participant(farmer_mary, DEMO_WALLET_PASSWORD)?;
         println!(
             "✅ Registered Farmer Mary (Premium Dairy) - ID: {}",
             farmer_mary_id
@@ -98,9 +102,11 @@ impl UHTDemo {
             expires_at: Utc::now() + Duration::days(305),
             status: CertificateStatus::Active,
             metadata: HashMap::new(),
+            credential: None,
         });
 
-        let uht_processor_id = blockchain.register_participant(uht_processor)?;
+        let uht_processor_id =
+            blockchain.register_participant(uht_processor, DEMO_WALLET_PASSWORD)?;
         println!(
             "✅ Registered UHT Processor (Alpine Corp) - ID: {}",
             uht_processor_id
@@ -121,9 +127,10 @@ impl UHTDemo {
             expires_at: Utc::now() + Duration::days(275),
             status: CertificateStatus::Active,
             metadata: HashMap::new(),
+            credential: None,
         });
 
-        let quality_lab_id = blockchain.register_participant(quality_lab)?;
+        let quality_lab_id = blockchain.register_participant(quality_lab, DEMO_WALLET_PASSWORD)?;
         println!(
             "✅ Registered Quality Lab (Midwest Testing) - ID: {}",
             quality_lab_id
@@ -134,7 +141,8 @@ impl UHTDemo {
             "ColdChain Express Logistics".to_string(),
             "Illinois, USA".to_string(),
         );
-        let logistics_provider_id = blockchain.register_participant(logistics_provider)?;
+        let logistics_provider_id =
+            blockchain.register_participant(logistics_provider, DEMO_WALLET_PASSWORD)?;
         println!(
             "✅ Registered Logistics Provider (ColdChain Express) - ID: {}",
             logistics_provider_id
@@ -145,7 +153,7 @@ impl UHTDemo {
             "FreshMart Supermarket Chain".to_string(),
             "Nationwide, USA".to_string(),
         );
-        let retailer_id = blockchain.register_participant(retailer)?;
+        let retailer_id = blockchain.register_participant(retailer, DEMO_WALLET_PASSWORD)?;
         println!("✅ Registered Retailer (FreshMart) - ID: {}", retailer_id);
 
         Ok(DemoParticipants {
@@ -297,7 +305,7 @@ impl UHTDemo {
         };
 
         // Process both milk batches together
-        let tx5 = self.blockchain.create_processing_transaction(
+        let mut tx5 = self.blockchain.create_processing_transaction(
             self.participants.uht_processor,
             vec![
                 "ORGANIC-MILK-BATCH-001".to_string(),
@@ -307,6 +315,11 @@ impl UHTDemo {
             "UHT_PASTEURIZATION".to_string(),
             Some(uht_conditions),
         )?;
+        // Both farmers own the batches being processed
+        self.blockchain
+            .cosign_transaction(&mut tx5, self.participants.farmer_john)?;
+        self.blockchain
+            .cosign_transaction(&mut tx5, self.participants.farmer_mary)?;
 
         let tx5_id = self.blockchain.submit_transaction(tx5)?;
         println!(
@@ -418,7 +431,8 @@ impl UHTDemo {
 
         // Create blocks with all pending transactions
         // Use UHT processor as the validator for this block
-        self.blockchain.create_block(10, self.participants.uht_processor)?; // Process up to 10 transactions per block
+        self.blockchain
+            .create_block(10, self.participants.uht_processor)?; // Process up to 10 transactions per block
 
         // Save to disk
         self.blockchain.save_to_disk()?;
@@ -57,6 +57,32 @@ pub struct NetworkConfig {
 
     /// Ping interval for connection health checks (seconds)
     pub ping_interval: u64,
+
+    /// Ed25519 node key file used to authenticate peer connections.
+    /// Authorities default to their authority key file.
+    #[serde(default)]
+    pub node_key_file: Option<String>,
+
+    /// Hex-encoded node keys admitted even when they are not validators
+    #[serde(default)]
+    pub trusted_peer_keys: Vec<String>,
+
+    /// Whether peers that are neither validators nor trusted may connect
+    #[serde(default = "default_allow_observers")]
+    pub allow_observers: bool,
+
+    /// Blocks a node must be behind before it syncs from a state snapshot instead of
+    /// block by block (0 disables snapshot sync)
+    #[serde(default = "default_snapshot_sync_threshold")]
+    pub snapshot_sync_threshold: u64,
+}
+
+fn default_allow_observers() -> bool {
+    false
+}
+
+fn default_snapshot_sync_threshold() -> u64 {
+    100
 }
 
 /// Consensus-related configuration
@@ -76,6 +102,14 @@ pub struct ConsensusConfig {
 
     /// Maximum block size in bytes
     pub max_block_size: usize,
+
+    /// Blocks between finality checkpoints co-signed by the authorities
+    #[serde(default = "default_checkpoint_interval")]
+    pub checkpoint_interval: u64,
+}
+
+fn default_checkpoint_interval() -> u64 {
+    crate::core::finality::DEFAULT_CHECKPOINT_INTERVAL
 }
 
 /// Storage-related configuration
@@ -146,6 +180,10 @@ impl Default for NetworkConfig {
             max_peers: 50,
             connection_timeout: 30,
             ping_interval: 30,
+            node_key_file: None,
+            trusted_peer_keys: vec![],
+            allow_observers: default_allow_observers(),
+            snapshot_sync_threshold: default_snapshot_sync_threshold(),
         }
     }
 }
@@ -158,6 +196,7 @@ impl Default for ConsensusConfig {
             authority_keys: vec![],
             block_interval: 10,
             max_block_size: 1024 * 1024, // 1MB
+            checkpoint_interval: default_checkpoint_interval(),
         }
     }
 }
@@ -267,6 +306,10 @@ impl NodeConfig {
             anyhow::bail!("Block interval must be greater than 0");
         }
 
+        if self.consensus.checkpoint_interval == 0 {
+            anyhow::bail!("Checkpoint interval must be greater than 0");
+        }
+
         // Validate storage configuration
         if self.storage.data_dir.is_empty() {
             anyhow::bail!("Data directory cannot be empty");
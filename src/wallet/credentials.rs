@@ -0,0 +1,579 @@
+//! W3C Verifiable Credentials for participant certificates
+//!
+//! A certificate is issued as a Verifiable Credential (VC Data Model 2.0) whose
+//! issuer is the certifying participant, `urn:uuid:{participant_id}`, secured with a
+//! `DataIntegrityProof` using the `eddsa-jcs-2022` cryptosuite and the issuer's
+//! Ed25519 key as a `did:key` verification method.
+//!
+//! Issuance is anchored on-chain with the SHA-256 digest of the signed credential and
+//! its index in the issuer's revocation list, a Bitstring Status List. Revoking sets
+//! that index through another on-chain record, and the issuer can publish the list as
+//! a `BitstringStatusListCredential`. Verifying a credential therefore needs the
+//! on-chain state, provided through [`CredentialResolver`].
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use uuid::Uuid;
+
+use super::Certificate;
+
+/// Base context of VC Data Model 2.0
+pub const CREDENTIALS_CONTEXT: &str = "https://www.w3.org/ns/credentials/v2";
+
+/// Entries in a status list; the minimum the Bitstring Status List spec allows, so a
+/// set bit does not single out a credential
+pub const STATUS_LIST_LENGTH: usize = 131_072;
+
+const CRYPTOSUITE: &str = "eddsa-jcs-2022";
+const ED25519_MULTICODEC: [u8; 2] = [0xed, 0x01];
+
+/// A verifiable credential, with its proof once signed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiableCredential {
+    #[serde(rename = "@context")]
+    pub context: Vec<Value>,
+    pub id: String,
+    #[serde(rename = "type")]
+    pub types: Vec<String>,
+    /// `urn:uuid:` of the issuing participant
+    pub issuer: String,
+    pub valid_from: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub valid_until: Option<DateTime<Utc>>,
+    pub credential_subject: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential_status: Option<CredentialStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof: Option<DataIntegrityProof>,
+}
+
+/// Position of a credential in its issuer's revocation list
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialStatus {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub status_type: String,
+    pub status_purpose: String,
+    pub status_list_index: String,
+    pub status_list_credential: String,
+}
+
+/// Data Integrity proof of a credential
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DataIntegrityProof {
+    #[serde(rename = "type")]
+    pub proof_type: String,
+    pub cryptosuite: String,
+    pub created: DateTime<Utc>,
+    /// `did:key` of the signing key
+    pub verification_method: String,
+    pub proof_purpose: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_value: Option<String>,
+}
+
+/// On-chain record of an issued credential, derived from the chain by the ledger
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AnchoredCredential {
+    pub credential_id: String,
+    pub subject_id: Uuid,
+    /// Hex-encoded SHA-256 of the signed credential
+    pub digest: String,
+    pub status_list_index: u64,
+    pub anchored_at: DateTime<Utc>,
+    /// Transaction recording the issuance
+    pub transaction_id: String,
+}
+
+/// On-chain state credentials are verified against
+pub trait CredentialResolver {
+    /// Whether `public_key` signed for `issuer_id` at time `at`
+    fn is_issuer_key(&self, issuer_id: Uuid, public_key: &VerifyingKey, at: DateTime<Utc>) -> bool;
+
+    /// Issuance record of a credential, if it was anchored
+    fn anchored_credential(
+        &self,
+        issuer_id: Uuid,
+        credential_id: &str,
+    ) -> Option<&AnchoredCredential>;
+
+    /// Whether entry `index` of the issuer's revocation list is set
+    fn is_revoked(&self, issuer_id: Uuid, index: u64) -> bool;
+}
+
+impl VerifiableCredential {
+    /// Unsigned credential for `certificate`, issued by `issuer_id` to `subject_id`
+    /// with entry `status_list_index` of the issuer's revocation list
+    pub fn for_certificate(
+        certificate: &Certificate,
+        issuer_id: Uuid,
+        subject_id: Uuid,
+        status_list_index: u64,
+    ) -> Self {
+        let status_list = status_list_id(issuer_id);
+        Self {
+            context: vec![
+                json!(CREDENTIALS_CONTEXT),
+                json!({ "@vocab": "http://provchain.org/trace#" }),
+            ],
+            id: format!("urn:uuid:{}", Uuid::new_v4()),
+            types: vec![
+                "VerifiableCredential".to_string(),
+                "CertificateCredential".to_string(),
+            ],
+            issuer: format!("urn:uuid:{}", issuer_id),
+            valid_from: certificate.issued_at,
+            valid_until: Some(certificate.expires_at),
+            credential_subject: json!({
+                "id": format!("urn:uuid:{}", subject_id),
+                "certificateId": certificate.id,
+                "certificateType": certificate.cert_type,
+                "issuerName": certificate.issuer,
+                "metadata": certificate.metadata,
+            }),
+            credential_status: Some(CredentialStatus {
+                id: format!("{}#{}", status_list, status_list_index),
+                status_type: "BitstringStatusListEntry".to_string(),
+                status_purpose: "revocation".to_string(),
+                status_list_index: status_list_index.to_string(),
+                status_list_credential: status_list,
+            }),
+            proof: None,
+        }
+    }
+
+    /// Secure the credential with an `eddsa-jcs-2022` proof by `signing_key`
+    pub fn sign(mut self, signing_key: &SigningKey) -> Result<Self> {
+        self.proof = None;
+        let verification_method = did_key(&signing_key.verifying_key());
+        let mut proof = DataIntegrityProof {
+            proof_type: "DataIntegrityProof".to_string(),
+            cryptosuite: CRYPTOSUITE.to_string(),
+            created: Utc::now(),
+            verification_method,
+            proof_purpose: "assertionMethod".to_string(),
+            proof_value: None,
+        };
+        let signature = signing_key.sign(&self.signing_input(&proof)?);
+        proof.proof_value = Some(format!("z{}", base58_encode(&signature.to_bytes())));
+        self.proof = Some(proof);
+        Ok(self)
+    }
+
+    /// Hex-encoded SHA-256 of the canonical signed credential, as anchored on-chain
+    pub fn digest(&self) -> Result<String> {
+        Ok(hex::encode(Sha256::digest(canonical_json(self)?)))
+    }
+
+    /// Participant that issued the credential
+    pub fn issuer_id(&self) -> Result<Uuid> {
+        parse_urn_uuid(&self.issuer)
+    }
+
+    /// Participant the credential was issued to
+    pub fn subject_id(&self) -> Result<Uuid> {
+        parse_urn_uuid(self.credential_subject["id"].as_str().unwrap_or_default())
+    }
+
+    /// Index of the credential in its issuer's revocation list
+    pub fn status_list_index(&self) -> Result<u64> {
+        let status = self
+            .credential_status
+            .as_ref()
+            .ok_or_else(|| anyhow!("Credential has no status entry"))?;
+        Ok(status.status_list_index.parse()?)
+    }
+
+    /// Check the proof against the issuer's keys, the validity period at `now`, the
+    /// on-chain anchor and the issuer's revocation list
+    pub fn verify(&self, resolver: &impl CredentialResolver, now: DateTime<Utc>) -> Result<()> {
+        let proof = self
+            .proof
+            .as_ref()
+            .ok_or_else(|| anyhow!("Credential is not signed"))?;
+        if proof.proof_type != "DataIntegrityProof" || proof.cryptosuite != CRYPTOSUITE {
+            return Err(anyhow!("Unsupported proof {}", proof.cryptosuite));
+        }
+
+        let issuer_id = self.issuer_id()?;
+        let public_key = parse_did_key(&proof.verification_method)?;
+        if !resolver.is_issuer_key(issuer_id, &public_key, proof.created) {
+            return Err(anyhow!(
+                "Credential is not signed by a key of issuer {}",
+                issuer_id
+            ));
+        }
+        let proof_value = proof
+            .proof_value
+            .as_deref()
+            .and_then(|value| value.strip_prefix('z'))
+            .ok_or_else(|| anyhow!("Proof value is not base58btc multibase"))?;
+        let signature = Signature::from_slice(&base58_decode(proof_value)?)?;
+        let unsigned_proof = DataIntegrityProof {
+            proof_value: None,
+            ..proof.clone()
+        };
+        public_key
+            .verify(&self.signing_input(&unsigned_proof)?, &signature)
+            .map_err(|_| anyhow!("Invalid credential signature"))?;
+
+        if now < self.valid_from || self.valid_until.is_some_and(|until| now >= until) {
+            return Err(anyhow!("Credential is outside its validity period"));
+        }
+
+        let index = self.status_list_index()?;
+        let anchored = resolver
+            .anchored_credential(issuer_id, &self.id)
+            .ok_or_else(|| anyhow!("Credential {} is not anchored on-chain", self.id))?;
+        if anchored.digest != self.digest()? || anchored.status_list_index != index {
+            return Err(anyhow!("Credential does not match its on-chain anchor"));
+        }
+        if resolver.is_revoked(issuer_id, index) {
+            return Err(anyhow!("Credential {} has been revoked", self.id));
+        }
+        Ok(())
+    }
+
+    /// `eddsa-jcs-2022` hash data: the hashes of the canonical proof options and of the
+    /// canonical unsecured credential
+    fn signing_input(&self, proof: &DataIntegrityProof) -> Result<Vec<u8>> {
+        let unsecured = Self {
+            proof: None,
+            ..self.clone()
+        };
+        let mut proof_config = serde_json::to_value(proof)?;
+        proof_config["@context"] = serde_json::to_value(&self.context)?;
+
+        let mut input = Sha256::digest(canonical_json(&proof_config)?).to_vec();
+        input.extend_from_slice(&Sha256::digest(canonical_json(&unsecured)?));
+        Ok(input)
+    }
+}
+
+/// Revocation bits of the credentials a participant issued, serialized as the GZIP-
+/// compressed, base64url multibase `encodedList` of the Bitstring Status List spec
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusList {
+    bits: Vec<u8>,
+}
+
+impl Default for StatusList {
+    fn default() -> Self {
+        Self {
+            bits: vec![0; STATUS_LIST_LENGTH / 8],
+        }
+    }
+}
+
+impl StatusList {
+    /// Whether entry `index` is set; the first entry is the left-most bit
+    pub fn is_set(&self, index: u64) -> bool {
+        let index = index as usize;
+        self.bits
+            .get(index / 8)
+            .is_some_and(|byte| byte & (0x80 >> (index % 8)) != 0)
+    }
+
+    pub fn set(&mut self, index: u64) -> Result<()> {
+        let index = index as usize;
+        let byte = self
+            .bits
+            .get_mut(index / 8)
+            .ok_or_else(|| anyhow!("Status list index {} out of range", index))?;
+        *byte |= 0x80 >> (index % 8);
+        Ok(())
+    }
+
+    /// The `encodedList` form
+    pub fn encode(&self) -> Result<String> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&self.bits)?;
+        Ok(format!("u{}", URL_SAFE_NO_PAD.encode(encoder.finish()?)))
+    }
+
+    /// Parse the `encodedList` form
+    pub fn decode(encoded: &str) -> Result<Self> {
+        let compressed = URL_SAFE_NO_PAD.decode(
+            encoded
+                .strip_prefix('u')
+                .ok_or_else(|| anyhow!("Status list is not base64url multibase"))?,
+        )?;
+        let mut bits = Vec::new();
+        GzDecoder::new(compressed.as_slice()).read_to_end(&mut bits)?;
+        if bits.len() < STATUS_LIST_LENGTH / 8 {
+            return Err(anyhow!(
+                "Status list has fewer than {} entries",
+                STATUS_LIST_LENGTH
+            ));
+        }
+        Ok(Self { bits })
+    }
+
+    /// Unsigned `BitstringStatusListCredential` publishing this list for `issuer_id`
+    pub fn to_credential(&self, issuer_id: Uuid) -> Result<VerifiableCredential> {
+        let id = status_list_id(issuer_id);
+        Ok(VerifiableCredential {
+            context: vec![json!(CREDENTIALS_CONTEXT)],
+            id: id.clone(),
+            types: vec![
+                "VerifiableCredential".to_string(),
+                "BitstringStatusListCredential".to_string(),
+            ],
+            issuer: format!("urn:uuid:{}", issuer_id),
+            valid_from: Utc::now(),
+            valid_until: None,
+            credential_subject: json!({
+                "id": format!("{}#list", id),
+                "type": "BitstringStatusList",
+                "statusPurpose": "revocation",
+                "encodedList": self.encode()?,
+            }),
+            credential_status: None,
+            proof: None,
+        })
+    }
+}
+
+impl Serialize for StatusList {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let encoded = self.encode().map_err(serde::ser::Error::custom)?;
+        serializer.serialize_str(&encoded)
+    }
+}
+
+impl<'de> Deserialize<'de> for StatusList {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        Self::decode(&encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+/// ID of a participant's revocation list credential
+pub fn status_list_id(issuer_id: Uuid) -> String {
+    format!("urn:provchain:status-list:{}", issuer_id)
+}
+
+/// `did:key` verification method of an Ed25519 public key
+pub fn did_key(public_key: &VerifyingKey) -> String {
+    let mut bytes = ED25519_MULTICODEC.to_vec();
+    bytes.extend_from_slice(public_key.as_bytes());
+    let fingerprint = format!("z{}", base58_encode(&bytes));
+    format!("did:key:{}#{}", fingerprint, fingerprint)
+}
+
+fn parse_did_key(verification_method: &str) -> Result<VerifyingKey> {
+    let fingerprint = verification_method
+        .strip_prefix("did:key:z")
+        .and_then(|rest| rest.split('#').next())
+        .ok_or_else(|| anyhow!("Unsupported verification method {}", verification_method))?;
+    let bytes = base58_decode(fingerprint)?;
+    let key = bytes
+        .strip_prefix(&ED25519_MULTICODEC[..])
+        .ok_or_else(|| anyhow!("Verification method is not an Ed25519 key"))?;
+    Ok(VerifyingKey::from_bytes(key.try_into()?)?)
+}
+
+fn parse_urn_uuid(urn: &str) -> Result<Uuid> {
+    let id = urn
+        .strip_prefix("urn:uuid:")
+        .ok_or_else(|| anyhow!("Expected a urn:uuid: identifier, got '{}'", urn))?;
+    Ok(Uuid::parse_str(id)?)
+}
+
+/// JSON Canonicalization Scheme form of `value`. Object keys come out sorted because
+/// `serde_json::Map` is ordered, and credentials carry no floating point numbers.
+fn canonical_json<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec(&serde_json::to_value(value)?)?)
+}
+
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+fn base58_encode(bytes: &[u8]) -> String {
+    let mut digits: Vec<u8> = Vec::new();
+    for &byte in bytes {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+    std::iter::repeat_n(b'1', zeros)
+        .chain(
+            digits
+                .iter()
+                .rev()
+                .map(|digit| BASE58_ALPHABET[*digit as usize]),
+        )
+        .map(char::from)
+        .collect()
+}
+
+fn base58_decode(encoded: &str) -> Result<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in encoded.bytes() {
+        let mut carry = BASE58_ALPHABET
+            .iter()
+            .position(|digit| *digit == c)
+            .ok_or_else(|| anyhow!("Invalid base58 character '{}'", c as char))?
+            as u32;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let zeros = encoded.bytes().take_while(|c| *c == b'1').count();
+    Ok(std::iter::repeat_n(0, zeros)
+        .chain(bytes.into_iter().rev())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallet::CertificateStatus;
+    use std::collections::HashMap;
+
+    /// Resolver over one issuer with a fixed key and anchor
+    struct Chain {
+        issuer_id: Uuid,
+        issuer_key: VerifyingKey,
+        anchored: Vec<AnchoredCredential>,
+        revocations: StatusList,
+    }
+
+    impl CredentialResolver for Chain {
+        fn is_issuer_key(&self, issuer_id: Uuid, key: &VerifyingKey, _: DateTime<Utc>) -> bool {
+            issuer_id == self.issuer_id && key == &self.issuer_key
+        }
+
+        fn anchored_credential(&self, _: Uuid, id: &str) -> Option<&AnchoredCredential> {
+            self.anchored.iter().find(|a| a.credential_id == id)
+        }
+
+        fn is_revoked(&self, _: Uuid, index: u64) -> bool {
+            self.revocations.is_set(index)
+        }
+    }
+
+    fn certificate() -> Certificate {
+        Certificate {
+            id: "ORGANIC-001".to_string(),
+            cert_type: "ORGANIC".to_string(),
+            issuer: "USDA".to_string(),
+            issued_at: Utc::now() - chrono::Duration::days(1),
+            expires_at: Utc::now() + chrono::Duration::days(365),
+            status: CertificateStatus::Active,
+            metadata: HashMap::new(),
+            credential: None,
+        }
+    }
+
+    #[test]
+    fn test_base58_and_did_key() {
+        assert_eq!(base58_encode(b"Hello World!"), "2NEpo7TZRRrLZSi2U");
+        assert_eq!(base58_encode(&[0, 0, 1]), "112");
+        assert_eq!(base58_decode("112").unwrap(), vec![0, 0, 1]);
+
+        let key = SigningKey::from_bytes(&rand::random::<[u8; 32]>()).verifying_key();
+        let method = did_key(&key);
+        assert!(method.starts_with("did:key:z6Mk"));
+        assert_eq!(parse_did_key(&method).unwrap(), key);
+    }
+
+    #[test]
+    fn test_credential_verification() {
+        let issuer_key = SigningKey::from_bytes(&rand::random::<[u8; 32]>());
+        let issuer_id = Uuid::new_v4();
+        let subject_id = Uuid::new_v4();
+        let credential =
+            VerifiableCredential::for_certificate(&certificate(), issuer_id, subject_id, 42)
+                .sign(&issuer_key)
+                .unwrap();
+        assert_eq!(credential.subject_id().unwrap(), subject_id);
+
+        let mut chain = Chain {
+            issuer_id,
+            issuer_key: issuer_key.verifying_key(),
+            anchored: Vec::new(),
+            revocations: StatusList::default(),
+        };
+        let now = Utc::now();
+        assert!(credential.verify(&chain, now).is_err(), "not anchored yet");
+
+        chain.anchored.push(AnchoredCredential {
+            credential_id: credential.id.clone(),
+            subject_id,
+            digest: credential.digest().unwrap(),
+            status_list_index: 42,
+            anchored_at: now,
+            transaction_id: "tx".to_string(),
+        });
+        credential.verify(&chain, now).unwrap();
+
+        // Survives a JSON round trip
+        let json = serde_json::to_string(&credential).unwrap();
+        assert!(json.contains("\"@context\""));
+        assert!(json.contains("\"credentialSubject\""));
+        let parsed: VerifiableCredential = serde_json::from_str(&json).unwrap();
+        parsed.verify(&chain, now).unwrap();
+
+        let mut tampered = credential.clone();
+        tampered.credential_subject["certificateType"] = json!("FDA_APPROVED");
+        assert!(tampered.verify(&chain, now).is_err());
+
+        let forged = credential
+            .clone()
+            .sign(&SigningKey::from_bytes(&rand::random::<[u8; 32]>()))
+            .unwrap();
+        assert!(forged.verify(&chain, now).is_err());
+
+        assert!(credential
+            .verify(&chain, now + chrono::Duration::days(400))
+            .is_err());
+
+        chain.revocations.set(42).unwrap();
+        assert!(credential.verify(&chain, now).is_err());
+    }
+
+    #[test]
+    fn test_status_list_encoding() {
+        let mut list = StatusList::default();
+        list.set(0).unwrap();
+        list.set(9).unwrap();
+        assert!(list.set(STATUS_LIST_LENGTH as u64).is_err());
+        assert_eq!(list.bits[0], 0x80);
+        assert_eq!(list.bits[1], 0x40);
+
+        let encoded = list.encode().unwrap();
+        assert!(encoded.starts_with('u'));
+        let decoded = StatusList::decode(&encoded).unwrap();
+        assert!(decoded.is_set(0) && decoded.is_set(9) && !decoded.is_set(1));
+
+        let credential = list.to_credential(Uuid::new_v4()).unwrap();
+        assert_eq!(credential.credential_subject["encodedList"], encoded);
+
+        let json = serde_json::to_value(&list).unwrap();
+        assert_eq!(json.as_str(), Some(encoded.as_str()));
+    }
+}
@@ -0,0 +1,183 @@
+//! Hierarchical deterministic Ed25519 keys (SLIP-0010)
+//!
+//! A participant's wallet key is the seed of a key tree from which each of its lines
+//! and devices gets its own signing key, e.g. `m/0'/3'` for device 3 of line 0. Only
+//! hardened derivation exists for Ed25519, so every path index is hardened; `m/0/3`
+//! and `m/0'/3'` name the same key.
+
+use anyhow::{anyhow, Result};
+use ed25519_dalek::SigningKey;
+use ring::hmac;
+use std::fmt;
+use std::str::FromStr;
+
+/// First hardened child index
+pub const HARDENED: u32 = 0x8000_0000;
+
+const MASTER_HMAC_KEY: &[u8] = b"ed25519 seed";
+
+/// Path of a key below the master key, such as `m/0'/3'`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    /// Child indices from the master key down, all hardened
+    pub fn indices(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = anyhow::Error;
+
+    fn from_str(path: &str) -> Result<Self> {
+        let mut segments = path.trim().split('/');
+        if segments.next() != Some("m") {
+            return Err(anyhow!("Derivation path must start with 'm': {}", path));
+        }
+
+        segments
+            .map(|segment| {
+                let index = segment.trim_end_matches(['\'', 'h', 'H']);
+                index
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|index| *index < HARDENED)
+                    .map(|index| index | HARDENED)
+                    .ok_or_else(|| anyhow!("Invalid derivation path segment '{}'", segment))
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(DerivationPath)
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for index in &self.0 {
+            write!(f, "/{}'", index & !HARDENED)?;
+        }
+        Ok(())
+    }
+}
+
+/// A signing key with the chain code needed to derive its children
+#[derive(Clone)]
+pub struct ExtendedKey {
+    secret: [u8; 32],
+    chain_code: [u8; 32],
+}
+
+impl fmt::Debug for ExtendedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtendedKey")
+            .field(
+                "public_key",
+                &hex::encode(self.signing_key().verifying_key().as_bytes()),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+impl ExtendedKey {
+    /// Master key of the tree grown from `seed`
+    pub fn master(seed: &[u8]) -> Self {
+        Self::from_hmac(MASTER_HMAC_KEY, &[seed])
+    }
+
+    /// Hardened child `index`; indices below `HARDENED` are hardened implicitly
+    pub fn child(&self, index: u32) -> Self {
+        let index = index | HARDENED;
+        Self::from_hmac(
+            &self.chain_code,
+            &[&[0], &self.secret, &index.to_be_bytes()],
+        )
+    }
+
+    /// Descendant at `path`
+    pub fn derive(&self, path: &DerivationPath) -> Self {
+        path.indices()
+            .iter()
+            .fold(self.clone(), |key, index| key.child(*index))
+    }
+
+    pub fn signing_key(&self) -> SigningKey {
+        SigningKey::from_bytes(&self.secret)
+    }
+
+    pub fn chain_code(&self) -> &[u8; 32] {
+        &self.chain_code
+    }
+
+    fn from_hmac(key: &[u8], data: &[&[u8]]) -> Self {
+        let mut context = hmac::Context::with_key(&hmac::Key::new(hmac::HMAC_SHA512, key));
+        for part in data {
+            context.update(part);
+        }
+        let digest = context.sign();
+        let (secret, chain_code) = digest.as_ref().split_at(32);
+        Self {
+            secret: secret.try_into().expect("HMAC-SHA512 output is 64 bytes"),
+            chain_code: chain_code
+                .try_into()
+                .expect("HMAC-SHA512 output is 64 bytes"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slip10_ed25519_vector() {
+        // Test vector 1 of SLIP-0010 for ed25519
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master = ExtendedKey::master(&seed);
+        assert_eq!(
+            hex::encode(master.chain_code()),
+            "90046a93de5380a72b5e45010748567d5ea02bbf6522f979e05c0d8d8ca9fffb"
+        );
+        assert_eq!(
+            hex::encode(master.signing_key().to_bytes()),
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+        );
+
+        let child = master.derive(&"m/0'".parse().unwrap());
+        assert_eq!(
+            hex::encode(child.chain_code()),
+            "8b59aa11380b624e81507a27fedda59fea6d0b779a778918a2fd3590e16e9c69"
+        );
+        assert_eq!(
+            hex::encode(child.signing_key().to_bytes()),
+            "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3"
+        );
+        assert_eq!(
+            hex::encode(child.signing_key().verifying_key().as_bytes()),
+            "8c8a13df77a28f3445213a0f432fde644acaa215fc72dcdf300d5efaa85d350c"
+        );
+    }
+
+    #[test]
+    fn test_derivation_paths() {
+        let path: DerivationPath = "m/0'/3h/7".parse().unwrap();
+        assert_eq!(path.to_string(), "m/0'/3'/7'");
+        assert_eq!(path, "m/0/3/7".parse().unwrap());
+        assert!("m".parse::<DerivationPath>().unwrap().indices().is_empty());
+
+        for invalid in ["", "0/1", "m/x", "m/2147483648", "m//1"] {
+            assert!(invalid.parse::<DerivationPath>().is_err(), "{}", invalid);
+        }
+
+        let master = ExtendedKey::master(b"participant seed");
+        let device = master.derive(&path);
+        assert_eq!(
+            device.signing_key(),
+            master.child(0).child(3).child(7).signing_key()
+        );
+        assert_ne!(
+            device.signing_key(),
+            master.derive(&"m/0'/3'/8'".parse().unwrap()).signing_key()
+        );
+    }
+}
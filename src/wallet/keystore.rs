@@ -0,0 +1,294 @@
+//! Password-encrypted storage for participant signing keys
+//!
+//! A keystore file holds one Ed25519 signing key:
+//!
+//! | bytes | field                                             |
+//! |-------|---------------------------------------------------|
+//! | 4     | magic `PCKS`                                      |
+//! | 1     | format version (`KEYSTORE_VERSION`)               |
+//! | 1     | key derivation function (1 = PBKDF2-HMAC-SHA256)  |
+//! | 4     | KDF iterations, big endian                        |
+//! | 16    | KDF salt                                          |
+//! | 12    | ChaCha20-Poly1305 nonce                           |
+//! | 32    | Ed25519 public key                                |
+//! | 48    | encrypted private key and authentication tag      |
+//!
+//! The encryption key is derived from the password with the salt and iteration count
+//! stored in the header, and the whole header is authenticated along with the
+//! ciphertext, so a file whose header was edited fails to open just like a wrong
+//! password.
+//!
+//! Unencrypted keys are exchanged as the raw 32 private key bytes written by the
+//! `generate-key` command and read by `--node-key`.
+
+use anyhow::{anyhow, Result};
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::fs;
+use std::io::Write;
+use std::num::NonZeroU32;
+use std::path::Path;
+
+/// Keystore format version written by this build
+pub const KEYSTORE_VERSION: u8 = 1;
+
+/// PBKDF2 iterations for new keystores
+pub const DEFAULT_KDF_ITERATIONS: u32 = 600_000;
+
+const MAGIC: &[u8; 4] = b"PCKS";
+const KDF_PBKDF2_SHA256: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = 4 + 1 + 1 + 4 + SALT_LEN + NONCE_LEN + 32;
+const SECRET_LEN: usize = 32;
+const TAG_LEN: usize = 16;
+
+/// A signing key encrypted under a password
+#[derive(Debug, Clone)]
+pub struct Keystore {
+    iterations: NonZeroU32,
+    salt: [u8; SALT_LEN],
+    nonce: [u8; NONCE_LEN],
+    public_key: VerifyingKey,
+    ciphertext: Vec<u8>,
+}
+
+impl Keystore {
+    /// Encrypt `signing_key` under `password` with a fresh salt and nonce
+    pub fn encrypt(signing_key: &SigningKey, password: &str, iterations: u32) -> Result<Self> {
+        let iterations = NonZeroU32::new(iterations)
+            .ok_or_else(|| anyhow!("KDF iterations must be positive"))?;
+        let rng = SystemRandom::new();
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rng.fill(&mut salt)
+            .and_then(|_| rng.fill(&mut nonce))
+            .map_err(|_| anyhow!("Failed to generate keystore salt"))?;
+
+        let mut keystore = Self {
+            iterations,
+            salt,
+            nonce,
+            public_key: signing_key.verifying_key(),
+            ciphertext: signing_key.to_bytes().to_vec(),
+        };
+        let header = keystore.header();
+        keystore
+            .cipher(password)?
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(&header[..]),
+                &mut keystore.ciphertext,
+            )
+            .map_err(|_| anyhow!("Failed to encrypt signing key"))?;
+        Ok(keystore)
+    }
+
+    /// Decrypt the signing key, failing on a wrong password or a tampered file
+    pub fn decrypt(&self, password: &str) -> Result<SigningKey> {
+        let header = self.header();
+        let mut in_out = self.ciphertext.clone();
+        let secret = self
+            .cipher(password)?
+            .open_in_place(
+                Nonce::assume_unique_for_key(self.nonce),
+                Aad::from(&header[..]),
+                &mut in_out,
+            )
+            .map_err(|_| anyhow!("Invalid password or corrupted keystore"))?;
+        let secret: [u8; SECRET_LEN] = secret
+            .try_into()
+            .map_err(|_| anyhow!("Invalid keystore key length"))?;
+
+        let signing_key = SigningKey::from_bytes(&secret);
+        if signing_key.verifying_key() != self.public_key {
+            return Err(anyhow!("Keystore key does not match its public key"));
+        }
+        Ok(signing_key)
+    }
+
+    /// Public key of the stored signing key, readable without the password
+    pub fn public_key(&self) -> &VerifyingKey {
+        &self.public_key
+    }
+
+    /// Iteration count the encryption key is derived with
+    pub fn iterations(&self) -> u32 {
+        self.iterations.get()
+    }
+
+    /// Serialize to the keystore file format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header();
+        bytes.extend_from_slice(&self.ciphertext);
+        bytes
+    }
+
+    /// Parse the keystore file format
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+            return Err(anyhow!("Not a keystore file"));
+        }
+        if bytes[4] != KEYSTORE_VERSION {
+            return Err(anyhow!("Unsupported keystore version {}", bytes[4]));
+        }
+        if bytes[5] != KDF_PBKDF2_SHA256 {
+            return Err(anyhow!("Unsupported key derivation function {}", bytes[5]));
+        }
+        if bytes.len() != HEADER_LEN + SECRET_LEN + TAG_LEN {
+            return Err(anyhow!("Truncated keystore file"));
+        }
+
+        let iterations = u32::from_be_bytes(bytes[6..10].try_into()?);
+        let iterations =
+            NonZeroU32::new(iterations).ok_or_else(|| anyhow!("Keystore has no KDF iterations"))?;
+        let salt_end = 10 + SALT_LEN;
+        let nonce_end = salt_end + NONCE_LEN;
+        let public_key = VerifyingKey::from_bytes(bytes[nonce_end..HEADER_LEN].try_into()?)
+            .map_err(|e| anyhow!("Invalid keystore public key: {}", e))?;
+
+        Ok(Self {
+            iterations,
+            salt: bytes[10..salt_end].try_into()?,
+            nonce: bytes[salt_end..nonce_end].try_into()?,
+            public_key,
+            ciphertext: bytes[HEADER_LEN..].to_vec(),
+        })
+    }
+
+    /// Read a keystore file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Write the keystore file, readable by the owner only
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        write_private_file(path.as_ref(), &self.to_bytes())
+    }
+
+    fn header(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.push(KEYSTORE_VERSION);
+        header.push(KDF_PBKDF2_SHA256);
+        header.extend_from_slice(&self.iterations.get().to_be_bytes());
+        header.extend_from_slice(&self.salt);
+        header.extend_from_slice(&self.nonce);
+        header.extend_from_slice(self.public_key.as_bytes());
+        header
+    }
+
+    fn cipher(&self, password: &str) -> Result<LessSafeKey> {
+        let mut key = [0u8; 32];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            self.iterations,
+            &self.salt,
+            password.as_bytes(),
+            &mut key,
+        );
+        let key = UnboundKey::new(&CHACHA20_POLY1305, &key)
+            .map_err(|_| anyhow!("Failed to create keystore cipher"))?;
+        Ok(LessSafeKey::new(key))
+    }
+}
+
+/// Read a raw 32-byte private key file, as written by `generate-key`
+pub fn read_raw_key<P: AsRef<Path>>(path: P) -> Result<SigningKey> {
+    let path = path.as_ref();
+    let bytes = fs::read(path)?;
+    let secret: [u8; SECRET_LEN] = bytes.as_slice().try_into().map_err(|_| {
+        anyhow!(
+            "{} is not a raw Ed25519 private key ({} bytes, expected {})",
+            path.display(),
+            bytes.len(),
+            SECRET_LEN
+        )
+    })?;
+    Ok(SigningKey::from_bytes(&secret))
+}
+
+/// Write `signing_key` as a raw 32-byte private key file, readable by `--node-key`
+pub fn write_raw_key<P: AsRef<Path>>(path: P, signing_key: &SigningKey) -> Result<()> {
+    write_private_file(path.as_ref(), &signing_key.to_bytes())
+}
+
+fn write_private_file(path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(bytes)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_bytes(&rand::random::<[u8; 32]>())
+    }
+
+    #[test]
+    fn test_keystore_round_trip() {
+        let key = signing_key();
+        let keystore = Keystore::encrypt(&key, "correct horse", 10).unwrap();
+
+        let bytes = keystore.to_bytes();
+        assert_eq!(&bytes[..4], b"PCKS");
+        assert_eq!(bytes[4], KEYSTORE_VERSION);
+        assert!(!bytes
+            .windows(SECRET_LEN)
+            .any(|window| window == key.to_bytes()));
+
+        let parsed = Keystore::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.iterations(), 10);
+        assert_eq!(parsed.public_key(), &key.verifying_key());
+        assert_eq!(parsed.decrypt("correct horse").unwrap(), key);
+        assert!(parsed.decrypt("wrong horse").is_err());
+    }
+
+    #[test]
+    fn test_keystore_header_is_authenticated() {
+        let keystore = Keystore::encrypt(&signing_key(), "password", 10).unwrap();
+
+        // Lowering the iteration count must not yield a weaker but valid file
+        let mut bytes = keystore.to_bytes();
+        bytes[9] ^= 1;
+        let tampered = Keystore::from_bytes(&bytes).unwrap();
+        assert!(tampered.decrypt("password").is_err());
+
+        let mut bytes = keystore.to_bytes();
+        bytes[4] = KEYSTORE_VERSION + 1;
+        assert!(Keystore::from_bytes(&bytes).is_err());
+        assert!(Keystore::from_bytes(&bytes[..HEADER_LEN]).is_err());
+    }
+
+    #[test]
+    fn test_raw_key_files() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("node.key");
+        let key = signing_key();
+
+        write_raw_key(&path, &key).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), key.to_bytes());
+        assert_eq!(read_raw_key(&path).unwrap(), key);
+        assert_eq!(
+            crate::network::transport::load_or_generate_node_key(&Some(
+                path.to_string_lossy().to_string()
+            ))
+            .unwrap(),
+            key
+        );
+
+        fs::write(&path, b"not a key").unwrap();
+        assert!(read_raw_key(&path).is_err());
+    }
+}
@@ -0,0 +1,1234 @@
+//! Wallet system for ProvChainOrg participants
+//!
+//! This module implements:
+//! - Multi-participant wallet management
+//! - Secure key storage and management
+//! - Participant identity and role management
+//! - Transaction signing capabilities
+//!
+//! Each wallet is stored as `{participant_id}.wallet`, holding only public data, and
+//! `{participant_id}.keystore`, holding the signing key encrypted under the
+//! participant's password (see [`keystore`]). A loaded wallet stays locked, unable to
+//! sign, until it is unlocked with that password.
+//!
+//! A participant's lines and devices sign with keys derived from its wallet key (see
+//! [`hd`]) once the participant has delegated them on-chain. Certificates are W3C
+//! Verifiable Credentials anchored on-chain (see [`credentials`]).
+
+pub mod credentials;
+pub mod hd;
+pub mod keystore;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use self::credentials::{CredentialResolver, VerifiableCredential};
+use self::keystore::{Keystore, DEFAULT_KDF_ITERATIONS};
+
+/// Participant types in the supply chain
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ParticipantType {
+    /// Raw material producers (farmers, suppliers)
+    Producer,
+    /// Manufacturing facilities (UHT processors, packagers)
+    Manufacturer,
+    /// Logistics and transport providers
+    LogisticsProvider,
+    /// Quality control laboratories
+    QualityLab,
+    /// Regulatory authorities and auditors
+    Auditor,
+    /// Retail and distribution
+    Retailer,
+    /// System administrators
+    Administrator,
+}
+
+/// Participant permissions for different operations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParticipantPermissions {
+    /// Can create production transactions
+    pub can_produce: bool,
+    /// Can create processing transactions
+    pub can_process: bool,
+    /// Can create transport transactions
+    pub can_transport: bool,
+    /// Can create quality control transactions
+    pub can_quality_test: bool,
+    /// Can create compliance transactions
+    pub can_audit: bool,
+    /// Can transfer ownership
+    pub can_transfer: bool,
+    /// Can view all transactions (admin privilege)
+    pub can_view_all: bool,
+    /// Can manage other participants (admin privilege)
+    pub can_manage_participants: bool,
+}
+
+impl ParticipantPermissions {
+    /// No permissions; read-only participants such as consumers
+    pub fn none() -> Self {
+        Self {
+            can_produce: false,
+            can_process: false,
+            can_transport: false,
+            can_quality_test: false,
+            can_audit: false,
+            can_transfer: false,
+            can_view_all: false,
+            can_manage_participants: false,
+        }
+    }
+
+    /// Whether the permissions allow a named operation
+    pub fn allows(&self, operation: &str) -> bool {
+        match operation {
+            "produce" => self.can_produce,
+            "process" => self.can_process,
+            "transport" => self.can_transport,
+            "quality_test" => self.can_quality_test,
+            "audit" => self.can_audit,
+            "transfer" => self.can_transfer,
+            "view_all" => self.can_view_all,
+            "manage_participants" => self.can_manage_participants,
+            _ => false,
+        }
+    }
+
+    /// Get default permissions for a participant type
+    pub fn for_type(participant_type: &ParticipantType) -> Self {
+        match participant_type {
+            ParticipantType::Producer => Self {
+                can_produce: true,
+                can_process: false,
+                can_transport: false,
+                can_quality_test: false,
+                can_audit: false,
+                can_transfer: true,
+                can_view_all: false,
+                can_manage_participants: false,
+            },
+            ParticipantType::Manufacturer => Self {
+                can_produce: false,
+                can_process: true,
+                can_transport: false,
+                can_quality_test: false,
+                can_audit: false,
+                can_transfer: true,
+                can_view_all: false,
+                can_manage_participants: false,
+            },
+            ParticipantType::LogisticsProvider => Self {
+                can_produce: false,
+                can_process: false,
+                can_transport: true,
+                can_quality_test: false,
+                can_audit: false,
+                can_transfer: false,
+                can_view_all: false,
+                can_manage_participants: false,
+            },
+            ParticipantType::QualityLab => Self {
+                can_produce: false,
+                can_process: false,
+                can_transport: false,
+                can_quality_test: true,
+                can_audit: false,
+                can_transfer: false,
+                can_view_all: false,
+                can_manage_participants: false,
+            },
+            ParticipantType::Auditor => Self {
+                can_produce: false,
+                can_process: false,
+                can_transport: false,
+                can_quality_test: false,
+                can_audit: true,
+                can_transfer: false,
+                can_view_all: true,
+                can_manage_participants: false,
+            },
+            ParticipantType::Retailer => Self {
+                can_produce: false,
+                can_process: false,
+                can_transport: false,
+                can_quality_test: false,
+                can_audit: false,
+                can_transfer: true,
+                can_view_all: false,
+                can_manage_participants: false,
+            },
+            ParticipantType::Administrator => Self {
+                can_produce: true,
+                can_process: true,
+                can_transport: true,
+                can_quality_test: true,
+                can_audit: true,
+                can_transfer: true,
+                can_view_all: true,
+                can_manage_participants: true,
+            },
+        }
+    }
+}
+
+/// Certificate information for participants
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Certificate {
+    /// Certificate ID
+    pub id: String,
+    /// Certificate type (e.g., "ORGANIC", "FDA_APPROVED", "ISO_9001")
+    pub cert_type: String,
+    /// Issuing authority
+    pub issuer: String,
+    /// Issue date
+    pub issued_at: DateTime<Utc>,
+    /// Expiration date
+    pub expires_at: DateTime<Utc>,
+    /// Certificate status as last recorded locally; `Wallet::has_valid_certificate`
+    /// checks the credential instead
+    pub status: CertificateStatus,
+    /// Additional metadata
+    pub metadata: HashMap<String, String>,
+    /// Signed credential issued for this certificate
+    #[serde(default)]
+    pub credential: Option<VerifiableCredential>,
+}
+
+/// Certificate status
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum CertificateStatus {
+    Active,
+    Expired,
+    Revoked,
+    Suspended,
+}
+
+/// Participant information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Participant {
+    /// Unique participant identifier
+    pub id: Uuid,
+    /// Participant name/label
+    pub name: String,
+    /// Participant type
+    pub participant_type: ParticipantType,
+    /// Contact information
+    pub contact_info: ContactInfo,
+    /// Location information
+    pub location: Option<String>,
+    /// Permissions for this participant
+    pub permissions: ParticipantPermissions,
+    /// Certificates held by this participant
+    pub certificates: Vec<Certificate>,
+    /// Registration timestamp
+    pub registered_at: DateTime<Utc>,
+    /// Last activity timestamp
+    pub last_activity: Option<DateTime<Utc>>,
+    /// Reputation score (0.0 to 1.0)
+    pub reputation: f64,
+    /// Additional metadata
+    pub metadata: HashMap<String, String>,
+}
+
+/// Contact information for participants
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContactInfo {
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub address: Option<String>,
+    pub website: Option<String>,
+}
+
+/// Wallet containing cryptographic keys and participant information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Wallet {
+    /// Participant information
+    pub participant: Participant,
+    /// Signing key for transactions, present while the wallet is unlocked
+    #[serde(skip)]
+    pub signing_key: Option<SigningKey>,
+    /// Public key for verification
+    pub public_key: VerifyingKey,
+    /// Key derivation path (for HD wallets)
+    pub derivation_path: Option<String>,
+    /// Wallet creation timestamp
+    pub created_at: DateTime<Utc>,
+    /// Last backup timestamp
+    pub last_backup: Option<DateTime<Utc>>,
+    /// Keys this wallet signed with before, oldest first
+    #[serde(default)]
+    pub retired_keys: Vec<RetiredKey>,
+    /// Key rotation submitted but not yet included in a block. Its key is already
+    /// stored encrypted; the wallet keeps signing with its current key until then.
+    #[serde(default)]
+    pub pending_key: Option<PendingKey>,
+    /// Signing key of the pending rotation, present while the wallet is unlocked
+    #[serde(skip)]
+    pending_signing_key: Option<SigningKey>,
+}
+
+/// A signing key replaced through key rotation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetiredKey {
+    /// Hex-encoded public key of the retired key
+    pub public_key: String,
+    /// Hex-encoded public key that replaced it
+    pub replaced_by: String,
+    pub retired_at: DateTime<Utc>,
+    /// Transaction recording the rotation on-chain
+    pub transaction_id: Option<String>,
+}
+
+/// The replacement key of a rotation waiting to be included in a block
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PendingKey {
+    /// Hex-encoded public key of the replacement key
+    pub public_key: String,
+    pub staged_at: DateTime<Utc>,
+    /// Transaction recording the rotation on-chain
+    pub transaction_id: String,
+}
+
+impl Wallet {
+    /// Create a new wallet for a participant
+    pub fn new(participant: Participant) -> Self {
+        let signing_key = SigningKey::from_bytes(&rand::random::<[u8; 32]>());
+        let public_key = signing_key.verifying_key();
+
+        Self {
+            participant,
+            signing_key: Some(signing_key),
+            public_key,
+            derivation_path: None,
+            created_at: Utc::now(),
+            last_backup: None,
+            retired_keys: Vec::new(),
+            pending_key: None,
+            pending_signing_key: None,
+        }
+    }
+
+    /// Create a wallet from an existing signing key
+    pub fn from_signing_key(participant: Participant, signing_key: SigningKey) -> Self {
+        let public_key = signing_key.verifying_key();
+
+        Self {
+            participant,
+            signing_key: Some(signing_key),
+            public_key,
+            derivation_path: None,
+            created_at: Utc::now(),
+            last_backup: None,
+            retired_keys: Vec::new(),
+            pending_key: None,
+            pending_signing_key: None,
+        }
+    }
+
+    /// Get the participant ID
+    pub fn participant_id(&self) -> Uuid {
+        self.participant.id
+    }
+
+    /// Get the participant type
+    pub fn participant_type(&self) -> &ParticipantType {
+        &self.participant.participant_type
+    }
+
+    /// Check if the wallet has permission for an operation
+    pub fn has_permission(&self, operation: &str) -> bool {
+        self.participant.permissions.allows(operation)
+    }
+
+    /// Whether the signing key is unavailable until the wallet is unlocked
+    pub fn is_locked(&self) -> bool {
+        self.signing_key.is_none()
+    }
+
+    /// Drop the signing keys from memory
+    pub fn lock(&mut self) {
+        self.signing_key = None;
+        self.pending_signing_key = None;
+    }
+
+    /// The signing key of an unlocked wallet
+    pub fn unlocked_key(&self) -> Result<&SigningKey> {
+        self.signing_key.as_ref().ok_or_else(|| {
+            anyhow!(
+                "Wallet of participant {} is locked: no signing key available",
+                self.participant.id
+            )
+        })
+    }
+
+    /// Sign data with the wallet's private key
+    pub fn sign(&self, data: &[u8]) -> Result<Signature> {
+        Ok(self.unlocked_key()?.sign(data))
+    }
+
+    /// Wallet of the device key at `path` below this wallet's key, acting for the same
+    /// participant. The wallet must be unlocked.
+    pub fn derive_device_wallet(&self, path: &str) -> Result<Wallet> {
+        let path: hd::DerivationPath = path.parse()?;
+        let device_key = hd::ExtendedKey::master(self.unlocked_key()?.as_bytes())
+            .derive(&path)
+            .signing_key();
+
+        let mut wallet = Wallet::from_signing_key(self.participant.clone(), device_key);
+        wallet.derivation_path = Some(path.to_string());
+        Ok(wallet)
+    }
+
+    /// Verify a signature against this wallet's public key
+    pub fn verify(&self, data: &[u8], signature: &Signature) -> bool {
+        use ed25519_dalek::Verifier;
+        self.public_key.verify(data, signature).is_ok()
+    }
+
+    /// Update last activity timestamp
+    pub fn update_activity(&mut self) {
+        self.participant.last_activity = Some(Utc::now());
+    }
+
+    /// Add a certificate to the participant
+    pub fn add_certificate(&mut self, certificate: Certificate) {
+        self.participant.certificates.push(certificate);
+    }
+
+    /// Check if participant holds a credential for a certificate of a specific type
+    /// whose signature, validity period, on-chain anchor and revocation status verify
+    /// against `resolver`
+    pub fn has_valid_certificate(
+        &self,
+        cert_type: &str,
+        resolver: &impl CredentialResolver,
+    ) -> bool {
+        let now = Utc::now();
+        self.participant
+            .certificates
+            .iter()
+            .filter_map(|cert| cert.credential.as_ref())
+            .any(|credential| {
+                credential.credential_subject["certificateType"] == cert_type
+                    && credential.subject_id().ok() == Some(self.participant.id)
+                    && credential.verify(resolver, now).is_ok()
+            })
+    }
+
+    /// Get active certificates
+    pub fn get_active_certificates(&self) -> Vec<&Certificate> {
+        let now = Utc::now();
+        self.participant
+            .certificates
+            .iter()
+            .filter(|cert| cert.status == CertificateStatus::Active && cert.expires_at > now)
+            .collect()
+    }
+}
+
+/// Wallet manager for handling multiple participant wallets
+#[derive(Debug)]
+pub struct WalletManager {
+    /// Map of participant ID to wallet
+    wallets: HashMap<Uuid, Wallet>,
+    /// Storage directory for wallet files
+    storage_dir: PathBuf,
+    /// PBKDF2 iterations for keystores written by this manager
+    kdf_iterations: u32,
+}
+
+impl WalletManager {
+    /// Create a wallet manager, loading the wallets already stored in `storage_dir`.
+    /// Loaded wallets are locked.
+    pub fn new<P: AsRef<Path>>(storage_dir: P) -> Result<Self> {
+        let storage_dir = storage_dir.as_ref().to_path_buf();
+
+        // Create storage directory if it doesn't exist
+        if !storage_dir.exists() {
+            fs::create_dir_all(&storage_dir)?;
+        }
+
+        let mut manager = Self {
+            wallets: HashMap::new(),
+            storage_dir,
+            kdf_iterations: DEFAULT_KDF_ITERATIONS,
+        };
+
+        for entry in fs::read_dir(&manager.storage_dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("wallet") {
+                continue;
+            }
+            let participant_id = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| Uuid::parse_str(stem).ok());
+            if let Some(participant_id) = participant_id {
+                manager.load_wallet(participant_id)?;
+            }
+        }
+
+        Ok(manager)
+    }
+
+    /// Use `iterations` rounds of PBKDF2 for keystores written from now on
+    pub fn with_kdf_iterations(mut self, iterations: u32) -> Self {
+        self.kdf_iterations = iterations;
+        self
+    }
+
+    /// Create a new participant wallet. It stays unlocked but its signing key is not
+    /// stored until a password is set with `set_password`.
+    pub fn create_wallet(&mut self, participant: Participant) -> Result<Uuid> {
+        let participant_id = participant.id;
+        let wallet = Wallet::new(participant);
+
+        // Save wallet to disk
+        self.save_wallet(&wallet)?;
+
+        // Add to memory
+        self.wallets.insert(participant_id, wallet);
+
+        Ok(participant_id)
+    }
+
+    /// Create a new participant wallet whose signing key is stored encrypted under
+    /// `password`
+    pub fn create_wallet_with_password(
+        &mut self,
+        participant: Participant,
+        password: &str,
+    ) -> Result<Uuid> {
+        let participant_id = self.create_wallet(participant)?;
+        self.set_password(participant_id, password)?;
+        Ok(participant_id)
+    }
+
+    /// Create a wallet for `participant` from a raw private key file written by
+    /// `generate-key`, storing the key encrypted under `password`
+    pub fn import_key_file<P: AsRef<Path>>(
+        &mut self,
+        participant: Participant,
+        key_file: P,
+        password: &str,
+    ) -> Result<Uuid> {
+        let signing_key = keystore::read_raw_key(key_file)?;
+        let participant_id = participant.id;
+        let wallet = Wallet::from_signing_key(participant, signing_key);
+
+        self.save_wallet(&wallet)?;
+        self.wallets.insert(participant_id, wallet);
+        self.set_password(participant_id, password)?;
+        Ok(participant_id)
+    }
+
+    /// Write the signing key of an unlocked wallet as a raw private key file, in the
+    /// format written by `generate-key` and read by `--node-key`
+    pub fn export_key_file<P: AsRef<Path>>(&self, participant_id: Uuid, key_file: P) -> Result<()> {
+        let wallet = self.wallet(participant_id)?;
+        keystore::write_raw_key(key_file, wallet.unlocked_key()?)
+    }
+
+    /// Load a wallet from storage, locked
+    pub fn load_wallet(&mut self, participant_id: Uuid) -> Result<()> {
+        let wallet_path = self.get_wallet_path(participant_id);
+
+        if !wallet_path.exists() {
+            return Err(anyhow!(
+                "Wallet file not found for participant {}",
+                participant_id
+            ));
+        }
+
+        let wallet: Wallet = serde_json::from_slice(&fs::read(&wallet_path)?)?;
+
+        self.wallets.insert(participant_id, wallet);
+        Ok(())
+    }
+
+    /// Save a wallet to storage. The signing key is not part of the wallet file.
+    pub fn save_wallet(&self, wallet: &Wallet) -> Result<()> {
+        let wallet_path = self.get_wallet_path(wallet.participant_id());
+        fs::write(wallet_path, serde_json::to_vec_pretty(wallet)?)?;
+        Ok(())
+    }
+
+    /// Encrypt the signing key of an unlocked wallet, and the key of its pending
+    /// rotation, under `password`, replacing any previous keystore
+    pub fn set_password(&mut self, participant_id: Uuid, password: &str) -> Result<()> {
+        let wallet = self.wallet(participant_id)?;
+        Keystore::encrypt(wallet.unlocked_key()?, password, self.kdf_iterations)?
+            .save(self.get_keystore_path(participant_id))?;
+        if let Some(pending_signing_key) = &wallet.pending_signing_key {
+            Keystore::encrypt(pending_signing_key, password, self.kdf_iterations)?
+                .save(self.get_pending_keystore_path(participant_id))?;
+        }
+        Ok(())
+    }
+
+    /// Decrypt the stored signing key so the wallet can sign again
+    pub fn unlock_wallet(&mut self, participant_id: Uuid, password: &str) -> Result<()> {
+        if !self.wallets.contains_key(&participant_id) {
+            self.load_wallet(participant_id)?;
+        }
+        let signing_key = self.open_keystore(participant_id, password)?;
+
+        let pending_signing_key = match self.wallet(participant_id)?.pending_key {
+            Some(_) => Some(
+                Keystore::load(self.get_pending_keystore_path(participant_id))?
+                    .decrypt(password)?,
+            ),
+            None => None,
+        };
+
+        let wallet = self.wallet_mut(participant_id)?;
+        if signing_key.verifying_key() != wallet.public_key {
+            return Err(anyhow!(
+                "Keystore of participant {} holds a different key than its wallet",
+                participant_id
+            ));
+        }
+        wallet.signing_key = Some(signing_key);
+        wallet.pending_signing_key = pending_signing_key;
+        Ok(())
+    }
+
+    /// Check that `password` opens the participant's keystore, if it has one
+    pub fn check_password(&self, participant_id: Uuid, password: &str) -> Result<()> {
+        if self.get_keystore_path(participant_id).exists() {
+            self.open_keystore(participant_id, password)?;
+        }
+        Ok(())
+    }
+
+    /// Drop the signing key of a wallet from memory
+    pub fn lock_wallet(&mut self, participant_id: Uuid) -> Result<()> {
+        self.wallet_mut(participant_id)?.lock();
+        Ok(())
+    }
+
+    /// Drop the signing keys of all wallets from memory
+    pub fn lock_all(&mut self) {
+        for wallet in self.wallets.values_mut() {
+            wallet.lock();
+        }
+    }
+
+    /// Store `new_key` under `password` as the pending replacement of an unlocked
+    /// wallet's signing key. The password must open the current keystore, if there is
+    /// one.
+    ///
+    /// The wallet keeps signing with its current key until `complete_rotation` is
+    /// called for the block including `transaction_id`; see
+    /// `TransactionBlockchain::rotate_participant_key`.
+    pub fn stage_rotation(
+        &mut self,
+        participant_id: Uuid,
+        new_key: SigningKey,
+        password: &str,
+        transaction_id: String,
+    ) -> Result<PendingKey> {
+        let wallet = self.wallet(participant_id)?;
+        wallet.unlocked_key()?;
+        if let Some(pending) = &wallet.pending_key {
+            return Err(anyhow!(
+                "Participant {} already has a key rotation pending in transaction {}",
+                participant_id,
+                pending.transaction_id
+            ));
+        }
+        self.check_password(participant_id, password)?;
+
+        Keystore::encrypt(&new_key, password, self.kdf_iterations)?
+            .save(self.get_pending_keystore_path(participant_id))?;
+        let pending = PendingKey {
+            public_key: hex::encode(new_key.verifying_key().as_bytes()),
+            staged_at: Utc::now(),
+            transaction_id,
+        };
+        let wallet = self.wallet_mut(participant_id)?;
+        wallet.pending_key = Some(pending.clone());
+        wallet.pending_signing_key = Some(new_key);
+        self.save_wallet(self.wallet(participant_id)?)?;
+        Ok(pending)
+    }
+
+    /// Drop the pending key rotation of a wallet, e.g. when its transaction was refused
+    pub fn discard_rotation(&mut self, participant_id: Uuid) -> Result<()> {
+        let wallet = self.wallet_mut(participant_id)?;
+        wallet.pending_key = None;
+        wallet.pending_signing_key = None;
+        let path = self.get_pending_keystore_path(participant_id);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        self.save_wallet(self.wallet(participant_id)?)
+    }
+
+    /// Switch a wallet to the hex-encoded `new_key` now that the rotation recorded by
+    /// `transaction_id` is in a block, retiring its current key.
+    ///
+    /// A rotation staged with `stage_rotation` moves its keystore into place. Any other
+    /// rotation, e.g. one made on another node, only changes the public key, and the
+    /// wallet stays locked until its keystore is replaced.
+    pub fn complete_rotation(
+        &mut self,
+        participant_id: Uuid,
+        new_key: &str,
+        transaction_id: &str,
+    ) -> Result<RetiredKey> {
+        let public_key = VerifyingKey::from_bytes(hex::decode(new_key)?.as_slice().try_into()?)?;
+        let staged = self
+            .wallet(participant_id)?
+            .pending_key
+            .as_ref()
+            .is_some_and(|pending| pending.public_key == new_key);
+        if staged {
+            fs::rename(
+                self.get_pending_keystore_path(participant_id),
+                self.get_keystore_path(participant_id),
+            )?;
+        }
+
+        let wallet = self.wallet_mut(participant_id)?;
+        let retired = RetiredKey {
+            public_key: hex::encode(wallet.public_key.as_bytes()),
+            replaced_by: new_key.to_string(),
+            retired_at: Utc::now(),
+            transaction_id: Some(transaction_id.to_string()),
+        };
+        wallet.public_key = public_key;
+        wallet.signing_key = None;
+        if staged {
+            wallet.pending_key = None;
+            wallet.signing_key = wallet.pending_signing_key.take();
+        }
+        wallet.retired_keys.push(retired.clone());
+
+        self.save_wallet(self.wallet(participant_id)?)?;
+        Ok(retired)
+    }
+
+    /// Mark the certificate carrying `credential_id` revoked in the holder's wallet, if
+    /// the holder's wallet is local
+    pub fn mark_certificate_revoked(&mut self, holder_id: Uuid, credential_id: &str) -> Result<()> {
+        let Some(holder) = self.wallets.get_mut(&holder_id) else {
+            return Ok(());
+        };
+        for cert in &mut holder.participant.certificates {
+            if cert.credential.as_ref().map(|c| c.id.as_str()) == Some(credential_id) {
+                cert.status = CertificateStatus::Revoked;
+            }
+        }
+        self.save_wallet(self.wallet(holder_id)?)
+    }
+
+    /// Get a wallet by participant ID
+    pub fn get_wallet(&self, participant_id: Uuid) -> Option<&Wallet> {
+        self.wallets.get(&participant_id)
+    }
+
+    /// Get a mutable wallet by participant ID
+    pub fn get_wallet_mut(&mut self, participant_id: Uuid) -> Option<&mut Wallet> {
+        self.wallets.get_mut(&participant_id)
+    }
+
+    /// List all participant IDs
+    pub fn list_participants(&self) -> Vec<Uuid> {
+        self.wallets.keys().cloned().collect()
+    }
+
+    /// Get participants by type
+    pub fn get_participants_by_type(&self, participant_type: &ParticipantType) -> Vec<&Wallet> {
+        self.wallets
+            .values()
+            .filter(|wallet| &wallet.participant.participant_type == participant_type)
+            .collect()
+    }
+
+    /// Remove a wallet
+    pub fn remove_wallet(&mut self, participant_id: Uuid) -> Result<()> {
+        // Remove from memory
+        self.wallets.remove(&participant_id);
+
+        // Remove from storage
+        for path in [
+            self.get_wallet_path(participant_id),
+            self.get_keystore_path(participant_id),
+            self.get_pending_keystore_path(participant_id),
+        ] {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create a backup of all wallets. Signing keys are not included; back up the
+    /// keystore files for those.
+    pub fn create_backup(&self) -> Result<String> {
+        let backup_dir = self.storage_dir.join("backups");
+        if !backup_dir.exists() {
+            fs::create_dir_all(&backup_dir)?;
+        }
+
+        let timestamp = Utc::now().format("%Y%m%d_%H%M%S");
+        let backup_path = backup_dir.join(format!("wallet_backup_{}.json", timestamp));
+
+        let backup_data = serde_json::to_string_pretty(&self.wallets)?;
+        fs::write(&backup_path, backup_data)?;
+
+        Ok(backup_path.to_string_lossy().to_string())
+    }
+
+    /// Get wallet file path
+    fn get_wallet_path(&self, participant_id: Uuid) -> PathBuf {
+        self.storage_dir.join(format!("{}.wallet", participant_id))
+    }
+
+    /// Get keystore file path
+    fn get_keystore_path(&self, participant_id: Uuid) -> PathBuf {
+        self.storage_dir
+            .join(format!("{}.keystore", participant_id))
+    }
+
+    /// Get the path of the keystore holding the key of a pending rotation
+    fn get_pending_keystore_path(&self, participant_id: Uuid) -> PathBuf {
+        self.storage_dir
+            .join(format!("{}.keystore.pending", participant_id))
+    }
+
+    fn open_keystore(&self, participant_id: Uuid, password: &str) -> Result<SigningKey> {
+        let path = self.get_keystore_path(participant_id);
+        if !path.exists() {
+            return Err(anyhow!(
+                "No keystore for participant {}; set a password first",
+                participant_id
+            ));
+        }
+        Keystore::load(path)?.decrypt(password)
+    }
+
+    fn wallet(&self, participant_id: Uuid) -> Result<&Wallet> {
+        self.wallets
+            .get(&participant_id)
+            .ok_or_else(|| anyhow!("Wallet not found for participant {}", participant_id))
+    }
+
+    fn wallet_mut(&mut self, participant_id: Uuid) -> Result<&mut Wallet> {
+        self.wallets
+            .get_mut(&participant_id)
+            .ok_or_else(|| anyhow!("Wallet not found for participant {}", participant_id))
+    }
+
+    /// Get wallet statistics
+    pub fn get_statistics(&self) -> WalletManagerStats {
+        let mut type_counts = HashMap::new();
+        let mut total_certificates = 0;
+        let mut active_participants = 0;
+
+        for wallet in self.wallets.values() {
+            *type_counts
+                .entry(wallet.participant.participant_type.clone())
+                .or_insert(0) += 1;
+            total_certificates += wallet.participant.certificates.len();
+
+            if wallet.participant.last_activity.is_some() {
+                active_participants += 1;
+            }
+        }
+
+        WalletManagerStats {
+            total_participants: self.wallets.len(),
+            type_distribution: type_counts,
+            total_certificates,
+            active_participants,
+        }
+    }
+}
+
+/// Wallet manager statistics
+#[derive(Debug, Clone)]
+pub struct WalletManagerStats {
+    pub total_participants: usize,
+    pub type_distribution: HashMap<ParticipantType, usize>,
+    pub total_certificates: usize,
+    pub active_participants: usize,
+}
+
+/// Helper functions for creating demo participants
+impl Participant {
+    /// Create a farmer participant
+    pub fn new_farmer(name: String, location: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            participant_type: ParticipantType::Producer,
+            contact_info: ContactInfo {
+                email: None,
+                phone: None,
+                address: Some(location.clone()),
+                website: None,
+            },
+            location: Some(location),
+            permissions: ParticipantPermissions::for_type(&ParticipantType::Producer),
+            certificates: vec![],
+            registered_at: Utc::now(),
+            last_activity: None,
+            reputation: 1.0,
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Create a UHT manufacturer participant
+    pub fn new_uht_manufacturer(name: String, location: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            participant_type: ParticipantType::Manufacturer,
+            contact_info: ContactInfo {
+                email: None,
+                phone: None,
+                address: Some(location.clone()),
+                website: None,
+            },
+            location: Some(location),
+            permissions: ParticipantPermissions::for_type(&ParticipantType::Manufacturer),
+            certificates: vec![],
+            registered_at: Utc::now(),
+            last_activity: None,
+            reputation: 1.0,
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Create a logistics provider participant
+    pub fn new_logistics_provider(name: String, location: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            participant_type: ParticipantType::LogisticsProvider,
+            contact_info: ContactInfo {
+                email: None,
+                phone: None,
+                address: Some(location.clone()),
+                website: None,
+            },
+            location: Some(location),
+            permissions: ParticipantPermissions::for_type(&ParticipantType::LogisticsProvider),
+            certificates: vec![],
+            registered_at: Utc::now(),
+            last_activity: None,
+            reputation: 1.0,
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Create a quality lab participant
+    pub fn new_quality_lab(name: String, location: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            participant_type: ParticipantType::QualityLab,
+            contact_info: ContactInfo {
+                email: None,
+                phone: None,
+                address: Some(location.clone()),
+                website: None,
+            },
+            location: Some(location),
+            permissions: ParticipantPermissions::for_type(&ParticipantType::QualityLab),
+            certificates: vec![],
+            registered_at: Utc::now(),
+            last_activity: None,
+            reputation: 1.0,
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Create a retailer participant
+    pub fn new_retailer(name: String, location: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            participant_type: ParticipantType::Retailer,
+            contact_info: ContactInfo {
+                email: None,
+                phone: None,
+                address: Some(location.clone()),
+                website: None,
+            },
+            location: Some(location),
+            permissions: ParticipantPermissions::for_type(&ParticipantType::Retailer),
+            certificates: vec![],
+            registered_at: Utc::now(),
+            last_activity: None,
+            reputation: 1.0,
+            metadata: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_participant_creation() {
+        let farmer =
+            Participant::new_farmer("John's Dairy Farm".to_string(), "Vermont, USA".to_string());
+
+        assert_eq!(farmer.participant_type, ParticipantType::Producer);
+        assert!(farmer.permissions.can_produce);
+        assert!(!farmer.permissions.can_process);
+    }
+
+    #[test]
+    fn test_wallet_creation() {
+        let farmer =
+            Participant::new_farmer("John's Dairy Farm".to_string(), "Vermont, USA".to_string());
+
+        let wallet = Wallet::new(farmer);
+        assert!(wallet.signing_key.is_some());
+        assert!(wallet.has_permission("produce"));
+        assert!(!wallet.has_permission("process"));
+    }
+
+    #[test]
+    fn test_wallet_signing() {
+        let farmer =
+            Participant::new_farmer("John's Dairy Farm".to_string(), "Vermont, USA".to_string());
+
+        let wallet = Wallet::new(farmer);
+        let data = b"test message";
+
+        let signature = wallet.sign(data).unwrap();
+        assert!(wallet.verify(data, &signature));
+    }
+
+    #[test]
+    fn test_wallet_manager() {
+        let temp_dir = tempdir().unwrap();
+        let mut manager = WalletManager::new(temp_dir.path()).unwrap();
+
+        let farmer =
+            Participant::new_farmer("John's Dairy Farm".to_string(), "Vermont, USA".to_string());
+
+        let participant_id = manager.create_wallet(farmer).unwrap();
+        assert!(manager.get_wallet(participant_id).is_some());
+
+        let stats = manager.get_statistics();
+        assert_eq!(stats.total_participants, 1);
+    }
+
+    #[test]
+    fn test_signing_key_survives_restart_encrypted() {
+        let temp_dir = tempdir().unwrap();
+        let mut manager = WalletManager::new(temp_dir.path())
+            .unwrap()
+            .with_kdf_iterations(10);
+        let farmer =
+            Participant::new_farmer("John's Dairy Farm".to_string(), "Vermont, USA".to_string());
+        let participant_id = manager
+            .create_wallet_with_password(farmer, "farm-password")
+            .unwrap();
+        let signature = manager
+            .get_wallet(participant_id)
+            .unwrap()
+            .sign(b"milk")
+            .unwrap();
+
+        // Neither file holds the private key in the clear
+        let secret = manager
+            .get_wallet(participant_id)
+            .unwrap()
+            .unlocked_key()
+            .unwrap()
+            .to_bytes();
+        for entry in fs::read_dir(temp_dir.path()).unwrap() {
+            let contents = fs::read(entry.unwrap().path()).unwrap();
+            assert!(!contents.windows(32).any(|window| window == secret));
+        }
+
+        let mut restarted = WalletManager::new(temp_dir.path()).unwrap();
+        assert_eq!(restarted.list_participants(), vec![participant_id]);
+        let wallet = restarted.get_wallet(participant_id).unwrap();
+        assert!(wallet.is_locked());
+        assert!(wallet.sign(b"milk").is_err());
+
+        assert!(restarted
+            .unlock_wallet(participant_id, "wrong-password")
+            .is_err());
+        restarted
+            .unlock_wallet(participant_id, "farm-password")
+            .unwrap();
+        let wallet = restarted.get_wallet(participant_id).unwrap();
+        assert_eq!(wallet.sign(b"milk").unwrap(), signature);
+
+        restarted.lock_wallet(participant_id).unwrap();
+        assert!(restarted.get_wallet(participant_id).unwrap().is_locked());
+    }
+
+    #[test]
+    fn test_key_rotation_switches_keys_once_confirmed() {
+        let temp_dir = tempdir().unwrap();
+        let mut manager = WalletManager::new(temp_dir.path())
+            .unwrap()
+            .with_kdf_iterations(10);
+        let farmer =
+            Participant::new_farmer("John's Dairy Farm".to_string(), "Vermont, USA".to_string());
+        let participant_id = manager
+            .create_wallet_with_password(farmer, "farm-password")
+            .unwrap();
+        let old_key = manager.get_wallet(participant_id).unwrap().public_key;
+        let new_key = SigningKey::from_bytes(&rand::random::<[u8; 32]>());
+        let new_public_key = hex::encode(new_key.verifying_key().as_bytes());
+
+        assert!(manager
+            .stage_rotation(
+                participant_id,
+                new_key.clone(),
+                "wrong-password",
+                "tx-1".to_string()
+            )
+            .is_err());
+        manager
+            .stage_rotation(
+                participant_id,
+                new_key.clone(),
+                "farm-password",
+                "tx-1".to_string(),
+            )
+            .unwrap();
+        // Only one rotation can be pending
+        assert!(manager
+            .stage_rotation(
+                participant_id,
+                SigningKey::from_bytes(&rand::random::<[u8; 32]>()),
+                "farm-password",
+                "tx-2".to_string(),
+            )
+            .is_err());
+        assert_eq!(
+            manager.get_wallet(participant_id).unwrap().public_key,
+            old_key
+        );
+
+        // The staged key survives a restart before the rotation is confirmed
+        let mut restarted = WalletManager::new(temp_dir.path()).unwrap();
+        restarted
+            .unlock_wallet(participant_id, "farm-password")
+            .unwrap();
+        assert_eq!(
+            restarted.get_wallet(participant_id).unwrap().public_key,
+            old_key
+        );
+        let retired = restarted
+            .complete_rotation(participant_id, &new_public_key, "tx-1")
+            .unwrap();
+        assert_eq!(retired.public_key, hex::encode(old_key.as_bytes()));
+        assert_eq!(retired.replaced_by, new_public_key);
+        let wallet = restarted.get_wallet(participant_id).unwrap();
+        assert_eq!(wallet.public_key, new_key.verifying_key());
+        assert!(wallet.pending_key.is_none());
+        assert_eq!(wallet.unlocked_key().unwrap(), &new_key);
+
+        let mut restarted = WalletManager::new(temp_dir.path()).unwrap();
+        restarted
+            .unlock_wallet(participant_id, "farm-password")
+            .unwrap();
+        let wallet = restarted.get_wallet(participant_id).unwrap();
+        assert_eq!(wallet.public_key, new_key.verifying_key());
+        assert_eq!(wallet.retired_keys, vec![retired]);
+    }
+
+    #[test]
+    fn test_import_and_export_raw_key_files() {
+        let temp_dir = tempdir().unwrap();
+        let mut manager = WalletManager::new(temp_dir.path().join("wallets"))
+            .unwrap()
+            .with_kdf_iterations(10);
+        let key_file = temp_dir.path().join("authority.key");
+        let signing_key = SigningKey::from_bytes(&rand::random::<[u8; 32]>());
+        fs::write(&key_file, signing_key.to_bytes()).unwrap();
+
+        let authority =
+            Participant::new_farmer("Authority Node".to_string(), "Bangkok".to_string());
+        let participant_id = manager
+            .import_key_file(authority, &key_file, "node-password")
+            .unwrap();
+        assert_eq!(
+            manager.get_wallet(participant_id).unwrap().public_key,
+            signing_key.verifying_key()
+        );
+
+        let exported = temp_dir.path().join("exported.key");
+        manager.lock_wallet(participant_id).unwrap();
+        assert!(manager.export_key_file(participant_id, &exported).is_err());
+        manager
+            .unlock_wallet(participant_id, "node-password")
+            .unwrap();
+        manager.export_key_file(participant_id, &exported).unwrap();
+        assert_eq!(fs::read(&exported).unwrap(), signing_key.to_bytes());
+    }
+
+    #[test]
+    fn test_device_wallets_derive_from_participant_key() {
+        let farmer =
+            Participant::new_farmer("John's Dairy Farm".to_string(), "Vermont, USA".to_string());
+        let mut wallet = Wallet::new(farmer);
+
+        let device = wallet.derive_device_wallet("m/0/2").unwrap();
+        assert_eq!(device.derivation_path.as_deref(), Some("m/0'/2'"));
+        assert_eq!(device.participant_id(), wallet.participant_id());
+        assert_eq!(
+            device.public_key,
+            wallet.derive_device_wallet("m/0'/2'").unwrap().public_key
+        );
+        assert_ne!(
+            device.public_key,
+            wallet.derive_device_wallet("m/0'/3'").unwrap().public_key
+        );
+
+        wallet.lock();
+        assert!(wallet.derive_device_wallet("m/0'/2'").is_err());
+    }
+
+    #[test]
+    fn test_certificate_management() {
+        let farmer =
+            Participant::new_farmer("John's Dairy Farm".to_string(), "Vermont, USA".to_string());
+
+        let mut wallet = Wallet::new(farmer);
+
+        let cert = Certificate {
+            id: "ORGANIC-001".to_string(),
+            cert_type: "ORGANIC".to_string(),
+            issuer: "USDA".to_string(),
+            issued_at: Utc::now(),
+            expires_at: Utc::now() + chrono::Duration::days(365),
+            status: CertificateStatus::Active,
+            metadata: HashMap::new(),
+            credential: None,
+        };
+
+        // A certificate without a verifiable credential is not trusted
+        let temp_dir = tempdir().unwrap();
+        let chain =
+            crate::transaction::TransactionBlockchain::new(temp_dir.path().to_str().unwrap())
+                .unwrap();
+        wallet.add_certificate(cert);
+        assert_eq!(wallet.get_active_certificates().len(), 1);
+        assert!(!wallet.has_valid_certificate("ORGANIC", &chain));
+        assert!(!wallet.has_valid_certificate("FDA_APPROVED", &chain));
+    }
+}
@@ -1,19 +1,29 @@
 //! Authentication and authorization module for web API
 
-use crate::web::models::{ActorRole, ApiError, AuthRequest, AuthResponse, UserClaims};
+use crate::error::WebError;
+use crate::web::handlers::web_error;
+use crate::web::models::{
+    ActorRole, ApiError, AuthRequest, AuthResponse, CreateUserRequest, RefreshRequest, UserClaims,
+    UserSummary,
+};
 use axum::{
-    extract::{Request, State},
+    extract::{Extension, Path, Request, State},
     http::{header::AUTHORIZATION, StatusCode},
     middleware::Next,
     response::Response,
     Json,
 };
 use bcrypt::{hash, verify, DEFAULT_COST};
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path as FsPath, PathBuf};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
 
 /// JWT secret key (loaded from environment variable only for security)
 fn get_jwt_secret() -> Result<Vec<u8>, crate::error::WebError> {
@@ -79,19 +89,153 @@ pub fn generate_secure_jwt_secret() -> Result<String, crate::error::WebError> {
     Ok(secret)
 }
 
-/// User database (in production, this would be a proper database)
+/// User database, keyed by username
 type UserDatabase = Arc<RwLock<HashMap<String, UserInfo>>>;
 
-#[derive(Debug, Clone)]
+/// File in the data directory holding users, refresh tokens and revoked tokens
+const USERS_FILE: &str = "users.json";
+
+/// Lifetime of an access token
+const ACCESS_TOKEN_HOURS: i64 = 24;
+
+/// Lifetime of a refresh token
+const REFRESH_TOKEN_DAYS: i64 = 7;
+
+/// Consecutive failed logins that lock an account
+pub const MAX_FAILED_LOGINS: u32 = 5;
+
+/// How long a locked account refuses logins
+pub const LOCKOUT_MINUTES: i64 = 15;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserInfo {
     pub username: String,
     pub password_hash: String,
     pub role: ActorRole,
+    /// Failed logins since the last successful one
+    #[serde(default)]
+    pub failed_logins: u32,
+    #[serde(default)]
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+impl UserInfo {
+    fn new(username: String, password_hash: String, role: ActorRole) -> Self {
+        Self {
+            username,
+            password_hash,
+            role,
+            failed_logins: 0,
+            locked_until: None,
+        }
+    }
+
+    fn is_locked(&self, now: DateTime<Utc>) -> bool {
+        self.locked_until.is_some_and(|until| until > now)
+    }
+
+    /// Record the outcome of a password check made at `now`. The lock is checked again
+    /// here, as guesses checked in parallel may have locked the account meanwhile.
+    fn record_login(&mut self, verified: bool, now: DateTime<Utc>) -> Result<ActorRole, WebError> {
+        if self.locked_until.is_some_and(|until| until <= now) {
+            self.failed_logins = 0;
+            self.locked_until = None;
+        }
+        if self.is_locked(now) {
+            return Err(locked(self));
+        }
+
+        if verified {
+            self.failed_logins = 0;
+            Ok(self.role.clone())
+        } else {
+            self.failed_logins += 1;
+            if self.failed_logins >= MAX_FAILED_LOGINS {
+                self.locked_until = Some(now + Duration::minutes(LOCKOUT_MINUTES));
+                Err(locked(self))
+            } else {
+                Err(WebError::AuthenticationFailed(
+                    "Invalid username or password".to_string(),
+                ))
+            }
+        }
+    }
+
+    fn summary(&self) -> UserSummary {
+        UserSummary {
+            username: self.username.clone(),
+            role: self.role.to_string(),
+            failed_logins: self.failed_logins,
+            locked_until: self.locked_until,
+        }
+    }
+}
+
+/// Issued refresh token; only its SHA-256 hash is kept, as the map key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RefreshTokenRecord {
+    username: String,
+    /// Login session the token belongs to; rotation keeps the family
+    family: String,
+    expires_at: DateTime<Utc>,
+    /// Set once exchanged; presenting the token again revokes the whole family
+    used: bool,
+    /// Access token issued with this refresh token, revoked along with the family
+    access_jti: String,
+    access_exp: usize,
+}
+
+/// Refresh tokens and revoked access tokens
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SessionState {
+    refresh_tokens: HashMap<String, RefreshTokenRecord>,
+    /// Revoked access-token ids with their expiry, dropped once the token expires
+    revoked_tokens: HashMap<String, usize>,
+}
+
+impl SessionState {
+    /// Drop every refresh token of the families matching `revoke` and revoke their
+    /// access tokens
+    fn revoke_where(&mut self, revoke: impl Fn(&RefreshTokenRecord) -> bool) {
+        let families: HashSet<String> = self
+            .refresh_tokens
+            .values()
+            .filter(|record| revoke(record))
+            .map(|record| record.family.clone())
+            .collect();
+        let revoked_tokens = &mut self.revoked_tokens;
+        self.refresh_tokens.retain(|_, record| {
+            if !families.contains(&record.family) {
+                return true;
+            }
+            revoked_tokens.insert(record.access_jti.clone(), record.access_exp);
+            false
+        });
+    }
+
+    fn prune(&mut self, now: DateTime<Utc>) {
+        let timestamp = now.timestamp() as usize;
+        self.refresh_tokens
+            .retain(|_, record| record.expires_at > now);
+        self.revoked_tokens.retain(|_, exp| *exp >= timestamp);
+    }
+}
+
+/// On-disk layout of `USERS_FILE`
+#[derive(Default, Deserialize)]
+struct AuthFile {
+    users: HashMap<String, UserInfo>,
+    #[serde(default)]
+    sessions: SessionState,
 }
 
 #[derive(Clone)]
 pub struct AuthState {
     pub users: UserDatabase,
+    sessions: Arc<RwLock<SessionState>>,
+    /// File users and sessions are saved to; `None` keeps them in memory only.
+    /// Held while writing so saves do not interleave.
+    storage: Arc<Mutex<Option<PathBuf>>>,
 }
 
 impl Default for AuthState {
@@ -104,13 +248,34 @@ impl AuthState {
     pub fn new() -> Self {
         // SECURITY: No default users created - users must be explicitly created
         // This prevents hardcoded credentials and improves security
-        let users = HashMap::new();
+        Self::with_users(HashMap::new())
+    }
 
+    fn with_users(users: HashMap<String, UserInfo>) -> Self {
         Self {
             users: Arc::new(RwLock::new(users)),
+            sessions: Arc::new(RwLock::new(SessionState::default())),
+            storage: Arc::new(Mutex::new(None)),
         }
     }
 
+    /// Open the user database kept in `data_dir`, starting empty if it does not exist yet
+    pub fn open<P: AsRef<FsPath>>(data_dir: P) -> anyhow::Result<Self> {
+        let path = data_dir.as_ref().join(USERS_FILE);
+        let mut file: AuthFile = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            AuthFile::default()
+        };
+        file.sessions.prune(Utc::now());
+
+        Ok(Self {
+            users: Arc::new(RwLock::new(file.users)),
+            sessions: Arc::new(RwLock::new(file.sessions)),
+            storage: Arc::new(Mutex::new(Some(path))),
+        })
+    }
+
     /// Initialize with default users ONLY if ALLOW_DEFAULT_USERS env var is set (for development)
     pub fn new_with_defaults() -> Self {
         // Only allow default users in development if explicitly requested
@@ -136,34 +301,24 @@ impl AuthState {
 
         users.insert(
             "admin".to_string(),
-            UserInfo {
-                username: "admin".to_string(),
-                password_hash: admin_hash,
-                role: ActorRole::Admin,
-            },
+            UserInfo::new("admin".to_string(), admin_hash, ActorRole::Admin),
         );
 
         users.insert(
             "farmer1".to_string(),
-            UserInfo {
-                username: "farmer1".to_string(),
-                password_hash: farmer_hash,
-                role: ActorRole::Farmer,
-            },
+            UserInfo::new("farmer1".to_string(), farmer_hash, ActorRole::Farmer),
         );
 
         users.insert(
             "processor1".to_string(),
-            UserInfo {
-                username: "processor1".to_string(),
-                password_hash: processor_hash,
-                role: ActorRole::Processor,
-            },
+            UserInfo::new(
+                "processor1".to_string(),
+                processor_hash,
+                ActorRole::Processor,
+            ),
         );
 
-        Self {
-            users: Arc::new(RwLock::new(users)),
-        }
+        Self::with_users(users)
     }
 
     /// Initialize with an admin user (for first-time setup)
@@ -179,16 +334,10 @@ impl AuthState {
 
         users.insert(
             username.clone(),
-            UserInfo {
-                username,
-                password_hash,
-                role: ActorRole::Admin,
-            },
+            UserInfo::new(username, password_hash, ActorRole::Admin),
         );
 
-        Ok(Self {
-            users: Arc::new(RwLock::new(users)),
-        })
+        Ok(Self::with_users(users))
     }
 
     /// Create a new user with secure password hashing and validation
@@ -217,17 +366,15 @@ impl AuthState {
 
         users.insert(
             username.clone(),
-            UserInfo {
-                username,
-                password_hash,
-                role,
-            },
+            UserInfo::new(username, password_hash, role),
         );
+        drop(users);
 
-        Ok(())
+        self.persist().await
     }
 
-    /// Update user password with secure hashing and validation
+    /// Update user password with secure hashing and validation; the user's sessions are
+    /// revoked
     pub async fn update_password(
         &self,
         username: &str,
@@ -243,7 +390,8 @@ impl AuthState {
 
         if let Some(user_info) = users.get_mut(username) {
             user_info.password_hash = password_hash;
-            Ok(())
+            drop(users);
+            self.revoke_user_sessions(username).await
         } else {
             Err(crate::error::WebError::ResourceNotFound(format!(
                 "User '{}' not found",
@@ -284,12 +432,21 @@ impl AuthState {
             .collect()
     }
 
-    /// Delete a user (admin only)
+    /// Users with their lockout state, sorted by username (admin only)
+    pub async fn user_summaries(&self) -> Vec<UserSummary> {
+        let users = self.users.read().await;
+        let mut summaries: Vec<UserSummary> = users.values().map(UserInfo::summary).collect();
+        summaries.sort_by(|a, b| a.username.cmp(&b.username));
+        summaries
+    }
+
+    /// Delete a user (admin only); the user's sessions are revoked
     pub async fn delete_user(&self, username: &str) -> Result<(), crate::error::WebError> {
         let mut users = self.users.write().await;
 
         if users.remove(username).is_some() {
-            Ok(())
+            drop(users);
+            self.revoke_user_sessions(username).await
         } else {
             Err(crate::error::WebError::ResourceNotFound(format!(
                 "User '{}' not found",
@@ -297,17 +454,242 @@ impl AuthState {
             )))
         }
     }
+
+    /// Clear a user's failed logins and lockout (admin only)
+    pub async fn unlock_user(&self, username: &str) -> Result<UserSummary, WebError> {
+        let mut users = self.users.write().await;
+        let user_info = users
+            .get_mut(username)
+            .ok_or_else(|| WebError::ResourceNotFound(format!("User '{}' not found", username)))?;
+        user_info.failed_logins = 0;
+        user_info.locked_until = None;
+        let summary = user_info.summary();
+        drop(users);
+
+        self.persist().await?;
+        Ok(summary)
+    }
+
+    /// Check a password, counting failures towards the lockout.
+    ///
+    /// `MAX_FAILED_LOGINS` consecutive failures lock the account for `LOCKOUT_MINUTES`;
+    /// a locked account is refused with `RateLimitExceeded` without checking the password.
+    pub async fn verify_credentials(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<ActorRole, WebError> {
+        let invalid = || WebError::AuthenticationFailed("Invalid username or password".to_string());
+        let now = Utc::now();
+
+        let password_hash = {
+            let users = self.users.read().await;
+            let user_info = users.get(username).ok_or_else(invalid)?;
+            if user_info.is_locked(now) {
+                return Err(locked(user_info));
+            }
+            user_info.password_hash.clone()
+        };
+
+        // bcrypt is slow, so the password is checked without holding the lock
+        let verified = verify(password, &password_hash).unwrap_or(false);
+
+        let mut users = self.users.write().await;
+        let result = users
+            .get_mut(username)
+            .ok_or_else(invalid)?
+            .record_login(verified, now);
+        drop(users);
+
+        self.persist().await?;
+        result
+    }
+
+    /// Issue an access token and a refresh token starting a new session
+    pub async fn start_session(
+        &self,
+        username: &str,
+        role: &ActorRole,
+    ) -> Result<AuthResponse, WebError> {
+        self.issue_tokens(username, role, Uuid::new_v4().to_string())
+            .await
+    }
+
+    /// Exchange a refresh token for a new token pair.
+    ///
+    /// Each refresh token works once. Presenting a used one means it leaked, so the
+    /// whole session is revoked, including the access token issued with its successor.
+    pub async fn refresh(&self, refresh_token: &str) -> Result<AuthResponse, WebError> {
+        let invalid = || WebError::AuthenticationFailed("Invalid refresh token".to_string());
+        let now = Utc::now();
+
+        let mut sessions = self.sessions.write().await;
+        let record = sessions
+            .refresh_tokens
+            .get_mut(&token_hash(refresh_token))
+            .ok_or_else(invalid)?;
+        if record.used {
+            let family = record.family.clone();
+            sessions.revoke_where(|record| record.family == family);
+            drop(sessions);
+            self.persist().await?;
+            return Err(WebError::AuthenticationFailed(
+                "Refresh token was already used; the session has been revoked".to_string(),
+            ));
+        }
+        if record.expires_at <= now {
+            return Err(WebError::AuthenticationFailed(
+                "Refresh token has expired".to_string(),
+            ));
+        }
+        record.used = true;
+        let (username, family) = (record.username.clone(), record.family.clone());
+        drop(sessions);
+
+        // The role is read again so role changes apply from the next refresh
+        let role = self
+            .users
+            .read()
+            .await
+            .get(&username)
+            .filter(|user_info| !user_info.is_locked(now))
+            .map(|user_info| user_info.role.clone());
+        match role {
+            Some(role) => self.issue_tokens(&username, &role, family).await,
+            None => {
+                self.sessions
+                    .write()
+                    .await
+                    .revoke_where(|record| record.family == family);
+                self.persist().await?;
+                Err(invalid())
+            }
+        }
+    }
+
+    /// Revoke an access token and the session it belongs to
+    pub async fn logout(&self, claims: &UserClaims) -> Result<(), WebError> {
+        let mut sessions = self.sessions.write().await;
+        sessions
+            .revoked_tokens
+            .insert(claims.jti.clone(), claims.exp);
+        sessions.revoke_where(|record| record.access_jti == claims.jti);
+        drop(sessions);
+
+        self.persist().await
+    }
+
+    /// Revoke every session of a user
+    pub async fn revoke_user_sessions(&self, username: &str) -> Result<(), WebError> {
+        self.sessions
+            .write()
+            .await
+            .revoke_where(|record| record.username == username);
+        self.persist().await
+    }
+
+    /// Whether validated claims still grant access: the token is not revoked and its
+    /// user still exists
+    pub async fn check_claims(&self, claims: &UserClaims) -> Result<(), WebError> {
+        if self
+            .sessions
+            .read()
+            .await
+            .revoked_tokens
+            .contains_key(&claims.jti)
+        {
+            return Err(WebError::AuthenticationFailed(
+                "Token has been revoked".to_string(),
+            ));
+        }
+        if !self.users.read().await.contains_key(&claims.sub) {
+            return Err(WebError::AuthenticationFailed(format!(
+                "User '{}' no longer exists",
+                claims.sub
+            )));
+        }
+        Ok(())
+    }
+
+    async fn issue_tokens(
+        &self,
+        username: &str,
+        role: &ActorRole,
+        family: String,
+    ) -> Result<AuthResponse, WebError> {
+        let now = Utc::now();
+        let claims = access_claims(username, role)?;
+        let token = encode_claims(&claims)?;
+        let refresh_token = hex::encode(rand::random::<[u8; 32]>());
+        let refresh_expires_at = now + Duration::days(REFRESH_TOKEN_DAYS);
+
+        let mut sessions = self.sessions.write().await;
+        sessions.prune(now);
+        sessions.refresh_tokens.insert(
+            token_hash(&refresh_token),
+            RefreshTokenRecord {
+                username: username.to_string(),
+                family,
+                expires_at: refresh_expires_at,
+                used: false,
+                access_jti: claims.jti.clone(),
+                access_exp: claims.exp,
+            },
+        );
+        drop(sessions);
+        self.persist().await?;
+
+        Ok(AuthResponse {
+            token,
+            expires_at: now + Duration::hours(ACCESS_TOKEN_HOURS),
+            user_role: role.to_string(),
+            refresh_token,
+            refresh_expires_at,
+        })
+    }
+
+    /// Write users and sessions to the storage file, if any
+    async fn persist(&self) -> Result<(), WebError> {
+        let storage = self.storage.lock().await;
+        let Some(path) = storage.as_ref() else {
+            return Ok(());
+        };
+        let users = self.users.read().await;
+        let sessions = self.sessions.read().await;
+        let write = || -> anyhow::Result<()> {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let contents = serde_json::json!({ "users": &*users, "sessions": &*sessions });
+            let temp = path.with_extension("json.tmp");
+            fs::write(&temp, serde_json::to_vec_pretty(&contents)?)?;
+            fs::rename(&temp, path)?;
+            Ok(())
+        };
+        write().map_err(|e| WebError::ServerError(format!("Failed to save users: {}", e)))
+    }
 }
 
-/// Generate JWT token for authenticated user
-pub fn generate_token(username: &str, role: &ActorRole) -> Result<String, crate::error::WebError> {
-    // Try to get secret from config/context if available, otherwise fallback to env
-    // For now, we'll use the existing get_jwt_secret which handles env vars
-    // In a real app, we'd pass the secret in via context
-    let jwt_secret = get_jwt_secret()?;
+fn locked(user_info: &UserInfo) -> WebError {
+    WebError::RateLimitExceeded(format!(
+        "Account locked after {} failed logins; try again after {}",
+        user_info.failed_logins,
+        user_info
+            .locked_until
+            .map(|until| until.to_rfc3339())
+            .unwrap_or_default()
+    ))
+}
+
+/// Hex SHA-256 of a refresh token, under which it is stored
+fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
 
+/// Claims of a new access token
+fn access_claims(username: &str, role: &ActorRole) -> Result<UserClaims, WebError> {
     let expiration = Utc::now()
-        .checked_add_signed(Duration::hours(24))
+        .checked_add_signed(Duration::hours(ACCESS_TOKEN_HOURS))
         .ok_or_else(|| {
             crate::error::WebError::ServerError(
                 "Failed to calculate token expiration time".to_string(),
@@ -315,15 +697,23 @@ pub fn generate_token(username: &str, role: &ActorRole) -> Result<String, crate:
         })?
         .timestamp() as usize;
 
-    let claims = UserClaims {
+    Ok(UserClaims {
         sub: username.to_string(),
         role: role.to_string(),
         exp: expiration,
-    };
+        jti: Uuid::new_v4().to_string(),
+    })
+}
+
+fn encode_claims(claims: &UserClaims) -> Result<String, WebError> {
+    // Try to get secret from config/context if available, otherwise fallback to env
+    // For now, we'll use the existing get_jwt_secret which handles env vars
+    // In a real app, we'd pass the secret in via context
+    let jwt_secret = get_jwt_secret()?;
 
     encode(
         &Header::default(),
-        &claims,
+        claims,
         &EncodingKey::from_secret(&jwt_secret),
     )
     .map_err(|e| {
@@ -331,6 +721,11 @@ pub fn generate_token(username: &str, role: &ActorRole) -> Result<String, crate:
     })
 }
 
+/// Generate JWT token for authenticated user
+pub fn generate_token(username: &str, role: &ActorRole) -> Result<String, crate::error::WebError> {
+    encode_claims(&access_claims(username, role)?)
+}
+
 /// Validate JWT token and extract claims
 pub fn validate_token(token: &str) -> Result<UserClaims, crate::error::WebError> {
     let jwt_secret = get_jwt_secret()?;
@@ -352,52 +747,131 @@ pub async fn authenticate(
     State(auth_state): State<AuthState>,
     Json(auth_request): Json<AuthRequest>,
 ) -> Result<Json<AuthResponse>, (StatusCode, Json<ApiError>)> {
-    let users = auth_state.users.read().await;
-
-    if let Some(user_info) = users.get(&auth_request.username) {
-        // Use bcrypt to verify password
-        match verify(&auth_request.password, &user_info.password_hash) {
-            Ok(true) => match generate_token(&auth_request.username, &user_info.role) {
-                Ok(token) => {
-                    let expires_at = Utc::now() + Duration::hours(24);
-                    Ok(Json(AuthResponse {
-                        token,
-                        expires_at,
-                        user_role: user_info.role.to_string(),
-                    }))
-                }
-                Err(_) => Err((
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ApiError {
-                        error: "token_generation_failed".to_string(),
-                        message: "Failed to generate authentication token".to_string(),
-                        timestamp: Utc::now(),
-                    }),
-                )),
-            },
-            Ok(false) | Err(_) => Err((
+    let role = match auth_state
+        .verify_credentials(&auth_request.username, &auth_request.password)
+        .await
+    {
+        Ok(role) => role,
+        Err(WebError::AuthenticationFailed(_)) => {
+            return Err((
                 StatusCode::UNAUTHORIZED,
                 Json(ApiError {
                     error: "invalid_credentials".to_string(),
                     message: "Invalid username or password".to_string(),
                     timestamp: Utc::now(),
                 }),
-            )),
+            ))
         }
-    } else {
-        Err((
-            StatusCode::UNAUTHORIZED,
+        Err(WebError::RateLimitExceeded(message)) => {
+            return Err((
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(ApiError {
+                    error: "account_locked".to_string(),
+                    message,
+                    timestamp: Utc::now(),
+                }),
+            ))
+        }
+        Err(e) => return Err(web_error(e)),
+    };
+
+    match auth_state
+        .start_session(&auth_request.username, &role)
+        .await
+    {
+        Ok(response) => Ok(Json(response)),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
             Json(ApiError {
-                error: "invalid_credentials".to_string(),
-                message: "Invalid username or password".to_string(),
+                error: "token_generation_failed".to_string(),
+                message: "Failed to generate authentication token".to_string(),
                 timestamp: Utc::now(),
             }),
-        ))
+        )),
+    }
+}
+
+/// Exchange a refresh token for a new token pair
+pub async fn refresh_token(
+    State(auth_state): State<AuthState>,
+    Json(request): Json<RefreshRequest>,
+) -> Result<Json<AuthResponse>, (StatusCode, Json<ApiError>)> {
+    auth_state
+        .refresh(&request.refresh_token)
+        .await
+        .map(Json)
+        .map_err(web_error)
+}
+
+/// Revoke the caller's token and session
+pub async fn logout(
+    State(auth_state): State<AuthState>,
+    Extension(claims): Extension<UserClaims>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    auth_state.logout(&claims).await.map_err(web_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List API users (admin)
+pub async fn list_user_accounts(State(auth_state): State<AuthState>) -> Json<Vec<UserSummary>> {
+    Json(auth_state.user_summaries().await)
+}
+
+/// Create an API user (admin)
+pub async fn create_user_account(
+    State(auth_state): State<AuthState>,
+    Json(request): Json<CreateUserRequest>,
+) -> Result<(StatusCode, Json<UserSummary>), (StatusCode, Json<ApiError>)> {
+    let role: ActorRole = request
+        .role
+        .parse()
+        .map_err(|e: String| web_error(WebError::BadRequest(e)))?;
+    auth_state
+        .create_user(request.username.clone(), request.password, role.clone())
+        .await
+        .map_err(web_error)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(UserSummary {
+            username: request.username,
+            role: role.to_string(),
+            failed_logins: 0,
+            locked_until: None,
+        }),
+    ))
+}
+
+/// Delete an API user and revoke their sessions (admin); admins cannot delete themselves
+pub async fn delete_user_account(
+    State(auth_state): State<AuthState>,
+    Extension(claims): Extension<UserClaims>,
+    Path(username): Path<String>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    if username == claims.sub {
+        return Err(web_error(WebError::BadRequest(
+            "Admins cannot delete their own account".to_string(),
+        )));
     }
+    auth_state.delete_user(&username).await.map_err(web_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Clear a user's lockout (admin)
+pub async fn unlock_user_account(
+    State(auth_state): State<AuthState>,
+    Path(username): Path<String>,
+) -> Result<Json<UserSummary>, (StatusCode, Json<ApiError>)> {
+    auth_state
+        .unlock_user(&username)
+        .await
+        .map(Json)
+        .map_err(web_error)
 }
 
-/// Middleware to verify JWT token
+/// Middleware to verify JWT token, refusing revoked tokens and deleted users
 pub async fn auth_middleware(
+    State(auth_state): State<AuthState>,
     mut request: Request,
     next: Next,
 ) -> Result<Response, (StatusCode, Json<ApiError>)> {
@@ -408,21 +882,10 @@ pub async fn auth_middleware(
 
     if let Some(auth_header) = auth_header {
         if let Some(token) = auth_header.strip_prefix("Bearer ") {
-            match validate_token(token) {
-                Ok(claims) => {
-                    // Add user claims to request extensions
-                    request.extensions_mut().insert(claims);
-                    Ok(next.run(request).await)
-                }
-                Err(_) => Err((
-                    StatusCode::UNAUTHORIZED,
-                    Json(ApiError {
-                        error: "invalid_token".to_string(),
-                        message: "Invalid or expired authentication token".to_string(),
-                        timestamp: Utc::now(),
-                    }),
-                )),
-            }
+            let claims = authenticate_bearer(&auth_state, token).await?;
+            // Add user claims to request extensions
+            request.extensions_mut().insert(claims);
+            Ok(next.run(request).await)
         } else {
             Err((
                 StatusCode::UNAUTHORIZED,
@@ -445,6 +908,68 @@ pub async fn auth_middleware(
     }
 }
 
+/// Authentication middleware for the WebSocket upgrade. Browsers cannot set headers on
+/// a WebSocket handshake, so the token may also be passed as the `token` query parameter.
+pub async fn websocket_auth_middleware(
+    State(auth_state): State<AuthState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, Json<ApiError>)> {
+    let header_token = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .map(str::to_string);
+    let query_token = || {
+        form_urlencoded::parse(request.uri().query().unwrap_or_default().as_bytes())
+            .find(|(name, _)| name == "token")
+            .map(|(_, token)| token.into_owned())
+    };
+    let Some(token) = header_token.or_else(query_token) else {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(ApiError {
+                error: "missing_token".to_string(),
+                message: "A bearer token or token query parameter is required".to_string(),
+                timestamp: Utc::now(),
+            }),
+        ));
+    };
+
+    let claims = authenticate_bearer(&auth_state, &token).await?;
+    request.extensions_mut().insert(claims);
+    Ok(next.run(request).await)
+}
+
+/// Claims of a valid bearer token whose session has not been revoked
+async fn authenticate_bearer(
+    auth_state: &AuthState,
+    token: &str,
+) -> Result<UserClaims, (StatusCode, Json<ApiError>)> {
+    let claims = validate_token(token).map_err(|_| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(ApiError {
+                error: "invalid_token".to_string(),
+                message: "Invalid or expired authentication token".to_string(),
+                timestamp: Utc::now(),
+            }),
+        )
+    })?;
+    if let Err(e) = auth_state.check_claims(&claims).await {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(ApiError {
+                error: "revoked_token".to_string(),
+                message: e.to_string(),
+                timestamp: Utc::now(),
+            }),
+        ));
+    }
+    Ok(claims)
+}
+
 /// Role-based authorization middleware
 pub fn require_role(
     required_role: ActorRole,
@@ -688,6 +1213,7 @@ mod auth_security_tests {
                 sub: "testuser".to_string(),
                 role: "admin".to_string(),
                 exp: (Utc::now() - Duration::minutes(1)).timestamp() as usize,
+                jti: uuid::Uuid::new_v4().to_string(),
             };
 
             let expired_token = encode(
@@ -1215,6 +1741,7 @@ mod auth_security_tests {
                 sub: "admin_user".to_string(),
                 role: "admin".to_string(),
                 exp: (Utc::now() + Duration::hours(1)).timestamp() as usize,
+                jti: uuid::Uuid::new_v4().to_string(),
             };
 
             // Admin should be able to access any role
@@ -1865,4 +2392,221 @@ mod auth_security_tests {
             }
         }
     }
+
+    // ========================================================================
+    // SESSION & LOCKOUT TESTS
+    // ========================================================================
+
+    mod session_security_tests {
+        use super::*;
+
+        const PASSWORD: &str = "FarmerPass123!";
+
+        async fn state_with_farmer() -> AuthState {
+            let auth_state = AuthState::new();
+            auth_state
+                .create_user(
+                    "farmer_one".to_string(),
+                    PASSWORD.to_string(),
+                    ActorRole::Farmer,
+                )
+                .await
+                .unwrap();
+            auth_state
+        }
+
+        fn claims_of(response: &AuthResponse) -> UserClaims {
+            // Decode without re-checking the secret, which other tests change concurrently
+            let mut validation = Validation::default();
+            validation.insecure_disable_signature_validation();
+            decode::<UserClaims>(&response.token, &DecodingKey::from_secret(&[]), &validation)
+                .unwrap()
+                .claims
+        }
+
+        #[tokio::test]
+        async fn test_lockout_after_failed_logins() {
+            let auth_state = state_with_farmer().await;
+
+            for _ in 1..MAX_FAILED_LOGINS {
+                assert!(matches!(
+                    auth_state
+                        .verify_credentials("farmer_one", "WrongPass123!")
+                        .await,
+                    Err(WebError::AuthenticationFailed(_))
+                ));
+            }
+            assert!(matches!(
+                auth_state
+                    .verify_credentials("farmer_one", "WrongPass123!")
+                    .await,
+                Err(WebError::RateLimitExceeded(_))
+            ));
+            // The right password is refused while the account is locked
+            assert!(matches!(
+                auth_state.verify_credentials("farmer_one", PASSWORD).await,
+                Err(WebError::RateLimitExceeded(_))
+            ));
+
+            let summary = auth_state.unlock_user("farmer_one").await.unwrap();
+            assert_eq!(summary.failed_logins, 0);
+            assert!(summary.locked_until.is_none());
+            assert_eq!(
+                auth_state
+                    .verify_credentials("farmer_one", PASSWORD)
+                    .await
+                    .unwrap(),
+                ActorRole::Farmer
+            );
+        }
+
+        #[test]
+        fn test_correct_password_checked_during_lockout_is_refused() {
+            // A correct guess whose bcrypt check overlapped the failures that locked the
+            // account is refused once its outcome is recorded
+            let now = Utc::now();
+            let mut user_info =
+                UserInfo::new("farmer_one".to_string(), String::new(), ActorRole::Farmer);
+            for _ in 0..MAX_FAILED_LOGINS {
+                assert!(user_info.record_login(false, now).is_err());
+            }
+            assert!(matches!(
+                user_info.record_login(true, now),
+                Err(WebError::RateLimitExceeded(_))
+            ));
+            assert_eq!(
+                user_info
+                    .record_login(true, now + Duration::minutes(LOCKOUT_MINUTES))
+                    .unwrap(),
+                ActorRole::Farmer
+            );
+        }
+
+        #[tokio::test]
+        async fn test_refresh_rotation_and_reuse_detection() {
+            let auth_state = state_with_farmer().await;
+            let first = auth_state
+                .start_session("farmer_one", &ActorRole::Farmer)
+                .await
+                .unwrap();
+
+            let second = auth_state.refresh(&first.refresh_token).await.unwrap();
+            assert_ne!(second.refresh_token, first.refresh_token);
+            assert_ne!(claims_of(&second).jti, claims_of(&first).jti);
+            assert!(auth_state.check_claims(&claims_of(&second)).await.is_ok());
+
+            // Replaying the first token revokes the session it belonged to
+            assert!(auth_state.refresh(&first.refresh_token).await.is_err());
+            assert!(auth_state.refresh(&second.refresh_token).await.is_err());
+            assert!(auth_state.check_claims(&claims_of(&second)).await.is_err());
+            assert!(auth_state.refresh("not-a-refresh-token").await.is_err());
+        }
+
+        #[tokio::test]
+        async fn test_logout_and_user_deletion_revoke_tokens() {
+            let auth_state = state_with_farmer().await;
+            let session = auth_state
+                .start_session("farmer_one", &ActorRole::Farmer)
+                .await
+                .unwrap();
+            let other = auth_state
+                .start_session("farmer_one", &ActorRole::Farmer)
+                .await
+                .unwrap();
+
+            auth_state.logout(&claims_of(&session)).await.unwrap();
+            assert!(auth_state.check_claims(&claims_of(&session)).await.is_err());
+            assert!(auth_state.refresh(&session.refresh_token).await.is_err());
+            // Other sessions of the same user are unaffected
+            assert!(auth_state.check_claims(&claims_of(&other)).await.is_ok());
+
+            auth_state.delete_user("farmer_one").await.unwrap();
+            assert!(auth_state.check_claims(&claims_of(&other)).await.is_err());
+            assert!(auth_state.refresh(&other.refresh_token).await.is_err());
+        }
+
+        #[tokio::test]
+        async fn test_users_and_sessions_survive_restart() {
+            let data_dir = tempfile::tempdir().unwrap();
+            let auth_state = AuthState::open(data_dir.path()).unwrap();
+            auth_state
+                .create_user(
+                    "farmer_one".to_string(),
+                    PASSWORD.to_string(),
+                    ActorRole::Farmer,
+                )
+                .await
+                .unwrap();
+            let session = auth_state
+                .start_session("farmer_one", &ActorRole::Farmer)
+                .await
+                .unwrap();
+            let revoked = auth_state
+                .start_session("farmer_one", &ActorRole::Farmer)
+                .await
+                .unwrap();
+            auth_state.logout(&claims_of(&revoked)).await.unwrap();
+            assert!(auth_state
+                .verify_credentials("farmer_one", "WrongPass123!")
+                .await
+                .is_err());
+
+            let reopened = AuthState::open(data_dir.path()).unwrap();
+            let summaries = reopened.user_summaries().await;
+            assert_eq!(summaries.len(), 1);
+            assert_eq!(summaries[0].username, "farmer_one");
+            assert_eq!(summaries[0].failed_logins, 1);
+            assert!(reopened.check_claims(&claims_of(&revoked)).await.is_err());
+            assert!(reopened.refresh(&session.refresh_token).await.is_ok());
+            assert_eq!(
+                reopened
+                    .verify_credentials("farmer_one", PASSWORD)
+                    .await
+                    .unwrap(),
+                ActorRole::Farmer
+            );
+        }
+    }
+
+    mod websocket_authentication {
+        use super::*;
+        use axum::{middleware, routing::get, Router};
+
+        async fn serve() -> String {
+            let app = Router::new()
+                .route("/ws", get(|| async { "upgraded" }))
+                .layer(middleware::from_fn_with_state(
+                    AuthState::new(),
+                    websocket_auth_middleware,
+                ));
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.unwrap();
+            });
+            format!("http://{}/ws", address)
+        }
+
+        #[tokio::test]
+        async fn test_websocket_upgrade_requires_a_token() {
+            let url = serve().await;
+            let error = |request: reqwest::RequestBuilder| async move {
+                let response = request.send().await.unwrap();
+                assert_eq!(response.status().as_u16(), 401);
+                response.json::<ApiError>().await.unwrap().error
+            };
+            let client = reqwest::Client::new();
+
+            assert_eq!(error(client.get(&url)).await, "missing_token");
+            // Both the header and the query parameter are checked
+            assert_eq!(
+                error(client.get(&url).bearer_auth("not-a-token")).await,
+                "invalid_token"
+            );
+            assert_eq!(
+                error(client.get(format!("{}?token=not-a-token", url))).await,
+                "invalid_token"
+            );
+        }
+    }
 }
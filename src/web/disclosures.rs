@@ -0,0 +1,240 @@
+//! Off-chain store of confidential triples
+//!
+//! Confidential triples committed through the API are anchored on-chain only as salted
+//! commitments (see `core::disclosure`). Their plaintext and salts stay here, on the
+//! owner's node, and never enter the RDF store. An owner discloses chosen triples to
+//! named users, who can then fetch a `DisclosureProof` for each and check it against the
+//! chain. Nobody else, admins included, can read the plaintext.
+//!
+//! The store is kept in `private_triples.json` in the node data directory, or only in
+//! memory when the node has no persistent storage.
+
+use crate::core::disclosure::SealedTriple;
+use crate::error::WebError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name of the store inside the data directory
+const STORE_FILE: &str = "private_triples.json";
+
+/// A confidential triple held off-chain by its owner
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivateTriple {
+    #[serde(flatten)]
+    pub sealed: SealedTriple,
+    pub owner: String,
+    /// Block holding the commitment
+    pub block_index: u64,
+    pub created_at: DateTime<Utc>,
+    /// Users the triple was disclosed to, with the time of disclosure
+    pub disclosed_to: BTreeMap<String, DateTime<Utc>>,
+}
+
+impl PrivateTriple {
+    /// Whether `user` may read the plaintext
+    pub fn can_read(&self, user: &str) -> bool {
+        self.owner == user || self.disclosed_to.contains_key(user)
+    }
+
+    /// The triple as seen by `user`; only the owner sees who it was disclosed to
+    pub fn view(&self, user: &str) -> serde_json::Value {
+        let mut view = serde_json::json!({
+            "commitment": self.sealed.commitment,
+            "triple": self.sealed.triple,
+            "block_index": self.block_index,
+            "owner": self.owner,
+            "created_at": self.created_at,
+        });
+        if self.owner == user {
+            view["disclosed_to"] = serde_json::json!(self.disclosed_to);
+        }
+        view
+    }
+}
+
+/// Plaintext of confidential triples, keyed by commitment
+#[derive(Debug, Default)]
+pub struct PrivateTripleStore {
+    triples: BTreeMap<String, PrivateTriple>,
+    /// Store file; `None` keeps the triples in memory only
+    path: Option<PathBuf>,
+}
+
+impl PrivateTripleStore {
+    /// Store that is not persisted
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open the store kept in `data_dir`, starting empty if it does not exist yet
+    pub fn open<P: AsRef<Path>>(data_dir: P) -> anyhow::Result<Self> {
+        let path = data_dir.as_ref().join(STORE_FILE);
+        let triples = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            triples,
+            path: Some(path),
+        })
+    }
+
+    /// Keep the plaintext of triples whose commitments were written to `block_index`
+    pub fn insert(
+        &mut self,
+        owner: &str,
+        block_index: u64,
+        sealed: Vec<SealedTriple>,
+    ) -> Result<(), WebError> {
+        let created_at = Utc::now();
+        for sealed in sealed {
+            self.triples.insert(
+                sealed.commitment.clone(),
+                PrivateTriple {
+                    sealed,
+                    owner: owner.to_string(),
+                    block_index,
+                    created_at,
+                    disclosed_to: BTreeMap::new(),
+                },
+            );
+        }
+        self.persist()
+    }
+
+    /// Triples `user` owns or was disclosed, oldest first
+    pub fn visible_to(&self, user: &str) -> Vec<&PrivateTriple> {
+        let mut visible: Vec<&PrivateTriple> = self
+            .triples
+            .values()
+            .filter(|triple| triple.can_read(user))
+            .collect();
+        visible.sort_by_key(|triple| triple.created_at);
+        visible
+    }
+
+    /// A triple `user` may read
+    pub fn get(&self, commitment: &str, user: &str) -> Result<&PrivateTriple, WebError> {
+        self.triples
+            .get(commitment)
+            .filter(|triple| triple.can_read(user))
+            .ok_or_else(|| not_found(commitment))
+    }
+
+    /// Disclose an owned triple to `recipient`
+    pub fn disclose(
+        &mut self,
+        commitment: &str,
+        owner: &str,
+        recipient: &str,
+    ) -> Result<&PrivateTriple, WebError> {
+        let recipient = recipient.trim();
+        if recipient.is_empty() || recipient == owner {
+            return Err(WebError::BadRequest(
+                "A disclosure needs a recipient other than the owner".to_string(),
+            ));
+        }
+        let triple = self
+            .triples
+            .get_mut(commitment)
+            .filter(|triple| triple.owner == owner)
+            .ok_or_else(|| not_found(commitment))?;
+        triple
+            .disclosed_to
+            .insert(recipient.to_string(), Utc::now());
+
+        self.persist()?;
+        Ok(&self.triples[commitment])
+    }
+
+    fn persist(&self) -> Result<(), WebError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let write = || -> anyhow::Result<()> {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let temp = path.with_extension("json.tmp");
+            fs::write(&temp, serde_json::to_vec_pretty(&self.triples)?)?;
+            fs::rename(&temp, path)?;
+            Ok(())
+        };
+        write().map_err(|e| {
+            WebError::ServerError(format!("Failed to save confidential triples: {}", e))
+        })
+    }
+}
+
+fn not_found(commitment: &str) -> WebError {
+    WebError::ResourceNotFound(format!("Confidential triple {}", commitment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &str = r#"
+        @prefix ex: <http://example.org/> .
+        ex:batch1 ex:unitPrice "4.20" ;
+                  ex:recipe "secret blend" .
+    "#;
+
+    #[test]
+    fn test_only_owner_and_recipients_read_plaintext() {
+        let mut store = PrivateTripleStore::new();
+        let sealed = SealedTriple::seal_turtle(DATA).unwrap();
+        let commitment = sealed[0].commitment.clone();
+        store.insert("processor_one", 1, sealed).unwrap();
+
+        assert_eq!(store.visible_to("processor_one").len(), 2);
+        assert!(store.visible_to("retailer_one").is_empty());
+        assert!(store.get(&commitment, "retailer_one").is_err());
+
+        // Only the owner can disclose, and not to themselves
+        assert!(store
+            .disclose(&commitment, "retailer_one", "retailer_one")
+            .is_err());
+        assert!(store
+            .disclose(&commitment, "processor_one", "processor_one")
+            .is_err());
+
+        store
+            .disclose(&commitment, "processor_one", "retailer_one")
+            .unwrap();
+        let visible = store.visible_to("retailer_one");
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].sealed.commitment, commitment);
+        assert!(store.visible_to("retailer_two").is_empty());
+
+        // Recipients do not learn who else the triple was disclosed to
+        let triple = store.get(&commitment, "retailer_one").unwrap();
+        assert!(triple.view("retailer_one").get("disclosed_to").is_none());
+        assert!(triple.view("processor_one")["disclosed_to"]
+            .get("retailer_one")
+            .is_some());
+    }
+
+    #[test]
+    fn test_store_persists() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let mut store = PrivateTripleStore::open(data_dir.path()).unwrap();
+        let sealed = SealedTriple::seal_turtle(DATA).unwrap();
+        let commitment = sealed[1].commitment.clone();
+        store.insert("processor_one", 3, sealed.clone()).unwrap();
+        store
+            .disclose(&commitment, "processor_one", "retailer_one")
+            .unwrap();
+
+        let reopened = PrivateTripleStore::open(data_dir.path()).unwrap();
+        let triple = reopened.get(&commitment, "retailer_one").unwrap();
+        assert_eq!(triple.sealed, sealed[1]);
+        assert_eq!(triple.block_index, 3);
+        assert_eq!(reopened.visible_to("processor_one").len(), 2);
+    }
+}
@@ -0,0 +1,505 @@
+//! Named-graph visibility for SPARQL queries
+//!
+//! Each block's data lives in its own named graph. A graph written through the API is
+//! owned by the user who wrote it and is private to them until the owner shares it with
+//! other users or roles, e.g. a processor sharing a batch with the retailer it ships to.
+//! Graphs without an owner (genesis, chain metadata and data received from peers) stay
+//! visible to everyone. Auditors and admins see every graph.
+//!
+//! User-supplied SPARQL is confined by `GraphAccessStore::restrict`, which narrows the
+//! query's dataset to the graphs the caller may see before it reaches `store.query`.
+//!
+//! Ownership and sharing are kept in `graph_access.json` in the node data directory, or
+//! only in memory when the node has no persistent storage.
+
+use crate::error::WebError;
+use crate::web::models::ActorRole;
+use crate::web::openapi::api_model;
+use crate::web::permissions::participant_permissions;
+use chrono::{DateTime, Utc};
+use oxigraph::model::{GraphName, NamedOrBlankNode};
+use oxigraph::sparql::Query;
+use oxigraph::store::Store;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name of the policy inside the data directory
+const POLICY_FILE: &str = "graph_access.json";
+
+api_model! {
+    /// Owner of a named graph and who it is shared with
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct GraphAccess {
+        pub graph: String,
+        pub owner: String,
+        pub shared_with_users: BTreeSet<String>,
+        pub shared_with_roles: BTreeSet<String>,
+        pub updated_at: DateTime<Utc>,
+    }
+}
+
+impl GraphAccess {
+    /// Whether `user` with `role` may query the graph
+    pub fn can_read(&self, user: &str, role: &str) -> bool {
+        self.can_modify(user, role)
+            || self.shared_with_users.contains(user)
+            || self.shared_with_roles.contains(role)
+    }
+
+    /// Whether `user` with `role` may change who the graph is shared with
+    pub fn can_modify(&self, user: &str, role: &str) -> bool {
+        self.owner == user || role == ActorRole::Admin.to_string()
+    }
+}
+
+/// Ownership and sharing of the named graphs in the RDF store
+#[derive(Debug, Default)]
+pub struct GraphAccessStore {
+    graphs: BTreeMap<String, GraphAccess>,
+    /// Policy file; `None` keeps the policy in memory only
+    path: Option<PathBuf>,
+}
+
+impl GraphAccessStore {
+    /// Policy that is not persisted
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open the policy kept in `data_dir`, starting empty if it does not exist yet
+    pub fn open<P: AsRef<Path>>(data_dir: P) -> anyhow::Result<Self> {
+        let path = data_dir.as_ref().join(POLICY_FILE);
+        let graphs = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            graphs,
+            path: Some(path),
+        })
+    }
+
+    /// Access record of a graph; `None` means the graph is visible to everyone
+    pub fn get(&self, graph: &str) -> Option<&GraphAccess> {
+        self.graphs.get(graph)
+    }
+
+    /// Make `owner` the owner of a graph it has just written, private to them
+    pub fn record_owner(&mut self, graph: &str, owner: &str) -> Result<(), WebError> {
+        self.graphs.insert(
+            graph.to_string(),
+            GraphAccess {
+                graph: graph.to_string(),
+                owner: owner.to_string(),
+                shared_with_users: BTreeSet::new(),
+                shared_with_roles: BTreeSet::new(),
+                updated_at: Utc::now(),
+            },
+        );
+        self.persist()
+    }
+
+    /// Drop the access record of a graph, e.g. one reserved for a block that was not added
+    pub fn remove(&mut self, graph: &str) -> Result<(), WebError> {
+        if self.graphs.remove(graph).is_some() {
+            self.persist()?;
+        }
+        Ok(())
+    }
+
+    /// Replace who an owned graph is shared with
+    pub fn share(
+        &mut self,
+        graph: &str,
+        user: &str,
+        role: &str,
+        users: &[String],
+        roles: &[String],
+    ) -> Result<&GraphAccess, WebError> {
+        let roles = validate_roles(roles)?;
+        let access = self.graphs.get_mut(graph).ok_or_else(|| {
+            WebError::BadRequest(format!(
+                "Graph {} has no owner and is visible to everyone",
+                graph
+            ))
+        })?;
+        if !access.can_modify(user, role) {
+            return Err(WebError::AuthorizationFailed(format!(
+                "Only the owner of {} or an admin can share it",
+                graph
+            )));
+        }
+        access.shared_with_users = users
+            .iter()
+            .map(|user| user.trim().to_string())
+            .filter(|user| !user.is_empty())
+            .collect();
+        access.shared_with_roles = roles;
+        access.updated_at = Utc::now();
+
+        self.persist()?;
+        Ok(&self.graphs[graph])
+    }
+
+    /// Whether anyone may read `graph`: nobody owns it
+    pub fn is_public(&self, graph: &str) -> bool {
+        !self.graphs.contains_key(graph)
+    }
+
+    /// Whether `user` with `role` may query a graph
+    pub fn can_read(&self, graph: &str, user: &str, role: &str) -> bool {
+        sees_all(role)
+            || self
+                .graphs
+                .get(graph)
+                .is_none_or(|access| access.can_read(user, role))
+    }
+
+    /// Narrow the dataset of `query` to the graphs of `store` that `user` may see.
+    ///
+    /// Graphs the query names explicitly (`FROM`, `FROM NAMED` or protocol parameters)
+    /// are kept only if visible, and a default graph that is the union of all graphs
+    /// becomes the union of the visible ones. Named graphs are limited the same way, so
+    /// `GRAPH ?g` patterns cannot reach hidden graphs either.
+    pub fn restrict(
+        &self,
+        query: &mut Query,
+        store: &Store,
+        user: &str,
+        role: &str,
+    ) -> Result<(), WebError> {
+        let Some(visible) = self.visible_graphs(store, user, role)? else {
+            return Ok(());
+        };
+
+        let dataset = query.dataset_mut();
+        let default_graph = match dataset.default_graph_graphs() {
+            Some(graphs) => graphs
+                .iter()
+                .filter(|graph| match graph {
+                    GraphName::NamedNode(node) => self.can_read(node.as_str(), user, role),
+                    _ => true,
+                })
+                .cloned()
+                .collect(),
+            None => std::iter::once(GraphName::DefaultGraph)
+                .chain(visible.iter().cloned().map(GraphName::from))
+                .collect(),
+        };
+        let named_graphs = match dataset.available_named_graphs() {
+            Some(graphs) => {
+                let visible: HashSet<&NamedOrBlankNode> = visible.iter().collect();
+                graphs
+                    .iter()
+                    .filter(|graph| visible.contains(graph))
+                    .cloned()
+                    .collect()
+            }
+            None => visible,
+        };
+        dataset.set_default_graph(default_graph);
+        dataset.set_available_named_graphs(named_graphs);
+        Ok(())
+    }
+
+    /// Named graphs of `store` that `user` may see, or `None` when nothing is hidden
+    pub fn visible_graphs(
+        &self,
+        store: &Store,
+        user: &str,
+        role: &str,
+    ) -> Result<Option<Vec<NamedOrBlankNode>>, WebError> {
+        if sees_all(role) || self.graphs.is_empty() {
+            return Ok(None);
+        }
+
+        let mut visible = Vec::new();
+        for graph in store.named_graphs() {
+            let graph = graph.map_err(|e| {
+                WebError::ServerError(format!("Failed to list named graphs: {}", e))
+            })?;
+            if self.is_visible(&graph, user, role) {
+                visible.push(graph);
+            }
+        }
+        Ok(Some(visible))
+    }
+
+    /// Copy of `store` with only the default graph and the named graphs `user` may see,
+    /// or `None` when nothing is hidden. For code that walks the store itself rather than
+    /// running a query `restrict` can narrow, such as the knowledge-graph builder.
+    pub fn visible_store(
+        &self,
+        store: &Store,
+        user: &str,
+        role: &str,
+    ) -> Result<Option<Store>, WebError> {
+        let Some(visible) = self.visible_graphs(store, user, role)? else {
+            return Ok(None);
+        };
+
+        let copy_error = |e: &dyn std::fmt::Display| {
+            WebError::ServerError(format!("Failed to copy visible graphs: {}", e))
+        };
+        let copy = Store::new().map_err(|e| copy_error(&e))?;
+        let graphs = std::iter::once(GraphName::DefaultGraph)
+            .chain(visible.into_iter().map(GraphName::from));
+        for graph in graphs {
+            for quad in store.quads_for_pattern(None, None, None, Some(graph.as_ref())) {
+                let quad = quad.map_err(|e| copy_error(&e))?;
+                copy.insert(&quad).map_err(|e| copy_error(&e))?;
+            }
+        }
+        Ok(Some(copy))
+    }
+
+    fn is_visible(&self, graph: &NamedOrBlankNode, user: &str, role: &str) -> bool {
+        match graph {
+            NamedOrBlankNode::NamedNode(node) => self.can_read(node.as_str(), user, role),
+            NamedOrBlankNode::BlankNode(_) => true,
+        }
+    }
+
+    fn persist(&self) -> Result<(), WebError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let write = || -> anyhow::Result<()> {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let temp = path.with_extension("json.tmp");
+            fs::write(&temp, serde_json::to_vec_pretty(&self.graphs)?)?;
+            fs::rename(&temp, path)?;
+            Ok(())
+        };
+        write().map_err(|e| WebError::ServerError(format!("Failed to save graph access: {}", e)))
+    }
+}
+
+/// Roles that can audit the chain see every graph
+fn sees_all(role: &str) -> bool {
+    role.parse::<ActorRole>()
+        .is_ok_and(|role| participant_permissions(&role).can_audit)
+}
+
+fn validate_roles(roles: &[String]) -> Result<BTreeSet<String>, WebError> {
+    roles
+        .iter()
+        .map(|role| {
+            role.trim()
+                .parse::<ActorRole>()
+                .map(|role| role.to_string())
+                .map_err(WebError::BadRequest)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxigraph::io::RdfFormat;
+    use oxigraph::sparql::QueryResults;
+
+    const DATA: &str = r#"
+        <http://example.org/genesis> <http://example.org/label> "genesis" <http://provchain.org/block/0> .
+        <http://example.org/batch1> <http://example.org/label> "farm batch" <http://provchain.org/block/1> .
+        <http://example.org/batch2> <http://example.org/label> "factory batch" <http://provchain.org/block/2> .
+    "#;
+
+    fn store() -> Store {
+        let store = Store::new().unwrap();
+        store
+            .load_from_reader(RdfFormat::NQuads, DATA.as_bytes())
+            .unwrap();
+        store
+    }
+
+    fn policy() -> GraphAccessStore {
+        let mut policy = GraphAccessStore::new();
+        policy
+            .record_owner("http://provchain.org/block/1", "farmer_one")
+            .unwrap();
+        policy
+            .record_owner("http://provchain.org/block/2", "processor_one")
+            .unwrap();
+        policy
+    }
+
+    /// Labels `user` gets back from `query`
+    fn labels(policy: &GraphAccessStore, query: &str, user: &str, role: &str) -> Vec<String> {
+        let store = store();
+        let mut query = Query::parse(query, None).unwrap();
+        crate::web::sparql::default_to_union(&mut query);
+        policy.restrict(&mut query, &store, user, role).unwrap();
+        let QueryResults::Solutions(solutions) = store.query(query).unwrap() else {
+            panic!("expected solutions");
+        };
+        let mut labels: Vec<String> = solutions
+            .map(|solution| match solution.unwrap().get("label") {
+                Some(oxigraph::model::Term::Literal(label)) => label.value().to_string(),
+                other => panic!("unexpected binding {:?}", other),
+            })
+            .collect();
+        labels.sort();
+        labels
+    }
+
+    #[test]
+    fn test_owners_see_their_graphs_and_unowned_ones() {
+        let policy = policy();
+        let query = "SELECT ?label WHERE { ?s <http://example.org/label> ?label }";
+
+        assert_eq!(
+            labels(&policy, query, "farmer_one", "farmer"),
+            vec!["farm batch", "genesis"]
+        );
+        assert_eq!(
+            labels(&policy, query, "retailer_one", "retailer"),
+            vec!["genesis"]
+        );
+        // Auditors and admins are not restricted
+        assert_eq!(labels(&policy, query, "auditor_one", "auditor").len(), 3);
+        assert_eq!(labels(&policy, query, "root", "admin").len(), 3);
+    }
+
+    #[test]
+    fn test_explicit_datasets_cannot_reach_hidden_graphs() {
+        let policy = policy();
+
+        let from = "SELECT ?label FROM <http://provchain.org/block/2> \
+                    WHERE { ?s <http://example.org/label> ?label }";
+        assert!(labels(&policy, from, "farmer_one", "farmer").is_empty());
+        assert_eq!(
+            labels(&policy, from, "processor_one", "processor"),
+            vec!["factory batch"]
+        );
+
+        let graph_pattern =
+            "SELECT ?label WHERE { GRAPH ?g { ?s <http://example.org/label> ?label } }";
+        assert_eq!(
+            labels(&policy, graph_pattern, "farmer_one", "farmer"),
+            vec!["farm batch", "genesis"]
+        );
+        let from_named = "SELECT ?label FROM NAMED <http://provchain.org/block/2> \
+                          WHERE { GRAPH ?g { ?s <http://example.org/label> ?label } }";
+        assert!(labels(&policy, from_named, "farmer_one", "farmer").is_empty());
+    }
+
+    #[test]
+    fn test_sharing_with_users_and_roles() {
+        let mut policy = policy();
+        let graph = "http://provchain.org/block/2";
+        let query = "SELECT ?label WHERE { ?s <http://example.org/label> ?label }";
+
+        // Only the owner or an admin can share
+        assert!(matches!(
+            policy.share(graph, "farmer_one", "farmer", &[], &["farmer".to_string()]),
+            Err(WebError::AuthorizationFailed(_))
+        ));
+        assert!(matches!(
+            policy.share("http://provchain.org/block/0", "root", "admin", &[], &[]),
+            Err(WebError::BadRequest(_))
+        ));
+        assert!(policy
+            .share(
+                graph,
+                "processor_one",
+                "processor",
+                &[],
+                &["grocer".to_string()]
+            )
+            .is_err());
+
+        policy
+            .share(
+                graph,
+                "processor_one",
+                "processor",
+                &["retailer_one".to_string()],
+                &[],
+            )
+            .unwrap();
+        assert_eq!(
+            labels(&policy, query, "retailer_one", "retailer"),
+            vec!["factory batch", "genesis"]
+        );
+        assert_eq!(
+            labels(&policy, query, "retailer_two", "retailer"),
+            vec!["genesis"]
+        );
+
+        policy
+            .share(graph, "root", "admin", &[], &["Retailer".to_string()])
+            .unwrap();
+        assert_eq!(
+            labels(&policy, query, "retailer_two", "retailer"),
+            vec!["factory batch", "genesis"]
+        );
+        assert_eq!(
+            labels(&policy, query, "retailer_one", "retailer"),
+            vec!["factory batch", "genesis"]
+        );
+    }
+
+    #[test]
+    fn test_visible_store_drops_hidden_graphs() {
+        let policy = policy();
+        let store = store();
+
+        let copy = policy
+            .visible_store(&store, "farmer_one", "farmer")
+            .unwrap()
+            .unwrap();
+        let graphs: BTreeSet<String> = copy
+            .named_graphs()
+            .map(|graph| graph.unwrap().to_string())
+            .collect();
+        assert_eq!(
+            graphs,
+            BTreeSet::from([
+                "<http://provchain.org/block/0>".to_string(),
+                "<http://provchain.org/block/1>".to_string(),
+            ])
+        );
+        assert!(policy
+            .visible_store(&store, "auditor_one", "auditor")
+            .unwrap()
+            .is_none());
+
+        // A graph reserved for a block that was never added is forgotten again
+        let mut policy = policy;
+        policy.remove("http://provchain.org/block/2").unwrap();
+        assert!(policy.is_public("http://provchain.org/block/2"));
+    }
+
+    #[test]
+    fn test_policy_persists() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let mut policy = GraphAccessStore::open(data_dir.path()).unwrap();
+        policy
+            .record_owner("http://provchain.org/block/1", "farmer_one")
+            .unwrap();
+        policy
+            .share(
+                "http://provchain.org/block/1",
+                "farmer_one",
+                "farmer",
+                &[],
+                &["processor".to_string()],
+            )
+            .unwrap();
+
+        let reopened = GraphAccessStore::open(data_dir.path()).unwrap();
+        assert_eq!(
+            reopened.get("http://provchain.org/block/1"),
+            policy.get("http://provchain.org/block/1")
+        );
+        assert!(reopened.can_read("http://provchain.org/block/1", "processor_one", "processor"));
+        assert!(!reopened.can_read("http://provchain.org/block/1", "retailer_one", "retailer"));
+    }
+}
@@ -0,0 +1,1790 @@
+//! GraphQL API over the provenance graph
+//!
+//! The schema is generated from the loaded ontology rather than written by hand. Every
+//! `owl:Class` becomes an object type with an `id`, a `label` and one field per
+//! datatype or object property whose `rdfs:domain` is the class or one of its
+//! ancestors. Classes descending from `prov:Entity`, `prov:Activity` or `prov:Agent`
+//! also get the PROV-O relations between them, so a batch can be fetched together with
+//! its activities, their agents and the agents' certificates in one nested query:
+//!
+//! ```graphql
+//! {
+//!   productBatch(id: "http://provchain.org/trace#uhtMilk001") {
+//!     label
+//!     activities {
+//!       nodes {
+//!         __typename
+//!         agents {
+//!           nodes { label ... on Organization { hasCertificate { nodes { label } } } }
+//!         }
+//!       }
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! A class with subclasses becomes an interface its subclasses implement, so fragments
+//! on subtypes apply to the nodes of a list; nodes of the class itself that are none of
+//! its subtypes are `<Class>Instance` objects.
+//!
+//! The root `Query` type has a lookup by IRI and a paginated list per class, e.g.
+//! `productBatch(id:)` and `productBatches(first:, after:, <scalar field>:)`. Every list
+//! is a connection with `totalCount`, `nodes` and `pageInfo`; cursors are opaque, and
+//! root lists are paged in SPARQL rather than in memory.
+//!
+//! Ontology terms are matched in their own namespace and, for ProvChain ontologies, in
+//! the `trace:` vocabulary chain data is written in, so `trace:ProductBatch` instances
+//! are `ProductBatch`s of the UHT ontology. Resolvers only read the named graphs the
+//! caller may see (see `GraphAccessStore::visible_graphs`). The schema is an
+//! `async-graphql` dynamic schema, so queries are validated and introspection works as
+//! with any GraphQL server; there are no mutations or subscriptions.
+
+use crate::core::blockchain::Blockchain;
+use crate::error::WebError;
+use crate::ontology::OntologyManager;
+use async_graphql::dynamic::{
+    Field, FieldFuture, FieldValue, InputValue, Interface, InterfaceField, Object, ObjectAccessor,
+    ResolverContext, Schema, SchemaBuilder, TypeRef,
+};
+use async_graphql::{Error as GraphQlError, Request, Value as GraphQlValue, Variables};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use oxigraph::model::{Literal, NamedNode, NamedOrBlankNode, Term};
+use oxigraph::sparql::{Query, QueryResults, QuerySolution};
+use oxigraph::store::Store;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::{self, Write};
+use std::sync::{Arc, Mutex, PoisonError};
+use tracing::warn;
+
+const PROV: &str = "http://www.w3.org/ns/prov#";
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const RDFS_LABEL: &str = "http://www.w3.org/2000/01/rdf-schema#label";
+const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+/// Namespace of the ProvChain ontologies
+const PROVCHAIN_NAMESPACE: &str = "http://provchain.org/";
+
+/// Vocabulary the chain data is written in
+const DATA_NAMESPACE: &str = "http://provchain.org/trace#";
+
+/// Page size of lists queried without `first`
+const DEFAULT_PAGE_SIZE: usize = 20;
+
+/// Largest page a list can be asked for
+const MAX_PAGE_SIZE: usize = 100;
+
+/// Deepest nesting of objects in a query
+const MAX_DEPTH: usize = 10;
+
+/// Most graph nodes a single query may read
+const MAX_NODES: usize = 5_000;
+
+/// Type names the schema defines itself
+const RESERVED_TYPES: &[&str] = &[
+    "Query", "PageInfo", "ID", "String", "Int", "Float", "Boolean",
+];
+
+/// PROV-O classes every schema has, with their descriptions
+const PROV_ROOTS: &[(&str, &str)] = &[
+    (
+        "Entity",
+        "A physical, digital or conceptual thing with fixed aspects",
+    ),
+    (
+        "Activity",
+        "Something that occurs over a period of time and acts upon entities",
+    ),
+    (
+        "Agent",
+        "Something that bears responsibility for an activity or entity",
+    ),
+];
+
+/// A PROV-O relation exposed on the types descending from a PROV class
+struct ProvRelation {
+    field: &'static str,
+    /// Type of the related nodes
+    target: &'static str,
+    /// PROV predicates followed, and whether each is followed backwards
+    links: &'static [(&'static str, bool)],
+    description: &'static str,
+}
+
+/// PROV-O relations of each PROV class
+const PROV_RELATIONS: &[(&str, &[ProvRelation])] = &[
+    (
+        "Entity",
+        &[
+            ProvRelation {
+                field: "activities",
+                target: "Activity",
+                links: &[("wasGeneratedBy", false), ("used", true)],
+                description: "Activities that generated or used the entity",
+            },
+            ProvRelation {
+                field: "agents",
+                target: "Agent",
+                links: &[("wasAttributedTo", false)],
+                description: "Agents the entity is attributed to",
+            },
+            ProvRelation {
+                field: "wasGeneratedBy",
+                target: "Activity",
+                links: &[("wasGeneratedBy", false)],
+                description: "Activities that generated the entity",
+            },
+            ProvRelation {
+                field: "wasDerivedFrom",
+                target: "Entity",
+                links: &[("wasDerivedFrom", false)],
+                description: "Entities the entity was derived from",
+            },
+        ],
+    ),
+    (
+        "Activity",
+        &[
+            ProvRelation {
+                field: "used",
+                target: "Entity",
+                links: &[("used", false)],
+                description: "Entities the activity used",
+            },
+            ProvRelation {
+                field: "generated",
+                target: "Entity",
+                links: &[("wasGeneratedBy", true)],
+                description: "Entities the activity generated",
+            },
+            ProvRelation {
+                field: "agents",
+                target: "Agent",
+                links: &[("wasAssociatedWith", false)],
+                description: "Agents associated with the activity",
+            },
+            ProvRelation {
+                field: "wasInformedBy",
+                target: "Activity",
+                links: &[("wasInformedBy", false)],
+                description: "Activities the activity was informed by",
+            },
+        ],
+    ),
+    (
+        "Agent",
+        &[
+            ProvRelation {
+                field: "activities",
+                target: "Activity",
+                links: &[("wasAssociatedWith", true)],
+                description: "Activities the agent was associated with",
+            },
+            ProvRelation {
+                field: "attributed",
+                target: "Entity",
+                links: &[("wasAttributedTo", true)],
+                description: "Entities attributed to the agent",
+            },
+            ProvRelation {
+                field: "actedOnBehalfOf",
+                target: "Agent",
+                links: &[("actedOnBehalfOf", false)],
+                description: "Agents the agent acted on behalf of",
+            },
+        ],
+    ),
+];
+
+const CLASS_QUERY: &str = r#"
+    PREFIX owl: <http://www.w3.org/2002/07/owl#>
+    PREFIX rdfs: <http://www.w3.org/2000/01/rdf-schema#>
+    SELECT ?class ?label ?comment ?parent WHERE {
+        ?class a owl:Class .
+        FILTER(isIRI(?class))
+        OPTIONAL { ?class rdfs:label ?label }
+        OPTIONAL { ?class rdfs:comment ?comment }
+        OPTIONAL { ?class rdfs:subClassOf ?parent FILTER(isIRI(?parent)) }
+    }
+"#;
+
+const PROPERTY_QUERY: &str = r#"
+    PREFIX owl: <http://www.w3.org/2002/07/owl#>
+    PREFIX rdfs: <http://www.w3.org/2000/01/rdf-schema#>
+    SELECT ?property ?kind ?domain ?range ?comment ?parent WHERE {
+        VALUES ?kind { owl:ObjectProperty owl:DatatypeProperty }
+        ?property a ?kind .
+        FILTER(isIRI(?property))
+        OPTIONAL { ?property rdfs:domain ?domain FILTER(isIRI(?domain)) }
+        OPTIONAL { ?property rdfs:range ?range FILTER(isIRI(?range)) }
+        OPTIONAL { ?property rdfs:comment ?comment }
+        OPTIONAL { ?property rdfs:subPropertyOf ?parent FILTER(isIRI(?parent)) }
+    }
+"#;
+
+type Outcome<T> = Result<T, GraphQlError>;
+
+// ---------------------------------------------------------------------------
+// Schema
+// ---------------------------------------------------------------------------
+
+/// Built-in scalar types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalarType {
+    String,
+    Int,
+    Float,
+    Boolean,
+}
+
+impl ScalarType {
+    /// Scalar for an `rdfs:range` datatype
+    fn from_range(range: &str) -> Self {
+        match range.strip_prefix(XSD).unwrap_or_default() {
+            "integer" | "int" | "long" | "short" | "byte" | "nonNegativeInteger"
+            | "positiveInteger" | "nonPositiveInteger" | "negativeInteger" | "unsignedInt"
+            | "unsignedLong" | "unsignedShort" | "unsignedByte" => Self::Int,
+            "decimal" | "double" | "float" => Self::Float,
+            "boolean" => Self::Boolean,
+            _ => Self::String,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::String => "String",
+            Self::Int => "Int",
+            Self::Float => "Float",
+            Self::Boolean => "Boolean",
+        }
+    }
+
+    /// GraphQL value of an RDF term, `None` if it does not fit the scalar
+    fn value(self, term: &Term) -> Option<GraphQlValue> {
+        let text = match term {
+            Term::Literal(literal) => literal.value().to_string(),
+            Term::NamedNode(node) => node.as_str().to_string(),
+            other => other.to_string(),
+        };
+        match self {
+            Self::String => Some(GraphQlValue::String(text)),
+            Self::Int => text.parse::<i64>().ok().map(GraphQlValue::from),
+            Self::Float => text.parse::<f64>().ok().map(GraphQlValue::from),
+            Self::Boolean => match text.as_str() {
+                "true" | "1" => Some(GraphQlValue::Boolean(true)),
+                "false" | "0" => Some(GraphQlValue::Boolean(false)),
+                _ => None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum FieldKind {
+    /// IRI of the node
+    Id,
+    /// IRIs of the node's classes
+    Types,
+    /// First value of any of the predicates
+    Value {
+        predicates: Vec<String>,
+        scalar: ScalarType,
+    },
+    /// Nodes reached over any of the predicates, each followed backwards if flagged
+    Link {
+        links: Vec<(String, bool)>,
+        target: String,
+    },
+}
+
+/// A field of an object type
+#[derive(Debug, Clone)]
+pub struct FieldDef {
+    pub name: String,
+    pub description: Option<String>,
+    kind: FieldKind,
+}
+
+impl FieldDef {
+    /// GraphQL type of the field's values
+    fn type_ref(&self) -> TypeRef {
+        match &self.kind {
+            FieldKind::Id => TypeRef::named_nn(TypeRef::ID),
+            FieldKind::Types => TypeRef::named_nn_list_nn(TypeRef::STRING),
+            FieldKind::Value { scalar, .. } => TypeRef::named(scalar.name()),
+            FieldKind::Link { target, .. } => TypeRef::named_nn(connection_name(target)),
+        }
+    }
+}
+
+/// An object type generated from an ontology class
+#[derive(Debug, Clone)]
+pub struct ObjectType {
+    pub name: String,
+    pub description: Option<String>,
+    /// IRIs of the class and its subclasses, in both vocabularies
+    classes: HashSet<String>,
+    /// Number of ancestor classes, to find the most specific type of a node
+    depth: usize,
+    pub fields: Vec<FieldDef>,
+    /// Types of ancestor classes the type implements, those whose fields it shares
+    pub implements: BTreeSet<String>,
+}
+
+impl ObjectType {
+    fn field(&self, name: &str) -> Option<&FieldDef> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// Scalar fields lists of the type can be filtered on
+    fn filters(&self) -> impl Iterator<Item = &FieldDef> {
+        self.fields
+            .iter()
+            .filter(|field| matches!(field.kind, FieldKind::Value { .. }))
+    }
+
+    fn has_instance(&self, node: &Node) -> bool {
+        node.types.iter().any(|class| self.classes.contains(class))
+    }
+
+    /// Whether `ty`'s fields are fields of this type, with the same types
+    fn shares_fields(&self, ty: &ObjectType) -> bool {
+        ty.fields.iter().all(|field| {
+            self.field(&field.name)
+                .is_some_and(|own| own.type_ref() == field.type_ref())
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum RootField {
+    /// Lookup of a node of the type by IRI
+    One(String),
+    /// Paginated list of the nodes of the type
+    Many(String),
+}
+
+#[derive(Debug, Default)]
+struct ClassInfo {
+    label: Option<String>,
+    comment: Option<String>,
+    parents: BTreeSet<String>,
+}
+
+#[derive(Debug, Default)]
+struct PropertyInfo {
+    object: bool,
+    domains: BTreeSet<String>,
+    ranges: BTreeSet<String>,
+    comment: Option<String>,
+    parents: BTreeSet<String>,
+}
+
+/// Object types generated from an ontology, shared with the resolvers
+#[derive(Debug)]
+struct TypeIndex {
+    types: BTreeMap<String, ObjectType>,
+    /// Type generated from each class IRI, in both vocabularies
+    class_types: HashMap<String, String>,
+    /// Types implemented by other types, which are interfaces
+    interfaces: BTreeSet<String>,
+}
+
+impl TypeIndex {
+    /// Most specific type of `node` that is `ty` or implements it
+    fn type_of<'s>(&'s self, node: &Node, ty: &'s ObjectType) -> &'s ObjectType {
+        node.types
+            .iter()
+            .filter_map(|class| self.class_types.get(class))
+            .filter_map(|name| self.types.get(name))
+            .filter(|candidate| {
+                candidate.name == ty.name || candidate.implements.contains(&ty.name)
+            })
+            .max_by_key(|candidate| candidate.depth)
+            .unwrap_or(ty)
+    }
+
+    /// Object type the nodes of `ty` and none of its subtypes are resolved as
+    fn object_name(&self, ty: &ObjectType) -> String {
+        if self.interfaces.contains(&ty.name) {
+            instance_name(&ty.name)
+        } else {
+            ty.name.clone()
+        }
+    }
+
+    /// `resolved` as the value of a field of type `ty`
+    fn object_value(&self, resolved: Resolved, ty: &ObjectType) -> FieldValue<'static> {
+        if self.interfaces.contains(&ty.name) {
+            let object = self.object_name(self.type_of(&resolved.node, ty));
+            FieldValue::owned_any(resolved).with_type(object)
+        } else {
+            FieldValue::owned_any(resolved)
+        }
+    }
+}
+
+/// GraphQL schema generated from an ontology
+#[derive(Debug, Clone)]
+pub struct GraphQlSchema {
+    index: Arc<TypeIndex>,
+    schema: Schema,
+}
+
+impl GraphQlSchema {
+    /// Schema of the chain's domain ontology, or of the core ontology loaded into its
+    /// store when no domain ontology is configured
+    pub fn for_blockchain(blockchain: &Blockchain) -> Self {
+        let schema = match &blockchain.ontology_manager {
+            Some(manager) => Self::from_ontology(manager),
+            None => Self::from_store(&blockchain.rdf_store.store),
+        };
+        schema
+            .or_else(|e| {
+                warn!(
+                    "Failed to read the ontology, GraphQL serves PROV types only: {}",
+                    e
+                );
+                Self::build(BTreeMap::new(), BTreeMap::new())
+            })
+            .expect("the PROV-O schema is valid")
+    }
+
+    /// Schema of the core and domain ontologies loaded by `manager`
+    pub fn from_ontology(manager: &OntologyManager) -> Result<Self, WebError> {
+        Self::from_store(manager.ontology_store())
+    }
+
+    /// Schema of the ontology in the default graph of `store`
+    pub fn from_store(store: &Store) -> Result<Self, WebError> {
+        let mut classes: BTreeMap<String, ClassInfo> = BTreeMap::new();
+        for solution in ontology_solutions(store, CLASS_QUERY)? {
+            let Some(class) = iri(&solution, "class") else {
+                continue;
+            };
+            let info = classes.entry(class).or_default();
+            info.label = info.label.take().or_else(|| text(&solution, "label"));
+            info.comment = info.comment.take().or_else(|| text(&solution, "comment"));
+            info.parents.extend(iri(&solution, "parent"));
+        }
+
+        let mut properties: BTreeMap<String, PropertyInfo> = BTreeMap::new();
+        for solution in ontology_solutions(store, PROPERTY_QUERY)? {
+            let Some(property) = iri(&solution, "property") else {
+                continue;
+            };
+            let info = properties.entry(property).or_default();
+            info.object |=
+                iri(&solution, "kind").is_some_and(|kind| kind.ends_with("#ObjectProperty"));
+            info.domains.extend(iri(&solution, "domain"));
+            info.ranges.extend(iri(&solution, "range"));
+            info.comment = info.comment.take().or_else(|| text(&solution, "comment"));
+            info.parents.extend(iri(&solution, "parent"));
+        }
+
+        Self::build(classes, properties)
+    }
+
+    fn build(
+        mut classes: BTreeMap<String, ClassInfo>,
+        properties: BTreeMap<String, PropertyInfo>,
+    ) -> Result<Self, WebError> {
+        for (name, description) in PROV_ROOTS {
+            classes
+                .entry(format!("{}{}", PROV, name))
+                .or_default()
+                .comment
+                .get_or_insert_with(|| description.to_string());
+        }
+        let ancestors: BTreeMap<&str, BTreeSet<&str>> = classes
+            .keys()
+            .map(|class| (class.as_str(), ancestors(class, &classes)))
+            .collect();
+
+        // PROV classes are named first so relations can rely on their names
+        let mut names: BTreeMap<&str, String> = BTreeMap::new();
+        let prov_roots: Vec<String> = PROV_ROOTS
+            .iter()
+            .map(|(name, _)| format!("{}{}", PROV, name))
+            .collect();
+        for class in prov_roots.iter().chain(classes.keys()) {
+            let Some((class, _)) = classes.get_key_value(class) else {
+                continue;
+            };
+            let Some(name) = graphql_name(local_name(class)) else {
+                continue;
+            };
+            if names.contains_key(class.as_str())
+                || names.values().any(|taken| *taken == name)
+                || RESERVED_TYPES.contains(&name.as_str())
+                || name.ends_with("Connection")
+                || name.ends_with("Instance")
+            {
+                continue;
+            }
+            names.insert(class, name);
+        }
+
+        let mut types = BTreeMap::new();
+        let mut class_types = HashMap::new();
+        for (&class, name) in &names {
+            let info = &classes[class];
+            let mut lineage = ancestors[class].clone();
+            lineage.insert(class);
+
+            let mut fields = vec![
+                FieldDef {
+                    name: "id".to_string(),
+                    description: Some("IRI of the node".to_string()),
+                    kind: FieldKind::Id,
+                },
+                FieldDef {
+                    name: "label".to_string(),
+                    description: Some("Human-readable name".to_string()),
+                    kind: FieldKind::Value {
+                        predicates: vec![RDFS_LABEL.to_string(), format!("{}label", PROV)],
+                        scalar: ScalarType::String,
+                    },
+                },
+                FieldDef {
+                    name: "types".to_string(),
+                    description: Some("IRIs of the node's classes".to_string()),
+                    kind: FieldKind::Types,
+                },
+            ];
+            for (root, relations) in PROV_RELATIONS {
+                if !lineage.contains(format!("{}{}", PROV, root).as_str()) {
+                    continue;
+                }
+                for relation in relations.iter() {
+                    if fields.iter().any(|field| field.name == relation.field) {
+                        continue;
+                    }
+                    fields.push(FieldDef {
+                        name: relation.field.to_string(),
+                        description: Some(relation.description.to_string()),
+                        kind: FieldKind::Link {
+                            links: relation
+                                .links
+                                .iter()
+                                .map(|(predicate, inverse)| {
+                                    (format!("{}{}", PROV, predicate), *inverse)
+                                })
+                                .collect(),
+                            target: relation.target.to_string(),
+                        },
+                    });
+                }
+            }
+            for (property, info) in &properties {
+                if !info
+                    .domains
+                    .iter()
+                    .any(|domain| lineage.contains(domain.as_str()))
+                {
+                    continue;
+                }
+                let Some(field) = graphql_name(local_name(property)) else {
+                    continue;
+                };
+                if fields.iter().any(|existing| existing.name == field) {
+                    continue;
+                }
+                let predicates: Vec<String> = subproperties(property, &properties)
+                    .into_iter()
+                    .flat_map(vocabulary_iris)
+                    .collect();
+                let kind = if info.object {
+                    let Some(target) = info
+                        .ranges
+                        .iter()
+                        .find_map(|range| names.get(range.as_str()))
+                    else {
+                        continue;
+                    };
+                    FieldKind::Link {
+                        links: predicates
+                            .into_iter()
+                            .map(|predicate| (predicate, false))
+                            .collect(),
+                        target: target.clone(),
+                    }
+                } else {
+                    let scalar = info
+                        .ranges
+                        .first()
+                        .map_or(ScalarType::String, |range| ScalarType::from_range(range));
+                    FieldKind::Value { predicates, scalar }
+                };
+                fields.push(FieldDef {
+                    name: field,
+                    description: info.comment.clone(),
+                    kind,
+                });
+            }
+
+            let subclasses = ancestors
+                .iter()
+                .filter(|(_, lineage)| lineage.contains(class))
+                .map(|(subclass, _)| *subclass)
+                .chain(std::iter::once(class));
+            types.insert(
+                name.clone(),
+                ObjectType {
+                    name: name.clone(),
+                    description: info.comment.clone().or_else(|| info.label.clone()),
+                    classes: subclasses.flat_map(vocabulary_iris).collect(),
+                    depth: ancestors[class].len(),
+                    fields,
+                    implements: ancestors[class]
+                        .iter()
+                        .filter_map(|ancestor| names.get(ancestor).cloned())
+                        .collect(),
+                },
+            );
+            for iri in vocabulary_iris(class) {
+                class_types.entry(iri).or_insert_with(|| name.clone());
+            }
+        }
+
+        // A type implements its ancestors' types only if it shares their fields, as a
+        // property can give a subclass a field of the same name with another type
+        let implements: Vec<BTreeSet<String>> = types
+            .values()
+            .map(|ty| {
+                ty.implements
+                    .iter()
+                    .filter(|ancestor| ty.shares_fields(&types[*ancestor]))
+                    .cloned()
+                    .collect()
+            })
+            .collect();
+        for (ty, implements) in types.values_mut().zip(implements) {
+            ty.implements = implements;
+        }
+        let interfaces = types
+            .values()
+            .flat_map(|ty| ty.implements.iter().cloned())
+            .collect();
+
+        let mut roots = BTreeMap::new();
+        for name in types.keys() {
+            let one = lower_camel(name);
+            let many = plural(&one);
+            if roots.contains_key(&one) || roots.contains_key(&many) {
+                continue;
+            }
+            roots.insert(one, RootField::One(name.clone()));
+            roots.insert(many, RootField::Many(name.clone()));
+        }
+
+        let index = Arc::new(TypeIndex {
+            types,
+            class_types,
+            interfaces,
+        });
+        let mut query = Object::new("Query");
+        for (name, root) in &roots {
+            query = query.field(root_field(&index, name, root));
+        }
+        let mut builder = Schema::build("Query", None, None)
+            .register(query)
+            .register(page_info_type());
+        for ty in index.types.values() {
+            builder = register_type(builder, &index, ty);
+        }
+        let schema = builder
+            .finish()
+            .map_err(|e| WebError::ServerError(format!("Invalid GraphQL schema: {}", e)))?;
+
+        Ok(Self { index, schema })
+    }
+
+    /// Object type named `name`
+    pub fn object_type(&self, name: &str) -> Option<&ObjectType> {
+        self.index.types.get(name)
+    }
+
+    /// The schema in GraphQL SDL
+    pub fn sdl(&self) -> String {
+        self.schema.sdl()
+    }
+
+    /// Run a query against the named graphs of `store`, limited to `visible` when set.
+    ///
+    /// Returns a GraphQL response with `data` and any `errors`.
+    pub async fn execute(
+        &self,
+        store: &Store,
+        visible: Option<Vec<NamedOrBlankNode>>,
+        query: &str,
+        operation_name: Option<&str>,
+        variables: &Map<String, Value>,
+    ) -> Value {
+        let mut request = Request::new(query)
+            .variables(Variables::from_json(Value::Object(variables.clone())))
+            .data(ReadContext {
+                store: store.clone(),
+                visible,
+                nodes: Mutex::new(HashMap::new()),
+            });
+        if let Some(name) = operation_name {
+            request = request.operation_name(name);
+        }
+        let response = self.schema.execute(request).await;
+        serde_json::to_value(response)
+            .unwrap_or_else(|e| json!({ "errors": [{ "message": e.to_string() }] }))
+    }
+}
+
+/// IRIs a class or property of the ontology is matched by: its own and, for ProvChain
+/// ontologies, the same term in the data vocabulary
+fn vocabulary_iris(iri: &str) -> Vec<String> {
+    let mut iris = vec![iri.to_string()];
+    if iri.starts_with(PROVCHAIN_NAMESPACE) && !iri.starts_with(DATA_NAMESPACE) {
+        iris.push(format!("{}{}", DATA_NAMESPACE, local_name(iri)));
+    }
+    iris
+}
+
+fn ancestors<'c>(class: &str, classes: &'c BTreeMap<String, ClassInfo>) -> BTreeSet<&'c str> {
+    let mut ancestors = BTreeSet::new();
+    let mut pending: Vec<&str> = classes
+        .get(class)
+        .map(|info| info.parents.iter().map(String::as_str).collect())
+        .unwrap_or_default();
+    while let Some(parent) = pending.pop() {
+        let Some((parent, info)) = classes.get_key_value(parent) else {
+            continue;
+        };
+        if parent.as_str() != class && ancestors.insert(parent.as_str()) {
+            pending.extend(info.parents.iter().map(String::as_str));
+        }
+    }
+    ancestors
+}
+
+/// `property` and its transitive subproperties
+fn subproperties<'p>(
+    property: &'p str,
+    properties: &'p BTreeMap<String, PropertyInfo>,
+) -> BTreeSet<&'p str> {
+    let mut found = BTreeSet::from([property]);
+    let mut pending = vec![property];
+    while let Some(parent) = pending.pop() {
+        for (child, info) in properties {
+            if info.parents.contains(parent) && found.insert(child.as_str()) {
+                pending.push(child.as_str());
+            }
+        }
+    }
+    found
+}
+
+fn ontology_solutions(store: &Store, query: &str) -> Result<Vec<QuerySolution>, WebError> {
+    match store.query(query) {
+        Ok(QueryResults::Solutions(solutions)) => solutions
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| WebError::ServerError(format!("Failed to read the ontology: {}", e))),
+        Ok(_) => Ok(Vec::new()),
+        Err(e) => Err(WebError::ServerError(format!(
+            "Failed to read the ontology: {}",
+            e
+        ))),
+    }
+}
+
+fn iri(solution: &QuerySolution, variable: &str) -> Option<String> {
+    match solution.get(variable) {
+        Some(Term::NamedNode(node)) => Some(node.as_str().to_string()),
+        _ => None,
+    }
+}
+
+fn text(solution: &QuerySolution, variable: &str) -> Option<String> {
+    match solution.get(variable) {
+        Some(Term::Literal(literal)) => Some(literal.value().to_string()),
+        _ => None,
+    }
+}
+
+fn local_name(iri: &str) -> &str {
+    iri.rsplit(['#', '/']).next().unwrap_or(iri)
+}
+
+/// `name` if it is a valid GraphQL name that is not reserved for introspection
+fn graphql_name(name: &str) -> Option<String> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|first| first == '_' || first.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+        && !name.starts_with("__");
+    valid.then(|| name.to_string())
+}
+
+/// Field name of a type name, e.g. `UHTProcessing` to `uhtProcessing`
+fn lower_camel(name: &str) -> String {
+    let upper = name.chars().take_while(char::is_ascii_uppercase).count();
+    let lowered = if upper > 1 && upper < name.len() {
+        upper - 1
+    } else {
+        upper
+    };
+    name[..lowered].to_ascii_lowercase() + &name[lowered..]
+}
+
+fn plural(name: &str) -> String {
+    if ["s", "x", "z", "ch", "sh"]
+        .iter()
+        .any(|ending| name.ends_with(ending))
+    {
+        format!("{}es", name)
+    } else if let Some(stem) = name.strip_suffix('y').filter(|stem| {
+        stem.chars()
+            .last()
+            .is_some_and(|c| !"aeiou".contains(c.to_ascii_lowercase()))
+    }) {
+        format!("{}ies", stem)
+    } else {
+        format!("{}s", name)
+    }
+}
+
+fn connection_name(type_name: &str) -> String {
+    format!("{}Connection", type_name)
+}
+
+/// Object type of the nodes of the interface `type_name` that are none of its subtypes
+fn instance_name(type_name: &str) -> String {
+    format!("{}Instance", type_name)
+}
+
+// ---------------------------------------------------------------------------
+// Schema types
+// ---------------------------------------------------------------------------
+
+/// Register `ty` and its connection. A type with subtypes is an interface, with an
+/// object type for the nodes that are none of its subtypes.
+fn register_type(builder: SchemaBuilder, index: &Arc<TypeIndex>, ty: &ObjectType) -> SchemaBuilder {
+    let (builder, mut object) = if index.interfaces.contains(&ty.name) {
+        let mut interface = Interface::new(&ty.name);
+        if let Some(description) = &ty.description {
+            interface = interface.description(description);
+        }
+        for parent in &ty.implements {
+            interface = interface.implement(parent);
+        }
+        for field in &ty.fields {
+            interface = interface.field(interface_field(field));
+        }
+        let object = Object::new(instance_name(&ty.name))
+            .description(format!("A {} that is none of its subtypes", ty.name))
+            .implement(&ty.name);
+        (builder.register(interface), object)
+    } else {
+        let mut object = Object::new(&ty.name);
+        if let Some(description) = &ty.description {
+            object = object.description(description);
+        }
+        (builder, object)
+    };
+    for parent in &ty.implements {
+        object = object.implement(parent);
+    }
+    for field in &ty.fields {
+        object = object.field(object_field(index, field));
+    }
+    builder
+        .register(object)
+        .register(connection_type(index, ty))
+}
+
+/// `first` and `after` arguments of a list
+fn page_arguments() -> [InputValue; 2] {
+    [
+        InputValue::new("first", TypeRef::named(TypeRef::INT)),
+        InputValue::new("after", TypeRef::named(TypeRef::STRING)),
+    ]
+}
+
+fn interface_field(def: &FieldDef) -> InterfaceField {
+    let mut field = InterfaceField::new(&def.name, def.type_ref());
+    if let Some(description) = &def.description {
+        field = field.description(description);
+    }
+    if let FieldKind::Link { .. } = def.kind {
+        for argument in page_arguments() {
+            field = field.argument(argument);
+        }
+    }
+    field
+}
+
+fn object_field(index: &Arc<TypeIndex>, def: &FieldDef) -> Field {
+    let shared = index.clone();
+    let kind = def.kind.clone();
+    let mut field = Field::new(&def.name, def.type_ref(), move |ctx| {
+        ready(resolve_field(&shared, &kind, &ctx))
+    });
+    if let Some(description) = &def.description {
+        field = field.description(description);
+    }
+    if let FieldKind::Link { .. } = def.kind {
+        for argument in page_arguments() {
+            field = field.argument(argument);
+        }
+    }
+    field
+}
+
+fn connection_type(index: &Arc<TypeIndex>, ty: &ObjectType) -> Object {
+    let shared = index.clone();
+    Object::new(connection_name(&ty.name))
+        .field(Field::new(
+            "totalCount",
+            TypeRef::named_nn(TypeRef::INT),
+            |ctx| ready(resolve_total(&ctx)),
+        ))
+        .field(Field::new(
+            "nodes",
+            TypeRef::named_nn_list_nn(&ty.name),
+            move |ctx| ready(resolve_nodes(&shared, &ctx)),
+        ))
+        .field(Field::new(
+            "pageInfo",
+            TypeRef::named_nn("PageInfo"),
+            |ctx| {
+                ready(
+                    ctx.parent_value
+                        .try_downcast_ref::<Page>()
+                        .map(|page| Some(FieldValue::borrowed_any(page))),
+                )
+            },
+        ))
+}
+
+fn page_info_type() -> Object {
+    Object::new("PageInfo")
+        .field(Field::new(
+            "hasNextPage",
+            TypeRef::named_nn(TypeRef::BOOLEAN),
+            |ctx| ready(page_info(&ctx, |page| Some(page.has_next_page.into()))),
+        ))
+        .field(Field::new(
+            "hasPreviousPage",
+            TypeRef::named_nn(TypeRef::BOOLEAN),
+            |ctx| ready(page_info(&ctx, |page| Some((page.offset > 0).into()))),
+        ))
+        .field(Field::new(
+            "endCursor",
+            TypeRef::named(TypeRef::STRING),
+            |ctx| ready(page_info(&ctx, |page| page.end_cursor().map(Into::into))),
+        ))
+}
+
+fn root_field(index: &Arc<TypeIndex>, name: &str, root: &RootField) -> Field {
+    let shared = index.clone();
+    match root {
+        RootField::One(ty) => {
+            let type_name = ty.clone();
+            Field::new(name, TypeRef::named(ty), move |ctx| {
+                ready(resolve_one(&shared, &shared.types[&type_name], &ctx))
+            })
+            .argument(InputValue::new("id", TypeRef::named_nn(TypeRef::ID)))
+        }
+        RootField::Many(ty) => {
+            let type_name = ty.clone();
+            let mut field = Field::new(name, TypeRef::named_nn(connection_name(ty)), move |ctx| {
+                ready(resolve_many(&shared.types[&type_name], &ctx))
+            });
+            for argument in page_arguments() {
+                field = field.argument(argument);
+            }
+            for filter in index.types[ty].filters() {
+                field = field.argument(InputValue::new(&filter.name, filter.type_ref()));
+            }
+            field
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Resolvers
+// ---------------------------------------------------------------------------
+
+/// Triples of one node in the visible graphs
+#[derive(Debug, Default)]
+struct Node {
+    types: BTreeSet<String>,
+    outgoing: HashMap<String, Vec<Term>>,
+    /// Subjects pointing at the node, by predicate
+    incoming: HashMap<String, BTreeSet<String>>,
+}
+
+impl Node {
+    fn value(&self, predicates: &[String]) -> Option<&Term> {
+        predicates
+            .iter()
+            .filter_map(|predicate| self.outgoing.get(predicate))
+            .flatten()
+            .min_by_key(|term| term.to_string())
+    }
+
+    /// IRIs of the nodes reached over `links`, in IRI order
+    fn linked(&self, links: &[(String, bool)]) -> Vec<String> {
+        let mut linked = BTreeSet::new();
+        for (predicate, inverse) in links {
+            if *inverse {
+                linked.extend(self.incoming.get(predicate).into_iter().flatten().cloned());
+            } else {
+                linked.extend(
+                    self.outgoing
+                        .get(predicate)
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|term| match term {
+                            Term::NamedNode(node) => Some(node.as_str().to_string()),
+                            _ => None,
+                        }),
+                );
+            }
+        }
+        linked.into_iter().collect()
+    }
+}
+
+/// A node resolved as an object
+struct Resolved {
+    iri: String,
+    node: Arc<Node>,
+    /// Nesting of the object in the query
+    depth: usize,
+}
+
+/// Number of items in a whole list
+enum Total {
+    Known(usize),
+    /// Counted over the instance pattern of a root list when selected
+    Counted(String),
+}
+
+/// One page of a list, resolved as a connection
+struct Page {
+    /// Type of the listed nodes
+    ty: String,
+    items: Vec<String>,
+    /// Position of the first item in the whole list
+    offset: usize,
+    has_next_page: bool,
+    total: Total,
+    /// Nesting of the listed objects in the query
+    depth: usize,
+}
+
+impl Page {
+    /// Page of `items` asked for by the `first` and `after` arguments
+    fn of(
+        ty: &ObjectType,
+        items: Vec<String>,
+        arguments: &ObjectAccessor<'_>,
+        depth: usize,
+    ) -> Outcome<Self> {
+        let (first, offset) = page_window(arguments)?;
+        let total = items.len();
+        Ok(Self {
+            ty: ty.name.clone(),
+            items: items.into_iter().skip(offset).take(first).collect(),
+            offset,
+            has_next_page: offset.saturating_add(first) < total,
+            total: Total::Known(total),
+            depth,
+        })
+    }
+
+    fn end_cursor(&self) -> Option<String> {
+        (!self.items.is_empty()).then(|| encode_cursor(self.offset + self.items.len() - 1))
+    }
+}
+
+/// Page size and offset asked for by the `first` and `after` arguments
+fn page_window(arguments: &ObjectAccessor<'_>) -> Outcome<(usize, usize)> {
+    let first = match arguments.get("first").filter(|first| !first.is_null()) {
+        None => DEFAULT_PAGE_SIZE,
+        Some(first) => first
+            .u64()
+            .ok()
+            .filter(|first| *first as usize <= MAX_PAGE_SIZE)
+            .ok_or_else(|| {
+                GraphQlError::new(format!("'first' must be between 0 and {}", MAX_PAGE_SIZE))
+            })? as usize,
+    };
+    let offset = match arguments.get("after").filter(|after| !after.is_null()) {
+        None => 0,
+        Some(cursor) => cursor
+            .string()
+            .ok()
+            .and_then(decode_cursor)
+            .ok_or_else(|| GraphQlError::new("'after' is not a valid cursor"))?
+            .saturating_add(1),
+    };
+    Ok((first, offset))
+}
+
+/// Resolvers run synchronously against the store
+fn ready(result: Outcome<Option<FieldValue<'_>>>) -> FieldFuture<'_> {
+    FieldFuture::new(std::future::ready(result))
+}
+
+fn resolve_one<'a>(
+    index: &TypeIndex,
+    ty: &ObjectType,
+    ctx: &ResolverContext<'a>,
+) -> Outcome<Option<FieldValue<'a>>> {
+    let id = ctx.args.try_get("id")?.string()?;
+    let node = ctx.data::<ReadContext>()?.node(id)?;
+    if !ty.has_instance(&node) {
+        return Ok(None);
+    }
+    let resolved = Resolved {
+        iri: id.to_string(),
+        node,
+        depth: 1,
+    };
+    Ok(Some(index.object_value(resolved, ty)))
+}
+
+fn resolve_many<'a>(ty: &ObjectType, ctx: &ResolverContext<'a>) -> Outcome<Option<FieldValue<'a>>> {
+    let (first, offset) = page_window(&ctx.args)?;
+    let pattern = instance_pattern(ty, &ctx.args)?;
+    // One item past the page tells whether there is a next page
+    let mut items = ctx
+        .data::<ReadContext>()?
+        .instances(&pattern, first + 1, offset)?;
+    let has_next_page = items.len() > first;
+    items.truncate(first);
+    Ok(Some(FieldValue::owned_any(Page {
+        ty: ty.name.clone(),
+        items,
+        offset,
+        has_next_page,
+        total: Total::Counted(pattern),
+        depth: 1,
+    })))
+}
+
+fn resolve_field<'a>(
+    index: &TypeIndex,
+    kind: &FieldKind,
+    ctx: &ResolverContext<'a>,
+) -> Outcome<Option<FieldValue<'a>>> {
+    let resolved = ctx.parent_value.try_downcast_ref::<Resolved>()?;
+    let node = &resolved.node;
+    Ok(match kind {
+        FieldKind::Id => Some(FieldValue::value(resolved.iri.clone())),
+        FieldKind::Types => Some(FieldValue::value(
+            node.types.iter().cloned().collect::<Vec<_>>(),
+        )),
+        FieldKind::Value { predicates, scalar } => node
+            .value(predicates)
+            .and_then(|term| scalar.value(term))
+            .map(FieldValue::value),
+        FieldKind::Link { links, target } => {
+            let target = &index.types[target];
+            let page = Page::of(target, node.linked(links), &ctx.args, resolved.depth + 1)?;
+            Some(FieldValue::owned_any(page))
+        }
+    })
+}
+
+fn resolve_total<'a>(ctx: &ResolverContext<'a>) -> Outcome<Option<FieldValue<'a>>> {
+    let page = ctx.parent_value.try_downcast_ref::<Page>()?;
+    let total = match &page.total {
+        Total::Known(total) => *total,
+        Total::Counted(pattern) => ctx.data::<ReadContext>()?.count(pattern)?,
+    };
+    Ok(Some(FieldValue::value(total)))
+}
+
+fn resolve_nodes<'a>(
+    index: &TypeIndex,
+    ctx: &ResolverContext<'a>,
+) -> Outcome<Option<FieldValue<'a>>> {
+    let page = ctx.parent_value.try_downcast_ref::<Page>()?;
+    if page.depth > MAX_DEPTH {
+        return Err(GraphQlError::new(format!(
+            "The query nests deeper than {} objects",
+            MAX_DEPTH
+        )));
+    }
+    let read = ctx.data::<ReadContext>()?;
+    let ty = &index.types[&page.ty];
+    let mut nodes = Vec::new();
+    for iri in &page.items {
+        let resolved = Resolved {
+            iri: iri.clone(),
+            node: read.node(iri)?,
+            depth: page.depth,
+        };
+        nodes.push(index.object_value(resolved, ty));
+    }
+    Ok(Some(FieldValue::list(nodes)))
+}
+
+/// Field of a `PageInfo`, read from its page
+fn page_info<'a>(
+    ctx: &ResolverContext<'a>,
+    value: impl Fn(&Page) -> Option<GraphQlValue>,
+) -> Outcome<Option<FieldValue<'a>>> {
+    let page = ctx.parent_value.try_downcast_ref::<Page>()?;
+    Ok(value(page).map(FieldValue::value))
+}
+
+/// Graph pattern of the instances of `ty` matching the scalar filters in `arguments`
+fn instance_pattern(ty: &ObjectType, arguments: &ObjectAccessor<'_>) -> Outcome<String> {
+    let mut classes: Vec<&String> = ty.classes.iter().collect();
+    classes.sort();
+    let mut pattern =
+        String::from("{\n  GRAPH ?g { ?node a ?class }\n  FILTER(isIRI(?node))\n  VALUES ?class {");
+    for class in classes {
+        let _ = write!(pattern, " <{}>", class);
+    }
+    pattern.push_str(" }\n");
+
+    for (i, field) in ty.filters().enumerate() {
+        let FieldKind::Value { predicates, .. } = &field.kind else {
+            continue;
+        };
+        let value = match arguments.get(&field.name).map(|value| value.as_value()) {
+            None | Some(GraphQlValue::Null) => continue,
+            Some(GraphQlValue::String(value)) => value.clone(),
+            Some(value @ (GraphQlValue::Number(_) | GraphQlValue::Boolean(_))) => value.to_string(),
+            Some(_) => {
+                return Err(GraphQlError::new(format!(
+                    "Filter '{}' takes a scalar value",
+                    field.name
+                )))
+            }
+        };
+        let _ = write!(
+            pattern,
+            "  GRAPH ?g{0} {{ ?node ?p{0} ?v{0} }}\n  VALUES ?p{0} {{",
+            i
+        );
+        for predicate in predicates {
+            let _ = write!(pattern, " <{}>", predicate);
+        }
+        let _ = writeln!(
+            pattern,
+            " }}\n  FILTER(STR(?v{}) = {})",
+            i,
+            Literal::new_simple_literal(value)
+        );
+    }
+    pattern.push('}');
+    Ok(pattern)
+}
+
+/// Store access of one request, limited to the graphs the caller may read
+struct ReadContext {
+    store: Store,
+    /// Named graphs the caller may read; `None` when nothing is hidden
+    visible: Option<Vec<NamedOrBlankNode>>,
+    /// Nodes read so far, so each is read once per query
+    nodes: Mutex<HashMap<String, Arc<Node>>>,
+}
+
+impl ReadContext {
+    /// IRIs of `limit` nodes matching `pattern` from `offset`, in IRI order
+    fn instances(&self, pattern: &str, limit: usize, offset: usize) -> Outcome<Vec<String>> {
+        let sparql = format!(
+            "SELECT DISTINCT ?node WHERE {}\nORDER BY ?node\nLIMIT {}\nOFFSET {}",
+            pattern, limit, offset
+        );
+        Ok(self
+            .select(&sparql)?
+            .iter()
+            .filter_map(|solution| match solution.get("node") {
+                Some(Term::NamedNode(node)) => Some(node.as_str().to_string()),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Number of nodes matching `pattern`
+    fn count(&self, pattern: &str) -> Outcome<usize> {
+        let sparql = format!("SELECT (COUNT(DISTINCT ?node) AS ?count) WHERE {}", pattern);
+        Ok(self
+            .select(&sparql)?
+            .first()
+            .and_then(|solution| match solution.get("count") {
+                Some(Term::Literal(count)) => count.value().parse().ok(),
+                _ => None,
+            })
+            .unwrap_or_default())
+    }
+
+    /// Triples of `iri` in the visible graphs, read once per query
+    fn node(&self, iri: &str) -> Outcome<Arc<Node>> {
+        {
+            let nodes = self.nodes.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(node) = nodes.get(iri) {
+                return Ok(node.clone());
+            }
+            if nodes.len() >= MAX_NODES {
+                return Err(GraphQlError::new(format!(
+                    "The query reads more than {} nodes",
+                    MAX_NODES
+                )));
+            }
+        }
+        let subject = NamedNode::new(iri)
+            .map_err(|_| GraphQlError::new(format!("'{}' is not a valid IRI", iri)))?;
+
+        let mut node = Node::default();
+        let outgoing = format!("SELECT ?p ?o WHERE {{ GRAPH ?g {{ {} ?p ?o }} }}", subject);
+        for solution in self.select(&outgoing)? {
+            let (Some(Term::NamedNode(predicate)), Some(object)) =
+                (solution.get("p"), solution.get("o"))
+            else {
+                continue;
+            };
+            if let (RDF_TYPE, Term::NamedNode(class)) = (predicate.as_str(), object) {
+                node.types.insert(class.as_str().to_string());
+            }
+            node.outgoing
+                .entry(predicate.as_str().to_string())
+                .or_default()
+                .push(object.clone());
+        }
+        let incoming = format!("SELECT ?s ?p WHERE {{ GRAPH ?g {{ ?s ?p {} }} }}", subject);
+        for solution in self.select(&incoming)? {
+            if let (Some(Term::NamedNode(source)), Some(Term::NamedNode(predicate))) =
+                (solution.get("s"), solution.get("p"))
+            {
+                node.incoming
+                    .entry(predicate.as_str().to_string())
+                    .or_default()
+                    .insert(source.as_str().to_string());
+            }
+        }
+
+        let node = Arc::new(node);
+        self.nodes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(iri.to_string(), node.clone());
+        Ok(node)
+    }
+
+    fn select(&self, sparql: &str) -> Outcome<Vec<QuerySolution>> {
+        let failed =
+            |e: &dyn fmt::Display| GraphQlError::new(format!("Failed to read the graph: {}", e));
+        let mut query = Query::parse(sparql, None).map_err(|e| failed(&e))?;
+        if let Some(visible) = &self.visible {
+            query
+                .dataset_mut()
+                .set_available_named_graphs(visible.clone());
+        }
+        match self.store.query(query) {
+            Ok(QueryResults::Solutions(solutions)) => solutions
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| failed(&e)),
+            Ok(_) => Ok(Vec::new()),
+            Err(e) => Err(failed(&e)),
+        }
+    }
+}
+
+fn encode_cursor(position: usize) -> String {
+    BASE64.encode(format!("cursor:{}", position))
+}
+
+fn decode_cursor(cursor: &str) -> Option<usize> {
+    let decoded = BASE64.decode(cursor).ok()?;
+    std::str::from_utf8(&decoded)
+        .ok()?
+        .strip_prefix("cursor:")?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::graph_access::GraphAccessStore;
+    use oxigraph::io::RdfFormat;
+
+    const FARM: &str = r#"
+        @prefix trace: <http://provchain.org/trace#> .
+        @prefix prov: <http://www.w3.org/ns/prov#> .
+        trace:milkBatch001 a trace:ProductBatch ;
+            rdfs:label "Raw milk" ;
+            prov:wasAttributedTo trace:FarmerSmith .
+        trace:FarmerSmith a trace:Farmer ;
+            prov:label "Smith Dairy Farm" ;
+            trace:hasCertificate trace:OrganicCert001 .
+        trace:OrganicCert001 a trace:Certificate ;
+            prov:label "Organic Certification 2025" .
+    "#;
+
+    const PROCESSING: &str = r#"
+        @prefix trace: <http://provchain.org/trace#> .
+        @prefix prov: <http://www.w3.org/ns/prov#> .
+        @prefix xsd: <http://www.w3.org/2001/XMLSchema#> .
+        trace:uhtProcessing001 a trace:UHTProcessing ;
+            prov:used trace:milkBatch001 ;
+            prov:wasAssociatedWith trace:UHTFactory ;
+            trace:recordedAt "2025-08-09T10:30:00Z"^^xsd:dateTime ;
+            trace:hasCondition trace:TempCondition001 .
+        trace:TempCondition001 a trace:EnvironmentalCondition ;
+            trace:hasTemperature "138.0"^^xsd:decimal .
+        trace:UHTFactory a trace:UHTManufacturer ;
+            prov:label "Premium UHT Processing Co." ;
+            trace:hasCertificate trace:HaccpCert001 .
+        trace:HaccpCert001 a trace:Certificate ;
+            prov:label "HACCP 2025" .
+        trace:uhtMilk001 a trace:ProductBatch ;
+            rdfs:label "UHT milk" ;
+            prov:wasGeneratedBy trace:uhtProcessing001 ;
+            trace:lotDerivedFrom trace:milkBatch001 .
+    "#;
+
+    const MILK: &str = "http://provchain.org/trace#uhtMilk001";
+
+    /// Schema of the core and UHT ontologies
+    fn schema() -> GraphQlSchema {
+        let store = Store::new().unwrap();
+        for path in [
+            "ontologies/generic_core.owl",
+            "ontologies/uht_manufacturing.owl",
+        ] {
+            let ontology = std::fs::read(path).unwrap();
+            store
+                .load_from_reader(RdfFormat::Turtle, ontology.as_slice())
+                .unwrap();
+        }
+        GraphQlSchema::from_store(&store).unwrap()
+    }
+
+    /// Chain with the farm in block 1 and the processing in block 2
+    fn blockchain() -> Blockchain {
+        let prefix = "@prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .\n";
+        let mut blockchain = Blockchain::new();
+        blockchain.add_block(format!("{}{}", prefix, FARM)).unwrap();
+        blockchain
+            .add_block(format!("{}{}", prefix, PROCESSING))
+            .unwrap();
+        blockchain
+    }
+
+    async fn execute(
+        schema: &GraphQlSchema,
+        blockchain: &Blockchain,
+        visible: Option<Vec<NamedOrBlankNode>>,
+        query: &str,
+        variables: Value,
+    ) -> Value {
+        let Value::Object(variables) = variables else {
+            panic!("variables must be an object");
+        };
+        schema
+            .execute(
+                &blockchain.rdf_store.store,
+                visible,
+                query,
+                None,
+                &variables,
+            )
+            .await
+    }
+
+    #[test]
+    fn test_schema_follows_ontology() {
+        let sdl = schema().sdl();
+
+        assert!(sdl.contains("type ProductBatch implements Batch & Entity & TraceableEntity {"));
+        assert!(sdl.contains("\tproductBatch(id: ID!): ProductBatch\n"));
+        assert!(sdl.contains("\tproductBatches(first: Int, after: String, label: String"));
+        assert!(sdl.contains("\tuhtProcessing(id: ID!): UHTProcessing\n"));
+        assert!(sdl.contains("\tprocesses(first: Int"));
+        assert!(sdl.contains("\thasTemperature: Float\n"));
+        assert!(sdl.contains("\thasCertificate(first: Int, after: String): CertificateConnection!"));
+        assert!(
+            sdl.contains("\tlotDerivedFrom(first: Int, after: String): IngredientLotConnection!")
+        );
+        assert!(sdl.contains("\tactivities(first: Int, after: String): ActivityConnection!"));
+        assert!(sdl.contains("type PageInfo {"));
+        // Classes with subclasses are interfaces, with an object for their own instances
+        assert!(sdl.contains("interface Process {"));
+        assert!(sdl.contains("type ProcessInstance implements Process & Activity {"));
+
+        let schema = schema();
+        let batch = schema.object_type("ProductBatch").unwrap();
+        // Inherited from TraceableEntity, and PROV relations as an entity
+        assert!(batch.field("hasIdentifier").is_some());
+        assert!(batch.field("wasGeneratedBy").is_some());
+        assert!(batch.field("hasCertificate").is_none());
+        let farmer = schema.object_type("Farmer").unwrap();
+        assert!(farmer.field("hasCertificate").is_some());
+        assert!(farmer.field("activities").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_nested_batch_query() {
+        let schema = schema();
+        let blockchain = blockchain();
+        let query = r#"
+            query Batch($id: ID!) {
+                productBatch(id: $id) {
+                    label
+                    activities {
+                        totalCount
+                        nodes {
+                            __typename
+                            ... on Process { recordedAt }
+                            agents { nodes { ...agent } }
+                        }
+                    }
+                    derived: lotDerivedFrom { nodes { id } }
+                    wasDerivedFrom { totalCount }
+                }
+                milk: productBatch(id: "http://provchain.org/trace#milkBatch001") {
+                    agents { nodes { ...agent } }
+                    activities { nodes { id } }
+                }
+            }
+
+            fragment agent on Agent {
+                label
+                ... on Organization {
+                    hasCertificate { nodes { label } }
+                }
+            }
+        "#;
+        let response = execute(&schema, &blockchain, None, query, json!({ "id": MILK })).await;
+        assert!(response.get("errors").is_none(), "{}", response);
+
+        let batch = &response["data"]["productBatch"];
+        assert_eq!(batch["label"], "UHT milk");
+        assert_eq!(batch["activities"]["totalCount"], 1);
+        let activity = &batch["activities"]["nodes"][0];
+        assert_eq!(activity["__typename"], "UHTProcessing");
+        assert_eq!(activity["recordedAt"], "2025-08-09T10:30:00Z");
+        let agent = &activity["agents"]["nodes"][0];
+        assert_eq!(agent["label"], "Premium UHT Processing Co.");
+        assert_eq!(agent["hasCertificate"]["nodes"][0]["label"], "HACCP 2025");
+        assert_eq!(
+            batch["derived"]["nodes"][0]["id"],
+            "http://provchain.org/trace#milkBatch001"
+        );
+        assert_eq!(batch["wasDerivedFrom"]["totalCount"], 0);
+
+        // The raw milk was used by the processing and is attributed to the farmer
+        let milk = &response["data"]["milk"];
+        assert_eq!(milk["agents"]["nodes"][0]["label"], "Smith Dairy Farm");
+        assert_eq!(
+            milk["agents"]["nodes"][0]["hasCertificate"]["nodes"][0]["label"],
+            "Organic Certification 2025"
+        );
+        assert_eq!(
+            milk["activities"]["nodes"][0]["id"],
+            "http://provchain.org/trace#uhtProcessing001"
+        );
+
+        // Nodes that are not of the requested type are not found
+        let query = r#"{ uhtProcessing(id: "http://provchain.org/trace#uhtMilk001") { id } }"#;
+        let response = execute(&schema, &blockchain, None, query, json!({})).await;
+        assert_eq!(response["data"]["uhtProcessing"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_lists_are_paginated_and_filtered() {
+        let schema = schema();
+        let blockchain = blockchain();
+        let query = r#"
+            query Page($after: String) {
+                productBatches(first: 1, after: $after) {
+                    totalCount
+                    nodes { id }
+                    pageInfo { hasNextPage hasPreviousPage endCursor }
+                }
+            }
+        "#;
+        let first = execute(&schema, &blockchain, None, query, json!({})).await;
+        let page = &first["data"]["productBatches"];
+        assert_eq!(page["totalCount"], 2);
+        assert_eq!(
+            page["nodes"][0]["id"],
+            "http://provchain.org/trace#milkBatch001"
+        );
+        assert_eq!(page["pageInfo"]["hasNextPage"], true);
+        assert_eq!(page["pageInfo"]["hasPreviousPage"], false);
+
+        let cursor = page["pageInfo"]["endCursor"].clone();
+        let second = execute(
+            &schema,
+            &blockchain,
+            None,
+            query,
+            json!({ "after": cursor }),
+        )
+        .await;
+        let page = &second["data"]["productBatches"];
+        assert_eq!(page["nodes"][0]["id"], MILK);
+        assert_eq!(page["pageInfo"]["hasNextPage"], false);
+        assert_eq!(page["pageInfo"]["hasPreviousPage"], true);
+
+        // Scalar fields filter lists, and subclass instances are included
+        let query = r#"{
+            productBatches(label: "UHT milk") { nodes { id } }
+            processes { totalCount }
+            entities { totalCount }
+        }"#;
+        let response = execute(&schema, &blockchain, None, query, json!({})).await;
+        let data = &response["data"];
+        assert_eq!(data["productBatches"]["nodes"].as_array().unwrap().len(), 1);
+        assert_eq!(data["productBatches"]["nodes"][0]["id"], MILK);
+        assert_eq!(data["processes"]["totalCount"], 1);
+        assert_eq!(data["entities"]["totalCount"], 2);
+
+        let query = "{ productBatches(first: 500) { totalCount } }";
+        let response = execute(&schema, &blockchain, None, query, json!({})).await;
+        assert!(response["errors"][0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("'first'"));
+    }
+
+    #[tokio::test]
+    async fn test_hidden_graphs_are_not_resolved() {
+        let schema = schema();
+        let blockchain = blockchain();
+        let mut access = GraphAccessStore::new();
+        access
+            .record_owner("http://provchain.org/block/2", "processor_one")
+            .unwrap();
+        let store = &blockchain.rdf_store.store;
+        let query = r#"{
+            productBatches { totalCount }
+            milk: productBatch(id: "http://provchain.org/trace#milkBatch001") {
+                activities { totalCount }
+            }
+        }"#;
+
+        let visible = access
+            .visible_graphs(store, "retailer_one", "retailer")
+            .unwrap();
+        assert!(visible.is_some());
+        let response = execute(&schema, &blockchain, visible, query, json!({})).await;
+        assert_eq!(response["data"]["productBatches"]["totalCount"], 1);
+        assert_eq!(response["data"]["milk"]["activities"]["totalCount"], 0);
+
+        let visible = access
+            .visible_graphs(store, "processor_one", "processor")
+            .unwrap();
+        let response = execute(&schema, &blockchain, visible, query, json!({})).await;
+        assert_eq!(response["data"]["productBatches"]["totalCount"], 2);
+        assert_eq!(response["data"]["milk"]["activities"]["totalCount"], 1);
+
+        // Auditors see everything without a restriction
+        assert!(access
+            .visible_graphs(store, "auditor_one", "auditor")
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_queries_are_rejected() {
+        let schema = schema();
+        let blockchain = blockchain();
+        let cases = [
+            ("{ productBatches { totalCount ", "expected selection_set"),
+            ("{ productBatches { colour } }", "Unknown field \"colour\""),
+            ("{ productBatches(colour: \"red\") { totalCount } }", "Unknown argument"),
+            ("{ productBatches { nodes { id { value } } } }", "must not have a selection"),
+            ("{ productBatches { nodes } }", "must have a selection of subfields"),
+            ("mutation { productBatches { totalCount } }", "not configured for mutations"),
+            ("{ productBatches { ...loop } } fragment loop on ProductBatchConnection { ...loop }", "recursion depth"),
+            ("{ productBatches { ... on Widget { id } } }", "Unknown type \"Widget\""),
+            ("{ productBatches { totalCount @cached } }", "Unknown directive \"cached\""),
+            ("{ productBatches(first: \"ten\") { totalCount } }", "Invalid value for argument"),
+            ("{ productBatch(id: \"not an iri\") { id } }", "not a valid IRI"),
+        ];
+        for (query, expected) in cases {
+            let response = execute(&schema, &blockchain, None, query, json!({})).await;
+            assert_eq!(response["data"], Value::Null, "{}", query);
+            let message = response["errors"][0]["message"].as_str().unwrap();
+            assert!(message.contains(expected), "{}: {}", query, message);
+        }
+
+        // Skipped fields are not resolved
+        let query = "query($all: Boolean!) {
+            productBatches { totalCount @skip(if: $all) pageInfo { hasPreviousPage } }
+        }";
+        let response = execute(&schema, &blockchain, None, query, json!({ "all": true })).await;
+        assert!(response.get("errors").is_none(), "{}", response);
+        assert_eq!(
+            response["data"]["productBatches"],
+            json!({ "pageInfo": { "hasPreviousPage": false } })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_schema_is_introspectable() {
+        let schema = schema();
+        let blockchain = blockchain();
+        let query = r#"{
+            __schema { queryType { name } }
+            processing: __type(name: "UHTProcessing") {
+                kind
+                interfaces { name }
+            }
+            process: __type(name: "Process") {
+                kind
+                possibleTypes { name }
+                fields { name }
+            }
+            batches: __type(name: "Query") {
+                fields { name args { name type { name } } }
+            }
+        }"#;
+        let response = execute(&schema, &blockchain, None, query, json!({})).await;
+        assert!(response.get("errors").is_none(), "{}", response);
+
+        let data = &response["data"];
+        assert_eq!(data["__schema"]["queryType"]["name"], "Query");
+        let processing = &data["processing"];
+        assert_eq!(processing["kind"], "OBJECT");
+        let interfaces = processing["interfaces"].as_array().unwrap();
+        assert!(interfaces.contains(&json!({ "name": "Process" })));
+        assert!(interfaces.contains(&json!({ "name": "Activity" })));
+        let process = &data["process"];
+        assert_eq!(process["kind"], "INTERFACE");
+        let possible: Vec<&str> = process["possibleTypes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|ty| ty["name"].as_str())
+            .collect();
+        assert!(possible.contains(&"UHTProcessing"));
+        assert!(possible.contains(&"ProcessInstance"));
+        assert!(!possible.contains(&"Farmer"));
+        assert!(process["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|field| field["name"] == "recordedAt"));
+
+        let batches = data["batches"]["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|field| field["name"] == "productBatches")
+            .unwrap();
+        assert!(batches["args"]
+            .as_array()
+            .unwrap()
+            .contains(&json!({ "name": "hasIdentifier", "type": { "name": "String" } })));
+    }
+}
@@ -1,33 +1,52 @@
 //! HTTP handlers for REST API endpoints
 
-use crate::core::blockchain::Blockchain;
-use crate::trace_optimization::EnhancedTraceResult;
+use crate::core::blockchain::{Block, Blockchain};
+use crate::core::disclosure::{DisclosureProof, SealedTriple};
+use crate::error::WebError;
+use crate::knowledge_graph::{builder::GraphBuilder, graph_db::GraphDatabase};
+use crate::storage::rdf_store::RDFStore;
+use crate::trace_optimization::{EnhancedTraceResult, EnhancedTraceabilitySystem};
+use crate::transaction::ledger::LedgerState;
 use crate::transaction::transaction::{
     ComplianceInfo, EnvironmentalConditions, QualityData, Transaction, TransactionInput,
     TransactionMetadata, TransactionOutput, TransactionPayload, TransactionType,
 };
-use crate::wallet::{ContactInfo, Participant, ParticipantType};
+use crate::wallet::{ContactInfo, Participant, ParticipantPermissions, ParticipantType};
+use crate::web::disclosures::PrivateTripleStore;
+use crate::web::graph_access::{GraphAccess, GraphAccessStore};
+use crate::web::graphql::GraphQlSchema;
 use crate::web::models::{
-    AddTripleRequest, ApiError, BlockInfo, CreateTransactionRequest, CreateTransactionResponse,
-    EnvironmentalData, ProductTrace, SignTransactionRequest, SignTransactionResponse,
-    SparqlQueryRequest, SparqlQueryResponse, SubmitTransactionRequest, SubmitTransactionResponse,
-    UserClaims, WalletRegistrationRequest, WalletRegistrationResponse,
+    ActorRole, AddTripleRequest, ApiError, BatchOwnershipResponse, BlockInfo,
+    ConfidentialTriplesRequest, CreateTransactionRequest, CreateTransactionResponse,
+    DiscloseTripleRequest, EnvironmentalData, GraphQlRequest, NextNonceResponse, OwnershipRecord,
+    ProductTrace, RegisterWebhookRequest, RunSavedQueryRequest, ShareGraphRequest,
+    SignTransactionRequest, SignTransactionResponse, SparqlQueryRequest, SparqlQueryResponse,
+    SubmitTransactionRequest, SubmitTransactionResponse, UserClaims, WalletRegistrationRequest,
+    WalletRegistrationResponse, WebhookDeliveriesQuery,
 };
+use crate::web::openapi::{self, api_model};
+use crate::web::permissions::participant_permissions;
+use crate::web::saved_queries::{LibrarySnapshot, SavedQuery, SavedQueryDraft, SavedQueryStore};
+use crate::web::sparql;
+use crate::web::webhooks::{Delivery, Webhook, WebhookStore};
+use crate::web::websocket::BlockchainEventBroadcaster;
 use axum::extract::Path as AxumPath;
 use axum::{
     extract::{Extension, Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
+    response::Response,
     Json,
 };
 use chrono::Utc;
-use oxigraph::model::{NamedNode, Subject, Term};
+use oxigraph::model::{GraphName, NamedNode, Subject, Term};
+use oxigraph::sparql::QueryResults;
+use oxigraph::store::Store;
 use regex::Regex;
 use serde::Deserialize;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::RwLock;
-use crate::knowledge_graph::{builder::GraphBuilder, graph_db::GraphDatabase};
 
 /// Input validation functions
 fn validate_uri(uri: &str) -> Result<(), String> {
@@ -98,14 +117,161 @@ fn validate_sparql_query(query: &str) -> Result<(), String> {
 #[derive(Clone)]
 pub struct AppState {
     pub blockchain: Arc<RwLock<Blockchain>>,
+    pub saved_queries: Arc<RwLock<SavedQueryStore>>,
+    pub graph_access: Arc<RwLock<GraphAccessStore>>,
+    pub private_triples: Arc<RwLock<PrivateTripleStore>>,
+    pub webhooks: Arc<RwLock<WebhookStore>>,
+    /// GraphQL schema generated from the chain's ontology
+    pub graphql: Arc<GraphQlSchema>,
+    /// Live event feed of the WebSocket server, when there is one
+    pub events: Option<BlockchainEventBroadcaster>,
+    /// UTXO and batch ownership state, caught up with the chain when queried
+    pub ledger: Arc<RwLock<LedgerState>>,
+    /// Transactions created through the API and not yet submitted, by id
+    pub pending_transactions: Arc<RwLock<HashMap<String, PendingTransaction>>>,
+}
+
+/// A transaction waiting in the pending pool for its creator to submit it
+#[derive(Debug, Clone)]
+pub struct PendingTransaction {
+    /// User who created the transaction
+    pub creator: String,
+    pub transaction: Transaction,
 }
 
 impl AppState {
     pub fn new(blockchain: Blockchain) -> Self {
         Self {
+            graphql: Arc::new(GraphQlSchema::for_blockchain(&blockchain)),
             blockchain: Arc::new(RwLock::new(blockchain)),
+            saved_queries: Arc::new(RwLock::new(SavedQueryStore::new())),
+            graph_access: Arc::new(RwLock::new(GraphAccessStore::new())),
+            private_triples: Arc::new(RwLock::new(PrivateTripleStore::new())),
+            webhooks: Arc::new(RwLock::new(WebhookStore::new())),
+            events: None,
+            ledger: Arc::new(RwLock::new(LedgerState::default())),
+            pending_transactions: Arc::new(RwLock::new(HashMap::new())),
         }
     }
+
+    /// Announce transactions submitted through the API on the WebSocket event feed.
+    /// Blocks are announced by the server for every block the chain appends.
+    pub fn with_events(mut self, events: BlockchainEventBroadcaster) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Use a persistent graph access policy instead of the in-memory one
+    pub fn with_graph_access(mut self, graph_access: GraphAccessStore) -> Self {
+        self.graph_access = Arc::new(RwLock::new(graph_access));
+        self
+    }
+
+    /// Use a persistent store of confidential triples instead of the in-memory one
+    pub fn with_private_triples(mut self, private_triples: PrivateTripleStore) -> Self {
+        self.private_triples = Arc::new(RwLock::new(private_triples));
+        self
+    }
+
+    /// Use a persistent saved-query library instead of the in-memory one
+    pub fn with_saved_queries(mut self, saved_queries: SavedQueryStore) -> Self {
+        self.saved_queries = Arc::new(RwLock::new(saved_queries));
+        self
+    }
+
+    /// Use persistent webhooks instead of the in-memory ones
+    pub fn with_webhooks(mut self, webhooks: WebhookStore) -> Self {
+        self.webhooks = Arc::new(RwLock::new(webhooks));
+        self
+    }
+}
+
+/// Map a web error to its HTTP status and API error body
+pub(crate) fn web_error(error: WebError) -> (StatusCode, Json<ApiError>) {
+    let (status, code) = match &error {
+        WebError::AuthenticationFailed(_) => (StatusCode::UNAUTHORIZED, "authentication_failed"),
+        WebError::AuthorizationFailed(_) => (StatusCode::FORBIDDEN, "insufficient_permissions"),
+        WebError::InvalidRequest(_) | WebError::BadRequest(_) => {
+            (StatusCode::BAD_REQUEST, "invalid_request")
+        }
+        WebError::ResourceNotFound(_) => (StatusCode::NOT_FOUND, "not_found"),
+        WebError::RateLimitExceeded(_) => (StatusCode::TOO_MANY_REQUESTS, "rate_limited"),
+        WebError::ServerError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "server_error"),
+    };
+    (
+        status,
+        Json(ApiError {
+            error: code.to_string(),
+            message: error.to_string(),
+            timestamp: Utc::now(),
+        }),
+    )
+}
+
+/// User and role a read is made as. `None` is an anonymous caller of a public route,
+/// who only sees the graphs nobody owns.
+fn reader(claims: Option<&UserClaims>) -> (&str, &str) {
+    claims.map_or(("", ""), |claims| {
+        (claims.sub.as_str(), claims.role.as_str())
+    })
+}
+
+/// Run one of the API's own SPARQL queries over the named graphs the caller may read
+async fn query_visible(
+    app_state: &AppState,
+    store: &Store,
+    claims: Option<&UserClaims>,
+    sparql_query: &str,
+) -> Result<QueryResults, (StatusCode, Json<ApiError>)> {
+    let query_failed = |e: &dyn std::fmt::Display| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError {
+                error: "query_execution_failed".to_string(),
+                message: format!("Failed to execute query: {}", e),
+                timestamp: Utc::now(),
+            }),
+        )
+    };
+
+    let mut query =
+        oxigraph::sparql::Query::parse(sparql_query, None).map_err(|e| query_failed(&e))?;
+    // These queries use GRAPH patterns; keep their default graph the store's own one
+    // rather than the union of visible graphs `restrict` would otherwise give them
+    if query.dataset().default_graph_graphs().is_none() {
+        query
+            .dataset_mut()
+            .set_default_graph(vec![GraphName::DefaultGraph]);
+    }
+    let (user, role) = reader(claims);
+    app_state
+        .graph_access
+        .read()
+        .await
+        .restrict(&mut query, store, user, role)
+        .map_err(web_error)?;
+
+    store.query(query).map_err(|e| query_failed(&e))
+}
+
+/// The chain's RDF store narrowed to the graphs the caller may read, for code that walks
+/// the store itself rather than running a query; `None` when nothing is hidden
+async fn visible_rdf_store(
+    app_state: &AppState,
+    store: &Store,
+    claims: Option<&UserClaims>,
+) -> Result<Option<RDFStore>, (StatusCode, Json<ApiError>)> {
+    let (user, role) = reader(claims);
+    let visible = app_state
+        .graph_access
+        .read()
+        .await
+        .visible_store(store, user, role)
+        .map_err(web_error)?;
+    Ok(visible.map(|store| RDFStore {
+        store,
+        ..RDFStore::new()
+    }))
 }
 
 /// Enhanced health check endpoint with security status
@@ -123,6 +289,11 @@ pub async fn health_check() -> Json<serde_json::Value> {
     }))
 }
 
+/// OpenAPI document of the REST API
+pub async fn get_openapi_document() -> Json<serde_json::Value> {
+    Json(openapi::document())
+}
+
 /// Get blockchain status
 pub async fn get_blockchain_status(
     State(app_state): State<AppState>,
@@ -202,11 +373,24 @@ pub async fn get_block(
 pub async fn get_block_rdf_summary(
     Path(block_index): Path<usize>,
     State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
     let blockchain = app_state.blockchain.read().await;
 
     // Construct the named graph IRI for this block's RDF data
     let graph_iri = format!("http://provchain.org/block/{}", block_index);
+    // A graph the caller may not read is reported as missing, as in `get_block_access`
+    if !app_state
+        .graph_access
+        .read()
+        .await
+        .can_read(&graph_iri, &claims.sub, &claims.role)
+    {
+        return Err(web_error(WebError::ResourceNotFound(format!(
+            "Block {}",
+            block_index
+        ))));
+    }
     let graph = match NamedNode::new(&graph_iri) {
         Ok(g) => g,
         Err(_) => {
@@ -290,6 +474,72 @@ pub async fn get_block_rdf_summary(
     Ok(Json(summary))
 }
 
+/// Owner and sharing of a block's named graph, as far as the caller may see it
+pub async fn get_block_access(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+    Path(block_index): Path<u64>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    let graph = block_graph(&app_state, block_index).await?;
+    let graph_access = app_state.graph_access.read().await;
+    match graph_access.get(&graph) {
+        None => Ok(Json(serde_json::json!({
+            "graph": graph,
+            "owner": null,
+            "public": true,
+        }))),
+        Some(access) if access.can_read(&claims.sub, &claims.role) => Ok(Json(serde_json::json!({
+            "graph": graph,
+            "owner": access.owner,
+            "public": false,
+            "shared_with_users": access.shared_with_users,
+            "shared_with_roles": access.shared_with_roles,
+            "updated_at": access.updated_at,
+        }))),
+        Some(_) => Err(web_error(WebError::ResourceNotFound(format!(
+            "Block {}",
+            block_index
+        )))),
+    }
+}
+
+/// Replace who a block's named graph is shared with; owner or admin only
+pub async fn share_block_graph(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+    Path(block_index): Path<u64>,
+    Json(request): Json<ShareGraphRequest>,
+) -> Result<Json<GraphAccess>, (StatusCode, Json<ApiError>)> {
+    let graph = block_graph(&app_state, block_index).await?;
+    let mut graph_access = app_state.graph_access.write().await;
+    graph_access
+        .share(
+            &graph,
+            &claims.sub,
+            &claims.role,
+            &request.users,
+            &request.roles,
+        )
+        .cloned()
+        .map(Json)
+        .map_err(web_error)
+}
+
+/// Named graph of an existing block
+async fn block_graph(
+    app_state: &AppState,
+    block_index: u64,
+) -> Result<String, (StatusCode, Json<ApiError>)> {
+    let blockchain = app_state.blockchain.read().await;
+    if block_index as usize >= blockchain.chain.len() {
+        return Err(web_error(WebError::ResourceNotFound(format!(
+            "Block {}",
+            block_index
+        ))));
+    }
+    Ok(Block::graph_uri(block_index))
+}
+
 /// Get all blocks
 pub async fn get_blocks(
     State(app_state): State<AppState>,
@@ -368,36 +618,149 @@ pub async fn validate_sparql_endpoint(body: String) -> Json<serde_json::Value> {
     }))
 }
 
-/// Saved queries endpoints (non-persistent demo)
-pub async fn get_saved_sparql_queries() -> Json<Vec<serde_json::Value>> {
-    Json(Vec::new())
+/// Saved queries visible to the caller
+pub async fn get_saved_sparql_queries(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+) -> Json<Vec<serde_json::Value>> {
+    let saved_queries = app_state.saved_queries.read().await;
+    Json(
+        saved_queries
+            .list(&claims.sub, &claims.role)
+            .into_iter()
+            .map(|query| query.view(&claims.sub))
+            .collect(),
+    )
 }
 
-pub async fn save_sparql_query(Json(query): Json<serde_json::Value>) -> Json<serde_json::Value> {
-    // Echo back with an id if missing
-    let mut q = query.clone();
-    if q.get("id").is_none() {
-        if let Some(map) = q.as_object_mut() {
-            map.insert(
-                "id".to_string(),
-                serde_json::Value::String(format!("q_{}", Utc::now().timestamp_millis())),
-            );
-        }
-    }
-    Json(q)
+/// A saved query with its version history
+pub async fn get_saved_sparql_query(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+    AxumPath(id): AxumPath<String>,
+) -> Result<Json<SavedQuery>, (StatusCode, Json<ApiError>)> {
+    let saved_queries = app_state.saved_queries.read().await;
+    saved_queries
+        .get(&id, &claims.sub, &claims.role)
+        .map(|query| Json(query.clone()))
+        .map_err(web_error)
 }
 
-pub async fn delete_sparql_query(AxumPath(_id): AxumPath<String>) -> StatusCode {
-    StatusCode::NO_CONTENT
+/// Save a new query owned by the caller
+pub async fn save_sparql_query(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+    Json(draft): Json<SavedQueryDraft>,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, Json<ApiError>)> {
+    let (view, snapshot) = {
+        let mut saved_queries = app_state.saved_queries.write().await;
+        let query = saved_queries
+            .create(&claims.sub, draft)
+            .map_err(web_error)?;
+        (query.view(&claims.sub), saved_queries.snapshot())
+    };
+    write_saved_queries(snapshot).await?;
+    Ok((StatusCode::CREATED, Json(view)))
+}
+
+/// Save an edit to a query as its next version
+pub async fn update_sparql_query(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+    AxumPath(id): AxumPath<String>,
+    Json(draft): Json<SavedQueryDraft>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    let (view, snapshot) = {
+        let mut saved_queries = app_state.saved_queries.write().await;
+        let query = saved_queries
+            .update(&id, &claims.sub, &claims.role, draft)
+            .map_err(web_error)?;
+        (query.view(&claims.sub), saved_queries.snapshot())
+    };
+    write_saved_queries(snapshot).await?;
+    Ok(Json(view))
+}
+
+pub async fn delete_sparql_query(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+    AxumPath(id): AxumPath<String>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    let snapshot = {
+        let mut saved_queries = app_state.saved_queries.write().await;
+        saved_queries
+            .delete(&id, &claims.sub, &claims.role)
+            .map_err(web_error)?;
+        saved_queries.snapshot()
+    };
+    write_saved_queries(snapshot).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn toggle_favorite_sparql_query(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
     AxumPath(id): AxumPath<String>,
-) -> Json<serde_json::Value> {
-    Json(serde_json::json!({
-        "id": id,
-        "toggled": true
-    }))
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    let (view, snapshot) = {
+        let mut saved_queries = app_state.saved_queries.write().await;
+        saved_queries
+            .toggle_favorite(&id, &claims.sub, &claims.role)
+            .map_err(web_error)?;
+        let query = saved_queries
+            .get(&id, &claims.sub, &claims.role)
+            .map_err(web_error)?;
+        (query.view(&claims.sub), saved_queries.snapshot())
+    };
+    write_saved_queries(snapshot).await?;
+    Ok(Json(view))
+}
+
+/// Write a snapshot of the query library taken under its lock, off the async runtime
+async fn write_saved_queries(
+    snapshot: Result<Option<LibrarySnapshot>, WebError>,
+) -> Result<(), (StatusCode, Json<ApiError>)> {
+    let Some(snapshot) = snapshot.map_err(web_error)? else {
+        return Ok(());
+    };
+    tokio::task::spawn_blocking(move || snapshot.write())
+        .await
+        .map_err(|e| web_error(WebError::ServerError(e.to_string())))?
+        .map_err(web_error)
+}
+
+/// Run a saved query with parameter bindings. Results are negotiated like `/sparql`.
+pub async fn run_saved_sparql_query(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+    AxumPath(id): AxumPath<String>,
+    headers: HeaderMap,
+    Json(request): Json<RunSavedQueryRequest>,
+) -> Result<Response, (StatusCode, Json<ApiError>)> {
+    let mut query = {
+        let saved_queries = app_state.saved_queries.read().await;
+        let saved = saved_queries
+            .get(&id, &claims.sub, &claims.role)
+            .map_err(web_error)?;
+        let version = saved.version(request.version).ok_or_else(|| {
+            web_error(WebError::ResourceNotFound(format!(
+                "Version {} of saved query {}",
+                request.version.unwrap_or_default(),
+                id
+            )))
+        })?;
+        version.bind(&request.bindings).map_err(web_error)?
+    };
+    sparql::default_to_union(&mut query);
+
+    sparql::respond(
+        &app_state,
+        &claims,
+        &headers,
+        request.format.as_deref(),
+        query,
+    )
+    .await
 }
 
 /// Add new triple to blockchain with SHACL validation
@@ -478,8 +841,24 @@ pub async fn add_triple(
 
     eprintln!("Adding triple data: {}", triple_data);
 
+    // The new block graph is private to its author until shared. Its owner is recorded
+    // before the block is added, so the data is never readable by everyone.
+    let graph = Block::graph_uri(blockchain.chain.len() as u64);
+    let mut graph_access = app_state.graph_access.write().await;
+    graph_access
+        .record_owner(&graph, &claims.sub)
+        .map_err(web_error)?;
+
     // STEP 9: Add to blockchain with SHACL validation (this also adds to the internal RDF store)
-    match blockchain.add_block(triple_data) {
+    let added = blockchain.add_block(triple_data);
+    if added.is_err() {
+        if let Err(e) = graph_access.remove(&graph) {
+            eprintln!("Failed to release owner of {}: {}", graph, e);
+        }
+    }
+    drop(graph_access);
+
+    match added {
         Ok(()) => {
             let block_hash = blockchain
                 .chain
@@ -534,10 +913,233 @@ pub async fn add_triple(
     }
 }
 
+/// Commit confidential triples: salted commitments go on-chain in a new block, the
+/// plaintext stays in the node's private store
+pub async fn commit_confidential_triples(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+    Json(request): Json<ConfidentialTriplesRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, Json<ApiError>)> {
+    let sealed = SealedTriple::seal_turtle(&request.data)
+        .map_err(|e| web_error(WebError::BadRequest(e.to_string())))?;
+    let commitments = sealed
+        .iter()
+        .map(SealedTriple::commitment_ntriples)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut blockchain = app_state.blockchain.write().await;
+    blockchain.add_block(commitments).map_err(|e| {
+        web_error(WebError::ServerError(format!(
+            "Failed to commit confidential triples: {}",
+            e
+        )))
+    })?;
+    let block = blockchain.chain.last().ok_or_else(|| {
+        web_error(WebError::ServerError(
+            "Chain is empty after adding a block".to_string(),
+        ))
+    })?;
+    let (block_index, block_hash) = (block.index, block.hash.clone());
+
+    let commitments: Vec<String> = sealed
+        .iter()
+        .map(|sealed| sealed.commitment.clone())
+        .collect();
+    app_state
+        .private_triples
+        .write()
+        .await
+        .insert(&claims.sub, block_index, sealed)
+        .map_err(web_error)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "block_index": block_index,
+            "block_hash": block_hash,
+            "commitments": commitments,
+        })),
+    ))
+}
+
+/// Confidential triples the caller owns or was disclosed
+pub async fn get_confidential_triples(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+) -> Json<serde_json::Value> {
+    let private_triples = app_state.private_triples.read().await;
+    let triples: Vec<serde_json::Value> = private_triples
+        .visible_to(&claims.sub)
+        .into_iter()
+        .map(|triple| triple.view(&claims.sub))
+        .collect();
+    Json(serde_json::json!({
+        "total_count": triples.len(),
+        "triples": triples,
+    }))
+}
+
+/// Disclose an owned confidential triple to another user, returning its disclosure proof
+pub async fn disclose_confidential_triple(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+    AxumPath(commitment): AxumPath<String>,
+    Json(request): Json<DiscloseTripleRequest>,
+) -> Result<Json<DisclosureProof>, (StatusCode, Json<ApiError>)> {
+    let blockchain = app_state.blockchain.read().await;
+    let mut private_triples = app_state.private_triples.write().await;
+    let triple = private_triples
+        .disclose(&commitment, &claims.sub, &request.recipient)
+        .map_err(web_error)?;
+    disclosure_proof(&blockchain, triple.block_index, &triple.sealed).map(Json)
+}
+
+/// Disclosure proof of a confidential triple the caller owns or was disclosed
+pub async fn get_disclosure_proof(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+    AxumPath(commitment): AxumPath<String>,
+) -> Result<Json<DisclosureProof>, (StatusCode, Json<ApiError>)> {
+    let blockchain = app_state.blockchain.read().await;
+    let private_triples = app_state.private_triples.read().await;
+    let triple = private_triples
+        .get(&commitment, &claims.sub)
+        .map_err(web_error)?;
+    disclosure_proof(&blockchain, triple.block_index, &triple.sealed).map(Json)
+}
+
+/// Verify a disclosure proof. It is only valid for a block on this node's chain, signed
+/// by one of the chain's validators.
+pub async fn verify_disclosure(
+    State(app_state): State<AppState>,
+    Json(proof): Json<DisclosureProof>,
+) -> Json<serde_json::Value> {
+    let blockchain = app_state.blockchain.read().await;
+    match proof.verify_on(&blockchain) {
+        Ok(verification) => Json(serde_json::json!({
+            "valid": true,
+            "on_local_chain": true,
+            "verification": verification,
+        })),
+        Err(e) => Json(serde_json::json!({
+            "valid": false,
+            "error": e.to_string(),
+        })),
+    }
+}
+
+fn disclosure_proof(
+    blockchain: &Blockchain,
+    block_index: u64,
+    sealed: &SealedTriple,
+) -> Result<DisclosureProof, (StatusCode, Json<ApiError>)> {
+    DisclosureProof::build(blockchain, block_index, sealed).map_err(|e| {
+        web_error(WebError::ServerError(format!(
+            "Failed to build disclosure proof: {}",
+            e
+        )))
+    })
+}
+
+/// Register a webhook; the response carries its signing secret, which is not shown again
+pub async fn register_webhook(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+    Json(request): Json<RegisterWebhookRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, Json<ApiError>)> {
+    let mut webhooks = app_state.webhooks.write().await;
+    let webhook = webhooks
+        .register(
+            &claims.sub,
+            &claims.role,
+            &request.url,
+            request.events,
+            request.filter,
+        )
+        .map_err(web_error)?;
+
+    let mut response = webhook.view();
+    response["secret"] = serde_json::json!(webhook.secret);
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+pub async fn list_webhooks(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+) -> Json<serde_json::Value> {
+    let webhooks = app_state.webhooks.read().await;
+    let webhooks: Vec<serde_json::Value> = webhooks
+        .list(&claims.sub, &claims.role)
+        .into_iter()
+        .map(Webhook::view)
+        .collect();
+    Json(serde_json::json!({
+        "total_count": webhooks.len(),
+        "webhooks": webhooks,
+    }))
+}
+
+pub async fn get_webhook(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+    AxumPath(id): AxumPath<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    let webhooks = app_state.webhooks.read().await;
+    let webhook = webhooks
+        .get(&id, &claims.sub, &claims.role)
+        .map_err(web_error)?;
+    Ok(Json(webhook.view()))
+}
+
+pub async fn delete_webhook(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+    AxumPath(id): AxumPath<String>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    let mut webhooks = app_state.webhooks.write().await;
+    webhooks
+        .delete(&id, &claims.sub, &claims.role)
+        .map_err(web_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Delivery history of a webhook, newest first; `?status=dead_lettered` lists its
+/// dead-letter queue
+pub async fn get_webhook_deliveries(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+    AxumPath(id): AxumPath<String>,
+    Query(params): Query<WebhookDeliveriesQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    let webhooks = app_state.webhooks.read().await;
+    let deliveries = webhooks
+        .deliveries(&id, &claims.sub, &claims.role, params.status)
+        .map_err(web_error)?;
+    Ok(Json(serde_json::json!({
+        "total_count": deliveries.len(),
+        "deliveries": deliveries,
+    })))
+}
+
+/// Queue a dead-lettered delivery again
+pub async fn redeliver_webhook_delivery(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+    AxumPath((id, delivery_id)): AxumPath<(String, String)>,
+) -> Result<Json<Delivery>, (StatusCode, Json<ApiError>)> {
+    let mut webhooks = app_state.webhooks.write().await;
+    let delivery = webhooks
+        .redeliver(&id, &delivery_id, &claims.sub, &claims.role)
+        .map_err(web_error)?;
+    Ok(Json(delivery.clone()))
+}
+
 /// Get all products with filtering and pagination
 pub async fn get_products(
     Query(params): Query<ProductsQueryParams>,
     State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
     let blockchain = app_state.blockchain.read().await;
 
@@ -574,19 +1176,13 @@ pub async fn get_products(
     }
 
     // Execute SPARQL query
-    let query_results = match blockchain.rdf_store.store.query(&sparql_query) {
-        Ok(results) => results,
-        Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError {
-                    error: "query_execution_failed".to_string(),
-                    message: format!("Failed to execute query: {}", e),
-                    timestamp: Utc::now(),
-                }),
-            ));
-        }
-    };
+    let query_results = query_visible(
+        &app_state,
+        &blockchain.rdf_store.store,
+        Some(&claims),
+        &sparql_query,
+    )
+    .await?;
 
     let mut products = Vec::new();
 
@@ -666,6 +1262,7 @@ pub async fn get_products(
 pub async fn get_product_by_id(
     Path(product_id): Path<String>,
     State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
     let blockchain = app_state.blockchain.read().await;
 
@@ -695,19 +1292,13 @@ pub async fn get_product_by_id(
         product_id
     );
 
-    let query_results = match blockchain.rdf_store.store.query(&sparql_query) {
-        Ok(results) => results,
-        Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError {
-                    error: "query_execution_failed".to_string(),
-                    message: format!("Failed to execute query: {}", e),
-                    timestamp: Utc::now(),
-                }),
-            ));
-        }
-    };
+    let query_results = query_visible(
+        &app_state,
+        &blockchain.rdf_store.store,
+        Some(&claims),
+        &sparql_query,
+    )
+    .await?;
 
     let mut product_found = false;
     let mut product = serde_json::json!({
@@ -787,6 +1378,7 @@ pub async fn get_product_by_id(
 pub async fn get_product_trace_path(
     Path(product_id): Path<String>,
     State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
     let blockchain = app_state.blockchain.read().await;
 
@@ -808,19 +1400,13 @@ pub async fn get_product_trace_path(
         product_id
     );
 
-    let query_results = match blockchain.rdf_store.store.query(&sparql_query) {
-        Ok(results) => results,
-        Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError {
-                    error: "query_execution_failed".to_string(),
-                    message: format!("Failed to execute query: {}", e),
-                    timestamp: Utc::now(),
-                }),
-            ));
-        }
-    };
+    let query_results = query_visible(
+        &app_state,
+        &blockchain.rdf_store.store,
+        Some(&claims),
+        &sparql_query,
+    )
+    .await?;
 
     let mut trace_steps = Vec::new();
 
@@ -867,6 +1453,7 @@ pub async fn get_product_trace_path(
 pub async fn get_product_provenance(
     Path(product_id): Path<String>,
     State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
 ) -> Result<Json<Vec<serde_json::Value>>, (StatusCode, Json<ApiError>)> {
     let blockchain = app_state.blockchain.read().await;
 
@@ -887,19 +1474,13 @@ pub async fn get_product_provenance(
         product_id
     );
 
-    let query_results = match blockchain.rdf_store.store.query(&sparql_query) {
-        Ok(results) => results,
-        Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError {
-                    error: "query_execution_failed".to_string(),
-                    message: format!("Failed to execute query: {}", e),
-                    timestamp: Utc::now(),
-                }),
-            ));
-        }
-    };
+    let query_results = query_visible(
+        &app_state,
+        &blockchain.rdf_store.store,
+        Some(&claims),
+        &sparql_query,
+    )
+    .await?;
 
     let mut provenance_chain = Vec::new();
 
@@ -932,6 +1513,41 @@ pub async fn get_product_provenance(
     Ok(Json(provenance_chain))
 }
 
+/// Run a GraphQL query over the provenance graph, limited to the graphs the caller may
+/// read. Query errors are reported in the response's `errors`, as GraphQL clients expect.
+pub async fn graphql_query(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+    Json(request): Json<GraphQlRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    let blockchain = app_state.blockchain.read().await;
+    let store = &blockchain.rdf_store.store;
+    let visible = app_state
+        .graph_access
+        .read()
+        .await
+        .visible_graphs(store, &claims.sub, &claims.role)
+        .map_err(web_error)?;
+
+    Ok(Json(
+        app_state
+            .graphql
+            .execute(
+                store,
+                visible,
+                &request.query,
+                request.operation_name.as_deref(),
+                &request.variables.unwrap_or_default(),
+            )
+            .await,
+    ))
+}
+
+/// The GraphQL schema in SDL
+pub async fn get_graphql_schema(State(app_state): State<AppState>) -> String {
+    app_state.graphql.sdl()
+}
+
 /// Get knowledge graph for items
 pub async fn get_knowledge_graph(
     Query(params): Query<KnowledgeGraphParams>,
@@ -970,19 +1586,8 @@ pub async fn get_knowledge_graph(
         item_filters
     );
 
-    let query_results = match blockchain.rdf_store.store.query(&sparql_query) {
-        Ok(results) => results,
-        Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError {
-                    error: "query_execution_failed".to_string(),
-                    message: format!("Failed to execute query: {}", e),
-                    timestamp: Utc::now(),
-                }),
-            ));
-        }
-    };
+    let query_results =
+        query_visible(&app_state, &blockchain.rdf_store.store, None, &sparql_query).await?;
 
     let mut nodes = std::collections::HashMap::new();
     let mut edges = Vec::new();
@@ -1075,6 +1680,7 @@ pub async fn get_knowledge_graph(
 pub async fn get_product_analytics(
     Path(product_id): Path<String>,
     State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
     let blockchain = app_state.blockchain.read().await;
 
@@ -1093,19 +1699,13 @@ pub async fn get_product_analytics(
         product_id
     );
 
-    let query_results = match blockchain.rdf_store.store.query(&sparql_query) {
-        Ok(results) => results,
-        Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError {
-                    error: "query_execution_failed".to_string(),
-                    message: format!("Failed to execute query: {}", e),
-                    timestamp: Utc::now(),
-                }),
-            ));
-        }
-    };
+    let query_results = query_visible(
+        &app_state,
+        &blockchain.rdf_store.store,
+        Some(&claims),
+        &sparql_query,
+    )
+    .await?;
 
     let mut participants = std::collections::HashSet::new();
     let mut locations = std::collections::HashSet::new();
@@ -1156,6 +1756,7 @@ pub async fn get_product_analytics(
 pub async fn get_products_by_type(
     Path(product_type): Path<String>,
     State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
 ) -> Result<Json<Vec<serde_json::Value>>, (StatusCode, Json<ApiError>)> {
     let blockchain = app_state.blockchain.read().await;
 
@@ -1175,19 +1776,13 @@ pub async fn get_products_by_type(
         typ = product_type
     );
 
-    let query_results = match blockchain.rdf_store.store.query(&sparql_query) {
-        Ok(results) => results,
-        Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError {
-                    error: "query_execution_failed".to_string(),
-                    message: format!("Failed to execute query: {}", e),
-                    timestamp: Utc::now(),
-                }),
-            ));
-        }
-    };
+    let query_results = query_visible(
+        &app_state,
+        &blockchain.rdf_store.store,
+        Some(&claims),
+        &sparql_query,
+    )
+    .await?;
 
     let mut items = Vec::new();
     if let oxigraph::sparql::QueryResults::Solutions(solutions) = query_results {
@@ -1221,6 +1816,7 @@ pub async fn get_products_by_type(
 pub async fn get_products_by_participant(
     Path(participant_id): Path<String>,
     State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
 ) -> Result<Json<Vec<serde_json::Value>>, (StatusCode, Json<ApiError>)> {
     let blockchain = app_state.blockchain.read().await;
 
@@ -1242,19 +1838,13 @@ pub async fn get_products_by_participant(
         pid = participant_id
     );
 
-    let query_results = match blockchain.rdf_store.store.query(&sparql_query) {
-        Ok(results) => results,
-        Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError {
-                    error: "query_execution_failed".to_string(),
-                    message: format!("Failed to execute query: {}", e),
-                    timestamp: Utc::now(),
-                }),
-            ));
-        }
-    };
+    let query_results = query_visible(
+        &app_state,
+        &blockchain.rdf_store.store,
+        Some(&claims),
+        &sparql_query,
+    )
+    .await?;
 
     let mut items = Vec::new();
     if let oxigraph::sparql::QueryResults::Solutions(solutions) = query_results {
@@ -1288,6 +1878,7 @@ pub async fn get_products_by_participant(
 pub async fn get_related_items(
     Path(item_id): Path<String>,
     State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
 ) -> Result<Json<Vec<serde_json::Value>>, (StatusCode, Json<ApiError>)> {
     let blockchain = app_state.blockchain.read().await;
 
@@ -1315,19 +1906,13 @@ pub async fn get_related_items(
         id = item_id
     );
 
-    let query_results = match blockchain.rdf_store.store.query(&sparql_query) {
-        Ok(results) => results,
-        Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError {
-                    error: "query_execution_failed".to_string(),
-                    message: format!("Failed to execute query: {}", e),
-                    timestamp: Utc::now(),
-                }),
-            ));
-        }
-    };
+    let query_results = query_visible(
+        &app_state,
+        &blockchain.rdf_store.store,
+        Some(&claims),
+        &sparql_query,
+    )
+    .await?;
 
     let mut items = Vec::new();
     if let oxigraph::sparql::QueryResults::Solutions(solutions) = query_results {
@@ -1390,9 +1975,98 @@ pub async fn validate_item(
     Ok(Json(response))
 }
 
-/// Create a new transaction
+/// Current owner of a batch and the chain of transfers leading to them
+pub async fn get_batch_ownership(
+    Path(batch_id): Path<String>,
+    State(app_state): State<AppState>,
+) -> Result<Json<BatchOwnershipResponse>, (StatusCode, Json<ApiError>)> {
+    let blockchain = app_state.blockchain.read().await;
+    let mut ledger = app_state.ledger.write().await;
+    if let Err(e) = ledger.sync(&blockchain.chain) {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError {
+                error: "ledger_sync_failed".to_string(),
+                message: format!("Failed to derive ownership from the chain: {}", e),
+                timestamp: Utc::now(),
+            }),
+        ));
+    }
+
+    let Some(ownership) = ledger.batch_ownership(&batch_id) else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ApiError {
+                error: "batch_not_found".to_string(),
+                message: format!("No batch {} on the chain", batch_id),
+                timestamp: Utc::now(),
+            }),
+        ));
+    };
+
+    Ok(Json(BatchOwnershipResponse {
+        batch_id: ownership.batch_id,
+        current_owner: ownership.current_owner.map(|owner| owner.to_string()),
+        consumed_by: ownership.consumed_by,
+        history: ownership
+            .history
+            .into_iter()
+            .map(|record| OwnershipRecord {
+                output_id: record.output.id,
+                owner: record.output.owner.to_string(),
+                transaction_id: record.created_by,
+                transaction_type: format!("{:?}", record.tx_type),
+                block_index: record.block_index,
+                timestamp: record.timestamp,
+                spent_by: record.spent_by,
+            })
+            .collect(),
+    }))
+}
+
+/// Nonce the participant's next signed transaction must carry
+///
+/// Reflects the transactions confirmed on the chain; a client submitting several
+/// transactions before the next block numbers them upwards from here.
+pub async fn get_next_nonce(
+    Path(participant_id): Path<String>,
+    State(app_state): State<AppState>,
+) -> Result<Json<NextNonceResponse>, (StatusCode, Json<ApiError>)> {
+    let Ok(participant) = uuid::Uuid::parse_str(&participant_id) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiError {
+                error: "invalid_participant_id".to_string(),
+                message: "Invalid participant ID format".to_string(),
+                timestamp: Utc::now(),
+            }),
+        ));
+    };
+
+    let blockchain = app_state.blockchain.read().await;
+    let mut ledger = app_state.ledger.write().await;
+    if let Err(e) = ledger.sync(&blockchain.chain) {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError {
+                error: "ledger_sync_failed".to_string(),
+                message: format!("Failed to derive nonces from the chain: {}", e),
+                timestamp: Utc::now(),
+            }),
+        ));
+    }
+
+    Ok(Json(NextNonceResponse {
+        participant_id: participant.to_string(),
+        next_nonce: ledger.next_nonce(participant),
+        block_height: ledger.height(),
+    }))
+}
+
+/// Create a new transaction and keep it in the pending pool until it is submitted
 pub async fn create_transaction(
-    State(_app_state): State<AppState>,
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
     Json(request): Json<CreateTransactionRequest>,
 ) -> Result<Json<CreateTransactionResponse>, (StatusCode, Json<ApiError>)> {
     // Validate transaction type
@@ -1416,6 +2090,22 @@ pub async fn create_transaction(
         }
     };
 
+    // Refuse transaction types the chain would not accept from the caller's participant type
+    let chain_permissions = claims
+        .role
+        .parse::<ActorRole>()
+        .map(|role| participant_permissions(&role))
+        .unwrap_or_else(|_| ParticipantPermissions::none());
+    if !tx_type
+        .required_operation()
+        .is_some_and(|operation| chain_permissions.allows(operation))
+    {
+        return Err(web_error(WebError::AuthorizationFailed(format!(
+            "Role '{}' cannot create {} transactions",
+            claims.role, request.tx_type
+        ))));
+    }
+
     // Convert metadata from models to transaction
     let metadata = TransactionMetadata {
         location: request.metadata.location,
@@ -1481,10 +2171,13 @@ pub async fn create_transaction(
     );
 
     let tx_id = transaction.id.clone();
-
-    // In a real implementation, we would:
-    // 1. Store the transaction in a pending pool
-    // 2. Return the transaction ID for signing
+    app_state.pending_transactions.write().await.insert(
+        tx_id.clone(),
+        PendingTransaction {
+            creator: claims.sub.clone(),
+            transaction,
+        },
+    );
 
     let response = CreateTransactionResponse {
         tx_id: tx_id.clone(),
@@ -1542,28 +2235,74 @@ pub async fn sign_transaction(
     Ok(Json(response))
 }
 
-/// Submit a signed transaction to the blockchain
+/// Submit a pending transaction to the blockchain.
+///
+/// Only the user who created the transaction, or an admin, may submit it. Its RDF data
+/// becomes a new block owned by the submitter, and the transaction leaves the pending
+/// pool once the block is added.
 pub async fn submit_transaction(
-    State(_app_state): State<AppState>,
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
     Json(request): Json<SubmitTransactionRequest>,
 ) -> Result<Json<SubmitTransactionResponse>, (StatusCode, Json<ApiError>)> {
     let tx_id = request.tx_id;
+    let pending = app_state
+        .pending_transactions
+        .read()
+        .await
+        .get(&tx_id)
+        .filter(|pending| pending.creator == claims.sub || claims.role == "admin")
+        .cloned()
+        .ok_or_else(|| {
+            web_error(WebError::ResourceNotFound(format!(
+                "Pending transaction {}",
+                tx_id
+            )))
+        })?;
+
+    if let Some(events) = &app_state.events {
+        events.broadcast_transaction_submitted(
+            tx_id.clone(),
+            format!("{:?}", pending.transaction.tx_type),
+            pending.creator.clone(),
+        );
+    }
 
-    // In a real implementation, we would:
-    // 1. Retrieve the signed transaction from the pending pool
-    // 2. Validate the transaction (signatures, business logic, etc.)
-    // 3. Submit the transaction to the blockchain
-    // 4. Remove the transaction from the pending pool
-    // 5. Return the block index where the transaction was included
+    let mut blockchain = app_state.blockchain.write().await;
+    let graph = Block::graph_uri(blockchain.chain.len() as u64);
+    let mut graph_access = app_state.graph_access.write().await;
+    graph_access
+        .record_owner(&graph, &claims.sub)
+        .map_err(web_error)?;
+    if let Err(e) = blockchain.add_block(pending.transaction.rdf_data.clone()) {
+        if let Err(e) = graph_access.remove(&graph) {
+            eprintln!("Failed to release owner of {}: {}", graph, e);
+        }
+        return Err(web_error(WebError::BadRequest(format!(
+            "Transaction {} was not added to the blockchain: {}",
+            tx_id, e
+        ))));
+    }
+    drop(graph_access);
+    let block_index = blockchain.get_latest_block_index();
+    drop(blockchain);
+
+    app_state.pending_transactions.write().await.remove(&tx_id);
+    if let Some(events) = &app_state.events {
+        events.broadcast_transaction_processed(tx_id.clone(), block_index);
+    }
 
     let response = SubmitTransactionResponse {
         tx_id: tx_id.clone(),
-        block_index: Some(0), // Placeholder - in real implementation this would be the actual block index
+        block_index: Some(block_index as usize),
         message: "Transaction submitted successfully".to_string(),
         timestamp: Utc::now(),
     };
 
-    println!("Submitted transaction {} to blockchain", tx_id);
+    println!(
+        "Submitted transaction {} to blockchain in block {}",
+        tx_id, block_index
+    );
 
     Ok(Json(response))
 }
@@ -1571,6 +2310,7 @@ pub async fn submit_transaction(
 /// Execute SPARQL query
 pub async fn execute_sparql_query(
     State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
     Json(request): Json<SparqlQueryRequest>,
 ) -> Result<Json<SparqlQueryResponse>, (StatusCode, Json<ApiError>)> {
     // Validate SPARQL query
@@ -1585,11 +2325,38 @@ pub async fn execute_sparql_query(
         ));
     }
 
+    let mut query = match oxigraph::sparql::Query::parse(&request.query, None) {
+        Ok(query) => query,
+        Err(e) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ApiError {
+                    error: "invalid_sparql_query".to_string(),
+                    message: format!("Invalid SPARQL query: {}", e),
+                    timestamp: Utc::now(),
+                }),
+            ));
+        }
+    };
+
     let blockchain = app_state.blockchain.read().await;
     let start_time = Instant::now();
 
+    // Only the named graphs the caller may read are queried
+    app_state
+        .graph_access
+        .read()
+        .await
+        .restrict(
+            &mut query,
+            &blockchain.rdf_store.store,
+            &claims.sub,
+            &claims.role,
+        )
+        .map_err(web_error)?;
+
     // Access the RDF store through the blockchain and handle potential query errors
-    let query_results = match blockchain.rdf_store.store.query(&request.query) {
+    let query_results = match blockchain.rdf_store.store.query(query) {
         Ok(results) => results,
         Err(e) => {
             return Err((
@@ -1688,66 +2455,90 @@ pub async fn execute_sparql_query(
     Ok(Json(response))
 }
 
-/// Query parameters for product trace
-#[derive(Deserialize)]
-pub struct TraceQueryParams {
-    batch_id: Option<String>,
-    product_name: Option<String>,
+api_model! {
+    /// Query parameters for product trace
+    #[derive(Deserialize)]
+    pub struct TraceQueryParams {
+        batch_id: Option<String>,
+        product_name: Option<String>,
+    }
 }
 
-/// Query parameters for enhanced product trace
-#[derive(Deserialize)]
-pub struct EnhancedTraceQueryParams {
-    batch_id: String,
-    #[serde(default = "default_optimization_level")]
-    optimization_level: u8,
+api_model! {
+    /// Query parameters for enhanced product trace
+    #[derive(Deserialize)]
+    pub struct EnhancedTraceQueryParams {
+        batch_id: String,
+        /// 0 (none) to 2 (aggressive)
+        #[serde(default = "default_optimization_level")]
+        optimization_level: u8,
+    }
 }
 
 fn default_optimization_level() -> u8 {
     1
 }
 
-/// Query parameters for products listing
-#[derive(Deserialize)]
-#[allow(dead_code)]
-pub struct ProductsQueryParams {
-    q: Option<String>,
-    page: Option<u32>,
-    limit: Option<u32>,
-    sort_by: Option<String>,
-    sort_order: Option<String>,
-    #[serde(rename = "type")]
-    product_type: Option<String>,
-    participant: Option<String>,
-    location: Option<String>,
-    status: Option<String>,
-    start_date: Option<String>,
-    end_date: Option<String>,
-}
-
-/// Query parameters for knowledge graph
-#[derive(Deserialize)]
-pub struct KnowledgeGraphParams {
-    item_id: Vec<String>,
-}
-
-/// Analytics query parameters
-#[derive(Deserialize)]
-pub struct AnalyticsQueryParams {
-    start_date: Option<String>,
-    end_date: Option<String>,
-    #[allow(dead_code)]
-    participant_type: Option<String>,
-    #[allow(dead_code)]
-    transaction_type: Option<String>,
+api_model! {
+    /// Query parameters for products listing
+    #[derive(Deserialize)]
     #[allow(dead_code)]
-    granularity: Option<String>, // hour | day | week | month
+    pub struct ProductsQueryParams {
+        q: Option<String>,
+        page: Option<u32>,
+        limit: Option<u32>,
+        sort_by: Option<String>,
+        sort_order: Option<String>,
+        #[serde(rename = "type")]
+        product_type: Option<String>,
+        participant: Option<String>,
+        location: Option<String>,
+        status: Option<String>,
+        start_date: Option<String>,
+        end_date: Option<String>,
+    }
+}
+
+api_model! {
+    /// Query parameters for knowledge graph
+    #[derive(Deserialize)]
+    pub struct KnowledgeGraphParams {
+        item_id: Vec<String>,
+    }
+}
+
+api_model! {
+    /// Query parameters for tracing a path between two entities; both are required
+    #[derive(Deserialize)]
+    pub struct TracePathParams {
+        /// Start entity URI
+        from: Option<String>,
+        /// End entity URI
+        to: Option<String>,
+    }
+}
+
+api_model! {
+    /// Analytics query parameters
+    #[derive(Deserialize)]
+    pub struct AnalyticsQueryParams {
+        start_date: Option<String>,
+        end_date: Option<String>,
+        #[allow(dead_code)]
+        participant_type: Option<String>,
+        #[allow(dead_code)]
+        transaction_type: Option<String>,
+        /// hour, day, week or month
+        #[allow(dead_code)]
+        granularity: Option<String>,
+    }
 }
 
 /// Get product traceability information
 pub async fn get_product_trace(
     Query(params): Query<TraceQueryParams>,
     State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
 ) -> Result<Json<ProductTrace>, (StatusCode, Json<ApiError>)> {
     let blockchain = app_state.blockchain.read().await;
 
@@ -1777,7 +2568,13 @@ pub async fn get_product_trace(
     .to_string();
 
     // Access the RDF store through the blockchain
-    let query_results = blockchain.rdf_store.query(&sparql_query);
+    let query_results = query_visible(
+        &app_state,
+        &blockchain.rdf_store.store,
+        Some(&claims),
+        &sparql_query,
+    )
+    .await?;
 
     let mut product_name = "Unknown Product".to_string();
     let mut origin = "Unknown Origin".to_string();
@@ -1911,6 +2708,7 @@ pub async fn get_recent_transactions(
 pub async fn get_enhanced_product_trace(
     Query(params): Query<EnhancedTraceQueryParams>,
     State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
 ) -> Result<Json<EnhancedTraceResult>, (StatusCode, Json<ApiError>)> {
     let blockchain = app_state.blockchain.read().await;
 
@@ -1926,8 +2724,14 @@ pub async fn get_enhanced_product_trace(
         ));
     }
 
-    // Perform enhanced trace using the SSSP-inspired optimization
-    let trace_result = blockchain.enhanced_trace(&params.batch_id, params.optimization_level);
+    // Perform enhanced trace using the SSSP-inspired optimization, over the graphs the
+    // caller may read
+    let trace_result =
+        match visible_rdf_store(&app_state, &blockchain.rdf_store.store, Some(&claims)).await? {
+            Some(rdf_store) => EnhancedTraceabilitySystem::with_store(&rdf_store)
+                .enhanced_trace(&params.batch_id, params.optimization_level),
+            None => blockchain.enhanced_trace(&params.batch_id, params.optimization_level),
+        };
 
     Ok(Json(trace_result))
 }
@@ -2263,10 +3067,10 @@ pub async fn register_wallet(
 
 /// Trace path between two entities using GraphDatabase
 pub async fn trace_path_api(
-    Query(params): Query<std::collections::HashMap<String, String>>,
+    Query(params): Query<TracePathParams>,
     State(app_state): State<AppState>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
-    let from = params.get("from").ok_or_else(|| {
+    let from = params.from.as_ref().ok_or_else(|| {
         (
             StatusCode::BAD_REQUEST,
             Json(ApiError {
@@ -2277,7 +3081,7 @@ pub async fn trace_path_api(
         )
     })?;
 
-    let to = params.get("to").ok_or_else(|| {
+    let to = params.to.as_ref().ok_or_else(|| {
         (
             StatusCode::BAD_REQUEST,
             Json(ApiError {
@@ -2290,9 +3094,12 @@ pub async fn trace_path_api(
 
     let blockchain = app_state.blockchain.read().await;
 
-    // Build knowledge graph from blockchain data
+    // Build knowledge graph from the graphs anyone may read
     // Note: In a production environment, this should be cached or incrementally updated
-    let builder = GraphBuilder::new(blockchain.rdf_store.clone());
+    let rdf_store = visible_rdf_store(&app_state, &blockchain.rdf_store.store, None)
+        .await?
+        .unwrap_or_else(|| blockchain.rdf_store.clone());
+    let builder = GraphBuilder::new(rdf_store);
     let kg = builder.build_knowledge_graph().map_err(|e| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -2330,3 +3137,73 @@ pub async fn trace_path_api(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app_state() -> AppState {
+        let mut blockchain = Blockchain::new();
+        blockchain
+            .add_block(
+                "@prefix ex: <http://example.org/> .\n\
+                 ex:batch1 ex:producedBy ex:farm1 ."
+                    .to_string(),
+            )
+            .unwrap();
+        AppState::new(blockchain)
+    }
+
+    fn claims(user: &str, role: &str) -> Extension<UserClaims> {
+        Extension(UserClaims {
+            sub: user.to_string(),
+            role: role.to_string(),
+            exp: 0,
+            jti: String::new(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_read_paths_only_see_permitted_graphs() {
+        let state = app_state();
+        state
+            .graph_access
+            .write()
+            .await
+            .record_owner("http://provchain.org/block/1", "alice")
+            .unwrap();
+
+        let summary = |user: &'static str, role: &'static str| {
+            get_block_rdf_summary(Path(1), State(state.clone()), claims(user, role))
+        };
+        assert!(summary("alice", "farmer").await.is_ok());
+        assert!(summary("carol", "auditor").await.is_ok());
+        assert_eq!(
+            summary("bob", "farmer").await.unwrap_err().0,
+            StatusCode::NOT_FOUND
+        );
+
+        // Anonymous callers of the public knowledge graph only see unowned graphs
+        let params = KnowledgeGraphParams {
+            item_id: vec!["http://example.org/batch1".to_string()],
+        };
+        let Json(graph) = get_knowledge_graph(Query(params), State(state.clone()))
+            .await
+            .unwrap();
+        assert_eq!(graph["metadata"]["total_nodes"], 0);
+
+        state
+            .graph_access
+            .write()
+            .await
+            .remove("http://provchain.org/block/1")
+            .unwrap();
+        let params = KnowledgeGraphParams {
+            item_id: vec!["http://example.org/batch1".to_string()],
+        };
+        let Json(graph) = get_knowledge_graph(Query(params), State(state))
+            .await
+            .unwrap();
+        assert_eq!(graph["metadata"]["total_nodes"], 1);
+    }
+}
@@ -2,9 +2,17 @@
 //! Provides REST API and web server functionality
 
 pub mod auth;
+pub mod disclosures;
+pub mod graph_access;
+pub mod graphql;
 pub mod handlers;
 pub mod models;
+pub mod openapi;
+pub mod permissions;
+pub mod saved_queries;
 pub mod server;
+pub mod sparql;
+pub mod webhooks;
 pub mod websocket;
 
 pub use server::WebServer;
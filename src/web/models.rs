@@ -1,137 +1,283 @@
 //! Data models for web API responses and requests
+//!
+//! Models are declared through `api_model!`, which also gives them their schema in the
+//! OpenAPI document.
 
+use crate::web::openapi::api_model;
+use crate::web::webhooks::DeliveryStatus;
+use crate::web::websocket::EventFilter;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-/// Response model for blockchain status
-#[derive(Debug, Serialize, Deserialize)]
-pub struct BlockchainStatus {
-    pub height: usize,
-    pub latest_block_hash: String,
-    pub total_transactions: usize,
-    pub network_peers: usize,
-    pub last_updated: DateTime<Utc>,
-}
-
-/// Response model for block information
-#[derive(Debug, Serialize, Deserialize)]
-pub struct BlockInfo {
-    pub index: usize,
-    pub hash: String,
-    pub previous_hash: String,
-    pub timestamp: DateTime<Utc>,
-    pub transaction_count: usize,
-    pub size_bytes: usize,
-}
-
-/// Response model for transaction/triple information
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TransactionInfo {
-    pub subject: String,
-    pub predicate: String,
-    pub object: String,
-    pub block_index: usize,
-    pub timestamp: DateTime<Utc>,
-}
-
-/// Request model for adding new triples
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AddTripleRequest {
-    pub subject: String,
-    pub predicate: String,
-    pub object: String,
-    pub graph_name: Option<String>,
-}
-
-/// Request model for SPARQL queries
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SparqlQueryRequest {
-    pub query: String,
-    pub format: Option<String>, // json, xml, turtle, etc.
-}
-
-/// Response model for SPARQL query results
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SparqlQueryResponse {
-    pub results: serde_json::Value,
-    pub execution_time_ms: u64,
-    pub result_count: usize,
-}
-
-/// Response model for product traceability
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ProductTrace {
-    pub batch_id: String,
-    pub product_name: String,
-    pub origin: String,
-    pub current_location: String,
-    pub status: String,
-    pub timeline: Vec<TraceEvent>,
-    pub certifications: Vec<String>,
-    pub environmental_data: Option<EnvironmentalData>,
-}
-
-/// Individual trace event in product journey
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TraceEvent {
-    pub timestamp: DateTime<Utc>,
-    pub location: String,
-    pub actor: String,
-    pub action: String,
-    pub details: String,
-    pub block_hash: String,
-}
-
-/// Environmental conditions data
-#[derive(Debug, Serialize, Deserialize)]
-pub struct EnvironmentalData {
-    pub temperature: Option<f64>,
-    pub humidity: Option<f64>,
-    pub co2_footprint: Option<f64>,
-    pub certifications: Vec<String>,
-}
-
-/// API error response
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ApiError {
-    pub error: String,
-    pub message: String,
-    pub timestamp: DateTime<Utc>,
-}
-
-/// Authentication request
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AuthRequest {
-    pub username: String,
-    pub password: String,
-}
-
-/// Authentication response
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AuthResponse {
-    pub token: String,
-    pub expires_at: DateTime<Utc>,
-    pub user_role: String,
-}
-
-/// User claims for JWT
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct UserClaims {
-    pub sub: String, // user id
-    pub role: String,
-    pub exp: usize, // expiration timestamp
-}
-
-/// Supply chain actor roles
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum ActorRole {
-    Farmer,
-    Processor,
-    Transporter,
-    Retailer,
-    Consumer,
-    Auditor,
-    Admin,
+api_model! {
+    /// Response model for blockchain status
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct BlockchainStatus {
+        pub height: usize,
+        pub latest_block_hash: String,
+        pub total_transactions: usize,
+        pub network_peers: usize,
+        pub last_updated: DateTime<Utc>,
+    }
+}
+
+api_model! {
+    /// Response model for block information
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct BlockInfo {
+        pub index: usize,
+        pub hash: String,
+        pub previous_hash: String,
+        pub timestamp: DateTime<Utc>,
+        pub transaction_count: usize,
+        pub size_bytes: usize,
+    }
+}
+
+api_model! {
+    /// Response model for transaction/triple information
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct TransactionInfo {
+        pub subject: String,
+        pub predicate: String,
+        pub object: String,
+        pub block_index: usize,
+        pub timestamp: DateTime<Utc>,
+    }
+}
+
+api_model! {
+    /// Request model for adding new triples
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct AddTripleRequest {
+        pub subject: String,
+        pub predicate: String,
+        pub object: String,
+        pub graph_name: Option<String>,
+    }
+}
+
+api_model! {
+    /// Request model for SPARQL queries
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct SparqlQueryRequest {
+        pub query: String,
+        pub format: Option<String>, // json, xml, turtle, etc.
+    }
+}
+
+api_model! {
+    /// Request to run a saved SPARQL query
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    pub struct RunSavedQueryRequest {
+        /// Parameter name (without `?`) to RDF term or plain value
+        #[serde(default)]
+        pub bindings: std::collections::HashMap<String, String>,
+        /// Version to run; the latest when omitted
+        pub version: Option<u32>,
+        /// Result format, overriding the `Accept` header
+        pub format: Option<String>,
+    }
+}
+
+api_model! {
+    /// Response model for SPARQL query results
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct SparqlQueryResponse {
+        pub results: serde_json::Value,
+        pub execution_time_ms: u64,
+        pub result_count: usize,
+    }
+}
+
+api_model! {
+    /// Response model for product traceability
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct ProductTrace {
+        pub batch_id: String,
+        pub product_name: String,
+        pub origin: String,
+        pub current_location: String,
+        pub status: String,
+        pub timeline: Vec<TraceEvent>,
+        pub certifications: Vec<String>,
+        pub environmental_data: Option<EnvironmentalData>,
+    }
+}
+
+api_model! {
+    /// Individual trace event in product journey
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct TraceEvent {
+        pub timestamp: DateTime<Utc>,
+        pub location: String,
+        pub actor: String,
+        pub action: String,
+        pub details: String,
+        pub block_hash: String,
+    }
+}
+
+api_model! {
+    /// Environmental conditions data
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct EnvironmentalData {
+        pub temperature: Option<f64>,
+        pub humidity: Option<f64>,
+        pub co2_footprint: Option<f64>,
+        pub certifications: Vec<String>,
+    }
+}
+
+api_model! {
+    /// API error response
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct ApiError {
+        pub error: String,
+        pub message: String,
+        pub timestamp: DateTime<Utc>,
+    }
+}
+
+api_model! {
+    /// Who a block's named graph is shared with, replacing the current sharing
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    pub struct ShareGraphRequest {
+        #[serde(default)]
+        pub users: Vec<String>,
+        #[serde(default)]
+        pub roles: Vec<String>,
+    }
+}
+
+api_model! {
+    /// Confidential triples to commit, as Turtle
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct ConfidentialTriplesRequest {
+        pub data: String,
+    }
+}
+
+api_model! {
+    /// Disclose a confidential triple to another user
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct DiscloseTripleRequest {
+        pub recipient: String,
+    }
+}
+
+api_model! {
+    /// GraphQL request body
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct GraphQlRequest {
+        pub query: String,
+        /// Operation to run when the document holds several
+        #[serde(default)]
+        pub operation_name: Option<String>,
+        #[serde(default)]
+        pub variables: Option<serde_json::Map<String, serde_json::Value>>,
+    }
+}
+
+api_model! {
+    /// Register a webhook for chain events
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct RegisterWebhookRequest {
+        pub url: String,
+        /// Event types; empty means every type
+        #[serde(default)]
+        pub events: Vec<String>,
+        #[serde(default)]
+        pub filter: EventFilter,
+    }
+}
+
+api_model! {
+    /// Filter of a webhook's delivery history
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct WebhookDeliveriesQuery {
+        /// Only deliveries with this status, e.g. `dead_lettered` for the dead-letter queue
+        pub status: Option<DeliveryStatus>,
+    }
+}
+
+api_model! {
+    /// Authentication request
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct AuthRequest {
+        pub username: String,
+        pub password: String,
+    }
+}
+
+api_model! {
+    /// Authentication response
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct AuthResponse {
+        pub token: String,
+        pub expires_at: DateTime<Utc>,
+        pub user_role: String,
+        /// Single-use token exchanged at `/auth/refresh` for a new token pair
+        pub refresh_token: String,
+        pub refresh_expires_at: DateTime<Utc>,
+    }
+}
+
+api_model! {
+    /// Refresh-token exchange request
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct RefreshRequest {
+        pub refresh_token: String,
+    }
+}
+
+api_model! {
+    /// User claims for JWT
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct UserClaims {
+        pub sub: String, // user id
+        pub role: String,
+        pub exp: usize, // expiration timestamp
+        /// Token id, checked against the revocation list
+        #[serde(default)]
+        pub jti: String,
+    }
+}
+
+api_model! {
+    /// Admin request to create an API user
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct CreateUserRequest {
+        pub username: String,
+        pub password: String,
+        pub role: String,
+    }
+}
+
+api_model! {
+    /// API user as listed to admins, without the password hash
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct UserSummary {
+        pub username: String,
+        pub role: String,
+        pub failed_logins: u32,
+        pub locked_until: Option<DateTime<Utc>>,
+    }
+}
+
+api_model! {
+    /// Supply chain actor roles
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    pub enum ActorRole {
+        Farmer,
+        Processor,
+        Transporter,
+        Retailer,
+        Consumer,
+        Auditor,
+        Admin,
+    }
 }
 
 impl std::fmt::Display for ActorRole {
@@ -148,143 +294,233 @@ impl std::fmt::Display for ActorRole {
     }
 }
 
-/// Wallet registration request
-#[derive(Debug, Serialize, Deserialize)]
-pub struct WalletRegistrationRequest {
-    pub name: String,
-    pub participant_type: String,
-    pub contact_info: Option<ContactInfo>,
-    pub location: Option<String>,
-}
-
-/// Contact information for participants
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ContactInfo {
-    pub email: Option<String>,
-    pub phone: Option<String>,
-    pub address: Option<String>,
-    pub website: Option<String>,
-}
-
-/// Wallet registration response
-#[derive(Debug, Serialize, Deserialize)]
-pub struct WalletRegistrationResponse {
-    pub participant_id: String,
-    pub public_key: String,
-    pub message: String,
-    pub timestamp: DateTime<Utc>,
-}
-
-/// Transaction creation request
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CreateTransactionRequest {
-    pub tx_type: String,
-    pub inputs: Vec<TransactionInput>,
-    pub outputs: Vec<TransactionOutput>,
-    pub rdf_data: String,
-    pub metadata: TransactionMetadata,
-}
-
-/// Transaction input
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TransactionInput {
-    pub prev_tx_id: String,
-    pub output_index: u32,
-}
-
-/// Transaction output
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TransactionOutput {
-    pub id: String,
-    pub owner: String, // Participant ID
-    pub asset_type: String,
-    pub value: f64,
-    pub metadata: std::collections::HashMap<String, String>,
-}
-
-/// Transaction metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TransactionMetadata {
-    pub location: Option<String>,
-    pub environmental_conditions: Option<EnvironmentalConditions>,
-    pub compliance_info: Option<ComplianceInfo>,
-    pub quality_data: Option<QualityData>,
-    pub custom_fields: std::collections::HashMap<String, String>,
-}
-
-/// Environmental conditions
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EnvironmentalConditions {
-    pub temperature: Option<f64>,
-    pub humidity: Option<f64>,
-    pub pressure: Option<f64>,
-    pub timestamp: DateTime<Utc>,
-    pub sensor_id: Option<String>,
-}
-
-/// Compliance information
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ComplianceInfo {
-    pub regulation_type: String,
-    pub compliance_status: String,
-    pub certificate_id: Option<String>,
-    pub auditor_id: Option<String>, // Participant ID
-    pub expiry_date: Option<DateTime<Utc>>,
-}
-
-/// Quality data
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct QualityData {
-    pub test_type: String,
-    pub test_result: String,
-    pub test_value: Option<f64>,
-    pub test_unit: Option<String>,
-    pub lab_id: Option<String>, // Participant ID
-    pub test_timestamp: DateTime<Utc>,
-}
-
-/// Transaction creation response
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CreateTransactionResponse {
-    pub tx_id: String,
-    pub message: String,
-    pub timestamp: DateTime<Utc>,
-}
-
-/// Transaction signing request
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SignTransactionRequest {
-    pub tx_id: String,
-    pub participant_id: String,
-}
-
-/// Transaction signing response
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SignTransactionResponse {
-    pub tx_id: String,
-    pub signatures: Vec<TransactionSignatureInfo>,
-    pub message: String,
-    pub timestamp: DateTime<Utc>,
-}
-
-/// Transaction signature information
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TransactionSignatureInfo {
-    pub signer_id: String,
-    pub timestamp: DateTime<Utc>,
-}
-
-/// Transaction submission request
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SubmitTransactionRequest {
-    pub tx_id: String,
-}
-
-/// Transaction submission response
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SubmitTransactionResponse {
-    pub tx_id: String,
-    pub block_index: Option<usize>,
-    pub message: String,
-    pub timestamp: DateTime<Utc>,
+impl std::str::FromStr for ActorRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "farmer" => Ok(ActorRole::Farmer),
+            "processor" => Ok(ActorRole::Processor),
+            "transporter" => Ok(ActorRole::Transporter),
+            "retailer" => Ok(ActorRole::Retailer),
+            "consumer" => Ok(ActorRole::Consumer),
+            "auditor" => Ok(ActorRole::Auditor),
+            "admin" => Ok(ActorRole::Admin),
+            other => Err(format!("Unknown role '{}'", other)),
+        }
+    }
+}
+
+api_model! {
+    /// Wallet registration request
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct WalletRegistrationRequest {
+        pub name: String,
+        pub participant_type: String,
+        pub contact_info: Option<ContactInfo>,
+        pub location: Option<String>,
+    }
+}
+
+api_model! {
+    /// Contact information for participants
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ContactInfo {
+        pub email: Option<String>,
+        pub phone: Option<String>,
+        pub address: Option<String>,
+        pub website: Option<String>,
+    }
+}
+
+api_model! {
+    /// Wallet registration response
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct WalletRegistrationResponse {
+        pub participant_id: String,
+        pub public_key: String,
+        pub message: String,
+        pub timestamp: DateTime<Utc>,
+    }
+}
+
+api_model! {
+    /// Transaction creation request
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct CreateTransactionRequest {
+        pub tx_type: String,
+        pub inputs: Vec<TransactionInput>,
+        pub outputs: Vec<TransactionOutput>,
+        pub rdf_data: String,
+        pub metadata: TransactionMetadata,
+    }
+}
+
+api_model! {
+    /// Transaction input
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct TransactionInput {
+        pub prev_tx_id: String,
+        pub output_index: u32,
+    }
+}
+
+api_model! {
+    /// Transaction output
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct TransactionOutput {
+        pub id: String,
+        pub owner: String, // Participant ID
+        pub asset_type: String,
+        pub value: f64,
+        pub metadata: std::collections::HashMap<String, String>,
+    }
+}
+
+api_model! {
+    /// Transaction metadata
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct TransactionMetadata {
+        pub location: Option<String>,
+        pub environmental_conditions: Option<EnvironmentalConditions>,
+        pub compliance_info: Option<ComplianceInfo>,
+        pub quality_data: Option<QualityData>,
+        pub custom_fields: std::collections::HashMap<String, String>,
+    }
+}
+
+api_model! {
+    /// Environmental conditions
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct EnvironmentalConditions {
+        pub temperature: Option<f64>,
+        pub humidity: Option<f64>,
+        pub pressure: Option<f64>,
+        pub timestamp: DateTime<Utc>,
+        pub sensor_id: Option<String>,
+    }
+}
+
+api_model! {
+    /// Compliance information
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ComplianceInfo {
+        pub regulation_type: String,
+        pub compliance_status: String,
+        pub certificate_id: Option<String>,
+        pub auditor_id: Option<String>, // Participant ID
+        pub expiry_date: Option<DateTime<Utc>>,
+    }
+}
+
+api_model! {
+    /// Quality data
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct QualityData {
+        pub test_type: String,
+        pub test_result: String,
+        pub test_value: Option<f64>,
+        pub test_unit: Option<String>,
+        pub lab_id: Option<String>, // Participant ID
+        pub test_timestamp: DateTime<Utc>,
+    }
+}
+
+api_model! {
+    /// Transaction creation response
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct CreateTransactionResponse {
+        pub tx_id: String,
+        pub message: String,
+        pub timestamp: DateTime<Utc>,
+    }
+}
+
+api_model! {
+    /// Transaction signing request
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct SignTransactionRequest {
+        pub tx_id: String,
+        pub participant_id: String,
+    }
+}
+
+api_model! {
+    /// Transaction signing response
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct SignTransactionResponse {
+        pub tx_id: String,
+        pub signatures: Vec<TransactionSignatureInfo>,
+        pub message: String,
+        pub timestamp: DateTime<Utc>,
+    }
+}
+
+api_model! {
+    /// Transaction signature information
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct TransactionSignatureInfo {
+        pub signer_id: String,
+        pub timestamp: DateTime<Utc>,
+    }
+}
+
+api_model! {
+    /// Transaction submission request
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct SubmitTransactionRequest {
+        pub tx_id: String,
+    }
+}
+
+api_model! {
+    /// Transaction submission response
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct SubmitTransactionResponse {
+        pub tx_id: String,
+        pub block_index: Option<usize>,
+        pub message: String,
+        pub timestamp: DateTime<Utc>,
+    }
+}
+
+api_model! {
+    /// Current owner of a batch and the chain of transfers leading to them
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct BatchOwnershipResponse {
+        pub batch_id: String,
+        /// Participant owning the batch; absent once the batch was consumed, e.g. by processing
+        pub current_owner: Option<String>,
+        /// Transaction that consumed the batch
+        pub consumed_by: Option<String>,
+        /// Outputs of the batch from the one producing it to the latest transfer
+        pub history: Vec<OwnershipRecord>,
+    }
+}
+
+api_model! {
+    /// Nonce a participant's next signed transaction must carry
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct NextNonceResponse {
+        pub participant_id: String,
+        pub next_nonce: u64,
+        /// Number of blocks the nonce was derived from
+        pub block_height: u64,
+    }
+}
+
+api_model! {
+    /// One owner in the history of a batch
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct OwnershipRecord {
+        pub output_id: String,
+        pub owner: String,
+        /// Transaction that created the output
+        pub transaction_id: String,
+        pub transaction_type: String,
+        pub block_index: u64,
+        pub timestamp: DateTime<Utc>,
+        /// Transaction that spent the output, passing the batch on
+        pub spent_by: Option<String>,
+    }
 }
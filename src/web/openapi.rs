@@ -0,0 +1,1217 @@
+//! OpenAPI 3 description of the REST API, served at `/api/openapi.json`
+//!
+//! Request and response models are declared through `api_model!`, which keeps each struct
+//! definition and its JSON schema in one place, and `operations` lists every route with
+//! the models it takes and returns. Whether an operation needs a token, and which
+//! permission, is read from `ROUTE_PERMISSIONS`.
+//!
+//! The checked-in copy of the document at `provchain-client/openapi.json` is what the
+//! typed Rust client is generated from; refresh it with `provchain-org openapi --out
+//! provchain-client/openapi.json` after changing a model or a route.
+
+use crate::web::permissions::ROUTE_PERMISSIONS;
+use axum::http::Method;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// A type with a JSON schema in the OpenAPI document
+pub trait ApiSchema {
+    /// Name under `components/schemas`; unnamed types are inlined where they are used
+    fn name() -> Option<&'static str> {
+        None
+    }
+
+    /// Schema of the type, registering the named types it refers to in `components`
+    fn schema(components: &mut Components) -> Value;
+
+    /// Whether a field of this type may be absent
+    fn optional() -> bool {
+        false
+    }
+}
+
+/// Named schemas collected while the document is built
+#[derive(Debug, Default)]
+pub struct Components {
+    schemas: BTreeMap<String, Value>,
+}
+
+impl Components {
+    /// A `$ref` to `T` for named types, registering it on first use, else `T`'s schema
+    pub fn schema_of<T: ApiSchema>(&mut self) -> Value {
+        let Some(name) = T::name() else {
+            return T::schema(self);
+        };
+        if !self.schemas.contains_key(name) {
+            // Placeholder first, so self-referencing models terminate
+            self.schemas.insert(name.to_string(), Value::Null);
+            let schema = T::schema(self);
+            self.schemas.insert(name.to_string(), schema);
+        }
+        json!({ "$ref": format!("#/components/schemas/{}", name) })
+    }
+}
+
+/// Declares a model struct, or an enum of unit variants, together with its `ApiSchema`.
+///
+/// Doc comments become descriptions, and the `serde` attributes that change the JSON
+/// shape are honoured: `rename_all` on the type, `default` on the type or a field, and
+/// `rename` on a field or variant.
+macro_rules! api_model {
+    (
+        $(#[$($attr:tt)*])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$($field_attr:tt)*])*
+                $field_vis:vis $field:ident : $ty:ty
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$($attr)*])*
+        $vis struct $name {
+            $(
+                $(#[$($field_attr)*])*
+                $field_vis $field: $ty,
+            )*
+        }
+
+        impl $crate::web::openapi::ApiSchema for $name {
+            fn name() -> Option<&'static str> {
+                Some(stringify!($name))
+            }
+
+            fn schema(components: &mut $crate::web::openapi::Components) -> serde_json::Value {
+                #[allow(unused_mut)]
+                let mut model = $crate::web::openapi::ModelSchema::default();
+                $( $crate::web::openapi::model_attr!(model, $($attr)*); )*
+                $(
+                    #[allow(unused_mut)]
+                    let mut member = $crate::web::openapi::Member::field::<$ty>(
+                        stringify!($field),
+                        components,
+                    );
+                    $( $crate::web::openapi::member_attr!(member, $($field_attr)*); )*
+                    model.member(member);
+                )*
+                model.object()
+            }
+        }
+    };
+    (
+        $(#[$($attr:tt)*])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[$($variant_attr:tt)*])*
+                $variant:ident
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$($attr)*])*
+        $vis enum $name {
+            $(
+                $(#[$($variant_attr)*])*
+                $variant,
+            )*
+        }
+
+        impl $crate::web::openapi::ApiSchema for $name {
+            fn name() -> Option<&'static str> {
+                Some(stringify!($name))
+            }
+
+            fn schema(_: &mut $crate::web::openapi::Components) -> serde_json::Value {
+                #[allow(unused_mut)]
+                let mut model = $crate::web::openapi::ModelSchema::default();
+                $( $crate::web::openapi::model_attr!(model, $($attr)*); )*
+                $(
+                    #[allow(unused_mut)]
+                    let mut member = $crate::web::openapi::Member::variant(stringify!($variant));
+                    $( $crate::web::openapi::member_attr!(member, $($variant_attr)*); )*
+                    model.member(member);
+                )*
+                model.string_enum()
+            }
+        }
+    };
+}
+
+/// Applies one attribute of an `api_model!` type
+macro_rules! model_attr {
+    ($model:ident, doc = $doc:literal) => {
+        $model.doc($doc);
+    };
+    ($model:ident, serde(rename_all = $rule:literal)) => {
+        $model.rename_all($rule);
+    };
+    ($model:ident, serde(default)) => {
+        $model.default_fields();
+    };
+    ($model:ident, $($other:tt)*) => {};
+}
+
+/// Applies one attribute of an `api_model!` field or variant
+macro_rules! member_attr {
+    ($member:ident, doc = $doc:literal) => {
+        $member.doc($doc);
+    };
+    ($member:ident, serde(rename = $name:literal)) => {
+        $member.rename($name);
+    };
+    ($member:ident, serde(default $($rest:tt)*)) => {
+        $member.defaulted();
+    };
+    ($member:ident, $($other:tt)*) => {};
+}
+
+pub(crate) use api_model;
+pub(crate) use member_attr;
+pub(crate) use model_attr;
+
+/// Schema of an `api_model!` type, assembled from its attributes and members
+#[derive(Debug, Default)]
+pub struct ModelSchema {
+    doc: Vec<&'static str>,
+    rename_all: Option<&'static str>,
+    default: bool,
+    members: Vec<Member>,
+}
+
+impl ModelSchema {
+    pub fn doc(&mut self, line: &'static str) {
+        self.doc.push(line);
+    }
+
+    pub fn rename_all(&mut self, rule: &'static str) {
+        self.rename_all = Some(rule);
+    }
+
+    /// Every field falls back to its default when absent
+    pub fn default_fields(&mut self) {
+        self.default = true;
+    }
+
+    pub fn member(&mut self, member: Member) {
+        self.members.push(member);
+    }
+
+    /// Schema of a struct
+    pub fn object(self) -> Value {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        for member in &self.members {
+            let name = member.json_name(self.rename_all);
+            let mut schema = member.schema.clone();
+            if let Some(description) = description(&member.doc) {
+                if schema.get("$ref").is_some() {
+                    // Siblings of `$ref` are ignored in OpenAPI 3.0
+                    schema = json!({ "allOf": [schema] });
+                }
+                schema["description"] = Value::String(description);
+            }
+            if !(member.optional || member.default || self.default) {
+                required.push(Value::String(name.clone()));
+            }
+            properties.insert(name, schema);
+        }
+
+        let mut schema = json!({ "type": "object", "properties": properties });
+        if !required.is_empty() {
+            schema["required"] = Value::Array(required);
+        }
+        if let Some(description) = description(&self.doc) {
+            schema["description"] = Value::String(description);
+        }
+        schema
+    }
+
+    /// Schema of an enum of unit variants, serialized as strings
+    pub fn string_enum(self) -> Value {
+        let names: Vec<String> = self
+            .members
+            .iter()
+            .map(|variant| variant.json_name(self.rename_all))
+            .collect();
+
+        let mut lines: Vec<String> = description(&self.doc).into_iter().collect();
+        let documented: Vec<String> = self
+            .members
+            .iter()
+            .zip(&names)
+            .filter_map(|(variant, name)| {
+                description(&variant.doc).map(|doc| format!("- `{}`: {}", name, doc))
+            })
+            .collect();
+        if !documented.is_empty() {
+            lines.push(documented.join("\n"));
+        }
+
+        let mut schema = json!({ "type": "string", "enum": names });
+        if !lines.is_empty() {
+            schema["description"] = Value::String(lines.join("\n\n"));
+        }
+        schema
+    }
+}
+
+/// A field of a struct or a variant of an enum
+#[derive(Debug)]
+pub struct Member {
+    name: &'static str,
+    variant: bool,
+    rename: Option<&'static str>,
+    doc: Vec<&'static str>,
+    default: bool,
+    optional: bool,
+    schema: Value,
+}
+
+impl Member {
+    pub fn field<T: ApiSchema>(name: &'static str, components: &mut Components) -> Self {
+        Self {
+            name,
+            variant: false,
+            rename: None,
+            doc: Vec::new(),
+            default: false,
+            optional: T::optional(),
+            schema: components.schema_of::<T>(),
+        }
+    }
+
+    pub fn variant(name: &'static str) -> Self {
+        Self {
+            name,
+            variant: true,
+            rename: None,
+            doc: Vec::new(),
+            default: false,
+            optional: false,
+            schema: Value::Null,
+        }
+    }
+
+    pub fn doc(&mut self, line: &'static str) {
+        self.doc.push(line);
+    }
+
+    pub fn rename(&mut self, name: &'static str) {
+        self.rename = Some(name);
+    }
+
+    pub fn defaulted(&mut self) {
+        self.default = true;
+    }
+
+    /// Name in JSON, after `rename` or the type's `rename_all`
+    fn json_name(&self, rename_all: Option<&str>) -> String {
+        match (self.rename, rename_all) {
+            (Some(name), _) => name.to_string(),
+            (None, Some(rule)) => rename(self.name, rule, self.variant),
+            (None, None) => self.name.to_string(),
+        }
+    }
+}
+
+/// Joins doc comment lines, keeping blank lines as paragraph breaks
+fn description(lines: &[&str]) -> Option<String> {
+    let mut paragraphs: Vec<String> = vec![String::new()];
+    for line in lines {
+        let line = line.trim();
+        let current = paragraphs.last_mut().expect("never empty");
+        if line.is_empty() {
+            if !current.is_empty() {
+                paragraphs.push(String::new());
+            }
+        } else {
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(line);
+        }
+    }
+    paragraphs.retain(|paragraph| !paragraph.is_empty());
+    (!paragraphs.is_empty()).then(|| paragraphs.join("\n\n"))
+}
+
+/// Applies a serde `rename_all` rule to a snake_case field or PascalCase variant name
+fn rename(name: &str, rule: &str, variant: bool) -> String {
+    let words: Vec<String> = if variant {
+        let mut words: Vec<String> = Vec::new();
+        for c in name.chars() {
+            match words.last_mut() {
+                Some(word) if !c.is_uppercase() => word.push(c),
+                _ => words.push(c.to_lowercase().collect()),
+            }
+        }
+        words
+    } else {
+        name.split('_').map(str::to_string).collect()
+    };
+    let pascal: String = words
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<String>>()
+        .concat();
+
+    match rule {
+        "lowercase" if variant => words.concat(),
+        "UPPERCASE" if variant => words.concat().to_uppercase(),
+        "lowercase" => name.to_lowercase(),
+        "UPPERCASE" => name.to_uppercase(),
+        "PascalCase" => pascal,
+        "camelCase" => {
+            let mut chars = pascal.chars();
+            chars
+                .next()
+                .map(|first| first.to_lowercase().chain(chars).collect())
+                .unwrap_or_default()
+        }
+        "snake_case" => words.join("_"),
+        "SCREAMING_SNAKE_CASE" => words.join("_").to_uppercase(),
+        "kebab-case" => words.join("-"),
+        "SCREAMING-KEBAB-CASE" => words.join("-").to_uppercase(),
+        _ => name.to_string(),
+    }
+}
+
+macro_rules! scalar_schema {
+    ($($ty:ty => $schema:tt),* $(,)?) => {
+        $(
+            impl ApiSchema for $ty {
+                fn schema(_: &mut Components) -> Value {
+                    json!($schema)
+                }
+            }
+        )*
+    };
+}
+
+scalar_schema! {
+    bool => { "type": "boolean" },
+    u8 => { "type": "integer", "format": "uint8", "minimum": 0 },
+    u16 => { "type": "integer", "format": "uint16", "minimum": 0 },
+    u32 => { "type": "integer", "format": "uint32", "minimum": 0 },
+    u64 => { "type": "integer", "format": "uint64", "minimum": 0 },
+    usize => { "type": "integer", "format": "uint64", "minimum": 0 },
+    i32 => { "type": "integer", "format": "int32" },
+    i64 => { "type": "integer", "format": "int64" },
+    f32 => { "type": "number", "format": "float" },
+    f64 => { "type": "number", "format": "double" },
+    String => { "type": "string" },
+    DateTime<Utc> => { "type": "string", "format": "date-time" },
+    Value => {},
+    Map<String, Value> => { "type": "object", "additionalProperties": {} },
+}
+
+impl<T: ApiSchema> ApiSchema for Option<T> {
+    fn schema(components: &mut Components) -> Value {
+        let schema = components.schema_of::<T>();
+        if schema.get("$ref").is_some() {
+            json!({ "allOf": [schema], "nullable": true })
+        } else {
+            let mut schema = schema;
+            schema["nullable"] = Value::Bool(true);
+            schema
+        }
+    }
+
+    fn optional() -> bool {
+        true
+    }
+}
+
+impl<T: ApiSchema> ApiSchema for Vec<T> {
+    fn schema(components: &mut Components) -> Value {
+        json!({ "type": "array", "items": components.schema_of::<T>() })
+    }
+}
+
+impl<T: ApiSchema> ApiSchema for BTreeSet<T> {
+    fn schema(components: &mut Components) -> Value {
+        json!({ "type": "array", "items": components.schema_of::<T>(), "uniqueItems": true })
+    }
+}
+
+impl<T: ApiSchema, S> ApiSchema for HashSet<T, S> {
+    fn schema(components: &mut Components) -> Value {
+        json!({ "type": "array", "items": components.schema_of::<T>(), "uniqueItems": true })
+    }
+}
+
+impl<V: ApiSchema> ApiSchema for BTreeMap<String, V> {
+    fn schema(components: &mut Components) -> Value {
+        json!({ "type": "object", "additionalProperties": components.schema_of::<V>() })
+    }
+}
+
+impl<V: ApiSchema, S> ApiSchema for HashMap<String, V, S> {
+    fn schema(components: &mut Components) -> Value {
+        json!({ "type": "object", "additionalProperties": components.schema_of::<V>() })
+    }
+}
+
+/// Disclosure proofs embed a block inclusion proof, checked as a whole by
+/// `verify-proof`; clients pass them on without looking inside
+impl ApiSchema for crate::core::disclosure::DisclosureProof {
+    fn name() -> Option<&'static str> {
+        Some("DisclosureProof")
+    }
+
+    fn schema(_: &mut Components) -> Value {
+        json!({
+            "type": "object",
+            "description": "A disclosed triple with its salt and the inclusion proof of its \
+                            commitment, as returned by the disclose and proof endpoints",
+        })
+    }
+}
+
+impl ApiSchema for crate::trace_optimization::EnhancedTraceResult {
+    fn name() -> Option<&'static str> {
+        Some("EnhancedTraceResult")
+    }
+
+    fn schema(_: &mut Components) -> Value {
+        json!({
+            "type": "object",
+            "description": "Trace path found by the optimized tracer, with the number of \
+                            entities explored and the time taken",
+        })
+    }
+}
+
+/// Builds the schema of a request, response or parameter set
+type SchemaFn = fn(&mut Components) -> Value;
+
+/// Body of a request or a response
+#[derive(Clone, Copy)]
+enum Content {
+    None,
+    Json(SchemaFn),
+    /// Text of the given media type, e.g. a SPARQL query or SPARQL results
+    Text(&'static str),
+}
+
+/// JSON body holding a `T`
+fn json<T: ApiSchema>() -> Content {
+    Content::Json(Components::schema_of::<T>)
+}
+
+/// One route and method of the API
+struct Operation {
+    method: Method,
+    path: &'static str,
+    id: &'static str,
+    summary: &'static str,
+    query: Option<SchemaFn>,
+    request: Content,
+    status: u16,
+    response: Content,
+}
+
+impl Operation {
+    fn new(method: Method, path: &'static str, id: &'static str, summary: &'static str) -> Self {
+        Self {
+            method,
+            path,
+            id,
+            summary,
+            query: None,
+            request: Content::None,
+            status: 200,
+            response: json::<Value>(),
+        }
+    }
+
+    /// Query parameters, one per field of `T`
+    fn query<T: ApiSchema>(mut self) -> Self {
+        self.query = Some(T::schema);
+        self
+    }
+
+    fn body(mut self, request: Content) -> Self {
+        self.request = request;
+        self
+    }
+
+    fn returns(mut self, response: Content) -> Self {
+        self.response = response;
+        self
+    }
+
+    fn created(mut self) -> Self {
+        self.status = 201;
+        self
+    }
+
+    fn no_content(mut self) -> Self {
+        self.status = 204;
+        self.response = Content::None;
+        self
+    }
+}
+
+fn get(path: &'static str, id: &'static str, summary: &'static str) -> Operation {
+    Operation::new(Method::GET, path, id, summary)
+}
+
+fn post(path: &'static str, id: &'static str, summary: &'static str) -> Operation {
+    Operation::new(Method::POST, path, id, summary)
+}
+
+fn put(path: &'static str, id: &'static str, summary: &'static str) -> Operation {
+    Operation::new(Method::PUT, path, id, summary)
+}
+
+fn delete(path: &'static str, id: &'static str, summary: &'static str) -> Operation {
+    Operation::new(Method::DELETE, path, id, summary)
+}
+
+/// Every REST operation, named after its handler. The WebSocket endpoint `/ws` and the
+/// static web UI are not part of the document.
+fn operations() -> Vec<Operation> {
+    use crate::web::graph_access::GraphAccess;
+    use crate::web::handlers::{
+        AnalyticsQueryParams, EnhancedTraceQueryParams, KnowledgeGraphParams, ProductsQueryParams,
+        TracePathParams, TraceQueryParams,
+    };
+    use crate::web::models::*;
+    use crate::web::saved_queries::{SavedQuery, SavedQueryDraft};
+    use crate::web::sparql::SparqlProtocolParams;
+    use crate::web::webhooks::Delivery;
+
+    use crate::core::disclosure::DisclosureProof;
+    use crate::trace_optimization::EnhancedTraceResult;
+
+    const SPARQL_RESULTS: Content = Content::Text("application/sparql-results+json");
+
+    vec![
+        // Public
+        get(
+            "/health",
+            "health_check",
+            "Health check with security status",
+        ),
+        get("/api/openapi.json", "get_openapi_document", "This document"),
+        post(
+            "/auth/login",
+            "authenticate",
+            "Log in with a username and password",
+        )
+        .body(json::<AuthRequest>())
+        .returns(json::<AuthResponse>()),
+        post(
+            "/auth/refresh",
+            "refresh_token",
+            "Exchange a refresh token for a new token pair",
+        )
+        .body(json::<RefreshRequest>())
+        .returns(json::<AuthResponse>()),
+        get(
+            "/api/trace",
+            "trace_path_api",
+            "Shortest path between two entities",
+        )
+        .query::<TracePathParams>(),
+        get(
+            "/api/knowledge-graph",
+            "get_knowledge_graph",
+            "Knowledge graph around items",
+        )
+        .query::<KnowledgeGraphParams>(),
+        // Blockchain
+        get(
+            "/api/blockchain/status",
+            "get_blockchain_status",
+            "Blockchain status",
+        ),
+        get("/api/blockchain/blocks", "get_blocks", "All blocks"),
+        get(
+            "/api/blockchain/blocks/:index",
+            "get_block",
+            "Block by index",
+        )
+        .returns(json::<BlockInfo>()),
+        get(
+            "/api/blockchain/blocks/:index/rdf-summary",
+            "get_block_rdf_summary",
+            "RDF summary of a block's named graph",
+        ),
+        get(
+            "/api/blockchain/blocks/:index/access",
+            "get_block_access",
+            "Owner and sharing of a block's named graph",
+        ),
+        put(
+            "/api/blockchain/blocks/:index/access",
+            "share_block_graph",
+            "Replace who a block's named graph is shared with",
+        )
+        .body(json::<ShareGraphRequest>())
+        .returns(json::<GraphAccess>()),
+        get(
+            "/api/blockchain/validate",
+            "validate_blockchain",
+            "Validate chain integrity",
+        ),
+        post(
+            "/api/blockchain/add-triple",
+            "add_triple",
+            "Add a triple in a new block",
+        )
+        .body(json::<AddTripleRequest>()),
+        get(
+            "/api/transactions/recent",
+            "get_recent_transactions",
+            "Recent transactions",
+        ),
+        get("/api/analytics", "get_analytics", "Supply chain analytics")
+            .query::<AnalyticsQueryParams>(),
+        // Queries
+        post(
+            "/api/sparql/query",
+            "execute_sparql_query",
+            "Run a SPARQL query",
+        )
+        .body(json::<SparqlQueryRequest>())
+        .returns(json::<SparqlQueryResponse>()),
+        get(
+            "/api/sparql/config",
+            "get_sparql_config",
+            "Query builder configuration",
+        ),
+        post(
+            "/api/sparql/validate",
+            "validate_sparql_endpoint",
+            "Check SPARQL syntax",
+        )
+        .body(Content::Text("text/plain")),
+        get("/sparql", "sparql_get", "SPARQL 1.1 Protocol query")
+            .query::<SparqlProtocolParams>()
+            .returns(SPARQL_RESULTS),
+        post(
+            "/sparql",
+            "sparql_post",
+            "SPARQL 1.1 Protocol query in the request body",
+        )
+        .body(Content::Text("application/sparql-query"))
+        .returns(SPARQL_RESULTS),
+        get(
+            "/api/graphql",
+            "get_graphql_schema",
+            "GraphQL schema in SDL",
+        )
+        .returns(Content::Text("text/plain")),
+        post("/api/graphql", "graphql_query", "Run a GraphQL query").body(json::<GraphQlRequest>()),
+        get(
+            "/api/sparql/queries",
+            "get_saved_sparql_queries",
+            "Saved queries visible to the caller",
+        )
+        .returns(json::<Vec<Value>>()),
+        post("/api/sparql/queries", "save_sparql_query", "Save a query")
+            .body(json::<SavedQueryDraft>())
+            .created(),
+        get(
+            "/api/sparql/queries/:id",
+            "get_saved_sparql_query",
+            "Saved query with its versions",
+        )
+        .returns(json::<SavedQuery>()),
+        put(
+            "/api/sparql/queries/:id",
+            "update_sparql_query",
+            "Save a new version of a query",
+        )
+        .body(json::<SavedQueryDraft>()),
+        delete(
+            "/api/sparql/queries/:id",
+            "delete_sparql_query",
+            "Delete a saved query",
+        )
+        .no_content(),
+        post(
+            "/api/sparql/queries/:id/run",
+            "run_saved_sparql_query",
+            "Run a saved query with parameter bindings",
+        )
+        .body(json::<RunSavedQueryRequest>())
+        .returns(SPARQL_RESULTS),
+        post(
+            "/api/sparql/queries/:id/favorite",
+            "toggle_favorite_sparql_query",
+            "Mark or unmark a saved query as favorite",
+        ),
+        // Traceability
+        get(
+            "/api/products/trace",
+            "get_product_trace",
+            "Trace of a product batch",
+        )
+        .query::<TraceQueryParams>()
+        .returns(json::<ProductTrace>()),
+        get(
+            "/api/products/trace/enhanced",
+            "get_enhanced_product_trace",
+            "Optimized trace of a product batch",
+        )
+        .query::<EnhancedTraceQueryParams>()
+        .returns(json::<EnhancedTraceResult>()),
+        get(
+            "/api/products",
+            "get_products",
+            "Products, filtered and paginated",
+        )
+        .query::<ProductsQueryParams>(),
+        get("/api/products/:id", "get_product_by_id", "Product by id"),
+        get(
+            "/api/products/:id/trace",
+            "get_product_trace_path",
+            "Trace path of a product",
+        ),
+        get(
+            "/api/products/:id/provenance",
+            "get_product_provenance",
+            "Provenance chain of a product",
+        )
+        .returns(json::<Vec<Value>>()),
+        get(
+            "/api/products/:id/analytics",
+            "get_product_analytics",
+            "Analytics of a product",
+        ),
+        get(
+            "/api/products/by-type/:type",
+            "get_products_by_type",
+            "Products of a type",
+        )
+        .returns(json::<Vec<Value>>()),
+        get(
+            "/api/products/by-participant/:participantId",
+            "get_products_by_participant",
+            "Products handled by a participant",
+        )
+        .returns(json::<Vec<Value>>()),
+        get(
+            "/api/products/:id/related",
+            "get_related_items",
+            "Items related to an item",
+        )
+        .returns(json::<Vec<Value>>()),
+        get(
+            "/api/products/:id/validate",
+            "validate_item",
+            "Validate an item",
+        ),
+        get(
+            "/api/batches/:id/ownership",
+            "get_batch_ownership",
+            "Current owner of a batch and its chain of transfers",
+        )
+        .returns(json::<BatchOwnershipResponse>()),
+        get(
+            "/api/participants/:id/nonce",
+            "get_next_nonce",
+            "Nonce the participant's next signed transaction must carry",
+        )
+        .returns(json::<NextNonceResponse>()),
+        // Confidential triples
+        get(
+            "/api/confidential/triples",
+            "get_confidential_triples",
+            "Confidential triples the caller owns or was disclosed",
+        ),
+        post(
+            "/api/confidential/triples",
+            "commit_confidential_triples",
+            "Commit confidential triples on chain",
+        )
+        .body(json::<ConfidentialTriplesRequest>())
+        .created(),
+        post(
+            "/api/confidential/:commitment/disclose",
+            "disclose_confidential_triple",
+            "Disclose a confidential triple to another user",
+        )
+        .body(json::<DiscloseTripleRequest>())
+        .returns(json::<DisclosureProof>()),
+        get(
+            "/api/confidential/:commitment/proof",
+            "get_disclosure_proof",
+            "Disclosure proof of a confidential triple",
+        )
+        .returns(json::<DisclosureProof>()),
+        post(
+            "/api/confidential/verify",
+            "verify_disclosure",
+            "Verify a disclosure proof",
+        )
+        .body(json::<DisclosureProof>()),
+        // Webhooks
+        get("/api/webhooks", "list_webhooks", "Webhooks of the caller"),
+        post(
+            "/api/webhooks",
+            "register_webhook",
+            "Register a webhook for chain events",
+        )
+        .body(json::<RegisterWebhookRequest>())
+        .created(),
+        get("/api/webhooks/:id", "get_webhook", "Webhook by id"),
+        delete("/api/webhooks/:id", "delete_webhook", "Delete a webhook").no_content(),
+        get(
+            "/api/webhooks/:id/deliveries",
+            "get_webhook_deliveries",
+            "Delivery history of a webhook, newest first",
+        )
+        .query::<WebhookDeliveriesQuery>(),
+        post(
+            "/api/webhooks/:id/redeliver/:delivery",
+            "redeliver_webhook_delivery",
+            "Queue a dead-lettered delivery again",
+        )
+        .returns(json::<Delivery>()),
+        // Wallets and transactions
+        post(
+            "/api/wallet/register",
+            "register_wallet",
+            "Register a participant wallet",
+        )
+        .body(json::<WalletRegistrationRequest>())
+        .returns(json::<WalletRegistrationResponse>()),
+        post(
+            "/api/participants",
+            "create_participant",
+            "Create a participant",
+        )
+        .body(json::<Value>()),
+        post(
+            "/api/transactions/create",
+            "create_transaction",
+            "Create a transaction",
+        )
+        .body(json::<CreateTransactionRequest>())
+        .returns(json::<CreateTransactionResponse>()),
+        post(
+            "/api/transactions/sign",
+            "sign_transaction",
+            "Sign a transaction",
+        )
+        .body(json::<SignTransactionRequest>())
+        .returns(json::<SignTransactionResponse>()),
+        post(
+            "/api/transactions/submit",
+            "submit_transaction",
+            "Submit a signed transaction",
+        )
+        .body(json::<SubmitTransactionRequest>())
+        .returns(json::<SubmitTransactionResponse>()),
+        // Accounts
+        post("/api/auth/logout", "logout", "Revoke the current session").no_content(),
+        get("/api/admin/users", "list_user_accounts", "API users")
+            .returns(json::<Vec<UserSummary>>()),
+        post(
+            "/api/admin/users",
+            "create_user_account",
+            "Create an API user",
+        )
+        .body(json::<CreateUserRequest>())
+        .created()
+        .returns(json::<UserSummary>()),
+        delete(
+            "/api/admin/users/:username",
+            "delete_user_account",
+            "Delete an API user",
+        )
+        .no_content(),
+        post(
+            "/api/admin/users/:username/unlock",
+            "unlock_user_account",
+            "Unlock an API user locked out by failed logins",
+        )
+        .returns(json::<UserSummary>()),
+    ]
+}
+
+/// The OpenAPI 3 document of the REST API
+pub fn document() -> Value {
+    let mut components = Components::default();
+    let error = components.schema_of::<crate::web::models::ApiError>();
+
+    let mut paths = Map::new();
+    for operation in operations() {
+        let item = paths
+            .entry(openapi_path(operation.path))
+            .or_insert_with(|| json!({}));
+        item[operation.method.as_str().to_lowercase()] =
+            operation_object(&operation, &mut components, &error);
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "ProvChain API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Supply chain traceability on an RDF blockchain. Operations other \
+                            than login, refresh, health and public tracing need a bearer \
+                            token from `/auth/login`; `x-permission` names the permission \
+                            the caller's role must have.",
+        },
+        "paths": paths,
+        "components": {
+            "schemas": components.schemas,
+            "securitySchemes": {
+                "bearerAuth": { "type": "http", "scheme": "bearer", "bearerFormat": "JWT" },
+            },
+        },
+        "security": [{ "bearerAuth": [] }],
+    })
+}
+
+fn operation_object(operation: &Operation, components: &mut Components, error: &Value) -> Value {
+    let mut parameters: Vec<Value> = operation
+        .path
+        .split('/')
+        .filter_map(|segment| segment.strip_prefix(':'))
+        .map(|name| {
+            json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": path_parameter_schema(name),
+            })
+        })
+        .collect();
+    if let Some(query) = operation.query {
+        parameters.extend(query_parameters(query(components)));
+    }
+
+    let mut object = json!({
+        "operationId": operation.id,
+        "summary": operation.summary,
+        "tags": [tag(operation.path)],
+    });
+    if !parameters.is_empty() {
+        object["parameters"] = Value::Array(parameters);
+    }
+    if let Some(content) = content(operation.request, components) {
+        object["requestBody"] = json!({ "required": true, "content": content });
+    }
+
+    let mut success = json!({ "description": status_description(operation.status) });
+    if let Some(content) = content(operation.response, components) {
+        success["content"] = content;
+    }
+    object["responses"] = json!({
+        operation.status.to_string(): success,
+        "default": {
+            "description": "Error",
+            "content": { "application/json": { "schema": error } },
+        },
+    });
+
+    match ROUTE_PERMISSIONS
+        .iter()
+        .find(|(method, path, _)| *method == operation.method && *path == operation.path)
+    {
+        Some((_, _, permission)) => object["x-permission"] = json!(format!("{:?}", permission)),
+        None => object["security"] = json!([]),
+    }
+    object
+}
+
+/// `:name` segments as `{name}`
+fn openapi_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(name) => format!("{{{}}}", name),
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Block indexes are the only numeric path parameters
+fn path_parameter_schema(name: &str) -> Value {
+    match name {
+        "index" => json!({ "type": "integer", "format": "uint64", "minimum": 0 }),
+        _ => json!({ "type": "string" }),
+    }
+}
+
+/// One query parameter per property of an object schema
+fn query_parameters(schema: Value) -> Vec<Value> {
+    let required: HashSet<&str> = schema["required"]
+        .as_array()
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let Some(properties) = schema["properties"].as_object() else {
+        return Vec::new();
+    };
+    properties
+        .iter()
+        .map(|(name, property)| {
+            let mut property = property.clone();
+            let description = property
+                .as_object_mut()
+                .and_then(|property| property.remove("description"));
+            let mut parameter = json!({
+                "name": name,
+                "in": "query",
+                "required": required.contains(name.as_str()),
+                "schema": property,
+            });
+            if let Some(description) = description {
+                parameter["description"] = description;
+            }
+            parameter
+        })
+        .collect()
+}
+
+fn content(content: Content, components: &mut Components) -> Option<Value> {
+    match content {
+        Content::None => None,
+        Content::Json(schema) => {
+            Some(json!({ "application/json": { "schema": schema(components) } }))
+        }
+        Content::Text(media_type) => {
+            Some(json!({ media_type: { "schema": { "type": "string" } } }))
+        }
+    }
+}
+
+fn status_description(status: u16) -> &'static str {
+    match status {
+        201 => "Created",
+        204 => "No content",
+        _ => "OK",
+    }
+}
+
+/// Groups operations by the first path segment after `/api`
+fn tag(path: &str) -> &str {
+    let path = path.strip_prefix("/api").unwrap_or(path);
+    let segment = path
+        .split('/')
+        .find(|segment| !segment.is_empty())
+        .unwrap_or("");
+    segment.split('.').next().unwrap_or(segment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_protected_route_is_documented() {
+        let operations = operations();
+        for (method, path, _) in ROUTE_PERMISSIONS {
+            assert!(
+                operations
+                    .iter()
+                    .any(|operation| operation.method == method && operation.path == *path),
+                "{} {} is missing from the OpenAPI document",
+                method,
+                path
+            );
+        }
+
+        let mut ids = HashSet::new();
+        for operation in &operations {
+            assert!(
+                ids.insert(operation.id),
+                "duplicate operation {}",
+                operation.id
+            );
+        }
+    }
+
+    #[test]
+    fn test_models_follow_serde_attributes() {
+        let document = document();
+        let schemas = &document["components"]["schemas"];
+
+        let request = &schemas["GraphQlRequest"];
+        assert!(request["properties"]["operationName"].is_object());
+        assert_eq!(request["required"], json!(["query"]));
+        assert_eq!(
+            request["properties"]["operationName"]["description"],
+            "Operation to run when the document holds several"
+        );
+
+        let status = &schemas["DeliveryStatus"];
+        assert_eq!(
+            status["enum"],
+            json!(["pending", "delivered", "dead_lettered"])
+        );
+
+        // `serde(default)` fields and options are not required; nested models are refs
+        let webhook = &schemas["RegisterWebhookRequest"];
+        assert_eq!(webhook["required"], json!(["url"]));
+        assert_eq!(
+            webhook["properties"]["filter"]["$ref"],
+            "#/components/schemas/EventFilter"
+        );
+        assert!(schemas["EventFilter"]["description"]
+            .as_str()
+            .unwrap()
+            .starts_with("Narrows the events a client receives."));
+
+        let block = &schemas["BlockInfo"]["properties"];
+        assert_eq!(block["timestamp"]["format"], "date-time");
+        assert_eq!(block["index"]["type"], "integer");
+    }
+
+    #[test]
+    fn test_operations_carry_parameters_and_security() {
+        let document = document();
+        let paths = &document["paths"];
+
+        let get_block = &paths["/api/blockchain/blocks/{index}"]["get"];
+        assert_eq!(get_block["x-permission"], "Read");
+        assert_eq!(get_block["parameters"][0]["in"], "path");
+        assert_eq!(
+            get_block["responses"]["200"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/BlockInfo"
+        );
+
+        let login = &paths["/auth/login"]["post"];
+        assert_eq!(login["security"], json!([]));
+
+        let products = &paths["/api/products"]["get"]["parameters"];
+        let names: Vec<&str> = products
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|parameter| parameter["name"].as_str().unwrap())
+            .collect();
+        assert!(names.contains(&"type"));
+        assert!(names.contains(&"page"));
+
+        let deliveries = &paths["/api/webhooks/{id}/deliveries"]["get"]["parameters"][1];
+        assert_eq!(deliveries["name"], "status");
+        assert_eq!(deliveries["required"], false);
+
+        let delete = &paths["/api/webhooks/{id}"]["delete"]["responses"];
+        assert!(delete["204"].get("content").is_none());
+    }
+
+    #[test]
+    fn test_client_spec_is_up_to_date() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/provchain-client/openapi.json");
+        let checked_in: Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert!(
+            checked_in == document(),
+            "provchain-client/openapi.json is stale; regenerate it with \
+             `provchain-org openapi --out provchain-client/openapi.json`"
+        );
+    }
+}
@@ -0,0 +1,414 @@
+//! Role-based authorization for API routes
+//!
+//! Every protected route maps to one `Permission` in `ROUTE_PERMISSIONS`, and
+//! `authorize_middleware` checks the caller's role against it after `auth_middleware` has
+//! validated the token. Routes missing from the table are refused, so a new route cannot
+//! be reachable by every role by accident.
+//!
+//! What a role may do is derived from the `wallet::ParticipantPermissions` of the matching
+//! participant type, so the API never lets a role do something the chain would reject
+//! from the same participant. Consumers have no participant permissions and are read-only.
+//! Managing API user accounts is reserved to admins.
+
+use crate::wallet::{ParticipantPermissions, ParticipantType};
+use crate::web::models::{ActorRole, ApiError, UserClaims};
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use chrono::Utc;
+
+/// Actions a route can require
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// Read blocks, products, traces and analytics
+    Read,
+    /// Run SPARQL queries and keep a saved-query library
+    Query,
+    /// Re-validate the whole chain
+    Validate,
+    /// Add RDF data or create, sign and submit transactions
+    Write,
+    /// Register wallets and participants
+    ManageParticipants,
+    /// Create, unlock and delete API user accounts
+    ManageUsers,
+}
+
+/// Permission required by each protected route, keyed by method and route pattern
+#[rustfmt::skip]
+pub const ROUTE_PERMISSIONS: &[(Method, &str, Permission)] = &[
+    (Method::GET,    "/api/blockchain/status",                      Permission::Read),
+    (Method::GET,    "/api/blockchain/blocks",                      Permission::Read),
+    (Method::GET,    "/api/blockchain/blocks/:index",               Permission::Read),
+    (Method::GET,    "/api/blockchain/blocks/:index/rdf-summary",   Permission::Read),
+    (Method::GET,    "/api/blockchain/blocks/:index/access",        Permission::Read),
+    (Method::PUT,    "/api/blockchain/blocks/:index/access",        Permission::Write),
+    (Method::GET,    "/api/blockchain/validate",                    Permission::Validate),
+    (Method::GET,    "/api/transactions/recent",                    Permission::Read),
+    (Method::GET,    "/api/analytics",                              Permission::Read),
+    (Method::POST,   "/api/sparql/query",                           Permission::Query),
+    (Method::GET,    "/api/sparql/config",                          Permission::Query),
+    (Method::GET,    "/api/graphql",                                Permission::Query),
+    (Method::POST,   "/api/graphql",                                Permission::Query),
+    (Method::GET,    "/sparql",                                     Permission::Query),
+    (Method::POST,   "/sparql",                                     Permission::Query),
+    (Method::POST,   "/api/sparql/validate",                        Permission::Query),
+    (Method::GET,    "/api/sparql/queries",                         Permission::Query),
+    (Method::POST,   "/api/sparql/queries",                         Permission::Query),
+    (Method::GET,    "/api/sparql/queries/:id",                     Permission::Query),
+    (Method::PUT,    "/api/sparql/queries/:id",                     Permission::Query),
+    (Method::DELETE, "/api/sparql/queries/:id",                     Permission::Query),
+    (Method::POST,   "/api/sparql/queries/:id/run",                 Permission::Query),
+    (Method::POST,   "/api/sparql/queries/:id/favorite",            Permission::Query),
+    (Method::GET,    "/api/products/trace",                         Permission::Read),
+    (Method::GET,    "/api/products/trace/enhanced",                Permission::Read),
+    (Method::POST,   "/api/blockchain/add-triple",                  Permission::Write),
+    (Method::GET,    "/api/confidential/triples",                   Permission::Read),
+    (Method::POST,   "/api/confidential/triples",                   Permission::Write),
+    (Method::POST,   "/api/confidential/:commitment/disclose",      Permission::Write),
+    (Method::GET,    "/api/confidential/:commitment/proof",         Permission::Read),
+    (Method::POST,   "/api/confidential/verify",                    Permission::Read),
+    (Method::GET,    "/api/webhooks",                               Permission::Read),
+    (Method::POST,   "/api/webhooks",                               Permission::Write),
+    (Method::GET,    "/api/webhooks/:id",                           Permission::Read),
+    (Method::DELETE, "/api/webhooks/:id",                           Permission::Write),
+    (Method::GET,    "/api/webhooks/:id/deliveries",                Permission::Read),
+    (Method::POST,   "/api/webhooks/:id/redeliver/:delivery",       Permission::Write),
+    (Method::POST,   "/api/wallet/register",                        Permission::ManageParticipants),
+    (Method::POST,   "/api/transactions/create",                    Permission::Write),
+    (Method::POST,   "/api/transactions/sign",                      Permission::Write),
+    (Method::POST,   "/api/transactions/submit",                    Permission::Write),
+    (Method::GET,    "/api/products",                               Permission::Read),
+    (Method::GET,    "/api/products/:id",                           Permission::Read),
+    (Method::GET,    "/api/products/:id/trace",                     Permission::Read),
+    (Method::GET,    "/api/products/:id/provenance",                Permission::Read),
+    (Method::GET,    "/api/products/:id/analytics",                 Permission::Read),
+    (Method::GET,    "/api/products/by-type/:type",                 Permission::Read),
+    (Method::GET,    "/api/products/by-participant/:participantId", Permission::Read),
+    (Method::GET,    "/api/products/:id/related",                   Permission::Read),
+    (Method::GET,    "/api/products/:id/validate",                  Permission::Read),
+    (Method::GET,    "/api/batches/:id/ownership",                  Permission::Read),
+    (Method::GET,    "/api/participants/:id/nonce",                 Permission::Read),
+    (Method::POST,   "/api/participants",                           Permission::ManageParticipants),
+    (Method::POST,   "/api/auth/logout",                            Permission::Read),
+    (Method::GET,    "/api/admin/users",                            Permission::ManageUsers),
+    (Method::POST,   "/api/admin/users",                            Permission::ManageUsers),
+    (Method::DELETE, "/api/admin/users/:username",                  Permission::ManageUsers),
+    (Method::POST,   "/api/admin/users/:username/unlock",           Permission::ManageUsers),
+];
+
+/// Participant type whose chain permissions a role carries; consumers have none
+pub fn participant_type(role: &ActorRole) -> Option<ParticipantType> {
+    match role {
+        ActorRole::Farmer => Some(ParticipantType::Producer),
+        ActorRole::Processor => Some(ParticipantType::Manufacturer),
+        ActorRole::Transporter => Some(ParticipantType::LogisticsProvider),
+        ActorRole::Retailer => Some(ParticipantType::Retailer),
+        ActorRole::Auditor => Some(ParticipantType::Auditor),
+        ActorRole::Admin => Some(ParticipantType::Administrator),
+        ActorRole::Consumer => None,
+    }
+}
+
+/// Chain-level permissions of a role
+pub fn participant_permissions(role: &ActorRole) -> ParticipantPermissions {
+    participant_type(role)
+        .map(|participant_type| ParticipantPermissions::for_type(&participant_type))
+        .unwrap_or_else(ParticipantPermissions::none)
+}
+
+impl Permission {
+    /// Whether `role` holds this permission
+    pub fn granted_to(self, role: &ActorRole) -> bool {
+        let chain = participant_permissions(role);
+        match self {
+            Permission::Read | Permission::Query => true,
+            Permission::Validate => chain.can_audit,
+            Permission::Write => {
+                chain.can_produce
+                    || chain.can_process
+                    || chain.can_transport
+                    || chain.can_quality_test
+                    || chain.can_audit
+                    || chain.can_transfer
+            }
+            Permission::ManageParticipants => chain.can_manage_participants,
+            // API accounts have no chain counterpart
+            Permission::ManageUsers => *role == ActorRole::Admin,
+        }
+    }
+}
+
+/// Permission a request needs, from its method and matched route pattern
+pub fn required_permission(method: &Method, route: &str) -> Option<Permission> {
+    // Axum answers HEAD with the GET handler
+    let method = if method == Method::HEAD {
+        &Method::GET
+    } else {
+        method
+    };
+    ROUTE_PERMISSIONS
+        .iter()
+        .find(|(route_method, pattern, _)| route_method == method && *pattern == route)
+        .map(|(_, _, permission)| *permission)
+}
+
+/// Middleware enforcing `ROUTE_PERMISSIONS`; must run after `auth_middleware`
+pub async fn authorize_middleware(
+    request: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, Json<ApiError>)> {
+    let Some(claims) = request.extensions().get::<UserClaims>() else {
+        return Err(denied(
+            StatusCode::UNAUTHORIZED,
+            "authentication_required",
+            "Authentication is required for this operation".to_string(),
+        ));
+    };
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str())
+        .unwrap_or_else(|| request.uri().path());
+
+    let Some(permission) = required_permission(request.method(), route) else {
+        return Err(denied(
+            StatusCode::FORBIDDEN,
+            "insufficient_permissions",
+            format!("No role may call {} {}", request.method(), route),
+        ));
+    };
+    let role: ActorRole = claims
+        .role
+        .parse()
+        .map_err(|e: String| denied(StatusCode::FORBIDDEN, "insufficient_permissions", e))?;
+    if !permission.granted_to(&role) {
+        return Err(denied(
+            StatusCode::FORBIDDEN,
+            "insufficient_permissions",
+            format!(
+                "Role '{}' lacks {:?} permission for {} {}",
+                role,
+                permission,
+                request.method(),
+                route
+            ),
+        ));
+    }
+
+    Ok(next.run(request).await)
+}
+
+fn denied(status: StatusCode, error: &str, message: String) -> (StatusCode, Json<ApiError>) {
+    (
+        status,
+        Json(ApiError {
+            error: error.to_string(),
+            message,
+            timestamp: Utc::now(),
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::transaction::TransactionType;
+    use axum::{
+        middleware,
+        routing::{get, post},
+        Router,
+    };
+
+    const ALL_ROLES: [ActorRole; 7] = [
+        ActorRole::Farmer,
+        ActorRole::Processor,
+        ActorRole::Transporter,
+        ActorRole::Retailer,
+        ActorRole::Consumer,
+        ActorRole::Auditor,
+        ActorRole::Admin,
+    ];
+
+    fn granted(role: ActorRole) -> Vec<Permission> {
+        [
+            Permission::Read,
+            Permission::Query,
+            Permission::Validate,
+            Permission::Write,
+            Permission::ManageParticipants,
+            Permission::ManageUsers,
+        ]
+        .into_iter()
+        .filter(|permission| permission.granted_to(&role))
+        .collect()
+    }
+
+    #[test]
+    fn test_permissions_per_role() {
+        use Permission::*;
+
+        for role in [
+            ActorRole::Farmer,
+            ActorRole::Processor,
+            ActorRole::Transporter,
+            ActorRole::Retailer,
+        ] {
+            assert_eq!(granted(role), vec![Read, Query, Write]);
+        }
+        assert_eq!(granted(ActorRole::Consumer), vec![Read, Query]);
+        assert_eq!(
+            granted(ActorRole::Auditor),
+            vec![Read, Query, Validate, Write]
+        );
+        assert_eq!(
+            granted(ActorRole::Admin),
+            vec![
+                Read,
+                Query,
+                Validate,
+                Write,
+                ManageParticipants,
+                ManageUsers
+            ]
+        );
+    }
+
+    #[test]
+    fn test_route_matrix() {
+        let validate = required_permission(&Method::GET, "/api/blockchain/validate").unwrap();
+        let writable = required_permission(&Method::POST, "/api/blockchain/add-triple").unwrap();
+        let readable = required_permission(&Method::HEAD, "/api/products/:id").unwrap();
+
+        for role in ALL_ROLES {
+            let auditor_or_admin = matches!(role, ActorRole::Auditor | ActorRole::Admin);
+            assert_eq!(validate.granted_to(&role), auditor_or_admin, "{}", role);
+            assert_eq!(
+                writable.granted_to(&role),
+                role != ActorRole::Consumer,
+                "{}",
+                role
+            );
+            assert!(readable.granted_to(&role), "{}", role);
+        }
+
+        // Unlisted routes and methods are refused
+        assert_eq!(
+            required_permission(&Method::DELETE, "/api/products/:id"),
+            None
+        );
+        assert_eq!(required_permission(&Method::GET, "/api/admin/secret"), None);
+    }
+
+    #[test]
+    fn test_api_and_chain_permissions_agree() {
+        // A role can write through the API only if its participant type can sign
+        // some transaction the chain accepts
+        let transaction_types = [
+            TransactionType::Production,
+            TransactionType::Processing,
+            TransactionType::Transport,
+            TransactionType::Quality,
+            TransactionType::Transfer,
+            TransactionType::Environmental,
+            TransactionType::Compliance,
+            TransactionType::Governance,
+        ];
+        for role in ALL_ROLES {
+            let chain = participant_permissions(&role);
+            let chain_can_write = transaction_types.iter().any(|tx_type| {
+                tx_type
+                    .required_operation()
+                    .is_some_and(|operation| chain.allows(operation))
+            });
+            assert_eq!(
+                Permission::Write.granted_to(&role),
+                chain_can_write,
+                "{}",
+                role
+            );
+            assert_eq!(
+                Permission::ManageParticipants.granted_to(&role),
+                chain.allows("manage_participants"),
+                "{}",
+                role
+            );
+        }
+    }
+
+    /// Serve routes behind `authorize_middleware`, taking the caller's role from an
+    /// `x-role` header in place of a token
+    async fn serve_with_roles() -> String {
+        async fn claims_from_header(mut request: Request, next: Next) -> Response {
+            let role = request
+                .headers()
+                .get("x-role")
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            request.extensions_mut().insert(UserClaims {
+                sub: "tester".to_string(),
+                role,
+                exp: 0,
+                jti: String::new(),
+            });
+            next.run(request).await
+        }
+
+        let app = Router::new()
+            .route("/api/blockchain/validate", get(|| async { "valid" }))
+            .route("/api/blockchain/add-triple", post(|| async { "added" }))
+            .route("/api/products/:id", get(|| async { "product" }))
+            .route("/api/unlisted", get(|| async { "unlisted" }))
+            .layer(middleware::from_fn(authorize_middleware))
+            .layer(middleware::from_fn(claims_from_header));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", address)
+    }
+
+    #[tokio::test]
+    async fn test_middleware_enforces_matrix() {
+        let base = serve_with_roles().await;
+        let client = reqwest::Client::new();
+        let status = |method: reqwest::Method, path: &str, role: &str| {
+            client
+                .request(method, format!("{}{}", base, path))
+                .header("x-role", role)
+                .send()
+        };
+        let get = |path: &'static str, role: &'static str| async move {
+            status(reqwest::Method::GET, path, role)
+                .await
+                .unwrap()
+                .status()
+                .as_u16()
+        };
+
+        assert_eq!(get("/api/blockchain/validate", "auditor").await, 200);
+        assert_eq!(get("/api/blockchain/validate", "admin").await, 200);
+        assert_eq!(get("/api/blockchain/validate", "farmer").await, 403);
+        assert_eq!(get("/api/blockchain/validate", "consumer").await, 403);
+
+        let add_triple = |role: &'static str| async move {
+            status(reqwest::Method::POST, "/api/blockchain/add-triple", role)
+                .await
+                .unwrap()
+                .status()
+                .as_u16()
+        };
+        assert_eq!(add_triple("farmer").await, 200);
+        assert_eq!(add_triple("consumer").await, 403);
+
+        // Matched by route pattern, not by concrete path
+        assert_eq!(get("/api/products/p1", "consumer").await, 200);
+
+        assert_eq!(get("/api/unlisted", "admin").await, 403);
+        assert_eq!(get("/api/products/p1", "root").await, 403);
+    }
+}
@@ -0,0 +1,642 @@
+//! Saved SPARQL query library
+//!
+//! Users save named queries that they can run again later. Queries may declare parameters:
+//! SPARQL variables such as `?batch` that are bound when the query is run by appending a
+//! `VALUES` block. Every edit adds a new version, and earlier versions can still be read
+//! and run. A query is visible to its author, to the roles it is shared with and to
+//! admins. Only the author or an admin can edit or delete it.
+//!
+//! The library is kept in `saved_queries.json` in the node data directory, or only in
+//! memory when the node has no persistent storage. Changes are serialized into a
+//! `LibrarySnapshot` while the store is locked and written after the lock is released.
+
+use crate::error::WebError;
+use crate::web::models::ActorRole;
+use crate::web::openapi::api_model;
+use chrono::{DateTime, Utc};
+use oxigraph::model::{Literal, NamedNode, Term, Variable};
+use oxigraph::sparql::Query;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+/// File name of the library inside the data directory
+const LIBRARY_FILE: &str = "saved_queries.json";
+
+/// Same limit as the query endpoints
+const MAX_QUERY_LENGTH: usize = 50_000;
+
+api_model! {
+    /// A named parameter of a saved query
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct QueryParameter {
+        /// Variable name without the leading `?`
+        pub name: String,
+        #[serde(default)]
+        pub description: Option<String>,
+        /// Value used when a run does not bind the parameter
+        #[serde(default)]
+        pub default_value: Option<String>,
+    }
+}
+
+api_model! {
+    /// One revision of a saved query
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct SavedQueryVersion {
+        /// Starts at 1 and increases with every edit
+        pub version: u32,
+        pub query: String,
+        pub parameters: Vec<QueryParameter>,
+        pub created_at: DateTime<Utc>,
+        pub created_by: String,
+    }
+}
+
+api_model! {
+    /// A saved query with its full version history
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct SavedQuery {
+        pub id: String,
+        pub name: String,
+        pub description: Option<String>,
+        pub created_by: String,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+        /// Roles other than the author's that may read and run the query
+        pub shared_with_roles: Vec<String>,
+        /// Users who marked the query as a favorite
+        pub favorited_by: BTreeSet<String>,
+        /// Oldest first; never empty
+        pub versions: Vec<SavedQueryVersion>,
+    }
+}
+
+api_model! {
+    /// Fields a user supplies when saving or editing a query
+    #[derive(Debug, Clone, Default, Deserialize)]
+    pub struct SavedQueryDraft {
+        pub name: Option<String>,
+        pub query: String,
+        #[serde(default)]
+        pub description: Option<String>,
+        #[serde(default)]
+        pub parameters: Vec<QueryParameter>,
+        /// Replaces the roles the query is shared with when given; an empty list stops
+        /// sharing it
+        #[serde(default)]
+        pub shared_with_roles: Option<Vec<String>>,
+    }
+}
+
+impl SavedQuery {
+    /// Current revision
+    pub fn latest(&self) -> &SavedQueryVersion {
+        self.versions
+            .last()
+            .expect("saved queries always have a version")
+    }
+
+    /// A specific revision, or the latest when `version` is `None`
+    pub fn version(&self, version: Option<u32>) -> Option<&SavedQueryVersion> {
+        match version {
+            Some(version) => self.versions.iter().find(|v| v.version == version),
+            None => self.versions.last(),
+        }
+    }
+
+    /// Whether `user` with `role` may read and run the query
+    pub fn can_read(&self, user: &str, role: &str) -> bool {
+        self.can_modify(user, role) || self.shared_with_roles.iter().any(|r| r == role)
+    }
+
+    /// Whether `user` with `role` may edit or delete the query
+    pub fn can_modify(&self, user: &str, role: &str) -> bool {
+        self.created_by == user || role == ActorRole::Admin.to_string()
+    }
+
+    /// Summary of the query as seen by `user`
+    pub fn view(&self, user: &str) -> serde_json::Value {
+        let latest = self.latest();
+        serde_json::json!({
+            "id": self.id,
+            "name": self.name,
+            "description": self.description,
+            "query": latest.query,
+            "parameters": latest.parameters,
+            "version": latest.version,
+            "created_at": self.created_at,
+            "updated_at": self.updated_at,
+            "created_by": self.created_by,
+            "shared_with_roles": self.shared_with_roles,
+            "is_favorite": self.favorited_by.contains(user),
+        })
+    }
+}
+
+impl SavedQueryVersion {
+    /// Parse the query with `bindings` applied to its parameters.
+    ///
+    /// Values are RDF terms in N-Triples syntax (`<iri>`, `"text"@en`,
+    /// `"5"^^<http://www.w3.org/2001/XMLSchema#integer>`); a bare absolute IRI is read as
+    /// an IRI and anything else as a plain string literal.
+    pub fn bind(&self, bindings: &HashMap<String, String>) -> Result<Query, WebError> {
+        if let Some(unknown) = bindings
+            .keys()
+            .find(|name| !self.parameters.iter().any(|p| &p.name == *name))
+        {
+            return Err(WebError::BadRequest(format!(
+                "Query has no parameter '{}'",
+                unknown
+            )));
+        }
+
+        let mut variables = Vec::new();
+        let mut values = Vec::new();
+        for parameter in &self.parameters {
+            let value = bindings
+                .get(&parameter.name)
+                .or(parameter.default_value.as_ref())
+                .ok_or_else(|| {
+                    WebError::BadRequest(format!("Missing binding for ?{}", parameter.name))
+                })?;
+            variables.push(format!("?{}", parameter.name));
+            values.push(parse_term(value)?.to_string());
+        }
+
+        let text = if variables.is_empty() {
+            self.query.clone()
+        } else {
+            format!(
+                "{}\nVALUES ({}) {{ ({}) }}",
+                self.query,
+                variables.join(" "),
+                values.join(" ")
+            )
+        };
+        Query::parse(&text, None)
+            .map_err(|e| WebError::BadRequest(format!("Invalid SPARQL query: {}", e)))
+    }
+}
+
+/// Per-user library of saved queries
+#[derive(Debug, Default)]
+pub struct SavedQueryStore {
+    queries: BTreeMap<String, SavedQuery>,
+    /// Library file; `None` keeps the library in memory only
+    path: Option<PathBuf>,
+    /// Number of changes made so far
+    generation: u64,
+    /// Generation of the library last written to `path`
+    written: Arc<Mutex<u64>>,
+}
+
+/// Serialized state of a library, to be written once the store is unlocked
+#[derive(Debug)]
+pub struct LibrarySnapshot {
+    path: PathBuf,
+    generation: u64,
+    contents: Vec<u8>,
+    written: Arc<Mutex<u64>>,
+}
+
+impl LibrarySnapshot {
+    /// Write the library through a temporary file so a crash never leaves it truncated.
+    /// Blocks; a snapshot older than the one last written is skipped.
+    pub fn write(self) -> Result<(), WebError> {
+        let mut written = self.written.lock().unwrap_or_else(|e| e.into_inner());
+        if *written >= self.generation {
+            return Ok(());
+        }
+        let write = || -> anyhow::Result<()> {
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let temp = self.path.with_extension("json.tmp");
+            fs::write(&temp, &self.contents)?;
+            fs::rename(&temp, &self.path)?;
+            Ok(())
+        };
+        write()
+            .map_err(|e| WebError::ServerError(format!("Failed to save query library: {}", e)))?;
+        *written = self.generation;
+        Ok(())
+    }
+}
+
+impl SavedQueryStore {
+    /// Library that is not persisted
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open the library kept in `data_dir`, starting empty if it does not exist yet
+    pub fn open<P: AsRef<Path>>(data_dir: P) -> anyhow::Result<Self> {
+        let path = data_dir.as_ref().join(LIBRARY_FILE);
+        let queries = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            queries,
+            path: Some(path),
+            ..Self::default()
+        })
+    }
+
+    /// Queries `user` can read, most recently updated first
+    pub fn list(&self, user: &str, role: &str) -> Vec<&SavedQuery> {
+        let mut visible: Vec<&SavedQuery> = self
+            .queries
+            .values()
+            .filter(|query| query.can_read(user, role))
+            .collect();
+        visible.sort_by_key(|query| std::cmp::Reverse(query.updated_at));
+        visible
+    }
+
+    /// A query `user` can read
+    pub fn get(&self, id: &str, user: &str, role: &str) -> Result<&SavedQuery, WebError> {
+        self.queries
+            .get(id)
+            .filter(|query| query.can_read(user, role))
+            .ok_or_else(|| not_found(id))
+    }
+
+    /// Save a new query owned by `user`
+    pub fn create(&mut self, user: &str, draft: SavedQueryDraft) -> Result<&SavedQuery, WebError> {
+        let name = draft
+            .name
+            .as_deref()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| WebError::BadRequest("Query name is required".to_string()))?
+            .to_string();
+        let shared_with_roles =
+            validate_roles(draft.shared_with_roles.as_deref().unwrap_or_default())?;
+        validate_query(&draft.query, &draft.parameters)?;
+
+        let now = Utc::now();
+        let query = SavedQuery {
+            id: Uuid::new_v4().to_string(),
+            name,
+            description: draft.description,
+            created_by: user.to_string(),
+            created_at: now,
+            updated_at: now,
+            shared_with_roles,
+            favorited_by: BTreeSet::new(),
+            versions: vec![SavedQueryVersion {
+                version: 1,
+                query: draft.query,
+                parameters: draft.parameters,
+                created_at: now,
+                created_by: user.to_string(),
+            }],
+        };
+        let id = query.id.clone();
+        self.queries.insert(id.clone(), query);
+        self.generation += 1;
+        Ok(&self.queries[&id])
+    }
+
+    /// Save an edit as a new version. Name, description and sharing are replaced when
+    /// given; the previous versions are kept.
+    pub fn update(
+        &mut self,
+        id: &str,
+        user: &str,
+        role: &str,
+        draft: SavedQueryDraft,
+    ) -> Result<&SavedQuery, WebError> {
+        self.check_modify(id, user, role)?;
+        let shared_with_roles = draft
+            .shared_with_roles
+            .as_deref()
+            .map(validate_roles)
+            .transpose()?;
+        validate_query(&draft.query, &draft.parameters)?;
+
+        let now = Utc::now();
+        let query = self.queries.get_mut(id).ok_or_else(|| not_found(id))?;
+        if let Some(name) = draft.name.as_deref().map(str::trim) {
+            if !name.is_empty() {
+                query.name = name.to_string();
+            }
+        }
+        if draft.description.is_some() {
+            query.description = draft.description;
+        }
+        if let Some(shared_with_roles) = shared_with_roles {
+            query.shared_with_roles = shared_with_roles;
+        }
+        let version = query.latest().version + 1;
+        query.versions.push(SavedQueryVersion {
+            version,
+            query: draft.query,
+            parameters: draft.parameters,
+            created_at: now,
+            created_by: user.to_string(),
+        });
+        query.updated_at = now;
+
+        self.generation += 1;
+        Ok(&self.queries[id])
+    }
+
+    /// Delete a query with all its versions
+    pub fn delete(&mut self, id: &str, user: &str, role: &str) -> Result<(), WebError> {
+        self.check_modify(id, user, role)?;
+        self.queries.remove(id);
+        self.generation += 1;
+        Ok(())
+    }
+
+    /// Flip whether `user` has the query as a favorite, returning the new state
+    pub fn toggle_favorite(&mut self, id: &str, user: &str, role: &str) -> Result<bool, WebError> {
+        self.get(id, user, role)?;
+        let query = self.queries.get_mut(id).ok_or_else(|| not_found(id))?;
+        let favorite = if query.favorited_by.remove(user) {
+            false
+        } else {
+            query.favorited_by.insert(user.to_string());
+            true
+        };
+        self.generation += 1;
+        Ok(favorite)
+    }
+
+    fn check_modify(&self, id: &str, user: &str, role: &str) -> Result<(), WebError> {
+        let query = self.get(id, user, role)?;
+        if !query.can_modify(user, role) {
+            return Err(WebError::AuthorizationFailed(format!(
+                "Only the author of query {} can change it",
+                id
+            )));
+        }
+        Ok(())
+    }
+
+    /// Snapshot of the library to write after the store is unlocked; `None` when the
+    /// library is kept in memory only
+    pub fn snapshot(&self) -> Result<Option<LibrarySnapshot>, WebError> {
+        let Some(path) = &self.path else {
+            return Ok(None);
+        };
+        let contents = serde_json::to_vec_pretty(&self.queries).map_err(|e| {
+            WebError::ServerError(format!("Failed to serialize query library: {}", e))
+        })?;
+        Ok(Some(LibrarySnapshot {
+            path: path.clone(),
+            generation: self.generation,
+            contents,
+            written: Arc::clone(&self.written),
+        }))
+    }
+}
+
+fn validate_query(query: &str, parameters: &[QueryParameter]) -> Result<(), WebError> {
+    if query.trim().is_empty() {
+        return Err(WebError::BadRequest(
+            "SPARQL query cannot be empty".to_string(),
+        ));
+    }
+    if query.len() > MAX_QUERY_LENGTH {
+        return Err(WebError::BadRequest(format!(
+            "SPARQL query too long (max {} characters)",
+            MAX_QUERY_LENGTH
+        )));
+    }
+    // Updates do not parse as queries, so they cannot be saved
+    Query::parse(query, None)
+        .map_err(|e| WebError::BadRequest(format!("Invalid SPARQL query: {}", e)))?;
+
+    let mut seen = BTreeSet::new();
+    for parameter in parameters {
+        Variable::new(&parameter.name).map_err(|_| {
+            WebError::BadRequest(format!("Invalid parameter name '{}'", parameter.name))
+        })?;
+        if !seen.insert(parameter.name.as_str()) {
+            return Err(WebError::BadRequest(format!(
+                "Parameter '{}' is declared twice",
+                parameter.name
+            )));
+        }
+        if !mentions_variable(query, &parameter.name) {
+            return Err(WebError::BadRequest(format!(
+                "Parameter '{}' does not appear in the query as ?{}",
+                parameter.name, parameter.name
+            )));
+        }
+        if let Some(default) = &parameter.default_value {
+            parse_term(default)?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether `?name` or `$name` occurs in the query as a whole variable
+fn mentions_variable(query: &str, name: &str) -> bool {
+    ['?', '$'].iter().any(|sigil| {
+        let token = format!("{}{}", sigil, name);
+        query.match_indices(&token).any(|(start, _)| {
+            !query[start + token.len()..]
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_alphanumeric() || c == '_')
+        })
+    })
+}
+
+fn parse_term(value: &str) -> Result<Term, WebError> {
+    let value = value.trim();
+    let term = if value.starts_with('<') || value.starts_with('"') {
+        Term::from_str(value)
+            .map_err(|e| WebError::BadRequest(format!("Invalid RDF term '{}': {}", value, e)))?
+    } else if value.contains("://") || value.starts_with("urn:") {
+        NamedNode::new(value)
+            .map_err(|e| WebError::BadRequest(format!("Invalid IRI '{}': {}", value, e)))?
+            .into()
+    } else {
+        Literal::new_simple_literal(value).into()
+    };
+    Ok(term)
+}
+
+fn validate_roles(roles: &[String]) -> Result<Vec<String>, WebError> {
+    let mut validated = Vec::new();
+    for role in roles {
+        let role = role
+            .trim()
+            .parse::<ActorRole>()
+            .map_err(WebError::BadRequest)?
+            .to_string();
+        if !validated.contains(&role) {
+            validated.push(role);
+        }
+    }
+    Ok(validated)
+}
+
+fn not_found(id: &str) -> WebError {
+    WebError::ResourceNotFound(format!("Saved query {}", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn save(store: &SavedQueryStore) {
+        store.snapshot().unwrap().unwrap().write().unwrap();
+    }
+
+    fn draft(query: &str, parameters: &[&str]) -> SavedQueryDraft {
+        SavedQueryDraft {
+            name: Some("Batch origin".to_string()),
+            query: query.to_string(),
+            parameters: parameters
+                .iter()
+                .map(|name| QueryParameter {
+                    name: name.to_string(),
+                    description: None,
+                    default_value: None,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parameters_are_bound_as_values() {
+        let mut store = SavedQueryStore::new();
+        let saved = store
+            .create(
+                "alice",
+                draft(
+                    "SELECT ?farm WHERE { ?batch <http://example.org/producedBy> ?farm }",
+                    &["batch"],
+                ),
+            )
+            .unwrap();
+        let version = saved.latest();
+
+        let store = oxigraph::store::Store::new().unwrap();
+        store
+            .load_from_reader(
+                oxigraph::io::RdfFormat::NTriples,
+                "<http://example.org/batch1> <http://example.org/producedBy> <http://example.org/farm1> .\n\
+                 <http://example.org/batch2> <http://example.org/producedBy> <http://example.org/farm2> .\n"
+                    .as_bytes(),
+            )
+            .unwrap();
+        let farms = |bindings: &[(&str, &str)]| -> Vec<String> {
+            let bindings = bindings
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            let query = version.bind(&bindings).unwrap();
+            match store.query(query).unwrap() {
+                oxigraph::sparql::QueryResults::Solutions(solutions) => solutions
+                    .map(|solution| solution.unwrap().get("farm").unwrap().to_string())
+                    .collect(),
+                _ => panic!("expected solutions"),
+            }
+        };
+
+        assert_eq!(
+            farms(&[("batch", "http://example.org/batch1")]),
+            vec!["<http://example.org/farm1>"]
+        );
+        assert_eq!(
+            farms(&[("batch", "<http://example.org/batch2>")]),
+            vec!["<http://example.org/farm2>"]
+        );
+        // Values are bound as terms, so they cannot inject query text
+        assert!(farms(&[("batch", "x\" } } DELETE WHERE { ?s ?p ?o } #")]).is_empty());
+
+        assert!(version.bind(&HashMap::new()).is_err());
+        let unknown = HashMap::from([("farm".to_string(), "x".to_string())]);
+        assert!(version.bind(&unknown).is_err());
+    }
+
+    #[test]
+    fn test_invalid_queries_are_rejected() {
+        let mut store = SavedQueryStore::new();
+        assert!(store
+            .create("alice", draft("DELETE WHERE { ?s ?p ?o }", &[]))
+            .is_err());
+        assert!(store
+            .create(
+                "alice",
+                draft("SELECT ?batchId WHERE { ?batchId ?p ?o }", &["batch"])
+            )
+            .is_err());
+        let mut unshared = draft("ASK {}", &[]);
+        unshared.shared_with_roles = Some(vec!["janitor".to_string()]);
+        assert!(store.create("alice", unshared).is_err());
+    }
+
+    #[test]
+    fn test_versions_sharing_and_persistence() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = SavedQueryStore::open(dir.path()).unwrap();
+
+        let mut shared = draft("SELECT * WHERE { ?s ?p ?o }", &[]);
+        shared.shared_with_roles = Some(vec!["Auditor".to_string()]);
+        let id = store.create("alice", shared).unwrap().id.clone();
+
+        // Sharing is kept when an edit does not mention it
+        let edited = store
+            .update(&id, "alice", "farmer", draft("ASK { ?s ?p ?o }", &[]))
+            .unwrap();
+        assert_eq!(edited.latest().version, 2);
+        assert_eq!(edited.shared_with_roles, vec!["auditor".to_string()]);
+
+        // Auditors can read and favorite it but not change it; other roles cannot see it
+        let stale = store.snapshot().unwrap().unwrap();
+        assert!(store.get(&id, "bob", "auditor").is_ok());
+        assert!(store.toggle_favorite(&id, "bob", "auditor").unwrap());
+        assert!(matches!(
+            store.delete(&id, "bob", "auditor"),
+            Err(WebError::AuthorizationFailed(_))
+        ));
+        assert!(store.list("carol", "retailer").is_empty());
+        assert!(matches!(
+            store.get(&id, "carol", "retailer"),
+            Err(WebError::ResourceNotFound(_))
+        ));
+        save(&store);
+        // A snapshot taken earlier but written late does not undo later changes
+        stale.write().unwrap();
+
+        let reopened = SavedQueryStore::open(dir.path()).unwrap();
+        let query = reopened.get(&id, "alice", "farmer").unwrap();
+        assert_eq!(query.versions.len(), 2);
+        assert_eq!(
+            query.version(Some(1)).unwrap().query,
+            "SELECT * WHERE { ?s ?p ?o }"
+        );
+        assert_eq!(query.view("bob")["is_favorite"], true);
+        assert_eq!(query.view("alice")["is_favorite"], false);
+
+        // An empty list stops sharing
+        let mut unshared = draft("ASK { ?s ?p ?o }", &[]);
+        unshared.shared_with_roles = Some(Vec::new());
+        let edited = store.update(&id, "alice", "farmer", unshared).unwrap();
+        assert!(edited.shared_with_roles.is_empty());
+        assert!(store.get(&id, "bob", "auditor").is_err());
+
+        store.delete(&id, "admin", "admin").unwrap();
+        save(&store);
+        assert!(SavedQueryStore::open(dir.path())
+            .unwrap()
+            .list("alice", "farmer")
+            .is_empty());
+    }
+}
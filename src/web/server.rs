@@ -3,20 +3,36 @@
 use crate::config::{Config, CorsConfig};
 use crate::core::blockchain::Blockchain;
 use crate::web::{
-    auth::{auth_middleware, authenticate, AuthState},
+    auth::{
+        auth_middleware, authenticate, create_user_account, delete_user_account,
+        list_user_accounts, logout, refresh_token, unlock_user_account, websocket_auth_middleware,
+        AuthState,
+    },
+    disclosures::PrivateTripleStore,
+    graph_access::GraphAccessStore,
     handlers::{
         add_triple,
+        commit_confidential_triples,
         create_participant,
         create_transaction,
         delete_sparql_query,
+        delete_webhook,
+        disclose_confidential_triple,
         execute_sparql_query,
         get_analytics,
+        get_batch_ownership,
         get_block,
+        get_block_access,
         get_block_rdf_summary,
         get_blockchain_status,
         get_blocks,
+        get_confidential_triples,
+        get_disclosure_proof,
         get_enhanced_product_trace,
+        get_graphql_schema,
         get_knowledge_graph,
+        get_next_nonce,
+        get_openapi_document,
 
         get_product_analytics,
         get_product_by_id,
@@ -29,20 +45,35 @@ use crate::web::{
         get_recent_transactions,
         get_related_items,
         get_saved_sparql_queries,
+        get_saved_sparql_query,
         // SPARQL helper endpoints
         get_sparql_config,
+        get_webhook,
+        get_webhook_deliveries,
+        graphql_query,
         health_check,
+        list_webhooks,
+        redeliver_webhook_delivery,
         register_wallet,
+        register_webhook,
+        run_saved_sparql_query,
         save_sparql_query,
+        share_block_graph,
         sign_transaction,
         submit_transaction,
         toggle_favorite_sparql_query,
         trace_path_api,
+        update_sparql_query,
         validate_blockchain,
         validate_item,
         validate_sparql_endpoint,
+        verify_disclosure,
         AppState,
     },
+    permissions::authorize_middleware,
+    saved_queries::SavedQueryStore,
+    sparql::{sparql_get, sparql_post},
+    webhooks::{DestinationPolicy, WebhookDispatcher, WebhookStore},
     websocket::{websocket_handler, BlockchainEventBroadcaster, WebSocketState},
 };
 use axum::{
@@ -50,10 +81,7 @@ use axum::{
     routing::{delete, get, post},
     Router,
 };
-use std::{
-    net::SocketAddr,
-    sync::{Arc, Mutex},
-};
+use std::{net::SocketAddr, sync::Arc};
 use tower::ServiceBuilder;
 use tower_http::{cors::CorsLayer, services::ServeDir, set_header::SetResponseHeaderLayer};
 use tracing::{error, info};
@@ -70,19 +98,79 @@ pub struct WebServer {
 impl WebServer {
     /// Create a new web server instance
     pub fn new(blockchain: Blockchain, config: Config) -> Self {
-        let blockchain_arc = Arc::new(Mutex::new(blockchain.clone()));
-        let websocket_state = WebSocketState::new(blockchain_arc);
+        let app_state = Self::build_app_state(blockchain, &config);
+        let websocket_state = WebSocketState::new(app_state.blockchain.clone())
+            .with_graph_access(app_state.graph_access.clone());
         let event_broadcaster = BlockchainEventBroadcaster::new(websocket_state.clone());
 
         Self {
-            app_state: AppState::new(blockchain),
-            auth_state: AuthState::new(),
+            app_state: app_state.with_events(event_broadcaster.clone()),
+            auth_state: Self::build_auth_state(&config),
             websocket_state,
             event_broadcaster,
             config,
         }
     }
 
+    /// Shared handler state, with the saved-query library, graph access policy,
+    /// confidential triples and webhooks kept in the data directory when storage is
+    /// persistent
+    fn build_app_state(blockchain: Blockchain, config: &Config) -> AppState {
+        let mut app_state = AppState::new(blockchain)
+            .with_webhooks(WebhookStore::new().with_destinations(Self::destinations(config)));
+        if !config.storage.persistent {
+            return app_state;
+        }
+        match SavedQueryStore::open(&config.storage.data_dir) {
+            Ok(saved_queries) => app_state = app_state.with_saved_queries(saved_queries),
+            Err(e) => error!(
+                "Failed to load saved queries, keeping them in memory: {}",
+                e
+            ),
+        }
+        match GraphAccessStore::open(&config.storage.data_dir) {
+            Ok(graph_access) => app_state = app_state.with_graph_access(graph_access),
+            Err(e) => error!(
+                "Failed to load graph access policy, keeping it in memory: {}",
+                e
+            ),
+        }
+        match WebhookStore::open(&config.storage.data_dir) {
+            Ok(webhooks) => {
+                app_state =
+                    app_state.with_webhooks(webhooks.with_destinations(Self::destinations(config)))
+            }
+            Err(e) => error!("Failed to load webhooks, keeping them in memory: {}", e),
+        }
+        match PrivateTripleStore::open(&config.storage.data_dir) {
+            Ok(private_triples) => app_state = app_state.with_private_triples(private_triples),
+            Err(e) => error!(
+                "Failed to load confidential triples, keeping them in memory: {}",
+                e
+            ),
+        }
+        app_state
+    }
+
+    /// Where webhooks may deliver besides public addresses
+    fn destinations(config: &Config) -> DestinationPolicy {
+        DestinationPolicy::new(config.web.webhook_allowed_hosts.iter().cloned())
+    }
+
+    /// User database, kept in the data directory when storage is persistent
+    fn build_auth_state(config: &Config) -> AuthState {
+        if !config.storage.persistent {
+            return AuthState::new();
+        }
+        match AuthState::open(&config.storage.data_dir) {
+            Ok(auth_state) => auth_state,
+            Err(e) => {
+                error!("Failed to load users, keeping them in memory: {}", e);
+                AuthState::new()
+            }
+        }
+    }
+
     /// Create a new web server with a specific port (helper for tests/benchmarks)
     pub fn new_with_port(port: u16) -> Self {
         let mut config = Config::default();
@@ -151,15 +239,21 @@ impl WebServer {
         // Static file serving
         let static_service = ServeDir::new("static").append_index_html_on_directories(true);
 
-        // WebSocket routes (no authentication required for WebSocket upgrade)
+        // WebSocket routes (the token may come as a query parameter)
         let websocket_routes = Router::new()
             .route("/ws", get(websocket_handler))
+            .layer(middleware::from_fn_with_state(
+                self.auth_state.clone(),
+                websocket_auth_middleware,
+            ))
             .with_state(self.websocket_state.clone());
 
         // Public routes (no authentication required)
         let public_routes = Router::new()
             .route("/health", get(health_check))
+            .route("/api/openapi.json", get(get_openapi_document))
             .route("/auth/login", post(authenticate))
+            .route("/auth/refresh", post(refresh_token))
             .with_state(self.auth_state.clone());
 
         // Public blockchain routes (no auth, uses AppState)
@@ -177,15 +271,27 @@ impl WebServer {
                 "/api/blockchain/blocks/:index/rdf-summary",
                 get(get_block_rdf_summary),
             )
+            .route(
+                "/api/blockchain/blocks/:index/access",
+                get(get_block_access).put(share_block_graph),
+            )
             .route("/api/blockchain/validate", get(validate_blockchain))
             .route("/api/transactions/recent", get(get_recent_transactions))
             .route("/api/analytics", get(get_analytics))
             .route("/api/sparql/query", post(execute_sparql_query))
             .route("/api/sparql/config", get(get_sparql_config))
+            .route("/api/graphql", get(get_graphql_schema).post(graphql_query))
+            .route("/sparql", get(sparql_get).post(sparql_post))
             .route("/api/sparql/validate", post(validate_sparql_endpoint))
             .route("/api/sparql/queries", get(get_saved_sparql_queries))
             .route("/api/sparql/queries", post(save_sparql_query))
-            .route("/api/sparql/queries/:id", delete(delete_sparql_query))
+            .route(
+                "/api/sparql/queries/:id",
+                get(get_saved_sparql_query)
+                    .put(update_sparql_query)
+                    .delete(delete_sparql_query),
+            )
+            .route("/api/sparql/queries/:id/run", post(run_saved_sparql_query))
             .route(
                 "/api/sparql/queries/:id/favorite",
                 post(toggle_favorite_sparql_query),
@@ -196,6 +302,26 @@ impl WebServer {
                 get(get_enhanced_product_trace),
             )
             .route("/api/blockchain/add-triple", post(add_triple))
+            .route(
+                "/api/confidential/triples",
+                get(get_confidential_triples).post(commit_confidential_triples),
+            )
+            .route(
+                "/api/confidential/:commitment/disclose",
+                post(disclose_confidential_triple),
+            )
+            .route(
+                "/api/confidential/:commitment/proof",
+                get(get_disclosure_proof),
+            )
+            .route("/api/confidential/verify", post(verify_disclosure))
+            .route("/api/webhooks", get(list_webhooks).post(register_webhook))
+            .route("/api/webhooks/:id", get(get_webhook).delete(delete_webhook))
+            .route("/api/webhooks/:id/deliveries", get(get_webhook_deliveries))
+            .route(
+                "/api/webhooks/:id/redeliver/:delivery",
+                post(redeliver_webhook_delivery),
+            )
             .route("/api/wallet/register", post(register_wallet))
             .route("/api/transactions/create", post(create_transaction))
             .route("/api/transactions/sign", post(sign_transaction))
@@ -213,10 +339,36 @@ impl WebServer {
             )
             .route("/api/products/:id/related", get(get_related_items))
             .route("/api/products/:id/validate", get(validate_item))
+            .route("/api/batches/:id/ownership", get(get_batch_ownership))
+            .route("/api/participants/:id/nonce", get(get_next_nonce))
             .route("/api/participants", post(create_participant))
-            .layer(middleware::from_fn(auth_middleware))
+            // Layers run bottom-up: authenticate, then check the role's permissions
+            .layer(middleware::from_fn(authorize_middleware))
+            .layer(middleware::from_fn_with_state(
+                self.auth_state.clone(),
+                auth_middleware,
+            ))
             .with_state(self.app_state.clone());
 
+        // Protected session and user-management routes (use AuthState)
+        let account_routes = Router::new()
+            .route("/api/auth/logout", post(logout))
+            .route(
+                "/api/admin/users",
+                get(list_user_accounts).post(create_user_account),
+            )
+            .route("/api/admin/users/:username", delete(delete_user_account))
+            .route(
+                "/api/admin/users/:username/unlock",
+                post(unlock_user_account),
+            )
+            .layer(middleware::from_fn(authorize_middleware))
+            .layer(middleware::from_fn_with_state(
+                self.auth_state.clone(),
+                auth_middleware,
+            ))
+            .with_state(self.auth_state.clone());
+
         // Configure CORS using configuration
         let cors_config = self.config.get_development_cors();
         let cors_layer = self.build_cors_layer(&cors_config);
@@ -226,6 +378,7 @@ impl WebServer {
             .merge(public_routes)
             .merge(public_blockchain_routes)
             .merge(protected_routes)
+            .merge(account_routes)
             .nest_service("/", static_service)
             .layer(
                 ServiceBuilder::new()
@@ -258,6 +411,16 @@ impl WebServer {
     /// Start the web server
     pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
         let app = self.build_router();
+        WebhookDispatcher::new(
+            self.app_state.webhooks.clone(),
+            self.app_state.blockchain.clone(),
+            self.app_state.graph_access.clone(),
+        )
+        .with_destinations(Self::destinations(&self.config))
+        .spawn(self.websocket_state.event_sender.subscribe());
+        self.event_broadcaster
+            .forward_blocks(self.app_state.blockchain.clone())
+            .await;
         let addr = SocketAddr::from(([0, 0, 0, 0], self.config.web.port));
 
         info!("Starting ProvChain web server on {}", addr);
@@ -271,8 +434,12 @@ impl WebServer {
         );
         info!("API endpoints available:");
         info!("  GET  /health - Health check");
-        info!("  GET  /ws - WebSocket connection for real-time updates");
+        info!("  GET  /api/openapi.json - OpenAPI description of the REST API");
+        info!("  GET  /ws?token=... - WebSocket connection for real-time updates");
         info!("  POST /auth/login - Authentication");
+        info!("  POST /auth/refresh - Exchange a refresh token for a new token pair");
+        info!("  POST /api/auth/logout - Revoke the current session");
+        info!("  GET  /api/admin/users - User management (admin)");
         info!("  POST /api/wallet/register - Register new wallet");
         info!("  POST /api/transactions/create - Create new transaction");
         info!("  POST /api/transactions/sign - Sign transaction");
@@ -281,10 +448,18 @@ impl WebServer {
         info!("  GET  /api/blockchain/blocks - All blocks");
         info!("  GET  /api/blockchain/blocks/:index - Specific block");
         info!("  GET  /api/blockchain/validate - Validate blockchain");
+        info!("  PUT  /api/blockchain/blocks/:index/access - Share a block graph");
         info!("  GET  /api/transactions/recent - Recent transactions");
         info!("  POST /api/sparql/query - Execute SPARQL query");
+        info!("  GET  /sparql - SPARQL 1.1 Protocol query endpoint (GET and POST)");
+        info!("  GET  /api/sparql/queries - Saved query library");
+        info!("  POST /api/sparql/queries/:id/run - Run a saved query with bindings");
+        info!("  POST /api/graphql - GraphQL queries over the provenance graph");
         info!("  GET  /api/products/trace - Product traceability");
         info!("  POST /api/blockchain/add-triple - Add new triple");
+        info!("  POST /api/confidential/triples - Commit confidential triples");
+        info!("  POST /api/confidential/verify - Verify a disclosed triple");
+        info!("  POST /api/webhooks - Register a webhook for chain events");
         info!("Static files served from: ./static/");
         info!("Real-time features: Block creation, transaction updates, integrity alerts");
 
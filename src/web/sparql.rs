@@ -0,0 +1,722 @@
+//! W3C SPARQL 1.1 Protocol endpoint
+//!
+//! `/sparql` accepts queries the way standard SPARQL clients send them:
+//!
+//! - `GET /sparql?query=...`
+//! - `POST /sparql` with an `application/x-www-form-urlencoded` body holding `query`
+//! - `POST /sparql` with an `application/sparql-query` body holding the query text
+//!
+//! `default-graph-uri` and `named-graph-uri` may be repeated to set the RDF dataset, and
+//! override any `FROM` / `FROM NAMED` clauses in the query. When neither the request nor
+//! the query names a dataset, the default graph is the union of all block graphs, so
+//! `SELECT * WHERE { ?s ?p ?o }` sees the whole chain.
+//!
+//! The response format is chosen from the `Accept` header: SPARQL Results JSON, XML, CSV
+//! or TSV for SELECT and ASK, and Turtle, N-Triples, JSON-LD or RDF/XML for CONSTRUCT and
+//! DESCRIBE. A `format` parameter (media type or file extension) overrides `Accept` for
+//! clients that cannot set headers. Updates are not accepted on this endpoint.
+//!
+//! Queries only see the named graphs the caller may read; see `graph_access`.
+
+use crate::web::handlers::{web_error, AppState};
+use crate::web::models::{ApiError, UserClaims};
+use crate::web::openapi::api_model;
+use axum::{
+    body::Bytes,
+    extract::{Extension, RawQuery, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use oxigraph::io::{JsonLdProfileSet, RdfFormat};
+use oxigraph::model::{GraphName, NamedNode, NamedOrBlankNode};
+use oxigraph::sparql::results::QueryResultsFormat;
+use oxigraph::sparql::{Query, QueryResults};
+use serde::Deserialize;
+
+/// Same limit as the JSON query API
+const MAX_QUERY_LENGTH: usize = 50_000;
+
+/// Result formats for SELECT and ASK, the first being the default
+const RESULTS_FORMATS: [QueryResultsFormat; 4] = [
+    QueryResultsFormat::Json,
+    QueryResultsFormat::Xml,
+    QueryResultsFormat::Csv,
+    QueryResultsFormat::Tsv,
+];
+
+/// Graph formats for CONSTRUCT and DESCRIBE, the first being the default
+const GRAPH_FORMATS: [RdfFormat; 4] = [
+    RdfFormat::Turtle,
+    RdfFormat::NTriples,
+    RdfFormat::JsonLd {
+        profile: JsonLdProfileSet::empty(),
+    },
+    RdfFormat::RdfXml,
+];
+
+pub(crate) type SparqlError = (StatusCode, Json<ApiError>);
+
+/// Protocol parameters of a query request
+#[derive(Debug, Default)]
+struct ProtocolRequest {
+    query: Option<String>,
+    default_graph_uris: Vec<String>,
+    named_graph_uris: Vec<String>,
+    format: Option<String>,
+    update: bool,
+}
+
+impl ProtocolRequest {
+    /// Collect protocol parameters from a URL query string or form body
+    fn parse(&mut self, encoded: &[u8]) {
+        for (name, value) in form_urlencoded::parse(encoded) {
+            match name.as_ref() {
+                "query" => self.query = Some(value.into_owned()),
+                "default-graph-uri" => self.default_graph_uris.push(value.into_owned()),
+                "named-graph-uri" => self.named_graph_uris.push(value.into_owned()),
+                "format" => self.format = Some(value.into_owned()),
+                "update" | "using-graph-uri" | "using-named-graph-uri" => self.update = true,
+                _ => {}
+            }
+        }
+    }
+}
+
+api_model! {
+    /// Query string of `GET /sparql` as described in the OpenAPI document. Requests are
+    /// read with `ProtocolRequest::parse`, which also takes repeated parameters.
+    #[derive(Debug, Deserialize)]
+    pub struct SparqlProtocolParams {
+        pub query: String,
+        /// Sets the default graph, overriding `FROM` clauses; may be repeated
+        #[serde(default)]
+        #[serde(rename = "default-graph-uri")]
+        pub default_graph_uri: Vec<String>,
+        /// Adds a named graph, overriding `FROM NAMED` clauses; may be repeated
+        #[serde(default)]
+        #[serde(rename = "named-graph-uri")]
+        pub named_graph_uri: Vec<String>,
+        /// Result media type or file extension, overriding the `Accept` header
+        pub format: Option<String>,
+    }
+}
+
+/// SPARQL 1.1 Protocol query via GET
+pub async fn sparql_get(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+    headers: HeaderMap,
+    RawQuery(raw_query): RawQuery,
+) -> Result<Response, SparqlError> {
+    let mut request = ProtocolRequest::default();
+    request.parse(raw_query.unwrap_or_default().as_bytes());
+    evaluate(&app_state, &claims, &headers, request).await
+}
+
+/// SPARQL 1.1 Protocol query via POST, either form-encoded or as a direct query body
+pub async fn sparql_post(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<UserClaims>,
+    headers: HeaderMap,
+    RawQuery(raw_query): RawQuery,
+    body: Bytes,
+) -> Result<Response, SparqlError> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    let mut request = ProtocolRequest::default();
+    match content_type.as_str() {
+        "application/sparql-query" => {
+            // The query is the body; dataset parameters stay in the URL
+            request.parse(raw_query.unwrap_or_default().as_bytes());
+            let query = String::from_utf8(body.to_vec()).map_err(|_| {
+                error(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_sparql_query",
+                    "SPARQL query body is not valid UTF-8".to_string(),
+                )
+            })?;
+            request.query = Some(query);
+        }
+        "application/x-www-form-urlencoded" => request.parse(&body),
+        "application/sparql-update" => request.update = true,
+        other => {
+            return Err(error(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "unsupported_media_type",
+                format!(
+                    "Unsupported content type '{}', expected application/sparql-query or \
+                     application/x-www-form-urlencoded",
+                    other
+                ),
+            ))
+        }
+    }
+
+    evaluate(&app_state, &claims, &headers, request).await
+}
+
+async fn evaluate(
+    app_state: &AppState,
+    claims: &UserClaims,
+    headers: &HeaderMap,
+    request: ProtocolRequest,
+) -> Result<Response, SparqlError> {
+    if request.update {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            "update_not_allowed",
+            "SPARQL updates are not accepted by this endpoint".to_string(),
+        ));
+    }
+    let query_text = request.query.as_deref().unwrap_or_default();
+    if query_text.trim().is_empty() {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            "missing_query",
+            "The 'query' parameter is required".to_string(),
+        ));
+    }
+    if query_text.len() > MAX_QUERY_LENGTH {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            "invalid_sparql_query",
+            format!(
+                "SPARQL query too long (max {} characters)",
+                MAX_QUERY_LENGTH
+            ),
+        ));
+    }
+
+    // Update operations do not parse as a query, so they are rejected here
+    let mut query = Query::parse(query_text, None).map_err(|e| {
+        error(
+            StatusCode::BAD_REQUEST,
+            "invalid_sparql_query",
+            format!("Invalid SPARQL query: {}", e),
+        )
+    })?;
+    set_dataset(&mut query, &request)?;
+
+    respond(app_state, claims, headers, request.format.as_deref(), query).await
+}
+
+/// Evaluate a parsed query and serialize the results in the negotiated format.
+///
+/// The query is first confined to the graphs `claims` may read. `format` overrides the
+/// `Accept` header when given.
+pub(crate) async fn respond(
+    app_state: &AppState,
+    claims: &UserClaims,
+    headers: &HeaderMap,
+    format: Option<&str>,
+    mut query: Query,
+) -> Result<Response, SparqlError> {
+    let accept = format.map_or_else(
+        || {
+            headers
+                .get(header::ACCEPT)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or("*/*")
+                .to_string()
+        },
+        |format| format.to_string(),
+    );
+
+    let blockchain = app_state.blockchain.read().await;
+    app_state
+        .graph_access
+        .read()
+        .await
+        .restrict(
+            &mut query,
+            &blockchain.rdf_store.store,
+            &claims.sub,
+            &claims.role,
+        )
+        .map_err(web_error)?;
+    let results = blockchain.rdf_store.store.query(query).map_err(|e| {
+        error(
+            StatusCode::BAD_REQUEST,
+            "query_evaluation_failed",
+            format!("SPARQL query evaluation failed: {}", e),
+        )
+    })?;
+
+    let (body, media_type) = match results {
+        QueryResults::Graph(_) => {
+            let format = negotiate_graph_format(&accept).ok_or_else(|| not_acceptable(&accept))?;
+            let body = results
+                .write_graph(Vec::new(), format)
+                .map_err(serialization_failed)?;
+            (body, format.media_type())
+        }
+        _ => {
+            let format =
+                negotiate_results_format(&accept).ok_or_else(|| not_acceptable(&accept))?;
+            let body = results
+                .write(Vec::new(), format)
+                .map_err(serialization_failed)?;
+            (body, format.media_type())
+        }
+    };
+
+    let mut response = body.into_response();
+    response
+        .headers_mut()
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static(media_type));
+    Ok(response)
+}
+
+/// Apply the protocol dataset, or default to the union of all graphs
+fn set_dataset(query: &mut Query, request: &ProtocolRequest) -> Result<(), SparqlError> {
+    if request.default_graph_uris.is_empty() && request.named_graph_uris.is_empty() {
+        default_to_union(query);
+        return Ok(());
+    }
+
+    let default_graphs = request
+        .default_graph_uris
+        .iter()
+        .map(|uri| graph_name(uri).map(GraphName::from))
+        .collect::<Result<Vec<_>, _>>()?;
+    let named_graphs = request
+        .named_graph_uris
+        .iter()
+        .map(|uri| graph_name(uri).map(NamedOrBlankNode::from))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let dataset = query.dataset_mut();
+    dataset.set_default_graph(default_graphs);
+    dataset.set_available_named_graphs(named_graphs);
+    Ok(())
+}
+
+/// Use the union of all graphs as the default graph unless the query names a dataset
+pub(crate) fn default_to_union(query: &mut Query) {
+    if query.dataset().is_default_dataset() {
+        query.dataset_mut().set_default_graph_as_union();
+    }
+}
+
+fn graph_name(uri: &str) -> Result<NamedNode, SparqlError> {
+    NamedNode::new(uri).map_err(|e| {
+        error(
+            StatusCode::BAD_REQUEST,
+            "invalid_graph_uri",
+            format!("Invalid graph URI '{}': {}", uri, e),
+        )
+    })
+}
+
+/// Media ranges of an `Accept` header (or a single `format` value), highest quality first
+fn media_ranges(accept: &str) -> Vec<String> {
+    let mut ranges: Vec<(String, f32)> = accept
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let media_type = parts.next()?.trim().to_ascii_lowercase();
+            if media_type.is_empty() {
+                return None;
+            }
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((media_type, quality))
+        })
+        .filter(|(_, quality)| *quality > 0.0)
+        .collect();
+    // Stable sort keeps the client's order among equal qualities
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranges
+        .into_iter()
+        .map(|(media_type, _)| media_type)
+        .collect()
+}
+
+/// Pick the first acceptable format from `candidates`, whose first entry is the default.
+///
+/// `parse` recognises a concrete media type or file extension; `media_type` gives the
+/// canonical media type of a candidate for matching `type/*` ranges.
+fn negotiate<F: Copy + PartialEq>(
+    accept: &str,
+    candidates: &[F],
+    parse: impl Fn(&str) -> Option<F>,
+    media_type: impl Fn(F) -> &'static str,
+) -> Option<F> {
+    for range in media_ranges(accept) {
+        if range == "*/*" || range == "*" {
+            return candidates.first().copied();
+        }
+        if let Some(prefix) = range.strip_suffix("/*") {
+            let matched = candidates.iter().copied().find(|candidate| {
+                media_type(*candidate)
+                    .split_once('/')
+                    .is_some_and(|(kind, _)| kind == prefix)
+            });
+            if matched.is_some() {
+                return matched;
+            }
+            continue;
+        }
+        if let Some(format) = parse(&range).filter(|format| candidates.contains(format)) {
+            return Some(format);
+        }
+    }
+    None
+}
+
+fn negotiate_results_format(accept: &str) -> Option<QueryResultsFormat> {
+    negotiate(
+        accept,
+        &RESULTS_FORMATS,
+        |range| {
+            QueryResultsFormat::from_media_type(range)
+                .or_else(|| QueryResultsFormat::from_extension(range))
+        },
+        QueryResultsFormat::media_type,
+    )
+}
+
+fn negotiate_graph_format(accept: &str) -> Option<RdfFormat> {
+    negotiate(
+        accept,
+        &GRAPH_FORMATS,
+        |range| {
+            RdfFormat::from_media_type(range)
+                .or_else(|| RdfFormat::from_extension(range))
+                .map(|format| match format {
+                    // Serialize JSON-LD in its plain form whatever profile was asked for
+                    RdfFormat::JsonLd { .. } => RdfFormat::JsonLd {
+                        profile: JsonLdProfileSet::empty(),
+                    },
+                    other => other,
+                })
+        },
+        RdfFormat::media_type,
+    )
+}
+
+fn not_acceptable(accept: &str) -> SparqlError {
+    error(
+        StatusCode::NOT_ACCEPTABLE,
+        "not_acceptable",
+        format!("No supported result format matches '{}'", accept),
+    )
+}
+
+fn serialization_failed(e: oxigraph::sparql::EvaluationError) -> SparqlError {
+    error(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "serialization_failed",
+        format!("Failed to serialize query results: {}", e),
+    )
+}
+
+fn error(status: StatusCode, error: &str, message: String) -> SparqlError {
+    (
+        status,
+        Json(ApiError {
+            error: error.to_string(),
+            message,
+            timestamp: Utc::now(),
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::blockchain::Blockchain;
+    use crate::web::handlers::{run_saved_sparql_query, save_sparql_query};
+    use crate::web::models::RunSavedQueryRequest;
+    use crate::web::saved_queries::{QueryParameter, SavedQueryDraft};
+    use axum::extract::Path;
+
+    fn app_state() -> AppState {
+        let mut blockchain = Blockchain::new();
+        blockchain
+            .add_block(
+                "@prefix ex: <http://example.org/> .\n\
+                 ex:batch1 ex:producedBy ex:farm1 .\n\
+                 ex:batch1 ex:weight \"12.5\" ."
+                    .to_string(),
+            )
+            .unwrap();
+        AppState::new(blockchain)
+    }
+
+    fn claims(user: &str, role: &str) -> Extension<UserClaims> {
+        Extension(UserClaims {
+            sub: user.to_string(),
+            role: role.to_string(),
+            exp: 0,
+            jti: String::new(),
+        })
+    }
+
+    fn headers(pairs: &[(header::HeaderName, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.clone(), HeaderValue::from_static(value)))
+            .collect()
+    }
+
+    async fn body_text(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    fn content_type(response: &Response) -> &str {
+        response.headers()[header::CONTENT_TYPE].to_str().unwrap()
+    }
+
+    #[test]
+    fn test_accept_negotiation() {
+        assert_eq!(
+            negotiate_results_format("*/*"),
+            Some(QueryResultsFormat::Json)
+        );
+        assert_eq!(
+            negotiate_results_format("text/csv;q=0.5, application/sparql-results+xml"),
+            Some(QueryResultsFormat::Xml)
+        );
+        assert_eq!(
+            negotiate_results_format("text/html, text/*;q=0.1"),
+            Some(QueryResultsFormat::Csv)
+        );
+        assert_eq!(
+            negotiate_results_format("tsv"),
+            Some(QueryResultsFormat::Tsv)
+        );
+        assert_eq!(negotiate_results_format("text/html"), None);
+
+        assert_eq!(negotiate_graph_format("*/*"), Some(RdfFormat::Turtle));
+        assert_eq!(
+            negotiate_graph_format("application/n-triples"),
+            Some(RdfFormat::NTriples)
+        );
+        assert_eq!(
+            negotiate_graph_format(
+                "application/ld+json;profile=\"http://www.w3.org/ns/json-ld#streaming\""
+            ),
+            Some(GRAPH_FORMATS[2])
+        );
+        assert_eq!(
+            negotiate_graph_format("application/sparql-results+json"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_select_as_csv() {
+        let query = "query=SELECT%20%3Fo%20WHERE%20%7B%20%3Chttp%3A%2F%2Fexample.org%2Fbatch1%3E%20%3Chttp%3A%2F%2Fexample.org%2FproducedBy%3E%20%3Fo%20%7D";
+        let response = sparql_get(
+            State(app_state()),
+            claims("alice", "farmer"),
+            headers(&[(header::ACCEPT, "text/csv")]),
+            RawQuery(Some(query.to_string())),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(content_type(&response), "text/csv; charset=utf-8");
+        assert_eq!(
+            body_text(response).await,
+            "o\r\nhttp://example.org/farm1\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_post_construct_as_ntriples() {
+        let response = sparql_post(
+            State(app_state()),
+            claims("alice", "farmer"),
+            headers(&[
+                (header::CONTENT_TYPE, "application/sparql-query"),
+                (header::ACCEPT, "application/n-triples"),
+            ]),
+            RawQuery(None),
+            Bytes::from_static(
+                b"CONSTRUCT { ?s <http://example.org/origin> ?o } \
+                  WHERE { ?s <http://example.org/producedBy> ?o }",
+            ),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(content_type(&response), "application/n-triples");
+        assert_eq!(
+            body_text(response).await.trim(),
+            "<http://example.org/batch1> <http://example.org/origin> <http://example.org/farm1> ."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_form_ask_as_xml_with_dataset() {
+        let state = app_state();
+        let ask = |graph: &'static str| {
+            let state = state.clone();
+            async move {
+                let body = form_urlencoded::Serializer::new(String::new())
+                    .append_pair("query", "ASK { ?s <http://example.org/producedBy> ?o }")
+                    .append_pair("default-graph-uri", graph)
+                    .finish();
+                let response = sparql_post(
+                    State(state),
+                    claims("alice", "farmer"),
+                    headers(&[
+                        (header::CONTENT_TYPE, "application/x-www-form-urlencoded"),
+                        (header::ACCEPT, "application/sparql-results+xml"),
+                    ]),
+                    RawQuery(None),
+                    Bytes::from(body),
+                )
+                .await
+                .unwrap();
+                assert_eq!(content_type(&response), "application/sparql-results+xml");
+                body_text(response).await
+            }
+        };
+
+        assert!(ask("http://provchain.org/block/1")
+            .await
+            .contains("<boolean>true</boolean>"));
+        assert!(ask("http://provchain.org/block/0")
+            .await
+            .contains("<boolean>false</boolean>"));
+    }
+
+    #[tokio::test]
+    async fn test_run_saved_query_by_id() {
+        let state = app_state();
+
+        let (_, Json(saved)) = save_sparql_query(
+            State(state.clone()),
+            claims("alice", "farmer"),
+            Json(SavedQueryDraft {
+                name: Some("Farm of batch".to_string()),
+                query: "SELECT ?farm WHERE { ?batch <http://example.org/producedBy> ?farm }"
+                    .to_string(),
+                parameters: vec![QueryParameter {
+                    name: "batch".to_string(),
+                    description: None,
+                    default_value: None,
+                }],
+                ..Default::default()
+            }),
+        )
+        .await
+        .unwrap();
+        let id = saved["id"].as_str().unwrap().to_string();
+
+        let run = |user: &'static str, role: &'static str, batch: &str| {
+            run_saved_sparql_query(
+                State(state.clone()),
+                claims(user, role),
+                Path(id.clone()),
+                headers(&[(header::ACCEPT, "text/tab-separated-values")]),
+                Json(RunSavedQueryRequest {
+                    bindings: [("batch".to_string(), batch.to_string())].into(),
+                    ..Default::default()
+                }),
+            )
+        };
+
+        let response = run("alice", "farmer", "http://example.org/batch1")
+            .await
+            .unwrap();
+        assert_eq!(
+            body_text(response).await,
+            "?farm\n<http://example.org/farm1>\n"
+        );
+
+        // Not shared with other roles
+        let denied = run("bob", "retailer", "http://example.org/batch1").await;
+        assert_eq!(denied.unwrap_err().0, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_queries_only_see_permitted_graphs() {
+        let state = app_state();
+        state
+            .graph_access
+            .write()
+            .await
+            .record_owner("http://provchain.org/block/1", "alice")
+            .unwrap();
+        let ask = |user: &'static str, role: &'static str| {
+            let state = state.clone();
+            async move {
+                let response = sparql_get(
+                    State(state),
+                    claims(user, role),
+                    headers(&[(header::ACCEPT, "text/csv")]),
+                    RawQuery(Some(
+                        "query=ASK%20%7B%20%3Fs%20%3Chttp%3A%2F%2Fexample.org%2FproducedBy%3E%20%3Fo%20%7D"
+                            .to_string(),
+                    )),
+                )
+                .await
+                .unwrap();
+                body_text(response).await
+            }
+        };
+
+        assert_eq!(ask("alice", "farmer").await, "true");
+        assert_eq!(ask("bob", "farmer").await, "false");
+        assert_eq!(ask("carol", "auditor").await, "true");
+    }
+
+    #[tokio::test]
+    async fn test_protocol_errors() {
+        let status = |result: Result<Response, SparqlError>| result.unwrap_err().0;
+
+        let unsupported = sparql_post(
+            State(app_state()),
+            claims("alice", "farmer"),
+            headers(&[(header::CONTENT_TYPE, "application/json")]),
+            RawQuery(None),
+            Bytes::from_static(b"{\"query\": \"ASK {}\"}"),
+        )
+        .await;
+        assert_eq!(status(unsupported), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let missing = sparql_get(
+            State(app_state()),
+            claims("alice", "farmer"),
+            HeaderMap::new(),
+            RawQuery(None),
+        )
+        .await;
+        assert_eq!(status(missing), StatusCode::BAD_REQUEST);
+
+        let update = sparql_post(
+            State(app_state()),
+            claims("alice", "farmer"),
+            headers(&[(header::CONTENT_TYPE, "application/sparql-query")]),
+            RawQuery(None),
+            Bytes::from_static(b"DELETE WHERE { ?s ?p ?o }"),
+        )
+        .await;
+        assert_eq!(status(update), StatusCode::BAD_REQUEST);
+
+        let not_acceptable = sparql_get(
+            State(app_state()),
+            claims("alice", "farmer"),
+            headers(&[(header::ACCEPT, "text/turtle")]),
+            RawQuery(Some("query=ASK%20%7B%7D".to_string())),
+        )
+        .await;
+        assert_eq!(status(not_acceptable), StatusCode::NOT_ACCEPTABLE);
+    }
+}
@@ -0,0 +1,1243 @@
+//! Outbound webhooks on chain events
+//!
+//! A user registers a URL together with the event types and `EventFilter` a WebSocket
+//! client would subscribe with. Every matching `BlockchainEvent` is then POSTed to the
+//! URL as JSON, signed with HMAC-SHA256 under a secret handed out once at registration:
+//!
+//! ```text
+//! X-ProvChain-Signature: sha256=<hex HMAC of the request body>
+//! ```
+//!
+//! Failed deliveries are retried with exponential backoff. A delivery still failing after
+//! the last attempt is dead-lettered: it stays in the webhook's delivery history until it
+//! is redelivered, expires or is pushed out by newer dead letters. The queue, the history
+//! and the dead letters of each webhook are bounded. Filters look into the block graphs
+//! the webhook's owner may read.
+//!
+//! Deliveries only go to public addresses: URLs naming a loopback, private, link-local
+//! (including cloud metadata endpoints) or otherwise local address are refused, and so
+//! are host names that resolve only to such addresses at delivery time, unless the host is on the node's
+//! `webhook_allowed_hosts` list. Redirects are not followed.
+//!
+//! Webhooks and their delivery queue and history are kept in `webhooks.json` in the node
+//! data directory, so pending deliveries survive a restart, or only in memory when the
+//! node has no persistent storage.
+
+use crate::core::blockchain::Blockchain;
+use crate::error::WebError;
+use crate::web::graph_access::GraphAccessStore;
+use crate::web::models::ActorRole;
+use crate::web::openapi::api_model;
+use crate::web::websocket::{BlockchainEvent, EventFilter, Subscription};
+use chrono::{DateTime, Utc};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// File name of the store inside the data directory
+const STORE_FILE: &str = "webhooks.json";
+
+/// Finished deliveries kept per webhook
+const HISTORY_LIMIT: usize = 100;
+
+/// Dead letters kept per webhook; the oldest are dropped first
+const DEAD_LETTER_LIMIT: usize = 100;
+
+/// How long a dead letter waits to be redelivered before it is dropped
+const DEAD_LETTER_RETENTION_DAYS: i64 = 7;
+
+/// Deliveries queued per webhook; the oldest beyond it are dead-lettered
+const QUEUE_LIMIT: usize = 1000;
+
+/// Host names that always point at the node itself or its cloud environment
+const INTERNAL_HOSTS: [&str; 3] = ["localhost", "metadata", "metadata.google.internal"];
+
+/// Header carrying the HMAC-SHA256 signature of the request body
+pub const SIGNATURE_HEADER: &str = "X-ProvChain-Signature";
+
+/// Header carrying the event type
+pub const EVENT_HEADER: &str = "X-ProvChain-Event";
+
+/// Header carrying the delivery ID, stable across retries
+pub const DELIVERY_HEADER: &str = "X-ProvChain-Delivery";
+
+/// A registered webhook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    pub owner: String,
+    /// Role of the owner at registration, deciding which block graphs filters see
+    pub owner_role: String,
+    /// Event types; empty means every type
+    pub events: Vec<String>,
+    pub filter: EventFilter,
+    /// Hex HMAC-SHA256 key; only shown when the webhook is registered
+    pub secret: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Webhook {
+    /// The webhook as returned by the API, without its secret
+    pub fn view(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "url": self.url,
+            "owner": self.owner,
+            "events": self.events,
+            "filter": self.filter,
+            "created_at": self.created_at,
+        })
+    }
+
+    fn visible_to(&self, user: &str, role: &str) -> bool {
+        self.owner == user || role == ActorRole::Admin.to_string()
+    }
+}
+
+api_model! {
+    /// Where a delivery stands
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum DeliveryStatus {
+        /// Waiting for its next attempt
+        Pending,
+        Delivered,
+        /// Out of attempts; waiting to be redelivered by hand
+        DeadLettered,
+    }
+}
+
+api_model! {
+    /// One HTTP call made for a delivery
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct DeliveryAttempt {
+        pub at: DateTime<Utc>,
+        /// HTTP status of the response, if one came back
+        pub status_code: Option<u16>,
+        pub error: Option<String>,
+    }
+}
+
+impl DeliveryAttempt {
+    /// Whether the receiver accepted the delivery
+    pub fn succeeded(&self) -> bool {
+        self.status_code
+            .is_some_and(|code| (200..300).contains(&code))
+    }
+}
+
+api_model! {
+    /// An event queued for, or delivered to, a webhook
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Delivery {
+        pub id: String,
+        pub webhook_id: String,
+        pub event_type: String,
+        /// Request body, signed as is on every attempt
+        pub payload: String,
+        pub status: DeliveryStatus,
+        pub attempts: Vec<DeliveryAttempt>,
+        pub created_at: DateTime<Utc>,
+        /// When the next attempt is due, while pending
+        pub next_attempt_at: Option<DateTime<Utc>>,
+    }
+}
+
+/// A pending delivery that is due, with what is needed to make the call
+#[derive(Debug, Clone)]
+pub struct DueDelivery {
+    pub delivery_id: String,
+    pub event_type: String,
+    pub url: String,
+    pub secret: String,
+    pub payload: String,
+}
+
+/// How often and how patiently deliveries are attempted
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Attempts before a delivery is dead-lettered
+    pub max_attempts: u32,
+    /// Wait after the first failed attempt, doubled after each further one
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Timeout of each HTTP call
+    pub timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 6,
+            initial_backoff: Duration::from_secs(5),
+            max_backoff: Duration::from_secs(600),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before the next attempt after `failed_attempts` failures
+    pub fn backoff(&self, failed_attempts: u32) -> Duration {
+        let doublings = failed_attempts.saturating_sub(1).min(31);
+        self.initial_backoff
+            .saturating_mul(1 << doublings)
+            .min(self.max_backoff)
+    }
+}
+
+/// Where deliveries may be sent
+#[derive(Debug, Clone, Default)]
+pub struct DestinationPolicy {
+    /// Hosts that may be reached on a non-public address, e.g. an ERP on the LAN
+    allowed_hosts: HashSet<String>,
+}
+
+impl DestinationPolicy {
+    /// Policy allowing `allowed_hosts` in addition to public addresses
+    pub fn new<I: IntoIterator<Item = String>>(allowed_hosts: I) -> Self {
+        Self {
+            allowed_hosts: allowed_hosts
+                .into_iter()
+                .map(|host| normalize_host(&host))
+                .filter(|host| !host.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Check a webhook URL: http or https, naming a public address or an allowed host
+    pub fn check(&self, url: &reqwest::Url) -> Result<(), WebError> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(WebError::BadRequest(
+                "Webhook URLs must be http or https".to_string(),
+            ));
+        }
+        let host = url
+            .host_str()
+            .map(normalize_host)
+            .ok_or_else(|| WebError::BadRequest("Webhook URLs must name a host".to_string()))?;
+        if self.allowed_hosts.contains(&host) {
+            return Ok(());
+        }
+
+        let local = match host.parse::<IpAddr>() {
+            Ok(ip) => !is_public(ip),
+            Err(_) => INTERNAL_HOSTS.contains(&host.as_str()) || host.ends_with(".localhost"),
+        };
+        if local {
+            return Err(WebError::BadRequest(format!(
+                "Webhook URLs may not point at {}, a local or private address",
+                host
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether `host` may be reached on `ip`
+    fn allows(&self, host: &str, ip: IpAddr) -> bool {
+        is_public(ip) || self.allowed_hosts.contains(&normalize_host(host))
+    }
+}
+
+/// Lower-case host without IPv6 brackets or a trailing dot
+fn normalize_host(host: &str) -> String {
+    host.trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .trim_end_matches('.')
+        .to_ascii_lowercase()
+}
+
+/// Whether `ip` is a public unicast address, rather than loopback, private, link-local,
+/// shared, documentation, multicast or reserved
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || first == 0
+                || first >= 240
+                || (first == 100 && second & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                !(ip.is_unspecified()
+                    || ip.is_loopback()
+                    || ip.is_multicast()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+/// HTTP client for deliveries, which does not follow redirects
+fn delivery_client() -> reqwest::ClientBuilder {
+    reqwest::Client::builder().redirect(reqwest::redirect::Policy::none())
+}
+
+/// Signature header value of a request body
+pub fn sign(secret: &str, payload: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    format!("sha256={}", hex::encode(hmac::sign(&key, payload).as_ref()))
+}
+
+/// Check a signature header value against a request body, as a receiver would
+pub fn verify_signature(secret: &str, payload: &[u8], signature: &str) -> bool {
+    let Some(tag) = signature
+        .strip_prefix("sha256=")
+        .and_then(|tag| hex::decode(tag).ok())
+    else {
+        return false;
+    };
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    hmac::verify(&key, payload, &tag).is_ok()
+}
+
+/// On-disk layout of the store
+#[derive(Default, Deserialize)]
+struct StoreFile {
+    webhooks: BTreeMap<String, Webhook>,
+    deliveries: Vec<Delivery>,
+}
+
+/// Registered webhooks and their delivery queue and history
+#[derive(Debug, Default)]
+pub struct WebhookStore {
+    webhooks: BTreeMap<String, Webhook>,
+    /// Deliveries of every webhook, oldest first
+    deliveries: Vec<Delivery>,
+    /// Parsed event types and filter of each webhook
+    subscriptions: HashMap<String, Subscription>,
+    /// Where webhooks may be registered to
+    destinations: DestinationPolicy,
+    /// Store file; `None` keeps everything in memory only
+    path: Option<PathBuf>,
+}
+
+impl WebhookStore {
+    /// Store that is not persisted
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open the store kept in `data_dir`, starting empty if it does not exist yet
+    pub fn open<P: AsRef<Path>>(data_dir: P) -> anyhow::Result<Self> {
+        let path = data_dir.as_ref().join(STORE_FILE);
+        let file: StoreFile = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            StoreFile::default()
+        };
+
+        let mut subscriptions = HashMap::new();
+        for webhook in file.webhooks.values() {
+            let subscription = Subscription::new(webhook.events.clone(), webhook.filter.clone())
+                .map_err(|e| anyhow::anyhow!("Webhook {}: {}", webhook.id, e))?;
+            subscriptions.insert(webhook.id.clone(), subscription);
+        }
+
+        Ok(Self {
+            webhooks: file.webhooks,
+            deliveries: file.deliveries,
+            subscriptions,
+            destinations: DestinationPolicy::default(),
+            path: Some(path),
+        })
+    }
+
+    /// Allow registering webhooks to the hosts of `destinations` on non-public addresses
+    pub fn with_destinations(mut self, destinations: DestinationPolicy) -> Self {
+        self.destinations = destinations;
+        self
+    }
+
+    /// Register a webhook for `owner`, generating its signing secret
+    pub fn register(
+        &mut self,
+        owner: &str,
+        owner_role: &str,
+        url: &str,
+        events: Vec<String>,
+        filter: EventFilter,
+    ) -> Result<&Webhook, WebError> {
+        let parsed = reqwest::Url::parse(url)
+            .map_err(|e| WebError::BadRequest(format!("Invalid webhook URL: {}", e)))?;
+        self.destinations.check(&parsed)?;
+        let subscription = Subscription::new(events.clone(), filter.clone())?;
+
+        let webhook = Webhook {
+            id: Uuid::new_v4().to_string(),
+            url: parsed.to_string(),
+            owner: owner.to_string(),
+            owner_role: owner_role.to_string(),
+            events,
+            filter,
+            secret: hex::encode(rand::random::<[u8; 32]>()),
+            created_at: Utc::now(),
+        };
+        let id = webhook.id.clone();
+        self.subscriptions.insert(id.clone(), subscription);
+        self.webhooks.insert(id.clone(), webhook);
+
+        self.persist()?;
+        Ok(&self.webhooks[&id])
+    }
+
+    /// Webhooks `user` owns, or every webhook for admins
+    pub fn list(&self, user: &str, role: &str) -> Vec<&Webhook> {
+        self.webhooks
+            .values()
+            .filter(|webhook| webhook.visible_to(user, role))
+            .collect()
+    }
+
+    /// A webhook `user` owns, or any webhook for admins
+    pub fn get(&self, id: &str, user: &str, role: &str) -> Result<&Webhook, WebError> {
+        self.webhooks
+            .get(id)
+            .filter(|webhook| webhook.visible_to(user, role))
+            .ok_or_else(|| WebError::ResourceNotFound(format!("Webhook {}", id)))
+    }
+
+    /// Delete a webhook together with its deliveries
+    pub fn delete(&mut self, id: &str, user: &str, role: &str) -> Result<(), WebError> {
+        self.get(id, user, role)?;
+        self.webhooks.remove(id);
+        self.subscriptions.remove(id);
+        self.deliveries.retain(|delivery| delivery.webhook_id != id);
+        self.persist()
+    }
+
+    /// Deliveries of a webhook, newest first, optionally only those with `status`
+    pub fn deliveries(
+        &self,
+        id: &str,
+        user: &str,
+        role: &str,
+        status: Option<DeliveryStatus>,
+    ) -> Result<Vec<&Delivery>, WebError> {
+        self.get(id, user, role)?;
+        Ok(self
+            .deliveries
+            .iter()
+            .rev()
+            .filter(|delivery| delivery.webhook_id == id)
+            .filter(|delivery| status.is_none_or(|status| delivery.status == status))
+            .collect())
+    }
+
+    /// Put a dead-lettered delivery back in the queue, due now
+    pub fn redeliver(
+        &mut self,
+        id: &str,
+        delivery_id: &str,
+        user: &str,
+        role: &str,
+    ) -> Result<&Delivery, WebError> {
+        self.get(id, user, role)?;
+        let index = self
+            .deliveries
+            .iter()
+            .position(|delivery| delivery.id == delivery_id && delivery.webhook_id == id)
+            .ok_or_else(|| WebError::ResourceNotFound(format!("Delivery {}", delivery_id)))?;
+        let delivery = &mut self.deliveries[index];
+        if delivery.status != DeliveryStatus::DeadLettered {
+            return Err(WebError::BadRequest(format!(
+                "Delivery {} is not dead-lettered",
+                delivery_id
+            )));
+        }
+        delivery.status = DeliveryStatus::Pending;
+        delivery.next_attempt_at = Some(Utc::now());
+
+        self.persist()?;
+        Ok(&self.deliveries[index])
+    }
+
+    /// Queue `event` for every webhook it matches, returning how many it was queued for
+    pub fn enqueue(
+        &mut self,
+        event: &BlockchainEvent,
+        blockchain: &Blockchain,
+        graph_access: &GraphAccessStore,
+    ) -> Result<usize, WebError> {
+        let now = Utc::now();
+        let mut queued = 0;
+        for webhook in self.webhooks.values() {
+            let readable =
+                |graph: &str| graph_access.can_read(graph, &webhook.owner, &webhook.owner_role);
+            if !self.subscriptions[&webhook.id].matches(event, blockchain, readable) {
+                continue;
+            }
+
+            let id = Uuid::new_v4().to_string();
+            let payload = serde_json::json!({
+                "delivery_id": id,
+                "webhook_id": webhook.id,
+                "event": event,
+                "created_at": now,
+            });
+            self.deliveries.push(Delivery {
+                id,
+                webhook_id: webhook.id.clone(),
+                event_type: event.event_type().to_string(),
+                payload: payload.to_string(),
+                status: DeliveryStatus::Pending,
+                attempts: Vec::new(),
+                created_at: now,
+                next_attempt_at: Some(now),
+            });
+            queued += 1;
+        }
+
+        let ids: Vec<String> = self.webhooks.keys().cloned().collect();
+        let mut pruned = false;
+        for id in ids {
+            pruned |= self.prune(&id, now);
+        }
+        if queued > 0 || pruned {
+            self.persist()?;
+        }
+        Ok(queued)
+    }
+
+    /// Pending deliveries whose next attempt is due at `now`
+    pub fn due(&self, now: DateTime<Utc>) -> Vec<DueDelivery> {
+        self.deliveries
+            .iter()
+            .filter(|delivery| delivery.status == DeliveryStatus::Pending)
+            .filter(|delivery| delivery.next_attempt_at.is_some_and(|at| at <= now))
+            .filter_map(|delivery| {
+                let webhook = self.webhooks.get(&delivery.webhook_id)?;
+                Some(DueDelivery {
+                    delivery_id: delivery.id.clone(),
+                    event_type: delivery.event_type.clone(),
+                    url: webhook.url.clone(),
+                    secret: webhook.secret.clone(),
+                    payload: delivery.payload.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Record an attempt, scheduling a retry or dead-lettering the delivery if it failed
+    pub fn record(
+        &mut self,
+        delivery_id: &str,
+        attempt: DeliveryAttempt,
+        policy: &RetryPolicy,
+    ) -> Result<(), WebError> {
+        let Some(delivery) = self
+            .deliveries
+            .iter_mut()
+            .find(|delivery| delivery.id == delivery_id)
+        else {
+            // The webhook was deleted while the call was in flight
+            return Ok(());
+        };
+
+        let succeeded = attempt.succeeded();
+        let at = attempt.at;
+        delivery.attempts.push(attempt);
+        // A redelivered dead letter gets a fresh round of attempts
+        let max_attempts = policy.max_attempts.max(1) as usize;
+        let attempt_in_round = (delivery.attempts.len() - 1) % max_attempts + 1;
+        if succeeded {
+            delivery.status = DeliveryStatus::Delivered;
+            delivery.next_attempt_at = None;
+        } else if attempt_in_round == max_attempts {
+            delivery.status = DeliveryStatus::DeadLettered;
+            delivery.next_attempt_at = None;
+        } else {
+            let backoff = chrono::Duration::from_std(policy.backoff(attempt_in_round as u32))
+                .unwrap_or(chrono::Duration::MAX);
+            delivery.next_attempt_at = Some(at + backoff);
+        }
+
+        let webhook_id = delivery.webhook_id.clone();
+        self.prune(&webhook_id, at);
+        self.persist()
+    }
+
+    /// Bound the deliveries of a webhook: the oldest pending ones beyond the queue limit
+    /// are dead-lettered, dead letters past their retention or beyond their limit are
+    /// dropped, and so are the oldest delivered ones beyond the history limit. Returns
+    /// whether anything changed.
+    fn prune(&mut self, webhook_id: &str, now: DateTime<Utc>) -> bool {
+        let before = self.deliveries.len();
+
+        let mut overflow = self
+            .count(webhook_id, DeliveryStatus::Pending)
+            .saturating_sub(QUEUE_LIMIT);
+        let overflowed = overflow > 0;
+        for delivery in self.deliveries.iter_mut() {
+            if overflow == 0 {
+                break;
+            }
+            if delivery.webhook_id == webhook_id && delivery.status == DeliveryStatus::Pending {
+                delivery.status = DeliveryStatus::DeadLettered;
+                delivery.next_attempt_at = None;
+                overflow -= 1;
+            }
+        }
+
+        let expiry = now - chrono::Duration::days(DEAD_LETTER_RETENTION_DAYS);
+        self.deliveries.retain(|delivery| {
+            let last_activity = delivery
+                .attempts
+                .last()
+                .map_or(delivery.created_at, |attempt| attempt.at);
+            !(delivery.webhook_id == webhook_id
+                && delivery.status == DeliveryStatus::DeadLettered
+                && last_activity < expiry)
+        });
+        self.drop_oldest(webhook_id, DeliveryStatus::DeadLettered, DEAD_LETTER_LIMIT);
+        self.drop_oldest(webhook_id, DeliveryStatus::Delivered, HISTORY_LIMIT);
+
+        overflowed || self.deliveries.len() != before
+    }
+
+    /// Deliveries of a webhook with `status`
+    fn count(&self, webhook_id: &str, status: DeliveryStatus) -> usize {
+        self.deliveries
+            .iter()
+            .filter(|delivery| delivery.webhook_id == webhook_id && delivery.status == status)
+            .count()
+    }
+
+    /// Drop the oldest deliveries of a webhook with `status` beyond `limit`
+    fn drop_oldest(&mut self, webhook_id: &str, status: DeliveryStatus, limit: usize) {
+        let mut excess = self.count(webhook_id, status).saturating_sub(limit);
+        self.deliveries.retain(|delivery| {
+            let drop = excess > 0 && delivery.webhook_id == webhook_id && delivery.status == status;
+            if drop {
+                excess -= 1;
+            }
+            !drop
+        });
+    }
+
+    fn persist(&self) -> Result<(), WebError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let write = || -> anyhow::Result<()> {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let file = serde_json::json!({
+                "webhooks": self.webhooks,
+                "deliveries": self.deliveries,
+            });
+            let temp = path.with_extension("json.tmp");
+            fs::write(&temp, serde_json::to_vec_pretty(&file)?)?;
+            fs::rename(&temp, path)?;
+            Ok(())
+        };
+        write().map_err(|e| WebError::ServerError(format!("Failed to save webhooks: {}", e)))
+    }
+}
+
+/// Background task turning chain events into webhook deliveries
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    webhooks: Arc<RwLock<WebhookStore>>,
+    blockchain: Arc<RwLock<Blockchain>>,
+    graph_access: Arc<RwLock<GraphAccessStore>>,
+    client: reqwest::Client,
+    destinations: DestinationPolicy,
+    policy: RetryPolicy,
+}
+
+impl WebhookDispatcher {
+    pub fn new(
+        webhooks: Arc<RwLock<WebhookStore>>,
+        blockchain: Arc<RwLock<Blockchain>>,
+        graph_access: Arc<RwLock<GraphAccessStore>>,
+    ) -> Self {
+        Self {
+            webhooks,
+            blockchain,
+            graph_access,
+            client: delivery_client().build().expect("webhook HTTP client"),
+            destinations: DestinationPolicy::default(),
+            policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Also deliver to the hosts of `destinations` on non-public addresses
+    pub fn with_destinations(mut self, destinations: DestinationPolicy) -> Self {
+        self.destinations = destinations;
+        self
+    }
+
+    /// Use a different retry policy
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Queue the events received on `events` and deliver due calls until the channel
+    /// closes
+    pub fn spawn(self, mut events: broadcast::Receiver<BlockchainEvent>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let tick = self.policy.initial_backoff.min(Duration::from_secs(1));
+            loop {
+                tokio::select! {
+                    event = events.recv() => match event {
+                        Ok(event) => {
+                            if let Err(e) = self.enqueue(&event).await {
+                                warn!("Failed to queue webhook deliveries: {}", e);
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(missed)) => {
+                            warn!("Webhook dispatcher missed {} events", missed);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    _ = tokio::time::sleep(tick) => {}
+                }
+                self.deliver_due().await;
+            }
+        })
+    }
+
+    /// Queue `event` for the webhooks it matches
+    pub async fn enqueue(&self, event: &BlockchainEvent) -> Result<usize, WebError> {
+        let blockchain = self.blockchain.read().await;
+        let graph_access = self.graph_access.read().await;
+        self.webhooks
+            .write()
+            .await
+            .enqueue(event, &blockchain, &graph_access)
+    }
+
+    /// Attempt every delivery that is due
+    pub async fn deliver_due(&self) {
+        let due = self.webhooks.read().await.due(Utc::now());
+        if due.is_empty() {
+            return;
+        }
+
+        let attempts =
+            futures_util::future::join_all(due.iter().map(|delivery| self.attempt(delivery))).await;
+        let mut webhooks = self.webhooks.write().await;
+        for (delivery, attempt) in due.iter().zip(attempts) {
+            if let Err(e) = webhooks.record(&delivery.delivery_id, attempt, &self.policy) {
+                warn!("Failed to record webhook delivery: {}", e);
+            }
+        }
+    }
+
+    /// Client for a call to `url`, checked against the destination policy again in case
+    /// the webhook was registered under a wider one. A host name is resolved here and the
+    /// call pinned to its permitted addresses, so the name cannot be pointed at the node's
+    /// own network after registration.
+    async fn client_for(&self, url: &str) -> Result<reqwest::Client, WebError> {
+        let url = reqwest::Url::parse(url)
+            .map_err(|e| WebError::BadRequest(format!("Invalid webhook URL: {}", e)))?;
+        self.destinations.check(&url)?;
+        let host = url.host_str().unwrap_or_default();
+        if normalize_host(host).parse::<IpAddr>().is_ok() {
+            return Ok(self.client.clone());
+        }
+
+        let port = url.port_or_known_default().unwrap_or(80);
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| WebError::ServerError(format!("Failed to resolve {}: {}", host, e)))?
+            .filter(|addr| self.destinations.allows(host, addr.ip()))
+            .collect();
+        if addrs.is_empty() {
+            return Err(WebError::BadRequest(format!(
+                "{} does not resolve to a public address",
+                host
+            )));
+        }
+        delivery_client()
+            .resolve_to_addrs(host, &addrs)
+            .build()
+            .map_err(|e| WebError::ServerError(format!("Failed to build HTTP client: {}", e)))
+    }
+
+    async fn attempt(&self, delivery: &DueDelivery) -> DeliveryAttempt {
+        let client = match self.client_for(&delivery.url).await {
+            Ok(client) => client,
+            Err(e) => {
+                return DeliveryAttempt {
+                    at: Utc::now(),
+                    status_code: None,
+                    error: Some(e.to_string()),
+                }
+            }
+        };
+
+        let response = client
+            .post(&delivery.url)
+            .timeout(self.policy.timeout)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(
+                SIGNATURE_HEADER,
+                sign(&delivery.secret, delivery.payload.as_bytes()),
+            )
+            .header(EVENT_HEADER, &delivery.event_type)
+            .header(DELIVERY_HEADER, &delivery.delivery_id)
+            .body(delivery.payload.clone())
+            .send()
+            .await;
+
+        let attempt = match response {
+            Ok(response) => DeliveryAttempt {
+                at: Utc::now(),
+                status_code: Some(response.status().as_u16()),
+                error: (!response.status().is_success())
+                    .then(|| format!("Receiver answered {}", response.status())),
+            },
+            Err(e) => DeliveryAttempt {
+                at: Utc::now(),
+                status_code: None,
+                error: Some(e.to_string()),
+            },
+        };
+        debug!(
+            "Webhook delivery {} to {}: {:?}",
+            delivery.delivery_id, delivery.url, attempt
+        );
+        attempt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::blockchain::Block;
+    use axum::{extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    fn batch_filter(batch: &str) -> EventFilter {
+        EventFilter {
+            batch_ids: vec![batch.to_string()],
+            ..Default::default()
+        }
+    }
+
+    /// Policy letting the tests deliver to their receiver on the loopback address
+    fn loopback() -> DestinationPolicy {
+        DestinationPolicy::new(["127.0.0.1".to_string()])
+    }
+
+    fn chain_with_batch() -> Blockchain {
+        let mut blockchain = Blockchain::new();
+        blockchain
+            .add_block(
+                "@prefix ex: <http://example.org/> .\n\
+                 ex:B001 <http://provchain.org/trace#hasBatchID> \"B001\" ."
+                    .to_string(),
+            )
+            .unwrap();
+        blockchain
+    }
+
+    #[test]
+    fn test_signatures_verify_only_the_signed_body() {
+        let signature = sign("secret", b"{\"event\":1}");
+        assert!(signature.starts_with("sha256="));
+        assert!(verify_signature("secret", b"{\"event\":1}", &signature));
+        assert!(!verify_signature("secret", b"{\"event\":2}", &signature));
+        assert!(!verify_signature("other", b"{\"event\":1}", &signature));
+        assert!(!verify_signature("secret", b"{\"event\":1}", "sha256=zz"));
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_secs(5),
+            max_backoff: Duration::from_secs(30),
+            ..Default::default()
+        };
+        let waits: Vec<u64> = (1..=5).map(|n| policy.backoff(n).as_secs()).collect();
+        assert_eq!(waits, vec![5, 10, 20, 30, 30]);
+    }
+
+    #[test]
+    fn test_webhooks_are_scoped_to_owners_and_readable_graphs() {
+        let mut store = WebhookStore::new();
+        assert!(store
+            .register(
+                "alice",
+                "farmer",
+                "ftp://erp.local",
+                Vec::new(),
+                EventFilter::default()
+            )
+            .is_err());
+        assert!(store
+            .register(
+                "alice",
+                "farmer",
+                "http://erp.local/hook",
+                vec!["BlockMined".to_string()],
+                EventFilter::default()
+            )
+            .is_err());
+
+        let alice = store
+            .register(
+                "alice",
+                "farmer",
+                "http://erp.local/a",
+                Vec::new(),
+                batch_filter("B001"),
+            )
+            .unwrap()
+            .id
+            .clone();
+        let bob = store
+            .register(
+                "bob",
+                "processor",
+                "http://erp.local/b",
+                Vec::new(),
+                batch_filter("B001"),
+            )
+            .unwrap()
+            .id
+            .clone();
+        assert_eq!(store.list("alice", "farmer").len(), 1);
+        assert_eq!(store.list("root", "admin").len(), 2);
+        assert!(store.get(&alice, "bob", "processor").is_err());
+        assert!(store.delete(&alice, "bob", "processor").is_err());
+        assert!(store.get(&alice, "alice", "farmer").unwrap().view()["secret"].is_null());
+
+        // Block 1 is private to alice, so only her webhook sees into it
+        let blockchain = chain_with_batch();
+        let mut graph_access = GraphAccessStore::new();
+        graph_access
+            .record_owner(&Block::graph_uri(1), "alice")
+            .unwrap();
+        let event = BlockchainEvent::block_created(&blockchain.chain[1]);
+        assert_eq!(
+            store.enqueue(&event, &blockchain, &graph_access).unwrap(),
+            1
+        );
+        assert_eq!(
+            store
+                .deliveries(&alice, "alice", "farmer", None)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(store
+            .deliveries(&bob, "bob", "processor", None)
+            .unwrap()
+            .is_empty());
+
+        store.delete(&alice, "alice", "farmer").unwrap();
+        assert!(store.due(Utc::now()).is_empty());
+    }
+
+    /// Stand-in receiver: `/flaky` fails its first two calls, `/down` always fails
+    async fn receiver() -> (String, Arc<Mutex<Vec<(HeaderMap, String)>>>) {
+        #[derive(Clone)]
+        struct Receiver {
+            calls: Arc<AtomicUsize>,
+            received: Arc<Mutex<Vec<(HeaderMap, String)>>>,
+        }
+
+        async fn flaky(
+            State(receiver): State<Receiver>,
+            headers: HeaderMap,
+            body: String,
+        ) -> StatusCode {
+            if receiver.calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                return StatusCode::INTERNAL_SERVER_ERROR;
+            }
+            receiver.received.lock().unwrap().push((headers, body));
+            StatusCode::NO_CONTENT
+        }
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let app = Router::new()
+            .route("/flaky", post(flaky))
+            .route("/down", post(|| async { StatusCode::SERVICE_UNAVAILABLE }))
+            .with_state(Receiver {
+                calls: Arc::new(AtomicUsize::new(0)),
+                received: received.clone(),
+            });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, received)
+    }
+
+    #[tokio::test]
+    async fn test_deliveries_retry_and_dead_letter() {
+        let (url, received) = receiver().await;
+        let webhooks = Arc::new(RwLock::new(
+            WebhookStore::new().with_destinations(loopback()),
+        ));
+        let dispatcher = WebhookDispatcher::new(
+            webhooks.clone(),
+            Arc::new(RwLock::new(chain_with_batch())),
+            Arc::new(RwLock::new(GraphAccessStore::new())),
+        )
+        .with_destinations(loopback())
+        .with_retry_policy(RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(20),
+            timeout: Duration::from_secs(5),
+        });
+
+        let (flaky, secret) = {
+            let mut store = webhooks.write().await;
+            let flaky = store
+                .register(
+                    "alice",
+                    "farmer",
+                    &format!("{}/flaky", url),
+                    Vec::new(),
+                    batch_filter("B001"),
+                )
+                .unwrap();
+            let flaky = (flaky.id.clone(), flaky.secret.clone());
+            store
+                .register(
+                    "alice",
+                    "farmer",
+                    &format!("{}/down", url),
+                    Vec::new(),
+                    EventFilter::default(),
+                )
+                .unwrap();
+            store
+                .register(
+                    "alice",
+                    "farmer",
+                    &format!("{}/flaky", url),
+                    Vec::new(),
+                    batch_filter("B002"),
+                )
+                .unwrap();
+            flaky
+        };
+        let down = webhooks
+            .read()
+            .await
+            .list("alice", "farmer")
+            .into_iter()
+            .find(|webhook| webhook.url.ends_with("/down"))
+            .unwrap()
+            .id
+            .clone();
+
+        let event = {
+            let blockchain = dispatcher.blockchain.read().await;
+            BlockchainEvent::block_created(&blockchain.chain[1])
+        };
+        assert_eq!(dispatcher.enqueue(&event).await.unwrap(), 2);
+        for _ in 0..50 {
+            dispatcher.deliver_due().await;
+            if webhooks
+                .read()
+                .await
+                .due(Utc::now() + chrono::Duration::hours(1))
+                .is_empty()
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // The flaky receiver got the event, signed, on the third attempt
+        let store = webhooks.read().await;
+        let delivered = store.deliveries(&flaky, "alice", "farmer", None).unwrap();
+        assert_eq!(delivered[0].status, DeliveryStatus::Delivered);
+        assert_eq!(delivered[0].attempts.len(), 3);
+        {
+            let received = received.lock().unwrap();
+            let (headers, body) = &received[0];
+            let signature = headers[SIGNATURE_HEADER].to_str().unwrap();
+            assert!(verify_signature(&secret, body.as_bytes(), signature));
+            assert_eq!(headers[EVENT_HEADER], "BlockCreated");
+            assert_eq!(headers[DELIVERY_HEADER], delivered[0].id.as_str());
+            let payload: serde_json::Value = serde_json::from_str(body).unwrap();
+            assert_eq!(payload["event"]["data"]["block_index"], 1);
+        }
+
+        // The unreachable one ends up dead-lettered, and can be queued again
+        let dead = store
+            .deliveries(&down, "alice", "farmer", Some(DeliveryStatus::DeadLettered))
+            .unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].attempts.len(), 3);
+        assert_eq!(dead[0].attempts[0].status_code, Some(503));
+        let (delivered_id, dead_id) = (delivered[0].id.clone(), dead[0].id.clone());
+        drop(store);
+
+        // Only dead letters can be redelivered
+        let mut store = webhooks.write().await;
+        assert!(store
+            .redeliver(&flaky, &delivered_id, "alice", "farmer")
+            .is_err());
+        let requeued = store.redeliver(&down, &dead_id, "alice", "farmer").unwrap();
+        assert_eq!(requeued.status, DeliveryStatus::Pending);
+        assert_eq!(store.due(Utc::now()).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_local_destinations_are_refused() {
+        let mut store = WebhookStore::new();
+        for url in [
+            "http://127.0.0.1:8080/hook",
+            "http://localhost/hook",
+            "http://api.localhost./hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://metadata.google.internal/computeMetadata/v1",
+            "http://10.1.2.3/hook",
+            "http://192.168.0.10/hook",
+            "http://100.64.0.1/hook",
+            "http://0.0.0.0/hook",
+            "http://[::1]/hook",
+            "http://[fd00::1]/hook",
+            "http://[::ffff:127.0.0.1]/hook",
+        ] {
+            assert!(
+                store
+                    .register("alice", "farmer", url, Vec::new(), EventFilter::default())
+                    .is_err(),
+                "{} should be refused",
+                url
+            );
+        }
+        assert!(store
+            .register(
+                "alice",
+                "farmer",
+                "https://93.184.216.34/hook",
+                Vec::new(),
+                EventFilter::default()
+            )
+            .is_ok());
+
+        // Allowed hosts may be local, but the dispatcher checks again on every attempt
+        let (url, received) = receiver().await;
+        let webhooks = Arc::new(RwLock::new(
+            WebhookStore::new().with_destinations(loopback()),
+        ));
+        let dispatcher = WebhookDispatcher::new(
+            webhooks.clone(),
+            Arc::new(RwLock::new(chain_with_batch())),
+            Arc::new(RwLock::new(GraphAccessStore::new())),
+        );
+        let id = webhooks
+            .write()
+            .await
+            .register(
+                "alice",
+                "farmer",
+                &format!("{}/flaky", url),
+                Vec::new(),
+                EventFilter::default(),
+            )
+            .unwrap()
+            .id
+            .clone();
+        let event = {
+            let blockchain = dispatcher.blockchain.read().await;
+            BlockchainEvent::block_created(&blockchain.chain[1])
+        };
+        dispatcher.enqueue(&event).await.unwrap();
+        dispatcher.deliver_due().await;
+
+        let store = webhooks.read().await;
+        let deliveries = store.deliveries(&id, "alice", "farmer", None).unwrap();
+        assert_eq!(deliveries[0].attempts.len(), 1);
+        assert!(deliveries[0].attempts[0]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("local or private"));
+        assert!(received.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_queue_and_dead_letters_are_bounded() {
+        let mut store = WebhookStore::new();
+        let id = store
+            .register(
+                "alice",
+                "farmer",
+                "http://erp.example/a",
+                Vec::new(),
+                EventFilter::default(),
+            )
+            .unwrap()
+            .id
+            .clone();
+        let blockchain = chain_with_batch();
+        let event = BlockchainEvent::block_created(&blockchain.chain[1]);
+        let graph_access = GraphAccessStore::new();
+        for _ in 0..QUEUE_LIMIT + DEAD_LETTER_LIMIT + 5 {
+            store.enqueue(&event, &blockchain, &graph_access).unwrap();
+        }
+
+        // The oldest deliveries overflowed into the dead letters, which are capped too
+        assert_eq!(store.count(&id, DeliveryStatus::Pending), QUEUE_LIMIT);
+        assert_eq!(
+            store.count(&id, DeliveryStatus::DeadLettered),
+            DEAD_LETTER_LIMIT
+        );
+
+        // Dead letters expire
+        let later = Utc::now() + chrono::Duration::days(DEAD_LETTER_RETENTION_DAYS + 1);
+        assert!(store.prune(&id, later));
+        assert_eq!(store.count(&id, DeliveryStatus::DeadLettered), 0);
+    }
+
+    #[test]
+    fn test_store_persists() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let mut store = WebhookStore::open(data_dir.path()).unwrap();
+        let id = store
+            .register(
+                "alice",
+                "farmer",
+                "http://erp.local/a",
+                Vec::new(),
+                batch_filter("B001"),
+            )
+            .unwrap()
+            .id
+            .clone();
+        let blockchain = chain_with_batch();
+        let event = BlockchainEvent::block_created(&blockchain.chain[1]);
+        store
+            .enqueue(&event, &blockchain, &GraphAccessStore::new())
+            .unwrap();
+
+        // Pending deliveries survive a restart
+        let reopened = WebhookStore::open(data_dir.path()).unwrap();
+        assert_eq!(reopened.list("alice", "farmer")[0].id, id);
+        assert_eq!(reopened.due(Utc::now()).len(), 1);
+    }
+}
@@ -1,24 +1,52 @@
 //! WebSocket server implementation for real-time blockchain events
+//!
+//! Clients authenticate with the same bearer token as the REST API, passed in the
+//! `Authorization` header or, since browsers cannot set headers on a WebSocket, as the
+//! `token` query parameter. Filters only look into the block graphs the user may read.
+//!
+//! Every client starts out receiving every event. A `Subscribe` message narrows that to
+//! some event types and an `EventFilter`, and can carry the index of the last block the
+//! client saw before reconnecting: matching `BlockCreated` events for the blocks added
+//! since are replayed before live events resume, so a client that reconnects misses no
+//! block. Replay reaches back at most `MAX_REPLAY_BLOCKS` blocks; a client further behind
+//! catches up through the REST API first. Other event types are live only.
 
-use crate::core::blockchain::Blockchain;
+use crate::core::blockchain::{Block, Blockchain};
 use crate::error::WebError;
+use crate::web::graph_access::GraphAccessStore;
+use crate::web::models::UserClaims;
+use crate::web::openapi::api_model;
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        State,
+        Extension, State,
     },
     response::Response,
 };
 use futures_util::{sink::SinkExt, stream::StreamExt};
+use oxigraph::model::{GraphNameRef, NamedNode, NamedNodeRef, Term};
+use oxigraph::sparql::{Query, QueryResults};
+use oxigraph::store::Store;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{Arc, Mutex},
 };
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
+const BATCH_ID: &str = "http://provchain.org/trace#hasBatchID";
+const ATTRIBUTED_TO: &str = "http://www.w3.org/ns/prov#wasAttributedTo";
+const TRANSACTION_TYPE: &str = "http://provchain.org/tx#hasType";
+
+/// Blocks a subscription may ask to have replayed
+pub const MAX_REPLAY_BLOCKS: u64 = 1000;
+
+/// Blocks replayed per hold of the chain lock, so a replay does not hold up writers
+const REPLAY_BATCH: u64 = 100;
+
 /// WebSocket client information
 #[derive(Debug, Clone)]
 pub struct WebSocketClient {
@@ -80,12 +108,305 @@ pub enum BlockchainEvent {
     },
 }
 
+impl BlockchainEvent {
+    /// Names of the event types, as sent in the `type` field
+    pub const TYPES: [&'static str; 7] = [
+        "BlockCreated",
+        "TransactionSubmitted",
+        "TransactionProcessed",
+        "ValidationComplete",
+        "IntegrityAlert",
+        "SystemStatus",
+        "MetricsUpdate",
+    ];
+
+    /// `BlockCreated` event of a block already on the chain; each block commits one
+    /// submission
+    pub fn block_created(block: &Block) -> Self {
+        BlockchainEvent::BlockCreated {
+            block_index: block.index,
+            block_hash: block.hash.clone(),
+            timestamp: block.timestamp.clone(),
+            transaction_count: 1,
+        }
+    }
+
+    /// Name of the event type
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            BlockchainEvent::BlockCreated { .. } => "BlockCreated",
+            BlockchainEvent::TransactionSubmitted { .. } => "TransactionSubmitted",
+            BlockchainEvent::TransactionProcessed { .. } => "TransactionProcessed",
+            BlockchainEvent::ValidationComplete { .. } => "ValidationComplete",
+            BlockchainEvent::IntegrityAlert { .. } => "IntegrityAlert",
+            BlockchainEvent::SystemStatus { .. } => "SystemStatus",
+            BlockchainEvent::MetricsUpdate { .. } => "MetricsUpdate",
+        }
+    }
+
+    /// Block the event is about, if any
+    pub fn block_index(&self) -> Option<u64> {
+        match self {
+            BlockchainEvent::BlockCreated { block_index, .. }
+            | BlockchainEvent::TransactionProcessed { block_index, .. }
+            | BlockchainEvent::ValidationComplete { block_index, .. } => Some(*block_index),
+            BlockchainEvent::IntegrityAlert { block_index, .. } => *block_index,
+            _ => None,
+        }
+    }
+}
+
+api_model! {
+    /// Narrows the events a client receives. Every criterion that is set must match, and a
+    /// criterion matches when any of its values does.
+    ///
+    /// Criteria are checked against the block graph of events about a block, so events
+    /// without one, such as `MetricsUpdate`, never match a non-empty filter. The exception is
+    /// `TransactionSubmitted`, matched on its own participant and transaction type. Only
+    /// public block graphs are looked into, as WebSocket clients are not authenticated.
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct EventFilter {
+        /// Values of `trace:hasBatchID` in the block
+        #[serde(default)]
+        pub batch_ids: Vec<String>,
+        /// Participants the block is attributed to (`prov:wasAttributedTo`), by IRI or by
+        /// local name
+        #[serde(default)]
+        pub participants: Vec<String>,
+        /// Transaction types recorded in the block (`tx:hasType`), e.g. `Production`
+        #[serde(default)]
+        pub transaction_types: Vec<String>,
+        /// SPARQL ASK or SELECT query run with the block graph as default graph; it matches
+        /// when the ASK holds or the SELECT has a solution
+        #[serde(default)]
+        pub sparql: Option<String>,
+    }
+}
+
+impl EventFilter {
+    /// Whether no criterion is set
+    pub fn is_empty(&self) -> bool {
+        self.batch_ids.is_empty()
+            && self.participants.is_empty()
+            && self.transaction_types.is_empty()
+            && self.sparql.is_none()
+    }
+}
+
+/// Event types and filter a client subscribed to
+#[derive(Debug, Clone, Default)]
+pub struct Subscription {
+    /// Event type names; `None` means every type
+    events: Option<HashSet<String>>,
+    filter: EventFilter,
+    query: Option<Query>,
+}
+
+impl Subscription {
+    /// Subscription to `events` (every type when empty) narrowed by `filter`
+    pub fn new(events: Vec<String>, filter: EventFilter) -> Result<Self, WebError> {
+        if let Some(unknown) = events
+            .iter()
+            .find(|event| !BlockchainEvent::TYPES.contains(&event.as_str()))
+        {
+            return Err(WebError::BadRequest(format!(
+                "Unknown event type: {}",
+                unknown
+            )));
+        }
+
+        let query = match &filter.sparql {
+            Some(sparql) => {
+                let query = Query::parse(sparql, None)
+                    .map_err(|e| WebError::BadRequest(format!("Invalid SPARQL filter: {}", e)))?;
+                // The query form is only exposed by evaluating it, on an empty store here
+                let is_graph_query = Store::new().is_ok_and(|store| {
+                    matches!(store.query(query.clone()), Ok(QueryResults::Graph(_)))
+                });
+                if is_graph_query {
+                    return Err(WebError::BadRequest(
+                        "SPARQL filters must be ASK or SELECT queries".to_string(),
+                    ));
+                }
+                Some(query)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            events: (!events.is_empty()).then(|| events.into_iter().collect()),
+            filter,
+            query,
+        })
+    }
+
+    /// Stop receiving `events`
+    pub fn unsubscribe(&mut self, events: &[String]) {
+        let subscribed = self.events.get_or_insert_with(|| {
+            BlockchainEvent::TYPES
+                .iter()
+                .map(|event| event.to_string())
+                .collect()
+        });
+        for event in events {
+            subscribed.remove(event);
+        }
+    }
+
+    /// Whether the subscription needs to look into block graphs
+    pub fn needs_chain(&self) -> bool {
+        !self.filter.is_empty()
+    }
+
+    /// Whether the client subscribed to the type of `event`; with no filter set, that is
+    /// all there is to match
+    pub fn wants_type(&self, event: &BlockchainEvent) -> bool {
+        self.events
+            .as_ref()
+            .is_none_or(|events| events.contains(event.event_type()))
+    }
+
+    /// Whether `event` should be sent to the subscriber. Block graphs for which
+    /// `readable` is false never match a filter.
+    pub fn matches(
+        &self,
+        event: &BlockchainEvent,
+        blockchain: &Blockchain,
+        readable: impl Fn(&str) -> bool,
+    ) -> bool {
+        if !self.wants_type(event) {
+            return false;
+        }
+        if self.filter.is_empty() {
+            return true;
+        }
+
+        if let BlockchainEvent::TransactionSubmitted {
+            participant,
+            transaction_type,
+            ..
+        } = event
+        {
+            return self.filter.batch_ids.is_empty()
+                && self.query.is_none()
+                && any_or_unset(&self.filter.participants, |wanted| {
+                    same_participant(wanted, participant)
+                })
+                && any_or_unset(&self.filter.transaction_types, |wanted| {
+                    wanted.eq_ignore_ascii_case(transaction_type)
+                });
+        }
+
+        let Some(index) = event.block_index() else {
+            return false;
+        };
+        let graph = Block::graph_uri(index);
+        if !readable(&graph) {
+            return false;
+        }
+        let Ok(graph) = NamedNode::new(graph) else {
+            return false;
+        };
+        self.block_matches(&blockchain.rdf_store.store, &graph)
+    }
+
+    fn block_matches(&self, store: &Store, graph: &NamedNode) -> bool {
+        let batch_ids = values(store, graph, BATCH_ID);
+        let participants = values(store, graph, ATTRIBUTED_TO);
+        let transaction_types = values(store, graph, TRANSACTION_TYPE);
+
+        any_or_unset(&self.filter.batch_ids, |wanted| batch_ids.contains(wanted))
+            && any_or_unset(&self.filter.participants, |wanted| {
+                participants
+                    .iter()
+                    .any(|participant| same_participant(wanted, participant))
+            })
+            && any_or_unset(&self.filter.transaction_types, |wanted| {
+                transaction_types
+                    .iter()
+                    .any(|transaction_type| wanted.eq_ignore_ascii_case(transaction_type))
+            })
+            && self
+                .query
+                .as_ref()
+                .is_none_or(|query| query_holds(store, graph, query))
+    }
+}
+
+/// Graphs a WebSocket client's user may have filters look into
+fn readable_graphs<'a>(
+    graph_access: Option<&'a GraphAccessStore>,
+    claims: &'a UserClaims,
+) -> impl Fn(&str) -> bool + 'a {
+    move |graph| graph_access.is_none_or(|access| access.can_read(graph, &claims.sub, &claims.role))
+}
+
+/// Whether no value is wanted, or any wanted value is present
+fn any_or_unset(wanted: &[String], present: impl Fn(&String) -> bool) -> bool {
+    wanted.is_empty() || wanted.iter().any(present)
+}
+
+/// A participant named by full IRI or by local name
+fn same_participant(wanted: &str, participant: &str) -> bool {
+    participant == wanted || participant.rsplit(['#', '/', ':']).next() == Some(wanted)
+}
+
+/// Lexical values of the objects of `predicate` in a block graph
+fn values(store: &Store, graph: &NamedNode, predicate: &str) -> Vec<String> {
+    store
+        .quads_for_pattern(
+            None,
+            Some(NamedNodeRef::new_unchecked(predicate)),
+            None,
+            Some(GraphNameRef::NamedNode(graph.as_ref())),
+        )
+        .filter_map(Result::ok)
+        .map(|quad| match quad.object {
+            Term::NamedNode(node) => node.into_string(),
+            Term::Literal(literal) => literal.value().to_string(),
+            other => other.to_string(),
+        })
+        .collect()
+}
+
+/// Run an ASK or SELECT filter with the block graph as the only graph
+fn query_holds(store: &Store, graph: &NamedNode, query: &Query) -> bool {
+    let mut query = query.clone();
+    let dataset = query.dataset_mut();
+    dataset.set_default_graph(vec![graph.clone().into()]);
+    dataset.set_available_named_graphs(Vec::new());
+    match store.query(query) {
+        Ok(QueryResults::Boolean(holds)) => holds,
+        Ok(QueryResults::Solutions(mut solutions)) => {
+            solutions.next().is_some_and(|solution| solution.is_ok())
+        }
+        Ok(QueryResults::Graph(_)) => false,
+        Err(e) => {
+            debug!("SPARQL event filter failed: {}", e);
+            false
+        }
+    }
+}
+
 /// WebSocket message types for client-server communication
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "payload")]
 pub enum WebSocketMessage {
-    /// Client subscribes to specific event types
-    Subscribe { events: Vec<String> },
+    /// Client subscribes to event types (every type when empty), narrowed by a filter.
+    /// With `since_block`, matching blocks added after that index are replayed first.
+    Subscribe {
+        events: Vec<String>,
+        #[serde(default)]
+        filter: EventFilter,
+        #[serde(default)]
+        since_block: Option<u64>,
+    },
+    /// Subscription acknowledgment, sent after any replayed events
+    Subscribed {
+        events: Vec<String>,
+        replayed: usize,
+        latest_block: u64,
+    },
     /// Client unsubscribes from event types
     Unsubscribe { events: Vec<String> },
     /// Heartbeat/ping message
@@ -105,21 +426,31 @@ pub enum WebSocketMessage {
 pub struct WebSocketState {
     pub clients: Arc<Mutex<HashMap<String, WebSocketClient>>>,
     pub event_sender: broadcast::Sender<BlockchainEvent>,
-    pub blockchain: Arc<Mutex<Blockchain>>,
+    pub blockchain: Arc<RwLock<Blockchain>>,
+    /// Graph policy deciding which block graphs filters may look into; without one,
+    /// every graph is public
+    pub graph_access: Option<Arc<RwLock<GraphAccessStore>>>,
 }
 
 impl WebSocketState {
     /// Create new WebSocket state
-    pub fn new(blockchain: Arc<Mutex<Blockchain>>) -> Self {
+    pub fn new(blockchain: Arc<RwLock<Blockchain>>) -> Self {
         let (event_sender, _) = broadcast::channel(1000);
 
         Self {
             clients: Arc::new(Mutex::new(HashMap::new())),
             event_sender,
             blockchain,
+            graph_access: None,
         }
     }
 
+    /// Keep subscription filters out of the block graphs `graph_access` keeps private
+    pub fn with_graph_access(mut self, graph_access: Arc<RwLock<GraphAccessStore>>) -> Self {
+        self.graph_access = Some(graph_access);
+        self
+    }
+
     /// Broadcast event to all connected clients
     pub fn broadcast_event(&self, event: BlockchainEvent) {
         match self.event_sender.send(event.clone()) {
@@ -140,7 +471,8 @@ impl WebSocketState {
         self.clients.lock().unwrap().len()
     }
 
-    /// Add new client
+    /// Add new client. Connecting is not a chain event, so nothing is broadcast to other
+    /// clients or webhooks.
     pub fn add_client(&self, client_id: String) {
         let client = WebSocketClient {
             id: client_id.clone(),
@@ -153,17 +485,6 @@ impl WebSocketState {
             .unwrap()
             .insert(client_id.clone(), client);
         info!("WebSocket client connected: {}", client_id);
-
-        // Broadcast system status to new client
-        if let Ok(blockchain) = self.blockchain.lock() {
-            let status_event = BlockchainEvent::SystemStatus {
-                blockchain_height: blockchain.get_latest_block_index(),
-                total_transactions: blockchain.get_transaction_count() as u64,
-                active_participants: blockchain.get_participant_count() as u64,
-                system_health: "healthy".to_string(),
-            };
-            self.broadcast_event(status_event);
-        }
     }
 
     /// Remove client
@@ -180,16 +501,17 @@ impl WebSocketState {
     }
 }
 
-/// WebSocket upgrade handler
+/// WebSocket upgrade handler, behind `websocket_auth_middleware`
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
     State(state): State<WebSocketState>,
+    Extension(claims): Extension<UserClaims>,
 ) -> Response {
-    ws.on_upgrade(move |socket| handle_websocket(socket, state))
+    ws.on_upgrade(move |socket| handle_websocket(socket, state, claims))
 }
 
 /// Handle individual WebSocket connection
-async fn handle_websocket(socket: WebSocket, state: WebSocketState) {
+async fn handle_websocket(socket: WebSocket, state: WebSocketState, claims: UserClaims) {
     let client_id = Uuid::new_v4().to_string();
 
     // Add client to state
@@ -199,7 +521,7 @@ async fn handle_websocket(socket: WebSocket, state: WebSocketState) {
     let (mut sender, mut receiver) = socket.split();
 
     // Subscribe to blockchain events
-    let mut event_receiver = state.event_sender.subscribe();
+    let event_receiver = state.event_sender.subscribe();
 
     // Send connection acknowledgment
     let connect_msg = WebSocketMessage::Connected {
@@ -217,23 +539,19 @@ async fn handle_websocket(socket: WebSocket, state: WebSocketState) {
         }
     }
 
+    // Client messages are handed to the outgoing task, which owns the subscription, so
+    // replies and replayed events are ordered with live events
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+
     // Spawn task to handle outgoing messages (events to client)
     let outgoing_client_id = client_id.clone();
     let outgoing_state = state.clone();
     let outgoing_task = tokio::spawn(async move {
-        while let Ok(event) = event_receiver.recv().await {
-            let message = WebSocketMessage::Event(event);
-
-            if let Ok(msg_text) = serde_json::to_string(&message) {
-                if sender.send(Message::Text(msg_text)).await.is_err() {
-                    debug!(
-                        "Client {} disconnected during event send",
-                        outgoing_client_id
-                    );
-                    break;
-                }
-            }
-        }
+        let mut session =
+            ClientSession::new(outgoing_client_id.clone(), outgoing_state.clone(), claims);
+        session
+            .run(&mut sender, event_receiver, command_receiver)
+            .await;
 
         outgoing_state.remove_client(&outgoing_client_id);
     });
@@ -245,10 +563,15 @@ async fn handle_websocket(socket: WebSocket, state: WebSocketState) {
         while let Some(msg) = receiver.next().await {
             match msg {
                 Ok(Message::Text(text)) => {
-                    if let Err(e) =
-                        handle_client_message(&text, &incoming_client_id, &incoming_state).await
-                    {
-                        warn!("Error handling client message: {}", e);
+                    let command =
+                        handle_client_message(&text, &incoming_client_id).unwrap_or_else(|e| {
+                            warn!("Error handling client message: {}", e);
+                            WebSocketMessage::Error {
+                                message: e.to_string(),
+                            }
+                        });
+                    if command_sender.send(command).is_err() {
+                        break;
                     }
                 }
                 Ok(Message::Binary(_)) => {
@@ -294,52 +617,222 @@ async fn handle_websocket(socket: WebSocket, state: WebSocketState) {
     state.remove_client(&client_id);
 }
 
-/// Handle incoming message from WebSocket client
-async fn handle_client_message(
-    message: &str,
-    client_id: &str,
-    state: &WebSocketState,
-) -> Result<(), WebError> {
+/// Parse a message from a WebSocket client
+fn handle_client_message(message: &str, client_id: &str) -> Result<WebSocketMessage, WebError> {
     let parsed_message: WebSocketMessage = serde_json::from_str(message)
         .map_err(|e| WebError::BadRequest(format!("Invalid message format: {}", e)))?;
 
-    match parsed_message {
-        WebSocketMessage::Subscribe { events } => {
-            debug!("Client {} subscribed to events: {:?}", client_id, events);
-            // In a more complex implementation, we would track per-client subscriptions
-            // For now, all clients receive all events
+    match &parsed_message {
+        WebSocketMessage::Subscribe {
+            events,
+            filter,
+            since_block,
+        } => {
+            debug!(
+                "Client {} subscribed to events {:?} with filter {:?} since block {:?}",
+                client_id, events, filter, since_block
+            );
         }
         WebSocketMessage::Unsubscribe { events } => {
             debug!(
                 "Client {} unsubscribed from events: {:?}",
                 client_id, events
             );
-            // In a more complex implementation, we would update per-client subscriptions
         }
         WebSocketMessage::Ping { timestamp } => {
             debug!("Received ping from client {} at {}", client_id, timestamp);
-            state.update_client_ping(client_id);
+        }
+        _ => {
+            return Err(WebError::BadRequest(format!(
+                "Unexpected message type from client {}",
+                client_id
+            )));
+        }
+    }
 
-            // Send pong response
-            let _pong_msg = WebSocketMessage::Pong {
-                timestamp: chrono::Utc::now().to_rfc3339(),
+    Ok(parsed_message)
+}
+
+/// Outgoing side of one client connection
+struct ClientSession {
+    client_id: String,
+    state: WebSocketState,
+    /// User the client authenticated as
+    claims: UserClaims,
+    subscription: Subscription,
+    /// Highest block replayed to the client; live `BlockCreated` events up to it are
+    /// duplicates
+    replayed_to: Option<u64>,
+}
+
+impl ClientSession {
+    fn new(client_id: String, state: WebSocketState, claims: UserClaims) -> Self {
+        Self {
+            client_id,
+            state,
+            claims,
+            subscription: Subscription::default(),
+            replayed_to: None,
+        }
+    }
+
+    /// Forward events and answer client messages until either side goes away
+    async fn run<S>(
+        &mut self,
+        sender: &mut S,
+        mut events: broadcast::Receiver<BlockchainEvent>,
+        mut commands: mpsc::UnboundedReceiver<WebSocketMessage>,
+    ) where
+        S: SinkExt<Message> + Unpin,
+    {
+        loop {
+            let messages = tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => self.event(event).await,
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!("Client {} missed {} events", self.client_id, missed);
+                        vec![WebSocketMessage::Error {
+                            message: format!(
+                                "{} events were dropped; subscribe again with since_block to catch up",
+                                missed
+                            ),
+                        }]
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                command = commands.recv() => match command {
+                    Some(command) => self.command(command).await,
+                    None => break,
+                },
             };
 
-            // Note: In a real implementation, we'd need to send this back to the specific client
-            // This would require maintaining per-client senders
+            for message in messages {
+                let Ok(msg_text) = serde_json::to_string(&message) else {
+                    continue;
+                };
+                if sender.send(Message::Text(msg_text)).await.is_err() {
+                    debug!("Client {} disconnected during event send", self.client_id);
+                    return;
+                }
+            }
         }
-        _ => {
-            warn!(
-                "Unexpected message type from client {}: {:?}",
-                client_id, parsed_message
-            );
+    }
+
+    /// A live event, if the client wants it
+    async fn event(&self, event: BlockchainEvent) -> Vec<WebSocketMessage> {
+        if let (Some(replayed_to), BlockchainEvent::BlockCreated { block_index, .. }) =
+            (self.replayed_to, &event)
+        {
+            if *block_index <= replayed_to {
+                return Vec::new();
+            }
+        }
+
+        let wanted = if self.subscription.needs_chain() {
+            let blockchain = self.state.blockchain.read().await;
+            let graph_access = match &self.state.graph_access {
+                Some(graph_access) => Some(graph_access.read().await),
+                None => None,
+            };
+            self.subscription.matches(
+                &event,
+                &blockchain,
+                readable_graphs(graph_access.as_deref(), &self.claims),
+            )
+        } else {
+            self.subscription.wants_type(&event)
+        };
+
+        if wanted {
+            vec![WebSocketMessage::Event(event)]
+        } else {
+            Vec::new()
         }
     }
 
-    Ok(())
+    /// Replies to a client message
+    async fn command(&mut self, command: WebSocketMessage) -> Vec<WebSocketMessage> {
+        match command {
+            WebSocketMessage::Subscribe {
+                events,
+                filter,
+                since_block,
+            } => match Subscription::new(events.clone(), filter) {
+                Ok(subscription) => self.replay(subscription, events, since_block).await,
+                Err(e) => vec![WebSocketMessage::Error {
+                    message: e.to_string(),
+                }],
+            },
+            WebSocketMessage::Unsubscribe { events } => {
+                self.subscription.unsubscribe(&events);
+                Vec::new()
+            }
+            WebSocketMessage::Ping { .. } => {
+                self.state.update_client_ping(&self.client_id);
+                vec![WebSocketMessage::Pong {
+                    timestamp: chrono::Utc::now().to_rfc3339(),
+                }]
+            }
+            other => vec![other],
+        }
+    }
+
+    /// Switch to `subscription` and replay matching `BlockCreated` events for blocks
+    /// after `since_block`, then acknowledge. A `since_block` more than
+    /// `MAX_REPLAY_BLOCKS` behind is refused and the current subscription kept.
+    async fn replay(
+        &mut self,
+        subscription: Subscription,
+        events: Vec<String>,
+        since_block: Option<u64>,
+    ) -> Vec<WebSocketMessage> {
+        let latest_block = self.state.blockchain.read().await.get_latest_block_index();
+        if let Some(since_block) = since_block {
+            if latest_block.saturating_sub(since_block) > MAX_REPLAY_BLOCKS {
+                return vec![WebSocketMessage::Error {
+                    message: format!(
+                        "since_block {} is more than {} blocks behind block {}; fetch the missed blocks from /api/blockchain/blocks and subscribe from a later block",
+                        since_block, MAX_REPLAY_BLOCKS, latest_block
+                    ),
+                }];
+            }
+        }
+        self.subscription = subscription;
+
+        let mut messages = Vec::new();
+        let mut next = since_block.map_or(latest_block + 1, |since_block| since_block + 1);
+        while next <= latest_block {
+            let end = (next + REPLAY_BATCH).min(latest_block + 1);
+            let blockchain = self.state.blockchain.read().await;
+            let graph_access = match &self.state.graph_access {
+                Some(graph_access) => Some(graph_access.read().await),
+                None => None,
+            };
+            let readable = readable_graphs(graph_access.as_deref(), &self.claims);
+            for index in next..end {
+                let Some(block) = blockchain.chain.get(index as usize) else {
+                    break;
+                };
+                let event = BlockchainEvent::block_created(block);
+                if self.subscription.matches(&event, &blockchain, &readable) {
+                    messages.push(WebSocketMessage::Event(event));
+                }
+            }
+            next = end;
+        }
+        self.replayed_to = since_block.map(|_| latest_block);
+
+        messages.push(WebSocketMessage::Subscribed {
+            events,
+            replayed: messages.len(),
+            latest_block,
+        });
+        messages
+    }
 }
 
 /// Blockchain event broadcaster - integrates with existing blockchain operations
+#[derive(Clone)]
 pub struct BlockchainEventBroadcaster {
     websocket_state: WebSocketState,
 }
@@ -367,6 +860,34 @@ impl BlockchainEventBroadcaster {
         self.websocket_state.broadcast_event(event);
     }
 
+    /// Broadcast a `BlockCreated` event for every block appended to `blockchain`, however
+    /// it got there, until the chain is dropped
+    pub async fn forward_blocks(&self, blockchain: Arc<RwLock<Blockchain>>) -> JoinHandle<()> {
+        let mut blocks = blockchain.read().await.subscribe_blocks();
+        let websocket_state = self.websocket_state.clone();
+        tokio::spawn(async move {
+            loop {
+                match blocks.recv().await {
+                    Ok(index) => {
+                        let event = blockchain
+                            .read()
+                            .await
+                            .chain
+                            .get(index as usize)
+                            .map(BlockchainEvent::block_created);
+                        if let Some(event) = event {
+                            websocket_state.broadcast_event(event);
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!("Missed {} appended blocks", missed);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
     /// Broadcast transaction submission event
     pub fn broadcast_transaction_submitted(
         &self,
@@ -384,6 +905,17 @@ impl BlockchainEventBroadcaster {
         self.websocket_state.broadcast_event(event);
     }
 
+    /// Broadcast that a submitted transaction was included in a block
+    pub fn broadcast_transaction_processed(&self, transaction_id: String, block_index: u64) {
+        let event = BlockchainEvent::TransactionProcessed {
+            transaction_id,
+            block_index,
+            status: "confirmed".to_string(),
+        };
+
+        self.websocket_state.broadcast_event(event);
+    }
+
     /// Broadcast validation completion event
     pub fn broadcast_validation_complete(
         &self,
@@ -448,7 +980,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_websocket_state_creation() {
-        let blockchain = Arc::new(Mutex::new(Blockchain::new()));
+        let blockchain = Arc::new(RwLock::new(Blockchain::new()));
         let state = WebSocketState::new(blockchain);
 
         assert_eq!(state.client_count(), 0);
@@ -456,13 +988,16 @@ mod tests {
 
     #[tokio::test]
     async fn test_client_management() {
-        let blockchain = Arc::new(Mutex::new(Blockchain::new()));
+        let blockchain = Arc::new(RwLock::new(Blockchain::new()));
         let state = WebSocketState::new(blockchain);
 
+        let mut events = state.event_sender.subscribe();
         let client_id = "test-client-123".to_string();
         state.add_client(client_id.clone());
 
         assert_eq!(state.client_count(), 1);
+        // Connecting is not broadcast to other clients or webhooks
+        assert!(events.try_recv().is_err());
 
         state.remove_client(&client_id);
         assert_eq!(state.client_count(), 0);
@@ -470,7 +1005,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_event_broadcasting() {
-        let blockchain = Arc::new(Mutex::new(Blockchain::new()));
+        let blockchain = Arc::new(RwLock::new(Blockchain::new()));
         let state = WebSocketState::new(blockchain);
 
         let event = BlockchainEvent::BlockCreated {
@@ -484,6 +1019,186 @@ mod tests {
         state.broadcast_event(event);
     }
 
+    /// Chain whose blocks 1 and 2 record batches from two farmers
+    fn supply_chain() -> Blockchain {
+        let mut blockchain = Blockchain::new();
+        for (batch, farmer) in [("B001", "farmer1"), ("B002", "farmer2")] {
+            blockchain
+                .add_block(format!(
+                    r#"@prefix ex: <http://example.org/> .
+                    @prefix trace: <http://provchain.org/trace#> .
+                    @prefix prov: <http://www.w3.org/ns/prov#> .
+                    @prefix tx: <http://provchain.org/tx#> .
+                    ex:{batch} trace:hasBatchID "{batch}" ;
+                        prov:wasAttributedTo ex:participant_{farmer} .
+                    ex:tx_{batch} tx:hasType "Production" ."#
+                ))
+                .unwrap();
+        }
+        blockchain
+    }
+
+    fn block_event(blockchain: &Blockchain, index: usize) -> BlockchainEvent {
+        BlockchainEvent::block_created(&blockchain.chain[index])
+    }
+
+    #[test]
+    fn test_subscription_filters_block_events() {
+        let blockchain = supply_chain();
+        let subscribe = |filter: EventFilter| Subscription::new(Vec::new(), filter).unwrap();
+        let matched = |subscription: &Subscription| -> Vec<usize> {
+            (0..blockchain.chain.len())
+                .filter(|index| {
+                    subscription.matches(&block_event(&blockchain, *index), &blockchain, |_| true)
+                })
+                .collect()
+        };
+
+        assert_eq!(matched(&Subscription::default()), vec![0, 1, 2]);
+        let by_batch = subscribe(EventFilter {
+            batch_ids: vec!["B002".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(matched(&by_batch), vec![2]);
+        let by_participant = subscribe(EventFilter {
+            participants: vec!["participant_farmer1".to_string()],
+            transaction_types: vec!["production".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(matched(&by_participant), vec![1]);
+        let by_query = subscribe(EventFilter {
+            sparql: Some(
+                "ASK { ?batch <http://provchain.org/trace#hasBatchID> ?id FILTER(?id != \"B002\") }"
+                    .to_string(),
+            ),
+            ..Default::default()
+        });
+        assert_eq!(matched(&by_query), vec![1]);
+
+        // Events without a block graph only match on their own fields
+        let submitted = BlockchainEvent::TransactionSubmitted {
+            transaction_id: "tx-1".to_string(),
+            transaction_type: "Production".to_string(),
+            participant: "participant_farmer1".to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+        assert!(by_participant.matches(&submitted, &blockchain, |_| true));
+        assert!(!by_batch.matches(&submitted, &blockchain, |_| true));
+        let metrics = BlockchainEvent::MetricsUpdate {
+            blocks_per_minute: 1.0,
+            transactions_per_minute: 1.0,
+            average_block_time: 60.0,
+            validation_performance: "good".to_string(),
+        };
+        assert!(!by_batch.matches(&metrics, &blockchain, |_| true));
+
+        // Event types narrow further
+        let mut metrics_only =
+            Subscription::new(vec!["MetricsUpdate".to_string()], EventFilter::default()).unwrap();
+        assert!(metrics_only.matches(&metrics, &blockchain, |_| true));
+        assert!(matched(&metrics_only).is_empty());
+        metrics_only.unsubscribe(&["MetricsUpdate".to_string()]);
+        assert!(!metrics_only.matches(&metrics, &blockchain, |_| true));
+    }
+
+    #[test]
+    fn test_filters_skip_private_block_graphs() {
+        let blockchain = supply_chain();
+        let mut graph_access = GraphAccessStore::new();
+        graph_access
+            .record_owner(&Block::graph_uri(1), "farmer1")
+            .unwrap();
+
+        let subscription = Subscription::new(
+            Vec::new(),
+            EventFilter {
+                transaction_types: vec!["Production".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let event = block_event(&blockchain, 1);
+        assert!(subscription.matches(&event, &blockchain, |_| true));
+        let public = |graph: &str| graph_access.is_public(graph);
+        assert!(!subscription.matches(&event, &blockchain, public));
+        assert!(subscription.matches(&block_event(&blockchain, 2), &blockchain, public));
+    }
+
+    fn claims(user: &str) -> UserClaims {
+        UserClaims {
+            sub: user.to_string(),
+            role: "farmer".to_string(),
+            exp: usize::MAX,
+            jti: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_only_reads_the_users_graphs() {
+        let mut graph_access = GraphAccessStore::new();
+        graph_access
+            .record_owner(&Block::graph_uri(1), "farmer1")
+            .unwrap();
+        let state = WebSocketState::new(Arc::new(RwLock::new(supply_chain())))
+            .with_graph_access(Arc::new(RwLock::new(graph_access)));
+        let subscribe = WebSocketMessage::Subscribe {
+            events: Vec::new(),
+            filter: EventFilter {
+                transaction_types: vec!["Production".to_string()],
+                ..Default::default()
+            },
+            since_block: Some(0),
+        };
+        let replayed = |messages: Vec<WebSocketMessage>| -> Vec<u64> {
+            messages
+                .into_iter()
+                .filter_map(|message| match message {
+                    WebSocketMessage::Event(event) => event.block_index(),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let mut owner = ClientSession::new("a".to_string(), state.clone(), claims("farmer1"));
+        assert_eq!(replayed(owner.command(subscribe.clone()).await), vec![1, 2]);
+        let mut other = ClientSession::new("b".to_string(), state, claims("farmer2"));
+        assert_eq!(replayed(other.command(subscribe).await), vec![2]);
+    }
+
+    #[tokio::test]
+    async fn test_every_appended_block_is_broadcast() {
+        let blockchain = Arc::new(RwLock::new(Blockchain::new()));
+        let state = WebSocketState::new(blockchain.clone());
+        let mut events = state.event_sender.subscribe();
+        let forwarder = BlockchainEventBroadcaster::new(state)
+            .forward_blocks(blockchain.clone())
+            .await;
+
+        blockchain
+            .write()
+            .await
+            .add_block("<http://example.org/a> <http://example.org/b> \"c\" .".to_string())
+            .unwrap();
+        let event = tokio::time::timeout(std::time::Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.block_index(), Some(1));
+        forwarder.abort();
+    }
+
+    #[test]
+    fn test_invalid_subscriptions_are_rejected() {
+        assert!(Subscription::new(vec!["BlockMined".to_string()], EventFilter::default()).is_err());
+        for sparql in ["SELECT WHERE", "CONSTRUCT { ?s ?p ?o } WHERE { ?s ?p ?o }"] {
+            let filter = EventFilter {
+                sparql: Some(sparql.to_string()),
+                ..Default::default()
+            };
+            assert!(Subscription::new(Vec::new(), filter).is_err());
+        }
+    }
+
     #[test]
     fn test_blockchain_event_serialization() {
         let event = BlockchainEvent::BlockCreated {
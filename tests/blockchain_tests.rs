@@ -52,3 +52,586 @@ fn test_hash_is_different_for_different_data() {
         "Hashes should be different for different data"
     );
 }
+
+#[test]
+fn test_signed_block_hash_is_stable_after_submission() {
+    use ed25519_dalek::{Signer, SigningKey};
+
+    let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+    let validator = hex::encode(signing_key.verifying_key().to_bytes());
+
+    let mut bc = Blockchain::new();
+    bc.governance.validator_set.insert(validator.clone());
+
+    let mut block = bc
+        .create_block_proposal(
+            "@prefix ex: <http://example.org/> . ex:batch1 ex:status \"Shipped\" .".into(),
+            validator,
+        )
+        .expect("proposal should be created");
+    let signed_hash = block.hash.clone();
+    block.signature = hex::encode(signing_key.sign(signed_hash.as_bytes()).to_bytes());
+
+    bc.submit_signed_block(block)
+        .expect("signed block should be accepted");
+
+    assert_eq!(bc.chain[1].hash, signed_hash);
+    assert!(bc.is_valid());
+
+    let report = bc.verify_chain_signatures();
+    assert!(report.is_clean(), "unexpected issues: {:?}", report.issues);
+    assert_eq!(report.signatures_verified, 1);
+    assert_eq!(report.unsigned_blocks, vec![0]);
+}
+
+#[test]
+fn test_block_with_altered_data_is_rejected() {
+    let mut bc = Blockchain::new();
+    let mut block = bc
+        .create_block_proposal("original".into(), "LEGACY_VALIDATOR".into())
+        .expect("proposal should be created");
+    block.data = "altered after proposal".into();
+
+    assert!(bc.submit_signed_block(block).is_err());
+    assert_eq!(bc.chain.len(), 1);
+}
+
+#[test]
+fn test_migration_records_data_hashes_for_persisted_chain() {
+    use ed25519_dalek::{Signer, SigningKey};
+
+    let signing_key = SigningKey::from_bytes(&[11u8; 32]);
+    let validator = hex::encode(signing_key.verifying_key().to_bytes());
+    let temp_dir = tempfile::tempdir().expect("temp dir");
+    let store_file = temp_dir.path().join("store.nq");
+
+    let tampered_signature = {
+        let mut bc = Blockchain::new_persistent(temp_dir.path()).expect("persistent chain");
+        bc.governance.validator_set.insert(validator.clone());
+        for status in ["Shipped", "Received"] {
+            let mut block = bc
+                .create_block_proposal(
+                    format!(
+                        "@prefix ex: <http://example.org/> . ex:batch1 ex:status \"{status}\" ."
+                    ),
+                    validator.clone(),
+                )
+                .expect("proposal should be created");
+            block.signature = hex::encode(signing_key.sign(block.hash.as_bytes()).to_bytes());
+            bc.submit_signed_block(block)
+                .expect("signed block should be accepted");
+        }
+        bc.rdf_store.save_to_disk().expect("save");
+
+        let signature = bc.chain[2].signature.clone();
+        let flipped = if signature.starts_with('0') { "1" } else { "0" };
+        let tampered = format!("{}{}", flipped, &signature[1..]);
+        (signature, tampered)
+    };
+
+    // Rewrite the saved store in the legacy format, without committed data hashes, and
+    // alter the signature of block 2 on disk
+    let legacy: String = std::fs::read_to_string(&store_file)
+        .expect("saved store")
+        .lines()
+        .filter(|line| !line.contains("<http://provchain.org/hasDataHash>"))
+        .map(|line| line.replace(&tampered_signature.0, &tampered_signature.1) + "\n")
+        .collect();
+    assert!(legacy.contains(&tampered_signature.1));
+    std::fs::write(&store_file, legacy).expect("write legacy store");
+
+    let mut bc = Blockchain::new_persistent(temp_dir.path()).expect("reload chain");
+    assert_eq!(bc.chain.len(), 3);
+    let report = bc.migrate_block_hashes().expect("migration should run");
+
+    assert_eq!(report.blocks_checked, 3);
+    assert_eq!(report.migrated_blocks, vec![0, 1, 2]);
+    assert_eq!(report.signature_failures(), vec![2]);
+    assert_eq!(report.issues.len(), 1);
+    assert_eq!(report.signatures_verified, 1);
+
+    // The data hashes are persisted, so a second run has nothing left to migrate
+    let migrated = std::fs::read_to_string(&store_file).expect("migrated store");
+    for block in &bc.chain {
+        assert!(!block.data_hash.is_empty());
+        assert!(migrated.contains(&format!(
+            "<http://provchain.org/block/{}> <http://provchain.org/hasDataHash> \"{}\"",
+            block.index, block.data_hash
+        )));
+    }
+    drop(bc);
+    let mut bc = Blockchain::new_persistent(temp_dir.path()).expect("reload migrated chain");
+    let report = bc.migrate_block_hashes().expect("migration should run");
+    assert!(report.migrated_blocks.is_empty());
+    assert_eq!(report.signature_failures(), vec![2]);
+}
+
+#[test]
+fn test_every_quad_proves_against_header_merkle_root() {
+    let mut bc = Blockchain::new();
+    bc.add_block(
+        "@prefix ex: <http://example.org/> .
+         ex:batch1 ex:status \"Shipped\" ;
+                   ex:origin [ ex:farm \"Farm A\" ] .
+         ex:batch2 ex:status \"Received\" ."
+            .into(),
+    )
+    .expect("block should be added");
+
+    let header = bc.chain[1].header();
+    assert!(!header.merkle_root.is_empty());
+    assert_eq!(header.hash, header.calculate_hash());
+
+    let quads = bc.canonical_quads(1);
+    assert_eq!(quads.len(), 4);
+    for quad in &quads {
+        let proof = bc
+            .inclusion_proof(1, quad)
+            .expect("quad should be provable");
+        assert!(proof.verify(quad, &header.merkle_root));
+    }
+
+    assert!(bc
+        .inclusion_proof(1, "<http://example.org/x> <http://example.org/y> \"z\" .")
+        .is_none());
+}
+
+#[test]
+fn test_merkle_root_survives_reload() {
+    let temp_dir = tempfile::tempdir().expect("temp dir");
+    let root = {
+        let mut bc = Blockchain::new_persistent(temp_dir.path()).expect("persistent chain");
+        let _ = bc.add_block("@prefix ex: <http://example.org/> . ex:a ex:b ex:c .".into());
+        bc.flush().expect("flush");
+        bc.chain[1].merkle_root.clone()
+    };
+
+    let bc = Blockchain::new_persistent(temp_dir.path()).expect("reload chain");
+    assert_eq!(bc.chain[1].merkle_root, root);
+    assert_eq!(bc.get_headers(0, 5).len(), 2);
+    assert!(bc.verify_chain_signatures().is_clean());
+}
+
+#[test]
+fn test_triple_inclusion_proof_verifies_offline() {
+    use ed25519_dalek::{Signer, SigningKey};
+    use provchain_org::core::proof::{TripleInclusionProof, TriplePattern};
+
+    let signing_key = SigningKey::from_bytes(&[9u8; 32]);
+    let validator = hex::encode(signing_key.verifying_key().to_bytes());
+
+    let mut bc = Blockchain::new();
+    bc.governance.validator_set.insert(validator.clone());
+    let mut block = bc
+        .create_block_proposal(
+            "@prefix ex: <http://example.org/> .
+             ex:batch1 ex:status \"Shipped\" ; ex:origin ex:farmA .
+             ex:batch2 ex:status \"Received\" ."
+                .into(),
+            validator.clone(),
+        )
+        .expect("proposal should be created");
+    block.signature = hex::encode(signing_key.sign(block.hash.as_bytes()).to_bytes());
+    bc.submit_signed_block(block)
+        .expect("signed block should be accepted");
+
+    let pattern = TriplePattern {
+        subject: Some("http://example.org/batch1".into()),
+        predicate: Some("http://example.org/status".into()),
+        object: None,
+    };
+    let proofs = bc.prove_triples(1, &pattern).expect("proof should be built");
+    assert_eq!(proofs.len(), 1);
+
+    // Round-trip through JSON as a regulator would receive it
+    let proof = TripleInclusionProof::from_json(&proofs[0].to_json().unwrap()).unwrap();
+    assert!(proof.canonical_quad.contains("\"Shipped\""));
+    let result = proof.verify().expect("proof should verify");
+    assert!(result.signed);
+    assert_eq!(result.validator, validator);
+
+    let mut forged_quad = proof.clone();
+    forged_quad.canonical_quad = forged_quad.canonical_quad.replace("Shipped", "Recalled");
+    assert!(forged_quad.verify().is_err());
+
+    let mut forged_root = proof.clone();
+    forged_root.header.merkle_root = "00".repeat(32);
+    assert!(forged_root.verify().is_err());
+
+    let mut forged_signature = proof;
+    forged_signature.header.signature = hex::encode([1u8; 64]);
+    assert!(forged_signature.verify().is_err());
+
+    assert!(bc.prove_triples(5, &pattern).is_err());
+}
+
+#[test]
+fn test_reorganize_replaces_block_graphs() {
+    use provchain_org::core::blockchain::Block;
+
+    let mut bc = Blockchain::new();
+    bc.add_block("@prefix ex: <http://example.org/> . ex:ours ex:p \"1\" .".into())
+        .expect("block should be added");
+    let genesis_hash = bc.chain[0].hash.clone();
+    let ours = bc.chain[1].clone();
+
+    let fork = Block::new(
+        1,
+        "@prefix ex: <http://example.org/> . ex:theirs ex:p \"1\" .".into(),
+        genesis_hash,
+        String::new(),
+        "LEGACY_VALIDATOR".into(),
+    );
+
+    // A block that does not extend the tip is rejected outright
+    assert!(bc.submit_signed_block(fork.clone()).is_err());
+
+    let orphaned = bc
+        .reorganize(0, vec![fork.clone()])
+        .expect("reorganization should succeed");
+    assert_eq!(orphaned.len(), 1);
+    assert_eq!(orphaned[0].hash, ours.hash);
+    assert_eq!(bc.chain[1].hash, fork.hash);
+    assert!(bc.is_valid());
+    assert!(bc.verify_chain_signatures().is_clean());
+    assert!(matches!(
+        bc.rdf_store
+            .query("ASK { GRAPH ?g { <http://example.org/ours> ?p ?o } }"),
+        oxigraph::sparql::QueryResults::Boolean(false)
+    ));
+}
+
+#[test]
+fn test_failed_reorganization_restores_chain() {
+    use provchain_org::core::blockchain::Block;
+
+    let mut bc = Blockchain::new();
+    bc.add_block("@prefix ex: <http://example.org/> . ex:ours ex:p \"1\" .".into())
+        .expect("block should be added");
+    let original_hash = bc.chain[1].hash.clone();
+
+    let mut bad = bc.chain[1].clone();
+    bad.data = "@prefix ex: <http://example.org/> . ex:forged ex:p \"1\" .".into();
+
+    assert!(bc.reorganize(0, vec![bad.clone()]).is_err());
+    assert_eq!(bc.chain.len(), 2);
+    assert_eq!(bc.chain[1].hash, original_hash);
+    assert!(bc.is_valid());
+
+    // A bad block deeper in the branch is caught before anything is rolled back
+    let fork = Block::new(
+        1,
+        "@prefix ex: <http://example.org/> . ex:theirs ex:p \"1\" .".into(),
+        bc.chain[0].hash.clone(),
+        String::new(),
+        "LEGACY_VALIDATOR".into(),
+    );
+    bad.index = 2;
+    bad.previous_hash = fork.hash.clone();
+    assert!(bc.reorganize(0, vec![fork, bad]).is_err());
+    assert_eq!(bc.chain.len(), 2);
+    assert_eq!(bc.chain[1].hash, original_hash);
+    assert!(bc.is_valid());
+}
+
+#[test]
+fn test_finality_certificate_blocks_rollback_and_survives_reload() {
+    use ed25519_dalek::{Signer, SigningKey};
+    use provchain_org::core::finality::{FinalityCertificate, FinalityVote};
+
+    let keys: Vec<SigningKey> = (1..=3u8)
+        .map(|i| SigningKey::from_bytes(&[i; 32]))
+        .collect();
+    let validators: Vec<String> = keys
+        .iter()
+        .map(|key| hex::encode(key.verifying_key().to_bytes()))
+        .collect();
+
+    let temp_dir = tempfile::tempdir().expect("temp dir");
+    {
+        let mut bc = Blockchain::new_persistent(temp_dir.path()).expect("persistent chain");
+        bc.governance
+            .validator_set
+            .extend(validators.iter().cloned());
+
+        for (i, key) in keys.iter().enumerate() {
+            let mut block = bc
+                .create_block_proposal(
+                    format!("@prefix ex: <http://example.org/> . ex:b{i} ex:p \"{i}\" ."),
+                    validators[i].clone(),
+                )
+                .expect("proposal should be created");
+            block.signature = hex::encode(key.sign(block.hash.as_bytes()).to_bytes());
+            bc.submit_signed_block(block)
+                .expect("signed block should be accepted");
+        }
+
+        let checkpoint = bc.chain[2].clone();
+        let mut certificate = FinalityCertificate {
+            block_index: checkpoint.index,
+            block_hash: checkpoint.hash.clone(),
+            votes: keys[..2]
+                .iter()
+                .map(|key| FinalityVote::sign(checkpoint.index, &checkpoint.hash, key))
+                .collect(),
+            finalized_at: chrono::Utc::now().to_rfc3339(),
+        };
+
+        // Two of three authorities is not a supermajority
+        assert!(bc.record_finality_certificate(&certificate).is_err());
+        assert_eq!(bc.finalized_index(), 0);
+
+        certificate.votes.push(FinalityVote::sign(
+            checkpoint.index,
+            &checkpoint.hash,
+            &keys[2],
+        ));
+        bc.record_finality_certificate(&certificate)
+            .expect("supermajority certificate should be recorded");
+        assert_eq!(bc.finalized_index(), 2);
+
+        assert!(bc.rollback_to(1).is_err());
+        assert!(bc.reorganize(0, Vec::new()).is_err());
+        assert_eq!(bc.chain.len(), 4);
+        assert_eq!(
+            bc.rollback_to(2).expect("rollback above checkpoint").len(),
+            1
+        );
+        bc.flush().expect("flush");
+    }
+
+    let bc = Blockchain::new_persistent(temp_dir.path()).expect("reload chain");
+    let checkpoint = bc
+        .last_finalized_checkpoint()
+        .expect("certificate should be persisted");
+    assert_eq!(checkpoint.block_index, 2);
+    assert_eq!(checkpoint.block_hash, bc.chain[2].hash);
+}
+
+#[test]
+fn test_snapshot_sync_installs_finalized_state() {
+    use ed25519_dalek::{Signer, SigningKey};
+    use provchain_org::core::finality::{FinalityCertificate, FinalityVote};
+
+    let keys: Vec<SigningKey> = (1..=3u8)
+        .map(|i| SigningKey::from_bytes(&[i; 32]))
+        .collect();
+    let validators: Vec<String> = keys
+        .iter()
+        .map(|key| hex::encode(key.verifying_key().to_bytes()))
+        .collect();
+
+    let mut source = Blockchain::new();
+    source
+        .governance
+        .validator_set
+        .extend(validators.iter().cloned());
+    for (i, key) in keys.iter().enumerate() {
+        let mut block = source
+            .create_block_proposal(
+                format!("@prefix ex: <http://example.org/> . ex:b{i} ex:p \"{i}\" ."),
+                validators[i].clone(),
+            )
+            .expect("proposal should be created");
+        block.signature = hex::encode(key.sign(block.hash.as_bytes()).to_bytes());
+        source
+            .submit_signed_block(block)
+            .expect("signed block should be accepted");
+    }
+
+    // Without a finalized checkpoint there is nothing to snapshot
+    assert!(source.create_snapshot(&keys[0]).is_err());
+
+    let checkpoint = source.chain[2].clone();
+    source
+        .record_finality_certificate(&FinalityCertificate {
+            block_index: checkpoint.index,
+            block_hash: checkpoint.hash.clone(),
+            votes: keys
+                .iter()
+                .map(|key| FinalityVote::sign(checkpoint.index, &checkpoint.hash, key))
+                .collect(),
+            finalized_at: chrono::Utc::now().to_rfc3339(),
+        })
+        .expect("certificate should be recorded");
+
+    let snapshot = source.create_snapshot(&keys[0]).expect("snapshot");
+    assert_eq!(snapshot.block_index, 2);
+    assert_eq!(snapshot.headers.len(), 3);
+    assert!(snapshot.nquads.contains("<http://example.org/b1>"));
+    assert!(!snapshot.nquads.contains("<http://example.org/b2>"));
+
+    // A node that does not know the validator set cannot check the certificate
+    let mut joiner = Blockchain::new();
+    assert!(joiner.install_snapshot(&snapshot).is_err());
+    assert_eq!(joiner.chain.len(), 1);
+
+    joiner
+        .governance
+        .validator_set
+        .extend(validators.iter().cloned());
+
+    // A tampered snapshot is rejected and leaves the local chain alone
+    let mut tampered = snapshot.clone();
+    tampered.nquads = tampered.nquads.replace("\"1\"", "\"forged\"");
+    assert!(joiner.install_snapshot(&tampered).is_err());
+    assert_eq!(joiner.chain.len(), 1);
+
+    // A snapshot signed by a key outside the validator set is rejected
+    let outsider = source
+        .create_snapshot(&SigningKey::from_bytes(&[42; 32]))
+        .expect("snapshot");
+    assert!(joiner.install_snapshot(&outsider).is_err());
+
+    joiner
+        .install_snapshot(&snapshot)
+        .expect("snapshot should install");
+    assert_eq!(joiner.chain.len(), 3);
+    assert_eq!(joiner.chain[2].hash, checkpoint.hash);
+    assert_eq!(joiner.finalized_index(), 2);
+
+    // Only blocks after the snapshot need to be synced
+    joiner
+        .submit_signed_block(source.chain[3].clone())
+        .expect("block after snapshot should extend the installed chain");
+    assert!(joiner.is_valid());
+}
+
+#[test]
+fn test_governance_votes_are_auditable_and_config_survives_reload() {
+    use ed25519_dalek::{Signer, SigningKey};
+    use oxigraph::sparql::QueryResults;
+    use provchain_org::governance::{ProposalStatus, ProposalVote};
+
+    let keys: Vec<SigningKey> = (1..=3u8)
+        .map(|i| SigningKey::from_bytes(&[i; 32]))
+        .collect();
+
+    let new_validator = hex::encode(SigningKey::from_bytes(&[4; 32]).verifying_key().to_bytes());
+
+    // Governance transactions only take effect once a validator's block includes them
+    let seal = |bc: &mut Blockchain, key: &SigningKey| {
+        let index = bc.chain.len();
+        let mut block = bc
+            .create_block_proposal(
+                format!("@prefix ex: <http://example.org/> . ex:b{index} ex:p \"{index}\" ."),
+                hex::encode(key.verifying_key().to_bytes()),
+            )
+            .expect("proposal should be created");
+        block.signature = hex::encode(key.sign(block.hash.as_bytes()).to_bytes());
+        bc.submit_signed_block(block)
+            .expect("signed block should be accepted");
+    };
+
+    let temp_dir = tempfile::tempdir().expect("temp dir");
+    {
+        let mut bc = Blockchain::new_persistent(temp_dir.path()).expect("persistent chain");
+        bc.governance.validator_set.extend(
+            keys.iter()
+                .map(|key| hex::encode(key.verifying_key().to_bytes())),
+        );
+
+        let tx = bc
+            .governance
+            .create_update_configuration_tx(
+                "checkpoint_interval".to_string(),
+                "25".to_string(),
+                vec![(&keys[0], uuid::Uuid::new_v4())],
+            )
+            .expect("governance transaction");
+        let proposal = bc
+            .submit_governance_proposal(&tx)
+            .expect("proposal should be queued");
+        bc.vote_on_proposal(ProposalVote::sign(&proposal, &keys[0]))
+            .expect("first vote");
+        bc.vote_on_proposal(ProposalVote::sign(&proposal, &keys[2]))
+            .expect("second vote");
+        assert_eq!(bc.governance.network_config.checkpoint_interval, 10);
+
+        seal(&mut bc, &keys[0]);
+        assert_eq!(
+            bc.governance.proposals[&proposal.id].status,
+            ProposalStatus::Executed
+        );
+        assert_eq!(bc.governance.network_config.checkpoint_interval, 25);
+
+        // Validator set changes survive a restart as well
+        let tx = bc
+            .governance
+            .create_add_validator_tx(
+                new_validator.clone(),
+                vec![(&keys[1], uuid::Uuid::new_v4())],
+            )
+            .expect("governance transaction");
+        let added = bc
+            .submit_governance_proposal(&tx)
+            .expect("proposal should be queued");
+        bc.vote_on_proposal(ProposalVote::sign(&added, &keys[0]))
+            .expect("first vote");
+        seal(&mut bc, &keys[1]);
+        assert_eq!(
+            bc.governance.proposals[&added.id].status,
+            ProposalStatus::Open
+        );
+
+        bc.vote_on_proposal(ProposalVote::sign(&added, &keys[1]))
+            .expect("second vote");
+        seal(&mut bc, &keys[2]);
+        let added = &bc.governance.proposals[&added.id];
+        assert_eq!(added.status, ProposalStatus::Executed);
+        assert_eq!(added.opened_at_block, 2);
+        assert_eq!(
+            added
+                .votes
+                .iter()
+                .map(|vote| vote.block_index)
+                .collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+
+        let query = r#"
+            PREFIX prov: <http://provchain.org/>
+            SELECT ?validator ?status WHERE {
+                GRAPH <http://provchain.org/governance> {
+                    ?vote a prov:GovernanceVote ;
+                          prov:castBy ?validator ;
+                          prov:onProposal ?proposal .
+                    ?proposal prov:hasStatus ?status .
+                }
+            }
+        "#;
+        let QueryResults::Solutions(solutions) = bc.rdf_store.query(query) else {
+            panic!("expected solutions");
+        };
+        let rows: Vec<_> = solutions.map(|s| s.expect("solution")).collect();
+        assert_eq!(rows.len(), 4, "every vote should be in the audit trail");
+        for row in &rows {
+            assert_eq!(
+                row.get("status").map(|term| term.to_string()),
+                Some("\"Executed\"".to_string())
+            );
+        }
+    }
+
+    // Reloading replays the blocks from the recorded genesis governance
+    let mut bc = Blockchain::new_persistent(temp_dir.path()).expect("reloaded chain");
+    assert_eq!(bc.governance.network_config.checkpoint_interval, 25);
+    assert_eq!(bc.governance.proposals.len(), 2);
+    assert_eq!(bc.governance.validator_set.len(), 4);
+    assert!(bc.governance.validator_set.contains(&new_validator));
+    assert_eq!(
+        bc.governance
+            .genesis
+            .as_ref()
+            .map(|genesis| genesis.validator_set.len()),
+        Some(3)
+    );
+
+    // Rolling back the block that executed the addition reopens the proposal and
+    // queues the vote it recorded again
+    bc.rollback_to(2).expect("rollback");
+    assert_eq!(bc.governance.validator_set.len(), 3);
+    assert_eq!(bc.governance.network_config.checkpoint_interval, 25);
+    assert_eq!(bc.governance.open_proposals().len(), 1);
+    assert_eq!(bc.governance.pending.len(), 1);
+}
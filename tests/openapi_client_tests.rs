@@ -0,0 +1,119 @@
+//! The generated `provchain-client` against a running node
+//!
+//! Checks that the OpenAPI document the client is generated from matches what the
+//! handlers actually accept and return.
+
+use provchain_client::{models, Client, Error, SparqlGetParams};
+use provchain_org::{
+    config::Config, core::blockchain::Blockchain, web::auth::AuthState, web::models::ActorRole,
+    web::server::create_web_server,
+};
+use std::net::TcpListener;
+use std::path::Path;
+use std::time::Duration;
+
+const USERNAME: &str = "integration";
+const PASSWORD: &str = "Integration-Pass-1";
+
+/// Starts a node keeping its users in `data_dir`, with one admin account
+async fn start_node(data_dir: &Path) -> Client {
+    AuthState::open(data_dir)
+        .unwrap()
+        .create_user(USERNAME.to_string(), PASSWORD.to_string(), ActorRole::Admin)
+        .await
+        .unwrap();
+
+    let port = {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    };
+    let mut config = Config::default();
+    config.web.port = port;
+    config.storage.persistent = true;
+    config.storage.data_dir = data_dir.to_string_lossy().to_string();
+
+    let server = create_web_server(Blockchain::new(), Some(config))
+        .await
+        .unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = server.start().await {
+            eprintln!("Server error: {}", e);
+        }
+    });
+
+    let client = Client::new(format!("http://127.0.0.1:{}", port));
+    for _ in 0..100 {
+        if client.health_check().await.is_ok() {
+            return client;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("node did not start");
+}
+
+#[tokio::test]
+async fn test_generated_client_round_trip() {
+    let data_dir = tempfile::tempdir().unwrap();
+    let mut client = start_node(data_dir.path()).await;
+
+    // Protected operations need the token from `login`
+    let error = client.get_block(0).await.unwrap_err();
+    assert_eq!(error.status(), Some(401));
+    client.login(USERNAME, PASSWORD).await.unwrap();
+
+    let document = client.get_openapi_document().await.unwrap();
+    assert_eq!(document["openapi"], "3.0.3");
+
+    client
+        .add_triple(&models::AddTripleRequest {
+            subject: "http://example.org/batch42".to_string(),
+            predicate: "http://example.org/hasOrigin".to_string(),
+            object: "Farm Valley".to_string(),
+            graph_name: None,
+        })
+        .await
+        .unwrap();
+    let block = client.get_block(1).await.unwrap();
+    assert_eq!(block.index, 1);
+    assert!(!block.hash.is_empty());
+
+    let saved = client
+        .save_sparql_query(&models::SavedQueryDraft {
+            name: Some("Origins".to_string()),
+            query: "SELECT ?origin WHERE { ?batch <http://example.org/hasOrigin> ?origin }"
+                .to_string(),
+            description: None,
+            parameters: Vec::new(),
+            shared_with_roles: None,
+        })
+        .await
+        .unwrap();
+    let query = client
+        .get_saved_sparql_query(saved["id"].as_str().unwrap())
+        .await
+        .unwrap();
+    assert_eq!(query.name, "Origins");
+    assert_eq!(query.versions.len(), 1);
+
+    let results = client
+        .sparql_get(&SparqlGetParams {
+            query: query.versions[0].query.clone(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(results.contains("Farm Valley"));
+
+    // Error bodies are decoded into `ApiError`
+    match client.get_block(999).await {
+        Err(Error::Api {
+            status: 404,
+            error: Some(error),
+            ..
+        }) => assert!(!error.message.is_empty()),
+        other => panic!("expected a 404 error, got {:?}", other),
+    }
+
+    let users = client.list_user_accounts().await.unwrap();
+    assert!(users.iter().any(|user| user.username == USERNAME));
+}
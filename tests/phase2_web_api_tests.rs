@@ -78,6 +78,8 @@ fn test_auth_response_serialization() {
         token: "test_token_123".to_string(),
         expires_at: Utc::now(),
         user_role: "admin".to_string(),
+        refresh_token: "test_refresh_456".to_string(),
+        refresh_expires_at: Utc::now(),
     };
 
     let json = serde_json::to_string(&auth_response).unwrap();
@@ -245,6 +247,7 @@ fn test_user_claims_model() {
         sub: "user123".to_string(),
         role: "farmer".to_string(),
         exp: 1234567890,
+        jti: "token-1".to_string(),
     };
 
     let json = serde_json::to_string(&claims).unwrap();
@@ -482,6 +485,7 @@ fn test_auth_model_validation() {
         sub: "user_123".to_string(),
         role: "farmer".to_string(),
         exp: 1234567890, // Fixed timestamp for testing
+        jti: "token-1".to_string(),
     };
 
     let claims_json = serde_json::to_string(&claims).unwrap();
@@ -1,34 +1,40 @@
 //! Comprehensive WebSocket integration tests
 //! Tests real-time event broadcasting, client connection management, and system integration
 
-use axum::{routing::get, Router};
+use axum::{routing::get, Extension, Router};
 use futures_util::{SinkExt, StreamExt};
 use provchain_org::{
     core::blockchain::Blockchain,
+    web::models::UserClaims,
     web::websocket::{
-        websocket_handler, BlockchainEvent, BlockchainEventBroadcaster, WebSocketMessage,
-        WebSocketState,
+        websocket_handler, BlockchainEvent, BlockchainEventBroadcaster, EventFilter,
+        WebSocketMessage, WebSocketState,
     },
 };
-use std::{
-    sync::{Arc, Mutex},
-    time::Duration,
-};
+use std::{sync::Arc, time::Duration};
 use tokio::{
     net::{TcpListener, TcpStream},
+    sync::RwLock,
     time::timeout,
 };
 use tokio_tungstenite::{
     connect_async, tungstenite::protocol::Message as TungsteniteMessage, WebSocketStream,
 };
 
-/// Test helper to create a test WebSocket server
+/// Test helper to create a test WebSocket server, with every client authenticated as
+/// an admin in place of `websocket_auth_middleware`
 async fn create_test_server() -> (String, WebSocketState) {
-    let blockchain = Arc::new(Mutex::new(Blockchain::new()));
+    let blockchain = Arc::new(RwLock::new(Blockchain::new()));
     let websocket_state = WebSocketState::new(blockchain);
 
     let app = Router::new()
         .route("/ws", get(websocket_handler))
+        .layer(Extension(UserClaims {
+            sub: "admin".to_string(),
+            role: "admin".to_string(),
+            exp: usize::MAX,
+            jti: String::new(),
+        }))
         .with_state(websocket_state.clone());
 
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
@@ -300,6 +306,8 @@ async fn test_client_message_handling() {
             "BlockCreated".to_string(),
             "TransactionSubmitted".to_string(),
         ],
+        filter: Default::default(),
+        since_block: None,
     };
 
     let msg_text = serde_json::to_string(&subscribe_msg).unwrap();
@@ -591,3 +599,102 @@ async fn test_event_broadcasting_performance() {
 
     println!("✅ Performance test completed successfully");
 }
+
+/// Read the next protocol message, skipping automatic system status events
+async fn next_message(
+    ws_stream: &mut WebSocketStream<tokio_tungstenite::MaybeTlsStream<TcpStream>>,
+) -> WebSocketMessage {
+    loop {
+        let msg = timeout(Duration::from_secs(5), ws_stream.next())
+            .await
+            .expect("Timeout waiting for message")
+            .unwrap()
+            .unwrap();
+        if let TungsteniteMessage::Text(text) = msg {
+            let parsed: WebSocketMessage = serde_json::from_str(&text).unwrap();
+            if !matches!(
+                parsed,
+                WebSocketMessage::Event(BlockchainEvent::SystemStatus { .. })
+            ) {
+                return parsed;
+            }
+        }
+    }
+}
+
+fn batch_block(batch: &str) -> String {
+    format!(
+        r#"@prefix ex: <http://example.org/> .
+        @prefix trace: <http://provchain.org/trace#> .
+        ex:{batch} trace:hasBatchID "{batch}" ."#
+    )
+}
+
+#[tokio::test]
+async fn test_filtered_subscription_resumes_after_reconnect() {
+    let (server_url, state) = create_test_server().await;
+    {
+        let mut blockchain = state.blockchain.write().await;
+        for batch in ["B001", "B002", "B001"] {
+            blockchain.add_block(batch_block(batch)).unwrap();
+        }
+    }
+
+    let mut ws_stream = connect_websocket_client(&server_url).await.unwrap();
+    let _ack = next_message(&mut ws_stream).await;
+
+    // Reconnecting after block 1: block 3 is the only later block for batch B001
+    let subscribe_msg = WebSocketMessage::Subscribe {
+        events: vec!["BlockCreated".to_string()],
+        filter: EventFilter {
+            batch_ids: vec!["B001".to_string()],
+            ..Default::default()
+        },
+        since_block: Some(1),
+    };
+    ws_stream
+        .send(TungsteniteMessage::Text(
+            serde_json::to_string(&subscribe_msg).unwrap(),
+        ))
+        .await
+        .unwrap();
+
+    match next_message(&mut ws_stream).await {
+        WebSocketMessage::Event(BlockchainEvent::BlockCreated { block_index, .. }) => {
+            assert_eq!(block_index, 3)
+        }
+        other => panic!("Expected replayed BlockCreated event, got: {:?}", other),
+    }
+    match next_message(&mut ws_stream).await {
+        WebSocketMessage::Subscribed {
+            replayed,
+            latest_block,
+            ..
+        } => {
+            assert_eq!(replayed, 1);
+            assert_eq!(latest_block, 3);
+        }
+        other => panic!("Expected subscription acknowledgment, got: {:?}", other),
+    }
+
+    // Live events: a late duplicate of block 3 and a block for another batch are dropped
+    let broadcaster = BlockchainEventBroadcaster::new(state.clone());
+    let mut live = Vec::new();
+    {
+        let mut blockchain = state.blockchain.write().await;
+        for batch in ["B002", "B001"] {
+            blockchain.add_block(batch_block(batch)).unwrap();
+        }
+        live.extend(blockchain.chain[3..].iter().cloned());
+    }
+    for block in &live {
+        broadcaster.broadcast_block_created(block.index, block.hash.clone(), 1);
+    }
+
+    match next_message(&mut ws_stream).await {
+        WebSocketMessage::Event(BlockchainEvent::BlockCreated { block_index, .. }) => {
+            assert_eq!(block_index, 5)
+        }
+        other => panic!("Expected live BlockCreated event, got: {:?}", other),
+    }
+}